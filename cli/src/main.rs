@@ -0,0 +1,164 @@
+//! `cellify` command-line tool
+//!
+//! Reads .xlsx, .ods and .xls files with the same parsing core as the
+//! browser build, through `cellify_wasm::native`. Built for `wasm32-wasip1`
+//! it runs under any WASI runtime, e.g. in a serverless function or a shell
+//! pipeline, with no Node bindings:
+//!
+//! ```text
+//! wasmtime --dir . cellify.wasm parse book.xlsx --json
+//! ```
+
+use cellify_wasm::{native, WorkbookData};
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: cellify parse <file> [--json] [--pretty] [--sheet <name>]
+
+Reads an .xlsx, .ods or .xls file, or standard input when <file> is `-`.
+Without --json, prints each sheet's name, row count and cell count.
+
+Options:
+  --json          Print the parsed workbook as JSON
+  --pretty        Indent the JSON
+  --sheet <name>  Only read this sheet
+  -h, --help      Print this help
+";
+
+/// Command-line arguments of `cellify parse`
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    file: String,
+    json: bool,
+    pretty: bool,
+    sheet: Option<String>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        Some("parse") => {}
+        Some(command) => return Err(format!("Unknown command: {}", command)),
+        None => return Err("Missing command".to_string()),
+    }
+    let mut parsed = Args::default();
+    let mut file = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => parsed.json = true,
+            "--pretty" => parsed.pretty = true,
+            "--sheet" => parsed.sheet = Some(args.next().ok_or("--sheet needs a sheet name")?),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("Unknown option: {}", arg))
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+    parsed.file = file.ok_or("Missing file")?;
+    Ok(parsed)
+}
+
+fn read_input(file: &str) -> io::Result<Vec<u8>> {
+    if file == "-" {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        std::fs::read(file)
+    }
+}
+
+/// Leave only the sheet named `name`, matched without regard to case
+fn select_sheet(workbook: &mut WorkbookData, name: &str) -> Result<(), String> {
+    let wanted = name.to_lowercase();
+    workbook
+        .sheets
+        .retain(|sheet| sheet.name.to_lowercase() == wanted);
+    if workbook.sheets.is_empty() {
+        return Err(format!("Unknown sheet: {}", name));
+    }
+    Ok(())
+}
+
+fn summary(workbook: &WorkbookData) -> String {
+    let mut out = String::new();
+    for sheet in &workbook.sheets {
+        let rows = &sheet.worksheet.rows;
+        let cells: usize = rows.iter().map(|row| row.cells.len()).sum();
+        out.push_str(&format!(
+            "{}\t{} rows\t{} cells",
+            sheet.name,
+            rows.len(),
+            cells
+        ));
+        if let Some(state) = &sheet.state {
+            out.push_str(&format!("\t({})", state));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let data = read_input(&args.file).map_err(|e| format!("{}: {}", args.file, e))?;
+    let mut workbook = native::read_file(&data, Default::default())?;
+    if let Some(name) = &args.sheet {
+        select_sheet(&mut workbook, name)?;
+    }
+
+    let output = if args.json && args.pretty {
+        serde_json::to_string_pretty(&workbook).map_err(|e| e.to_string())? + "\n"
+    } else if args.json {
+        serde_json::to_string(&workbook).map_err(|e| e.to_string())? + "\n"
+    } else {
+        summary(&workbook)
+    };
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(output.as_bytes()) {
+        // The reader went away, e.g. `cellify parse book.xlsx --json | head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let result = parse_args(args).and_then(run);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("cellify: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Args, String> {
+        parse_args(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = args("parse book.xlsx --json --sheet Data").unwrap();
+        assert_eq!(parsed.file, "book.xlsx");
+        assert!(parsed.json && !parsed.pretty);
+        assert_eq!(parsed.sheet.as_deref(), Some("Data"));
+        assert_eq!(args("parse -").unwrap().file, "-");
+
+        assert!(args("parse").is_err());
+        assert!(args("parse book.xlsx --sheet").is_err());
+        assert!(args("parse a.xlsx b.xlsx").is_err());
+        assert!(args("parse book.xlsx --csv").is_err());
+        assert!(args("convert book.xlsx").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! Native Node.js bindings for the Cellify parser
+//!
+//! Server-side Node can load this N-API module instead of the wasm build to
+//! parse at native speed with no wasm boundary in between. It wraps the
+//! plain Rust API of the shared parsing core (`cellify_wasm::native`), so
+//! both builds return the same shapes.
+
+use cellify_wasm::native;
+use cellify_wasm::{ParseLimits, WorksheetParseOptions};
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+fn to_json(value: &impl Serialize) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Options given as a JS object, with missing fields defaulted
+fn options<T: DeserializeOwned + Default>(value: Option<Value>) -> Result<T> {
+    match value {
+        Some(value) => serde_json::from_value(value).map_err(|e| Error::from_reason(e.to_string())),
+        None => Ok(T::default()),
+    }
+}
+
+/// Parse worksheet XML; `options` as for the wasm `parse_worksheet_with_options`
+#[napi]
+pub fn parse_worksheet(xml: String, options_value: Option<Value>) -> Result<Value> {
+    let options: WorksheetParseOptions = options(options_value)?;
+    let worksheet = native::parse_worksheet(&xml, &options);
+    if let Some(warning) = worksheet.warnings.first().filter(|_| options.strict) {
+        return Err(Error::from_reason(warning.to_string()));
+    }
+    to_json(&worksheet)
+}
+
+#[napi]
+pub fn parse_shared_strings(xml: String) -> Vec<String> {
+    native::parse_shared_strings(&xml)
+}
+
+#[napi]
+pub fn parse_styles(xml: String) -> Result<Value> {
+    to_json(&native::parse_styles(&xml))
+}
+
+#[napi]
+pub fn parse_workbook(xml: String) -> Result<Value> {
+    to_json(&native::parse_workbook(&xml))
+}
+
+#[napi]
+pub fn parse_relationships(xml: String) -> Result<Value> {
+    to_json(&native::parse_relationships(&xml))
+}
+
+/// Read a whole .xlsx, .ods or .xls file into
+/// `{ sheets, shared_strings, styles, date1904 }`
+#[napi]
+pub fn read_file(data: Buffer, limits: Option<Value>) -> Result<Value> {
+    let limits: ParseLimits = options(limits)?;
+    let workbook = native::read_file(&data, limits).map_err(Error::from_reason)?;
+    to_json(&workbook)
+}
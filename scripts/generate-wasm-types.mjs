@@ -0,0 +1,299 @@
+#!/usr/bin/env node
+/**
+ * Generate TypeScript definitions for the values the WASM module exchanges
+ *
+ * Reads every public struct and enum deriving `Serialize` in wasm/src and
+ * writes matching interfaces to src/formats/xlsx/xlsx.wasm.types.ts, using
+ * the shapes serde-wasm-bindgen produces: `None` becomes undefined and maps
+ * become `Map`s.
+ *
+ * Usage: node scripts/generate-wasm-types.mjs [--check]
+ * With --check, exits non-zero when the checked-in file is out of date.
+ */
+
+import { readFileSync, readdirSync, writeFileSync } from 'node:fs';
+import { dirname, join } from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+const root = join(dirname(fileURLToPath(import.meta.url)), '..');
+export const SOURCE_DIR = join(root, 'wasm', 'src');
+export const OUTPUT_FILE = join(root, 'src', 'formats', 'xlsx', 'xlsx.wasm.types.ts');
+
+const HEADER = `/**
+ * Shapes of the values the WASM module returns and accepts
+ *
+ * Generated from the Rust types in wasm/src by scripts/generate-wasm-types.mjs;
+ * do not edit by hand. Run \`npm run build:wasm-types\` after changing them.
+ */
+`;
+
+const NUMBER_TYPES = new Set([
+  'u8', 'u16', 'u32', 'u64', 'usize', 'i8', 'i16', 'i32', 'i64', 'isize', 'f32', 'f64',
+]);
+const STRING_TYPES = new Set(['String', 'str', 'char', 'Cow']);
+const MAP_TYPES = new Set(['HashMap', 'BTreeMap']);
+const LIST_TYPES = new Set(['Vec', 'VecDeque', 'HashSet', 'BTreeSet']);
+
+/**
+ * Split Rust source into the items the generator cares about
+ */
+function collectItems(source, file) {
+  // Test modules sit at the end of each file
+  const cut = source.indexOf('\n#[cfg(test)]');
+  const lines = (cut >= 0 ? source.slice(0, cut) : source).split('\n');
+  const items = [];
+  let docs = [];
+  let attrs = [];
+
+  for (let i = 0; i < lines.length; i++) {
+    const line = lines[i];
+    const doc = line.match(/^\/\/\/ ?(.*)$/);
+    if (doc) {
+      docs.push(doc[1]);
+      continue;
+    }
+    if (line.startsWith('#[')) {
+      attrs.push(line);
+      continue;
+    }
+    const item = line.match(/^pub (struct|enum) (\w+)/);
+    if (item && attrs.some((a) => /derive\(.*\bSerialize\b/.test(a))) {
+      const body = [];
+      for (i++; i < lines.length && lines[i] !== '}'; i++) {
+        body.push(lines[i]);
+      }
+      items.push({ kind: item[1], name: item[2], docs, attrs, body, file });
+    }
+    docs = [];
+    attrs = [];
+  }
+  return items;
+}
+
+/**
+ * Serde attribute arguments on a list of attribute lines, e.g. `rename = "type"`
+ */
+function serdeArgs(attrs) {
+  return attrs
+    .filter((a) => a.trim().startsWith('#[serde('))
+    .map((a) => a.trim().slice('#[serde('.length, -2))
+    .join(', ');
+}
+
+/**
+ * Fields, variants and their docs and attributes, one member per entry
+ */
+function collectMembers(body) {
+  const members = [];
+  let docs = [];
+  let attrs = [];
+  for (let i = 0; i < body.length; i++) {
+    const line = body[i].trim();
+    if (line === '') continue;
+    const doc = line.match(/^\/\/\/ ?(.*)$/);
+    if (doc) {
+      docs.push(doc[1]);
+      continue;
+    }
+    if (line.startsWith('//')) continue;
+    if (line.startsWith('#[')) {
+      attrs.push(line);
+      continue;
+    }
+    // Gather a member spanning several lines, e.g. a struct variant
+    let text = line;
+    let depth = bracketDepth(line);
+    while ((depth > 0 || !/,$/.test(text)) && i + 1 < body.length) {
+      const next = body[++i].trim();
+      text += ' ' + next;
+      depth += bracketDepth(next);
+    }
+    members.push({ text: text.replace(/,$/, ''), docs, attrs });
+    docs = [];
+    attrs = [];
+  }
+  return members;
+}
+
+function bracketDepth(text) {
+  let depth = 0;
+  for (const ch of text) {
+    if ('<({['.includes(ch)) depth++;
+    if ('>)}]'.includes(ch)) depth--;
+  }
+  return depth;
+}
+
+/**
+ * Translate a Rust type to TypeScript
+ */
+export function rustTypeToTs(rust, known) {
+  // References and lifetimes don't change the serialized shape
+  const text = rust.replace(/&('\w+\s+)?(mut\s+)?/g, '').replace(/'\w+,?\s*/g, '');
+  return parseType({ text, pos: 0 }, known);
+}
+
+function peek(p) {
+  while (p.text[p.pos] === ' ') p.pos++;
+  return p.text[p.pos];
+}
+
+function parseList(p, close, known) {
+  const types = [];
+  while (peek(p) !== close) {
+    types.push(parseType(p, known));
+    if (peek(p) === ',') p.pos++;
+  }
+  p.pos++;
+  return types;
+}
+
+function parseType(p, known) {
+  const ch = peek(p);
+  if (ch === '(') {
+    p.pos++;
+    const types = parseList(p, ')', known);
+    return types.length === 0 ? 'null' : `[${types.join(', ')}]`;
+  }
+  if (ch === '[') {
+    p.pos++;
+    const inner = parseType(p, known);
+    // Skip an array length
+    while (p.text[p.pos] !== ']') p.pos++;
+    p.pos++;
+    return arrayOf(inner);
+  }
+
+  const path = p.text.slice(p.pos).match(/^[\w:]+/);
+  if (!path) {
+    throw new Error(`Unexpected '${ch}' in type ${p.text}`);
+  }
+  p.pos += path[0].length;
+  const name = path[0].split('::').pop();
+  const args = peek(p) === '<' ? (p.pos++, parseList(p, '>', known)) : [];
+
+  if (NUMBER_TYPES.has(name)) return 'number';
+  if (STRING_TYPES.has(name)) return 'string';
+  if (name === 'bool') return 'boolean';
+  if (name === 'Option') return `${args[0]} | undefined`;
+  if (name === 'Box' || name === 'Rc' || name === 'Arc') return args[0];
+  if (LIST_TYPES.has(name)) return arrayOf(args[0]);
+  if (MAP_TYPES.has(name)) return `Map<${args[0]}, ${args[1]}>`;
+  if (known.has(name)) return name;
+  throw new Error(`No TypeScript mapping for Rust type ${name}`);
+}
+
+function arrayOf(inner) {
+  return inner.includes(' | ') ? `(${inner})[]` : `${inner}[]`;
+}
+
+/**
+ * JSDoc block for a list of doc comment lines
+ */
+function jsdoc(docs, indent) {
+  // Rustdoc links become plain code spans
+  docs = docs.map((d) => d.replace(/\[`(?:crate::)?([^`\]]+)`\]/g, '`$1`'));
+  if (docs.length === 0) return '';
+  if (docs.length === 1) return `${indent}/** ${docs[0]} */\n`;
+  const lines = docs.map((d) => `${indent} *${d ? ' ' + d : ''}`);
+  return `${indent}/**\n${lines.join('\n')}\n${indent} */\n`;
+}
+
+function renderStruct(item, known) {
+  const structDefault = /\bdefault\b/.test(serdeArgs(item.attrs));
+  const fields = collectMembers(item.body).map(({ text, docs, attrs }) => {
+    const field = text.match(/^(?:pub(?:\([\w:]+\))?\s+)?(\w+):\s*(.+)$/);
+    if (!field) {
+      throw new Error(`Can't read field '${text}' of ${item.name}`);
+    }
+    const args = serdeArgs(attrs);
+    if (/\bskip\b(?!_)/.test(args)) return '';
+    if (/\bflatten\b/.test(args)) {
+      throw new Error(`Flattened field ${field[1]} of ${item.name} is not supported`);
+    }
+    const name = args.match(/\brename\s*=\s*"([^"]+)"/)?.[1] ?? field[1];
+    // `None` reads back as undefined and may be left out on input
+    const option = field[2].match(/^Option<(.+)>$/);
+    // Omitted on output, or filled in from Default on input
+    const optional = option || structDefault || /\bskip_serializing_if\b/.test(args);
+    const ts = rustTypeToTs(option ? option[1] : field[2], known);
+    return `${jsdoc(docs, '  ')}  ${name}${optional ? '?' : ''}: ${ts};\n`;
+  });
+  return `${jsdoc(item.docs, '')}export interface ${item.name} {\n${fields.join('')}}\n`;
+}
+
+function snakeCase(name) {
+  return name.replace(/[A-Z]/g, (c, i) => (i > 0 ? '_' : '') + c.toLowerCase());
+}
+
+function renderEnum(item, known) {
+  const args = serdeArgs(item.attrs);
+  const variants = collectMembers(item.body);
+  let members;
+  if (/\buntagged\b/.test(args)) {
+    members = variants.map(({ text }) => {
+      const tuple = text.match(/^\w+\s*\((.+)\)$/);
+      if (tuple) return rustTypeToTs(tuple[1], known);
+      const struct = text.match(/^\w+\s*\{(.*)\}$/);
+      if (struct) {
+        const fields = struct[1]
+          .split(',')
+          .map((f) => f.trim())
+          .filter(Boolean)
+          .map((f) => {
+            const [name, type] = f.split(/:\s*/);
+            return `${name}: ${rustTypeToTs(type, known)}`;
+          });
+        return `{ ${fields.join('; ')} }`;
+      }
+      return 'null';
+    });
+  } else if (variants.every(({ text }) => /^\w+$/.test(text))) {
+    const snake = /rename_all\s*=\s*"snake_case"/.test(args);
+    members = variants.map(({ text }) => `'${snake ? snakeCase(text) : text}'`);
+  } else {
+    throw new Error(`Externally tagged enum ${item.name} is not supported`);
+  }
+  return `${jsdoc(item.docs, '')}export type ${item.name} = ${members.join(' | ')};\n`;
+}
+
+/**
+ * The generated file's contents
+ */
+export function generateWasmTypes(sourceDir = SOURCE_DIR) {
+  const items = readdirSync(sourceDir)
+    .filter((f) => f.endsWith('.rs'))
+    .sort()
+    .flatMap((f) => collectItems(readFileSync(join(sourceDir, f), 'utf8'), f));
+  const known = new Set(items.map((item) => item.name));
+
+  const sections = [HEADER];
+  let file = null;
+  for (const item of items) {
+    if (item.file !== file) {
+      file = item.file;
+      sections.push(`// ${file}\n`);
+    }
+    sections.push(item.kind === 'struct' ? renderStruct(item, known) : renderEnum(item, known));
+  }
+  return sections.join('\n');
+}
+
+if (process.argv[1] === fileURLToPath(import.meta.url)) {
+  const output = generateWasmTypes();
+  if (process.argv.includes('--check')) {
+    let current = '';
+    try {
+      current = readFileSync(OUTPUT_FILE, 'utf8');
+    } catch {
+      // A missing file is out of date
+    }
+    if (current !== output) {
+      console.error(`${OUTPUT_FILE} is out of date; run npm run build:wasm-types`);
+      process.exit(1);
+    }
+  } else {
+    writeFileSync(OUTPUT_FILE, output);
+    console.log(`Wrote ${OUTPUT_FILE}`);
+  }
+}
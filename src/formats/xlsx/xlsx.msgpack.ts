@@ -0,0 +1,130 @@
+/**
+ * MessagePack decoder for the WASM parser's binary output
+ *
+ * The `parse_*_msgpack` exports return their results as a single Uint8Array
+ * instead of a JS object graph. This decoder turns those bytes back into the
+ * same shape the object-returning parsers produce. It covers the subset of
+ * MessagePack the encoder emits (no extension types).
+ */
+
+const textDecoder = new TextDecoder();
+
+/**
+ * Decode a MessagePack buffer into plain JS values
+ *
+ * Maps decode to plain objects, nil to null and binary to Uint8Array.
+ */
+export function decodeMsgpack(bytes: Uint8Array): unknown {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  let pos = 0;
+
+  const str = (length: number): string => {
+    const value = textDecoder.decode(bytes.subarray(pos, pos + length));
+    pos += length;
+    return value;
+  };
+
+  const bin = (length: number): Uint8Array => {
+    const value = bytes.slice(pos, pos + length);
+    pos += length;
+    return value;
+  };
+
+  const array = (length: number): unknown[] => {
+    const value = new Array<unknown>(length);
+    for (let i = 0; i < length; i++) {
+      value[i] = read();
+    }
+    return value;
+  };
+
+  const map = (length: number): Record<string, unknown> => {
+    const value: Record<string, unknown> = {};
+    for (let i = 0; i < length; i++) {
+      const key = String(read());
+      value[key] = read();
+    }
+    return value;
+  };
+
+  const read = (): unknown => {
+    const byte = bytes[pos++];
+    if (byte <= 0x7f) return byte;
+    if (byte >= 0xe0) return byte - 0x100;
+    if ((byte & 0xe0) === 0xa0) return str(byte & 0x1f);
+    if ((byte & 0xf0) === 0x90) return array(byte & 0x0f);
+    if ((byte & 0xf0) === 0x80) return map(byte & 0x0f);
+
+    let value: unknown;
+    switch (byte) {
+      case 0xc0:
+        return null;
+      case 0xc2:
+        return false;
+      case 0xc3:
+        return true;
+      case 0xc4:
+        return bin(bytes[pos++]);
+      case 0xc5:
+        pos += 2;
+        return bin(view.getUint16(pos - 2));
+      case 0xc6:
+        pos += 4;
+        return bin(view.getUint32(pos - 4));
+      case 0xca:
+        value = view.getFloat32(pos);
+        pos += 4;
+        return value;
+      case 0xcb:
+        value = view.getFloat64(pos);
+        pos += 8;
+        return value;
+      case 0xcc:
+        return bytes[pos++];
+      case 0xcd:
+        pos += 2;
+        return view.getUint16(pos - 2);
+      case 0xce:
+        pos += 4;
+        return view.getUint32(pos - 4);
+      case 0xcf:
+        pos += 8;
+        return Number(view.getBigUint64(pos - 8));
+      case 0xd0:
+        return view.getInt8(pos++);
+      case 0xd1:
+        pos += 2;
+        return view.getInt16(pos - 2);
+      case 0xd2:
+        pos += 4;
+        return view.getInt32(pos - 4);
+      case 0xd3:
+        pos += 8;
+        return Number(view.getBigInt64(pos - 8));
+      case 0xd9:
+        return str(bytes[pos++]);
+      case 0xda:
+        pos += 2;
+        return str(view.getUint16(pos - 2));
+      case 0xdb:
+        pos += 4;
+        return str(view.getUint32(pos - 4));
+      case 0xdc:
+        pos += 2;
+        return array(view.getUint16(pos - 2));
+      case 0xdd:
+        pos += 4;
+        return array(view.getUint32(pos - 4));
+      case 0xde:
+        pos += 2;
+        return map(view.getUint16(pos - 2));
+      case 0xdf:
+        pos += 4;
+        return map(view.getUint32(pos - 4));
+      default:
+        throw new Error(`Unsupported MessagePack type 0x${byte.toString(16)} at offset ${pos - 1}`);
+    }
+  };
+
+  return read();
+}
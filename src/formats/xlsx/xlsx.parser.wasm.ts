@@ -9,14 +9,18 @@ import {
   initWasm,
   isWasmAvailable,
   parseWorksheetWasm,
+  parseWorksheetStreamWasm,
   parseSharedStringsWasm,
   parseStylesWasm,
   parseWorkbookWasm,
   parseRelationshipsWasm,
+  openZipWasm,
+  type WasmZipPackage,
   type ParsedWorksheet,
   type ParsedStyles,
   type ParsedSheetInfo,
   type ParsedRelationship,
+  type WorksheetParseOptions,
 } from './xlsx.wasm.js';
 
 // Re-export types for convenience
@@ -32,6 +36,8 @@ export type {
   ParsedFont,
   ParsedFill,
   ParsedBorder,
+  WasmZipPackage,
+  WorksheetParseOptions,
 } from './xlsx.wasm.js';
 
 // Module state
@@ -95,6 +101,18 @@ export function parseWorksheetAccelerated(xml: string): ParsedWorksheet | null {
   return parseWorksheetWasm(xml);
 }
 
+/**
+ * Parse worksheet XML from a stream with WASM acceleration, while it downloads
+ * Returns null if WASM is not available (use JS fallback)
+ */
+export async function parseWorksheetStreamAccelerated(
+  stream: ReadableStream<Uint8Array>,
+  options?: WorksheetParseOptions
+): Promise<ParsedWorksheet | null> {
+  if (!isXlsxWasmReady()) return null;
+  return parseWorksheetStreamWasm(stream, options);
+}
+
 /**
  * Parse styles with WASM acceleration
  * Returns null if WASM is not available (use JS fallback)
@@ -121,3 +139,12 @@ export function parseRelationshipsAccelerated(xml: string): ParsedRelationship[]
   if (!isXlsxWasmReady()) return null;
   return parseRelationshipsWasm(xml);
 }
+
+/**
+ * Open a ZIP package with WASM inflation
+ * Returns null if WASM is not available (use JS fallback)
+ */
+export function openZipAccelerated(data: Uint8Array): WasmZipPackage | null {
+  if (!isXlsxWasmReady()) return null;
+  return openZipWasm(data);
+}
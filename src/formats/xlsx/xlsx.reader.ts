@@ -34,6 +34,8 @@ import {
   parseSharedStringsAccelerated,
   parseStylesAccelerated,
   parseWorksheetAccelerated,
+  openZipAccelerated,
+  type WasmZipPackage,
   type ParsedWorksheet,
   type ParsedStyles,
 } from './xlsx.parser.wasm.js';
@@ -87,22 +89,54 @@ export function xlsxToWorkbook(
     },
   };
 
-  // Unzip the XLSX file
+  // Unzip the XLSX file; with WASM, parts are inflated on demand instead
   ctx.onProgress?.('unzip', 0, 1);
-  let files: Record<string, Uint8Array>;
+  let zipPackage: WasmZipPackage | null = null;
+  let readFile: (path: string) => string | undefined;
   try {
-    files = unzipSync(buffer);
+    zipPackage = opts.useWasm ? openZipAccelerated(buffer) : null;
+    if (zipPackage) {
+      const pkg = zipPackage;
+      readFile = (path) => pkg.read_text(path) ?? undefined;
+    } else {
+      const files = unzipSync(buffer);
+      const decoder = new TextDecoder('utf-8');
+      readFile = (path) => {
+        const data = files[path];
+        return data ? decoder.decode(data) : undefined;
+      };
+    }
   } catch (error) {
-    throw new Error(`Failed to unzip XLSX file: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    throw new Error(`Failed to unzip XLSX file: ${error instanceof Error ? error.message : describeWasmError(error)}`);
   }
   ctx.onProgress?.('unzip', 1, 1);
 
-  // Helper to read file as string
-  const readFile = (path: string): string | undefined => {
-    const data = files[path];
-    if (!data) return undefined;
-    return new TextDecoder('utf-8').decode(data);
-  };
+  try {
+    return readWorkbookParts(readFile, ctx, startTime);
+  } finally {
+    zipPackage?.free();
+  }
+}
+
+/**
+ * Message of an error thrown by WASM, which may be a string or a
+ * `{ error: 'limits_exceeded', message }` object
+ */
+function describeWasmError(error: unknown): string {
+  if (typeof error === 'string') return error;
+  if (error && typeof error === 'object' && 'message' in error) return String(error.message);
+  return 'Unknown error';
+}
+
+/**
+ * Parse the workbook parts once the package is open
+ */
+function readWorkbookParts(
+  readFile: (path: string) => string | undefined,
+  ctx: XlsxParseContext,
+  startTime: number
+): XlsxImportResult {
+  const opts = ctx.options;
 
   // Parse workbook relationships to find sheet targets
   const workbookRelsXml = readFile('xl/_rels/workbook.xml.rels');
@@ -458,8 +492,8 @@ function parseStyles(xml: string, ctx: XlsxParseContext): void {
  * Apply WASM-parsed styles to parse context
  */
 function applyWasmStyles(styles: ParsedStyles, ctx: XlsxParseContext): void {
-  for (const [id, code] of Object.entries(styles.num_fmts)) {
-    ctx.numberFormats.set(Number(id), code);
+  for (const [id, code] of styles.num_fmts) {
+    ctx.numberFormats.set(id, code);
   }
 
   for (const font of styles.fonts) {
@@ -572,8 +606,8 @@ function applyWasmWorksheet(ws: ParsedWorksheet, sheet: Sheet, ctx: XlsxParseCon
   const opts = ctx.options;
 
   if (opts.importDimensions) {
-    for (const [colNum, width] of Object.entries(ws.col_widths)) {
-      const colIndex = Number(colNum) - 1;
+    for (const [colNum, width] of ws.col_widths) {
+      const colIndex = colNum - 1;
       if (width > 0) {
         sheet.setColumnWidth(colIndex, width);
       }
@@ -645,12 +679,12 @@ function applyWasmWorksheet(ws: ParsedWorksheet, sheet: Sheet, ctx: XlsxParseCon
  * Parse cell value from WASM-parsed data
  */
 function parseCellValueFromWasm(
-  cellType: string | null,
-  valueStr: string | null,
+  cellType: string | undefined,
+  valueStr: string | undefined,
   ctx: XlsxParseContext,
   styleIndex: number
 ): string | number | boolean | Date | null {
-  if (valueStr === null && cellType !== 'inlineStr') {
+  if (valueStr === undefined && cellType !== 'inlineStr') {
     return null;
   }
 
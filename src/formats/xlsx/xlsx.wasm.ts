@@ -5,108 +5,74 @@
  * Falls back to JavaScript parser when WASM is not available.
  */
 
-// Type definitions for WASM module exports
-export interface ParsedCell {
-  reference: string;
-  cell_type: string | null;
-  style_index: number | null;
-  value: string | null;
-  formula: string | null;
-}
-
-export interface ParsedRow {
-  row_num: number;
-  cells: ParsedCell[];
-  height: number | null;
-  hidden: boolean;
-}
-
-export interface ParsedWorksheet {
-  rows: ParsedRow[];
-  merge_cells: string[];
-  hyperlinks: ParsedHyperlink[];
-  col_widths: Record<number, number>;
-}
-
-export interface ParsedHyperlink {
-  reference: string;
-  rid: string | null;
-  location: string | null;
-  display: string | null;
-  tooltip: string | null;
-}
-
-export interface ParsedStyle {
-  num_fmt_id: number | null;
-  font_id: number | null;
-  fill_id: number | null;
-  border_id: number | null;
-  xf_id: number | null;
-  apply_number_format: boolean;
-  apply_font: boolean;
-  apply_fill: boolean;
-  apply_border: boolean;
-  apply_alignment: boolean;
-  horizontal: string | null;
-  vertical: string | null;
-  wrap_text: boolean;
-  text_rotation: number | null;
-  indent: number | null;
-}
-
-export interface ParsedFont {
-  bold: boolean;
-  italic: boolean;
-  underline: boolean;
-  strikethrough: boolean;
-  size: number | null;
-  color: string | null;
-  name: string | null;
-}
-
-export interface ParsedFill {
-  pattern_type: string | null;
-  fg_color: string | null;
-  bg_color: string | null;
-}
+import type {
+  ParsedWorksheet,
+  ParsedStyles,
+  ParsedSheetInfo,
+  ParsedRelationship,
+  WorksheetParseOptions,
+} from './xlsx.wasm.types.js';
+
+// Type definitions for WASM module exports, generated from the Rust structs
+export type {
+  ParsedCell,
+  ParsedRow,
+  ParsedWorksheet,
+  ParseWarning,
+  WarningKind,
+  ParsedHyperlink,
+  ParsedComment,
+  CommentReply,
+  SheetImage,
+  SheetChart,
+  ChartSeries,
+  SheetProtection,
+  DocumentProperties,
+  CustomProperty,
+  DataValidation,
+  AutoFilter,
+  FilterColumn,
+  CustomFilter,
+  ConditionalFormat,
+  ConditionalRule,
+  ConditionalThreshold,
+  ParsedStyle,
+  ParsedFont,
+  ParsedFill,
+  ParsedBorder,
+  ParsedDxf,
+  ParsedStyles,
+  RichSharedString,
+  RichTextRun,
+  CustomSheetView,
+  ViewPrintSettings,
+  PageMargins,
+  ParsedSheetInfo,
+  ParsedRelationship,
+  WorksheetParseOptions,
+} from './xlsx.wasm.types.js';
 
-export interface ParsedBorder {
-  left_style: string | null;
-  left_color: string | null;
-  right_style: string | null;
-  right_color: string | null;
-  top_style: string | null;
-  top_color: string | null;
-  bottom_style: string | null;
-  bottom_color: string | null;
-}
-
-export interface ParsedStyles {
-  cell_xfs: ParsedStyle[];
-  fonts: ParsedFont[];
-  fills: ParsedFill[];
-  borders: ParsedBorder[];
-  num_fmts: Record<number, string>;
-}
-
-export interface ParsedSheetInfo {
-  name: string;
-  sheet_id: number;
-  rid: string;
-  state: string | null;
-}
-
-export interface ParsedRelationship {
-  id: string;
-  rel_type: string;
-  target: string;
-  target_mode: string | null;
+/**
+ * ZIP package held in WASM memory; parts are inflated when read.
+ * Call `free()` when done to release the package bytes.
+ */
+export interface WasmZipPackage {
+  names(): string[];
+  read(name: string): Uint8Array | undefined;
+  read_text(name: string): string | undefined;
+  free(): void;
 }
 
 // WASM module interface
 interface WasmModule {
   init(): void;
+  ZipPackage: new (data: Uint8Array, limits?: unknown) => WasmZipPackage;
+  SharedZipPackage: new (data: Uint8Array, limits?: unknown) => WasmZipPackage;
   parse_worksheet(xml: string): ParsedWorksheet;
+  parse_worksheet_stream(
+    read: () => Promise<ReadableStreamReadResult<Uint8Array>>,
+    options?: WorksheetParseOptions
+  ): Promise<ParsedWorksheet>;
   parse_shared_strings(xml: string): string[];
   parse_styles(xml: string): ParsedStyles;
   parse_workbook(xml: string): ParsedSheetInfo[];
@@ -157,6 +123,22 @@ async function loadWasmModule(): Promise<boolean> {
   }
 }
 
+/**
+ * Stop using a WASM instance that panicked, falling back to JS from then on
+ *
+ * A panic comes back as a `{ error: "panic" | "poisoned" }` error or, in
+ * builds that abort on panic, as a `WebAssembly.RuntimeError` trap. Either
+ * way the instance's memory can't be trusted for later calls.
+ */
+function discardIfPanicked(error: unknown): void {
+  const code = (error as { error?: unknown } | null)?.error;
+  if (error instanceof WebAssembly.RuntimeError || code === 'panic' || code === 'poisoned') {
+    console.warn('[Cellify] WASM parser panicked, using JS fallback:', error);
+    wasmModule = null;
+    wasmAvailable = false;
+  }
+}
+
 /**
  * Check if WASM parser is available
  */
@@ -171,8 +153,33 @@ export function parseWorksheetWasm(xml: string): ParsedWorksheet | null {
   if (!wasmModule) return null;
   try {
     return wasmModule.parse_worksheet(xml);
-  } catch {
+  } catch (error) {
+    discardIfPanicked(error);
+    return null;
+  }
+}
+
+/**
+ * Parse worksheet XML from a stream using WASM (if available)
+ *
+ * Rows are parsed as each chunk arrives, so a sheet being downloaded is
+ * mostly parsed by the time the download ends. The stream is consumed even
+ * when this returns null after a failure.
+ */
+export async function parseWorksheetStreamWasm(
+  stream: ReadableStream<Uint8Array>,
+  options?: WorksheetParseOptions
+): Promise<ParsedWorksheet | null> {
+  if (!wasmModule) return null;
+  const reader = stream.getReader();
+  try {
+    return await wasmModule.parse_worksheet_stream(() => reader.read(), options);
+  } catch (error) {
+    discardIfPanicked(error);
+    await reader.cancel(error).catch(() => undefined);
     return null;
+  } finally {
+    reader.releaseLock();
   }
 }
 
@@ -183,7 +190,8 @@ export function parseSharedStringsWasm(xml: string): string[] | null {
   if (!wasmModule) return null;
   try {
     return wasmModule.parse_shared_strings(xml);
-  } catch {
+  } catch (error) {
+    discardIfPanicked(error);
     return null;
   }
 }
@@ -195,7 +203,8 @@ export function parseStylesWasm(xml: string): ParsedStyles | null {
   if (!wasmModule) return null;
   try {
     return wasmModule.parse_styles(xml);
-  } catch {
+  } catch (error) {
+    discardIfPanicked(error);
     return null;
   }
 }
@@ -207,7 +216,8 @@ export function parseWorkbookWasm(xml: string): ParsedSheetInfo[] | null {
   if (!wasmModule) return null;
   try {
     return wasmModule.parse_workbook(xml);
-  } catch {
+  } catch (error) {
+    discardIfPanicked(error);
     return null;
   }
 }
@@ -219,7 +229,25 @@ export function parseRelationshipsWasm(xml: string): ParsedRelationship[] | null
   if (!wasmModule) return null;
   try {
     return wasmModule.parse_relationships(xml);
-  } catch {
+  } catch (error) {
+    discardIfPanicked(error);
     return null;
   }
 }
+
+/**
+ * Open a ZIP package in WASM (if available)
+ *
+ * Unlike the parse helpers this does not swallow errors: a corrupt archive
+ * or a limits_exceeded error must not fall back to unzipping in JS.
+ *
+ * A view onto a `SharedArrayBuffer` is left where it is, so workers sharing
+ * one file each copy only the parts they read rather than the whole file.
+ */
+export function openZipWasm(data: Uint8Array): WasmZipPackage | null {
+  if (!wasmModule) return null;
+  if (typeof SharedArrayBuffer !== 'undefined' && data.buffer instanceof SharedArrayBuffer) {
+    return new wasmModule.SharedZipPackage(data);
+  }
+  return new wasmModule.ZipPackage(data);
+}
@@ -0,0 +1,1553 @@
+/**
+ * Shapes of the values the WASM module returns and accepts
+ *
+ * Generated from the Rust types in wasm/src by scripts/generate-wasm-types.mjs;
+ * do not edit by hand. Run `npm run build:wasm-types` after changing them.
+ */
+
+// adjust.rs
+
+/** A structural edit, as passed to `adjust_formulas` */
+export interface StructureChange {
+  /** "insert_rows", "delete_rows", "insert_cols" or "delete_cols" */
+  action: string;
+  /** First inserted or deleted row/column (1-based) */
+  index: number;
+  count: number;
+  /** Sheet being edited; defaults to the sheet holding the formulas */
+  sheet?: string;
+  /** Sheet the formulas live on, which unqualified references point to */
+  formula_sheet?: string;
+}
+
+// aggregate.rs
+
+/** What to compute for `aggregate` */
+export interface AggregateSpec {
+  /**
+   * Columns whose values group rows ("A", "C", ...); all rows form one
+   * group when empty
+   */
+  group_by?: string[];
+  aggregates?: Aggregate[];
+  /** First row read (1-based), e.g. 2 to leave out a header row */
+  first_row?: number;
+  shared_strings?: string[];
+}
+
+/** One figure to compute */
+export interface Aggregate {
+  /** Column letters, e.g. "C" */
+  column: string;
+  /**
+   * "sum", "avg", "min" or "max" over the column's numbers, "count" of
+   * its values or "distinct" values
+   */
+  function: string;
+}
+
+/** The figures for one group of rows */
+export interface AggregateGroup {
+  /** The group's value in each `group_by` column */
+  key: (TypedValue | undefined)[];
+  /** Rows in the group; rows without any value are left out */
+  rows: number;
+  /**
+   * One per aggregate, in order; `None` for an average or extreme
+   * without numbers
+   */
+  values: (number | undefined)[];
+}
+
+// arrow.rs
+
+/** Options for `worksheet_to_arrow` */
+export interface ArrowExportOptions {
+  /** Row holding the column names (1-based); defaults to the first used row */
+  header_row?: number;
+  /** Skip rows with no values */
+  skip_empty_rows?: boolean;
+  shared_strings?: string[];
+  /** Styles used to detect date-formatted numbers */
+  styles?: ParsedStyles;
+  date1904?: boolean;
+}
+
+// batch.rs
+
+/** One worksheet of a batch, in input order */
+export interface BatchSheet {
+  name: string;
+  worksheet: ParsedWorksheet;
+}
+
+// builder.rs
+
+/**
+ * A cell style for `WorkbookBuilder::add_style` and
+ * `WorkbookBuilder::apply_style`; parts left out keep the workbook
+ * defaults or, for `apply_style`, what each cell already has
+ */
+export interface StyleSpec {
+  font?: ParsedFont;
+  fill?: ParsedFill;
+  border?: ParsedBorder;
+  /** Number format code, e.g. "0.00%" or "yyyy-mm-dd" */
+  num_fmt?: string;
+  horizontal?: string;
+  vertical?: string;
+  wrap_text?: boolean;
+}
+
+/** Options for `WorkbookBuilder::add_table` */
+export interface TableSpec {
+  /** Name used in formulas; "Table1", "Table2", ... when left out */
+  name?: string;
+  /** "TableStyleMedium2" with banded rows when left out */
+  style?: TableStyleInfo;
+  /**
+   * Make the range's last row a totals row showing these, one per column
+   * from the first
+   */
+  totals?: ColumnTotal[];
+}
+
+// csv.rs
+
+/** Options for `worksheet_to_csv` */
+export interface CsvExportOptions {
+  delimiter?: string;
+  row_delimiter?: string;
+  quote_char?: string;
+  quote_all_fields?: boolean;
+  /** Range to export (e.g. "A1:D10"); defaults to the used range */
+  range?: string;
+  null_value?: string;
+  /** "ISO" (yyyy-mm-dd) or a pattern using yyyy, yy, mm, dd, HH, MM, SS */
+  date_format?: string;
+  include_bom?: boolean;
+  /** Write `=FORMULA` for formula cells instead of their cached value */
+  use_formulas?: boolean;
+  /** Shared string table for resolving `t="s"` cells */
+  shared_strings?: string[];
+  /** Styles used to detect date-formatted numbers */
+  styles?: ParsedStyles;
+  date1904?: boolean;
+}
+
+/** Options for `parse_csv` */
+export interface CsvImportOptions {
+  /** Field delimiter; sniffed from the data when not set */
+  delimiter?: string;
+  quote_char?: string;
+  skip_empty_lines?: boolean;
+  trim_values?: boolean;
+  start_cell?: string;
+  detect_numbers?: boolean;
+  detect_dates?: boolean;
+  date_formats?: string[];
+  /** Style index given to detected dates so they display as dates */
+  date_style_index?: number;
+  /** Maximum number of rows to import (0 = unlimited) */
+  max_rows?: number;
+  /** Lines starting with this are skipped */
+  comment_char?: string;
+}
+
+// duplicates.rs
+
+/** Options for `find_duplicate_rows` */
+export interface DuplicateOptions {
+  /** First row compared (1-based), e.g. 2 to leave out a header row */
+  first_row?: number;
+  /** Compare text without regard to case */
+  ignore_case?: boolean;
+  /** Compare text without its leading and trailing whitespace */
+  trim?: boolean;
+  shared_strings?: string[];
+}
+
+/** Rows holding the same values in the key columns */
+export interface DuplicateGroup {
+  /** Row numbers, in sheet order; the first is the original */
+  rows: number[];
+}
+
+// eval.rs
+
+/** Workbook contents to recalculate */
+export interface EvalWorkbook {
+  sheets: ParsedSheet[];
+  shared_strings: string[];
+  /** `local_sheet_id` is taken as an index into `sheets` */
+  defined_names: ParsedDefinedName[];
+  tables: ParsedTable[];
+  date1904: boolean;
+}
+
+/** A recalculated formula cell, typed like `ParsedCell` */
+export interface EvaluatedCell {
+  sheet: string;
+  reference: string;
+  /** `None` for numbers, otherwise "str", "b" or "e" */
+  cell_type?: string;
+  value: string;
+}
+
+// export.rs
+
+/** A sheet to export */
+export interface ExportSheet {
+  name: string;
+  state?: string;
+  worksheet: ParsedWorksheet;
+  /**
+   * Sheet-level relationships (e.g. external hyperlink targets) referenced
+   * by `r:id` from the worksheet
+   */
+  relationships: ParsedRelationship[];
+  /**
+   * Tables on the sheet, each written as its own part; their header
+   * cells should hold the column names
+   */
+  tables: ParsedTable[];
+  /**
+   * Print area and titles, written as the sheet's built-in names in
+   * place of any `defined_names` gives it
+   */
+  print: PrintSettings;
+}
+
+/** What printing a sheet takes in */
+export interface PrintSettings {
+  /**
+   * Ranges to print, e.g. "A1:F40" or "A1:F40,H1:K40"; all of the sheet
+   * when left out
+   */
+  area?: string;
+  /** Rows repeated at the top of every page, e.g. "1:2" */
+  title_rows?: string;
+  /** Columns repeated at the left of every page, e.g. "A:B" */
+  title_cols?: string;
+}
+
+/**
+ * Everything needed to produce an .xlsx package
+ *
+ * Cells of type `s` index into `shared_strings` and cell style indices
+ * index into `styles.cell_xfs`; both are remapped after deduplication.
+ */
+export interface ExportWorkbook {
+  sheets: ExportSheet[];
+  shared_strings: SharedStringEntry[];
+  styles?: ParsedStyles;
+  defined_names: ParsedDefinedName[];
+  workbook_pr?: ParsedWorkbookPr;
+  /** DEFLATE level for XML parts (0 stores uncompressed, max 10) */
+  compression_level?: number;
+  /** Encrypt the package with this password (ECMA-376 agile encryption) */
+  password?: string;
+  /** Title, author, company and custom properties, written to `docProps` */
+  properties?: DocumentProperties;
+}
+
+// filter.rs
+
+/** A test on a row, as passed to `filter_rows` */
+export type RowPredicate = { and: RowPredicate[] } | { or: RowPredicate[] } | { not: RowPredicate } | RowCondition;
+
+/** A test on one cell of the row */
+export interface RowCondition {
+  /** Column letters, e.g. "C" */
+  column: string;
+  /**
+   * "eq", "ne", "gt", "gte", "lt", "lte" (or "=", "!=", ">", ">=", "<",
+   * "<="), "contains", "starts_with", "ends_with", "in", "empty" or
+   * "not_empty"
+   */
+  operator: string;
+  /** Value to compare with; ISO date text compares with date serials */
+  value?: TypedValue;
+  /** Values for "in" */
+  values: TypedValue[];
+}
+
+/** Options for `filter_rows` */
+export interface FilterOptions {
+  /** First row tested (1-based), e.g. 2 to keep a header row out of it */
+  first_row?: number;
+  /** Compare text with its case */
+  case_sensitive?: boolean;
+  /**
+   * Return the worksheet holding the rows above `first_row` and the
+   * matching rows, rather than the matching row numbers
+   */
+  sheet?: boolean;
+  shared_strings?: string[];
+}
+
+// graph.rs
+
+/** Sheets, plus any defined names and tables, to build the graph from */
+export interface GraphWorkbook {
+  sheets: ParsedSheet[];
+  /** `local_sheet_id` is taken as an index into `sheets` */
+  defined_names: ParsedDefinedName[];
+  tables: ParsedTable[];
+}
+
+/** A formula cell and its direct links */
+export interface DependencyNode {
+  /** Sheet-qualified cell, e.g. `Sheet1!B2` */
+  cell: string;
+  formula: string;
+  /** Referenced cells and ranges, sheet-qualified and without `$` markers */
+  precedents: string[];
+  /** Formula cells that reference this cell */
+  dependents: string[];
+}
+
+export interface DependencyGraph {
+  /** Formula cells in sheet and row order */
+  nodes: DependencyNode[];
+  /**
+   * Formula cells ordered so each comes after its precedents; the members
+   * of a cycle are kept together
+   */
+  order: string[];
+  /** Groups of formula cells that depend on each other */
+  cycles: string[][];
+}
+
+/** A formula cell calling volatile functions */
+export interface VolatileCell {
+  cell: string;
+  /** Volatile functions called, directly or through defined names */
+  functions: string[];
+}
+
+/** What a host should know before turning on recalculation */
+export interface FormulaAnalysis {
+  volatile: VolatileCell[];
+  /** Formula cells that depend on a volatile cell, and so recalculate with it */
+  volatile_dependents: string[];
+  /** Groups of formula cells that depend on each other */
+  cycles: string[][];
+}
+
+// hash.rs
+
+/** Parsed sheets hashed by `hash_workbook` */
+export interface HashWorkbook {
+  sheets: ParsedSheet[];
+  shared_strings: string[];
+}
+
+/** Options for `hash_worksheet` and `hash_workbook` */
+export interface HashOptions {
+  /**
+   * Also hash cell style indexes, row heights, hidden rows and column
+   * widths
+   */
+  include_formatting?: boolean;
+  /** Shared strings for `hash_worksheet`; a workbook carries its own */
+  shared_strings?: string[];
+}
+
+/** A workbook's content hash, with each sheet's */
+export interface WorkbookHash {
+  /** Hex SHA-256 over the sheets' names, visibility and hashes, in order */
+  hash: string;
+  sheets: SheetHash[];
+}
+
+/** One sheet's content hash */
+export interface SheetHash {
+  name: string;
+  /** Hex SHA-256, as `hash_worksheet` gives it */
+  hash: string;
+}
+
+// infer.rs
+
+/** Options for `infer_column_types` */
+export interface ColumnTypeOptions {
+  /** Row holding the headers (1-based); defaults to the first used row */
+  header_row?: number;
+  /** Treat every row as data and name columns by letter */
+  no_header?: boolean;
+  /** Rows to read below the header; all of them when left out */
+  sample_rows?: number;
+  /** Read numbers, ISO dates and true/false written as text as those types */
+  parse_text?: boolean;
+  /**
+   * Share of a column's values a type must fit (0 to 1), below which
+   * the column is a string
+   */
+  min_confidence?: number;
+  shared_strings?: string[];
+  /** Styles used to detect date-formatted numbers */
+  styles?: ParsedStyles;
+}
+
+/** A column's inferred type */
+export interface ColumnType {
+  /** 1-based column */
+  col: number;
+  /** Header text, unique and never empty (falls back to the column letter) */
+  header: string;
+  /**
+   * "integer", "float", "date", "datetime", "bool", "string" or "empty"
+   * (no values read)
+   */
+  column_type: string;
+  /**
+   * Share of the values read that fit the type, from 0 to 1; for a
+   * string, the share that no other type reads
+   */
+  confidence: number;
+  /** Cells read with a value */
+  value_count: number;
+  /** Cells read that are empty, blank text or an error */
+  null_count: number;
+}
+
+// intern.rs
+
+/** A cell whose `cell_type` is an index into the worksheet's `strings` */
+export interface InternedCell {
+  reference: string;
+  cell_type?: number;
+  style_index?: number;
+  value?: string;
+  formula?: string;
+}
+
+/** A row of `InternedCell`s */
+export interface InternedRow {
+  row_num: number;
+  cells: InternedCell[];
+  height?: number;
+  hidden: boolean;
+}
+
+/** `ParsedWorksheet` with interned cell types */
+export interface InternedWorksheet {
+  strings: string[];
+  rows: InternedRow[];
+  merge_cells: string[];
+  hyperlinks: ParsedHyperlink[];
+  col_widths: Map<number, number>;
+}
+
+/** `ParsedStyle` with interned alignment names */
+export interface InternedStyle {
+  num_fmt_id?: number;
+  font_id?: number;
+  fill_id?: number;
+  border_id?: number;
+  xf_id?: number;
+  apply_number_format: boolean;
+  apply_font: boolean;
+  apply_fill: boolean;
+  apply_border: boolean;
+  apply_alignment: boolean;
+  horizontal?: number;
+  vertical?: number;
+  wrap_text: boolean;
+  text_rotation?: number;
+  indent?: number;
+}
+
+/** `ParsedFont` with interned color and name */
+export interface InternedFont {
+  bold: boolean;
+  italic: boolean;
+  underline: boolean;
+  strikethrough: boolean;
+  size?: number;
+  color?: number;
+  name?: number;
+}
+
+/** `ParsedFill` with interned pattern and colors */
+export interface InternedFill {
+  pattern_type?: number;
+  fg_color?: number;
+  bg_color?: number;
+}
+
+/** `ParsedBorder` with interned styles and colors */
+export interface InternedBorder {
+  left_style?: number;
+  left_color?: number;
+  right_style?: number;
+  right_color?: number;
+  top_style?: number;
+  top_color?: number;
+  bottom_style?: number;
+  bottom_color?: number;
+}
+
+/** `ParsedStyles` with every attribute string interned into `strings` */
+export interface InternedStyles {
+  strings: string[];
+  cell_xfs: InternedStyle[];
+  fonts: InternedFont[];
+  fills: InternedFill[];
+  borders: InternedBorder[];
+  num_fmts: Map<number, string>;
+}
+
+// json.rs
+
+/** Options for `worksheet_to_json` */
+export interface JsonExportOptions {
+  /** Row holding the headers (1-based); defaults to the first used row */
+  header_row?: number;
+  /** "ndjson" for one record per line, "array" for a JSON array */
+  format?: string;
+  /** Skip rows with no values */
+  skip_empty_rows?: boolean;
+  /** Emit `null` for empty cells instead of omitting the key */
+  include_empty?: boolean;
+  shared_strings?: string[];
+  /** Styles used to detect date-formatted numbers */
+  styles?: ParsedStyles;
+  date1904?: boolean;
+}
+
+// lib.rs
+
+/** Parsed cell data from worksheet XML */
+export interface ParsedCell {
+  reference: string;
+  cell_type?: string;
+  style_index?: number;
+  value?: string;
+  formula?: string;
+}
+
+/** Parsed row data */
+export interface ParsedRow {
+  row_num: number;
+  cells: ParsedCell[];
+  height?: number;
+  hidden: boolean;
+}
+
+/** Parsed worksheet data */
+export interface ParsedWorksheet {
+  rows: ParsedRow[];
+  merge_cells: string[];
+  hyperlinks: ParsedHyperlink[];
+  col_widths: Map<number, number>;
+  /** Malformed XML that was skipped over (or, in strict mode, stopped at) */
+  warnings?: ParseWarning[];
+  /** Validation rules to write; the parsers don't read them yet */
+  data_validations?: DataValidation[];
+  /** Conditional formatting to write; the parsers don't read it yet */
+  conditional_formats?: ConditionalFormat[];
+  /** Filter dropdowns to write; the parsers don't read them yet */
+  auto_filter?: AutoFilter;
+  /** Relationship ids of the sheet's table parts, to write */
+  table_parts?: string[];
+  /** Comments to write; the parsers don't read them yet */
+  comments?: ParsedComment[];
+  /** Relationship id of the VML drawing that shows the comments, to write */
+  legacy_drawing?: string;
+  /** Pictures to write; the parsers don't read them yet */
+  images?: SheetImage[];
+  /** Charts to write; the parsers don't read them yet */
+  charts?: SheetChart[];
+  /**
+   * Relationship id of the drawing that holds the pictures and charts, to
+   * write
+   */
+  drawing?: string;
+  /**
+   * Locks against editing the sheet, to write; the parsers don't read it
+   * yet
+   */
+  protection?: SheetProtection;
+}
+
+/** What a `ParseWarning` reports */
+export type WarningKind = 'malformed_xml' | 'out_of_bounds' | 'duplicate_row';
+
+/** A problem found while parsing a part, located so it can be fixed */
+export interface ParseWarning {
+  kind: WarningKind;
+  /** Byte offset in the part where the problem was detected */
+  offset: number;
+  /** 1-based line and column of `offset` (approximate for streamed parts) */
+  line: number;
+  column: number;
+  /** Innermost element being read, e.g. "v" or "c" */
+  element?: string;
+  /** Reference of the last cell read before the problem */
+  last_cell?: string;
+  message: string;
+}
+
+/** A worksheet read from a non-XLSX workbook, with its sheet metadata */
+export interface ParsedSheet {
+  name: string;
+  /** "hidden" or "veryHidden"; `None` when visible */
+  state?: string;
+  worksheet: ParsedWorksheet;
+}
+
+/** Parsed hyperlink */
+export interface ParsedHyperlink {
+  reference: string;
+  rid?: string;
+  location?: string;
+  display?: string;
+  tooltip?: string;
+  /**
+   * External address behind `rid`, filled in by whole-workbook reads; on
+   * export a link with a target gets its relationship written for it
+   */
+  target?: string;
+}
+
+/** A data validation rule, such as a dropdown list */
+export interface DataValidation {
+  /** Cells it applies to, as space-separated ranges ("A2:A100 C2") */
+  sqref?: string;
+  /**
+   * "list", "whole", "decimal", "date", "time", "textLength" or "custom";
+   * none accepts any value and only shows the prompt
+   */
+  validation_type?: string;
+  /** "between" (the default), "notBetween", "equal", "lessThan", ... */
+  operator?: string;
+  /** A list's items ("\"Yes,No\"") or source range, or the first bound */
+  formula1?: string;
+  formula2?: string;
+  allow_blank?: boolean;
+  /** Leave out a list's in-cell dropdown */
+  hide_dropdown?: boolean;
+  show_input_message?: boolean;
+  show_error_message?: boolean;
+  /** "stop" (the default), "warning" or "information" */
+  error_style?: string;
+  error_title?: string;
+  error?: string;
+  prompt_title?: string;
+  prompt?: string;
+}
+
+/**
+ * Filter dropdowns on the header row of a range
+ *
+ * Excel doesn't apply the criteria when it opens a file, only when the
+ * filter is next changed, so rows they exclude should also be hidden.
+ */
+export interface AutoFilter {
+  /** The range filtered, header row included ("A1:D100") */
+  reference?: string;
+  /** Criteria of the columns that are filtered */
+  columns?: FilterColumn[];
+}
+
+/** Criteria for one column of an `AutoFilter` */
+export interface FilterColumn {
+  /** Position in the filtered range, from 0 for its first column */
+  col_id?: number;
+  /** Show only cells with one of these values */
+  values?: string[];
+  /** Show blank cells too */
+  blank?: boolean;
+  /** Up to two comparisons, shown when either holds */
+  custom?: CustomFilter[];
+  /** Show only cells where both comparisons hold */
+  and?: boolean;
+}
+
+/** A comparison in a `FilterColumn` */
+export interface CustomFilter {
+  /**
+   * "equal" (the default), "notEqual", "greaterThan", "lessThan",
+   * "greaterThanOrEqual" or "lessThanOrEqual"
+   */
+  operator?: string;
+  /** Number or text compared with, where text can use `*` and `?` */
+  value?: string;
+}
+
+/** A note on a cell, or the first post of a comment thread */
+export interface ParsedComment {
+  reference?: string;
+  author?: string;
+  text?: string;
+  /**
+   * Write a threaded comment, which Excel 365 shows as a conversation,
+   * rather than a note; comments with replies always are
+   */
+  threaded?: boolean;
+  replies?: CommentReply[];
+  /** When a thread was started, as an ISO 8601 date and time */
+  date?: string;
+  /** Mark the thread resolved */
+  resolved?: boolean;
+}
+
+/** A picture placed on a sheet */
+export interface SheetImage {
+  /** The image file: PNG, JPEG, GIF or BMP */
+  data?: number[];
+  /**
+   * A cell for the picture's top-left corner, at the image's own size, or
+   * a range to stretch it over
+   */
+  anchor?: string;
+  /** Alternative text */
+  description?: string;
+}
+
+/** A chart placed on a sheet */
+export interface SheetChart {
+  /** "column", "bar" (horizontal bars), "line" or "pie" */
+  chart_type?: string;
+  title?: string;
+  series?: ChartSeries[];
+  /**
+   * A cell for the chart's top-left corner, at Excel's default size, or a
+   * range to fill
+   */
+  anchor?: string;
+  /**
+   * Where the legend goes: "r", "l", "t", "b" or "tr"; no legend when left
+   * out
+   */
+  legend?: string;
+}
+
+/** One line, set of bars or pie of a chart */
+export interface ChartSeries {
+  /** Name shown in the legend */
+  name?: string;
+  /** Range holding the category labels, e.g. "Data!$A$2:$A$13" */
+  categories?: string;
+  /** Range holding the values, e.g. "Data!$B$2:$B$13" */
+  values?: string;
+}
+
+/** A reply in a comment thread */
+export interface CommentReply {
+  author?: string;
+  text?: string;
+  date?: string;
+}
+
+/** Conditional formatting rules for a set of cells */
+export interface ConditionalFormat {
+  /** Cells the rules apply to, as space-separated ranges ("A2:A100 C2") */
+  sqref?: string;
+  rules?: ConditionalRule[];
+}
+
+/** One conditional formatting rule; which fields it uses depends on its type */
+export interface ConditionalRule {
+  /**
+   * "cellIs", "expression", "containsText", "top10", "aboveAverage",
+   * "duplicateValues", "colorScale", "dataBar" or "iconSet"
+   */
+  rule_type?: string;
+  /** A "cellIs" comparison: "between", "equal", "greaterThan", ... */
+  operator?: string;
+  /** Bounds or condition, relative to the first cell of `sqref` */
+  formulas?: string[];
+  /** Formatting to apply, as an index into `ParsedStyles.dxfs` */
+  dxf_id?: number;
+  /**
+   * Evaluation order, lowest first; defaults to the rule's position
+   * among the sheet's rules
+   */
+  priority?: number;
+  stop_if_true?: boolean;
+  /** The text a "containsText" rule looks for */
+  text?: string;
+  /** How many cells (or what percent of them) a "top10" rule picks */
+  rank?: number;
+  percent?: boolean;
+  /** Pick the lowest values, or those below the average */
+  bottom?: boolean;
+  /**
+   * Thresholds of a color scale, data bar or icon set, lowest first;
+   * left out, they are spread evenly from the lowest value to the highest
+   */
+  thresholds?: ConditionalThreshold[];
+  /** A color scale's color for each threshold, or a data bar's color */
+  colors?: string[];
+  /**
+   * "3TrafficLights1" (the default), "3Arrows", "5Rating", ...; "3Stars",
+   * "3Triangles" and "5Boxes" need Excel 2010 or later
+   */
+  icon_set?: string;
+  /** Show icons from lowest to highest instead */
+  reverse?: boolean;
+  /** Show only the bar or icon, not the cell's value */
+  hide_value?: boolean;
+  /** Fill data bars with a solid color rather than a gradient (Excel 2010) */
+  solid_fill?: boolean;
+  /** Color of data bars for negative values (Excel 2010) */
+  negative_color?: string;
+}
+
+/** A threshold of a color scale, data bar or icon set */
+export interface ConditionalThreshold {
+  /** "min", "max", "num", "percent", "percentile" or "formula" */
+  value_type?: string;
+  value?: string;
+}
+
+/** Options for `parse_worksheet_with_options` */
+export interface WorksheetParseOptions {
+  /** Omit cells with no value or formula whose style is unset or 0 */
+  skip_empty_cells?: boolean;
+  /** With `skip_empty_cells`, also omit empty cells carrying another style */
+  skip_styled_empty_cells?: boolean;
+  /** Only read cells inside this A1 range, e.g. "A1:F200" */
+  range?: string;
+  /** Skip this many `<row>` elements (after `range`), for paging through a sheet */
+  skip_rows?: number;
+  /** Stop after this many `<row>` elements (after `skip_rows`) */
+  max_rows?: number;
+  /** Leave out styles, formulas, hyperlinks, merges and column widths */
+  values_only?: boolean;
+  /**
+   * Stop at the first malformed XML or out-of-bounds row or cell instead
+   * of skipping to the next one
+   */
+  strict?: boolean;
+  /**
+   * Only read elements in the SpreadsheetML namespace, skipping foreign
+   * elements whose local names collide (e.g. `row` inside an extension)
+   */
+  check_namespaces?: boolean;
+  /**
+   * Sort rows that come out of order and merge rows repeating a row
+   * number, later cells replacing earlier ones in the same column
+   */
+  normalize_rows?: boolean;
+}
+
+/** Style definition from styles.xml */
+export interface ParsedStyle {
+  num_fmt_id?: number;
+  font_id?: number;
+  fill_id?: number;
+  border_id?: number;
+  xf_id?: number;
+  apply_number_format: boolean;
+  apply_font: boolean;
+  apply_fill: boolean;
+  apply_border: boolean;
+  apply_alignment: boolean;
+  horizontal?: string;
+  vertical?: string;
+  wrap_text: boolean;
+  text_rotation?: number;
+  indent?: number;
+}
+
+/** Font definition */
+export interface ParsedFont {
+  bold: boolean;
+  italic: boolean;
+  underline: boolean;
+  strikethrough: boolean;
+  size?: number;
+  color?: string;
+  name?: string;
+}
+
+/** Fill definition */
+export interface ParsedFill {
+  pattern_type?: string;
+  fg_color?: string;
+  bg_color?: string;
+}
+
+/** Border definition */
+export interface ParsedBorder {
+  left_style?: string;
+  left_color?: string;
+  right_style?: string;
+  right_color?: string;
+  top_style?: string;
+  top_color?: string;
+  bottom_style?: string;
+  bottom_color?: string;
+}
+
+/** Parsed styles data */
+export interface ParsedStyles {
+  cell_xfs: ParsedStyle[];
+  fonts: ParsedFont[];
+  fills: ParsedFill[];
+  borders: ParsedBorder[];
+  num_fmts: Map<number, string>;
+  /** Formatting for conditional formatting rules to write; not parsed yet */
+  dxfs?: ParsedDxf[];
+}
+
+/** Differential formatting a conditional format lays over a cell's own */
+export interface ParsedDxf {
+  font?: ParsedFont;
+  fill?: ParsedFill;
+  border?: ParsedBorder;
+  /** Number format code */
+  num_fmt?: string;
+}
+
+/** Workbook sheet info */
+export interface ParsedSheetInfo {
+  name: string;
+  sheet_id: number;
+  rid: string;
+  state?: string;
+}
+
+/** Pivot cache registration (cacheId to workbook relationship) */
+export interface ParsedPivotCache {
+  cache_id: number;
+  rid: string;
+}
+
+/** Application version info from `<fileVersion>` */
+export interface ParsedFileVersion {
+  app_name?: string;
+  last_edited?: number;
+  lowest_edited?: number;
+  rup_build?: number;
+}
+
+/** Defined name (named range, print area, etc.) */
+export interface ParsedDefinedName {
+  name: string;
+  value: string;
+  local_sheet_id?: number;
+  hidden: boolean;
+}
+
+/** Workbook-level properties from `<workbookPr>` and the first `<workbookView>` */
+export interface ParsedWorkbookPr {
+  date1904: boolean;
+  code_name?: string;
+  default_theme_version?: number;
+  active_tab?: number;
+  first_sheet?: number;
+}
+
+/** Parsed workbook.xml data */
+export interface ParsedWorkbook {
+  sheets: ParsedSheetInfo[];
+  defined_names: ParsedDefinedName[];
+  workbook_pr: ParsedWorkbookPr;
+  pivot_caches: ParsedPivotCache[];
+  file_version?: ParsedFileVersion;
+  /** Absolute path the file was last saved to (`x15ac:absPath`) */
+  abs_path?: string;
+}
+
+/** Relationship info */
+export interface ParsedRelationship {
+  id: string;
+  rel_type: string;
+  target: string;
+  target_mode?: string;
+}
+
+// limits.rs
+
+/** Limits applied when parsing a workbook from bytes */
+export interface ParseLimits {
+  /** Total bytes decompressed from the archive */
+  max_decompressed_size?: number;
+  /** Entries in the archive */
+  max_parts?: number;
+  /** Cells across all sheets */
+  max_cells?: number;
+  /** XML element nesting depth */
+  max_depth?: number;
+}
+
+/** Which limit was crossed; sent to JS with `error: "limits_exceeded"` */
+export interface LimitExceeded {
+  /** "decompressed_size", "part_count", "cell_count" or "nesting_depth" */
+  limit: string;
+  max: number;
+  /** The package part being read, when the limit is tied to one */
+  part?: string;
+}
+
+// memory.rs
+
+/** Snapshot returned by `memory_stats`; sizes are in bytes */
+export interface MemoryStats {
+  /** Size of the wasm linear memory (never shrinks); null outside wasm */
+  heap_size?: number;
+  /** Bytes currently allocated */
+  allocated: number;
+  /** Most bytes allocated at once since start-up or `reset_memory_peak` */
+  peak_allocated: number;
+  limit?: number;
+}
+
+// ods.rs
+
+/** Result of `parse_ods` */
+export interface ParsedOds {
+  sheets: ParsedSheet[];
+  styles: ParsedStyles;
+}
+
+// pivot.rs
+
+/** Options for `pivot` */
+export interface PivotOptions {
+  /** First row read (1-based), e.g. 2 to leave out a header row */
+  first_row?: number;
+  shared_strings?: string[];
+}
+
+/**
+ * A computed pivot table
+ *
+ * Each figure list holds one figure per value field, in order; `None`
+ * where no rows fall, or for an average or extreme without numbers.
+ */
+export interface PivotTable {
+  /** Labels of the table's columns, one value per column field */
+  columns: ((TypedValue | undefined)[])[];
+  rows: PivotRow[];
+  /** Figures for each column over all rows */
+  totals: ((number | undefined)[])[];
+  /** Figures over every row */
+  grand_total: (number | undefined)[];
+}
+
+/** One row of a pivot table */
+export interface PivotRow {
+  /** The row's label, one value per row field */
+  key: (TypedValue | undefined)[];
+  /** Figures for each of the table's columns */
+  values: ((number | undefined)[])[];
+  /** Figures over the row's columns */
+  total: (number | undefined)[];
+}
+
+// profile.rs
+
+/** Accumulated time of one phase */
+export interface PhaseTiming {
+  name: string;
+  ms: number;
+  /** How many times the phase ran */
+  calls: number;
+}
+
+/** Report returned by `get_profile`; phases are in order of first use */
+export interface ProfileReport {
+  phases: PhaseTiming[];
+  /** Sum of the outermost phases, so nested phases are not counted twice */
+  total_ms: number;
+}
+
+// properties.rs
+
+/** A workbook's title, author and other metadata */
+export interface DocumentProperties {
+  title?: string;
+  subject?: string;
+  /** Author */
+  creator?: string;
+  /** Tags */
+  keywords?: string;
+  /** Comments */
+  description?: string;
+  last_modified_by?: string;
+  category?: string;
+  /** When the file was created, ISO 8601 in UTC ("2024-05-01T09:30:00Z") */
+  created?: string;
+  /** When the file was last saved, ISO 8601 in UTC */
+  modified?: string;
+  /** Program named as the file's author; "Microsoft Excel" when left out */
+  application?: string;
+  company?: string;
+  manager?: string;
+  /** Properties named by the user, as in File > Properties > Custom */
+  custom?: CustomProperty[];
+}
+
+/** A named custom property holding text, a number or a yes/no value */
+export interface CustomProperty {
+  name: string;
+  value: TypedValue;
+}
+
+// protection.rs
+
+/**
+ * A worksheet's protection
+ *
+ * The flags say what users may still do on the protected sheet; by
+ * default they can only select cells.
+ */
+export interface SheetProtection {
+  /** Algorithm of `hash_value`, "SHA-512" */
+  algorithm_name?: string;
+  /** Base64 hash of the salted password */
+  hash_value?: string;
+  /** Base64 salt */
+  salt_value?: string;
+  /** Times the hash was rehashed */
+  spin_count?: number;
+  /** Legacy 16-bit password hash in hex, e.g. "DAA7" */
+  legacy_hash?: string;
+  select_locked_cells?: boolean;
+  select_unlocked_cells?: boolean;
+  format_cells?: boolean;
+  format_columns?: boolean;
+  format_rows?: boolean;
+  insert_columns?: boolean;
+  insert_rows?: boolean;
+  insert_hyperlinks?: boolean;
+  delete_columns?: boolean;
+  delete_rows?: boolean;
+  sort?: boolean;
+  auto_filter?: boolean;
+  pivot_tables?: boolean;
+  /** Edit pictures, charts and comments */
+  objects?: boolean;
+  scenarios?: boolean;
+}
+
+// reference.rs
+
+/** 0-based position of a cell */
+export interface CellPosition {
+  row: number;
+  col: number;
+}
+
+/** 0-based inclusive corners of a range, normalised so start <= end */
+export interface RangePosition {
+  start_row: number;
+  start_col: number;
+  end_row: number;
+  end_col: number;
+}
+
+/** Options for `compute_used_range` */
+export interface UsedRangeOptions {
+  /** Leave out cells that carry a style but no value or formula */
+  ignore_style_only?: boolean;
+}
+
+/** Bounding box of a worksheet's non-empty cells */
+export interface UsedRange {
+  /** A1 form such as `B2:D9`, or a single cell */
+  reference: string;
+  start_row: number;
+  start_col: number;
+  end_row: number;
+  end_col: number;
+}
+
+// repair.rs
+
+/** One problem worked around while opening a package */
+export interface Repair {
+  /** "missing_part", "dangling_relationship" or "missing_content_type" */
+  kind: string;
+  /** The part the problem was found in or about */
+  part?: string;
+  description: string;
+}
+
+// rich.rs
+
+/** A shared string item with its formatted runs */
+export interface RichSharedString {
+  /** The item's text, as `parse_shared_strings` gives it */
+  text: string;
+  /** Runs in order; empty for plain text */
+  runs: RichTextRun[];
+}
+
+// search.rs
+
+/** Workbook contents searched by `search` */
+export interface SearchWorkbook {
+  sheets: ParsedSheet[];
+  shared_strings: string[];
+  comments: CellComment[];
+}
+
+/** A cell comment (note) to include in the search */
+export interface CellComment {
+  sheet: string;
+  reference: string;
+  text: string;
+}
+
+/** Options for `search` */
+export interface SearchOptions {
+  match_case?: boolean;
+  /** Only match when the query covers the whole text */
+  whole_cell?: boolean;
+  /** Treat the query as a regular expression */
+  regex?: boolean;
+  /** Any of "values", "formulas" and "comments" */
+  scope?: string[];
+  /** Stop after this many matches */
+  limit?: number;
+}
+
+/** A match location; `start` and `end` are UTF-16 offsets into `text` */
+export interface SearchMatch {
+  sheet: string;
+  reference: string;
+  /** 1-based row */
+  row: number;
+  /** 1-based column */
+  col: number;
+  /** "value", "formula" or "comment" */
+  scope: string;
+  text: string;
+  start: number;
+  end: number;
+}
+
+// sort.rs
+
+/** A column to sort by */
+export interface SortKey {
+  /** Column letters, e.g. "C" */
+  column: string;
+  descending: boolean;
+}
+
+/** Options for `sort_rows` */
+export interface SortOptions {
+  /** First row sorted (1-based), e.g. 2 to keep a header row in place */
+  first_row?: number;
+  /** Last row sorted; defaults to the last row with cells */
+  last_row?: number;
+  /**
+   * Tell case apart, lowercase first ("a" before "A"), rather than
+   * treating it as equal
+   */
+  case_sensitive?: boolean;
+  /**
+   * BCP 47 locale ("de", "sv-SE") whose collation orders text, through
+   * `Intl.Collator`; text is otherwise compared by code point after case
+   * folding
+   */
+  locale?: string;
+  shared_strings?: string[];
+}
+
+// stats.rs
+
+/** Element counts for a worksheet */
+export interface WorksheetStats {
+  rows: number;
+  /** `<c>` elements, including style-only cells without a value */
+  cells: number;
+  formulas: number;
+  /** Highest 1-based column referenced by a cell, 0 when there are none */
+  max_col: number;
+}
+
+/** Parsed sheets summarized by `workbook_stats` */
+export interface StatsWorkbook {
+  sheets: ParsedSheet[];
+  shared_strings: string[];
+  /** Styles used to tell dates from other numbers */
+  styles?: ParsedStyles;
+}
+
+/** What a parsed sheet holds */
+export interface SheetStats {
+  name: string;
+  rows: number;
+  cells: number;
+  numbers: number;
+  /** Numbers in date-formatted cells */
+  dates: number;
+  text: number;
+  booleans: number;
+  errors: number;
+  /** Cells without a value, such as style-only cells */
+  empty: number;
+  formulas: number;
+  merges: number;
+  hyperlinks: number;
+  comments: number;
+  /** Distinct cell styles the sheet's cells use */
+  styles_used: number;
+  /** Highest 1-based row with a cell, 0 when there are none */
+  max_row: number;
+  /** Highest 1-based column with a cell, 0 when there are none */
+  max_col: number;
+}
+
+// stream.rs
+
+/** Options for `WorksheetWriter` */
+export interface WorksheetWriterOptions {
+  col_widths: Map<number, number>;
+  /** DEFLATE level (0-10) */
+  compression_level?: number;
+}
+
+// table.rs
+
+/** A table definition */
+export interface ParsedTable {
+  name: string;
+  /** Name used in formulas */
+  display_name: string;
+  /** Range covered by the table, headers and totals included */
+  reference: string;
+  header_row_count: number;
+  totals_row_count: number;
+  columns: string[];
+  /**
+   * Sheet holding the table; table parts don't record it, so hosts fill
+   * it in from the worksheet relationships
+   */
+  sheet?: string;
+  style?: TableStyleInfo;
+  /** What the totals row shows under each column, in column order */
+  totals?: ColumnTotal[];
+}
+
+/** How a table is drawn */
+export interface TableStyleInfo {
+  /** "TableStyleMedium2", "TableStyleLight9", ... */
+  name?: string;
+  show_first_column?: boolean;
+  show_last_column?: boolean;
+  show_row_stripes?: boolean;
+  show_column_stripes?: boolean;
+}
+
+/** A table column's cell in the totals row */
+export interface ColumnTotal {
+  /**
+   * "sum", "average", "count", "countNums", "max", "min", "stdDev",
+   * "var" or "custom"
+   */
+  function?: string;
+  /** Text shown instead of a function, e.g. "Total" */
+  label?: string;
+  /** The formula of a "custom" function */
+  formula?: string;
+}
+
+// typed.rs
+
+/** A cell reduced to its typed value; errors are their code as a string */
+export interface ValueCell {
+  reference: string;
+  value?: TypedValue;
+}
+
+export interface ValueRow {
+  row_num: number;
+  cells: ValueCell[];
+}
+
+/** Options for `parse_worksheet_values` */
+export interface ValuesParseOptions {
+  shared_strings?: string[];
+  /** Same as `WorksheetParseOptions::range` */
+  range?: string;
+  skip_rows?: number;
+  max_rows?: number;
+  /** Leave out cells without a value */
+  skip_empty_cells?: boolean;
+}
+
+/** Headers and inferred types from the top of a worksheet */
+export interface WorksheetProbe {
+  /** The `<dimension>` ref as written, which may be stale or missing */
+  dimension?: string;
+  /** Extent of the whole sheet per the dimension, else of the sampled rows */
+  range?: string;
+  columns: ProbeColumn[];
+  /** Data rows sampled after the header row */
+  sampled_rows: number;
+}
+
+export interface ProbeColumn {
+  /** 1-based column */
+  col: number;
+  /** Header text, unique and never empty (falls back to the column letter) */
+  header: string;
+  /** "number", "string", "boolean", "mixed" or "empty" (no values sampled) */
+  value_type: string;
+}
+
+// validate.rs
+
+/** One problem found in a workbook */
+export interface ValidationIssue {
+  /**
+   * "error" for content that is lost or wrong, "warning" for packaging
+   * that Excel would tolerate
+   */
+  severity: string;
+  /** Machine-readable kind, e.g. "duplicate_sheet_name" or "missing_part" */
+  code: string;
+  part?: string;
+  sheet?: string;
+  /** First cell or range the issue applies to */
+  reference?: string;
+  message: string;
+}
+
+// views.rs
+
+/** One sheet's part of a custom view */
+export interface CustomSheetView {
+  /** The view's id ("{9C1F...}"), shared by its part on every sheet */
+  guid?: string;
+  /** Zoom in percent */
+  scale?: number;
+  /** "normal", "pageBreakPreview" or "pageLayout" */
+  view?: string;
+  /** "visible", "hidden" or "veryHidden": whether the view shows the sheet */
+  state?: string;
+  /** Cell scrolled to the top left */
+  top_left_cell?: string;
+  show_grid_lines?: boolean;
+  show_row_col?: boolean;
+  show_formulas?: boolean;
+  show_zeros?: boolean;
+  show_auto_filter?: boolean;
+  /** Rows the view hides ("3:5"), from its `.wvu.Rows` name */
+  hidden_rows?: string[];
+  /** Columns the view hides ("C:D"), from its `.wvu.Cols` name */
+  hidden_columns?: string[];
+  /** Ranges printed, from its `.wvu.PrintArea` name */
+  print_area?: string[];
+  /**
+   * Rows and columns repeated on each printed page, from its
+   * `.wvu.PrintTitles` name
+   */
+  print_titles?: string[];
+  /** The view's filter dropdowns and criteria */
+  auto_filter?: AutoFilter;
+  print?: ViewPrintSettings;
+}
+
+/** How a custom view prints its sheet */
+export interface ViewPrintSettings {
+  /** "portrait" or "landscape" */
+  orientation?: string;
+  /** Paper size code: 1 for Letter, 9 for A4, ... */
+  paper_size?: number;
+  /** Print scale in percent */
+  scale?: number;
+  /**
+   * Shrink to `fit_to_width` pages wide and `fit_to_height` tall
+   * rather than print at `scale`
+   */
+  fit_to_page?: boolean;
+  /** Pages wide, 0 for any number; Excel takes 1 when missing */
+  fit_to_width?: number;
+  /** Pages tall, 0 for any number; Excel takes 1 when missing */
+  fit_to_height?: number;
+  margins?: PageMargins;
+  grid_lines?: boolean;
+  headings?: boolean;
+  horizontal_centered?: boolean;
+  vertical_centered?: boolean;
+}
+
+/** Page margins in inches */
+export interface PageMargins {
+  left?: number;
+  right?: number;
+  top?: number;
+  bottom?: number;
+  header?: number;
+  footer?: number;
+}
+
+// workbook.rs
+
+/** Parsed workbook contents accepted by the `Workbook` constructor */
+export interface WorkbookData {
+  sheets: ParsedSheet[];
+  shared_strings: string[];
+  /** Styles used to report date-formatted numbers as dates */
+  styles?: ParsedStyles;
+  date1904: boolean;
+  /** Named ranges, print areas and the like (.xlsx only) */
+  defined_names: ParsedDefinedName[];
+}
+
+/** A typed cell value; dates are ISO 8601 strings */
+export type TypedValue = number | string | boolean;
+
+/** One cell as returned by the `Workbook` accessors */
+export interface CellData {
+  reference: string;
+  /** "number", "string", "boolean", "error", "date" or "empty" */
+  type: string;
+  value?: TypedValue;
+  formula?: string;
+}
+
+// writer.rs
+
+/** A formatted run inside a rich-text shared string */
+export interface RichTextRun {
+  text: string;
+  font?: ParsedFont;
+}
+
+/** Shared string table entry: either plain text or a list of rich-text runs */
+export type SharedStringEntry = string | RichTextRun[];
+
+/** Result of writing sharedStrings.xml */
+export interface WrittenSharedStrings {
+  xml: string;
+  /** Index in the written table for each input entry, in input order */
+  indices: number[];
+  unique_count: number;
+}
+
+/** Result of writing styles.xml */
+export interface WrittenStyles {
+  xml: string;
+  /** Written cellXfs index for each input cellXfs entry, in input order */
+  xf_indices: number[];
+}
+
+/**
+ * A package part listed in [Content_Types].xml
+ *
+ * Accepts either a bare part name, whose content type is inferred from its
+ * location, or an explicit name/content type pair.
+ */
+export type PackagePart = string | { part_name: string; content_type: string };
+
+// xls.rs
+
+/** Result of `parse_xls` */
+export interface ParsedXls {
+  sheets: ParsedSheet[];
+  shared_strings: string[];
+  styles: ParsedStyles;
+  date1904: boolean;
+}
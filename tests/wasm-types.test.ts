@@ -0,0 +1,21 @@
+/**
+ * Tests for the TypeScript definitions generated from the WASM crate's types
+ */
+
+import { describe, it, expect } from 'vitest';
+import { readFileSync } from 'node:fs';
+import { generateWasmTypes, rustTypeToTs, OUTPUT_FILE } from '../scripts/generate-wasm-types.mjs';
+
+describe('generate-wasm-types', () => {
+  it('should match the checked-in definitions', () => {
+    expect(readFileSync(OUTPUT_FILE, 'utf8')).toBe(generateWasmTypes());
+  });
+
+  it('should map Rust types to their serde-wasm-bindgen shapes', () => {
+    const known = new Set(['ParsedCell']);
+    expect(rustTypeToTs('Vec<Option<ParsedCell>>', known)).toBe('(ParsedCell | undefined)[]');
+    expect(rustTypeToTs('HashMap<u32, f64>', known)).toBe('Map<number, number>');
+    expect(rustTypeToTs('(String, bool)', known)).toBe('[string, boolean]');
+    expect(() => rustTypeToTs('JsValue', known)).toThrow('JsValue');
+  });
+});
@@ -122,11 +122,11 @@ describe('xlsx.reader.ts WASM code paths', () => {
             apply_fill: false,
             apply_border: false,
             apply_alignment: false,
-            horizontal: null,
-            vertical: null,
+            horizontal: undefined,
+            vertical: undefined,
             wrap_text: false,
-            text_rotation: null,
-            indent: null,
+            text_rotation: undefined,
+            indent: undefined,
           },
         ],
         fonts: [
@@ -140,20 +140,20 @@ describe('xlsx.reader.ts WASM code paths', () => {
             name: 'Arial',
           },
         ],
-        fills: [{ pattern_type: 'solid', fg_color: '#FFFF00', bg_color: null }],
+        fills: [{ pattern_type: 'solid', fg_color: '#FFFF00', bg_color: undefined }],
         borders: [
           {
             left_style: 'thin',
             left_color: '#000000',
-            right_style: null,
-            right_color: null,
-            top_style: null,
-            top_color: null,
-            bottom_style: null,
-            bottom_color: null,
+            right_style: undefined,
+            right_color: undefined,
+            top_style: undefined,
+            top_color: undefined,
+            bottom_style: undefined,
+            bottom_color: undefined,
           },
         ],
-        num_fmts: { 164: '#,##0.00' },
+        num_fmts: new Map([[164, '#,##0.00']]),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -198,8 +198,8 @@ describe('xlsx.reader.ts WASM code paths', () => {
           {
             row_num: 1,
             cells: [
-              { reference: 'A1', cell_type: null, style_index: 0, value: '42', formula: null },
-              { reference: 'B1', cell_type: null, style_index: 0, value: '100', formula: null },
+              { reference: 'A1', cell_type: undefined, style_index: 0, value: '42', formula: undefined },
+              { reference: 'B1', cell_type: undefined, style_index: 0, value: '100', formula: undefined },
             ],
             height: 20,
             hidden: false,
@@ -207,7 +207,7 @@ describe('xlsx.reader.ts WASM code paths', () => {
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: { 1: 15, 2: 20 },
+        col_widths: new Map([[1, 15], [2, 20]]),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -235,16 +235,16 @@ describe('xlsx.reader.ts WASM code paths', () => {
           {
             row_num: 1,
             cells: [
-              { reference: 'A1', cell_type: null, style_index: 0, value: '10', formula: null },
+              { reference: 'A1', cell_type: undefined, style_index: 0, value: '10', formula: undefined },
               { reference: 'B1', cell_type: 'n', style_index: 0, value: '30', formula: 'A1*3' },
             ],
-            height: null,
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -270,14 +270,14 @@ describe('xlsx.reader.ts WASM code paths', () => {
         rows: [
           {
             row_num: 1,
-            cells: [{ reference: 'A1', cell_type: 'str', style_index: 0, value: 'Merged', formula: null }],
-            height: null,
+            cells: [{ reference: 'A1', cell_type: 'str', style_index: 0, value: 'Merged', formula: undefined }],
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: ['A1:C1'],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -304,14 +304,14 @@ describe('xlsx.reader.ts WASM code paths', () => {
         rows: [
           {
             row_num: 1,
-            cells: [{ reference: 'A1', cell_type: 's', style_index: 0, value: '0', formula: null }],
-            height: null,
+            cells: [{ reference: 'A1', cell_type: 's', style_index: 0, value: '0', formula: undefined }],
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -336,16 +336,16 @@ describe('xlsx.reader.ts WASM code paths', () => {
           {
             row_num: 1,
             cells: [
-              { reference: 'A1', cell_type: 'b', style_index: 0, value: '1', formula: null },
-              { reference: 'B1', cell_type: 'b', style_index: 0, value: '0', formula: null },
+              { reference: 'A1', cell_type: 'b', style_index: 0, value: '1', formula: undefined },
+              { reference: 'B1', cell_type: 'b', style_index: 0, value: '0', formula: undefined },
             ],
-            height: null,
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -370,14 +370,14 @@ describe('xlsx.reader.ts WASM code paths', () => {
         rows: [
           {
             row_num: 1,
-            cells: [{ reference: 'A1', cell_type: 'str', style_index: 0, value: 'DirectString', formula: null }],
-            height: null,
+            cells: [{ reference: 'A1', cell_type: 'str', style_index: 0, value: 'DirectString', formula: undefined }],
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -401,14 +401,14 @@ describe('xlsx.reader.ts WASM code paths', () => {
         rows: [
           {
             row_num: 1,
-            cells: [{ reference: 'A1', cell_type: 'e', style_index: 0, value: '#DIV/0!', formula: null }],
-            height: null,
+            cells: [{ reference: 'A1', cell_type: 'e', style_index: 0, value: '#DIV/0!', formula: undefined }],
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -430,13 +430,13 @@ describe('xlsx.reader.ts WASM code paths', () => {
     it('should respect maxRows option with WASM parser', async () => {
       const mockWorksheet = {
         rows: [
-          { row_num: 1, cells: [{ reference: 'A1', cell_type: null, style_index: 0, value: '1', formula: null }], height: null, hidden: false },
-          { row_num: 2, cells: [{ reference: 'A2', cell_type: null, style_index: 0, value: '2', formula: null }], height: null, hidden: false },
-          { row_num: 3, cells: [{ reference: 'A3', cell_type: null, style_index: 0, value: '3', formula: null }], height: null, hidden: false },
+          { row_num: 1, cells: [{ reference: 'A1', cell_type: undefined, style_index: 0, value: '1', formula: undefined }], height: undefined, hidden: false },
+          { row_num: 2, cells: [{ reference: 'A2', cell_type: undefined, style_index: 0, value: '2', formula: undefined }], height: undefined, hidden: false },
+          { row_num: 3, cells: [{ reference: 'A3', cell_type: undefined, style_index: 0, value: '3', formula: undefined }], height: undefined, hidden: false },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -464,17 +464,17 @@ describe('xlsx.reader.ts WASM code paths', () => {
           {
             row_num: 1,
             cells: [
-              { reference: 'A1', cell_type: null, style_index: 0, value: '1', formula: null },
-              { reference: 'B1', cell_type: null, style_index: 0, value: '2', formula: null },
-              { reference: 'C1', cell_type: null, style_index: 0, value: '3', formula: null },
+              { reference: 'A1', cell_type: undefined, style_index: 0, value: '1', formula: undefined },
+              { reference: 'B1', cell_type: undefined, style_index: 0, value: '2', formula: undefined },
+              { reference: 'C1', cell_type: undefined, style_index: 0, value: '3', formula: undefined },
             ],
-            height: null,
+            height: undefined,
             hidden: false,
           },
         ],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       };
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -523,7 +523,7 @@ describe('xlsx.reader.ts WASM code paths', () => {
         rows: [],
         merge_cells: [],
         hyperlinks: [],
-        col_widths: {},
+        col_widths: new Map(),
       });
 
       vi.doMock('../src/formats/xlsx/xlsx.parser.wasm.js', () => ({
@@ -12,11 +12,28 @@ if ! command -v wasm-pack &> /dev/null; then
     cargo install wasm-pack
 fi
 
-wasm-pack build --target web --out-dir ../src/formats/xlsx/wasm --release
+# wasm SIMD (all current browsers, Node 16.4+) speeds up memchr/simdutf8 and quick-xml's scanning
+export RUSTFLAGS="${RUSTFLAGS:+$RUSTFLAGS }-C target-feature=+simd128"
 
-rm -f ../src/formats/xlsx/wasm/.gitignore
-rm -f ../src/formats/xlsx/wasm/package.json
-rm -f ../src/formats/xlsx/wasm/README.md
+# --core leaves out the optional parsers (the `full` feature: .xls, .ods,
+# encryption, formulas, Arrow/HTML, charts, pivots, drawings and comments)
+# for a smaller module that reads XLSX values and styles; the full build
+# can then be loaded on demand next to it
+OUT_DIR=../src/formats/xlsx/wasm
+FEATURES=()
+if [ "$1" = "--core" ]; then
+    OUT_DIR=../src/formats/xlsx/wasm-core
+    FEATURES=(--no-default-features --features wasm,console_error_panic_hook)
+fi
+
+wasm-pack build --target web --out-dir "$OUT_DIR" --release -- "${FEATURES[@]}"
+
+rm -f "$OUT_DIR/.gitignore"
+rm -f "$OUT_DIR/package.json"
+rm -f "$OUT_DIR/README.md"
+
+# Keep the TypeScript definitions in step with the Rust structs
+node ../scripts/generate-wasm-types.mjs
 
 echo "WASM module built successfully!"
-echo "Output: src/formats/xlsx/wasm/"
+echo "Output: ${OUT_DIR#../}/"
@@ -0,0 +1,328 @@
+//! Reference rewriting for row and column inserts and deletes
+//!
+//! References on the edited sheet move with the cells they point at. Ranges
+//! that straddle an insert grow, ranges that lose some of their rows or
+//! columns to a delete shrink, and references whose cells are all deleted
+//! become `#REF!`.
+
+use crate::formula::{rewrite_references, Axis, Notation, RefPoint, Reference, MAX_COLS, MAX_ROWS};
+use crate::reference::{bounds_ref, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A structural edit, as passed to [`adjust_formulas`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureChange {
+    /// "insert_rows", "delete_rows", "insert_cols" or "delete_cols"
+    pub action: String,
+    /// First inserted or deleted row/column (1-based)
+    pub index: u32,
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// Sheet being edited; defaults to the sheet holding the formulas
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// Sheet the formulas live on, which unqualified references point to
+    #[serde(default)]
+    pub formula_sheet: Option<String>,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+/// Shift references in `formulas` (a string array) for a row/column insert or
+/// delete, returning the rewritten formulas in the same order
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn adjust_formulas(formulas: JsValue, change: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let formulas: Vec<String> = serde_wasm_bindgen::from_value(formulas)?;
+        let change: StructureChange = serde_wasm_bindgen::from_value(change)?;
+        let adjusted =
+            adjust_formulas_impl(&formulas, &change).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&adjusted)?)
+    })
+}
+
+/// Which axis moves and how
+#[derive(Clone, Copy)]
+pub(crate) enum Edit {
+    Insert { at: u32, count: u32 },
+    Delete { first: u32, last: u32 },
+}
+
+impl Edit {
+    fn count(self) -> u32 {
+        match self {
+            Edit::Insert { count, .. } => count,
+            Edit::Delete { first, last } => last - first + 1,
+        }
+    }
+
+    /// New (start, end) of a span along the edited axis, or None once it is gone
+    ///
+    /// An insert that pushes the end of a span past `max` leaves it ending on
+    /// `max`, as Excel keeps `A1:A1048576` whole; the span is only gone when
+    /// its start is pushed off too.
+    pub(crate) fn apply(self, start: u32, end: u32, max: u32) -> Option<(u32, u32)> {
+        match self {
+            Edit::Insert { at, count } => {
+                let shift = |i: u32| if i >= at { i.saturating_add(count) } else { i };
+                let (start, end) = (shift(start), shift(end));
+                (start <= max).then_some((start, end.min(max)))
+            }
+            Edit::Delete { first, last } => {
+                if start >= first && end <= last {
+                    return None;
+                }
+                let start = if start > last {
+                    start - self.count()
+                } else {
+                    start.min(first)
+                };
+                let end = if end > last {
+                    end - self.count()
+                } else if end >= first {
+                    first - 1
+                } else {
+                    end
+                };
+                Some((start, end))
+            }
+        }
+    }
+}
+
+fn same_sheet(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Move one reference; `None` means it now points at deleted cells
+fn adjust_reference(reference: &Reference, edit: Edit, rows: bool) -> Option<Reference> {
+    let end = reference.end.unwrap_or(reference.start);
+    let axis = |p: &RefPoint| if rows { p.row } else { p.col };
+    let (first, last) = match (axis(&reference.start), axis(&end)) {
+        (Some(first), Some(last)) => (first, last),
+        // Whole columns are untouched by row edits and vice versa
+        _ => return Some(reference.clone()),
+    };
+
+    // Ranges may be written bottom-up; adjust them in sorted order
+    let (low, high, swapped) = if first.index <= last.index {
+        (first.index, last.index, false)
+    } else {
+        (last.index, first.index, true)
+    };
+    let max = if rows { MAX_ROWS } else { MAX_COLS };
+    let (low, high) = edit.apply(low, high, max)?;
+    let (new_first, new_last) = if swapped { (high, low) } else { (low, high) };
+
+    let set = |p: &RefPoint, index: u32, template: Axis| {
+        let axis = Some(Axis { index, ..template });
+        if rows {
+            RefPoint { row: axis, ..*p }
+        } else {
+            RefPoint { col: axis, ..*p }
+        }
+    };
+    let mut adjusted = reference.clone();
+    adjusted.start = set(&reference.start, new_first, first);
+    if let Some(end) = &reference.end {
+        adjusted.end = Some(set(end, new_last, last));
+    }
+    Some(adjusted)
+}
+
+impl StructureChange {
+    /// The edit this change makes, and whether it moves rows rather than columns
+    pub(crate) fn edit(&self) -> Result<(Edit, bool), String> {
+        if self.index == 0 || self.count == 0 {
+            return Err("Structure change needs a 1-based index and a positive count".to_string());
+        }
+        let (rows, insert) = match self.action.as_str() {
+            "insert_rows" => (true, true),
+            "delete_rows" => (true, false),
+            "insert_cols" => (false, true),
+            "delete_cols" => (false, false),
+            other => return Err(format!("Unknown structure change: {}", other)),
+        };
+        let (max, axis) = if rows {
+            (MAX_ROWS, "row")
+        } else {
+            (MAX_COLS, "column")
+        };
+        let last = self
+            .index
+            .checked_add(self.count - 1)
+            .filter(|&last| last <= max)
+            .ok_or_else(|| format!("Structure change runs past the last {}", axis))?;
+        let edit = if insert {
+            Edit::Insert {
+                at: self.index,
+                count: self.count,
+            }
+        } else {
+            Edit::Delete {
+                first: self.index,
+                last,
+            }
+        };
+        Ok((edit, rows))
+    }
+}
+
+/// Move an A1 cell or range on the edited sheet, such as a merge; `None` once
+/// all its cells are deleted. Ranges that can't be read are kept as they are.
+pub(crate) fn adjust_range(range: &str, edit: Edit, rows: bool) -> Option<String> {
+    let Some((r1, c1, r2, c2)) = range_bounds(range) else {
+        return Some(range.to_string());
+    };
+    let bounds = if rows {
+        let (r1, r2) = edit.apply(r1, r2, MAX_ROWS)?;
+        (r1, c1, r2, c2)
+    } else {
+        let (c1, c2) = edit.apply(c1, c2, MAX_COLS)?;
+        (r1, c1, r2, c2)
+    };
+    Some(bounds_ref(bounds))
+}
+
+pub(crate) fn adjust_formulas_impl(
+    formulas: &[String],
+    change: &StructureChange,
+) -> Result<Vec<String>, String> {
+    let (edit, rows) = change.edit()?;
+
+    // Unqualified references live on the formula's own sheet
+    let local_edited = match (&change.sheet, &change.formula_sheet) {
+        (Some(sheet), Some(formula_sheet)) => same_sheet(sheet, formula_sheet),
+        _ => true,
+    };
+    let edited_sheet = change.sheet.as_ref().or(change.formula_sheet.as_ref());
+
+    formulas
+        .iter()
+        .enumerate()
+        .map(|(i, formula)| {
+            rewrite_references(formula, Notation::A1, |reference| {
+                let affected = match &reference.sheet {
+                    None => local_edited,
+                    Some(sheet) => edited_sheet.is_some_and(|edited| {
+                        same_sheet(sheet, edited)
+                            || reference
+                                .last_sheet
+                                .as_deref()
+                                .is_some_and(|last| same_sheet(last, edited))
+                    }),
+                };
+                if !affected {
+                    return reference.to_a1();
+                }
+                match adjust_reference(reference, edit, rows) {
+                    Some(adjusted) => adjusted.to_a1(),
+                    None => format!("{}#REF!", reference.sheet_prefix()),
+                }
+            })
+            .map_err(|e| format!("Formula {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adjust(formula: &str, action: &str, index: u32, count: u32, sheet: Option<&str>) -> String {
+        let change = StructureChange {
+            action: action.to_string(),
+            index,
+            count,
+            sheet: sheet.map(str::to_string),
+            formula_sheet: Some("Sheet1".to_string()),
+        };
+        adjust_formulas_impl(&[formula.to_string()], &change)
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn test_insert() {
+        assert_eq!(
+            adjust("=A1+A5+SUM(A2:B10)+$C$7", "insert_rows", 3, 2, None),
+            "=A1+A7+SUM(A2:B12)+$C$9"
+        );
+        assert_eq!(
+            adjust(
+                "=SUM(B:D)+Sheet2!C3+C3",
+                "insert_cols",
+                3,
+                1,
+                Some("Sheet2")
+            ),
+            "=SUM(B:D)+Sheet2!D3+C3"
+        );
+        assert_eq!(adjust("=A1048576", "insert_rows", 1, 1, None), "=#REF!");
+        // Ranges ending on the last row stay pinned to it
+        assert_eq!(
+            adjust(
+                "=SUM(A1:A1048576)+SUM(A6:B1048575)",
+                "insert_rows",
+                5,
+                1,
+                None
+            ),
+            "=SUM(A1:A1048576)+SUM(A7:B1048576)"
+        );
+        assert_eq!(
+            adjust("=SUM(A1048575:A1048576)", "insert_rows", 2, 2, None),
+            "=SUM(#REF!)"
+        );
+        assert_eq!(
+            adjust("=SUM(XFC1:XFD1)", "insert_cols", 1, 1, None),
+            "=SUM(XFD1:XFD1)"
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let change = |action: &str, index, count| StructureChange {
+            action: action.to_string(),
+            index,
+            count,
+            sheet: None,
+            formula_sheet: None,
+        };
+        assert!(change("insert_rows", 5, u32::MAX).edit().is_err());
+        assert!(change("delete_rows", 1_048_576, 2).edit().is_err());
+        assert!(change("insert_cols", 16_384, 1).edit().is_ok());
+        assert!(change("delete_cols", 16_385, 1).edit().is_err());
+        let formulas = ["=A1".to_string()];
+        assert!(adjust_formulas_impl(&formulas, &change("insert_rows", 1, u32::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_delete() {
+        assert_eq!(
+            adjust("=A2+A3+A6+SUM(A1:A4)+SUM(A3:A4)", "delete_rows", 3, 2, None),
+            "=A2+#REF!+A4+SUM(A1:A2)+SUM(#REF!)"
+        );
+        assert_eq!(
+            adjust(
+                "=SUM(B1:E1)+'My Sheet'!C1+SUM(2:2)",
+                "delete_cols",
+                2,
+                2,
+                Some("My Sheet")
+            ),
+            "=SUM(B1:E1)+'My Sheet'!#REF!+SUM(2:2)"
+        );
+        assert_eq!(
+            adjust("=SUM(B1:E1)+\"B1\"", "delete_cols", 2, 2, None),
+            "=SUM(B1:C1)+\"B1\""
+        );
+    }
+}
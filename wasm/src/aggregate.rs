@@ -0,0 +1,305 @@
+//! Column aggregation
+//!
+//! Computes sums, averages, extremes and counts over a parsed worksheet's
+//! columns, overall or per group of rows sharing key values, so summary
+//! figures don't need every row serialized to JS first.
+
+use crate::duplicates::{key_value, DuplicateOptions, Key};
+use crate::reference::{parse_cell_ref, parse_col};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{resolve_cell_value, CellValue};
+use crate::workbook::TypedValue;
+use crate::{ParsedRow, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// What to compute for [`aggregate`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AggregateSpec {
+    /// Columns whose values group rows ("A", "C", ...); all rows form one
+    /// group when empty
+    pub group_by: Vec<String>,
+    pub aggregates: Vec<Aggregate>,
+    /// First row read (1-based), e.g. 2 to leave out a header row
+    pub first_row: Option<u32>,
+    pub shared_strings: Vec<String>,
+}
+
+/// One figure to compute
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Aggregate {
+    /// Column letters, e.g. "C"
+    pub column: String,
+    /// "sum", "avg", "min" or "max" over the column's numbers, "count" of
+    /// its values or "distinct" values
+    pub function: String,
+}
+
+/// The figures for one group of rows
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AggregateGroup {
+    /// The group's value in each `group_by` column
+    pub key: Vec<Option<TypedValue>>,
+    /// Rows in the group; rows without any value are left out
+    pub rows: u32,
+    /// One per aggregate, in order; `None` for an average or extreme
+    /// without numbers
+    pub values: Vec<Option<f64>>,
+}
+
+/// An aggregate function
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Function {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+    Distinct,
+}
+
+impl Function {
+    /// Read a function name, in any case
+    pub(crate) fn parse(name: &str) -> Result<Function, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "sum" => Ok(Function::Sum),
+            "avg" => Ok(Function::Avg),
+            "min" => Ok(Function::Min),
+            "max" => Ok(Function::Max),
+            "count" => Ok(Function::Count),
+            "distinct" => Ok(Function::Distinct),
+            _ => Err(format!("Unknown aggregate: {}", name)),
+        }
+    }
+}
+
+/// Running state of one aggregate in one group
+#[derive(Default)]
+pub(crate) struct Accumulator {
+    sum: f64,
+    numbers: u32,
+    min: Option<f64>,
+    max: Option<f64>,
+    values: u32,
+    distinct: HashSet<Key>,
+}
+
+impl Accumulator {
+    /// Take in a non-empty value
+    pub(crate) fn add(&mut self, value: &CellValue) {
+        self.values += 1;
+        if let CellValue::Number(n) = *value {
+            self.sum += n;
+            self.numbers += 1;
+            self.min = Some(self.min.map_or(n, |min| min.min(n)));
+            self.max = Some(self.max.map_or(n, |max| max.max(n)));
+        }
+        // Distinct values compare exactly, as find_duplicate_rows does by
+        // default
+        self.distinct
+            .insert(key_value(value.clone(), &DuplicateOptions::default()));
+    }
+
+    /// The figure `function` gives for the values taken in
+    pub(crate) fn result(&self, function: Function) -> Option<f64> {
+        match function {
+            Function::Sum => Some(self.sum),
+            Function::Avg => (self.numbers > 0).then(|| self.sum / f64::from(self.numbers)),
+            Function::Min => self.min,
+            Function::Max => self.max,
+            Function::Count => Some(f64::from(self.values)),
+            Function::Distinct => Some(self.distinct.len() as f64),
+        }
+    }
+}
+
+/// Compute `spec`'s aggregates per group, groups ordered by their first row
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn aggregate(worksheet: JsValue, spec: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let spec: AggregateSpec = serde_wasm_bindgen::from_value(spec)?;
+        let groups = aggregate_impl(&worksheet, &spec).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&groups)?)
+    })
+}
+
+pub(crate) fn aggregate_impl(
+    worksheet: &ParsedWorksheet,
+    spec: &AggregateSpec,
+) -> Result<Vec<AggregateGroup>, String> {
+    let column = |letters: &str| parse_col(letters).ok_or(format!("Invalid column: {}", letters));
+    let group_by = spec
+        .group_by
+        .iter()
+        .map(|letters| column(letters))
+        .collect::<Result<Vec<u32>, String>>()?;
+    let aggregates = spec
+        .aggregates
+        .iter()
+        .map(|aggregate| {
+            let function = Function::parse(&aggregate.function)?;
+            Ok((column(&aggregate.column)?, function))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let first_row = spec.first_row.unwrap_or(1);
+    let mut groups: HashMap<Vec<Key>, usize> = HashMap::new();
+    let mut states: Vec<(AggregateGroup, Vec<Accumulator>)> = Vec::new();
+    for row in &worksheet.rows {
+        if row.row_num < first_row {
+            continue;
+        }
+        let by_col = row_values(row, &spec.shared_strings);
+        if by_col.is_empty() {
+            continue;
+        }
+
+        let values: Vec<Option<&CellValue>> = group_by.iter().map(|col| by_col.get(col)).collect();
+        let group = *groups.entry(group_key(&values)).or_insert_with(|| {
+            let key = values.iter().map(|value| value.map(typed_value)).collect();
+            let group = AggregateGroup {
+                key,
+                rows: 0,
+                values: Vec::new(),
+            };
+            states.push((
+                group,
+                aggregates.iter().map(|_| Default::default()).collect(),
+            ));
+            states.len() - 1
+        });
+        let (group, accumulators) = &mut states[group];
+        group.rows += 1;
+        for (&(col, _), acc) in aggregates.iter().zip(accumulators.iter_mut()) {
+            if let Some(value) = by_col.get(&col) {
+                acc.add(value);
+            }
+        }
+    }
+
+    Ok(states
+        .into_iter()
+        .map(|(mut group, accumulators)| {
+            group.values = aggregates
+                .iter()
+                .zip(accumulators)
+                .map(|(&(_, function), acc)| acc.result(function))
+                .collect();
+            group
+        })
+        .collect())
+}
+
+/// A row's non-empty values by column; blank text counts as empty
+pub(crate) fn row_values(row: &ParsedRow, shared_strings: &[String]) -> HashMap<u32, CellValue> {
+    row.cells
+        .iter()
+        .filter_map(|cell| {
+            let (_, col) = parse_cell_ref(&cell.reference)?;
+            match resolve_cell_value(cell, shared_strings) {
+                CellValue::Text(text) if text.trim().is_empty() => None,
+                CellValue::Empty => None,
+                value => Some((col, value)),
+            }
+        })
+        .collect()
+}
+
+/// The key grouping rows with these values; values compare exactly, as
+/// distinct values do
+pub(crate) fn group_key(values: &[Option<&CellValue>]) -> Vec<Key> {
+    values
+        .iter()
+        .map(|value| match value {
+            Some(value) => key_value((*value).clone(), &DuplicateOptions::default()),
+            None => Key::Empty,
+        })
+        .collect()
+}
+
+/// A key cell's value as returned to JS; errors read as their code
+pub(crate) fn typed_value(value: &CellValue) -> TypedValue {
+    match value {
+        CellValue::Number(n) => TypedValue::Number(*n),
+        CellValue::Bool(b) => TypedValue::Bool(*b),
+        CellValue::Text(text) | CellValue::Error(text) => TypedValue::Text(text.clone()),
+        CellValue::Empty => TypedValue::Text(String::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::sheet_from_rows;
+
+    #[test]
+    fn test_aggregate() {
+        let worksheet = sheet_from_rows(&[
+            &["Region", "Rep", "Sales"],
+            &["East", "Ann", "10"],
+            &["West", "Bob", "4"],
+            &["East", "Cy", "2.5"],
+            &["", "", ""],
+            &["West", "Bob", "n/a"],
+            &["", "Dee", "6"],
+        ]);
+        let aggregates = ["sum", "avg", "min", "max", "count"]
+            .iter()
+            .map(|function| Aggregate {
+                column: "C".to_string(),
+                function: function.to_string(),
+            })
+            .chain([Aggregate {
+                column: "$b".to_string(),
+                function: "Distinct".to_string(),
+            }])
+            .collect();
+        let mut spec = AggregateSpec {
+            aggregates,
+            first_row: Some(2),
+            ..Default::default()
+        };
+
+        let totals = aggregate_impl(&worksheet, &spec).unwrap();
+        assert_eq!(
+            totals,
+            [AggregateGroup {
+                key: Vec::new(),
+                rows: 5,
+                values: vec![
+                    Some(22.5),
+                    Some(5.625),
+                    Some(2.5),
+                    Some(10.0),
+                    Some(5.0),
+                    Some(4.0)
+                ],
+            }]
+        );
+
+        spec.group_by = vec!["A".to_string()];
+        let groups = aggregate_impl(&worksheet, &spec).unwrap();
+        let text = |s: &str| Some(TypedValue::Text(s.to_string()));
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|g| (g.key.clone(), g.rows, g.values[0], g.values[1], g.values[5]))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (vec![text("East")], 2, Some(12.5), Some(6.25), Some(2.0)),
+                (vec![text("West")], 2, Some(4.0), Some(4.0), Some(1.0)),
+                (vec![None], 1, Some(6.0), Some(6.0), Some(1.0)),
+            ]
+        );
+
+        spec.aggregates[0].function = "median".to_string();
+        assert!(aggregate_impl(&worksheet, &spec).is_err());
+    }
+}
@@ -0,0 +1,646 @@
+//! Apache Arrow IPC stream export
+//!
+//! Writes a schema message, a single record batch and the end-of-stream
+//! marker. The flatbuffer metadata is small and fixed-shape, so it is encoded
+//! with a tiny forward-only builder instead of pulling in the `arrow` and
+//! `flatbuffers` crates.
+
+use crate::json::header_names;
+use crate::reference::{parse_cell_ref, used_range};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{
+    date_style_flags, days_from_civil, format_number, resolve_cell_value, serial_to_datetime,
+    CellValue,
+};
+use crate::{ParsedStyles, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Options for [`worksheet_to_arrow`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArrowExportOptions {
+    /// Row holding the column names (1-based); defaults to the first used row
+    pub header_row: Option<u32>,
+    /// Skip rows with no values
+    pub skip_empty_rows: bool,
+    pub shared_strings: Vec<String>,
+    /// Styles used to detect date-formatted numbers
+    pub styles: Option<ParsedStyles>,
+    pub date1904: bool,
+}
+
+impl Default for ArrowExportOptions {
+    fn default() -> Self {
+        ArrowExportOptions {
+            header_row: None,
+            skip_empty_rows: true,
+            shared_strings: Vec::new(),
+            styles: None,
+            date1904: false,
+        }
+    }
+}
+
+/// Export a parsed worksheet as an Arrow IPC stream
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn worksheet_to_arrow(worksheet: JsValue, options: JsValue) -> Result<Vec<u8>, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<ArrowExportOptions> = serde_wasm_bindgen::from_value(options)?;
+        Ok(worksheet_to_arrow_impl(
+            &worksheet,
+            &options.unwrap_or_default(),
+        ))
+    })
+}
+
+// Flatbuffer enum values from the Arrow format (Schema.fbs / Message.fbs)
+const METADATA_VERSION_V5: i16 = 4;
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+const TYPE_FLOATING_POINT: u8 = 3;
+const TYPE_UTF8: u8 = 5;
+const TYPE_BOOL: u8 = 6;
+const TYPE_TIMESTAMP: u8 = 10;
+const PRECISION_DOUBLE: i16 = 2;
+const TIME_UNIT_MILLISECOND: i16 = 1;
+
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+
+/// A flatbuffer object in a message being serialized
+enum Node {
+    /// Fields as (field id, value)
+    Table(Vec<(u16, Slot)>),
+    Str(String),
+    Tables(Vec<Node>),
+    /// Vector of 8-byte-aligned structs, already encoded
+    Structs(usize, Vec<u8>),
+}
+
+/// A table field value
+enum Slot {
+    Byte(u8),
+    Short(i16),
+    Long(i64),
+    Child(Node),
+}
+
+impl Slot {
+    fn size(&self) -> usize {
+        match self {
+            Slot::Byte(_) => 1,
+            Slot::Short(_) => 2,
+            Slot::Long(_) => 8,
+            Slot::Child(_) => 4,
+        }
+    }
+}
+
+fn pad_to(buf: &mut Vec<u8>, align: usize) {
+    while !buf.len().is_multiple_of(align) {
+        buf.push(0);
+    }
+}
+
+fn patch_offset(buf: &mut [u8], at: usize, target: usize) {
+    buf[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+}
+
+impl Node {
+    /// Append the object and everything it references; returns its position
+    ///
+    /// Children are always written after their parent so every `uoffset`
+    /// points forward, as flatbuffers require.
+    fn write(self, buf: &mut Vec<u8>) -> usize {
+        match self {
+            Node::Table(mut slots) => {
+                let field_count = slots
+                    .iter()
+                    .map(|(id, _)| *id as usize + 1)
+                    .max()
+                    .unwrap_or(0);
+
+                pad_to(buf, 4);
+                let vtable_pos = buf.len();
+                let vtable_size = 4 + 2 * field_count;
+                buf.extend_from_slice(&(vtable_size as u16).to_le_bytes());
+                buf.extend_from_slice(&[0; 2]);
+                buf.resize(buf.len() + 2 * field_count, 0);
+                pad_to(buf, 4);
+
+                let table_pos = buf.len();
+                buf.extend_from_slice(&((table_pos - vtable_pos) as i32).to_le_bytes());
+
+                // Largest fields first keeps padding to a minimum
+                slots.sort_by_key(|(_, slot)| std::cmp::Reverse(slot.size()));
+                let mut children = Vec::new();
+                for (id, slot) in slots {
+                    pad_to(buf, slot.size());
+                    let field_pos = buf.len();
+                    let entry = vtable_pos + 4 + 2 * id as usize;
+                    buf[entry..entry + 2]
+                        .copy_from_slice(&((field_pos - table_pos) as u16).to_le_bytes());
+                    match slot {
+                        Slot::Byte(value) => buf.push(value),
+                        Slot::Short(value) => buf.extend_from_slice(&value.to_le_bytes()),
+                        Slot::Long(value) => buf.extend_from_slice(&value.to_le_bytes()),
+                        Slot::Child(node) => {
+                            buf.extend_from_slice(&[0; 4]);
+                            children.push((field_pos, node));
+                        }
+                    }
+                }
+                let table_size = (buf.len() - table_pos) as u16;
+                buf[vtable_pos + 2..vtable_pos + 4].copy_from_slice(&table_size.to_le_bytes());
+
+                for (field_pos, node) in children {
+                    let child_pos = node.write(buf);
+                    patch_offset(buf, field_pos, child_pos);
+                }
+                table_pos
+            }
+            Node::Str(text) => {
+                pad_to(buf, 4);
+                let pos = buf.len();
+                buf.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                buf.extend_from_slice(text.as_bytes());
+                buf.push(0);
+                pos
+            }
+            Node::Tables(nodes) => {
+                pad_to(buf, 4);
+                let pos = buf.len();
+                buf.extend_from_slice(&(nodes.len() as u32).to_le_bytes());
+                let first = buf.len();
+                buf.resize(first + 4 * nodes.len(), 0);
+                for (i, node) in nodes.into_iter().enumerate() {
+                    let child_pos = node.write(buf);
+                    patch_offset(buf, first + 4 * i, child_pos);
+                }
+                pos
+            }
+            Node::Structs(count, bytes) => {
+                // The elements (after the length prefix) must be 8-byte aligned
+                while !(buf.len() + 4).is_multiple_of(8) {
+                    buf.push(0);
+                }
+                let pos = buf.len();
+                buf.extend_from_slice(&(count as u32).to_le_bytes());
+                buf.extend_from_slice(&bytes);
+                pos
+            }
+        }
+    }
+}
+
+/// Serialize a `Message` table and frame it with the IPC continuation and length
+fn write_message(out: &mut Vec<u8>, header_type: u8, header: Node, body: &[u8]) {
+    let message = Node::Table(vec![
+        (0, Slot::Short(METADATA_VERSION_V5)),
+        (1, Slot::Byte(header_type)),
+        (2, Slot::Child(header)),
+        (3, Slot::Long(body.len() as i64)),
+    ]);
+
+    // Root uoffset, then the message table
+    let mut metadata = vec![0u8; 4];
+    let root = message.write(&mut metadata);
+    patch_offset(&mut metadata, 0, root);
+    pad_to(&mut metadata, 8);
+
+    out.extend_from_slice(&CONTINUATION.to_le_bytes());
+    out.extend_from_slice(&(metadata.len() as i32).to_le_bytes());
+    out.extend_from_slice(&metadata);
+    out.extend_from_slice(body);
+}
+
+/// Inferred physical type of a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Float64,
+    Bool,
+    Timestamp,
+    Utf8,
+}
+
+/// A typed cell value collected for a column
+enum Value {
+    Null,
+    Number(f64),
+    Date(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl Value {
+    fn as_text(&self) -> Option<String> {
+        match self {
+            Value::Null => None,
+            Value::Number(number) | Value::Date(number) => Some(format_number(*number)),
+            Value::Bool(b) => Some(if *b { "TRUE" } else { "FALSE" }.to_string()),
+            Value::Text(text) => Some(text.clone()),
+        }
+    }
+}
+
+/// The narrowest type that can hold every non-null value in the column
+fn infer_type(values: &[Value]) -> ColumnType {
+    let mut inferred: Option<ColumnType> = None;
+    for value in values {
+        let ty = match value {
+            Value::Null => continue,
+            Value::Number(_) => ColumnType::Float64,
+            Value::Date(_) => ColumnType::Timestamp,
+            Value::Bool(_) => ColumnType::Bool,
+            Value::Text(_) => return ColumnType::Utf8,
+        };
+        inferred = Some(match inferred {
+            None => ty,
+            Some(current) if current == ty => ty,
+            // Dates mixed with plain numbers stay numeric (serials)
+            Some(ColumnType::Float64) | Some(ColumnType::Timestamp)
+                if matches!(ty, ColumnType::Float64 | ColumnType::Timestamp) =>
+            {
+                ColumnType::Float64
+            }
+            Some(_) => return ColumnType::Utf8,
+        });
+    }
+    inferred.unwrap_or(ColumnType::Utf8)
+}
+
+/// Milliseconds since the Unix epoch for an Excel serial date
+fn serial_to_epoch_ms(serial: f64, date1904: bool) -> Option<i64> {
+    let dt = serial_to_datetime(serial, date1904)?;
+    let days = days_from_civil(dt.year, dt.month, dt.day);
+    let seconds = days * 86400 + (dt.hour * 3600 + dt.minute * 60 + dt.second) as i64;
+    Some(seconds * 1000)
+}
+
+/// Accumulates the record batch body and its buffer/node descriptors
+struct BodyBuilder {
+    body: Vec<u8>,
+    buffers: Vec<u8>,
+    buffer_count: usize,
+    nodes: Vec<u8>,
+    node_count: usize,
+}
+
+impl BodyBuilder {
+    fn push_buffer(&mut self, data: &[u8]) {
+        let offset = self.body.len();
+        self.body.extend_from_slice(data);
+        pad_to(&mut self.body, 8);
+        self.buffers
+            .extend_from_slice(&(offset as i64).to_le_bytes());
+        self.buffers
+            .extend_from_slice(&(data.len() as i64).to_le_bytes());
+        self.buffer_count += 1;
+    }
+
+    fn push_node(&mut self, length: usize, null_count: usize) {
+        self.nodes.extend_from_slice(&(length as i64).to_le_bytes());
+        self.nodes
+            .extend_from_slice(&(null_count as i64).to_le_bytes());
+        self.node_count += 1;
+    }
+}
+
+/// LSB-first bitmap with one bit per value
+fn bitmap(bits: impl Iterator<Item = bool>, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len.div_ceil(8)];
+    for (i, bit) in bits.enumerate() {
+        if bit {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bytes
+}
+
+pub(crate) fn worksheet_to_arrow_impl(
+    worksheet: &ParsedWorksheet,
+    options: &ArrowExportOptions,
+) -> Vec<u8> {
+    let (start_row, start_col, end_row, end_col) = used_range(worksheet).unwrap_or((1, 1, 0, 0));
+    let width = (end_col + 1).saturating_sub(start_col) as usize;
+
+    let header_row = options.header_row.unwrap_or(start_row);
+    let header_cells = worksheet
+        .rows
+        .iter()
+        .find(|row| row.row_num == header_row)
+        .map(|row| row.cells.as_slice());
+    let headers = if width > 0 {
+        header_names(header_cells, start_col, end_col, &options.shared_strings)
+    } else {
+        Vec::new()
+    };
+
+    let date_styles = options
+        .styles
+        .as_ref()
+        .map(date_style_flags)
+        .unwrap_or_default();
+
+    let mut rows: Vec<_> = worksheet
+        .rows
+        .iter()
+        .filter(|row| row.row_num > header_row && row.row_num <= end_row)
+        .collect();
+    rows.sort_by_key(|row| row.row_num);
+
+    // Collect values column-major
+    let mut columns: Vec<Vec<Value>> = (0..width).map(|_| Vec::new()).collect();
+    for row in rows {
+        let mut values: Vec<Value> = (0..width).map(|_| Value::Null).collect();
+        for cell in &row.cells {
+            let col = match parse_cell_ref(&cell.reference) {
+                Some((_, col)) if col >= start_col && col <= end_col => col,
+                _ => continue,
+            };
+            values[(col - start_col) as usize] =
+                match resolve_cell_value(cell, &options.shared_strings) {
+                    CellValue::Empty => Value::Null,
+                    CellValue::Text(text) | CellValue::Error(text) => Value::Text(text),
+                    CellValue::Bool(b) => Value::Bool(b),
+                    CellValue::Number(number) => {
+                        let is_date = cell
+                            .style_index
+                            .and_then(|s| date_styles.get(s as usize).copied())
+                            .unwrap_or(false);
+                        if is_date && number.is_finite() && number >= 0.0 {
+                            Value::Date(number)
+                        } else {
+                            Value::Number(number)
+                        }
+                    }
+                };
+        }
+
+        if options.skip_empty_rows && values.iter().all(|value| matches!(value, Value::Null)) {
+            continue;
+        }
+        for (column, value) in columns.iter_mut().zip(values) {
+            column.push(value);
+        }
+    }
+    let length = columns.first().map_or(0, Vec::len);
+
+    let mut fields = Vec::with_capacity(width);
+    let mut body = BodyBuilder {
+        body: Vec::new(),
+        buffers: Vec::new(),
+        buffer_count: 0,
+        nodes: Vec::new(),
+        node_count: 0,
+    };
+
+    for (name, values) in headers.into_iter().zip(&columns) {
+        let ty = infer_type(values);
+        let (type_id, type_table) = match ty {
+            ColumnType::Float64 => (
+                TYPE_FLOATING_POINT,
+                Node::Table(vec![(0, Slot::Short(PRECISION_DOUBLE))]),
+            ),
+            ColumnType::Bool => (TYPE_BOOL, Node::Table(Vec::new())),
+            ColumnType::Timestamp => (
+                TYPE_TIMESTAMP,
+                Node::Table(vec![(0, Slot::Short(TIME_UNIT_MILLISECOND))]),
+            ),
+            ColumnType::Utf8 => (TYPE_UTF8, Node::Table(Vec::new())),
+        };
+        fields.push(Node::Table(vec![
+            (0, Slot::Child(Node::Str(name))),
+            (1, Slot::Byte(1)),
+            (2, Slot::Byte(type_id)),
+            (3, Slot::Child(type_table)),
+            (5, Slot::Child(Node::Tables(Vec::new()))),
+        ]));
+
+        let null_count = values
+            .iter()
+            .filter(|value| matches!(value, Value::Null))
+            .count();
+        body.push_node(length, null_count);
+        if null_count > 0 {
+            let validity = bitmap(
+                values.iter().map(|value| !matches!(value, Value::Null)),
+                length,
+            );
+            body.push_buffer(&validity);
+        } else {
+            body.push_buffer(&[]);
+        }
+
+        match ty {
+            ColumnType::Float64 => {
+                let mut data = Vec::with_capacity(length * 8);
+                for value in values {
+                    let number = match value {
+                        Value::Number(number) | Value::Date(number) => *number,
+                        _ => 0.0,
+                    };
+                    data.extend_from_slice(&number.to_le_bytes());
+                }
+                body.push_buffer(&data);
+            }
+            ColumnType::Timestamp => {
+                let mut data = Vec::with_capacity(length * 8);
+                for value in values {
+                    let ms = match value {
+                        Value::Date(serial) => {
+                            serial_to_epoch_ms(*serial, options.date1904).unwrap_or(0)
+                        }
+                        _ => 0,
+                    };
+                    data.extend_from_slice(&ms.to_le_bytes());
+                }
+                body.push_buffer(&data);
+            }
+            ColumnType::Bool => {
+                let data = bitmap(
+                    values
+                        .iter()
+                        .map(|value| matches!(value, Value::Bool(true))),
+                    length,
+                );
+                body.push_buffer(&data);
+            }
+            ColumnType::Utf8 => {
+                let mut offsets = Vec::with_capacity((length + 1) * 4);
+                let mut data = Vec::new();
+                offsets.extend_from_slice(&0i32.to_le_bytes());
+                for value in values {
+                    if let Some(text) = value.as_text() {
+                        data.extend_from_slice(text.as_bytes());
+                    }
+                    offsets.extend_from_slice(&(data.len() as i32).to_le_bytes());
+                }
+                body.push_buffer(&offsets);
+                body.push_buffer(&data);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let schema = Node::Table(vec![
+        (0, Slot::Short(0)), // little endian
+        (1, Slot::Child(Node::Tables(fields))),
+    ]);
+    write_message(&mut out, HEADER_SCHEMA, schema, &[]);
+
+    let record_batch = Node::Table(vec![
+        (0, Slot::Long(length as i64)),
+        (1, Slot::Child(Node::Structs(body.node_count, body.nodes))),
+        (
+            2,
+            Slot::Child(Node::Structs(body.buffer_count, body.buffers)),
+        ),
+    ]);
+    write_message(&mut out, HEADER_RECORD_BATCH, record_batch, &body.body);
+
+    // End-of-stream marker
+    out.extend_from_slice(&CONTINUATION.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+    use crate::ParsedStyle;
+
+    fn u32_at(buf: &[u8], pos: usize) -> usize {
+        u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap()) as usize
+    }
+
+    /// Position of a table field, following the vtable
+    fn field_pos(buf: &[u8], table: usize, id: usize) -> Option<usize> {
+        let soffset = i32::from_le_bytes(buf[table..table + 4].try_into().unwrap());
+        let vtable = (table as i64 - soffset as i64) as usize;
+        let vtable_size = u16::from_le_bytes([buf[vtable], buf[vtable + 1]]) as usize;
+        if 4 + 2 * id >= vtable_size {
+            return None;
+        }
+        let entry = vtable + 4 + 2 * id;
+        match u16::from_le_bytes([buf[entry], buf[entry + 1]]) {
+            0 => None,
+            offset => Some(table + offset as usize),
+        }
+    }
+
+    fn deref(buf: &[u8], pos: usize) -> usize {
+        pos + u32_at(buf, pos)
+    }
+
+    #[test]
+    fn test_worksheet_to_arrow() {
+        let mut date = cell("C2", None, "45292");
+        date.style_index = Some(1);
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                row(
+                    1,
+                    vec![
+                        cell("A1", Some("inlineStr"), "name"),
+                        cell("B1", Some("inlineStr"), "amount"),
+                        cell("C1", Some("inlineStr"), "when"),
+                        cell("D1", Some("inlineStr"), "ok"),
+                    ],
+                ),
+                row(
+                    2,
+                    vec![
+                        cell("A2", Some("inlineStr"), "x"),
+                        cell("B2", None, "1.5"),
+                        date,
+                        cell("D2", Some("b"), "1"),
+                    ],
+                ),
+                row(3, vec![cell("B3", None, "-2")]),
+            ],
+            ..Default::default()
+        };
+        let options = ArrowExportOptions {
+            styles: Some(ParsedStyles {
+                cell_xfs: vec![
+                    ParsedStyle::default(),
+                    ParsedStyle {
+                        num_fmt_id: Some(14),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let bytes = worksheet_to_arrow_impl(&worksheet, &options);
+
+        // Schema message
+        assert_eq!(u32_at(&bytes, 0), CONTINUATION as usize);
+        let schema_len = u32_at(&bytes, 4);
+        assert_eq!(schema_len % 8, 0);
+        let meta = &bytes[8..8 + schema_len];
+        let message = deref(meta, 0);
+        assert_eq!(meta[field_pos(meta, message, 1).unwrap()], HEADER_SCHEMA);
+        let schema = deref(meta, field_pos(meta, message, 2).unwrap());
+        let fields = deref(meta, field_pos(meta, schema, 1).unwrap());
+        assert_eq!(u32_at(meta, fields), 4);
+
+        let type_ids: Vec<u8> = (0..4)
+            .map(|i| {
+                let field = deref(meta, fields + 4 + 4 * i);
+                meta[field_pos(meta, field, 2).unwrap()]
+            })
+            .collect();
+        assert_eq!(
+            type_ids,
+            vec![TYPE_UTF8, TYPE_FLOATING_POINT, TYPE_TIMESTAMP, TYPE_BOOL]
+        );
+        let second = deref(meta, fields + 8);
+        let name = deref(meta, field_pos(meta, second, 0).unwrap());
+        assert_eq!(&meta[name + 4..name + 4 + u32_at(meta, name)], b"amount");
+
+        // Record batch message
+        let batch_start = 8 + schema_len;
+        assert_eq!(u32_at(&bytes, batch_start), CONTINUATION as usize);
+        let batch_len = u32_at(&bytes, batch_start + 4);
+        let meta = &bytes[batch_start + 8..batch_start + 8 + batch_len];
+        let message = deref(meta, 0);
+        assert_eq!(
+            meta[field_pos(meta, message, 1).unwrap()],
+            HEADER_RECORD_BATCH
+        );
+        let body_len = field_pos(meta, message, 3).unwrap();
+        let body_len = i64::from_le_bytes(meta[body_len..body_len + 8].try_into().unwrap());
+        let batch = deref(meta, field_pos(meta, message, 2).unwrap());
+        let length = field_pos(meta, batch, 0).unwrap();
+        assert_eq!(
+            i64::from_le_bytes(meta[length..length + 8].try_into().unwrap()),
+            2
+        );
+
+        // name uses buffers 0-2 (validity, offsets, data); amount's values follow its validity
+        let buffers = deref(meta, field_pos(meta, batch, 2).unwrap());
+        assert_eq!(u32_at(meta, buffers), 9);
+        let amount_values = buffers + 4 + 16 * 4;
+        let offset = i64::from_le_bytes(meta[amount_values..amount_values + 8].try_into().unwrap());
+        let body = &bytes[batch_start + 8 + batch_len..];
+        let offset = offset as usize;
+        let first = f64::from_le_bytes(body[offset..offset + 8].try_into().unwrap());
+        let second = f64::from_le_bytes(body[offset + 8..offset + 16].try_into().unwrap());
+        assert_eq!((first, second), (1.5, -2.0));
+
+        // Body plus end-of-stream marker
+        assert_eq!(body.len(), body_len as usize + 8);
+        assert_eq!(
+            &body[body.len() - 8..],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Row heights for wrapped text
+//!
+//! Excel only grows a row to fit wrapped text when it recalculates the row
+//! itself, so a written file shows wrapped cells clipped to one line. The
+//! heights here come from estimated glyph widths (Calibri-like proportions
+//! scaled by each cell's font size) word-wrapped into the column's width.
+
+use crate::reference::{parse_cell_ref, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{ParsedCell, ParsedStyles, ParsedWorksheet};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Font size of cells without a font
+const DEFAULT_FONT_SIZE: f64 = 11.0;
+/// Width of columns without a `<col>` entry, in characters
+const DEFAULT_COL_WIDTH: f64 = 8.43;
+/// Pixels per character of column width (the default font's digit width)
+const PIXELS_PER_CHAR: f64 = 7.0;
+/// Line height per point of font size; 11pt text gets Excel's 15pt rows
+const LINE_SPACING: f64 = 15.0 / 11.0;
+/// Heights are whole pixels, at 0.75pt each
+const POINTS_PER_PIXEL: f64 = 0.75;
+
+/// Row heights in points that show every wrap-text cell in full, keyed by
+/// row number; only rows with wrapped text are listed
+///
+/// `col_widths` maps column numbers to widths in characters, as in
+/// `ParsedWorksheet.col_widths`, which is used when it is left out.
+/// `shared_strings` supplies the text of shared-string cells.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn autofit_rows(
+    worksheet: JsValue,
+    styles: JsValue,
+    col_widths: JsValue,
+    shared_strings: JsValue,
+) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let styles: Option<ParsedStyles> = serde_wasm_bindgen::from_value(styles)?;
+        let col_widths: Option<HashMap<u32, f64>> = serde_wasm_bindgen::from_value(col_widths)?;
+        let shared_strings: Option<Vec<String>> = serde_wasm_bindgen::from_value(shared_strings)?;
+        let heights = autofit_rows_impl(
+            &worksheet,
+            &styles.unwrap_or_default(),
+            col_widths.as_ref().unwrap_or(&worksheet.col_widths),
+            &shared_strings.unwrap_or_default(),
+        );
+        Ok(serde_wasm_bindgen::to_value(&heights)?)
+    })
+}
+
+pub(crate) fn autofit_rows_impl(
+    worksheet: &ParsedWorksheet,
+    styles: &ParsedStyles,
+    col_widths: &HashMap<u32, f64>,
+    shared_strings: &[String],
+) -> BTreeMap<u32, f64> {
+    // Excel doesn't fit rows to merged cells, so neither does this
+    let merges: Vec<_> = worksheet
+        .merge_cells
+        .iter()
+        .filter_map(|range| range_bounds(range))
+        .collect();
+    let merged = |row: u32, col: u32| {
+        let inside = |&(r1, c1, r2, c2): &_| (r1..=r2).contains(&row) && (c1..=c2).contains(&col);
+        merges.iter().any(inside)
+    };
+
+    let mut heights = BTreeMap::new();
+    for row in &worksheet.rows {
+        let mut height: f64 = 0.0;
+        let mut wraps = false;
+        for cell in &row.cells {
+            let Some(text) = cell_text(cell, shared_strings) else {
+                continue;
+            };
+            let Some((_, col)) = parse_cell_ref(&cell.reference) else {
+                continue;
+            };
+            let style = cell
+                .style_index
+                .and_then(|i| styles.cell_xfs.get(i as usize));
+            let font = style
+                .and_then(|style| style.font_id)
+                .or(Some(0))
+                .and_then(|id| styles.fonts.get(id as usize));
+            let size = font.and_then(|font| font.size).unwrap_or(DEFAULT_FONT_SIZE);
+            let bold = font.is_some_and(|font| font.bold);
+
+            let wrap = style.is_some_and(|style| style.wrap_text) && !merged(row.row_num, col);
+            let lines = if wrap {
+                wraps = true;
+                let width = col_widths.get(&col).copied().unwrap_or(DEFAULT_COL_WIDTH);
+                let available = width * PIXELS_PER_CHAR * POINTS_PER_PIXEL;
+                line_count(text, available, size, bold)
+            } else {
+                1
+            };
+            height = height.max(lines as f64 * size * LINE_SPACING);
+        }
+        if wraps {
+            let pixels = (height / POINTS_PER_PIXEL).ceil();
+            heights.insert(row.row_num, pixels * POINTS_PER_PIXEL);
+        }
+    }
+    heights
+}
+
+/// Text a cell shows, near enough to measure
+pub(crate) fn cell_text<'a>(cell: &'a ParsedCell, shared_strings: &'a [String]) -> Option<&'a str> {
+    let value = cell.value.as_deref().filter(|value| !value.is_empty())?;
+    match cell.cell_type.as_deref() {
+        Some("s") => value
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| shared_strings.get(index))
+            .map(String::as_str),
+        Some("b") if value == "1" => Some("TRUE"),
+        Some("b") => Some("FALSE"),
+        _ => Some(value),
+    }
+}
+
+/// Estimated width of `ch` in points at the given font size
+fn char_width(ch: char, size: f64, bold: bool) -> f64 {
+    let em = match ch {
+        ' ' => 0.23,
+        'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' => 0.23,
+        'f' | 'r' | 't' | 'I' | '(' | ')' | '[' | ']' | '-' => 0.33,
+        'm' | 'w' | 'M' | 'W' | '@' | '%' => 0.8,
+        '0'..='9' => 0.51,
+        'A'..='Z' => 0.58,
+        _ if ch.is_ascii() => 0.47,
+        // East Asian scripts and other wide glyphs take a full em
+        '\u{1100}'..='\u{115F}' | '\u{2E80}'..='\u{A4CF}' | '\u{AC00}'..='\u{D7A3}' => 1.0,
+        '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FF60}' | '\u{FFE0}'..='\u{FFE6}' => 1.0,
+        _ => 0.55,
+    };
+    em * size * if bold { 1.05 } else { 1.0 }
+}
+
+/// Lines `text` takes when word-wrapped to `available` points
+fn line_count(text: &str, available: f64, size: f64, bold: bool) -> usize {
+    let width = |s: &str| s.chars().map(|ch| char_width(ch, size, bold)).sum::<f64>();
+    let space = char_width(' ', size, bold);
+    let mut lines = 0;
+    for paragraph in text.split('\n') {
+        lines += 1;
+        let mut used = 0.0;
+        for word in paragraph.split(' ') {
+            let mut word_width = width(word);
+            let gap = if used > 0.0 { space } else { 0.0 };
+            if used > 0.0 && used + gap + word_width > available {
+                lines += 1;
+                used = 0.0;
+            } else {
+                used += gap;
+            }
+            // A word wider than the column breaks between characters
+            while word_width > available && used == 0.0 {
+                lines += 1;
+                word_width -= available;
+            }
+            used += word_width;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParsedFont, ParsedRow, ParsedStyle};
+
+    fn cell(reference: &str, text: &str, style: u32) -> ParsedCell {
+        ParsedCell {
+            reference: reference.to_string(),
+            cell_type: Some("str".to_string()),
+            style_index: Some(style),
+            value: Some(text.to_string()),
+            formula: None,
+        }
+    }
+
+    #[test]
+    fn test_autofit_rows() {
+        let styles = ParsedStyles {
+            cell_xfs: vec![
+                ParsedStyle::default(),
+                ParsedStyle {
+                    wrap_text: true,
+                    ..Default::default()
+                },
+                ParsedStyle {
+                    wrap_text: true,
+                    font_id: Some(1),
+                    ..Default::default()
+                },
+            ],
+            fonts: vec![
+                ParsedFont {
+                    size: Some(11.0),
+                    ..Default::default()
+                },
+                ParsedFont {
+                    size: Some(22.0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let long = "the quick brown fox jumps over the lazy dog";
+        let row = |row_num, cells| ParsedRow {
+            row_num,
+            cells,
+            height: None,
+            hidden: false,
+        };
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                row(1, vec![cell("A1", long, 0)]),
+                row(2, vec![cell("A2", "short", 1)]),
+                row(3, vec![cell("A3", long, 1)]),
+                row(4, vec![cell("B4", long, 1)]),
+                row(5, vec![cell("A5", "one\ntwo\nsix", 2)]),
+                row(6, vec![cell("A6", long, 1)]),
+            ],
+            merge_cells: vec!["A6:B6".to_string()],
+            col_widths: HashMap::from([(2, 60.0)]),
+            ..Default::default()
+        };
+
+        let heights = autofit_rows_impl(&worksheet, &styles, &worksheet.col_widths, &[]);
+        // Unwrapped rows are left alone; short text and wide columns need one line
+        assert_eq!(heights.get(&1), None);
+        assert_eq!(heights[&2], 15.0);
+        assert_eq!(heights[&4], 15.0);
+        assert!(heights[&3] >= 45.0, "{}", heights[&3]);
+        assert_eq!(heights[&5], 90.0);
+        assert_eq!(heights.get(&6), None);
+        assert_eq!(line_count("aaaaaaaaaa", 10.0, 11.0, false), 6);
+    }
+}
@@ -0,0 +1,89 @@
+//! Parsing many worksheets in one call
+//!
+//! Large workbooks have dozens of sheets. Passing them all at once pays the
+//! JS/wasm call and option decoding cost once, and every sheet after the
+//! first reuses the working memory grown by the ones before it.
+
+use crate::context::ParserScratch;
+use crate::memory;
+use crate::profile::phase;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{parse_worksheet_with, ParsedWorksheet, WorksheetParseOptions};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// One worksheet of a batch, in input order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSheet {
+    pub name: String,
+    pub worksheet: ParsedWorksheet,
+}
+
+/// Parse `[name, xml]` pairs, applying the same options to every sheet
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheets_batch(sheets: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let sheets: Vec<(String, String)> = serde_wasm_bindgen::from_value(sheets)?;
+        let options: Option<WorksheetParseOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+        let guard = memory::guard();
+        let result = parse_worksheets_batch_impl(&sheets, &options);
+        guard.check().map_err(|e| JsValue::from_str(&e))?;
+        for sheet in &result {
+            options.check(&sheet.worksheet)?;
+        }
+        Ok(phase("serialization", || {
+            serde_wasm_bindgen::to_value(&result)
+        })?)
+    })
+}
+
+pub(crate) fn parse_worksheets_batch_impl(
+    sheets: &[(String, String)],
+    options: &WorksheetParseOptions,
+) -> Vec<BatchSheet> {
+    let mut scratch = ParserScratch::default();
+    let mut parsed = Vec::with_capacity(sheets.len());
+    for (name, xml) in sheets {
+        if memory::over_limit() {
+            break;
+        }
+        let worksheet = phase(&format!("sheet:{}", name), || {
+            parse_worksheet_with(xml, &mut scratch, options)
+        });
+        parsed.push(BatchSheet {
+            name: name.clone(),
+            worksheet,
+        });
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch() {
+        let sheet = |value: &str| {
+            format!(
+                r#"<worksheet><sheetData><row r="1"><c r="A1"><v>{}</v></c></row></sheetData></worksheet>"#,
+                value
+            )
+        };
+        let sheets = vec![
+            ("One".to_string(), sheet("1")),
+            ("Two".to_string(), sheet("2")),
+            ("Empty".to_string(), "<worksheet/>".to_string()),
+        ];
+        let parsed = parse_worksheets_batch_impl(&sheets, &WorksheetParseOptions::default());
+        let names: Vec<_> = parsed.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["One", "Two", "Empty"]);
+        let value = parsed[1].worksheet.rows[0].cells[0].value.as_deref();
+        assert_eq!(value, Some("2"));
+        assert!(parsed[2].worksheet.rows.is_empty());
+    }
+}
@@ -0,0 +1,2505 @@
+//! Editable workbook model
+//!
+//! A [`WorkbookBuilder`] holds sheets as parsed rows and cells that can be
+//! changed in place, starting empty or from an opened file, and writes the
+//! result back out as .xlsx, so edits round-trip instead of being read-only.
+//! Inserting or deleting rows and columns moves the cells along with the
+//! merges, links, tables, pictures, charts, validations, conditional
+//! formats, filters, defined names and formula references that point at
+//! them.
+
+use crate::adjust::{adjust_formulas_impl, adjust_range, Edit, StructureChange};
+use crate::autofit::{autofit_rows_impl, cell_text};
+#[cfg(feature = "charts")]
+use crate::chart::{check_chart, series_ref};
+#[cfg(feature = "wasm")]
+use crate::crypto::random_bytes;
+#[cfg(feature = "drawings")]
+use crate::drawing::image_info;
+use crate::export::{
+    print_area, print_titles, set_local_name, workbook_to_xlsx_impl, ExportSheet, ExportWorkbook,
+    PrintSettings, PRINT_AREA, PRINT_TITLES,
+};
+use crate::formula::{
+    needs_quotes, rewrite_references, translate_formula, Notation, MAX_COLS, MAX_ROWS,
+};
+use crate::limits::ParseLimits;
+#[cfg(feature = "wasm")]
+use crate::memory;
+use crate::properties::{write_core_properties_impl, write_custom_properties_impl};
+#[cfg(feature = "wasm")]
+use crate::protection::SALT_LEN;
+use crate::protection::{set_password, SheetProtection};
+use crate::reference::{bounds_ref, cell_ref, parse_cell_ref, range_bounds, used_range, Bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::workbook::{read_workbook, split_sheet, TypedValue, WorkbookData};
+use crate::writer::SharedStringEntry;
+#[cfg(feature = "charts")]
+use crate::SheetChart;
+#[cfg(feature = "drawings")]
+use crate::SheetImage;
+use crate::{
+    normalize_rows, ColumnTotal, DocumentProperties, ParsedBorder, ParsedCell, ParsedDefinedName,
+    ParsedFill, ParsedFont, ParsedHyperlink, ParsedRow, ParsedSheet, ParsedStyle, ParsedStyles,
+    ParsedTable, ParsedWorkbookPr, ParsedWorksheet, TableStyleInfo,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Characters Excel doesn't allow in a sheet name
+const INVALID_SHEET_CHARS: &[char] = &['[', ']', ':', '*', '?', '/', '\\'];
+/// First id free for custom number formats
+const FIRST_CUSTOM_NUM_FMT: u32 = 164;
+/// Most cells one range edit (`apply_style`, `copy_range`) may cover
+const MAX_RANGE_CELLS: u64 = 1_000_000;
+
+/// A cell style for [`WorkbookBuilder::add_style`] and
+/// [`WorkbookBuilder::apply_style`]; parts left out keep the workbook
+/// defaults or, for `apply_style`, what each cell already has
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StyleSpec {
+    pub font: Option<ParsedFont>,
+    pub fill: Option<ParsedFill>,
+    pub border: Option<ParsedBorder>,
+    /// Number format code, e.g. "0.00%" or "yyyy-mm-dd"
+    pub num_fmt: Option<String>,
+    pub horizontal: Option<String>,
+    pub vertical: Option<String>,
+    pub wrap_text: Option<bool>,
+}
+
+/// Options for [`WorkbookBuilder::add_table`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableSpec {
+    /// Name used in formulas; "Table1", "Table2", ... when left out
+    pub name: Option<String>,
+    /// "TableStyleMedium2" with banded rows when left out
+    pub style: Option<TableStyleInfo>,
+    /// Make the range's last row a totals row showing these, one per column
+    /// from the first
+    pub totals: Vec<ColumnTotal>,
+}
+
+/// A workbook that can be edited and saved
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Default)]
+pub struct WorkbookBuilder {
+    sheets: Vec<ParsedSheet>,
+    shared_strings: Vec<String>,
+    /// Position of each string in `shared_strings`
+    string_indices: HashMap<String, usize>,
+    styles: ParsedStyles,
+    date1904: bool,
+    defined_names: Vec<ParsedDefinedName>,
+    /// Tables on every sheet, each naming its sheet in `sheet`
+    tables: Vec<ParsedTable>,
+    properties: Option<DocumentProperties>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl WorkbookBuilder {
+    /// Start an empty workbook; add a sheet before setting cells
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WorkbookBuilder {
+        WorkbookBuilder::default()
+    }
+
+    /// Open an .xlsx, .ods or .xls file for editing
+    pub fn open(data: &[u8]) -> Result<WorkbookBuilder, JsValue> {
+        trap(|| {
+            let guard = memory::guard();
+            let workbook = read_workbook(data, ParseLimits::default(), None)?;
+            guard.check().map_err(|e| JsValue::from_str(&e))?;
+            Ok(WorkbookBuilder::from_data(workbook))
+        })
+    }
+
+    /// Sheet names in workbook order
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.sheets.iter().map(|sheet| sheet.name.clone()).collect()
+    }
+
+    /// Append an empty sheet, returning its index
+    pub fn add_sheet(&mut self, name: &str) -> Result<usize, JsValue> {
+        trap(|| self.insert_sheet(name).map_err(|e| JsValue::from_str(&e)))
+    }
+
+    /// Remove a sheet; formulas, defined names, links and chart series
+    /// that refer to it get #REF! in its place
+    pub fn delete_sheet(&mut self, name: &str) -> Result<(), JsValue> {
+        trap(|| self.remove_sheet(name).map_err(|e| JsValue::from_str(&e)))
+    }
+
+    /// Rename a sheet, updating the formulas, defined names and links that
+    /// mention it
+    pub fn rename_sheet(&mut self, old: &str, new: &str) -> Result<(), JsValue> {
+        trap(|| self.rename(old, new).map_err(|e| JsValue::from_str(&e)))
+    }
+
+    /// Set a cell to a number, string or boolean, replacing any formula;
+    /// null or undefined clears it
+    pub fn set_cell_value(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        value: JsValue,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            let value: Option<TypedValue> = serde_wasm_bindgen::from_value(value)?;
+            self.set_value(sheet, reference, value)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Set a cell's formula, e.g. "SUM(A1:A9)"; it is saved without a cached value
+    pub fn set_formula(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        formula: Option<String>,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            self.set_cell_formula(sheet, reference, formula)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Register a `StyleSpec`, returning the index to pass to `set_cell_style`
+    pub fn add_style(&mut self, style: JsValue) -> Result<u32, JsValue> {
+        trap(|| {
+            let style: StyleSpec = serde_wasm_bindgen::from_value(style)?;
+            Ok(self.register_style(style))
+        })
+    }
+
+    /// Apply a `StyleSpec` to every cell of a range such as "A1:D20", on top of
+    /// each cell's own style; empty cells in the range are styled too
+    pub fn apply_style(&mut self, sheet: &str, range: &str, style: JsValue) -> Result<(), JsValue> {
+        trap(|| {
+            let style: StyleSpec = serde_wasm_bindgen::from_value(style)?;
+            self.style_range(sheet, range, &style)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Give a cell a style from `add_style` (or the opened file); undefined resets it
+    pub fn set_cell_style(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        style: Option<u32>,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            self.set_style(sheet, reference, style)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Merge a range such as "A1:C3"; cells other than the top-left one
+    /// lose their values and formulas, as in Excel
+    pub fn merge_cells(&mut self, sheet: &str, range: &str) -> Result<(), JsValue> {
+        trap(|| {
+            self.add_merge(sheet, range)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Split a merged range again; its cells stay empty
+    pub fn unmerge(&mut self, sheet: &str, range: &str) -> Result<(), JsValue> {
+        trap(|| {
+            self.remove_merge(sheet, range)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Link a cell or range to a URL, or to a place in the workbook when
+    /// `target` starts with "#" ("#Summary!A1"), replacing any link it has
+    pub fn add_hyperlink(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        target: &str,
+        tooltip: Option<String>,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            self.link(sheet, reference, target, tooltip)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    pub fn remove_hyperlink(&mut self, sheet: &str, reference: &str) -> Result<(), JsValue> {
+        trap(|| {
+            self.unlink(sheet, reference)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Copy a range's values, formulas, styles and merges to `dest`, a cell
+    /// or range that may name another sheet ("Summary!B2"), adjusting
+    /// relative references as Excel's copy and paste does. A destination
+    /// range that is a multiple of the source's size is filled with copies.
+    pub fn copy_range(&mut self, sheet: &str, source: &str, dest: &str) -> Result<(), JsValue> {
+        trap(|| {
+            self.paste_range(sheet, source, dest)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Make a range into an Excel table and return its name. The first row
+    /// is the header: its cells name the columns, and blank or repeated
+    /// names are made unique as Excel does. Formulas can then use
+    /// structured references such as `Table1[Amount]`.
+    pub fn add_table(
+        &mut self,
+        sheet: &str,
+        range: &str,
+        options: JsValue,
+    ) -> Result<String, JsValue> {
+        trap(|| {
+            let spec: Option<TableSpec> = serde_wasm_bindgen::from_value(options)?;
+            self.insert_table(sheet, range, spec.unwrap_or_default())
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Name a range or formula, e.g. `define_name("Rates", "Data!$B$2:$B$9")`,
+    /// for the whole workbook or, given `sheet`, for formulas on that sheet
+    pub fn define_name(
+        &mut self,
+        name: &str,
+        value: &str,
+        sheet: Option<String>,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            self.insert_name(name, value, sheet.as_deref())
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Remove a name given by `define_name`
+    pub fn remove_name(&mut self, name: &str, sheet: Option<String>) -> Result<(), JsValue> {
+        trap(|| {
+            self.delete_name(name, sheet.as_deref())
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Print only `range` of a sheet ("A1:F40", or several joined by
+    /// commas); without one the whole sheet prints
+    pub fn set_print_area(&mut self, sheet: &str, range: Option<String>) -> Result<(), JsValue> {
+        trap(|| {
+            self.print_range(sheet, range.as_deref())
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Repeat `rows` ("1:2") at the top and `cols` ("A:B") at the left of
+    /// every printed page; leaving both out stops repeating them
+    pub fn set_print_titles(
+        &mut self,
+        sheet: &str,
+        rows: Option<String>,
+        cols: Option<String>,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            self.repeat_titles(sheet, rows.as_deref(), cols.as_deref())
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Lock a sheet against edits, with a password if given; `options` says
+    /// what users may still do (`format_cells`, `insert_rows`, `sort`, ...)
+    /// and may carry a hash from `sheet_protection` instead of a password
+    pub fn protect_sheet(
+        &mut self,
+        sheet: &str,
+        password: Option<String>,
+        options: JsValue,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            let options: Option<SheetProtection> = serde_wasm_bindgen::from_value(options)?;
+            let salt = match password {
+                Some(_) => random_bytes(SALT_LEN)?,
+                None => Vec::new(),
+            };
+            self.protect(
+                sheet,
+                options.unwrap_or_default(),
+                password.as_deref(),
+                &salt,
+            )
+            .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Remove a sheet's protection
+    pub fn unprotect_sheet(&mut self, sheet: &str) -> Result<(), JsValue> {
+        trap(|| {
+            self.sheet_mut(sheet)
+                .map(|worksheet| worksheet.protection = None)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Set the title, author, company and custom properties saved with the
+    /// workbook, replacing any set before
+    pub fn set_properties(&mut self, properties: JsValue) -> Result<(), JsValue> {
+        trap(|| {
+            let properties: DocumentProperties = serde_wasm_bindgen::from_value(properties)?;
+            self.set_document_properties(properties)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Give rows with wrap-text cells the height their text needs, so the
+    /// saved file doesn't show it clipped
+    pub fn autofit_rows(&mut self, sheet: &str) -> Result<(), JsValue> {
+        trap(|| self.fit_rows(sheet).map_err(|e| JsValue::from_str(&e)))
+    }
+
+    /// Insert `count` empty rows before row `index` (1-based)
+    pub fn insert_rows(&mut self, sheet: &str, index: u32, count: u32) -> Result<(), JsValue> {
+        self.structure(sheet, "insert_rows", index, count)
+    }
+
+    /// Delete `count` rows starting at row `index` (1-based); references to
+    /// them become #REF!
+    pub fn delete_rows(&mut self, sheet: &str, index: u32, count: u32) -> Result<(), JsValue> {
+        self.structure(sheet, "delete_rows", index, count)
+    }
+
+    /// Insert `count` empty columns before column `index` (1-based)
+    pub fn insert_cols(&mut self, sheet: &str, index: u32, count: u32) -> Result<(), JsValue> {
+        self.structure(sheet, "insert_cols", index, count)
+    }
+
+    /// Delete `count` columns starting at column `index` (1-based); references
+    /// to them become #REF!
+    pub fn delete_cols(&mut self, sheet: &str, index: u32, count: u32) -> Result<(), JsValue> {
+        self.structure(sheet, "delete_cols", index, count)
+    }
+
+    /// Write the workbook as an .xlsx file
+    pub fn to_xlsx(&self) -> Result<Vec<u8>, JsValue> {
+        trap(|| self.write().map_err(|e| JsValue::from_str(&e)))
+    }
+}
+
+#[cfg(all(feature = "wasm", feature = "drawings"))]
+#[wasm_bindgen]
+impl WorkbookBuilder {
+    /// Place a PNG, JPEG, GIF or BMP image on a sheet. `anchor` is a cell for
+    /// its top-left corner, where it keeps its own size, or a range to
+    /// stretch it over; either way it moves with the cells.
+    pub fn add_image(&mut self, sheet: &str, data: &[u8], anchor: &str) -> Result<(), JsValue> {
+        trap(|| {
+            self.insert_image(sheet, data.to_vec(), anchor)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+}
+
+#[cfg(all(feature = "wasm", feature = "charts"))]
+#[wasm_bindgen]
+impl WorkbookBuilder {
+    /// Place a chart on a sheet: "column", "bar", "line" or "pie", with its
+    /// series' ranges, title and legend. Ranges without a sheet name are
+    /// read from `sheet`.
+    pub fn add_chart(&mut self, sheet: &str, chart: JsValue) -> Result<(), JsValue> {
+        trap(|| {
+            let chart: SheetChart = serde_wasm_bindgen::from_value(chart)?;
+            self.insert_chart(sheet, chart)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl WorkbookBuilder {
+    fn structure(
+        &mut self,
+        sheet: &str,
+        action: &str,
+        index: u32,
+        count: u32,
+    ) -> Result<(), JsValue> {
+        trap(|| {
+            self.change_structure(sheet, action, index, count)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+}
+
+impl WorkbookBuilder {
+    pub(crate) fn from_data(workbook: WorkbookData) -> Self {
+        let mut sheets = workbook.sheets;
+        for sheet in &mut sheets {
+            normalize_rows(&mut sheet.worksheet.rows);
+            // Relationships are written afresh on save, so links keep only their targets
+            let links = &mut sheet.worksheet.hyperlinks;
+            links.retain(|link| link.rid.is_none() || link.target.is_some());
+            links.iter_mut().for_each(|link| link.rid = None);
+            sheet.worksheet.warnings.clear();
+        }
+        let mut string_indices = HashMap::new();
+        for (index, text) in workbook.shared_strings.iter().enumerate() {
+            string_indices.entry(text.clone()).or_insert(index);
+        }
+        WorkbookBuilder {
+            sheets,
+            shared_strings: workbook.shared_strings,
+            string_indices,
+            styles: workbook.styles.unwrap_or_default(),
+            date1904: workbook.date1904,
+            defined_names: workbook.defined_names,
+            tables: Vec::new(),
+            properties: None,
+        }
+    }
+
+    /// Position of the sheet named `name`, matched without regard to case
+    fn sheet_index(&self, name: &str) -> Result<usize, String> {
+        let lower = name.to_lowercase();
+        self.sheets
+            .iter()
+            .position(|sheet| sheet.name.to_lowercase() == lower)
+            .ok_or(format!("Unknown sheet: {}", name))
+    }
+
+    fn sheet_mut(&mut self, name: &str) -> Result<&mut ParsedWorksheet, String> {
+        let index = self.sheet_index(name)?;
+        Ok(&mut self.sheets[index].worksheet)
+    }
+
+    /// Check that `name` is a valid sheet name no sheet but `own` already has
+    fn check_sheet_name(&self, name: &str, own: Option<usize>) -> Result<(), String> {
+        if name.trim().is_empty() || name.chars().count() > 31 {
+            return Err(format!("Sheet names must be 1 to 31 characters: {}", name));
+        }
+        if name.contains(INVALID_SHEET_CHARS) || name.starts_with('\'') || name.ends_with('\'') {
+            return Err(format!("Invalid sheet name: {}", name));
+        }
+        match self.sheet_index(name) {
+            Ok(index) if Some(index) != own => {
+                Err(format!("A sheet named {} already exists", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn insert_sheet(&mut self, name: &str) -> Result<usize, String> {
+        self.check_sheet_name(name, None)?;
+        self.sheets.push(ParsedSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet::default(),
+        });
+        Ok(self.sheets.len() - 1)
+    }
+
+    pub(crate) fn remove_sheet(&mut self, name: &str) -> Result<(), String> {
+        let index = self.sheet_index(name)?;
+        // References to the sheet become #REF!, and a 3D range starting or
+        // ending on it loses it from that end
+        let names: Vec<String> = self.sheets.iter().map(|sheet| sheet.name.clone()).collect();
+        let position = |sheet: &str| {
+            let sheet = sheet.to_lowercase();
+            names.iter().position(|name| name.to_lowercase() == sheet)
+        };
+        self.rewrite_formulas(Some(index), |formula| {
+            rewrite_references(formula, Notation::A1, |reference| {
+                let first = reference.sheet.as_deref().and_then(position);
+                let last = reference.last_sheet.as_deref().and_then(position).or(first);
+                let (Some(first), Some(last)) = (first, last) else {
+                    return reference.to_a1();
+                };
+                if first != index && last != index {
+                    return reference.to_a1();
+                }
+                if first == last {
+                    return "#REF!".to_string();
+                }
+                let toward = |from: usize, to: usize| if from < to { from + 1 } else { from - 1 };
+                let (first, last) = match first == index {
+                    true => (toward(first, last), last),
+                    false => (first, toward(last, first)),
+                };
+                let mut reference = reference.clone();
+                reference.sheet = Some(names[first].clone());
+                reference.last_sheet = (first != last).then(|| names[last].clone());
+                reference.to_a1()
+            })
+        })?;
+        let removed = self.sheets.remove(index);
+        self.tables
+            .retain(|table| table.sheet.as_ref() != Some(&removed.name));
+        // Names scoped to later sheets follow their sheet down one place
+        self.defined_names
+            .retain_mut(|name| match name.local_sheet_id {
+                Some(id) if id as usize == index => false,
+                Some(id) if id as usize > index => {
+                    name.local_sheet_id = Some(id - 1);
+                    true
+                }
+                _ => true,
+            });
+        Ok(())
+    }
+
+    pub(crate) fn fit_rows(&mut self, sheet: &str) -> Result<(), String> {
+        let index = self.sheet_index(sheet)?;
+        let worksheet = &mut self.sheets[index].worksheet;
+        let heights = autofit_rows_impl(
+            worksheet,
+            &self.styles,
+            &worksheet.col_widths,
+            &self.shared_strings,
+        );
+        for row in worksheet.rows.iter_mut().filter(|row| !row.hidden) {
+            if let Some(&height) = heights.get(&row.row_num) {
+                row.height = Some(height);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn link(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        target: &str,
+        tooltip: Option<String>,
+    ) -> Result<(), String> {
+        let bounds = range_bounds(reference).ok_or(format!("Invalid range: {}", reference))?;
+        let (location, target) = match target.strip_prefix('#') {
+            Some(location) => (Some(location.to_string()), None),
+            None => (None, Some(target.to_string())),
+        };
+        if location.as_deref().or(target.as_deref()) == Some("") {
+            return Err("A hyperlink needs a target".to_string());
+        }
+        let links = &mut self.sheet_mut(sheet)?.hyperlinks;
+        links.retain(|link| range_bounds(&link.reference) != Some(bounds));
+        links.push(ParsedHyperlink {
+            reference: bounds_ref(bounds),
+            rid: None,
+            location,
+            display: None,
+            tooltip,
+            target,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn unlink(&mut self, sheet: &str, reference: &str) -> Result<(), String> {
+        let bounds = range_bounds(reference).ok_or(format!("Invalid range: {}", reference))?;
+        let links = &mut self.sheet_mut(sheet)?.hyperlinks;
+        let count = links.len();
+        links.retain(|link| range_bounds(&link.reference) != Some(bounds));
+        if links.len() == count {
+            return Err(format!("{} has no hyperlink", reference));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn add_merge(&mut self, sheet: &str, range: &str) -> Result<(), String> {
+        let bounds = range_bounds(range).ok_or(format!("Invalid range: {}", range))?;
+        let (r1, c1, r2, c2) = bounds;
+        if (r1, c1) == (r2, c2) {
+            return Err(format!("A merge needs more than one cell: {}", range));
+        }
+        let index = self.sheet_index(sheet)?;
+        if let Some(table) = self.table_at(&self.sheets[index].name, bounds) {
+            return Err(format!("Cells of the table {} can't be merged", table.name));
+        }
+        let worksheet = &mut self.sheets[index].worksheet;
+        if let Some(merge) = worksheet
+            .merge_cells
+            .iter()
+            .find(|merge| range_bounds(merge).is_some_and(|merge| overlaps(merge, bounds)))
+        {
+            return Err(format!("{} overlaps the merged range {}", range, merge));
+        }
+
+        // Only the top-left cell keeps its contents; the others keep their style
+        for row in &mut worksheet.rows {
+            if row.row_num < r1 || row.row_num > r2 {
+                continue;
+            }
+            let row_num = row.row_num;
+            row.cells.retain_mut(|cell| {
+                let col = parse_cell_ref(&cell.reference).map_or(0, |(_, c)| c);
+                if col < c1 || col > c2 || (row_num, col) == (r1, c1) {
+                    return true;
+                }
+                cell.cell_type = None;
+                cell.value = None;
+                cell.formula = None;
+                cell.style_index.is_some()
+            });
+        }
+        worksheet
+            .rows
+            .retain(|row| !row.cells.is_empty() || row.height.is_some() || row.hidden);
+        worksheet.merge_cells.push(bounds_ref(bounds));
+        Ok(())
+    }
+
+    pub(crate) fn remove_merge(&mut self, sheet: &str, range: &str) -> Result<(), String> {
+        let bounds = range_bounds(range).ok_or(format!("Invalid range: {}", range))?;
+        let merges = &mut self.sheet_mut(sheet)?.merge_cells;
+        let index = merges
+            .iter()
+            .position(|merge| range_bounds(merge) == Some(bounds))
+            .ok_or(format!("{} is not a merged range", range))?;
+        merges.remove(index);
+        Ok(())
+    }
+
+    pub(crate) fn rename(&mut self, old: &str, new: &str) -> Result<(), String> {
+        let index = self.sheet_index(old)?;
+        self.check_sheet_name(new, Some(index))?;
+        let old = self.sheets[index].name.clone();
+        self.rewrite_formulas(None, |formula| {
+            rewrite_references(formula, Notation::A1, |reference| {
+                let mut reference = reference.clone();
+                for sheet in [&mut reference.sheet, &mut reference.last_sheet] {
+                    if sheet
+                        .as_ref()
+                        .is_some_and(|s| s.to_lowercase() == old.to_lowercase())
+                    {
+                        *sheet = Some(new.to_string());
+                    }
+                }
+                reference.to_a1()
+            })
+        })?;
+        for table in self.tables.iter_mut() {
+            if table.sheet.as_ref() == Some(&old) {
+                table.sheet = Some(new.to_string());
+            }
+        }
+        self.sheets[index].name = new.to_string();
+        Ok(())
+    }
+
+    /// Rewrite the formulas of every sheet but `skip`: cell formulas,
+    /// validation and conditional formatting rules and defined names, then
+    /// the link locations and chart series that can be read as references
+    ///
+    /// Every formula is rewritten before any is replaced, so one that can't
+    /// be read fails the whole change and leaves the workbook as it was.
+    fn rewrite_formulas<F>(&mut self, skip: Option<usize>, rewrite: F) -> Result<(), String>
+    where
+        F: Fn(&str) -> Result<String, String>,
+    {
+        let rewrite_all = |formulas: &mut dyn Iterator<Item = &String>| {
+            formulas
+                .map(|formula| rewrite(formula).map_err(|e| format!("{}: {}", formula, e)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let mut formulas = Vec::with_capacity(self.sheets.len());
+        let mut rules = Vec::with_capacity(self.sheets.len());
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            if skip == Some(index) {
+                formulas.push(Vec::new());
+                rules.push(Vec::new());
+                continue;
+            }
+            let cells = sheet.worksheet.rows.iter().flat_map(|row| &row.cells);
+            let mut cell_formulas = cells.filter_map(|cell| cell.formula.as_ref());
+            formulas.push(rewrite_all(&mut cell_formulas)?);
+            rules.push(rewrite_all(&mut rule_formulas(&sheet.worksheet))?);
+        }
+        let names = self
+            .defined_names
+            .iter()
+            .map(|name| rewrite(&name.value).map_err(|e| format!("{}: {}", name.name, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let sheets = self.sheets.iter_mut().zip(formulas).zip(rules).enumerate();
+        for (index, ((sheet, formulas), rules)) in sheets {
+            if skip == Some(index) {
+                continue;
+            }
+            let mut formulas = formulas.into_iter();
+            let cells = sheet
+                .worksheet
+                .rows
+                .iter_mut()
+                .flat_map(|row| &mut row.cells);
+            for cell in cells.filter(|cell| cell.formula.is_some()) {
+                cell.formula = formulas.next();
+            }
+            for (formula, rewritten) in rule_formulas_mut(&mut sheet.worksheet).zip(rules) {
+                *formula = rewritten;
+            }
+            for location in locations_mut(&mut sheet.worksheet) {
+                if let Ok(rewritten) = rewrite(location) {
+                    *location = rewritten;
+                }
+            }
+        }
+        for (name, value) in self.defined_names.iter_mut().zip(names) {
+            name.value = value;
+        }
+        Ok(())
+    }
+
+    /// Insert or delete rows or columns of `sheet` (`action` as in
+    /// [`StructureChange`]), moving everything that points at the moved
+    /// cells; on error the workbook is left as it was
+    pub(crate) fn change_structure(
+        &mut self,
+        sheet: &str,
+        action: &str,
+        index: u32,
+        count: u32,
+    ) -> Result<(), String> {
+        let edited = self.sheet_index(sheet)?;
+        let change = StructureChange {
+            action: action.to_string(),
+            index,
+            count,
+            sheet: Some(self.sheets[edited].name.clone()),
+            formula_sheet: None,
+        };
+        let (edit, rows) = change.edit()?;
+        let max = if rows { MAX_ROWS } else { MAX_COLS };
+        let moved = |index: u32| edit.apply(index, index, max).map(|(index, _)| index);
+
+        let worksheet = &self.sheets[edited].worksheet;
+        let last = if rows {
+            worksheet.rows.last().map(|row| row.row_num)
+        } else {
+            used_range(worksheet).map(|(_, _, _, last)| last)
+        };
+        if matches!(edit, Edit::Insert { .. }) && last.is_some_and(|last| moved(last).is_none()) {
+            return Err("The insert would push cells off the end of the sheet".to_string());
+        }
+        // Tables keep their columns, header and totals rows, and a data row
+        let name = &self.sheets[edited].name;
+        for table in self
+            .tables
+            .iter()
+            .filter(|t| t.sheet.as_ref() == Some(name))
+        {
+            let (Some((r1, c1, r2, c2)), Some((s1, t1, s2, t2))) = (
+                range_bounds(&table.reference),
+                adjust_range(&table.reference, edit, rows).and_then(|r| range_bounds(&r)),
+            ) else {
+                continue;
+            };
+            let header = r1..r1 + table.header_row_count;
+            let totals = r2 + 1 - table.totals_row_count..=r2;
+            if rows
+                && (header.chain(totals).any(|row| moved(row).is_none())
+                    || s2 - s1 < table.header_row_count + table.totals_row_count)
+            {
+                return Err(format!(
+                    "The table {} needs its header, totals and at least one data row",
+                    table.name
+                ));
+            }
+            if t2 - t1 != c2 - c1 {
+                return Err(format!(
+                    "Columns can't be inserted or deleted inside the table {}",
+                    table.name
+                ));
+            }
+        }
+
+        // Rewrite every formula before moving anything, so one that can't be
+        // read fails the whole change
+        let on_sheet = |formula_sheet: Option<&ParsedSheet>| StructureChange {
+            formula_sheet: formula_sheet.map(|sheet| sheet.name.clone()),
+            ..change.clone()
+        };
+        let mut formulas = Vec::with_capacity(self.sheets.len());
+        let mut rules = Vec::with_capacity(self.sheets.len());
+        for sheet in &self.sheets {
+            let written: Vec<String> = sheet
+                .worksheet
+                .rows
+                .iter()
+                .flat_map(|row| &row.cells)
+                .filter_map(|cell| cell.formula.clone())
+                .collect();
+            formulas.push(adjust_formulas_impl(&written, &on_sheet(Some(sheet)))?);
+            let written: Vec<String> = rule_formulas(&sheet.worksheet).cloned().collect();
+            rules.push(adjust_formulas_impl(&written, &on_sheet(Some(sheet)))?);
+        }
+        let mut names = Vec::with_capacity(self.defined_names.len());
+        for name in &self.defined_names {
+            let scope = name
+                .local_sheet_id
+                .and_then(|id| self.sheets.get(id as usize));
+            let value = std::slice::from_ref(&name.value);
+            names.extend(adjust_formulas_impl(value, &on_sheet(scope))?);
+        }
+
+        for ((sheet, formulas), rules) in self.sheets.iter_mut().zip(formulas).zip(rules) {
+            let mut formulas = formulas.into_iter();
+            let cells = sheet
+                .worksheet
+                .rows
+                .iter_mut()
+                .flat_map(|row| &mut row.cells);
+            for cell in cells.filter(|cell| cell.formula.is_some()) {
+                cell.formula = formulas.next();
+            }
+            for (formula, adjusted) in rule_formulas_mut(&mut sheet.worksheet).zip(rules) {
+                *formula = adjusted;
+            }
+            // Links to a place in the workbook and chart series move like
+            // formulas; unreadable ones stay
+            let change = on_sheet(Some(sheet));
+            for location in locations_mut(&mut sheet.worksheet) {
+                if let Ok(mut adjusted) =
+                    adjust_formulas_impl(std::slice::from_ref(location), &change)
+                {
+                    *location = adjusted.remove(0);
+                }
+            }
+        }
+        for (name, value) in self.defined_names.iter_mut().zip(names) {
+            name.value = value;
+        }
+
+        let worksheet = &mut self.sheets[edited].worksheet;
+        worksheet.rows.retain_mut(|row| {
+            if rows {
+                match moved(row.row_num) {
+                    Some(row_num) => row.row_num = row_num,
+                    None => return false,
+                }
+            }
+            let row_num = row.row_num;
+            row.cells.retain_mut(|cell| {
+                let reference = parse_cell_ref(&cell.reference).and_then(|(_, col)| {
+                    if rows {
+                        Some(col)
+                    } else {
+                        moved(col)
+                    }
+                });
+                match reference {
+                    Some(col) => cell.reference = cell_ref(row_num, col),
+                    None => return false,
+                }
+                true
+            });
+            !row.cells.is_empty() || row.height.is_some() || row.hidden
+        });
+        if !rows {
+            let widths = std::mem::take(&mut worksheet.col_widths);
+            worksheet.col_widths = widths
+                .into_iter()
+                .filter_map(|(col, width)| Some((moved(col)?, width)))
+                .collect();
+        }
+        // A merge cut down to one cell is no longer a merge
+        let merges = std::mem::take(&mut worksheet.merge_cells);
+        worksheet.merge_cells = merges
+            .iter()
+            .filter_map(|range| adjust_range(range, edit, rows))
+            .filter(|range| range.contains(':'))
+            .collect();
+        worksheet
+            .hyperlinks
+            .retain_mut(|link| match adjust_range(&link.reference, edit, rows) {
+                Some(reference) => {
+                    link.reference = reference;
+                    true
+                }
+                None => false,
+            });
+        // Pictures and charts stretched over a range stay stretched over
+        // what is left of it
+        #[cfg(feature = "drawings")]
+        {
+            let anchors = worksheet.images.iter_mut().map(|image| &mut image.anchor);
+            #[cfg(feature = "charts")]
+            let anchors = anchors.chain(worksheet.charts.iter_mut().map(|chart| &mut chart.anchor));
+            for anchor in anchors {
+                match adjust_range(anchor, edit, rows) {
+                    Some(moved) if anchor.contains(':') && !moved.contains(':') => {
+                        *anchor = format!("{}:{}", moved, moved);
+                    }
+                    Some(moved) => *anchor = moved,
+                    None => anchor.clear(),
+                }
+            }
+            worksheet.images.retain(|image| !image.anchor.is_empty());
+            #[cfg(feature = "charts")]
+            worksheet.charts.retain(|chart| !chart.anchor.is_empty());
+        }
+        // Validations and conditional formats keep what is left of their
+        // cells, and filter criteria stay with their columns
+        let validations = worksheet.data_validations.iter_mut().map(|v| &mut v.sqref);
+        let formats = worksheet.conditional_formats.iter_mut();
+        for sqref in validations.chain(formats.map(|format| &mut format.sqref)) {
+            *sqref = sqref
+                .split_whitespace()
+                .filter_map(|range| adjust_range(range, edit, rows))
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        worksheet.data_validations.retain(|v| !v.sqref.is_empty());
+        worksheet
+            .conditional_formats
+            .retain(|f| !f.sqref.is_empty());
+        if let Some(mut filter) = worksheet.auto_filter.take() {
+            let first = range_bounds(&filter.reference).map(|(_, first, _, _)| first);
+            if let Some(reference) = adjust_range(&filter.reference, edit, rows) {
+                let moved_first = range_bounds(&reference).map(|(_, first, _, _)| first);
+                if let (false, Some(first), Some(moved_first)) = (rows, first, moved_first) {
+                    filter.columns.retain_mut(|column| {
+                        let Some(col) = moved(first + column.col_id) else {
+                            return false;
+                        };
+                        column.col_id = col - moved_first;
+                        true
+                    });
+                }
+                filter.reference = reference;
+                worksheet.auto_filter = Some(filter);
+            }
+        }
+        let name = &self.sheets[edited].name;
+        self.tables.retain_mut(|table| {
+            if table.sheet.as_ref() != Some(name) {
+                return true;
+            }
+            match adjust_range(&table.reference, edit, rows) {
+                Some(reference) => {
+                    table.reference = reference;
+                    true
+                }
+                None => false,
+            }
+        });
+        Ok(())
+    }
+
+    /// Table on `sheet` (its exact name) that shares a cell with `bounds`
+    fn table_at(&self, sheet: &str, bounds: Bounds) -> Option<&ParsedTable> {
+        self.tables.iter().find(|table| {
+            table.sheet.as_deref() == Some(sheet)
+                && range_bounds(&table.reference).is_some_and(|table| overlaps(table, bounds))
+        })
+    }
+
+    /// Check that `name` can name a table and isn't a table or defined name
+    /// already
+    fn check_table_name(&self, name: &str) -> Result<(), String> {
+        if !valid_name(name) {
+            return Err(format!("Invalid table name: {}", name));
+        }
+        let lower = name.to_lowercase();
+        let tables = self.tables.iter().map(|table| &table.name);
+        let names = self.defined_names.iter().map(|defined| &defined.name);
+        if tables
+            .chain(names)
+            .any(|taken| taken.to_lowercase() == lower)
+        {
+            return Err(format!("The name {} is already used", name));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn insert_table(
+        &mut self,
+        sheet: &str,
+        range: &str,
+        spec: TableSpec,
+    ) -> Result<String, String> {
+        let index = self.sheet_index(sheet)?;
+        let bounds = range_bounds(range).ok_or(format!("Invalid range: {}", range))?;
+        let (r1, c1, r2, c2) = bounds;
+        let totals_rows = u32::from(!spec.totals.is_empty());
+        if r2 - r1 < 1 + totals_rows {
+            return Err(format!(
+                "A table needs a header row and a data row: {}",
+                range
+            ));
+        }
+        if spec.totals.len() > (c2 - c1 + 1) as usize {
+            return Err(format!("{} has fewer columns than totals", range));
+        }
+        for total in &spec.totals {
+            match (total.function.as_deref(), &total.label) {
+                (None, _) => {}
+                (Some(_), Some(_)) => {
+                    return Err("A totals cell has a label or a function, not both".to_string())
+                }
+                (Some("custom"), None) if total.formula.is_some() => {}
+                (Some(_), None) if total.subtotal().is_some() => {}
+                (Some(function), None) => {
+                    return Err(format!("Unknown totals function: {}", function))
+                }
+            }
+        }
+        let name = match spec.name {
+            Some(name) => {
+                self.check_table_name(&name)?;
+                name
+            }
+            None => (1..)
+                .map(|n| format!("Table{}", n))
+                .find(|name| self.check_table_name(name).is_ok())
+                .unwrap_or_default(),
+        };
+        let sheet_name = self.sheets[index].name.clone();
+        if let Some(table) = self.table_at(&sheet_name, bounds) {
+            return Err(format!("{} overlaps the table {}", range, table.name));
+        }
+        let worksheet = &self.sheets[index].worksheet;
+        if let Some(merge) = worksheet
+            .merge_cells
+            .iter()
+            .find(|merge| range_bounds(merge).is_some_and(|merge| overlaps(merge, bounds)))
+        {
+            return Err(format!("{} overlaps the merged range {}", range, merge));
+        }
+
+        // Column names come from the header cells, made unique as Excel does
+        let header_row = worksheet.rows.iter().find(|row| row.row_num == r1);
+        let mut columns: Vec<String> = Vec::new();
+        for col in c1..=c2 {
+            let header = header_row
+                .and_then(|row| row.cells.iter().find(|cell| column(cell) == col))
+                .and_then(|cell| cell_text(cell, &self.shared_strings))
+                .map(str::trim)
+                .filter(|text| !text.is_empty());
+            let base = match header {
+                Some(text) => text.to_string(),
+                None => format!("Column{}", col - c1 + 1),
+            };
+            let mut column = base.clone();
+            let mut suffix = 2;
+            while columns
+                .iter()
+                .any(|c| c.to_lowercase() == column.to_lowercase())
+            {
+                column = format!("{}{}", base, suffix);
+                suffix += 1;
+            }
+            columns.push(column);
+        }
+
+        // Header cells must hold the names exactly, and totals cells compute
+        // only hidden-row-aware subtotals
+        for (col, column) in (c1..).zip(&columns) {
+            let text = TypedValue::Text(column.clone());
+            self.set_value(sheet, &cell_ref(r1, col), Some(text))?;
+        }
+        for ((col, total), column) in (c1..).zip(&spec.totals).zip(&columns) {
+            let reference = cell_ref(r2, col);
+            if let Some(label) = &total.label {
+                self.set_value(sheet, &reference, Some(TypedValue::Text(label.clone())))?;
+            } else if let Some(code) = total.subtotal() {
+                let formula = format!("SUBTOTAL({},{}[{}])", code, name, escape_column(column));
+                self.set_cell_formula(sheet, &reference, Some(formula))?;
+            } else if total.function.is_some() {
+                self.set_cell_formula(sheet, &reference, total.formula.clone())?;
+            }
+        }
+
+        self.tables.push(ParsedTable {
+            name: name.clone(),
+            display_name: name.clone(),
+            reference: bounds_ref(bounds),
+            header_row_count: 1,
+            totals_row_count: totals_rows,
+            columns,
+            sheet: Some(sheet_name),
+            style: Some(spec.style.unwrap_or_default()),
+            totals: spec.totals,
+        });
+        Ok(name)
+    }
+
+    #[cfg(feature = "drawings")]
+    pub(crate) fn insert_image(
+        &mut self,
+        sheet: &str,
+        data: Vec<u8>,
+        anchor: &str,
+    ) -> Result<(), String> {
+        if image_info(&data).is_none() {
+            return Err("Images must be PNG, JPEG, GIF or BMP files".to_string());
+        }
+        let bounds = range_bounds(anchor).ok_or(format!("Invalid image anchor: {}", anchor))?;
+        let anchor = match anchor.contains(':') {
+            true => format!(
+                "{}:{}",
+                cell_ref(bounds.0, bounds.1),
+                cell_ref(bounds.2, bounds.3)
+            ),
+            false => bounds_ref(bounds),
+        };
+        self.sheet_mut(sheet)?.images.push(SheetImage {
+            data,
+            anchor,
+            description: None,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "charts")]
+    pub(crate) fn insert_chart(
+        &mut self,
+        sheet: &str,
+        mut chart: SheetChart,
+    ) -> Result<(), String> {
+        check_chart(&chart)?;
+        let index = self.sheet_index(sheet)?;
+        let bounds =
+            range_bounds(&chart.anchor).ok_or(format!("Invalid chart anchor: {}", chart.anchor))?;
+        if chart.anchor.contains(':') {
+            chart.anchor = format!(
+                "{}:{}",
+                cell_ref(bounds.0, bounds.1),
+                cell_ref(bounds.2, bounds.3)
+            );
+        } else {
+            chart.anchor = bounds_ref(bounds);
+        }
+        let name = &self.sheets[index].name;
+        for series in &mut chart.series {
+            let ranges = std::iter::once(&mut series.values).chain(series.categories.as_mut());
+            for range in ranges {
+                *range =
+                    series_ref(range, name).ok_or(format!("Invalid chart range: {}", range))?;
+            }
+        }
+        self.sheets[index].worksheet.charts.push(chart);
+        Ok(())
+    }
+
+    pub(crate) fn insert_name(
+        &mut self,
+        name: &str,
+        value: &str,
+        sheet: Option<&str>,
+    ) -> Result<(), String> {
+        if !valid_name(name) || name.to_lowercase().starts_with("_xlnm.") {
+            return Err(format!("Invalid name: {}", name));
+        }
+        let local_sheet_id = sheet
+            .map(|sheet| self.sheet_index(sheet).map(|index| index as u32))
+            .transpose()?;
+        let lower = name.to_lowercase();
+        let taken = self.defined_names.iter().any(|defined| {
+            defined.local_sheet_id == local_sheet_id && defined.name.to_lowercase() == lower
+        });
+        if taken || self.tables.iter().any(|t| t.name.to_lowercase() == lower) {
+            return Err(format!("The name {} is already used", name));
+        }
+        let value = value.strip_prefix('=').unwrap_or(value);
+        rewrite_references(value, Notation::A1, |reference| reference.to_a1())
+            .map_err(|e| format!("{}: {}", value, e))?;
+        self.defined_names.push(ParsedDefinedName {
+            name: name.to_string(),
+            value: value.to_string(),
+            local_sheet_id,
+            hidden: false,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn delete_name(&mut self, name: &str, sheet: Option<&str>) -> Result<(), String> {
+        let local_sheet_id = sheet
+            .map(|sheet| self.sheet_index(sheet).map(|index| index as u32))
+            .transpose()?;
+        let lower = name.to_lowercase();
+        let position = self.defined_names.iter().position(|defined| {
+            defined.local_sheet_id == local_sheet_id && defined.name.to_lowercase() == lower
+        });
+        let position = position.ok_or(format!("No name {}", name))?;
+        self.defined_names.remove(position);
+        Ok(())
+    }
+
+    pub(crate) fn print_range(&mut self, sheet: &str, range: Option<&str>) -> Result<(), String> {
+        let index = self.sheet_index(sheet)?;
+        let name = &self.sheets[index].name;
+        let value = range.map(|range| print_area(name, range)).transpose()?;
+        set_local_name(&mut self.defined_names, PRINT_AREA, index as u32, value);
+        Ok(())
+    }
+
+    pub(crate) fn repeat_titles(
+        &mut self,
+        sheet: &str,
+        rows: Option<&str>,
+        cols: Option<&str>,
+    ) -> Result<(), String> {
+        let index = self.sheet_index(sheet)?;
+        let name = &self.sheets[index].name;
+        let value = match (rows, cols) {
+            (None, None) => None,
+            _ => Some(print_titles(name, rows, cols)?),
+        };
+        set_local_name(&mut self.defined_names, PRINT_TITLES, index as u32, value);
+        Ok(())
+    }
+
+    pub(crate) fn protect(
+        &mut self,
+        sheet: &str,
+        mut protection: SheetProtection,
+        password: Option<&str>,
+        salt: &[u8],
+    ) -> Result<(), String> {
+        let worksheet = self.sheet_mut(sheet)?;
+        if let Some(password) = password {
+            set_password(&mut protection, password, Some(salt))?;
+        }
+        worksheet.protection = Some(protection);
+        Ok(())
+    }
+
+    pub(crate) fn set_document_properties(
+        &mut self,
+        properties: DocumentProperties,
+    ) -> Result<(), String> {
+        // Bad dates and custom names fail here rather than when saving
+        write_core_properties_impl(&properties)?;
+        write_custom_properties_impl(&properties.custom)?;
+        self.properties = Some(properties);
+        Ok(())
+    }
+
+    pub(crate) fn set_value(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        value: Option<TypedValue>,
+    ) -> Result<(), String> {
+        let (cell_type, text) = match value {
+            None => (None, None),
+            Some(TypedValue::Number(n)) if !n.is_finite() => {
+                return Err(format!("{} is not a finite number", n))
+            }
+            Some(TypedValue::Number(n)) => (None, Some(n.to_string())),
+            Some(TypedValue::Bool(b)) => (Some("b"), Some(if b { "1" } else { "0" }.to_string())),
+            Some(TypedValue::Text(text)) => (Some("s"), Some(self.intern(text).to_string())),
+        };
+        self.edit(sheet, reference, |cell| {
+            cell.cell_type = cell_type.map(str::to_string);
+            cell.value = text;
+            cell.formula = None;
+        })
+    }
+
+    pub(crate) fn set_cell_formula(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        formula: Option<String>,
+    ) -> Result<(), String> {
+        let formula = formula.map(|f| f.strip_prefix('=').unwrap_or(&f).to_string());
+        self.edit(sheet, reference, |cell| {
+            if formula.is_some() {
+                cell.cell_type = None;
+                cell.value = None;
+            }
+            cell.formula = formula;
+        })
+    }
+
+    pub(crate) fn set_style(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        style: Option<u32>,
+    ) -> Result<(), String> {
+        if let Some(index) = style.filter(|&i| i as usize >= self.styles.cell_xfs.len()) {
+            return Err(format!("Unknown style: {}", index));
+        }
+        self.edit(sheet, reference, |cell| cell.style_index = style)
+    }
+
+    pub(crate) fn register_style(&mut self, spec: StyleSpec) -> u32 {
+        let style = self.patch_style(ParsedStyle::default(), &spec);
+        self.style_index(style)
+    }
+
+    pub(crate) fn style_range(
+        &mut self,
+        sheet: &str,
+        range: &str,
+        spec: &StyleSpec,
+    ) -> Result<(), String> {
+        let (r1, c1, r2, c2) = range_bounds(range).ok_or(format!("Invalid range: {}", range))?;
+        check_size(range, r2 - r1 + 1, c2 - c1 + 1)?;
+        let index = self.sheet_index(sheet)?;
+
+        // Each style in the range is patched once, however many cells share it
+        let mut restyled = HashMap::new();
+        let rows = &self.sheets[index].worksheet.rows;
+        let in_range = rows
+            .iter()
+            .filter(|row| row.row_num >= r1 && row.row_num <= r2);
+        let bases = in_range
+            .flat_map(|row| &row.cells)
+            .filter(|cell| (c1..=c2).contains(&column(cell)))
+            .map(|cell| cell.style_index);
+        let bases: Vec<_> = bases.chain([None]).collect();
+        for base in bases {
+            if let Entry::Vacant(entry) = restyled.entry(base) {
+                let style = base
+                    .and_then(|i| self.styles.cell_xfs.get(i as usize).cloned())
+                    .unwrap_or_default();
+                let style = self.patch_style(style, spec);
+                entry.insert(self.style_index(style));
+            }
+        }
+
+        let rows = &mut self.sheets[index].worksheet.rows;
+        let start = rows.partition_point(|row| row.row_num < r1);
+        let end = rows.partition_point(|row| row.row_num <= r2);
+        let mut existing = rows
+            .drain(start..end)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+        let mut filled = Vec::with_capacity((r2 - r1 + 1) as usize);
+        for row_num in r1..=r2 {
+            let mut row = existing
+                .next_if(|row| row.row_num == row_num)
+                .unwrap_or_else(|| empty_row(row_num));
+            let mut cells = std::mem::take(&mut row.cells).into_iter().peekable();
+            let mut out = Vec::with_capacity(cells.len() + (c2 - c1 + 1) as usize);
+            while let Some(cell) = cells.next_if(|cell| column(cell) < c1) {
+                out.push(cell);
+            }
+            for col in c1..=c2 {
+                let mut cell = cells
+                    .next_if(|cell| column(cell) == col)
+                    .unwrap_or_else(|| empty_cell(row_num, col));
+                cell.style_index = Some(restyled[&cell.style_index]);
+                out.push(cell);
+            }
+            out.extend(cells);
+            row.cells = out;
+            filled.push(row);
+        }
+        rows.splice(start..start, filled);
+        Ok(())
+    }
+
+    pub(crate) fn paste_range(
+        &mut self,
+        sheet: &str,
+        source: &str,
+        dest: &str,
+    ) -> Result<(), String> {
+        let (r1, c1, r2, c2) = range_bounds(source).ok_or(format!("Invalid range: {}", source))?;
+        let (dest_sheet, dest_range) = split_sheet(dest);
+        let (d1, e1, d2, e2) =
+            range_bounds(dest_range).ok_or(format!("Invalid destination: {}", dest))?;
+        let (height, width) = (r2 - r1 + 1, c2 - c1 + 1);
+        // Copies repeat to fill the destination, or go in once at its corner
+        let down = ((d2 - d1 + 1) / height).max(1);
+        let across = ((e2 - e1 + 1) / width).max(1);
+        let (rows, cols) = (height * down, width * across);
+        if d1 + rows - 1 > MAX_ROWS || e1 + cols - 1 > MAX_COLS {
+            return Err(format!("Pasting {} at {} runs off the sheet", source, dest));
+        }
+        check_size(dest, rows, cols)?;
+        let from = self.sheet_index(sheet)?;
+        let to = match &dest_sheet {
+            Some(name) => self.sheet_index(name)?,
+            None => from,
+        };
+
+        let worksheet = &self.sheets[from].worksheet;
+        let copied: Vec<ParsedCell> = worksheet
+            .rows
+            .iter()
+            .filter(|row| (r1..=r2).contains(&row.row_num))
+            .flat_map(|row| &row.cells)
+            .filter(|cell| (c1..=c2).contains(&column(cell)))
+            .cloned()
+            .collect();
+        let merges: Vec<Bounds> = worksheet
+            .merge_cells
+            .iter()
+            .filter_map(|merge| range_bounds(merge))
+            .filter(|&(m1, n1, m2, n2)| m1 >= r1 && n1 >= c1 && m2 <= r2 && n2 <= c2)
+            .collect();
+
+        // Build every pasted cell first, so a bad formula leaves the sheet as it was
+        let mut pasted = Vec::with_capacity(copied.len() * (down * across) as usize);
+        for (i, j) in (0..down).flat_map(|i| (0..across).map(move |j| (i, j))) {
+            let (top, left) = (d1 + i * height, e1 + j * width);
+            for cell in &copied {
+                let (row, col) = parse_cell_ref(&cell.reference).unwrap_or((r1, c1));
+                let target = (top + row - r1, left + col - c1);
+                let formula = match &cell.formula {
+                    Some(formula) => Some(
+                        translate_formula(formula, (row, col), target)
+                            .ok_or(format!("Can't adjust the formula in {}", cell.reference))?,
+                    ),
+                    None => None,
+                };
+                pasted.push(ParsedCell {
+                    reference: cell_ref(target.0, target.1),
+                    formula,
+                    ..cell.clone()
+                });
+            }
+        }
+
+        // The pasted area is replaced outright, empty source cells included
+        let (last_row, last_col) = (d1 + rows - 1, e1 + cols - 1);
+        let area = (d1, e1, last_row, last_col);
+        let worksheet = &mut self.sheets[to].worksheet;
+        for row in &mut worksheet.rows {
+            if (d1..=last_row).contains(&row.row_num) {
+                row.cells
+                    .retain(|cell| !(e1..=last_col).contains(&column(cell)));
+            }
+        }
+        worksheet
+            .merge_cells
+            .retain(|merge| range_bounds(merge).is_none_or(|merge| !overlaps(merge, area)));
+        for (i, j) in (0..down).flat_map(|i| (0..across).map(move |j| (i, j))) {
+            let (top, left) = (d1 + i * height, e1 + j * width);
+            for &(m1, n1, m2, n2) in &merges {
+                let merge = (top + m1 - r1, left + n1 - c1, top + m2 - r1, left + n2 - c1);
+                worksheet.merge_cells.push(bounds_ref(merge));
+            }
+        }
+        let name = self.sheets[to].name.clone();
+        for cell in pasted {
+            let reference = cell.reference.clone();
+            self.edit(&name, &reference, |target| *target = cell)?;
+        }
+        let rows = &mut self.sheets[to].worksheet.rows;
+        rows.retain(|row| !row.cells.is_empty() || row.height.is_some() || row.hidden);
+        Ok(())
+    }
+
+    /// `style` with the parts `spec` sets replaced
+    fn patch_style(&mut self, mut style: ParsedStyle, spec: &StyleSpec) -> ParsedStyle {
+        let styles = &mut self.styles;
+        // Index 0 of each table is what unstyled cells get, so it stays the default
+        if styles.cell_xfs.is_empty() {
+            styles.cell_xfs.push(ParsedStyle::default());
+        }
+        if styles.fonts.is_empty() {
+            styles.fonts.push(ParsedFont {
+                size: Some(11.0),
+                name: Some("Calibri".to_string()),
+                ..ParsedFont::default()
+            });
+        }
+        if styles.borders.is_empty() {
+            styles.borders.push(ParsedBorder::default());
+        }
+
+        if let Some(font) = &spec.font {
+            style.font_id = Some(position_or_push(&mut styles.fonts, font));
+            style.apply_font = true;
+        }
+        if let Some(fill) = &spec.fill {
+            style.fill_id = Some(position_or_push(&mut styles.fills, fill));
+            style.apply_fill = true;
+        }
+        if let Some(border) = &spec.border {
+            style.border_id = Some(position_or_push(&mut styles.borders, border));
+            style.apply_border = true;
+        }
+        if let Some(code) = &spec.num_fmt {
+            let existing = styles.num_fmts.iter().find(|(_, c)| *c == code);
+            let id = match existing {
+                _ if code == "General" => 0,
+                Some((&id, _)) => id,
+                None => {
+                    let id = styles
+                        .num_fmts
+                        .keys()
+                        .map(|&id| id + 1)
+                        .fold(FIRST_CUSTOM_NUM_FMT, u32::max);
+                    styles.num_fmts.insert(id, code.clone());
+                    id
+                }
+            };
+            style.num_fmt_id = Some(id);
+            style.apply_number_format = true;
+        }
+        if let Some(horizontal) = &spec.horizontal {
+            style.horizontal = Some(horizontal.clone());
+            style.apply_alignment = true;
+        }
+        if let Some(vertical) = &spec.vertical {
+            style.vertical = Some(vertical.clone());
+            style.apply_alignment = true;
+        }
+        if let Some(wrap_text) = spec.wrap_text {
+            style.wrap_text = wrap_text;
+            style.apply_alignment = true;
+        }
+        style
+    }
+
+    /// Index of a cell format equal to `style`, adding it when new
+    fn style_index(&mut self, style: ParsedStyle) -> u32 {
+        position_or_push(&mut self.styles.cell_xfs, &style)
+    }
+
+    /// Index of `text` in the shared strings, adding it when new
+    fn intern(&mut self, text: String) -> usize {
+        if let Some(&index) = self.string_indices.get(&text) {
+            return index;
+        }
+        self.shared_strings.push(text.clone());
+        self.string_indices
+            .insert(text, self.shared_strings.len() - 1);
+        self.shared_strings.len() - 1
+    }
+
+    /// Change one cell, creating its row and cell as needed and removing
+    /// them again when the edit leaves them empty
+    fn edit(
+        &mut self,
+        sheet: &str,
+        reference: &str,
+        f: impl FnOnce(&mut ParsedCell),
+    ) -> Result<(), String> {
+        let (row_num, col) =
+            parse_cell_ref(reference).ok_or(format!("Invalid cell reference: {}", reference))?;
+        let rows = &mut self.sheet_mut(sheet)?.rows;
+        let row_index = match rows.binary_search_by_key(&row_num, |row| row.row_num) {
+            Ok(index) => index,
+            Err(index) => {
+                rows.insert(index, empty_row(row_num));
+                index
+            }
+        };
+        let row = &mut rows[row_index];
+        let cell_index = match row.cells.iter().position(|cell| column(cell) >= col) {
+            Some(index) if column(&row.cells[index]) == col => index,
+            position => {
+                let index = position.unwrap_or(row.cells.len());
+                row.cells.insert(index, empty_cell(row_num, col));
+                index
+            }
+        };
+
+        let cell = &mut row.cells[cell_index];
+        f(cell);
+        if cell.value.is_none() && cell.formula.is_none() && cell.style_index.is_none() {
+            row.cells.remove(cell_index);
+            if row.cells.is_empty() && row.height.is_none() && !row.hidden {
+                rows.remove(row_index);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write(&self) -> Result<Vec<u8>, String> {
+        let sheets = self
+            .sheets
+            .iter()
+            .map(|sheet| ExportSheet {
+                name: sheet.name.clone(),
+                state: sheet.state.clone(),
+                worksheet: sheet.worksheet.clone(),
+                relationships: Vec::new(),
+                tables: self
+                    .tables
+                    .iter()
+                    .filter(|table| table.sheet.as_ref() == Some(&sheet.name))
+                    .cloned()
+                    .collect(),
+                // Print settings are kept as the sheets' built-in names
+                print: PrintSettings::default(),
+            })
+            .collect();
+        workbook_to_xlsx_impl(ExportWorkbook {
+            sheets,
+            shared_strings: self
+                .shared_strings
+                .iter()
+                .cloned()
+                .map(SharedStringEntry::Plain)
+                .collect(),
+            styles: Some(self.styles.clone()),
+            defined_names: self.defined_names.clone(),
+            workbook_pr: Some(ParsedWorkbookPr {
+                date1904: self.date1904,
+                ..ParsedWorkbookPr::default()
+            }),
+            compression_level: None,
+            password: None,
+            properties: self.properties.clone(),
+        })
+    }
+}
+
+/// Whether `name` can name a table or range: it starts with a letter or
+/// underscore and can't be read as a cell
+fn valid_name(name: &str) -> bool {
+    let starts_well = name.starts_with(|c: char| c.is_alphabetic() || c == '_');
+    starts_well && !needs_quotes(name) && name.chars().count() <= 255
+}
+
+/// Places in the workbook a sheet's links go to, then the ranges its
+/// charts read their series from
+fn locations_mut(worksheet: &mut ParsedWorksheet) -> impl Iterator<Item = &mut String> {
+    let links = worksheet.hyperlinks.iter_mut();
+    #[cfg(feature = "charts")]
+    let series = worksheet
+        .charts
+        .iter_mut()
+        .flat_map(|chart| &mut chart.series);
+    #[cfg(feature = "charts")]
+    let ranges = series
+        .flat_map(|series| std::iter::once(&mut series.values).chain(series.categories.as_mut()));
+    #[cfg(not(feature = "charts"))]
+    let ranges = std::iter::empty();
+    links
+        .filter_map(|link| link.location.as_mut())
+        .chain(ranges)
+}
+
+/// Formulas of a sheet's validation and conditional formatting rules, in
+/// the order [`rule_formulas_mut`] gives them
+fn rule_formulas(worksheet: &ParsedWorksheet) -> impl Iterator<Item = &String> {
+    let validations = worksheet.data_validations.iter();
+    let validations = validations.flat_map(|v| v.formula1.iter().chain(&v.formula2));
+    let rules = worksheet.conditional_formats.iter().flat_map(|f| &f.rules);
+    validations.chain(rules.flat_map(|rule| {
+        let thresholds = rule.thresholds.iter().filter(|t| t.value_type == "formula");
+        rule.formulas
+            .iter()
+            .chain(thresholds.filter_map(|t| t.value.as_ref()))
+    }))
+}
+
+fn rule_formulas_mut(worksheet: &mut ParsedWorksheet) -> impl Iterator<Item = &mut String> {
+    let validations = worksheet.data_validations.iter_mut();
+    let validations = validations.flat_map(|v| v.formula1.iter_mut().chain(&mut v.formula2));
+    let rules = worksheet.conditional_formats.iter_mut();
+    let rules = rules.flat_map(|f| &mut f.rules);
+    validations.chain(rules.flat_map(|rule| {
+        let thresholds = rule.thresholds.iter_mut();
+        let thresholds = thresholds.filter(|t| t.value_type == "formula");
+        rule.formulas
+            .iter_mut()
+            .chain(thresholds.filter_map(|t| t.value.as_mut()))
+    }))
+}
+
+/// Escape the characters a structured reference's column name can't hold
+fn escape_column(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for ch in name.chars() {
+        if matches!(ch, '[' | ']' | '#' | '\'') {
+            out.push('\'');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Whether two ranges share a cell
+fn overlaps((r1, c1, r2, c2): Bounds, (s1, t1, s2, t2): Bounds) -> bool {
+    s1 <= r2 && r1 <= s2 && t1 <= c2 && c1 <= t2
+}
+
+/// Fail for ranges of more than [`MAX_RANGE_CELLS`] cells
+fn check_size(range: &str, rows: u32, cols: u32) -> Result<(), String> {
+    let count = u64::from(rows) * u64::from(cols);
+    if count > MAX_RANGE_CELLS {
+        return Err(format!(
+            "{} covers {} cells; one edit takes at most {}",
+            range, count, MAX_RANGE_CELLS
+        ));
+    }
+    Ok(())
+}
+
+/// Column of a cell, 0 when its reference can't be read
+fn column(cell: &ParsedCell) -> u32 {
+    parse_cell_ref(&cell.reference).map_or(0, |(_, col)| col)
+}
+
+fn empty_row(row_num: u32) -> ParsedRow {
+    ParsedRow {
+        row_num,
+        cells: Vec::new(),
+        height: None,
+        hidden: false,
+    }
+}
+
+fn empty_cell(row: u32, col: u32) -> ParsedCell {
+    ParsedCell {
+        reference: cell_ref(row, col),
+        cell_type: None,
+        style_index: None,
+        value: None,
+        formula: None,
+    }
+}
+
+/// Index of an entry equal to `item`, adding a copy when there is none
+fn position_or_push<T: Clone + PartialEq>(items: &mut Vec<T>, item: &T) -> u32 {
+    match items.iter().position(|existing| existing == item) {
+        Some(index) => index as u32,
+        None => {
+            items.push(item.clone());
+            items.len() as u32 - 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::parse_table_impl;
+    use crate::testing::{cell, row};
+    use crate::workbook::Workbook;
+    use crate::zip::ZipArchive;
+    #[cfg(feature = "charts")]
+    use crate::ChartSeries;
+    use crate::{AutoFilter, ConditionalFormat, ConditionalRule, DataValidation, FilterColumn};
+
+    #[test]
+    fn test_edit_round_trip() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Scratch").unwrap();
+        assert!(builder.insert_sheet("data").is_err());
+        assert!(builder.insert_sheet("a/b").is_err());
+
+        let number = Some(TypedValue::Number(2.5));
+        builder.set_value("Data", "B2", number).unwrap();
+        let text = Some(TypedValue::Text("hi".to_string()));
+        builder.set_value("Data", "A2", text).unwrap();
+        builder
+            .set_value("Data", "C1", Some(TypedValue::Bool(true)))
+            .unwrap();
+        builder
+            .set_cell_formula("Data", "C2", Some("=B2*2".to_string()))
+            .unwrap();
+        let style = builder.register_style(StyleSpec {
+            font: Some(ParsedFont {
+                bold: true,
+                ..ParsedFont::default()
+            }),
+            num_fmt: Some("0.00%".to_string()),
+            ..StyleSpec::default()
+        });
+        builder.set_style("Data", "B2", Some(style)).unwrap();
+        assert!(builder.set_style("Data", "B2", Some(99)).is_err());
+        builder.remove_sheet("Scratch").unwrap();
+
+        let data = read_workbook(&builder.write().unwrap(), ParseLimits::default(), None).unwrap();
+        assert_eq!(data.sheets.len(), 1);
+        let rows = &data.sheets[0].worksheet.rows;
+        let refs: Vec<_> = rows[1].cells.iter().map(|c| c.reference.as_str()).collect();
+        assert_eq!(refs, vec!["A2", "B2", "C2"]);
+        assert_eq!(rows[1].cells[2].formula.as_deref(), Some("B2*2"));
+        let styles = data.styles.as_ref().unwrap();
+        let xf = &styles.cell_xfs[rows[1].cells[1].style_index.unwrap() as usize];
+        assert!(styles.fonts[xf.font_id.unwrap() as usize].bold);
+        assert_eq!(styles.num_fmts[&xf.num_fmt_id.unwrap()], "0.00%");
+
+        // Reopened, edited again and saved, the changes stack up
+        let mut builder = WorkbookBuilder::from_data(data);
+        builder.set_value("Data", "A2", None).unwrap();
+        builder.set_value("Data", "C1", None).unwrap();
+        let data = read_workbook(&builder.write().unwrap(), ParseLimits::default(), None).unwrap();
+        let workbook = Workbook::from_data(data);
+        let sheet = workbook.sheet_handle(Some("Data")).unwrap();
+        assert_eq!(sheet.used_range_ref().as_deref(), Some("B2:C2"));
+        let b2 = sheet.cell_at("B2").unwrap().data();
+        assert_eq!(b2.value, Some(TypedValue::Number(2.5)));
+    }
+
+    #[test]
+    fn test_apply_style() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        let bold = StyleSpec {
+            font: Some(ParsedFont {
+                bold: true,
+                ..ParsedFont::default()
+            }),
+            ..StyleSpec::default()
+        };
+        let style = builder.register_style(bold);
+        builder.set_style("Data", "A1", Some(style)).unwrap();
+        builder
+            .set_value("Data", "C2", Some(TypedValue::Number(1.0)))
+            .unwrap();
+
+        let fill = StyleSpec {
+            fill: Some(ParsedFill {
+                pattern_type: Some("solid".to_string()),
+                fg_color: Some("FFFF0000".to_string()),
+                bg_color: None,
+            }),
+            ..StyleSpec::default()
+        };
+        builder.style_range("Data", "A1:B2", &fill).unwrap();
+        let xfs = builder.styles.cell_xfs.len();
+        builder.style_range("data", "A1:B2", &fill).unwrap();
+        assert_eq!(builder.styles.cell_xfs.len(), xfs);
+        assert_eq!(builder.styles.fills.len(), 1);
+
+        let rows = &builder.sheets[0].worksheet.rows;
+        let refs: Vec<_> = rows
+            .iter()
+            .flat_map(|row| row.cells.iter().map(|cell| cell.reference.as_str()))
+            .collect();
+        assert_eq!(refs, vec!["A1", "B1", "A2", "B2", "C2"]);
+        let xf =
+            |i: usize| &builder.styles.cell_xfs[rows[0].cells[i].style_index.unwrap() as usize];
+        // A1 keeps its bold font under the fill, B1 only gets the fill
+        assert_eq!(xf(0).fill_id, xf(1).fill_id);
+        assert!(builder.styles.fonts[xf(0).font_id.unwrap() as usize].bold);
+        assert_eq!(xf(1).font_id, None);
+        assert_eq!(rows[1].cells[2].style_index, None);
+
+        assert!(builder.style_range("Data", "A:A", &fill).is_err());
+        assert!(builder.style_range("Data", "A1:XFD1000", &fill).is_err());
+    }
+
+    #[test]
+    fn test_hyperlinks() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Notes").unwrap();
+        let site = "https://example.com/?a=1&b=2";
+        builder.link("Data", "A1", site, None).unwrap();
+        builder.link("Data", "B2", "#Notes!A1", None).unwrap();
+        builder
+            .link("Data", "C3", "https://old.example", None)
+            .unwrap();
+        builder
+            .link("Notes", "A1", "https://example.org", None)
+            .unwrap();
+        let tooltip = Some("Replaced".to_string());
+        builder
+            .link("Data", "C3", "https://new.example", tooltip)
+            .unwrap();
+        builder.unlink("Notes", "A1").unwrap();
+        assert!(builder.unlink("Notes", "A1").is_err());
+        assert!(builder.link("Data", "A2", "#", None).is_err());
+
+        let data = read_workbook(&builder.write().unwrap(), ParseLimits::default(), None).unwrap();
+        let links = &data.sheets[0].worksheet.hyperlinks;
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].target.as_deref(), Some(site));
+        assert_eq!(links[1].location.as_deref(), Some("Notes!A1"));
+        assert_eq!(
+            (links[1].rid.as_ref(), links[1].target.as_ref()),
+            (None, None)
+        );
+        assert_eq!(links[2].target.as_deref(), Some("https://new.example"));
+        assert_ne!(links[0].rid, links[2].rid);
+        assert!(data.sheets[1].worksheet.hyperlinks.is_empty());
+
+        // Reopened, the links are kept and saved again with new relationships
+        let mut builder = WorkbookBuilder::from_data(data);
+        builder.unlink("Data", "A1").unwrap();
+        let data = read_workbook(&builder.write().unwrap(), ParseLimits::default(), None).unwrap();
+        let links = &data.sheets[0].worksheet.hyperlinks;
+        assert_eq!(links[1].target.as_deref(), Some("https://new.example"));
+        assert_eq!(links[1].rid.as_deref(), Some("rId1"));
+    }
+
+    #[test]
+    fn test_copy_range() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Other").unwrap();
+        for (reference, n) in [("A1", 1.0), ("A2", 2.0), ("C3", 3.0)] {
+            let value = Some(TypedValue::Number(n));
+            builder.set_value("Data", reference, value).unwrap();
+        }
+        let formula = Some("A1*$A$2+Data!A1".to_string());
+        builder.set_cell_formula("Data", "B1", formula).unwrap();
+        builder.add_merge("Data", "A2:B2").unwrap();
+
+        // Four rows of destination take two copies of the two-row source
+        builder.paste_range("Data", "A1:B2", "A3:B6").unwrap();
+        let data = &builder.sheets[0].worksheet;
+        let cells: Vec<_> = data
+            .rows
+            .iter()
+            .flat_map(|row| &row.cells)
+            .map(|cell| (cell.reference.as_str(), cell.formula.as_deref()))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                ("A1", None),
+                ("B1", Some("A1*$A$2+Data!A1")),
+                ("A2", None),
+                ("A3", None),
+                ("B3", Some("A3*$A$2+Data!A3")),
+                ("C3", None),
+                ("A4", None),
+                ("A5", None),
+                ("B5", Some("A5*$A$2+Data!A5")),
+                ("A6", None),
+            ]
+        );
+        assert_eq!(data.merge_cells, vec!["A2:B2", "A4:B4", "A6:B6"]);
+
+        builder.paste_range("Data", "B1", "'Other'!A1").unwrap();
+        let other = &builder.sheets[1].worksheet.rows[0].cells[0];
+        assert_eq!(other.formula.as_deref(), Some("#REF!*$A$2+#REF!"));
+        assert!(builder.paste_range("Data", "A1", "XFE1").is_err());
+        assert!(builder.paste_range("Data", "A1:B1", "XFD1").is_err());
+    }
+
+    #[test]
+    fn test_merge_cells() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        for reference in ["A1", "B1", "B2", "D2"] {
+            let text = Some(TypedValue::Text(reference.to_string()));
+            builder.set_value("Data", reference, text).unwrap();
+        }
+        let style = builder.register_style(StyleSpec::default());
+        builder.set_style("Data", "B2", Some(style)).unwrap();
+
+        builder.add_merge("Data", "b2:a1").unwrap();
+        let data = &builder.sheets[0].worksheet;
+        assert_eq!(data.merge_cells, vec!["A1:B2"]);
+        let refs: Vec<_> = data
+            .rows
+            .iter()
+            .flat_map(|row| row.cells.iter().map(|cell| cell.reference.as_str()))
+            .collect();
+        // B1 is gone, B2 keeps only its style
+        assert_eq!(refs, vec!["A1", "B2", "D2"]);
+        assert!(data.rows[1].cells[0].value.is_none());
+
+        assert!(builder.add_merge("Data", "B2:C3").is_err());
+        assert!(builder.add_merge("Data", "D4").is_err());
+        assert!(builder.remove_merge("Data", "A1:B3").is_err());
+        builder.remove_merge("Data", "A1:B2").unwrap();
+        builder.add_merge("Data", "B2:C3").unwrap();
+    }
+
+    #[test]
+    fn test_rename_sheet() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Totals").unwrap();
+        let formula = Some("SUM(data!A1:A3)+Totals!B1+B1+'Data:Totals'!C1".to_string());
+        builder.set_cell_formula("Totals", "A1", formula).unwrap();
+        let totals = &mut builder.sheets[1].worksheet;
+        totals.data_validations.push(DataValidation {
+            sqref: "B2".to_string(),
+            validation_type: Some("list".to_string()),
+            formula1: Some("Data!$A$1:$A$3".to_string()),
+            ..Default::default()
+        });
+        totals.conditional_formats.push(ConditionalFormat {
+            sqref: "B2".to_string(),
+            rules: vec![ConditionalRule {
+                rule_type: "expression".to_string(),
+                formulas: vec!["B2>MAX(Data!A:A)".to_string()],
+                ..Default::default()
+            }],
+        });
+        builder.defined_names.push(ParsedDefinedName {
+            name: "Input".to_string(),
+            value: "Data!$A$1".to_string(),
+            local_sheet_id: None,
+            hidden: false,
+        });
+
+        assert!(builder.rename("Data", "totals").is_err());
+        assert!(builder.rename("Data", "a:b").is_err());
+        builder.rename("data", "Raw Data").unwrap();
+        let names: Vec<_> = builder.sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Raw Data", "Totals"]);
+        let cell = &builder.sheets[1].worksheet.rows[0].cells[0];
+        assert_eq!(
+            cell.formula.as_deref(),
+            Some("SUM('Raw Data'!A1:A3)+Totals!B1+B1+'Raw Data:Totals'!C1")
+        );
+        assert_eq!(builder.defined_names[0].value, "'Raw Data'!$A$1");
+        let totals = &builder.sheets[1].worksheet;
+        assert_eq!(
+            totals.data_validations[0].formula1.as_deref(),
+            Some("'Raw Data'!$A$1:$A$3")
+        );
+        assert_eq!(
+            totals.conditional_formats[0].rules[0].formulas,
+            ["B2>MAX('Raw Data'!A:A)"]
+        );
+        // A different case of the same name is a rename too
+        builder.rename("Totals", "TOTALS").unwrap();
+    }
+
+    #[test]
+    fn test_remove_sheet() {
+        let mut builder = WorkbookBuilder::default();
+        for name in ["Data", "Scratch", "Totals"] {
+            builder.insert_sheet(name).unwrap();
+        }
+        let formula = "SUM(scratch!A1:A3)+Data!B1+'Data:Scratch'!C1+SUM(Scratch:Totals!D1)";
+        builder
+            .set_cell_formula("Totals", "A1", Some(formula.to_string()))
+            .unwrap();
+        builder
+            .set_cell_formula("Scratch", "A1", Some("Totals!A1".to_string()))
+            .unwrap();
+        builder.link("Totals", "B1", "#Scratch!A1", None).unwrap();
+        let totals = &mut builder.sheets[2].worksheet;
+        totals.data_validations.push(DataValidation {
+            sqref: "B2".to_string(),
+            validation_type: Some("list".to_string()),
+            formula1: Some("Scratch!$A$1:$A$3".to_string()),
+            ..Default::default()
+        });
+        #[cfg(feature = "charts")]
+        totals.charts.push(SheetChart {
+            chart_type: "line".to_string(),
+            series: vec![ChartSeries {
+                values: "Scratch!$B$1:$B$3".to_string(),
+                categories: Some("Data!$A$1:$A$3".to_string()),
+                ..Default::default()
+            }],
+            anchor: "D2".to_string(),
+            ..Default::default()
+        });
+        let name = |name: &str, value: &str, local_sheet_id| ParsedDefinedName {
+            name: name.to_string(),
+            value: value.to_string(),
+            local_sheet_id,
+            hidden: false,
+        };
+        builder.defined_names = vec![
+            name("Input", "Scratch!$A$1", None),
+            name("Local", "Scratch!$A$2", Some(1)),
+            name("Total", "Totals!$A$1", Some(2)),
+        ];
+
+        // A formula that can't be read stops the removal before any change
+        let data = &mut builder.sheets[0].worksheet;
+        data.rows.push(row(1, vec![cell("A1", None, "0")]));
+        data.rows[0].cells[0].formula = Some("Scratch!A1+'Oops".to_string());
+        assert!(builder.remove_sheet("Scratch").is_err());
+        assert_eq!(builder.sheets.len(), 3);
+        assert_eq!(builder.defined_names[0].value, "Scratch!$A$1");
+        builder.sheets[0].worksheet.rows.clear();
+
+        builder.remove_sheet("Scratch").unwrap();
+        let totals = &builder.sheets[1].worksheet;
+        assert_eq!(
+            totals.rows[0].cells[0].formula.as_deref(),
+            Some("SUM(#REF!)+Data!B1+Data!C1+SUM(Totals!D1)")
+        );
+        assert_eq!(totals.hyperlinks[0].location.as_deref(), Some("#REF!"));
+        assert_eq!(
+            totals.data_validations[0].formula1.as_deref(),
+            Some("#REF!")
+        );
+        #[cfg(feature = "charts")]
+        {
+            let series = &totals.charts[0].series[0];
+            assert_eq!(series.values, "#REF!");
+            assert_eq!(series.categories.as_deref(), Some("Data!$A$1:$A$3"));
+        }
+        let names: Vec<_> = builder
+            .defined_names
+            .iter()
+            .map(|name| (name.name.as_str(), name.value.as_str(), name.local_sheet_id))
+            .collect();
+        assert_eq!(
+            names,
+            [("Input", "#REF!", None), ("Total", "Totals!$A$1", Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_structure_changes() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Sum").unwrap();
+        for (reference, n) in [("A1", 1.0), ("A2", 2.0), ("B3", 3.0), ("C3", 4.0)] {
+            let value = Some(TypedValue::Number(n));
+            builder.set_value("Data", reference, value).unwrap();
+        }
+        let formula = |f: &str| Some(f.to_string());
+        builder
+            .set_cell_formula("Data", "C1", formula("SUM(A1:A2)+B3"))
+            .unwrap();
+        builder
+            .set_cell_formula("Sum", "A1", formula("Data!A2*Data!C3+A2"))
+            .unwrap();
+        let data = &mut builder.sheets[0].worksheet;
+        data.merge_cells = vec!["A1:C1".to_string(), "B3:C3".to_string()];
+        data.col_widths.insert(3, 20.0);
+        data.data_validations.push(DataValidation {
+            sqref: "B3:C3 A5".to_string(),
+            validation_type: Some("list".to_string()),
+            formula1: formula("$A$1:$A$2"),
+            ..Default::default()
+        });
+        data.conditional_formats.push(ConditionalFormat {
+            sqref: "C1:C3".to_string(),
+            rules: vec![ConditionalRule {
+                rule_type: "expression".to_string(),
+                formulas: vec!["$C$3>0".to_string()],
+                ..Default::default()
+            }],
+        });
+        data.auto_filter = Some(AutoFilter {
+            reference: "A1:C3".to_string(),
+            columns: [1, 2]
+                .map(|col_id| FilterColumn {
+                    col_id,
+                    ..Default::default()
+                })
+                .to_vec(),
+        });
+        builder.defined_names.push(ParsedDefinedName {
+            name: "Totals".to_string(),
+            value: "Data!$B$3:$C$3".to_string(),
+            local_sheet_id: None,
+            hidden: false,
+        });
+
+        builder
+            .change_structure("data", "insert_rows", 2, 2)
+            .unwrap();
+        builder
+            .change_structure("Data", "delete_cols", 2, 1)
+            .unwrap();
+
+        let data = &builder.sheets[0].worksheet;
+        let refs: Vec<_> = data
+            .rows
+            .iter()
+            .flat_map(|row| row.cells.iter().map(|cell| cell.reference.as_str()))
+            .collect();
+        assert_eq!(refs, vec!["A1", "B1", "A4", "B5"]);
+        assert_eq!(data.rows[1].row_num, 4);
+        assert_eq!(
+            data.rows[0].cells[1].formula.as_deref(),
+            Some("SUM(A1:A4)+#REF!")
+        );
+        assert_eq!(data.merge_cells, vec!["A1:B1"]);
+        assert_eq!(data.col_widths.get(&2), Some(&20.0));
+        let sum = &builder.sheets[1].worksheet.rows[0].cells[0];
+        assert_eq!(sum.formula.as_deref(), Some("Data!A4*Data!B5+A2"));
+        assert_eq!(builder.defined_names[0].value, "Data!$B$5:$B$5");
+        assert_eq!(data.data_validations[0].sqref, "B5 A7");
+        assert_eq!(
+            data.data_validations[0].formula1.as_deref(),
+            Some("$A$1:$A$4")
+        );
+        assert_eq!(data.conditional_formats[0].sqref, "B1:B5");
+        assert_eq!(data.conditional_formats[0].rules[0].formulas, ["$B$5>0"]);
+        let filter = data.auto_filter.as_ref().unwrap();
+        assert_eq!(filter.reference, "A1:B5");
+        let columns: Vec<_> = filter.columns.iter().map(|c| c.col_id).collect();
+        assert_eq!(columns, [1]);
+
+        assert!(builder
+            .change_structure("Data", "insert_rows", 0, 1)
+            .is_err());
+        let full = builder.change_structure("Data", "insert_rows", 1, MAX_ROWS - 4);
+        assert!(full.is_err());
+        assert_eq!(builder.sheets[0].worksheet.rows[0].row_num, 1);
+    }
+
+    #[test]
+    fn test_add_table() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        let text = |text: &str| Some(TypedValue::Text(text.to_string()));
+        builder.set_value("Data", "B2", text("Region")).unwrap();
+        builder.set_value("Data", "C2", text("Unit [kg]")).unwrap();
+        builder.set_value("Data", "D2", text("region")).unwrap();
+        for row in 3..=5 {
+            let amount = Some(TypedValue::Number(row as f64));
+            builder
+                .set_value("Data", &cell_ref(row, 3), amount)
+                .unwrap();
+        }
+        let total = |function: Option<&str>, label: Option<&str>| ColumnTotal {
+            function: function.map(str::to_string),
+            label: label.map(str::to_string),
+            formula: None,
+        };
+        let spec = TableSpec {
+            totals: vec![total(None, Some("Total")), total(Some("sum"), None)],
+            ..TableSpec::default()
+        };
+        assert_eq!(
+            builder.insert_table("Data", "B2:E6", spec).unwrap(),
+            "Table1"
+        );
+
+        // Overlaps, clashing names, merges and unknown functions are refused
+        let named = |name: &str| TableSpec {
+            name: Some(name.to_string()),
+            ..TableSpec::default()
+        };
+        assert!(builder
+            .insert_table("Data", "E6:F8", named("Other"))
+            .is_err());
+        assert!(builder
+            .insert_table("Data", "H1:I3", named("table1"))
+            .is_err());
+        assert!(builder.insert_table("Data", "H1:I3", named("A1")).is_err());
+        assert!(builder
+            .insert_table("Data", "H1:I1", named("Other"))
+            .is_err());
+        let spec = TableSpec {
+            totals: vec![total(Some("median"), None)],
+            ..TableSpec::default()
+        };
+        assert!(builder.insert_table("Data", "H1:I3", spec).is_err());
+        assert!(builder.add_merge("Data", "C4:D4").is_err());
+        assert_eq!(
+            builder.insert_table("Data", "H1:I3", TableSpec::default()),
+            Ok("Table2".into())
+        );
+
+        // Rows inside the table grow it; its header row and columns stay put
+        builder
+            .change_structure("Data", "insert_rows", 4, 1)
+            .unwrap();
+        assert_eq!(builder.tables[0].reference, "B2:E7");
+        assert!(builder
+            .change_structure("Data", "delete_rows", 2, 1)
+            .is_err());
+        assert!(builder
+            .change_structure("Data", "insert_cols", 3, 1)
+            .is_err());
+        builder.rename("Data", "Sales").unwrap();
+
+        let bytes = builder.write().unwrap();
+        let archive = ZipArchive::new(&bytes[..], ParseLimits::default()).unwrap();
+        let part = |name: &str| String::from_utf8(archive.read(name).unwrap().unwrap()).unwrap();
+        let table = parse_table_impl(&part("xl/tables/table1.xml")).unwrap();
+        assert_eq!(table.columns, ["Region", "Unit [kg]", "region2", "Column4"]);
+        assert_eq!(
+            (table.reference.as_str(), table.totals_row_count),
+            ("B2:E7", 1)
+        );
+        assert_eq!(table.totals[1].function.as_deref(), Some("sum"));
+        assert!(table.style.unwrap().show_row_stripes);
+        assert!(part("xl/worksheets/sheet1.xml").contains("<tableParts count=\"2\">"));
+        assert!(part("xl/worksheets/_rels/sheet1.xml.rels").contains("../tables/table2.xml"));
+        assert!(part("[Content_Types].xml").contains("/xl/tables/table2.xml"));
+
+        let data = read_workbook(&bytes, ParseLimits::default(), None).unwrap();
+        let workbook = Workbook::from_data(data);
+        let sheet = workbook.sheet_handle(Some("Sales")).unwrap();
+        let formula = sheet.cell_at("C7").unwrap().data().formula;
+        assert_eq!(
+            formula.as_deref(),
+            Some("SUBTOTAL(109,Table1[Unit '[kg']])")
+        );
+        let label = sheet.cell_at("B7").unwrap().data().value;
+        assert_eq!(label, text("Total"));
+    }
+
+    #[cfg(feature = "drawings")]
+    #[test]
+    fn test_add_image() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Report").unwrap();
+        builder.insert_sheet("Data").unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x40\0\0\0\x20".to_vec();
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        builder.insert_image("Report", png.clone(), "B2").unwrap();
+        builder
+            .insert_image("Report", png.clone(), "D3:F5")
+            .unwrap();
+        builder.insert_image("Data", png.clone(), "A1").unwrap();
+        assert!(builder
+            .insert_image("Report", b"GIF".to_vec(), "A1")
+            .is_err());
+        assert!(builder.insert_image("Report", png, "B0").is_err());
+
+        builder
+            .change_structure("Report", "insert_rows", 1, 2)
+            .unwrap();
+        builder
+            .change_structure("Report", "delete_cols", 5, 2)
+            .unwrap();
+        let anchors: Vec<_> = builder.sheets[0]
+            .worksheet
+            .images
+            .iter()
+            .map(|image| image.anchor.as_str())
+            .collect();
+        assert_eq!(anchors, ["B4", "D5:D7"]);
+
+        let bytes = builder.write().unwrap();
+        let archive = ZipArchive::new(&bytes[..], ParseLimits::default()).unwrap();
+        let part = |name: &str| String::from_utf8(archive.read(name).unwrap().unwrap()).unwrap();
+        assert!(part("xl/worksheets/sheet1.xml").contains("<drawing r:id=\"rId1\"/>"));
+        assert!(part("xl/worksheets/_rels/sheet1.xml.rels").contains("../drawings/drawing1.xml"));
+        let rels = part("xl/drawings/_rels/drawing1.xml.rels");
+        assert!(rels.contains("Id=\"rId2\""));
+        assert!(rels.contains("Target=\"../media/image2.png\""));
+        assert!(part("xl/drawings/_rels/drawing2.xml.rels").contains("../media/image3.png"));
+        assert!(archive.read("xl/media/image1.png").unwrap().is_some());
+        let types = part("[Content_Types].xml");
+        assert!(types.contains("<Default Extension=\"png\" ContentType=\"image/png\"/>"));
+        assert!(types.contains("PartName=\"/xl/drawings/drawing2.xml\""));
+    }
+
+    #[test]
+    fn test_defined_names_and_print_settings() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Q1 Sales").unwrap();
+        builder
+            .insert_name("Rates", "=Data!$B$2:$B$9", None)
+            .unwrap();
+        builder
+            .insert_name("rates", "'Q1 Sales'!$A$1", Some("q1 sales"))
+            .unwrap();
+        assert!(builder.insert_name("RATES", "1", None).is_err());
+        assert!(builder.insert_name("B2", "1", None).is_err());
+        assert!(builder.insert_name("_xlnm.Print_Area", "1", None).is_err());
+        assert!(builder.insert_name("Broken", "'Raw Data'B2", None).is_err());
+        assert!(builder.delete_name("Missing", None).is_err());
+
+        builder
+            .print_range("Q1 Sales", Some("A1:F40, H1:K40"))
+            .unwrap();
+        builder
+            .repeat_titles("Q1 Sales", Some("1:2"), Some("A"))
+            .unwrap();
+        builder.repeat_titles("Data", Some("$3"), None).unwrap();
+        builder.repeat_titles("Data", None, None).unwrap();
+        assert!(builder.print_range("Data", Some("A1:")).is_err());
+        assert!(builder.repeat_titles("Data", Some("0:1"), None).is_err());
+
+        // The settings move with the rows, like any other name
+        builder
+            .change_structure("Q1 Sales", "insert_rows", 1, 1)
+            .unwrap();
+        builder.delete_name("RATES", None).unwrap();
+        let names: Vec<_> = builder
+            .defined_names
+            .iter()
+            .map(|name| (name.name.as_str(), name.local_sheet_id, name.value.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("rates", Some(1), "'Q1 Sales'!$A$2"),
+                (
+                    PRINT_AREA,
+                    Some(1),
+                    "'Q1 Sales'!$A$2:$F$41,'Q1 Sales'!$H$2:$K$41"
+                ),
+                (PRINT_TITLES, Some(1), "'Q1 Sales'!$A:$A,'Q1 Sales'!$2:$3"),
+            ]
+        );
+    }
+
+    #[cfg(feature = "charts")]
+    #[test]
+    fn test_add_chart() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        builder.insert_sheet("Report").unwrap();
+        let chart = |chart_type: &str, values: &str, anchor: &str| SheetChart {
+            chart_type: chart_type.to_string(),
+            title: Some("Sales".to_string()),
+            series: vec![ChartSeries {
+                name: Some("2024".to_string()),
+                categories: Some("A2:A5".to_string()),
+                values: values.to_string(),
+            }],
+            anchor: anchor.to_string(),
+            legend: Some("r".to_string()),
+        };
+        builder
+            .insert_chart("Data", chart("column", "B2:B5", "D2:J15"))
+            .unwrap();
+        builder
+            .insert_chart("Report", chart("line", "Data!B2:B5", "A1"))
+            .unwrap();
+        assert!(builder
+            .insert_chart("Data", chart("area", "B2:B5", "D2"))
+            .is_err());
+        assert!(builder
+            .insert_chart("Data", chart("pie", "B2:", "D2"))
+            .is_err());
+
+        // Series ranges and anchors move with the cells and follow renames
+        builder
+            .change_structure("Data", "insert_rows", 1, 1)
+            .unwrap();
+        builder.rename("Data", "Q1 Sales").unwrap();
+        let series = &builder.sheets[0].worksheet.charts[0].series[0];
+        assert_eq!(series.values, "'Q1 Sales'!$B$3:$B$6");
+        assert_eq!(series.categories.as_deref(), Some("'Q1 Sales'!$A$3:$A$6"));
+        assert_eq!(builder.sheets[0].worksheet.charts[0].anchor, "D3:J16");
+        let series = &builder.sheets[1].worksheet.charts[0].series[0];
+        assert_eq!(series.values, "'Q1 Sales'!$B$3:$B$6");
+
+        let bytes = builder.write().unwrap();
+        let archive = ZipArchive::new(&bytes[..], ParseLimits::default()).unwrap();
+        let part = |name: &str| String::from_utf8(archive.read(name).unwrap().unwrap()).unwrap();
+        assert!(part("xl/charts/chart1.xml").contains("<c:barDir val=\"col\"/>"));
+        assert!(part("xl/charts/chart2.xml").contains("<c:lineChart>"));
+        assert!(part("xl/drawings/drawing1.xml").contains("<xdr:to><xdr:col>10</xdr:col>"));
+        assert!(part("xl/drawings/_rels/drawing2.xml.rels").contains("../charts/chart2.xml"));
+        assert!(part("xl/worksheets/sheet2.xml").contains("<drawing r:id=\"rId1\"/>"));
+        assert!(part("[Content_Types].xml").contains("PartName=\"/xl/charts/chart1.xml\""));
+    }
+
+    #[test]
+    fn test_protect_sheet() {
+        let mut builder = WorkbookBuilder::default();
+        builder.insert_sheet("Data").unwrap();
+        let options = SheetProtection {
+            sort: true,
+            ..Default::default()
+        };
+        let salt = [7; 16];
+        assert!(builder
+            .protect("Data", options.clone(), Some(""), &salt)
+            .is_err());
+        assert!(builder
+            .protect("Missing", options.clone(), None, &salt)
+            .is_err());
+        builder
+            .protect("Data", options, Some("secret"), &salt)
+            .unwrap();
+
+        let bytes = builder.write().unwrap();
+        let archive = ZipArchive::new(&bytes[..], ParseLimits::default()).unwrap();
+        let sheet =
+            String::from_utf8(archive.read("xl/worksheets/sheet1.xml").unwrap().unwrap()).unwrap();
+        assert!(
+            sheet.contains("<sheetData/><sheetProtection algorithmName=\"SHA-512\" hashValue=\"")
+        );
+        assert!(sheet.contains("saltValue=\"BwcHBwcHBwcHBwcHBwcHBw==\" spinCount=\"100000\""));
+        assert!(sheet.contains("objects=\"1\" scenarios=\"1\" sort=\"0\"/>"));
+    }
+}
@@ -0,0 +1,465 @@
+//! Compound File Binary (OLE2) container reader
+//!
+//! Legacy .xls workbooks and password-protected OOXML files are stored as
+//! compound files: a small FAT file system inside a single byte buffer.
+//! Streams can be read by path; writing produces a flat file of root-level
+//! streams, which is all encrypted packages need.
+
+const SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+const END_OF_CHAIN: u32 = 0xFFFF_FFFE;
+const FREE_SECT: u32 = 0xFFFF_FFFF;
+const NO_STREAM: u32 = 0xFFFF_FFFF;
+
+const TYPE_STORAGE: u8 = 1;
+const TYPE_STREAM: u8 = 2;
+const TYPE_ROOT: u8 = 5;
+
+/// Whether `data` starts with the compound file signature
+pub(crate) fn is_compound_file(data: &[u8]) -> bool {
+    data.starts_with(&SIGNATURE)
+}
+
+struct DirEntry {
+    name: String,
+    entry_type: u8,
+    left: u32,
+    right: u32,
+    child: u32,
+    start: u32,
+    size: u64,
+}
+
+/// A parsed compound file
+pub(crate) struct CompoundFile<'a> {
+    data: &'a [u8],
+    sector_size: usize,
+    mini_sector_size: usize,
+    mini_cutoff: u64,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    entries: Vec<DirEntry>,
+    mini_stream: Vec<u8>,
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u64(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+impl<'a> CompoundFile<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Result<Self, String> {
+        if !is_compound_file(data) {
+            return Err("Not a compound file".to_string());
+        }
+        let header_err = || "Truncated compound file header".to_string();
+
+        let sector_shift = read_u16(data, 0x1E).ok_or_else(header_err)?;
+        let mini_shift = read_u16(data, 0x20).ok_or_else(header_err)?;
+        if !(7..=16).contains(&sector_shift) || mini_shift >= sector_shift {
+            return Err("Invalid compound file sector size".to_string());
+        }
+
+        let mut cfb = CompoundFile {
+            data,
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_shift,
+            mini_cutoff: read_u32(data, 0x38).ok_or_else(header_err)? as u64,
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            entries: Vec::new(),
+            mini_stream: Vec::new(),
+        };
+
+        // The first 109 FAT sector locations live in the header, the rest in DIFAT sectors
+        let mut fat_sectors: Vec<u32> = (0..109)
+            .filter_map(|i| read_u32(data, 0x4C + i * 4))
+            .filter(|&sector| sector != FREE_SECT && sector != END_OF_CHAIN)
+            .collect();
+        let mut difat = read_u32(data, 0x44).ok_or_else(header_err)?;
+        let per_sector = cfb.sector_size / 4 - 1;
+        let mut guard = 0;
+        while difat != END_OF_CHAIN && difat != FREE_SECT && guard < cfb.sector_count() {
+            let sector = cfb.sector(difat)?;
+            fat_sectors.extend(
+                (0..per_sector)
+                    .filter_map(|i| read_u32(sector, i * 4))
+                    .filter(|&s| s != FREE_SECT && s != END_OF_CHAIN),
+            );
+            difat = read_u32(sector, per_sector * 4).unwrap_or(END_OF_CHAIN);
+            guard += 1;
+        }
+
+        for &sector in &fat_sectors {
+            let sector = cfb.sector(sector)?;
+            cfb.fat.extend(
+                sector
+                    .chunks_exact(4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+        }
+
+        let dir_start = read_u32(data, 0x30).ok_or_else(header_err)?;
+        let directory = cfb.read_chain(dir_start, None)?;
+        cfb.entries = directory
+            .chunks_exact(128)
+            .map(|entry| {
+                let name_len = (read_u16(entry, 64).unwrap_or(0) as usize).min(64);
+                let units: Vec<u16> = entry[..name_len.saturating_sub(2)]
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                    .collect();
+                DirEntry {
+                    name: String::from_utf16_lossy(&units),
+                    entry_type: entry[66],
+                    left: read_u32(entry, 68).unwrap_or(NO_STREAM),
+                    right: read_u32(entry, 72).unwrap_or(NO_STREAM),
+                    child: read_u32(entry, 76).unwrap_or(NO_STREAM),
+                    start: read_u32(entry, 116).unwrap_or(END_OF_CHAIN),
+                    size: read_u64(entry, 120).unwrap_or(0),
+                }
+            })
+            .collect();
+
+        let root = cfb
+            .entries
+            .first()
+            .filter(|entry| entry.entry_type == TYPE_ROOT)
+            .ok_or("Compound file has no root entry")?;
+        let (mini_start, mini_size) = (root.start, root.size);
+
+        let mini_fat_start = read_u32(data, 0x3C).ok_or_else(header_err)?;
+        if mini_fat_start != END_OF_CHAIN {
+            let mini_fat = cfb.read_chain(mini_fat_start, None)?;
+            cfb.mini_fat = mini_fat
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+        }
+        if mini_start != END_OF_CHAIN {
+            cfb.mini_stream = cfb.read_chain(mini_start, Some(mini_size))?;
+        }
+
+        Ok(cfb)
+    }
+
+    fn sector_count(&self) -> usize {
+        (self.data.len() / self.sector_size).saturating_sub(1)
+    }
+
+    fn sector(&self, index: u32) -> Result<&'a [u8], String> {
+        let start = (index as usize + 1) * self.sector_size;
+        self.data
+            .get(start..start + self.sector_size)
+            .ok_or_else(|| format!("Compound file sector {} out of range", index))
+    }
+
+    /// Follow a FAT chain, truncating to `size` when given
+    fn read_chain(&self, start: u32, size: Option<u64>) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        let mut sector = start;
+        let mut steps = 0;
+        while sector != END_OF_CHAIN {
+            if steps > self.fat.len() {
+                return Err("Cycle in compound file sector chain".to_string());
+            }
+            out.extend_from_slice(self.sector(sector)?);
+            if size.is_some_and(|size| out.len() as u64 >= size) {
+                break;
+            }
+            sector = *self
+                .fat
+                .get(sector as usize)
+                .ok_or("Compound file FAT entry out of range")?;
+            steps += 1;
+        }
+        if let Some(size) = size {
+            out.truncate(size as usize);
+        }
+        Ok(out)
+    }
+
+    /// Follow a mini FAT chain inside the mini stream
+    fn read_mini_chain(&self, start: u32, size: u64) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        let mut sector = start;
+        let mut steps = 0;
+        while sector != END_OF_CHAIN && (out.len() as u64) < size {
+            if steps > self.mini_fat.len() {
+                return Err("Cycle in compound file mini sector chain".to_string());
+            }
+            let begin = sector as usize * self.mini_sector_size;
+            let chunk = self
+                .mini_stream
+                .get(begin..begin + self.mini_sector_size)
+                .ok_or("Compound file mini sector out of range")?;
+            out.extend_from_slice(chunk);
+            sector = *self
+                .mini_fat
+                .get(sector as usize)
+                .ok_or("Compound file mini FAT entry out of range")?;
+            steps += 1;
+        }
+        out.truncate(size as usize);
+        Ok(out)
+    }
+
+    /// Find a child of a storage by name (case-insensitive)
+    fn find_child(&self, storage: usize, name: &str) -> Option<usize> {
+        let mut stack = vec![self.entries.get(storage)?.child];
+        let mut visited = 0;
+        while let Some(id) = stack.pop() {
+            if id == NO_STREAM || visited > self.entries.len() {
+                continue;
+            }
+            visited += 1;
+            let entry = self.entries.get(id as usize)?;
+            if entry.name.eq_ignore_ascii_case(name) {
+                return Some(id as usize);
+            }
+            stack.push(entry.left);
+            stack.push(entry.right);
+        }
+        None
+    }
+
+    fn resolve(&self, path: &str) -> Option<usize> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(0, |storage, part| self.find_child(storage, part))
+    }
+
+    /// Read a stream by its `/`-separated path from the root storage
+    pub(crate) fn read_stream(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let entry = match self.resolve(path).and_then(|id| self.entries.get(id)) {
+            Some(entry) if entry.entry_type == TYPE_STREAM => entry,
+            Some(entry) if entry.entry_type == TYPE_STORAGE => {
+                return Err(format!("{} is a storage, not a stream", path))
+            }
+            _ => return Ok(None),
+        };
+
+        if entry.size < self.mini_cutoff {
+            self.read_mini_chain(entry.start, entry.size).map(Some)
+        } else {
+            self.read_chain(entry.start, Some(entry.size)).map(Some)
+        }
+    }
+}
+
+const FAT_SECT: u32 = 0xFFFF_FFFD;
+const DIFAT_SECT: u32 = 0xFFFF_FFFC;
+
+const SECTOR_SIZE: usize = 512;
+const MINI_SECTOR_SIZE: usize = 64;
+const MINI_STREAM_CUTOFF: usize = 4096;
+const ENTRIES_PER_FAT_SECTOR: usize = SECTOR_SIZE / 4;
+
+/// Directory order: shorter names first, then case-insensitive comparison
+fn compare_names(a: &str, b: &str) -> std::cmp::Ordering {
+    let (a, b) = (a.to_uppercase(), b.to_uppercase());
+    a.encode_utf16()
+        .count()
+        .cmp(&b.encode_utf16().count())
+        .then_with(|| a.encode_utf16().cmp(b.encode_utf16()))
+}
+
+/// Append `data` as a sector chain, returning its start sector
+fn push_chain(sectors: &mut Vec<u8>, fat: &mut Vec<u32>, data: &[u8], sector_size: usize) -> u32 {
+    if data.is_empty() {
+        return END_OF_CHAIN;
+    }
+    let start = fat.len() as u32;
+    let count = data.len().div_ceil(sector_size);
+    for i in 0..count {
+        fat.push(if i + 1 == count {
+            END_OF_CHAIN
+        } else {
+            start + i as u32 + 1
+        });
+    }
+    sectors.extend_from_slice(data);
+    sectors.resize(fat.len() * sector_size, 0);
+    start
+}
+
+/// Link `ids[range]` into a balanced binary tree, returning its root entry
+fn build_tree(ids: &[u32], links: &mut [(u32, u32)]) -> u32 {
+    if ids.is_empty() {
+        return NO_STREAM;
+    }
+    let mid = ids.len() / 2;
+    let left = build_tree(&ids[..mid], links);
+    let right = build_tree(&ids[mid + 1..], links);
+    links[ids[mid] as usize] = (left, right);
+    ids[mid]
+}
+
+/// Write a version 3 compound file holding the given root-level streams
+pub(crate) fn write_compound_file(streams: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut streams: Vec<(&str, &[u8])> = streams.to_vec();
+    streams.sort_by(|a, b| compare_names(a.0, b.0));
+
+    // Small streams share the mini stream, which lives in the root entry's chain
+    let mut mini_stream = Vec::new();
+    let mut mini_fat = Vec::new();
+    let mut sectors = Vec::new();
+    let mut fat = Vec::new();
+    let starts: Vec<u32> = streams
+        .iter()
+        .map(|(_, data)| {
+            if data.len() < MINI_STREAM_CUTOFF {
+                push_chain(&mut mini_stream, &mut mini_fat, data, MINI_SECTOR_SIZE)
+            } else {
+                push_chain(&mut sectors, &mut fat, data, SECTOR_SIZE)
+            }
+        })
+        .collect();
+
+    let mini_stream_start = push_chain(&mut sectors, &mut fat, &mini_stream, SECTOR_SIZE);
+    let mini_fat_bytes: Vec<u8> = mini_fat.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let mini_fat_start = push_chain(&mut sectors, &mut fat, &mini_fat_bytes, SECTOR_SIZE);
+
+    let ids: Vec<u32> = (1..=streams.len() as u32).collect();
+    let mut links = vec![(NO_STREAM, NO_STREAM); streams.len() + 1];
+    let root_child = build_tree(&ids, &mut links);
+
+    let mut directory = Vec::new();
+    let mut push_entry = |name: &str, entry_type: u8, links: (u32, u32), child, start, size| {
+        let mut entry = [0u8; 128];
+        let units: Vec<u16> = name.encode_utf16().take(31).collect();
+        for (i, unit) in units.iter().enumerate() {
+            entry[i * 2..i * 2 + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        entry[64..66].copy_from_slice(&((units.len() as u16 + 1) * 2).to_le_bytes());
+        entry[66] = entry_type;
+        entry[67] = 1; // black
+        entry[68..72].copy_from_slice(&links.0.to_le_bytes());
+        entry[72..76].copy_from_slice(&links.1.to_le_bytes());
+        entry[76..80].copy_from_slice(&u32::to_le_bytes(child));
+        entry[116..120].copy_from_slice(&u32::to_le_bytes(start));
+        entry[120..128].copy_from_slice(&u64::to_le_bytes(size));
+        directory.extend_from_slice(&entry);
+    };
+    push_entry(
+        "Root Entry",
+        TYPE_ROOT,
+        (NO_STREAM, NO_STREAM),
+        root_child,
+        mini_stream_start,
+        mini_stream.len() as u64,
+    );
+    for (i, (name, data)) in streams.iter().enumerate() {
+        push_entry(
+            name,
+            TYPE_STREAM,
+            links[i + 1],
+            NO_STREAM,
+            starts[i],
+            data.len() as u64,
+        );
+    }
+    // Unused entries in the last directory sector point nowhere
+    while directory.len() % SECTOR_SIZE != 0 {
+        let mut entry = [0u8; 128];
+        entry[68..80].fill(0xFF);
+        directory.extend_from_slice(&entry);
+    }
+    let directory_start = push_chain(&mut sectors, &mut fat, &directory, SECTOR_SIZE);
+
+    // FAT sectors must also describe themselves and any DIFAT sectors
+    let data_sectors = fat.len();
+    let mut fat_count = 0;
+    let mut difat_count = 0;
+    while fat_count * ENTRIES_PER_FAT_SECTOR < data_sectors + fat_count + difat_count {
+        fat_count += 1;
+        difat_count = fat_count
+            .saturating_sub(109)
+            .div_ceil(ENTRIES_PER_FAT_SECTOR - 1);
+    }
+    let fat_start = fat.len() as u32;
+    fat.extend(std::iter::repeat_n(FAT_SECT, fat_count));
+    let difat_start = fat.len() as u32;
+    fat.extend(std::iter::repeat_n(DIFAT_SECT, difat_count));
+    fat.resize(fat_count * ENTRIES_PER_FAT_SECTOR, FREE_SECT);
+
+    let fat_sectors: Vec<u32> = (0..fat_count as u32).map(|i| fat_start + i).collect();
+
+    let mut header = vec![0u8; SECTOR_SIZE];
+    header[..8].copy_from_slice(&SIGNATURE);
+    header[0x18..0x1A].copy_from_slice(&0x3Eu16.to_le_bytes());
+    header[0x1A..0x1C].copy_from_slice(&3u16.to_le_bytes());
+    header[0x1C..0x1E].copy_from_slice(&0xFFFEu16.to_le_bytes());
+    header[0x1E..0x20].copy_from_slice(&9u16.to_le_bytes());
+    header[0x20..0x22].copy_from_slice(&6u16.to_le_bytes());
+    header[0x2C..0x30].copy_from_slice(&(fat_count as u32).to_le_bytes());
+    header[0x30..0x34].copy_from_slice(&directory_start.to_le_bytes());
+    header[0x38..0x3C].copy_from_slice(&(MINI_STREAM_CUTOFF as u32).to_le_bytes());
+    header[0x3C..0x40].copy_from_slice(&mini_fat_start.to_le_bytes());
+    header[0x40..0x44]
+        .copy_from_slice(&(mini_fat_bytes.len().div_ceil(SECTOR_SIZE) as u32).to_le_bytes());
+    let first_difat = if difat_count > 0 {
+        difat_start
+    } else {
+        END_OF_CHAIN
+    };
+    header[0x44..0x48].copy_from_slice(&first_difat.to_le_bytes());
+    header[0x48..0x4C].copy_from_slice(&(difat_count as u32).to_le_bytes());
+    for i in 0..109 {
+        let sector = fat_sectors.get(i).copied().unwrap_or(FREE_SECT);
+        header[0x4C + i * 4..0x50 + i * 4].copy_from_slice(&sector.to_le_bytes());
+    }
+
+    let mut out = header;
+    out.extend_from_slice(&sectors);
+    out.extend(fat.iter().flat_map(|v| v.to_le_bytes()));
+    // Each DIFAT sector lists further FAT sectors and ends with the next DIFAT sector
+    let mut remaining = fat_sectors.iter().skip(109);
+    for i in 0..difat_count {
+        for _ in 0..ENTRIES_PER_FAT_SECTOR - 1 {
+            let sector = remaining.next().copied().unwrap_or(FREE_SECT);
+            out.extend_from_slice(&sector.to_le_bytes());
+        }
+        let next = if i + 1 < difat_count {
+            difat_start + i as u32 + 1
+        } else {
+            END_OF_CHAIN
+        };
+        out.extend_from_slice(&next.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_streams() {
+        let big: Vec<u8> = (0..5000u32).map(|i| i as u8).collect();
+        let file = write_compound_file(&[("Workbook", &big), ("Small", b"hello"), ("Empty", b"")]);
+
+        let cfb = CompoundFile::new(&file).unwrap();
+        assert_eq!(cfb.read_stream("Workbook").unwrap().unwrap(), big);
+        assert_eq!(cfb.read_stream("/small").unwrap().unwrap(), b"hello");
+        assert_eq!(cfb.read_stream("Empty").unwrap().unwrap(), b"");
+        assert!(cfb.read_stream("Missing").unwrap().is_none());
+        assert!(CompoundFile::new(b"PK\x03\x04").is_err());
+    }
+
+    #[test]
+    fn test_write_large_file() {
+        // Enough sectors to need more than 109 FAT sectors, and so a DIFAT chain
+        let big = vec![0xA5u8; 110 * ENTRIES_PER_FAT_SECTOR * SECTOR_SIZE];
+        let file = write_compound_file(&[("Big", &big)]);
+        let cfb = CompoundFile::new(&file).unwrap();
+        assert_eq!(cfb.read_stream("Big").unwrap().unwrap().len(), big.len());
+    }
+}
@@ -0,0 +1,249 @@
+//! Charts for XLSX export
+//!
+//! Each chart is a DrawingML chart part, `xl/charts/chartN.xml`, placed on
+//! its sheet by a graphic frame in the sheet's drawing. Series point at
+//! ranges of cells rather than carrying cached values, so Excel reads the
+//! numbers from the sheet when it opens the file.
+
+use crate::formula::format_sheet;
+use crate::reference::{col_to_letters, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::{push_escaped, NS_RELATIONSHIPS, XML_DECLARATION};
+use crate::{ChartSeries, SheetChart};
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+pub(crate) const NS_CHART: &str = "http://schemas.openxmlformats.org/drawingml/2006/chart";
+const NS_DRAWINGML: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const LEGEND_POSITIONS: [&str; 5] = ["r", "l", "t", "b", "tr"];
+/// Ids joining a chart's category and value axes
+const CATEGORY_AXIS: u32 = 500_000_001;
+const VALUE_AXIS: u32 = 500_000_002;
+
+/// Check that a chart has a known type, at least one series and a legend
+/// position Excel accepts
+pub(crate) fn check_chart(chart: &SheetChart) -> Result<(), String> {
+    if !matches!(chart.chart_type.as_str(), "column" | "bar" | "line" | "pie") {
+        return Err(format!("Unknown chart type: {}", chart.chart_type));
+    }
+    if chart.series.is_empty() {
+        return Err("A chart needs at least one series".to_string());
+    }
+    if let Some(legend) = &chart.legend {
+        if !LEGEND_POSITIONS.contains(&legend.as_str()) {
+            return Err(format!("Unknown legend position: {}", legend));
+        }
+    }
+    Ok(())
+}
+
+/// `reference` as a chart writes it: sheet-qualified, on `sheet` when it
+/// names none, with absolute cells
+pub(crate) fn series_ref(reference: &str, sheet: &str) -> Option<String> {
+    let (prefix, range) = match reference.rsplit_once('!') {
+        Some((prefix, range)) => (format!("{}!", prefix), range),
+        None => (format_sheet(sheet, None), reference),
+    };
+    let (r1, c1, r2, c2) = range_bounds(range)?;
+    let cell = |row, col| format!("${}${}", col_to_letters(col), row);
+    if (r1, c1) == (r2, c2) {
+        Some(format!("{}{}", prefix, cell(r1, c1)))
+    } else {
+        Some(format!("{}{}:{}", prefix, cell(r1, c1), cell(r2, c2)))
+    }
+}
+
+/// Serialize a chart part (`xl/charts/chartN.xml`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_chart(chart: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let chart: SheetChart = serde_wasm_bindgen::from_value(chart)?;
+        write_chart_impl(&chart).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn write_chart_impl(chart: &SheetChart) -> Result<String, String> {
+    check_chart(chart)?;
+    let kind = chart.chart_type.as_str();
+
+    let mut out = String::with_capacity(1024 + chart.series.len() * 256);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<c:chartSpace xmlns:c=\"{}\" xmlns:a=\"{}\" xmlns:r=\"{}\">\
+         <c:roundedCorners val=\"0\"/><c:chart>",
+        NS_CHART, NS_DRAWINGML, NS_RELATIONSHIPS
+    );
+    match &chart.title {
+        Some(title) => {
+            out.push_str("<c:title><c:tx><c:rich><a:bodyPr/><a:p><a:r><a:t>");
+            push_escaped(&mut out, title);
+            out.push_str("</a:t></a:r></a:p></c:rich></c:tx><c:overlay val=\"0\"/></c:title>");
+            out.push_str("<c:autoTitleDeleted val=\"0\"/>");
+        }
+        None => out.push_str("<c:autoTitleDeleted val=\"1\"/>"),
+    }
+
+    out.push_str("<c:plotArea><c:layout/>");
+    match kind {
+        "pie" => out.push_str("<c:pieChart><c:varyColors val=\"1\"/>"),
+        "line" => {
+            out.push_str("<c:lineChart><c:grouping val=\"standard\"/><c:varyColors val=\"0\"/>")
+        }
+        _ => {
+            let direction = if kind == "bar" { "bar" } else { "col" };
+            let _ = write!(
+                out,
+                "<c:barChart><c:barDir val=\"{}\"/><c:grouping val=\"clustered\"/>\
+                 <c:varyColors val=\"0\"/>",
+                direction
+            );
+        }
+    }
+    for (i, series) in chart.series.iter().enumerate() {
+        write_series(&mut out, kind, i, series);
+    }
+    match kind {
+        "pie" => out.push_str("<c:firstSliceAng val=\"0\"/></c:pieChart>"),
+        "line" => {
+            out.push_str("<c:marker val=\"1\"/>");
+            let _ = write!(
+                out,
+                "<c:axId val=\"{}\"/><c:axId val=\"{}\"/></c:lineChart>",
+                CATEGORY_AXIS, VALUE_AXIS
+            );
+        }
+        _ => {
+            out.push_str("<c:gapWidth val=\"150\"/>");
+            let _ = write!(
+                out,
+                "<c:axId val=\"{}\"/><c:axId val=\"{}\"/></c:barChart>",
+                CATEGORY_AXIS, VALUE_AXIS
+            );
+        }
+    }
+    if kind != "pie" {
+        // Horizontal bars put the categories up the left side
+        let (category_pos, value_pos) = if kind == "bar" {
+            ("l", "b")
+        } else {
+            ("b", "l")
+        };
+        let _ = write!(
+            out,
+            "<c:catAx><c:axId val=\"{cat}\"/><c:scaling><c:orientation val=\"minMax\"/>\
+             </c:scaling><c:delete val=\"0\"/><c:axPos val=\"{}\"/>\
+             <c:majorTickMark val=\"out\"/><c:minorTickMark val=\"none\"/>\
+             <c:tickLblPos val=\"nextTo\"/><c:crossAx val=\"{val}\"/>\
+             <c:crosses val=\"autoZero\"/><c:auto val=\"1\"/><c:lblAlgn val=\"ctr\"/>\
+             <c:lblOffset val=\"100\"/></c:catAx>\
+             <c:valAx><c:axId val=\"{val}\"/><c:scaling><c:orientation val=\"minMax\"/>\
+             </c:scaling><c:delete val=\"0\"/><c:axPos val=\"{}\"/><c:majorGridlines/>\
+             <c:numFmt formatCode=\"General\" sourceLinked=\"1\"/>\
+             <c:majorTickMark val=\"out\"/><c:minorTickMark val=\"none\"/>\
+             <c:tickLblPos val=\"nextTo\"/><c:crossAx val=\"{cat}\"/>\
+             <c:crosses val=\"autoZero\"/><c:crossBetween val=\"between\"/></c:valAx>",
+            category_pos,
+            value_pos,
+            cat = CATEGORY_AXIS,
+            val = VALUE_AXIS
+        );
+    }
+    out.push_str("</c:plotArea>");
+
+    if let Some(legend) = &chart.legend {
+        let _ = write!(
+            out,
+            "<c:legend><c:legendPos val=\"{}\"/><c:overlay val=\"0\"/></c:legend>",
+            legend
+        );
+    }
+    out.push_str(
+        "<c:plotVisOnly val=\"1\"/><c:dispBlanksAs val=\"gap\"/></c:chart></c:chartSpace>",
+    );
+    Ok(out)
+}
+
+fn write_series(out: &mut String, kind: &str, index: usize, series: &ChartSeries) {
+    let _ = write!(
+        out,
+        "<c:ser><c:idx val=\"{}\"/><c:order val=\"{}\"/>",
+        index, index
+    );
+    if let Some(name) = &series.name {
+        out.push_str("<c:tx><c:v>");
+        push_escaped(out, name);
+        out.push_str("</c:v></c:tx>");
+    }
+    match kind {
+        "line" => out.push_str("<c:marker><c:symbol val=\"none\"/></c:marker>"),
+        "pie" => {}
+        _ => out.push_str("<c:invertIfNegative val=\"0\"/>"),
+    }
+    if let Some(categories) = &series.categories {
+        out.push_str("<c:cat><c:strRef><c:f>");
+        push_escaped(out, categories);
+        out.push_str("</c:f></c:strRef></c:cat>");
+    }
+    out.push_str("<c:val><c:numRef><c:f>");
+    push_escaped(out, &series.values);
+    out.push_str("</c:f></c:numRef></c:val>");
+    if kind == "line" {
+        out.push_str("<c:smooth val=\"0\"/>");
+    }
+    out.push_str("</c:ser>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_chart() {
+        assert_eq!(
+            series_ref("b2:B13", "Q1 Sales").as_deref(),
+            Some("'Q1 Sales'!$B$2:$B$13")
+        );
+        assert_eq!(
+            series_ref("Data!$A2", "Other").as_deref(),
+            Some("Data!$A$2")
+        );
+        assert_eq!(series_ref("Data!B", "Data"), None);
+
+        let series = ChartSeries {
+            name: Some("Sales & returns".to_string()),
+            categories: Some("Data!$A$2:$A$5".to_string()),
+            values: "Data!$B$2:$B$5".to_string(),
+        };
+        let mut chart = SheetChart {
+            chart_type: "bar".to_string(),
+            title: Some("By region".to_string()),
+            series: vec![series],
+            anchor: "D2:J16".to_string(),
+            legend: Some("b".to_string()),
+        };
+        let xml = write_chart_impl(&chart).unwrap();
+        assert!(xml.contains("<a:t>By region</a:t>"));
+        assert!(xml.contains("<c:barDir val=\"bar\"/>"));
+        assert!(xml.contains(
+            "<c:tx><c:v>Sales &amp; returns</c:v></c:tx><c:invertIfNegative val=\"0\"/>\
+             <c:cat><c:strRef><c:f>Data!$A$2:$A$5</c:f></c:strRef></c:cat>\
+             <c:val><c:numRef><c:f>Data!$B$2:$B$5</c:f></c:numRef></c:val>"
+        ));
+        assert!(xml.contains("<c:legendPos val=\"b\"/>"));
+
+        chart.chart_type = "pie".to_string();
+        chart.title = None;
+        let xml = write_chart_impl(&chart).unwrap();
+        assert!(xml.contains("<c:autoTitleDeleted val=\"1\"/>"));
+        assert!(xml.contains("<c:pieChart>") && !xml.contains("<c:catAx>"));
+        chart.legend = Some("right".to_string());
+        assert!(write_chart_impl(&chart).is_err());
+        chart.legend = None;
+        chart.chart_type = "radar".to_string();
+        assert!(write_chart_impl(&chart).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Columnar (structure-of-arrays) worksheet output
+//!
+//! Instead of one JS object per row and cell, the cells come back as
+//! parallel typed arrays. Crossing the wasm boundary is then a handful of
+//! memory copies rather than millions of object and string constructions.
+//!
+//! The `*_view` methods skip even those copies and return typed arrays over
+//! wasm memory. A view is only valid until the worksheet is `free()`d or
+//! wasm memory grows, which any later call into the module may cause; read
+//! it straight away, or `.slice()` it to keep a copy.
+
+use crate::context::ParserScratch;
+use crate::reference::parse_cell_ref;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{parse_worksheet_with, ParsedCell, ParsedWorksheet, WorksheetParseOptions};
+#[cfg(feature = "wasm")]
+use js_sys::{Float64Array, Uint16Array, Uint32Array, Uint8Array};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+// Value type tags, as listed on `ColumnarWorksheet::types`
+pub(crate) const TYPE_EMPTY: u8 = 0;
+pub(crate) const TYPE_NUMBER: u8 = 1;
+/// `numbers` holds the shared string index
+pub(crate) const TYPE_SHARED_STRING: u8 = 2;
+/// The value is in the string data
+pub(crate) const TYPE_STRING: u8 = 3;
+/// `numbers` holds 1 or 0
+pub(crate) const TYPE_BOOLEAN: u8 = 4;
+/// The error code is in the string data
+pub(crate) const TYPE_ERROR: u8 = 5;
+
+/// Worksheet cells as parallel arrays, one entry per cell in document order
+///
+/// Text for cell `i` is the UTF-8 slice `string_data[string_offsets[i]..string_offsets[i + 1]]`
+/// (empty for non-text cells); formulas are laid out the same way.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Default)]
+pub struct ColumnarWorksheet {
+    rows: Vec<u32>,
+    cols: Vec<u16>,
+    types: Vec<u8>,
+    numbers: Vec<f64>,
+    styles: Vec<u32>,
+    string_data: Vec<u8>,
+    string_offsets: Vec<u32>,
+    formula_data: Vec<u8>,
+    formula_offsets: Vec<u32>,
+    /// Columns with a custom width, ascending, paired with `widths`
+    width_cols: Vec<u32>,
+    widths: Vec<f64>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl ColumnarWorksheet {
+    /// Number of cells
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.types.len()
+    }
+
+    /// 1-based row of each cell
+    #[wasm_bindgen(getter)]
+    pub fn rows(&self) -> Vec<u32> {
+        self.rows.clone()
+    }
+
+    /// 1-based column of each cell
+    #[wasm_bindgen(getter)]
+    pub fn cols(&self) -> Vec<u16> {
+        self.cols.clone()
+    }
+
+    /// Value type tag of each cell: 0 empty, 1 number, 2 shared string,
+    /// 3 string, 4 boolean, 5 error
+    #[wasm_bindgen(getter)]
+    pub fn types(&self) -> Vec<u8> {
+        self.types.clone()
+    }
+
+    /// Numeric payload: the number, shared string index or boolean; NaN otherwise
+    #[wasm_bindgen(getter)]
+    pub fn numbers(&self) -> Vec<f64> {
+        self.numbers.clone()
+    }
+
+    /// Style (cellXfs) index of each cell, 0 when unset
+    #[wasm_bindgen(getter)]
+    pub fn styles(&self) -> Vec<u32> {
+        self.styles.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn string_data(&self) -> Vec<u8> {
+        self.string_data.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn string_offsets(&self) -> Vec<u32> {
+        self.string_offsets.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn formula_data(&self) -> Vec<u8> {
+        self.formula_data.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn formula_offsets(&self) -> Vec<u32> {
+        self.formula_offsets.clone()
+    }
+
+    /// 1-based columns that have a custom width
+    #[wasm_bindgen(getter)]
+    pub fn width_cols(&self) -> Vec<u32> {
+        self.width_cols.clone()
+    }
+
+    /// Width of each column in `width_cols`
+    #[wasm_bindgen(getter)]
+    pub fn widths(&self) -> Vec<f64> {
+        self.widths.clone()
+    }
+
+    // Views borrow the vectors above in place. The vectors are never
+    // modified after parsing, so a view stays valid until `free()` or
+    // memory growth, as described in the module docs.
+
+    /// `rows` without copying; see the module docs for how long it is valid
+    pub fn rows_view(&self) -> Uint32Array {
+        unsafe { Uint32Array::view(&self.rows) }
+    }
+
+    /// `cols` without copying
+    pub fn cols_view(&self) -> Uint16Array {
+        unsafe { Uint16Array::view(&self.cols) }
+    }
+
+    /// `types` without copying
+    pub fn types_view(&self) -> Uint8Array {
+        unsafe { Uint8Array::view(&self.types) }
+    }
+
+    /// `numbers` without copying
+    pub fn numbers_view(&self) -> Float64Array {
+        unsafe { Float64Array::view(&self.numbers) }
+    }
+
+    /// `styles` without copying
+    pub fn styles_view(&self) -> Uint32Array {
+        unsafe { Uint32Array::view(&self.styles) }
+    }
+
+    /// `string_data` without copying
+    pub fn string_data_view(&self) -> Uint8Array {
+        unsafe { Uint8Array::view(&self.string_data) }
+    }
+
+    /// `string_offsets` without copying
+    pub fn string_offsets_view(&self) -> Uint32Array {
+        unsafe { Uint32Array::view(&self.string_offsets) }
+    }
+
+    /// `formula_data` without copying
+    pub fn formula_data_view(&self) -> Uint8Array {
+        unsafe { Uint8Array::view(&self.formula_data) }
+    }
+
+    /// `formula_offsets` without copying
+    pub fn formula_offsets_view(&self) -> Uint32Array {
+        unsafe { Uint32Array::view(&self.formula_offsets) }
+    }
+
+    /// `width_cols` without copying
+    pub fn width_cols_view(&self) -> Uint32Array {
+        unsafe { Uint32Array::view(&self.width_cols) }
+    }
+
+    /// `widths` without copying
+    pub fn widths_view(&self) -> Float64Array {
+        unsafe { Float64Array::view(&self.widths) }
+    }
+}
+
+/// Parse worksheet XML into columnar arrays
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_columnar(xml: &str) -> Result<ColumnarWorksheet, JsValue> {
+    trap(|| {
+        Ok(parse_worksheet_columnar_with(
+            xml,
+            &mut ParserScratch::default(),
+        ))
+    })
+}
+
+pub(crate) fn parse_worksheet_columnar_with(
+    xml: &str,
+    scratch: &mut ParserScratch,
+) -> ColumnarWorksheet {
+    to_columnar(&parse_worksheet_with(
+        xml,
+        scratch,
+        &WorksheetParseOptions::default(),
+    ))
+}
+
+/// Type tag, numeric payload and text of a cell
+fn classify(cell: &ParsedCell) -> (u8, f64, &str) {
+    let value = match cell.value.as_deref() {
+        Some(value) => value,
+        None => return (TYPE_EMPTY, f64::NAN, ""),
+    };
+    match cell.cell_type.as_deref() {
+        Some("s") => match value.parse::<u32>() {
+            Ok(index) => (TYPE_SHARED_STRING, index as f64, ""),
+            Err(_) => (TYPE_EMPTY, f64::NAN, ""),
+        },
+        Some("b") => {
+            let truthy = value == "1" || value.eq_ignore_ascii_case("true");
+            (TYPE_BOOLEAN, if truthy { 1.0 } else { 0.0 }, "")
+        }
+        Some("e") => (TYPE_ERROR, f64::NAN, value),
+        Some("str") | Some("inlineStr") => (TYPE_STRING, f64::NAN, value),
+        _ => match value.parse::<f64>() {
+            Ok(number) => (TYPE_NUMBER, number, ""),
+            Err(_) if value.is_empty() => (TYPE_EMPTY, f64::NAN, ""),
+            Err(_) => (TYPE_STRING, f64::NAN, value),
+        },
+    }
+}
+
+fn to_columnar(worksheet: &ParsedWorksheet) -> ColumnarWorksheet {
+    let count = worksheet.rows.iter().map(|row| row.cells.len()).sum();
+    let mut out = ColumnarWorksheet {
+        rows: Vec::with_capacity(count),
+        cols: Vec::with_capacity(count),
+        types: Vec::with_capacity(count),
+        numbers: Vec::with_capacity(count),
+        styles: Vec::with_capacity(count),
+        string_offsets: Vec::with_capacity(count + 1),
+        formula_offsets: Vec::with_capacity(count + 1),
+        ..Default::default()
+    };
+    out.string_offsets.push(0);
+    out.formula_offsets.push(0);
+
+    for cell in worksheet.rows.iter().flat_map(|row| &row.cells) {
+        let Some((row, col)) = parse_cell_ref(&cell.reference) else {
+            continue;
+        };
+        let (tag, number, text) = classify(cell);
+        out.rows.push(row);
+        out.cols.push(col as u16);
+        out.types.push(tag);
+        out.numbers.push(number);
+        out.styles.push(cell.style_index.unwrap_or(0));
+        out.string_data.extend_from_slice(text.as_bytes());
+        out.string_offsets.push(out.string_data.len() as u32);
+        if let Some(formula) = &cell.formula {
+            out.formula_data.extend_from_slice(formula.as_bytes());
+        }
+        out.formula_offsets.push(out.formula_data.len() as u32);
+    }
+
+    let mut widths: Vec<_> = worksheet.col_widths.iter().collect();
+    widths.sort_by_key(|(&col, _)| col);
+    out.width_cols = widths.iter().map(|(&col, _)| col).collect();
+    out.widths = widths.iter().map(|(_, &width)| width).collect();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columnar() {
+        let xml = r#"<worksheet><cols><col min="3" max="3" width="20" customWidth="1"/>
+            <col min="1" max="1" width="9.5" customWidth="1"/></cols><sheetData><row r="2">
+            <c r="A2" t="s"><v>4</v></c>
+            <c r="C2" s="1"><f>A1*2</f><v>2.5</v></c>
+            <c r="D2" t="str"><v>héllo</v></c>
+            <c r="E2" t="b"><v>1</v></c>
+        </row></sheetData></worksheet>"#;
+        let sheet = parse_worksheet_columnar_with(xml, &mut ParserScratch::default());
+        assert_eq!(sheet.rows, vec![2, 2, 2, 2]);
+        assert_eq!(sheet.cols, vec![1, 3, 4, 5]);
+        assert_eq!(
+            sheet.types,
+            vec![TYPE_SHARED_STRING, TYPE_NUMBER, TYPE_STRING, TYPE_BOOLEAN]
+        );
+        assert_eq!(sheet.numbers[0], 4.0);
+        assert_eq!(sheet.numbers[1], 2.5);
+        assert_eq!(sheet.styles, vec![0, 1, 0, 0]);
+        assert_eq!(sheet.string_offsets, vec![0, 0, 0, 6, 6]);
+        assert_eq!(&sheet.string_data, "héllo".as_bytes());
+        assert_eq!(sheet.formula_offsets, vec![0, 0, 4, 4, 4]);
+        assert_eq!(sheet.width_cols, vec![1, 3]);
+        assert_eq!(sheet.widths, vec![9.5, 20.0]);
+    }
+}
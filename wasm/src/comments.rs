@@ -0,0 +1,394 @@
+//! Comment parts for XLSX export
+//!
+//! Each sheet's comments take two parts: `commentsN.xml` holds their text
+//! and `vmlDrawingN.vml` the boxes Excel shows them in; without the drawing
+//! the comments are in the file but never appear. Threads also go in
+//! `threadedCommentN.xml`, which Excel 365 reads instead, with their authors
+//! listed once for the workbook in `persons/person.xml`. Older versions show
+//! the note written alongside each thread.
+
+use crate::reference::parse_cell_ref;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::{
+    needs_space_preserve, push_attr, push_escaped, NS_SPREADSHEETML, XML_DECLARATION,
+};
+use crate::ParsedComment;
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const NS_THREADED_COMMENTS: &str =
+    "http://schemas.microsoft.com/office/spreadsheetml/2018/threadedcomments";
+/// Shape ids in a VML drawing come in blocks of this many
+const SHAPE_BLOCK: u32 = 1024;
+/// Display name for thread posts without an author
+const UNKNOWN_AUTHOR: &str = "Unknown";
+/// What Excel writes as the note behind a thread, before the posts
+const THREAD_NOTE: &str = "[Threaded comment]\n\nYour version of Excel allows you to read this \
+    threaded comment; however, any edits to it will get removed if the file is opened in a \
+    newer version of Excel. Learn more: https://go.microsoft.com/fwlink/?linkid=870924\n\n";
+
+/// Whether a comment is written as a thread
+pub(crate) fn is_thread(comment: &ParsedComment) -> bool {
+    comment.threaded || !comment.replies.is_empty()
+}
+
+/// Id of post `post` of thread `index` on sheet `sheet`; the thread's first
+/// post is 0 and its replies follow
+fn post_id(sheet: u32, index: usize, post: usize) -> String {
+    format!(
+        "{{{:08X}-{:04X}-4000-8000-{:012X}}}",
+        sheet,
+        post,
+        index + 1
+    )
+}
+
+fn person_id(index: usize) -> String {
+    format!("{{00000000-0000-4000-A000-{:012X}}}", index + 1)
+}
+
+fn display_name(author: &Option<String>) -> &str {
+    author
+        .as_deref()
+        .filter(|author| !author.is_empty())
+        .unwrap_or(UNKNOWN_AUTHOR)
+}
+
+/// Authors of the threads among `comments` and their replies, each once
+pub(crate) fn thread_authors<'a>(
+    comments: impl IntoIterator<Item = &'a ParsedComment>,
+) -> Vec<String> {
+    let mut authors: Vec<String> = Vec::new();
+    for comment in comments.into_iter().filter(|comment| is_thread(comment)) {
+        let posts =
+            std::iter::once(&comment.author).chain(comment.replies.iter().map(|r| &r.author));
+        for author in posts {
+            let name = display_name(author);
+            if !authors.iter().any(|known| known == name) {
+                authors.push(name.to_string());
+            }
+        }
+    }
+    authors
+}
+
+fn push_text(out: &mut String, text: &str) {
+    if needs_space_preserve(text) {
+        out.push_str("<t xml:space=\"preserve\">");
+    } else {
+        out.push_str("<t>");
+    }
+    push_escaped(out, text);
+    out.push_str("</t>");
+}
+
+/// Serialize a sheet's comments part (`xl/commentsN.xml`); `sheet` is the
+/// sheet's number, which threads are identified by
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_comments(comments: JsValue, sheet: u32) -> Result<String, JsValue> {
+    trap(|| {
+        let comments: Vec<ParsedComment> = serde_wasm_bindgen::from_value(comments)?;
+        Ok(write_comments_impl(&comments, sheet))
+    })
+}
+
+pub(crate) fn write_comments_impl(comments: &[ParsedComment], sheet: u32) -> String {
+    // A thread's note names the thread rather than a person
+    let authors: Vec<String> = comments
+        .iter()
+        .enumerate()
+        .map(|(i, comment)| match is_thread(comment) {
+            true => format!("tc={}", post_id(sheet, i, 0)),
+            false => comment.author.clone().unwrap_or_default(),
+        })
+        .collect();
+    let mut names: Vec<&str> = Vec::new();
+    for author in &authors {
+        if !names.contains(&author.as_str()) {
+            names.push(author);
+        }
+    }
+
+    let mut out = String::with_capacity(256 + comments.len() * 128);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(out, "<comments xmlns=\"{}\"><authors>", NS_SPREADSHEETML);
+    for name in &names {
+        out.push_str("<author>");
+        push_escaped(&mut out, name);
+        out.push_str("</author>");
+    }
+    out.push_str("</authors><commentList>");
+    for (comment, author) in comments.iter().zip(&authors) {
+        out.push_str("<comment");
+        push_attr(&mut out, "ref", &comment.reference);
+        let author_id = names.iter().position(|name| name == author).unwrap_or(0);
+        let _ = write!(out, " authorId=\"{}\"", author_id);
+        out.push_str("><text>");
+        if is_thread(comment) {
+            let mut text = format!("{}Comment:\n    {}", THREAD_NOTE, comment.text);
+            for reply in &comment.replies {
+                let _ = write!(text, "\nReply:\n    {}", reply.text);
+            }
+            push_text(&mut out, &text);
+        } else {
+            push_text(&mut out, &comment.text);
+        }
+        out.push_str("</text></comment>");
+    }
+    out.push_str("</commentList></comments>");
+    out
+}
+
+/// Serialize the VML drawing (`xl/drawings/vmlDrawingN.vml`) that shows a
+/// sheet's comments
+///
+/// Shape ids are taken from blocks of 1024 starting at `first_block`, which
+/// must not overlap another sheet's; [`vml_blocks`] gives how many are used.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_vml_drawing(comments: JsValue, first_block: u32) -> Result<String, JsValue> {
+    trap(|| {
+        let comments: Vec<ParsedComment> = serde_wasm_bindgen::from_value(comments)?;
+        write_vml_drawing_impl(&comments, first_block).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+/// Number of shape id blocks a drawing of `count` comments takes
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn vml_blocks(count: u32) -> u32 {
+    count / SHAPE_BLOCK + 1
+}
+
+pub(crate) fn write_vml_drawing_impl(
+    comments: &[ParsedComment],
+    first_block: u32,
+) -> Result<String, String> {
+    let blocks = first_block..first_block + vml_blocks(comments.len() as u32);
+    let ids: Vec<String> = blocks.map(|block| block.to_string()).collect();
+
+    let mut out = String::with_capacity(1024 + comments.len() * 640);
+    out.push_str(
+        "<xml xmlns:v=\"urn:schemas-microsoft-com:vml\" \
+         xmlns:o=\"urn:schemas-microsoft-com:office:office\" \
+         xmlns:x=\"urn:schemas-microsoft-com:office:excel\">",
+    );
+    let _ = write!(
+        out,
+        "<o:shapelayout v:ext=\"edit\"><o:idmap v:ext=\"edit\" data=\"{}\"/></o:shapelayout>",
+        ids.join(",")
+    );
+    out.push_str(
+        "<v:shapetype id=\"_x0000_t202\" coordsize=\"21600,21600\" o:spt=\"202\" \
+         path=\"m,l,21600r21600,l21600,xe\"><v:stroke joinstyle=\"miter\"/>\
+         <v:path gradientshapeok=\"t\" o:connecttype=\"rect\"/></v:shapetype>",
+    );
+    for (i, comment) in comments.iter().enumerate() {
+        let (row, col) = parse_cell_ref(&comment.reference)
+            .ok_or_else(|| format!("Invalid comment cell: {}", comment.reference))?;
+        let (row, col) = (row - 1, col - 1);
+        // The box sits to the right of the cell, a little above it
+        let top = row.saturating_sub(1);
+        let (top_offset, bottom_offset) = if row == 0 { (2, 16) } else { (10, 4) };
+        let _ = write!(
+            out,
+            "<v:shape id=\"_x0000_s{}\" type=\"#_x0000_t202\" \
+             style=\"position:absolute;margin-left:59.25pt;margin-top:1.5pt;width:108pt;\
+             height:59.25pt;z-index:{};visibility:hidden\" fillcolor=\"#ffffe1\" \
+             o:insetmode=\"auto\"><v:fill color2=\"#ffffe1\"/>\
+             <v:shadow on=\"t\" color=\"black\" obscured=\"t\"/>\
+             <v:path o:connecttype=\"none\"/><v:textbox style=\"mso-direction-alt:auto\">\
+             <div style=\"text-align:left\"></div></v:textbox>\
+             <x:ClientData ObjectType=\"Note\"><x:MoveWithCells/><x:SizeWithCells/>\
+             <x:Anchor>{}, 15, {}, {}, {}, 15, {}, {}</x:Anchor><x:AutoFill>False</x:AutoFill>\
+             <x:Row>{}</x:Row><x:Column>{}</x:Column></x:ClientData></v:shape>",
+            first_block * SHAPE_BLOCK + i as u32 + 1,
+            i + 1,
+            col + 1,
+            top,
+            top_offset,
+            col + 3,
+            top + 4,
+            bottom_offset,
+            row,
+            col
+        );
+    }
+    out.push_str("</xml>");
+    Ok(out)
+}
+
+/// Serialize a sheet's threaded comments part
+/// (`xl/threadedComments/threadedCommentN.xml`)
+///
+/// `sheet` is the number given to `write_comments` and `persons` the list
+/// written by `write_persons`, which must name every author.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_threaded_comments(
+    comments: JsValue,
+    sheet: u32,
+    persons: Vec<String>,
+) -> Result<String, JsValue> {
+    trap(|| {
+        let comments: Vec<ParsedComment> = serde_wasm_bindgen::from_value(comments)?;
+        write_threaded_comments_impl(&comments, sheet, &persons).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn write_threaded_comments_impl(
+    comments: &[ParsedComment],
+    sheet: u32,
+    persons: &[String],
+) -> Result<String, String> {
+    let person = |author: &Option<String>| {
+        let name = display_name(author);
+        persons
+            .iter()
+            .position(|person| person == name)
+            .map(person_id)
+            .ok_or_else(|| format!("Comment author {} is not in the person list", name))
+    };
+    let post = |out: &mut String, reference: &str, date: &Option<String>| {
+        out.push_str("<threadedComment");
+        push_attr(out, "ref", reference);
+        if let Some(date) = date {
+            push_attr(out, "dT", date);
+        }
+    };
+    let text = |out: &mut String, text: &str| {
+        out.push_str("><text>");
+        push_escaped(out, text);
+        out.push_str("</text></threadedComment>");
+    };
+
+    let mut out = String::with_capacity(256 + comments.len() * 256);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<ThreadedComments xmlns=\"{}\" xmlns:x=\"{}\">",
+        NS_THREADED_COMMENTS, NS_SPREADSHEETML
+    );
+    for (i, comment) in comments.iter().enumerate() {
+        if !is_thread(comment) {
+            continue;
+        }
+        let id = post_id(sheet, i, 0);
+        post(&mut out, &comment.reference, &comment.date);
+        let _ = write!(
+            out,
+            " personId=\"{}\" id=\"{}\"",
+            person(&comment.author)?,
+            id
+        );
+        if comment.resolved {
+            out.push_str(" done=\"1\"");
+        }
+        text(&mut out, &comment.text);
+        for (j, reply) in comment.replies.iter().enumerate() {
+            post(&mut out, &comment.reference, &reply.date);
+            let _ = write!(
+                out,
+                " personId=\"{}\" id=\"{}\" parentId=\"{}\"",
+                person(&reply.author)?,
+                post_id(sheet, i, j + 1),
+                id
+            );
+            text(&mut out, &reply.text);
+        }
+    }
+    out.push_str("</ThreadedComments>");
+    Ok(out)
+}
+
+/// Serialize the workbook's list of thread authors (`xl/persons/person.xml`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_persons(persons: Vec<String>) -> Result<String, JsValue> {
+    trap(|| Ok(write_persons_impl(&persons)))
+}
+
+pub(crate) fn write_persons_impl(persons: &[String]) -> String {
+    let mut out = String::with_capacity(256 + persons.len() * 128);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<personList xmlns=\"{}\" xmlns:x=\"{}\">",
+        NS_THREADED_COMMENTS, NS_SPREADSHEETML
+    );
+    for (i, name) in persons.iter().enumerate() {
+        out.push_str("<person");
+        push_attr(&mut out, "displayName", name);
+        let _ = write!(out, " id=\"{}\"", person_id(i));
+        push_attr(&mut out, "userId", name);
+        out.push_str(" providerId=\"None\"/>");
+    }
+    out.push_str("</personList>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommentReply;
+
+    #[test]
+    fn test_write_comments() {
+        let comments = vec![
+            ParsedComment {
+                reference: "B3".to_string(),
+                author: Some("Ana".to_string()),
+                text: "Check this".to_string(),
+                ..Default::default()
+            },
+            ParsedComment {
+                reference: "A1".to_string(),
+                author: Some("Ben".to_string()),
+                text: "Why & how?".to_string(),
+                replies: vec![CommentReply {
+                    author: None,
+                    text: "Because".to_string(),
+                    date: Some("2024-05-01T10:00:00.00".to_string()),
+                }],
+                resolved: true,
+                ..Default::default()
+            },
+        ];
+
+        let xml = write_comments_impl(&comments, 2);
+        let thread = "{00000002-0000-4000-8000-000000000002}";
+        assert!(xml.contains(&format!(
+            "<authors><author>Ana</author><author>tc={}</author></authors>",
+            thread
+        )));
+        assert!(xml.contains("<comment ref=\"B3\" authorId=\"0\"><text><t>Check this</t>"));
+        assert!(xml.contains("Comment:\n    Why &amp; how?\nReply:\n    Because</t>"));
+
+        let persons = thread_authors(&comments);
+        assert_eq!(persons, vec!["Ben", "Unknown"]);
+        let threads = write_threaded_comments_impl(&comments, 2, &persons).unwrap();
+        assert!(threads.contains(&format!(
+            "<threadedComment ref=\"A1\" personId=\"{}\" id=\"{}\" done=\"1\"><text>Why &amp; \
+             how?</text></threadedComment><threadedComment ref=\"A1\" \
+             dT=\"2024-05-01T10:00:00.00\" personId=\"{}\" id=\"{}\" parentId=\"{}\">",
+            person_id(0),
+            thread,
+            person_id(1),
+            post_id(2, 1, 1),
+            thread
+        )));
+        assert!(!threads.contains("B3"));
+        assert!(write_threaded_comments_impl(&comments, 2, &persons[..1]).is_err());
+
+        // Row and column are 0-based; shape ids start in the sheet's block
+        let vml = write_vml_drawing_impl(&comments, 3).unwrap();
+        assert!(vml.contains("<o:idmap v:ext=\"edit\" data=\"3\"/>"));
+        assert!(vml.contains("_x0000_s3073"));
+        assert!(vml.contains("<x:Anchor>2, 15, 1, 10, 4, 15, 5, 4</x:Anchor>"));
+        assert!(vml.contains("<x:Row>2</x:Row><x:Column>1</x:Column>"));
+        assert_eq!(vml_blocks(1023), 1);
+        assert_eq!(vml_blocks(1024), 2);
+    }
+}
@@ -0,0 +1,130 @@
+//! Reusable parser working memory
+//!
+//! Parsing a workbook calls the same parsers once per part. A
+//! [`ParserContext`] keeps their event buffer, text scratch space and
+//! lookup tables alive between calls, so only the first sheet pays for
+//! growing them.
+
+use crate::columnar::{parse_worksheet_columnar_with, ColumnarWorksheet};
+use crate::intern::parse_worksheet_interned_with;
+use crate::memory;
+use crate::profile::phase;
+use crate::strings::{parse_shared_strings_table_with, SharedStringTable};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{parse_shared_strings_with, parse_worksheet_with, WorksheetParseOptions};
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Working memory shared by successive parser calls
+#[derive(Debug, Default)]
+pub(crate) struct ParserScratch {
+    /// quick-xml event buffer
+    pub buf: Vec<u8>,
+    /// Accumulated text of the element being read
+    pub text: String,
+    /// Shared formula masters by `si`: (formula, anchor cell)
+    pub shared_formulas: HashMap<String, (String, (u32, u32))>,
+    /// Row count of the last worksheet, used to presize the next one
+    pub row_capacity: usize,
+    /// Cell count of the last row, used to presize the next one
+    pub cell_capacity: usize,
+    /// Set while a sheet is parsed in pieces: the last row number read so
+    /// far, with `shared_formulas` kept from the piece before
+    pub resume: Option<u32>,
+}
+
+/// Parser state held across calls from JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Default)]
+pub struct ParserContext {
+    scratch: ParserScratch,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl ParserContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ParserContext {
+        ParserContext::default()
+    }
+
+    /// Same as the free `parse_worksheet_with_options`, reusing this context's buffers
+    pub fn parse_worksheet(&mut self, xml: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let options: Option<WorksheetParseOptions> = serde_wasm_bindgen::from_value(options)?;
+            let options = options.unwrap_or_default();
+            let guard = memory::guard();
+            let result = phase("worksheet", || {
+                parse_worksheet_with(xml, &mut self.scratch, &options)
+            });
+            guard.check().map_err(|e| JsValue::from_str(&e))?;
+            options.check(&result)?;
+            Ok(phase("serialization", || {
+                serde_wasm_bindgen::to_value(&result)
+            })?)
+        })
+    }
+
+    /// Same as the free `parse_worksheet_json`, reusing this context's buffers
+    pub fn parse_worksheet_json(&mut self, xml: &str) -> String {
+        let result =
+            parse_worksheet_with(xml, &mut self.scratch, &WorksheetParseOptions::default());
+        serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Same as the free `parse_worksheet_interned`, reusing this context's buffers
+    pub fn parse_worksheet_interned(&mut self, xml: &str) -> JsValue {
+        let result = parse_worksheet_interned_with(xml, &mut self.scratch);
+        serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+    }
+
+    /// Same as the free `parse_worksheet_columnar`, reusing this context's buffers
+    pub fn parse_worksheet_columnar(&mut self, xml: &str) -> ColumnarWorksheet {
+        parse_worksheet_columnar_with(xml, &mut self.scratch)
+    }
+
+    /// Same as the free `parse_shared_strings`, reusing this context's buffers
+    pub fn parse_shared_strings(&mut self, xml: &str) -> JsValue {
+        let result = phase("sharedStrings", || {
+            parse_shared_strings_with(xml, &mut self.scratch)
+        });
+        phase("serialization", || serde_wasm_bindgen::to_value(&result)).unwrap_or(JsValue::NULL)
+    }
+
+    /// Same as the free `parse_shared_strings_table`, reusing this context's buffers
+    pub fn parse_shared_strings_table(&mut self, xml: &str) -> SharedStringTable {
+        parse_shared_strings_table_with(xml, &mut self.scratch)
+    }
+
+    /// Release the retained buffers
+    pub fn reset(&mut self) {
+        self.scratch = ParserScratch::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_reuse() {
+        let mut scratch = ParserScratch::default();
+        let first = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><f t="shared" ref="A1:A2" si="0">B1*2</f><v>2</v></c></row>
+            <row r="2"><c r="A2"><f t="shared" si="0"/><v>4</v></c></row>
+        </sheetData></worksheet>"#;
+        let sheet = parse_worksheet_with(first, &mut scratch, &WorksheetParseOptions::default());
+        assert_eq!(sheet.rows[1].cells[0].formula.as_deref(), Some("B2*2"));
+        assert_eq!(scratch.row_capacity, 2);
+
+        // Shared formulas from the previous sheet must not leak into the next
+        let second = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><f t="shared" si="0"/><v>7</v></c></row>
+        </sheetData></worksheet>"#;
+        let sheet = parse_worksheet_with(second, &mut scratch, &WorksheetParseOptions::default());
+        assert_eq!(sheet.rows[0].cells[0].formula, None);
+        assert_eq!(sheet.rows[0].cells[0].value.as_deref(), Some("7"));
+    }
+}
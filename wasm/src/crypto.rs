@@ -0,0 +1,678 @@
+//! Hash functions, AES and base64 for workbook encryption, sheet
+//! protection and content hashes
+//!
+//! ECMA-376 encryption only needs a handful of primitives, so they are
+//! implemented here rather than pulling in a crypto stack (see ADR-002).
+//! None of this is constant-time; it protects files at rest, it does not
+//! guard keys against side channels.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::JsCast;
+
+/// Hash algorithms allowed by the agile encryption descriptor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SHA1" | "SHA-1" => Some(HashAlgorithm::Sha1),
+            "SHA256" | "SHA-256" => Some(HashAlgorithm::Sha256),
+            "SHA384" | "SHA-384" => Some(HashAlgorithm::Sha384),
+            "SHA512" | "SHA-512" => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Input block length, used by HMAC
+    fn block_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 | HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha384 | HashAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// Hash the concatenation of `parts`
+    pub(crate) fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        let data = parts.concat();
+        match self {
+            HashAlgorithm::Sha1 => sha1(&data).to_vec(),
+            HashAlgorithm::Sha256 => sha256(&data).to_vec(),
+            HashAlgorithm::Sha384 => sha512_words(&data, SHA384_IV)[..48].to_vec(),
+            HashAlgorithm::Sha512 => sha512_words(&data, SHA512_IV).to_vec(),
+        }
+    }
+}
+
+/// Merkle-Damgård padding: 0x80, zeros, then the bit length in `len_bytes` bytes
+fn pad_message(data: &[u8], block: usize, len_bytes: usize) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while !(padded.len() + len_bytes).is_multiple_of(block) {
+        padded.push(0);
+    }
+    let bits = (data.len() as u128) * 8;
+    padded.extend_from_slice(&bits.to_be_bytes()[16 - len_bytes..]);
+    padded
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    for block in pad_message(data, 64, 8).chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap_or_default());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut out = [0u8; 20];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// SHA-512 round constants; SHA-256 uses the high 32 bits of the first 64
+const K512: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+const SHA512_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SHA384_IV: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = SHA512_IV.map(|v| (v >> 32) as u32);
+    for block in pad_message(data, 64, 8).chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap_or_default());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut s = h;
+        for i in 0..64 {
+            let [a, b, c, d, e, f, g, hh] = s;
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add((K512[i] >> 32) as u32)
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            s = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+        }
+        for (state, value) in h.iter_mut().zip(s) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut out = [0u8; 32];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// SHA-512 compression from the given initial state; SHA-384 truncates the result
+fn sha512_words(data: &[u8], iv: [u64; 8]) -> [u8; 64] {
+    let mut h = iv;
+    for block in pad_message(data, 128, 16).chunks_exact(128) {
+        let mut w = [0u64; 80];
+        for i in 0..16 {
+            w[i] = u64::from_be_bytes(block[i * 8..i * 8 + 8].try_into().unwrap_or_default());
+        }
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut s = h;
+        for i in 0..80 {
+            let [a, b, c, d, e, f, g, hh] = s;
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K512[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            s = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+        }
+        for (state, value) in h.iter_mut().zip(s) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut out = [0u8; 64];
+    for (chunk, word) in out.chunks_exact_mut(8).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let high = a & 0x80;
+        a <<= 1;
+        if high != 0 {
+            a ^= 0x1B;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// The AES S-box: multiplicative inverse followed by the affine transform
+const fn build_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        // x^254 is the inverse of x in GF(2^8), and maps 0 to 0
+        let x = i as u8;
+        let mut inverse = 1u8;
+        let mut power = 0;
+        while power < 254 {
+            inverse = gf_mul(inverse, x);
+            power += 1;
+        }
+        if x == 0 {
+            inverse = 0;
+        }
+        sbox[i] = inverse
+            ^ inverse.rotate_left(1)
+            ^ inverse.rotate_left(2)
+            ^ inverse.rotate_left(3)
+            ^ inverse.rotate_left(4)
+            ^ 0x63;
+        i += 1;
+    }
+    sbox
+}
+
+const fn invert_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inverse = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inverse[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    inverse
+}
+
+const SBOX: [u8; 256] = build_sbox();
+const INV_SBOX: [u8; 256] = invert_sbox(&SBOX);
+
+/// An expanded AES-128/192/256 key
+pub(crate) struct Aes {
+    round_keys: Vec<[u8; 16]>,
+}
+
+impl Aes {
+    pub(crate) fn new(key: &[u8]) -> Result<Self, String> {
+        let nk = match key.len() {
+            16 | 24 | 32 => key.len() / 4,
+            n => return Err(format!("Invalid AES key length: {} bytes", n)),
+        };
+        let rounds = nk + 6;
+
+        let mut words: Vec<[u8; 4]> = key
+            .chunks_exact(4)
+            .map(|w| [w[0], w[1], w[2], w[3]])
+            .collect();
+        let mut rcon = 1u8;
+        for i in nk..4 * (rounds + 1) {
+            let mut temp = words[i - 1];
+            if i % nk == 0 {
+                temp = [
+                    SBOX[temp[1] as usize] ^ rcon,
+                    SBOX[temp[2] as usize],
+                    SBOX[temp[3] as usize],
+                    SBOX[temp[0] as usize],
+                ];
+                rcon = gf_mul(rcon, 2);
+            } else if nk > 6 && i % nk == 4 {
+                temp = temp.map(|b| SBOX[b as usize]);
+            }
+            let prev = words[i - nk];
+            words.push([
+                prev[0] ^ temp[0],
+                prev[1] ^ temp[1],
+                prev[2] ^ temp[2],
+                prev[3] ^ temp[3],
+            ]);
+        }
+
+        let round_keys = words
+            .chunks_exact(4)
+            .map(|w| {
+                let mut key = [0u8; 16];
+                for (chunk, word) in key.chunks_exact_mut(4).zip(w) {
+                    chunk.copy_from_slice(word);
+                }
+                key
+            })
+            .collect();
+        Ok(Aes { round_keys })
+    }
+
+    fn add_round_key(state: &mut [u8; 16], key: &[u8; 16]) {
+        for (byte, k) in state.iter_mut().zip(key) {
+            *byte ^= k;
+        }
+    }
+
+    /// Encrypt one 16-byte block in place (state is column-major)
+    fn encrypt_block(&self, state: &mut [u8; 16]) {
+        let rounds = self.round_keys.len() - 1;
+        Self::add_round_key(state, &self.round_keys[0]);
+        for round in 1..=rounds {
+            for byte in state.iter_mut() {
+                *byte = SBOX[*byte as usize];
+            }
+            // ShiftRows: row r rotates left by r
+            let copy = *state;
+            for col in 0..4 {
+                for row in 0..4 {
+                    state[col * 4 + row] = copy[((col + row) % 4) * 4 + row];
+                }
+            }
+            if round < rounds {
+                for col in state.chunks_exact_mut(4) {
+                    let [a, b, c, d] = [col[0], col[1], col[2], col[3]];
+                    col[0] = gf_mul(a, 2) ^ gf_mul(b, 3) ^ c ^ d;
+                    col[1] = a ^ gf_mul(b, 2) ^ gf_mul(c, 3) ^ d;
+                    col[2] = a ^ b ^ gf_mul(c, 2) ^ gf_mul(d, 3);
+                    col[3] = gf_mul(a, 3) ^ b ^ c ^ gf_mul(d, 2);
+                }
+            }
+            Self::add_round_key(state, &self.round_keys[round]);
+        }
+    }
+
+    /// Decrypt one 16-byte block in place (state is column-major)
+    fn decrypt_block(&self, state: &mut [u8; 16]) {
+        let rounds = self.round_keys.len() - 1;
+        Self::add_round_key(state, &self.round_keys[rounds]);
+        for round in (0..rounds).rev() {
+            // Inverse ShiftRows: row r rotates right by r
+            let copy = *state;
+            for col in 0..4 {
+                for row in 0..4 {
+                    state[col * 4 + row] = copy[((col + 4 - row) % 4) * 4 + row];
+                }
+            }
+            for byte in state.iter_mut() {
+                *byte = INV_SBOX[*byte as usize];
+            }
+            Self::add_round_key(state, &self.round_keys[round]);
+            if round > 0 {
+                for col in state.chunks_exact_mut(4) {
+                    let [a, b, c, d] = [col[0], col[1], col[2], col[3]];
+                    col[0] = gf_mul(a, 14) ^ gf_mul(b, 11) ^ gf_mul(c, 13) ^ gf_mul(d, 9);
+                    col[1] = gf_mul(a, 9) ^ gf_mul(b, 14) ^ gf_mul(c, 11) ^ gf_mul(d, 13);
+                    col[2] = gf_mul(a, 13) ^ gf_mul(b, 9) ^ gf_mul(c, 14) ^ gf_mul(d, 11);
+                    col[3] = gf_mul(a, 11) ^ gf_mul(b, 13) ^ gf_mul(c, 9) ^ gf_mul(d, 14);
+                }
+            }
+        }
+    }
+
+    /// AES-CBC decryption without padding removal
+    pub(crate) fn decrypt_cbc(&self, iv: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+        if iv.len() != 16 || !data.len().is_multiple_of(16) {
+            return Err("AES-CBC input is not a whole number of blocks".to_string());
+        }
+        let mut previous: [u8; 16] = iv.try_into().unwrap_or_default();
+        let mut out = Vec::with_capacity(data.len());
+        for block in data.chunks_exact(16) {
+            let cipher: [u8; 16] = block.try_into().unwrap_or_default();
+            let mut state = cipher;
+            self.decrypt_block(&mut state);
+            out.extend(state.iter().zip(&previous).map(|(a, b)| a ^ b));
+            previous = cipher;
+        }
+        Ok(out)
+    }
+
+    /// AES-CBC encryption; a partial last block is padded with zeros
+    pub(crate) fn encrypt_cbc(&self, iv: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut previous = [0u8; 16];
+        previous.copy_from_slice(&iv[..16]);
+        let mut out = Vec::with_capacity(data.len().div_ceil(16) * 16);
+        for block in data.chunks(16) {
+            let mut state = previous;
+            for (byte, plain) in state.iter_mut().zip(block) {
+                *byte ^= plain;
+            }
+            self.encrypt_block(&mut state);
+            out.extend_from_slice(&state);
+            previous = state;
+        }
+        out
+    }
+}
+
+/// HMAC (RFC 2104) over `data` with the given hash
+pub(crate) fn hmac(hash: HashAlgorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    let block = hash.block_len();
+    let mut key = if key.len() > block {
+        hash.digest(&[key])
+    } else {
+        key.to_vec()
+    };
+    key.resize(block, 0);
+    let inner_pad: Vec<u8> = key.iter().map(|b| b ^ 0x36).collect();
+    let outer_pad: Vec<u8> = key.iter().map(|b| b ^ 0x5C).collect();
+    let inner = hash.digest(&[&inner_pad, data]);
+    hash.digest(&[&outer_pad, &inner])
+}
+
+/// Cryptographically secure random bytes from the host's `crypto.getRandomValues`
+#[cfg(feature = "wasm")]
+pub(crate) fn random_bytes(len: usize) -> Result<Vec<u8>, JsValue> {
+    let crypto = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("crypto"))?;
+    let get_random_values = js_sys::Reflect::get(&crypto, &JsValue::from_str("getRandomValues"))?
+        .dyn_into::<js_sys::Function>()
+        .map_err(|_| JsValue::from_str("crypto.getRandomValues is not available"))?;
+    let array = js_sys::Uint8Array::new_with_length(len as u32);
+    get_random_values.call1(&crypto, &array)?;
+    Ok(array.to_vec())
+}
+
+/// Encode as standard padded base64
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((triple >> (18 - i * 6)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard base64, ignoring whitespace
+pub(crate) fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_hashes() {
+        let digest = |algorithm: HashAlgorithm| hex(&algorithm.digest(&[b"a", b"bc"]));
+        assert_eq!(
+            digest(HashAlgorithm::Sha1),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Sha384),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed\
+             8086072ba1e7cc2358baeca134c825a7"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Sha512),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        // Two-block message
+        assert_eq!(
+            hex(&HashAlgorithm::Sha256
+                .digest(&[b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"])),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_aes() {
+        // FIPS-197 appendix C vectors
+        let plain = unhex("00112233445566778899aabbccddeeff");
+        let cases = [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f1011121314151617",
+                "dda97ca4864cdfe06eaf70a0ec0d7191",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ];
+        for (key, cipher) in cases {
+            let aes = Aes::new(&unhex(key)).unwrap();
+            let decrypted = aes.decrypt_cbc(&[0; 16], &unhex(cipher)).unwrap();
+            assert_eq!(decrypted, plain);
+            assert_eq!(hex(&aes.encrypt_cbc(&[0; 16], &plain)), cipher);
+        }
+
+        let iv = [7u8; 16];
+        let aes = Aes::new(&[1; 32]).unwrap();
+        let message = b"thirty-two bytes of cbc message!";
+        let encrypted = aes.encrypt_cbc(&iv, message);
+        assert_eq!(aes.decrypt_cbc(&iv, &encrypted).unwrap(), message);
+    }
+
+    #[test]
+    fn test_hmac_and_base64() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac(
+                HashAlgorithm::Sha256,
+                b"Jefe",
+                b"what do ya want for nothing?"
+            )),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        for text in ["", "h", "he", "hel", "hello"] {
+            let encoded = base64_encode(text.as_bytes());
+            assert_eq!(base64_decode(&encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(base64_encode(b"hello"), "aGVsbG8=");
+        assert_eq!(base64_decode("aGVs\nbG8=").unwrap(), b"hello");
+    }
+}
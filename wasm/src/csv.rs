@@ -0,0 +1,720 @@
+//! CSV import and export
+//!
+//! Both directions work on the parser's row model: `parse_csv` produces the
+//! same `ParsedWorksheet` as `parse_worksheet`, and `worksheet_to_csv`
+//! produces RFC 4180 output matching `sheetToCsv` in the JS layer without
+//! materialising the sheet as JS objects.
+
+use crate::reference::{cell_ref, parse_cell_ref, range_bounds, used_range};
+use crate::scan::utf8_lossy;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{
+    date_style_flags, datetime_to_serial, format_datetime, format_number, parse_iso_date,
+    resolve_cell_value, serial_to_datetime, valid_date, CellValue, DateTime,
+};
+use crate::{ParsedCell, ParsedRow, ParsedStyles, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Options for [`worksheet_to_csv`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvExportOptions {
+    pub delimiter: String,
+    pub row_delimiter: String,
+    pub quote_char: String,
+    pub quote_all_fields: bool,
+    /// Range to export (e.g. "A1:D10"); defaults to the used range
+    pub range: Option<String>,
+    pub null_value: String,
+    /// "ISO" (yyyy-mm-dd) or a pattern using yyyy, yy, mm, dd, HH, MM, SS
+    pub date_format: String,
+    pub include_bom: bool,
+    /// Write `=FORMULA` for formula cells instead of their cached value
+    pub use_formulas: bool,
+    /// Shared string table for resolving `t="s"` cells
+    pub shared_strings: Vec<String>,
+    /// Styles used to detect date-formatted numbers
+    pub styles: Option<ParsedStyles>,
+    pub date1904: bool,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        CsvExportOptions {
+            delimiter: ",".to_string(),
+            row_delimiter: "\r\n".to_string(),
+            quote_char: "\"".to_string(),
+            quote_all_fields: false,
+            range: None,
+            null_value: String::new(),
+            date_format: "ISO".to_string(),
+            include_bom: false,
+            use_formulas: false,
+            shared_strings: Vec::new(),
+            styles: None,
+            date1904: false,
+        }
+    }
+}
+
+/// Export a parsed worksheet as CSV text
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn worksheet_to_csv(worksheet: JsValue, options: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<CsvExportOptions> = serde_wasm_bindgen::from_value(options)?;
+        worksheet_to_csv_impl(&worksheet, &options.unwrap_or_default())
+            .map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn worksheet_to_csv_impl(
+    worksheet: &ParsedWorksheet,
+    options: &CsvExportOptions,
+) -> Result<String, String> {
+    let mut out = String::new();
+    if options.include_bom {
+        out.push('\u{FEFF}');
+    }
+
+    let bounds = match options.range {
+        Some(ref range) => {
+            Some(range_bounds(range).ok_or_else(|| format!("Invalid range: {}", range))?)
+        }
+        None => used_range(worksheet),
+    };
+    let (start_row, start_col, end_row, end_col) = match bounds {
+        Some(bounds) => bounds,
+        None => return Ok(out),
+    };
+
+    let date_styles = options
+        .styles
+        .as_ref()
+        .map(date_style_flags)
+        .unwrap_or_default();
+    let width = (end_col - start_col + 1) as usize;
+    let mut fields: Vec<Option<String>> = vec![None; width];
+
+    // Rows are usually sorted, but tolerate any order
+    let mut rows: Vec<_> = worksheet
+        .rows
+        .iter()
+        .filter(|row| row.row_num >= start_row && row.row_num <= end_row)
+        .collect();
+    rows.sort_by_key(|row| row.row_num);
+    let mut rows = rows.into_iter().peekable();
+
+    for row_num in start_row..=end_row {
+        fields.iter_mut().for_each(|field| *field = None);
+
+        while let Some(row) = rows.next_if(|row| row.row_num <= row_num) {
+            for cell in &row.cells {
+                let col = match parse_cell_ref(&cell.reference) {
+                    Some((_, col)) if col >= start_col && col <= end_col => col,
+                    _ => continue,
+                };
+
+                let text = match cell.formula {
+                    Some(ref formula) if options.use_formulas => Some(format!("={}", formula)),
+                    _ => match resolve_cell_value(cell, &options.shared_strings) {
+                        CellValue::Empty => None,
+                        CellValue::Text(text) => Some(text),
+                        CellValue::Bool(b) => Some(if b { "TRUE" } else { "FALSE" }.to_string()),
+                        CellValue::Error(error) => Some(error),
+                        CellValue::Number(number) => {
+                            let is_date = cell
+                                .style_index
+                                .and_then(|s| date_styles.get(s as usize).copied())
+                                .unwrap_or(false);
+                            match serial_to_datetime(number, options.date1904) {
+                                Some(dt) if is_date => {
+                                    Some(format_datetime(&dt, &options.date_format))
+                                }
+                                _ => Some(format_number(number)),
+                            }
+                        }
+                    },
+                };
+                fields[(col - start_col) as usize] = text;
+            }
+        }
+
+        if row_num > start_row {
+            out.push_str(&options.row_delimiter);
+        }
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                out.push_str(&options.delimiter);
+            }
+            push_field(
+                &mut out,
+                field.as_deref().unwrap_or(&options.null_value),
+                options,
+            );
+        }
+    }
+
+    Ok(out)
+}
+
+/// Append a field, quoting it when it contains special characters
+fn push_field(out: &mut String, value: &str, options: &CsvExportOptions) {
+    let quote = options.quote_char.as_str();
+    let needs_quoting = options.quote_all_fields
+        || (!options.delimiter.is_empty() && value.contains(options.delimiter.as_str()))
+        || (!quote.is_empty() && value.contains(quote))
+        || value.contains('\n')
+        || value.contains('\r');
+
+    if !needs_quoting || quote.is_empty() {
+        out.push_str(value);
+        return;
+    }
+
+    out.push_str(quote);
+    for (i, part) in value.split(quote).enumerate() {
+        if i > 0 {
+            out.push_str(quote);
+            out.push_str(quote);
+        }
+        out.push_str(part);
+    }
+    out.push_str(quote);
+}
+
+/// Options for [`parse_csv`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvImportOptions {
+    /// Field delimiter; sniffed from the data when not set
+    pub delimiter: Option<String>,
+    pub quote_char: String,
+    pub skip_empty_lines: bool,
+    pub trim_values: bool,
+    pub start_cell: String,
+    pub detect_numbers: bool,
+    pub detect_dates: bool,
+    pub date_formats: Vec<String>,
+    /// Style index given to detected dates so they display as dates
+    pub date_style_index: Option<u32>,
+    /// Maximum number of rows to import (0 = unlimited)
+    pub max_rows: u32,
+    /// Lines starting with this are skipped
+    pub comment_char: Option<String>,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        CsvImportOptions {
+            delimiter: None,
+            quote_char: "\"".to_string(),
+            skip_empty_lines: true,
+            trim_values: true,
+            start_cell: "A1".to_string(),
+            detect_numbers: true,
+            detect_dates: false,
+            date_formats: vec![
+                "yyyy-mm-dd".to_string(),
+                "mm/dd/yyyy".to_string(),
+                "dd/mm/yyyy".to_string(),
+            ],
+            date_style_index: None,
+            max_rows: 0,
+            comment_char: None,
+        }
+    }
+}
+
+/// Parse CSV/TSV bytes into the same row model as `parse_worksheet`
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_csv(data: &[u8], options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let options: Option<CsvImportOptions> = serde_wasm_bindgen::from_value(options)?;
+        let result = parse_csv_impl(data, &options.unwrap_or_default());
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    })
+}
+
+/// Decode CSV bytes, honouring a UTF-8 or UTF-16 byte order mark
+pub(crate) fn decode_text(data: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match data {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => utf8_lossy(rest).into_owned(),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => utf8_lossy(data).into_owned(),
+    }
+}
+
+/// Candidate delimiters, in order of preference on ties
+const SNIFF_DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Number of records sampled when sniffing the delimiter
+const SNIFF_LINES: usize = 20;
+
+/// Pick the delimiter that splits the leading records most consistently
+fn sniff_delimiter(text: &str, quote: Option<char>) -> char {
+    let mut counts = vec![[0usize; SNIFF_DELIMITERS.len()]];
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if Some(ch) == quote {
+            if in_quotes && chars.peek() == Some(&ch) {
+                chars.next();
+            } else {
+                in_quotes = !in_quotes;
+            }
+        } else if in_quotes {
+            continue;
+        } else if ch == '\n' || ch == '\r' {
+            if ch == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            if counts.len() == SNIFF_LINES {
+                break;
+            }
+            counts.push([0; SNIFF_DELIMITERS.len()]);
+        } else if let Some(i) = SNIFF_DELIMITERS.iter().position(|&d| d == ch) {
+            if let Some(line) = counts.last_mut() {
+                line[i] += 1;
+            }
+        }
+    }
+
+    // Ignore a trailing empty record left by a final newline
+    if counts.len() > 1
+        && counts
+            .last()
+            .is_some_and(|line| line.iter().all(|&n| n == 0))
+    {
+        counts.pop();
+    }
+
+    let mut best = (SNIFF_DELIMITERS[0], false, 0);
+    for (i, &delimiter) in SNIFF_DELIMITERS.iter().enumerate() {
+        let first = counts[0][i];
+        if first == 0 {
+            continue;
+        }
+        let consistent = counts.iter().all(|line| line[i] == first);
+        if (consistent, first) > (best.1, best.2) {
+            best = (delimiter, consistent, first);
+        }
+    }
+    best.0
+}
+
+/// Split text into records of raw fields (RFC 4180, multi-line quoted fields)
+fn split_records(
+    text: &str,
+    delimiter: char,
+    quote: Option<char>,
+    skip_empty_lines: bool,
+    comment: Option<&str>,
+    max_rows: usize,
+) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    let finish_record = |record: &mut Vec<String>, records: &mut Vec<Vec<String>>| {
+        let is_comment = comment
+            .is_some_and(|c| !c.is_empty() && record.first().is_some_and(|f| f.starts_with(c)));
+        let is_empty = skip_empty_lines && record.len() == 1 && record[0].is_empty();
+        if is_comment || is_empty {
+            record.clear();
+        } else {
+            records.push(std::mem::take(record));
+        }
+    };
+
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            if Some(ch) == quote {
+                if chars.peek() == Some(&ch) {
+                    chars.next();
+                    field.push(ch);
+                } else {
+                    in_quotes = false;
+                }
+            } else if ch == '\r' {
+                // Normalise line endings inside quoted fields
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                field.push('\n');
+            } else {
+                field.push(ch);
+            }
+        } else if Some(ch) == quote {
+            in_quotes = true;
+        } else if ch == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if ch == '\n' || ch == '\r' {
+            if ch == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            finish_record(&mut record, &mut records);
+            if max_rows > 0 && records.len() >= max_rows {
+                return records;
+            }
+        } else {
+            field.push(ch);
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        finish_record(&mut record, &mut records);
+    }
+    if max_rows > 0 {
+        records.truncate(max_rows);
+    }
+    records
+}
+
+pub(crate) fn parse_csv_impl(data: &[u8], options: &CsvImportOptions) -> ParsedWorksheet {
+    let text = decode_text(data);
+    let quote = options.quote_char.chars().next();
+    let delimiter = match options.delimiter.as_deref().and_then(|d| d.chars().next()) {
+        Some(delimiter) => delimiter,
+        None => sniff_delimiter(&text, quote),
+    };
+
+    let records = split_records(
+        &text,
+        delimiter,
+        quote,
+        options.skip_empty_lines,
+        options.comment_char.as_deref(),
+        options.max_rows as usize,
+    );
+    let (start_row, start_col) = parse_cell_ref(&options.start_cell).unwrap_or((1, 1));
+
+    let rows = records
+        .into_iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let row_num = start_row + i as u32;
+            let cells = record
+                .into_iter()
+                .enumerate()
+                .filter_map(|(j, raw)| {
+                    let raw = if options.trim_values {
+                        raw.trim().to_string()
+                    } else {
+                        raw
+                    };
+                    infer_cell(raw, options).map(|mut cell| {
+                        cell.reference = cell_ref(row_num, start_col + j as u32);
+                        cell
+                    })
+                })
+                .collect();
+            ParsedRow {
+                row_num,
+                cells,
+                height: None,
+                hidden: false,
+            }
+        })
+        .collect();
+
+    ParsedWorksheet {
+        rows,
+        ..Default::default()
+    }
+}
+
+/// Convert a raw field to a typed cell, mirroring `convertValue` in the JS reader
+fn infer_cell(raw: String, options: &CsvImportOptions) -> Option<ParsedCell> {
+    if raw.is_empty() {
+        return None;
+    }
+
+    let typed = |cell_type: Option<&str>, value: String, style_index: Option<u32>| ParsedCell {
+        reference: String::new(),
+        cell_type: cell_type.map(str::to_string),
+        style_index,
+        value: Some(value),
+        formula: None,
+    };
+
+    if raw.eq_ignore_ascii_case("true") {
+        return Some(typed(Some("b"), "1".to_string(), None));
+    }
+    if raw.eq_ignore_ascii_case("false") {
+        return Some(typed(Some("b"), "0".to_string(), None));
+    }
+
+    if options.detect_numbers {
+        if let Some(number) = parse_number(&raw) {
+            return Some(typed(None, format_number(number), None));
+        }
+    }
+
+    if options.detect_dates {
+        if let Some(dt) = parse_date(&raw, &options.date_formats) {
+            let serial = format_number(datetime_to_serial(&dt));
+            return Some(typed(None, serial, options.date_style_index));
+        }
+    }
+
+    Some(typed(Some("inlineStr"), raw, None))
+}
+
+/// Check for `-?\d*\.?\d+([eE][+-]?\d+)?`
+fn is_plain_number(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or(("", mantissa));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if frac_part.is_empty() || !digits(int_part) || !digits(frac_part) {
+        return false;
+    }
+
+    match exponent {
+        Some(exp) => {
+            let exp = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+            !exp.is_empty() && digits(exp)
+        }
+        None => true,
+    }
+}
+
+/// Try to parse a field as a number, accepting percentages, currency and thousands separators
+fn parse_number(value: &str) -> Option<f64> {
+    let cleaned = value.trim();
+    if cleaned.is_empty()
+        || cleaned
+            .chars()
+            .any(|c| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+    {
+        return None;
+    }
+
+    if let Some(percent) = cleaned.strip_suffix('%') {
+        let percent = percent.trim();
+        return if is_plain_number(percent) {
+            percent.parse::<f64>().ok().map(|n| n / 100.0)
+        } else {
+            None
+        };
+    }
+
+    const CURRENCY: [char; 5] = ['$', '€', '£', '¥', '₹'];
+    let without_currency = cleaned.strip_prefix(CURRENCY).unwrap_or(cleaned);
+    let without_currency = without_currency
+        .strip_suffix(CURRENCY)
+        .unwrap_or(without_currency);
+    let normalized = strip_thousands(without_currency)?;
+
+    if !is_plain_number(&normalized) {
+        return None;
+    }
+    normalized.parse::<f64>().ok()
+}
+
+/// Drop the commas from `-?\d{1,3}(,\d{3})+(\.\d+)?`; a field with commas
+/// anywhere else, such as "1,2,3", is text
+fn strip_thousands(s: &str) -> Option<String> {
+    if !s.contains(',') {
+        return Some(s.to_string());
+    }
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    let (int_part, frac_part) = match unsigned.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (unsigned, None),
+    };
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let mut groups = int_part.split(',');
+    let lead = groups.next().filter(|lead| lead.len() <= 3 && digits(lead));
+    let valid = lead.is_some()
+        && groups.all(|group| group.len() == 3 && digits(group))
+        && frac_part.is_none_or(digits);
+    valid.then(|| s.replace(',', ""))
+}
+
+/// Try to parse a field as a date: ISO first, then each configured format
+fn parse_date(value: &str, formats: &[String]) -> Option<DateTime> {
+    parse_iso_date(value).or_else(|| {
+        formats
+            .iter()
+            .find_map(|format| parse_date_with_format(value, format))
+    })
+}
+
+fn parse_date_with_format(value: &str, format: &str) -> Option<DateTime> {
+    let format = format.to_ascii_lowercase();
+    let format_parts: Vec<&str> = format.split(|c: char| !c.is_ascii_lowercase()).collect();
+    let value_parts: Vec<&str> = value.split(|c: char| !c.is_ascii_digit()).collect();
+    if format_parts.len() != value_parts.len() {
+        return None;
+    }
+
+    let (mut year, mut month, mut day) = (0i32, 0u32, 0u32);
+    for (fp, vp) in format_parts.iter().zip(&value_parts) {
+        let n: u32 = vp.parse().ok()?;
+        match *fp {
+            "yyyy" => year = n as i32,
+            "yy" => year = if n > 50 { 1900 } else { 2000 } + n as i32,
+            "mm" | "m" => month = n,
+            "dd" | "d" => day = n,
+            _ => {}
+        }
+    }
+    valid_date(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+    use crate::ParsedStyle;
+
+    #[test]
+    fn test_parse_csv() {
+        let data = "\u{FEFF}name;amount;paid;when\r\n\"Smith; J\"\"r\";\"1,250.50\";TRUE;2024-01-01\r\n\r\n  Lee  ;45%;no;03/15/2024\n\"multi\nline\";;;\n";
+        let options = CsvImportOptions {
+            detect_dates: true,
+            date_style_index: Some(3),
+            ..Default::default()
+        };
+        let worksheet = parse_csv_impl(data.as_bytes(), &options);
+        assert_eq!(worksheet.rows.len(), 4);
+
+        let row = &worksheet.rows[1];
+        assert_eq!(row.row_num, 2);
+        assert_eq!(row.cells[0].value.as_deref(), Some("Smith; J\"r"));
+        assert_eq!(row.cells[0].cell_type.as_deref(), Some("inlineStr"));
+        assert_eq!(row.cells[1].value.as_deref(), Some("1250.5"));
+        assert_eq!(row.cells[1].cell_type, None);
+        assert_eq!(row.cells[2].cell_type.as_deref(), Some("b"));
+        assert_eq!(row.cells[3].value.as_deref(), Some("45292"));
+        assert_eq!(row.cells[3].style_index, Some(3));
+
+        let row = &worksheet.rows[2];
+        assert_eq!(row.cells[0].value.as_deref(), Some("Lee"));
+        assert_eq!(row.cells[1].value.as_deref(), Some("0.45"));
+        assert_eq!(row.cells[3].reference, "D3");
+        assert_eq!(row.cells[3].value.as_deref(), Some("45366"));
+
+        let row = &worksheet.rows[3];
+        assert_eq!(row.cells.len(), 1);
+        assert_eq!(row.cells[0].value.as_deref(), Some("multi\nline"));
+
+        // Tab-separated, UTF-16 with BOM, placed at C5
+        let tsv: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("a\tb,c\n1\t2\n".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let options = CsvImportOptions {
+            start_cell: "C5".to_string(),
+            ..Default::default()
+        };
+        let worksheet = parse_csv_impl(&tsv, &options);
+        assert_eq!(worksheet.rows[0].cells[1].reference, "D5");
+        assert_eq!(worksheet.rows[0].cells[1].value.as_deref(), Some("b,c"));
+        assert_eq!(worksheet.rows[1].row_num, 6);
+
+        // Commas are only thousands separators in groups of three
+        let options = CsvImportOptions {
+            delimiter: Some(";".to_string()),
+            ..Default::default()
+        };
+        let worksheet = parse_csv_impl(b"1,2,3;-12,345.5;1,234,567;1,23;12,34.5\n", &options);
+        let values: Vec<_> = worksheet.rows[0]
+            .cells
+            .iter()
+            .map(|cell| (cell.value.as_deref().unwrap(), cell.cell_type.as_deref()))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("1,2,3", Some("inlineStr")),
+                ("-12345.5", None),
+                ("1234567", None),
+                ("1,23", Some("inlineStr")),
+                ("12,34.5", Some("inlineStr")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_worksheet_to_csv() {
+        let mut date = cell("C1", None, "45292");
+        date.style_index = Some(1);
+        let mut total = cell("B3", None, "3");
+        total.formula = Some("SUM(B1:B2)".to_string());
+
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                row(
+                    1,
+                    vec![cell("A1", Some("s"), "0"), cell("B1", None, "1.5"), date],
+                ),
+                row(
+                    3,
+                    vec![
+                        cell("A3", Some("inlineStr"), "say \"hi\", then\nleave"),
+                        total,
+                        cell("C3", Some("b"), "1"),
+                    ],
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let options = CsvExportOptions {
+            shared_strings: vec!["Name".to_string()],
+            styles: Some(ParsedStyles {
+                cell_xfs: vec![
+                    ParsedStyle::default(),
+                    ParsedStyle {
+                        num_fmt_id: Some(14),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let csv = worksheet_to_csv_impl(&worksheet, &options).unwrap();
+        assert_eq!(
+            csv,
+            "Name,1.5,2024-01-01\r\n,,\r\n\"say \"\"hi\"\", then\nleave\",3,TRUE"
+        );
+
+        let options = CsvExportOptions {
+            delimiter: ";".to_string(),
+            row_delimiter: "\n".to_string(),
+            range: Some("B2:B3".to_string()),
+            use_formulas: true,
+            null_value: "NULL".to_string(),
+            ..Default::default()
+        };
+        let csv = worksheet_to_csv_impl(&worksheet, &options).unwrap();
+        assert_eq!(csv, "NULL\n=SUM(B1:B2)");
+    }
+}
@@ -0,0 +1,110 @@
+//! Incremental row access for virtualized grids
+//!
+//! A [`RowCursor`] keeps the parsed rows in wasm memory and hands them to JS
+//! a page at a time, so the host never materializes the whole sheet as JS
+//! objects.
+
+use crate::context::ParserScratch;
+use crate::memory;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{parse_worksheet_with, ParsedRow, WorksheetParseOptions};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Forward cursor over the rows of one worksheet
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Default)]
+pub struct RowCursor {
+    rows: Vec<ParsedRow>,
+    position: usize,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl RowCursor {
+    /// Parse worksheet XML (with `parse_worksheet_with_options` options) into a cursor
+    #[wasm_bindgen(constructor)]
+    pub fn new(xml: &str, options: JsValue) -> Result<RowCursor, JsValue> {
+        trap(|| {
+            let options: Option<WorksheetParseOptions> = serde_wasm_bindgen::from_value(options)?;
+            let guard = memory::guard();
+            let worksheet = parse_worksheet_with(
+                xml,
+                &mut ParserScratch::default(),
+                &options.unwrap_or_default(),
+            );
+            guard.check().map_err(|e| JsValue::from_str(&e))?;
+            Ok(RowCursor::from_rows(worksheet.rows))
+        })
+    }
+
+    /// The next `count` rows as `ParsedRow` objects; empty once the cursor is done
+    pub fn next_rows(&mut self, count: usize) -> Result<JsValue, JsValue> {
+        trap(|| Ok(serde_wasm_bindgen::to_value(self.take(count))?))
+    }
+
+    /// Whether every row has been returned
+    #[wasm_bindgen(getter)]
+    pub fn done(&self) -> bool {
+        self.position >= self.rows.len()
+    }
+
+    /// Number of rows returned so far
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Total number of rows
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Move to row index `position` (0-based, in document order)
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.rows.len());
+    }
+}
+
+impl RowCursor {
+    pub(crate) fn from_rows(rows: Vec<ParsedRow>) -> Self {
+        RowCursor { rows, position: 0 }
+    }
+
+    fn take(&mut self, count: usize) -> &[ParsedRow] {
+        let start = self.position;
+        self.position = start.saturating_add(count).min(self.rows.len());
+        &self.rows[start..self.position]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_row_cursor() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c></row>
+            <row r="2"><c r="A2"><v>2</v></c></row>
+            <row r="5"><c r="A5"><v>5</v></c></row>
+        </sheetData></worksheet>"#;
+        let worksheet = parse_worksheet_with(
+            xml,
+            &mut ParserScratch::default(),
+            &WorksheetParseOptions::default(),
+        );
+        let mut cursor = RowCursor::from_rows(worksheet.rows);
+        let page: Vec<_> = cursor.take(2).iter().map(|r| r.row_num).collect();
+        assert_eq!(page, vec![1, 2]);
+        let page: Vec<_> = cursor.take(2).iter().map(|r| r.row_num).collect();
+        assert_eq!(page, vec![5]);
+        assert!(cursor.done() && cursor.take(2).is_empty());
+
+        cursor.seek(1);
+        assert_eq!(cursor.take(1)[0].row_num, 2);
+    }
+}
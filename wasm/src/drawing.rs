@@ -0,0 +1,280 @@
+//! Pictures and chart frames for XLSX export
+//!
+//! A sheet's pictures and charts go in one DrawingML part,
+//! `xl/drawings/drawingN.xml`, which anchors each to the sheet's cells and
+//! reaches its image file in `xl/media` or its chart part through the
+//! drawing's own relationships. A picture anchored to one cell keeps the
+//! image's own size, read from its header.
+
+#[cfg(feature = "charts")]
+use crate::chart::NS_CHART;
+use crate::reference::range_bounds;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::{push_attr, NS_RELATIONSHIPS, XML_DECLARATION};
+#[cfg(feature = "charts")]
+use crate::SheetChart;
+use crate::SheetImage;
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const NS_SPREADSHEET_DRAWING: &str =
+    "http://schemas.openxmlformats.org/drawingml/2006/spreadsheetDrawing";
+const NS_DRAWINGML: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+/// DrawingML lengths are in English Metric Units, 9525 to a pixel
+const EMU_PER_PIXEL: u64 = 9525;
+/// Excel's size for a new chart, in pixels
+#[cfg(feature = "charts")]
+const CHART_SIZE: (u32, u32) = (480, 288);
+
+/// File type and pixel size of an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ImageInfo {
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Read the format and size from the header of a PNG, JPEG, GIF or BMP file
+pub(crate) fn image_info(data: &[u8]) -> Option<ImageInfo> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?));
+    let le16 = |at: usize| Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?));
+    let be32 = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?));
+    let le32 = |at: usize| Some(i32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?));
+    let info = |extension, width: u32, height: u32| {
+        Some(ImageInfo {
+            extension,
+            width,
+            height,
+        })
+    };
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return info("png", be32(16)?, be32(20)?);
+    }
+    if data.starts_with(b"GIF8") {
+        return info("gif", le16(6)?.into(), le16(8)?.into());
+    }
+    if data.starts_with(b"BM") {
+        // Bottom-up bitmaps give a negative height
+        return info("bmp", le32(18)?.unsigned_abs(), le32(22)?.unsigned_abs());
+    }
+    if data.starts_with(b"\xFF\xD8") {
+        // The size is in the start-of-frame segment, after any metadata
+        let mut at = 2;
+        while *data.get(at)? == 0xFF {
+            let marker = *data.get(at + 1)?;
+            let frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if frame {
+                return info("jpeg", be16(at + 7)?.into(), be16(at + 5)?.into());
+            }
+            at += 2 + usize::from(be16(at + 2)?);
+        }
+    }
+    None
+}
+
+/// Serialize a sheet's drawing part (`xl/drawings/drawingN.xml`)
+///
+/// Picture `n` (counting from 1) embeds the image related as `rIdN`, and
+/// the charts follow on from the pictures' relationship ids.
+#[cfg(all(feature = "wasm", feature = "charts"))]
+#[wasm_bindgen]
+pub fn write_drawing(images: JsValue, charts: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let images: Option<Vec<SheetImage>> = serde_wasm_bindgen::from_value(images)?;
+        let charts: Option<Vec<SheetChart>> = serde_wasm_bindgen::from_value(charts)?;
+        write_drawing_impl(&images.unwrap_or_default(), &charts.unwrap_or_default())
+            .map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+/// Serialize a sheet's drawing part (`xl/drawings/drawingN.xml`)
+///
+/// Picture `n` (counting from 1) embeds the image related as `rIdN`; a
+/// build without the `charts` feature has no chart frames to place.
+#[cfg(all(feature = "wasm", not(feature = "charts")))]
+#[wasm_bindgen]
+pub fn write_drawing(images: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let images: Option<Vec<SheetImage>> = serde_wasm_bindgen::from_value(images)?;
+        write_drawing_impl(&images.unwrap_or_default()).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn write_drawing_impl(
+    images: &[SheetImage],
+    #[cfg(feature = "charts")] charts: &[SheetChart],
+) -> Result<String, String> {
+    let mut out = String::with_capacity(256 + images.len() * 1024);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<xdr:wsDr xmlns:xdr=\"{}\" xmlns:a=\"{}\">",
+        NS_SPREADSHEET_DRAWING, NS_DRAWINGML
+    );
+    for (i, image) in images.iter().enumerate() {
+        let info = image_info(&image.data).ok_or("Images must be PNG, JPEG, GIF or BMP files")?;
+        let size = (info.width, info.height);
+        let anchor = open_anchor(&mut out, &image.anchor, size, " editAs=\"oneCell\"")?;
+        let (cx, cy) = emu(size);
+        let _ = write!(
+            out,
+            "<xdr:pic><xdr:nvPicPr><xdr:cNvPr id=\"{}\" name=\"Picture {}\"",
+            i + 2,
+            i + 1
+        );
+        if let Some(description) = &image.description {
+            push_attr(&mut out, "descr", description);
+        }
+        let _ = write!(
+            out,
+            "/><xdr:cNvPicPr><a:picLocks noChangeAspect=\"1\"/></xdr:cNvPicPr></xdr:nvPicPr>\
+             <xdr:blipFill><a:blip xmlns:r=\"{}\" r:embed=\"rId{}\"/>\
+             <a:stretch><a:fillRect/></a:stretch></xdr:blipFill>\
+             <xdr:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{}\" cy=\"{}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></xdr:spPr></xdr:pic>\
+             <xdr:clientData/></xdr:{}>",
+            NS_RELATIONSHIPS,
+            i + 1,
+            cx,
+            cy,
+            anchor
+        );
+    }
+    #[cfg(feature = "charts")]
+    for (i, chart) in charts.iter().enumerate() {
+        let anchor = open_anchor(&mut out, &chart.anchor, CHART_SIZE, "")?;
+        let id = images.len() + i + 1;
+        let _ = write!(
+            out,
+            "<xdr:graphicFrame macro=\"\"><xdr:nvGraphicFramePr>\
+             <xdr:cNvPr id=\"{}\" name=\"Chart {}\"/><xdr:cNvGraphicFramePr/>\
+             </xdr:nvGraphicFramePr><xdr:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"0\" cy=\"0\"/>\
+             </xdr:xfrm><a:graphic><a:graphicData uri=\"{}\"><c:chart xmlns:c=\"{}\" \
+             xmlns:r=\"{}\" r:id=\"rId{}\"/></a:graphicData></a:graphic></xdr:graphicFrame>\
+             <xdr:clientData/></xdr:{}>",
+            id + 1,
+            i + 1,
+            NS_CHART,
+            NS_CHART,
+            NS_RELATIONSHIPS,
+            id,
+            anchor
+        );
+    }
+    out.push_str("</xdr:wsDr>");
+    Ok(out)
+}
+
+/// Size in EMUs of `(width, height)` pixels
+fn emu((width, height): (u32, u32)) -> (u64, u64) {
+    (
+        u64::from(width) * EMU_PER_PIXEL,
+        u64::from(height) * EMU_PER_PIXEL,
+    )
+}
+
+/// Open the anchor element placing an object at `anchor` and return its
+/// name: a cell takes the object's top-left corner at `size` pixels, and a
+/// range is covered from its first cell's corner to past its last
+fn open_anchor(
+    out: &mut String,
+    anchor: &str,
+    size: (u32, u32),
+    edit_as: &str,
+) -> Result<&'static str, String> {
+    let (r1, c1, r2, c2) =
+        range_bounds(anchor).ok_or_else(|| format!("Invalid anchor: {}", anchor))?;
+    let marker = |out: &mut String, tag: &str, row: u32, col: u32| {
+        let _ = write!(
+            out,
+            "<xdr:{tag}><xdr:col>{}</xdr:col><xdr:colOff>0</xdr:colOff>\
+             <xdr:row>{}</xdr:row><xdr:rowOff>0</xdr:rowOff></xdr:{tag}>",
+            col,
+            row,
+            tag = tag
+        );
+    };
+    if anchor.contains(':') {
+        let _ = write!(out, "<xdr:twoCellAnchor{}>", edit_as);
+        marker(out, "from", r1 - 1, c1 - 1);
+        marker(out, "to", r2, c2);
+        Ok("twoCellAnchor")
+    } else {
+        out.push_str("<xdr:oneCellAnchor>");
+        marker(out, "from", r1 - 1, c1 - 1);
+        let (cx, cy) = emu(size);
+        let _ = write!(out, "<xdr:ext cx=\"{}\" cy=\"{}\"/>", cx, cy);
+        Ok("oneCellAnchor")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header of a `width` by `height` PNG, enough for `image_info`
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_write_drawing() {
+        let jpeg = [
+            &[0xFF, 0xD8, 0xFF, 0xE0, 0, 4, 0, 0][..],
+            &[0xFF, 0xC2, 0, 11, 8, 0, 30, 0, 40, 3, 0],
+        ]
+        .concat();
+        let size =
+            |data: &[u8]| image_info(data).map(|info| (info.extension, info.width, info.height));
+        assert_eq!(size(&jpeg), Some(("jpeg", 40, 30)));
+        assert_eq!(size(&png(64, 32)), Some(("png", 64, 32)));
+        assert_eq!(size(b"GIF89a\x10\0\x08\0"), Some(("gif", 16, 8)));
+        assert_eq!(size(b"<svg/>"), None);
+
+        let images = vec![
+            SheetImage {
+                data: png(64, 32),
+                anchor: "B3".to_string(),
+                description: Some("Logo & name".to_string()),
+            },
+            SheetImage {
+                data: jpeg,
+                anchor: "D2:F9".to_string(),
+                description: None,
+            },
+        ];
+        let xml = write_drawing_impl(
+            &images,
+            #[cfg(feature = "charts")]
+            &[],
+        )
+        .unwrap();
+        assert!(xml.contains(
+            "<xdr:oneCellAnchor><xdr:from><xdr:col>1</xdr:col><xdr:colOff>0</xdr:colOff>\
+             <xdr:row>2</xdr:row><xdr:rowOff>0</xdr:rowOff></xdr:from>\
+             <xdr:ext cx=\"609600\" cy=\"304800\"/>"
+        ));
+        assert!(xml.contains("name=\"Picture 1\" descr=\"Logo &amp; name\""));
+        assert!(xml.contains("<xdr:to><xdr:col>6</xdr:col>"));
+        assert!(xml.contains("<xdr:row>9</xdr:row><xdr:rowOff>0</xdr:rowOff></xdr:to>"));
+        assert!(xml.contains("r:embed=\"rId2\""));
+        let svg = SheetImage {
+            data: b"<svg/>".to_vec(),
+            anchor: "A1".to_string(),
+            description: None,
+        };
+        let result = write_drawing_impl(
+            &[svg],
+            #[cfg(feature = "charts")]
+            &[],
+        );
+        assert!(result.is_err());
+    }
+}
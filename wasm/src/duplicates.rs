@@ -0,0 +1,188 @@
+//! Duplicate row detection
+//!
+//! Rows are keyed by the values of chosen columns and grouped by key in one
+//! pass, which for large sheets is far cheaper than comparing rows after
+//! they have been serialized to JS objects.
+
+use crate::reference::{parse_cell_ref, parse_col};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{resolve_cell_value, CellValue};
+use crate::ParsedWorksheet;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Options for [`find_duplicate_rows`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateOptions {
+    /// First row compared (1-based), e.g. 2 to leave out a header row
+    pub first_row: Option<u32>,
+    /// Compare text without regard to case
+    pub ignore_case: bool,
+    /// Compare text without its leading and trailing whitespace
+    pub trim: bool,
+    pub shared_strings: Vec<String>,
+}
+
+/// Rows holding the same values in the key columns
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DuplicateGroup {
+    /// Row numbers, in sheet order; the first is the original
+    pub rows: Vec<u32>,
+}
+
+/// One cell's value as compared
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Key {
+    Empty,
+    Number(u64),
+    Text(String),
+    Bool(bool),
+    Error(String),
+}
+
+/// Groups of rows that repeat the values of `key_columns` ("A", "C", ...;
+/// every column when empty), ordered by their first row
+///
+/// Rows whose key cells are all empty are never duplicates.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn find_duplicate_rows(
+    worksheet: JsValue,
+    key_columns: Vec<String>,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<DuplicateOptions> = serde_wasm_bindgen::from_value(options)?;
+        let groups =
+            find_duplicate_rows_impl(&worksheet, &key_columns, &options.unwrap_or_default())
+                .map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&groups)?)
+    })
+}
+
+pub(crate) fn find_duplicate_rows_impl(
+    worksheet: &ParsedWorksheet,
+    key_columns: &[String],
+    options: &DuplicateOptions,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let columns = key_columns
+        .iter()
+        .map(|letters| parse_col(letters).ok_or(format!("Invalid column: {}", letters)))
+        .collect::<Result<Vec<u32>, String>>()?;
+
+    let first_row = options.first_row.unwrap_or(1);
+    let mut groups: HashMap<Vec<(u32, Key)>, usize> = HashMap::new();
+    let mut rows_by_group: Vec<Vec<u32>> = Vec::new();
+    for row in &worksheet.rows {
+        if row.row_num < first_row {
+            continue;
+        }
+        let mut by_col = HashMap::new();
+        for cell in &row.cells {
+            if let Some((_, col)) = parse_cell_ref(&cell.reference) {
+                let key = key_value(resolve_cell_value(cell, &options.shared_strings), options);
+                if key != Key::Empty {
+                    by_col.insert(col, key);
+                }
+            }
+        }
+        let key: Vec<(u32, Key)> = if columns.is_empty() {
+            let mut key: Vec<_> = by_col.into_iter().collect();
+            key.sort_by_key(|&(col, _)| col);
+            key
+        } else {
+            columns
+                .iter()
+                .map(|&col| (col, by_col.remove(&col).unwrap_or(Key::Empty)))
+                .collect()
+        };
+        if key.iter().all(|(_, key)| *key == Key::Empty) {
+            continue;
+        }
+        let group = *groups.entry(key).or_insert_with(|| {
+            rows_by_group.push(Vec::new());
+            rows_by_group.len() - 1
+        });
+        rows_by_group[group].push(row.row_num);
+    }
+
+    Ok(rows_by_group
+        .into_iter()
+        .filter(|rows| rows.len() > 1)
+        .map(|rows| DuplicateGroup { rows })
+        .collect())
+}
+
+/// A cell's value as compared under `options`; blank text counts as empty
+pub(crate) fn key_value(value: CellValue, options: &DuplicateOptions) -> Key {
+    match value {
+        CellValue::Empty => Key::Empty,
+        // 0 and -0 are the same number
+        CellValue::Number(n) => Key::Number((n + 0.0).to_bits()),
+        CellValue::Bool(b) => Key::Bool(b),
+        CellValue::Error(code) => Key::Error(code),
+        CellValue::Text(mut text) => {
+            if options.trim {
+                text = text.trim().to_string();
+            }
+            if options.ignore_case {
+                text = text.to_lowercase();
+            }
+            match text.is_empty() {
+                true => Key::Empty,
+                false => Key::Text(text),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::sheet_from_rows;
+
+    #[test]
+    fn test_find_duplicate_rows() {
+        let worksheet = sheet_from_rows(&[
+            &["Email", "Name", "Score"],
+            &["ann@example.com", "Ann", "1"],
+            &["bob@example.com", "Bob", "2"],
+            &["ANN@example.com ", "Ann", "3"],
+            &["", "", ""],
+            &["bob@example.com", "Bob", "2"],
+            &["", "", ""],
+        ]);
+        let groups = |columns: &[&str], options: &DuplicateOptions| {
+            let columns: Vec<_> = columns.iter().map(|c| c.to_string()).collect();
+            find_duplicate_rows_impl(&worksheet, &columns, options)
+                .unwrap()
+                .into_iter()
+                .map(|group| group.rows)
+                .collect::<Vec<_>>()
+        };
+
+        let options = DuplicateOptions::default();
+        assert_eq!(groups(&[], &options), [vec![3, 6]]);
+        assert_eq!(groups(&["b"], &options), [vec![2, 4], vec![3, 6]]);
+        assert_eq!(groups(&["A"], &options), [vec![3, 6]]);
+        let options = DuplicateOptions {
+            first_row: Some(3),
+            ignore_case: true,
+            trim: true,
+            ..Default::default()
+        };
+        assert_eq!(groups(&["$A"], &options), [vec![3, 6]]);
+        let options = DuplicateOptions {
+            first_row: Some(2),
+            ..options
+        };
+        assert_eq!(groups(&["A"], &options), [vec![2, 4], vec![3, 6]]);
+        let columns = ["A1".to_string()];
+        assert!(find_duplicate_rows_impl(&worksheet, &columns, &options).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Character encoding detection for XML parts given as bytes
+//!
+//! Nearly every part is UTF-8, but some producers write UTF-16 (with or
+//! without a byte order mark) or declare a single-byte encoding. The
+//! bytes-based entry points detect the encoding from the BOM, the byte
+//! pattern of `<?xml`, or the `encoding` declaration, and transcode to
+//! UTF-8 before parsing. An encoding that can't be handled is an error
+//! rather than a page of mojibake.
+
+use crate::scan::utf8_lossy;
+use memchr::memmem;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum XmlEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+/// Windows-1252 characters for bytes 0x80-0x9F; the rest match Latin-1
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// Work out the encoding from the first bytes of a part
+pub(crate) fn detect_encoding(head: &[u8]) -> Result<XmlEncoding, String> {
+    match head {
+        [0xEF, 0xBB, 0xBF, ..] => return Ok(XmlEncoding::Utf8),
+        [0xFF, 0xFE, ..] | [b'<', 0, b'?', 0, ..] => return Ok(XmlEncoding::Utf16Le),
+        [0xFE, 0xFF, ..] | [0, b'<', 0, b'?', ..] => return Ok(XmlEncoding::Utf16Be),
+        _ => {}
+    }
+    let Some(name) = declared_encoding(head) else {
+        return Ok(XmlEncoding::Utf8);
+    };
+    match name.to_ascii_lowercase().as_str() {
+        // A UTF-16 label on ASCII-compatible bytes is a mislabelled UTF-8 file
+        "utf-8" | "utf8" | "utf-16" | "utf-16le" | "utf-16be" | "us-ascii" | "ascii" => {
+            Ok(XmlEncoding::Utf8)
+        }
+        "iso-8859-1" | "latin1" | "iso_8859-1" | "l1" => Ok(XmlEncoding::Latin1),
+        "windows-1252" | "cp1252" => Ok(XmlEncoding::Windows1252),
+        _ => Err(format!("Unsupported XML encoding: {}", name)),
+    }
+}
+
+/// The `encoding` pseudo-attribute of an `<?xml ...?>` declaration
+fn declared_encoding(head: &[u8]) -> Option<String> {
+    let head = head.strip_prefix(b"<?xml")?;
+    let decl = &head[..memmem::find(head, b"?>")?];
+    let after = &decl[memmem::find(decl, b"encoding")? + b"encoding".len()..];
+    let after = after
+        .trim_ascii_start()
+        .strip_prefix(b"=")?
+        .trim_ascii_start();
+    let (&quote, value) = after.split_first()?;
+    if quote != b'"' && quote != b'\'' {
+        return None;
+    }
+    let end = value.iter().position(|&b| b == quote)?;
+    Some(String::from_utf8_lossy(&value[..end]).into_owned())
+}
+
+/// Decode a part to text, transcoding from its detected encoding
+pub(crate) fn decode_xml(data: &[u8]) -> Result<Cow<'_, str>, String> {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from([pair[0], pair[1]]))
+            .collect();
+        Cow::Owned(String::from_utf16_lossy(&units))
+    };
+    Ok(match detect_encoding(data)? {
+        XmlEncoding::Utf8 => utf8_lossy(data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data)),
+        XmlEncoding::Utf16Le => utf16(
+            data.strip_prefix(b"\xFF\xFE").unwrap_or(data),
+            u16::from_le_bytes,
+        ),
+        XmlEncoding::Utf16Be => utf16(
+            data.strip_prefix(b"\xFE\xFF").unwrap_or(data),
+            u16::from_be_bytes,
+        ),
+        XmlEncoding::Latin1 => Cow::Owned(data.iter().map(|&b| b as char).collect()),
+        XmlEncoding::Windows1252 => Cow::Owned(
+            data.iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                    b => b as char,
+                })
+                .collect(),
+        ),
+    })
+}
+
+/// [`decode_xml`] for owned bytes, reusing them when they are already UTF-8
+pub(crate) fn xml_to_string(data: Vec<u8>) -> Result<String, String> {
+    if detect_encoding(&data)? == XmlEncoding::Utf8 && !data.starts_with(b"\xEF\xBB\xBF") {
+        return Ok(match String::from_utf8(data) {
+            Ok(text) => text,
+            Err(e) => utf8_lossy(e.as_bytes()).into_owned(),
+        });
+    }
+    decode_xml(&data).map(Cow::into_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_xml() {
+        let text = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><t>héllo</t>";
+        let mut le = vec![0xFF, 0xFE];
+        le.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode_xml(&le).unwrap(), text);
+        // Without a BOM the byte pattern of `<?` gives it away
+        let be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode_xml(&be).unwrap(), text);
+
+        assert_eq!(decode_xml(b"\xEF\xBB\xBF<t/>").unwrap(), "<t/>");
+        let latin1 = b"<?xml version='1.0' encoding='ISO-8859-1'?><t>caf\xE9</t>";
+        assert!(decode_xml(latin1).unwrap().ends_with("<t>caf\u{e9}</t>"));
+        let cp1252 = b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><t>\x80</t>";
+        assert!(decode_xml(cp1252).unwrap().ends_with("<t>\u{20AC}</t>"));
+        let ebcdic = b"<?xml version=\"1.0\" encoding=\"EBCDIC-US\"?><t/>";
+        assert_eq!(
+            decode_xml(ebcdic).unwrap_err(),
+            "Unsupported XML encoding: EBCDIC-US"
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Password-protected OOXML workbooks (ECMA-376 agile encryption)
+//!
+//! Excel saves an encrypted .xlsx as a compound file rather than a ZIP. The
+//! `EncryptionInfo` stream describes how to derive the key from the password
+//! and `EncryptedPackage` holds the AES-encrypted ZIP. Decrypting returns the
+//! ordinary package bytes, which then go through the normal reader; writing
+//! wraps a finished package the same way.
+
+use crate::cfb::{is_compound_file, read_u64, write_compound_file, CompoundFile};
+#[cfg(feature = "wasm")]
+use crate::crypto::random_bytes;
+use crate::crypto::{base64_decode, base64_encode, hmac, Aes, HashAlgorithm};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Block keys from MS-OFFCRYPTO 2.3.4.13
+const VERIFIER_INPUT_BLOCK: [u8; 8] = [0xFE, 0xA7, 0xD2, 0x76, 0x3B, 0x4B, 0x9E, 0x79];
+const VERIFIER_VALUE_BLOCK: [u8; 8] = [0xD7, 0xAA, 0x0F, 0x6D, 0x30, 0x61, 0x34, 0x4E];
+const KEY_VALUE_BLOCK: [u8; 8] = [0x14, 0x6E, 0x0B, 0xE7, 0xAB, 0xAC, 0xD0, 0xD6];
+const INTEGRITY_KEY_BLOCK: [u8; 8] = [0x5F, 0xB2, 0xAD, 0x01, 0x0C, 0xB9, 0xE1, 0xF6];
+const INTEGRITY_VALUE_BLOCK: [u8; 8] = [0xA0, 0x67, 0x7F, 0x02, 0xB2, 0x2C, 0x84, 0x33];
+
+/// The package is encrypted in independent segments of this size
+const SEGMENT_LENGTH: usize = 4096;
+
+/// Largest spin count MS-OFFCRYPTO allows; higher ones would stall the
+/// key derivation
+const MAX_SPIN_COUNT: u32 = 10_000_000;
+
+/// Whether `data` is an encrypted OOXML package (a compound file with an
+/// `EncryptedPackage` stream) rather than a plain ZIP
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn is_encrypted_workbook(data: &[u8]) -> bool {
+    is_compound_file(data)
+        && CompoundFile::new(data)
+            .and_then(|cfb| cfb.read_stream("EncryptedPackage"))
+            .is_ok_and(|stream| stream.is_some())
+}
+
+/// Decrypt a password-protected .xlsx, returning the plain ZIP package
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn decrypt_workbook(data: &[u8], password: &str) -> Result<Vec<u8>, JsValue> {
+    trap(|| decrypt_workbook_impl(data, password).map_err(|e| JsValue::from_str(&e)))
+}
+
+/// Cipher parameters shared by `keyData` and the password `encryptedKey`
+struct CipherParams {
+    salt: Vec<u8>,
+    block_size: usize,
+    key_bytes: usize,
+    hash_size: usize,
+    hash: HashAlgorithm,
+}
+
+struct PasswordKey {
+    params: CipherParams,
+    spin_count: u32,
+    verifier_hash_input: Vec<u8>,
+    verifier_hash_value: Vec<u8>,
+    key_value: Vec<u8>,
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+fn required(e: &BytesStart, name: &[u8]) -> Result<String, String> {
+    attribute(e, name).ok_or_else(|| {
+        format!(
+            "EncryptionInfo is missing the {} attribute",
+            String::from_utf8_lossy(name)
+        )
+    })
+}
+
+fn required_number(e: &BytesStart, name: &[u8]) -> Result<usize, String> {
+    required(e, name)?.parse().map_err(|_| {
+        format!(
+            "Invalid {} in EncryptionInfo",
+            String::from_utf8_lossy(name)
+        )
+    })
+}
+
+/// The password key's spin count, which the file chooses and which sets how
+/// long deriving the key takes
+fn spin_count(e: &BytesStart) -> Result<u32, String> {
+    u32::try_from(required_number(e, b"spinCount")?)
+        .ok()
+        .filter(|&count| count <= MAX_SPIN_COUNT)
+        .ok_or_else(|| format!("spinCount is over the maximum of {}", MAX_SPIN_COUNT))
+}
+
+fn required_base64(e: &BytesStart, name: &[u8]) -> Result<Vec<u8>, String> {
+    base64_decode(&required(e, name)?)
+        .ok_or_else(|| format!("Invalid base64 in {}", String::from_utf8_lossy(name)))
+}
+
+fn cipher_params(e: &BytesStart) -> Result<CipherParams, String> {
+    let cipher = required(e, b"cipherAlgorithm")?;
+    let chaining = required(e, b"cipherChaining")?;
+    if cipher != "AES" || chaining != "ChainingModeCBC" {
+        return Err(format!("Unsupported cipher: {} {}", cipher, chaining));
+    }
+    let hash_name = required(e, b"hashAlgorithm")?;
+    let params = CipherParams {
+        salt: required_base64(e, b"saltValue")?,
+        block_size: required_number(e, b"blockSize")?,
+        key_bytes: required_number(e, b"keyBits")? / 8,
+        hash_size: required_number(e, b"hashSize")?,
+        hash: HashAlgorithm::from_name(&hash_name)
+            .ok_or_else(|| format!("Unsupported hash algorithm: {}", hash_name))?,
+    };
+    if params.block_size != 16 {
+        return Err(format!("Unsupported AES block size: {}", params.block_size));
+    }
+    Ok(params)
+}
+
+/// The parsed agile `EncryptionInfo` descriptor
+struct EncryptionInfo {
+    key_data: CipherParams,
+    password_key: PasswordKey,
+    /// Encrypted HMAC key and value over the `EncryptedPackage` stream
+    integrity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+fn parse_encryption_info(stream: &[u8]) -> Result<EncryptionInfo, String> {
+    let version = (
+        stream.first().copied().unwrap_or(0),
+        stream.get(2).copied().unwrap_or(0),
+    );
+    match version {
+        (4, 4) => {}
+        (2..=4, 2) => return Err("Standard (non-agile) encryption is not supported".to_string()),
+        _ => {
+            return Err(format!(
+                "Unknown encryption version {}.{}",
+                version.0, version.1
+            ))
+        }
+    }
+
+    let xml = std::str::from_utf8(stream.get(8..).unwrap_or_default())
+        .map_err(|_| "EncryptionInfo is not valid UTF-8".to_string())?;
+    let mut reader = Reader::from_str(xml);
+    let mut key_data = None;
+    let mut password_key = None;
+    let mut integrity = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                match e.local_name().as_ref() {
+                    b"keyData" => key_data = Some(cipher_params(e)?),
+                    b"dataIntegrity" => {
+                        integrity = Some((
+                            required_base64(e, b"encryptedHmacKey")?,
+                            required_base64(e, b"encryptedHmacValue")?,
+                        ));
+                    }
+                    // Certificate key encryptors have no spin count
+                    b"encryptedKey" if attribute(e, b"spinCount").is_some() => {
+                        password_key = Some(PasswordKey {
+                            params: cipher_params(e)?,
+                            spin_count: spin_count(e)?,
+                            verifier_hash_input: required_base64(e, b"encryptedVerifierHashInput")?,
+                            verifier_hash_value: required_base64(e, b"encryptedVerifierHashValue")?,
+                            key_value: required_base64(e, b"encryptedKeyValue")?,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Invalid EncryptionInfo XML: {}", e)),
+            _ => {}
+        }
+    }
+
+    match (key_data, password_key) {
+        (Some(key_data), Some(password_key)) => Ok(EncryptionInfo {
+            key_data,
+            password_key,
+            integrity,
+        }),
+        (None, _) => Err("EncryptionInfo has no keyData".to_string()),
+        (_, None) => Err("Workbook is not encrypted with a password".to_string()),
+    }
+}
+
+/// Truncate, or pad with 0x36, to exactly `len` bytes
+fn fit(mut bytes: Vec<u8>, len: usize) -> Vec<u8> {
+    bytes.resize(len, 0x36);
+    bytes
+}
+
+/// The spun password hash the password key's block keys are derived from
+fn password_hash(params: &CipherParams, spin_count: u32, password: &str) -> Vec<u8> {
+    let password: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut hash = params.hash.digest(&[&params.salt, &password]);
+    for i in 0..spin_count {
+        hash = params.hash.digest(&[&i.to_le_bytes(), &hash]);
+    }
+    hash
+}
+
+/// Cipher for one of the password key's fields, keyed for `block_key`
+fn key_cipher(
+    params: &CipherParams,
+    password_hash: &[u8],
+    block_key: &[u8],
+) -> Result<Aes, String> {
+    Aes::new(&fit(
+        params.hash.digest(&[password_hash, block_key]),
+        params.key_bytes,
+    ))
+}
+
+/// IV derived from the key data salt and a block key or segment index
+fn derived_iv(params: &CipherParams, block_key: &[u8]) -> Vec<u8> {
+    fit(
+        params.hash.digest(&[&params.salt, block_key]),
+        params.block_size,
+    )
+}
+
+/// Recover the package key from the password, checking the verifier first
+fn unlock(key: &PasswordKey, password: &str) -> Result<Vec<u8>, String> {
+    let params = &key.params;
+    let hash = password_hash(params, key.spin_count, password);
+    let iv = fit(params.salt.clone(), params.block_size);
+
+    let mut input = key_cipher(params, &hash, &VERIFIER_INPUT_BLOCK)?
+        .decrypt_cbc(&iv, &key.verifier_hash_input)?;
+    input.truncate(params.salt.len());
+    let mut expected = key_cipher(params, &hash, &VERIFIER_VALUE_BLOCK)?
+        .decrypt_cbc(&iv, &key.verifier_hash_value)?;
+    expected.truncate(params.hash_size);
+    if params.hash.digest(&[&input]) != expected {
+        return Err("Incorrect password".to_string());
+    }
+
+    let mut secret =
+        key_cipher(params, &hash, &KEY_VALUE_BLOCK)?.decrypt_cbc(&iv, &key.key_value)?;
+    secret.truncate(params.key_bytes);
+    Ok(secret)
+}
+
+pub(crate) fn decrypt_workbook_impl(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    let cfb = CompoundFile::new(data)?;
+    let (info, package) = match (
+        cfb.read_stream("EncryptionInfo")?,
+        cfb.read_stream("EncryptedPackage")?,
+    ) {
+        (Some(info), Some(package)) => (info, package),
+        _ => return Err("Not an encrypted workbook".to_string()),
+    };
+
+    let info = parse_encryption_info(&info)?;
+    let key_data = &info.key_data;
+    let secret = unlock(&info.password_key, password)?;
+    let aes = Aes::new(&secret)?;
+
+    if let Some((hmac_key, hmac_value)) = &info.integrity {
+        let mut hmac_key =
+            aes.decrypt_cbc(&derived_iv(key_data, &INTEGRITY_KEY_BLOCK), hmac_key)?;
+        hmac_key.truncate(key_data.hash_size);
+        let mut expected =
+            aes.decrypt_cbc(&derived_iv(key_data, &INTEGRITY_VALUE_BLOCK), hmac_value)?;
+        expected.truncate(key_data.hash_size);
+        if hmac(key_data.hash, &hmac_key, &package) != expected {
+            return Err("Encrypted package failed its integrity check".to_string());
+        }
+    }
+
+    let size = read_u64(&package, 0).ok_or("EncryptedPackage is truncated")? as usize;
+    let mut out = Vec::with_capacity(package.len());
+    for (index, segment) in package[8..].chunks(SEGMENT_LENGTH).enumerate() {
+        let iv = derived_iv(key_data, &(index as u32).to_le_bytes());
+        // Trailing bytes past the last whole block are padding
+        let whole = segment.len() - segment.len() % 16;
+        out.extend(aes.decrypt_cbc(&iv, &segment[..whole])?);
+    }
+
+    if out.len() < size {
+        return Err("EncryptedPackage is truncated".to_string());
+    }
+    out.truncate(size);
+    Ok(out)
+}
+
+/// Encrypt a ZIP package with a password, as Excel does: AES-256, SHA-512
+/// and a 100,000-round spin count
+#[cfg(feature = "wasm")]
+pub(crate) fn encrypt_with_password(package: &[u8], password: &str) -> Result<Vec<u8>, JsValue> {
+    if password.is_empty() {
+        return Err(JsValue::from_str("Encryption password must not be empty"));
+    }
+    Ok(encrypt_package(
+        package,
+        password,
+        &random_bytes(ENCRYPTION_RANDOM_BYTES)?,
+    )?)
+}
+
+/// Salts, package key, verifier and HMAC key
+const ENCRYPTION_RANDOM_BYTES: usize = 16 + 16 + 32 + 16 + 64;
+
+/// Spin count Excel uses when saving with a password
+const SPIN_COUNT: u32 = 100_000;
+
+fn write_params(salt: &[u8]) -> CipherParams {
+    CipherParams {
+        salt: salt.to_vec(),
+        block_size: 16,
+        key_bytes: 32,
+        hash_size: 64,
+        hash: HashAlgorithm::Sha512,
+    }
+}
+
+/// Attributes shared by `keyData` and `p:encryptedKey`
+fn params_xml(params: &CipherParams) -> String {
+    format!(
+        "saltSize=\"{}\" blockSize=\"{}\" keyBits=\"{}\" hashSize=\"{}\" \
+         cipherAlgorithm=\"AES\" cipherChaining=\"ChainingModeCBC\" \
+         hashAlgorithm=\"SHA512\" saltValue=\"{}\"",
+        params.salt.len(),
+        params.block_size,
+        params.key_bytes * 8,
+        params.hash_size,
+        base64_encode(&params.salt)
+    )
+}
+
+/// Build the encrypted compound file; `random` supplies [`ENCRYPTION_RANDOM_BYTES`]
+fn encrypt_package(package: &[u8], password: &str, random: &[u8]) -> Result<Vec<u8>, String> {
+    if random.len() < ENCRYPTION_RANDOM_BYTES {
+        return Err("Not enough random bytes for encryption".to_string());
+    }
+    let (key_salt, rest) = random.split_at(16);
+    let (password_salt, rest) = rest.split_at(16);
+    let (secret, rest) = rest.split_at(32);
+    let (verifier, hmac_key) = rest.split_at(16);
+
+    let key_data = write_params(key_salt);
+    let aes = Aes::new(secret)?;
+    let mut encrypted = (package.len() as u64).to_le_bytes().to_vec();
+    for (index, segment) in package.chunks(SEGMENT_LENGTH).enumerate() {
+        let iv = derived_iv(&key_data, &(index as u32).to_le_bytes());
+        encrypted.extend(aes.encrypt_cbc(&iv, segment));
+    }
+
+    let hmac_value = hmac(key_data.hash, hmac_key, &encrypted);
+    let encrypted_hmac_key =
+        aes.encrypt_cbc(&derived_iv(&key_data, &INTEGRITY_KEY_BLOCK), hmac_key);
+    let encrypted_hmac_value =
+        aes.encrypt_cbc(&derived_iv(&key_data, &INTEGRITY_VALUE_BLOCK), &hmac_value);
+
+    let key_params = write_params(password_salt);
+    let hash = password_hash(&key_params, SPIN_COUNT, password);
+    let encrypt_field = |block_key: &[u8], data: &[u8]| {
+        Ok::<_, String>(key_cipher(&key_params, &hash, block_key)?.encrypt_cbc(password_salt, data))
+    };
+    let verifier_input = encrypt_field(&VERIFIER_INPUT_BLOCK, verifier)?;
+    let verifier_value =
+        encrypt_field(&VERIFIER_VALUE_BLOCK, &key_params.hash.digest(&[verifier]))?;
+    let key_value = encrypt_field(&KEY_VALUE_BLOCK, secret)?;
+
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\r\n\
+         <encryption xmlns=\"http://schemas.microsoft.com/office/2006/encryption\" \
+         xmlns:p=\"http://schemas.microsoft.com/office/2006/keyEncryptor/password\" \
+         xmlns:c=\"http://schemas.microsoft.com/office/2006/keyEncryptor/certificate\">\
+         <keyData {}/>\
+         <dataIntegrity encryptedHmacKey=\"{}\" encryptedHmacValue=\"{}\"/>\
+         <keyEncryptors><keyEncryptor uri=\"http://schemas.microsoft.com/office/2006/keyEncryptor/password\">\
+         <p:encryptedKey spinCount=\"{}\" {} encryptedVerifierHashInput=\"{}\" \
+         encryptedVerifierHashValue=\"{}\" encryptedKeyValue=\"{}\"/>\
+         </keyEncryptor></keyEncryptors></encryption>",
+        params_xml(&key_data),
+        base64_encode(&encrypted_hmac_key),
+        base64_encode(&encrypted_hmac_value),
+        SPIN_COUNT,
+        params_xml(&key_params),
+        base64_encode(&verifier_input),
+        base64_encode(&verifier_value),
+        base64_encode(&key_value),
+    );
+    // Version 4.4 with the fAgile reserved flag
+    let mut info = vec![4, 0, 4, 0, 0x40, 0, 0, 0];
+    info.extend_from_slice(xml.as_bytes());
+
+    Ok(write_compound_file(&[
+        ("EncryptionInfo", &info),
+        ("EncryptedPackage", &encrypted),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfb::write_compound_file;
+
+    /// Produced with a reference implementation: password "secret",
+    /// AES-128 / SHA-512 and a spin count of 1000
+    const ENCRYPTION_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<encryption xmlns="http://schemas.microsoft.com/office/2006/encryption" xmlns:p="http://schemas.microsoft.com/office/2006/keyEncryptor/password"><keyData saltSize="16" blockSize="16" keyBits="128" hashSize="64" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512" saltValue="AAECAwQFBgcICQoLDA0ODw=="/><keyEncryptors><keyEncryptor uri="http://schemas.microsoft.com/office/2006/keyEncryptor/password"><p:encryptedKey spinCount="1000" saltSize="16" blockSize="16" keyBits="128" hashSize="64" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512" saltValue="EBESExQVFhcYGRobHB0eHw==" encryptedVerifierHashInput="Yh1SEvJ+L6v9MHYcpkyTPA==" encryptedVerifierHashValue="/lju52nLbUcsJjftcYfp9S+MgeDVAoLXfak0JYXwknWNTIuXGMZGUeafLbnWKM1rqN6fr23bAqKBKvm7rhCGrQ==" encryptedKeyValue="SEHKTqLfj08kv/rYe9pPAQ=="/></keyEncryptor></keyEncryptors></encryption>"#;
+
+    const PACKAGE: &str =
+        "IwAAAAAAAAD0GVSYwP4EpyyNJFsjNLWZT2skEvHuycrNvPqEqa5j93iN9iX8FqtX0UFHONz4OMg=";
+    const PLAINTEXT: &[u8] = b"PK\x03\x04 cellify encrypted package test";
+
+    fn encrypted_file(xml: &str) -> Vec<u8> {
+        let mut info = vec![4, 0, 4, 0, 0x40, 0, 0, 0];
+        info.extend_from_slice(xml.as_bytes());
+        let package = base64_decode(PACKAGE).unwrap();
+        write_compound_file(&[("EncryptionInfo", &info), ("EncryptedPackage", &package)])
+    }
+
+    #[test]
+    fn test_decrypt_workbook() {
+        let data = encrypted_file(ENCRYPTION_XML);
+        assert!(is_encrypted_workbook(&data));
+        assert!(!is_encrypted_workbook(b"PK\x03\x04"));
+
+        let plain = decrypt_workbook_impl(&data, "secret").unwrap();
+        assert_eq!(plain, PLAINTEXT);
+        assert_eq!(
+            decrypt_workbook_impl(&data, "wrong").unwrap_err(),
+            "Incorrect password"
+        );
+
+        // A spin count past the format's maximum would take hours to spin
+        let xml = ENCRYPTION_XML.replace("spinCount=\"1000\"", "spinCount=\"4294967295\"");
+        let error = decrypt_workbook_impl(&encrypted_file(&xml), "secret").unwrap_err();
+        assert!(error.contains("spinCount"), "{}", error);
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let package: Vec<u8> = (0..9000u32).map(|i| (i % 251) as u8).collect();
+        let random: Vec<u8> = (0..ENCRYPTION_RANDOM_BYTES as u8).collect();
+        let mut file = encrypt_package(&package, "pässword", &random).unwrap();
+
+        assert!(is_encrypted_workbook(&file));
+        assert_eq!(decrypt_workbook_impl(&file, "pässword").unwrap(), package);
+
+        // Flip a byte of the encrypted payload, which sits after the header
+        let at = file.len() / 2;
+        file[at] ^= 1;
+        assert!(decrypt_workbook_impl(&file, "pässword").is_err());
+    }
+}
@@ -0,0 +1,1569 @@
+//! Formula evaluation
+//!
+//! Formulas are parsed from [`tokenize`] output into an expression tree and
+//! evaluated over the workbook's current cell values. Recalculation walks
+//! the dependency graph so every formula sees up-to-date precedents; cells
+//! in a cycle are evaluated once each, reading each other's previous values.
+//!
+//! Errors travel as `Err` holding the Excel error code (`#VALUE!`, `#N/A`,
+//! ...) so `?` propagates them the way Excel does.
+
+use crate::formula::{tokenize, Notation, Reference, Token, MAX_COLS, MAX_ROWS};
+use crate::graph::{find_defined_name, formula_graph, sheet_index};
+use crate::reference::{cell_ref, parse_cell_ref};
+use crate::table::{resolve_structured_reference, ParsedTable};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{
+    days_from_civil, format_number, is_date_format, resolve_cell_value, serial_to_datetime,
+    CellValue, DateTime,
+};
+use crate::{ParsedDefinedName, ParsedSheet};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Defined names may refer to other names; stop runaway chains here
+const MAX_NAME_DEPTH: usize = 32;
+
+/// Workbook contents to recalculate
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalWorkbook {
+    pub sheets: Vec<ParsedSheet>,
+    #[serde(default)]
+    pub shared_strings: Vec<String>,
+    /// `local_sheet_id` is taken as an index into `sheets`
+    #[serde(default)]
+    pub defined_names: Vec<ParsedDefinedName>,
+    #[serde(default)]
+    pub tables: Vec<ParsedTable>,
+    #[serde(default)]
+    pub date1904: bool,
+}
+
+/// A recalculated formula cell, typed like [`crate::ParsedCell`]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct EvaluatedCell {
+    pub sheet: String,
+    pub reference: String,
+    /// `None` for numbers, otherwise "str", "b" or "e"
+    pub cell_type: Option<String>,
+    pub value: String,
+}
+
+/// Recalculate every formula cell, returning the new values in sheet and row order
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn recalculate_workbook(workbook: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let workbook: EvalWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        let cells = recalculate_workbook_impl(&workbook).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&cells)?)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(String),
+    Ref(Reference),
+    Name(String),
+    /// `Table1[Column]`, resolved against the workbook's tables when evaluated
+    StructuredRef(String),
+    /// Array constant, row by row
+    Array(Vec<Vec<CellValue>>),
+    /// An omitted function argument, as in `IF(A1,,1)`
+    Missing,
+    Negate(Box<Expr>),
+    Percent(Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// Left and right binding power of an infix operator
+fn binding_power(op: &str) -> Option<(u8, u8)> {
+    Some(match op {
+        "=" | "<>" | "<" | ">" | "<=" | ">=" => (1, 2),
+        "&" => (3, 4),
+        "+" | "-" => (5, 6),
+        "*" | "/" => (7, 8),
+        "^" => (9, 10),
+        _ => return None,
+    })
+}
+
+const PERCENT_POWER: u8 = 11;
+const PREFIX_POWER: u8 = 12;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(formula: &str) -> Result<Self, String> {
+        let mut tokens: Vec<Token> = tokenize(formula, Notation::A1)?
+            .into_iter()
+            .map(|(token, _)| token)
+            .filter(|token| *token != Token::Whitespace)
+            .collect();
+        if tokens.first() == Some(&Token::Operator("=".to_string())) {
+            tokens.remove(0);
+        }
+        Ok(Parser { tokens, pos: 0 })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("Expected {:?} at end of formula", expected)),
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.expr(0)?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+        }
+    }
+
+    fn expr(&mut self, min_power: u8) -> Result<Expr, String> {
+        let mut lhs = self.primary()?;
+        while let Some(Token::Operator(op)) = self.peek() {
+            if op == "%" {
+                if PERCENT_POWER < min_power {
+                    break;
+                }
+                self.pos += 1;
+                lhs = Expr::Percent(Box::new(lhs));
+                continue;
+            }
+            let Some((left, right)) = binding_power(op) else {
+                return Err(format!("Unsupported operator {}", op));
+            };
+            if left < min_power {
+                break;
+            }
+            let op = op.clone();
+            self.pos += 1;
+            let rhs = self.expr(right)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.next().ok_or("Unexpected end of formula")?;
+        Ok(match token {
+            Token::Number(n) => Expr::Number(n),
+            Token::Text(text) => Expr::Text(text),
+            Token::Bool(b) => Expr::Bool(b),
+            Token::Error(error) => Expr::Error(error),
+            Token::Ref(reference) => Expr::Ref(reference),
+            Token::Name(name) => Expr::Name(name),
+            Token::StructuredRef(written) => Expr::StructuredRef(written),
+            Token::Operator(op) if op == "-" => Expr::Negate(Box::new(self.expr(PREFIX_POWER)?)),
+            Token::Operator(op) if op == "+" || op == "@" => self.expr(PREFIX_POWER)?,
+            Token::Open => {
+                let expr = self.expr(0)?;
+                self.expect(Token::Close)?;
+                expr
+            }
+            Token::Function(name) => {
+                self.expect(Token::Open)?;
+                let name = name.to_ascii_uppercase();
+                let name = name.strip_prefix("_XLFN.").unwrap_or(&name).to_string();
+                Expr::Call(name, self.arguments()?)
+            }
+            Token::ArrayOpen => Expr::Array(self.array()?),
+            token => return Err(format!("Unexpected {:?}", token)),
+        })
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expr>, String> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::Close) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            let arg = match self.peek() {
+                Some(Token::Separator(_)) | Some(Token::Close) => Expr::Missing,
+                _ => self.expr(0)?,
+            };
+            args.push(arg);
+            match self.next() {
+                Some(Token::Separator(_)) => {}
+                Some(Token::Close) => return Ok(args),
+                _ => return Err("Unterminated function call".to_string()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Vec<Vec<CellValue>>, String> {
+        let mut rows = vec![Vec::new()];
+        loop {
+            let negative = self.peek() == Some(&Token::Operator("-".to_string()));
+            if negative {
+                self.pos += 1;
+            }
+            let value = match self.next() {
+                Some(Token::Number(n)) if negative => CellValue::Number(-n),
+                Some(Token::Number(n)) => CellValue::Number(n),
+                Some(Token::Text(text)) if !negative => CellValue::Text(text),
+                Some(Token::Bool(b)) if !negative => CellValue::Bool(b),
+                Some(Token::Error(error)) if !negative => CellValue::Error(error),
+                _ => return Err("Invalid array constant".to_string()),
+            };
+            if let Some(row) = rows.last_mut() {
+                row.push(value);
+            }
+            match self.next() {
+                Some(Token::Separator(',')) => {}
+                Some(Token::Separator(_)) => rows.push(Vec::new()),
+                Some(Token::ArrayClose) => break,
+                _ => return Err("Unterminated array constant".to_string()),
+            }
+        }
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err("Array constant rows differ in length".to_string());
+        }
+        Ok(rows)
+    }
+}
+
+/// A block of values; trailing empty rows and columns may be left out of `cells`
+#[derive(Debug, Clone, PartialEq)]
+struct Grid {
+    rows: usize,
+    cols: usize,
+    cells: Vec<Vec<CellValue>>,
+}
+
+impl Grid {
+    fn from_rows(cells: Vec<Vec<CellValue>>) -> Self {
+        Grid {
+            rows: cells.len(),
+            cols: cells.first().map_or(0, Vec::len),
+            cells,
+        }
+    }
+
+    fn get(&self, row: usize, col: usize) -> CellValue {
+        self.cells
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .cloned()
+            .unwrap_or(CellValue::Empty)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &CellValue> {
+        self.cells.iter().flatten()
+    }
+
+    /// The values of a single row or column, for lookups
+    fn vector(&self) -> Option<Vec<CellValue>> {
+        if self.rows == 1 {
+            Some((0..self.cols).map(|c| self.get(0, c)).collect())
+        } else if self.cols == 1 {
+            Some((0..self.rows).map(|r| self.get(r, 0)).collect())
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Scalar(CellValue),
+    /// Cells read through a reference; aggregates skip their text and logicals
+    Range(Grid),
+    /// An array constant or computed array
+    Array(Grid),
+}
+
+type Outcome<T> = Result<T, String>;
+
+fn error<T>(code: &str) -> Outcome<T> {
+    Err(code.to_string())
+}
+
+impl Value {
+    /// Collapse to one value: the only cell of a reference, or an array's first
+    fn scalar(self) -> Outcome<CellValue> {
+        let value = match self {
+            Value::Scalar(value) => value,
+            Value::Range(grid) if grid.rows == 1 && grid.cols == 1 => grid.get(0, 0),
+            Value::Range(_) => return error("#VALUE!"),
+            Value::Array(grid) => grid.get(0, 0),
+        };
+        match value {
+            CellValue::Error(code) => Err(code),
+            value => Ok(value),
+        }
+    }
+}
+
+fn to_number(value: &CellValue) -> Outcome<f64> {
+    match value {
+        CellValue::Empty => Ok(0.0),
+        CellValue::Number(n) => Ok(*n),
+        CellValue::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+        CellValue::Text(text) => {
+            let trimmed = text.trim();
+            match trimmed.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f64>().map(|n| n / 100.0),
+                None => trimmed.parse::<f64>(),
+            }
+            .or_else(|_| error("#VALUE!"))
+        }
+        CellValue::Error(code) => Err(code.clone()),
+    }
+}
+
+fn to_text(value: &CellValue) -> Outcome<String> {
+    match value {
+        CellValue::Empty => Ok(String::new()),
+        CellValue::Number(n) => Ok(format_number(*n)),
+        CellValue::Bool(b) => Ok(if *b { "TRUE" } else { "FALSE" }.to_string()),
+        CellValue::Text(text) => Ok(text.clone()),
+        CellValue::Error(code) => Err(code.clone()),
+    }
+}
+
+fn to_bool(value: &CellValue) -> Outcome<bool> {
+    match value {
+        CellValue::Empty => Ok(false),
+        CellValue::Number(n) => Ok(*n != 0.0),
+        CellValue::Bool(b) => Ok(*b),
+        CellValue::Text(text) if text.eq_ignore_ascii_case("TRUE") => Ok(true),
+        CellValue::Text(text) if text.eq_ignore_ascii_case("FALSE") => Ok(false),
+        CellValue::Text(_) => error("#VALUE!"),
+        CellValue::Error(code) => Err(code.clone()),
+    }
+}
+
+fn number_result(n: f64) -> Outcome<Value> {
+    if n.is_finite() {
+        Ok(Value::Scalar(CellValue::Number(n)))
+    } else {
+        error("#NUM!")
+    }
+}
+
+/// Excel's ordering for comparison operators: numbers, then text, then logicals
+fn compare(a: &CellValue, b: &CellValue) -> Ordering {
+    fn rank(value: &CellValue) -> u8 {
+        match value {
+            CellValue::Number(_) | CellValue::Empty => 0,
+            CellValue::Text(_) => 1,
+            _ => 2,
+        }
+    }
+    match (a, b) {
+        // A blank compares as zero, an empty string or FALSE depending on the other side
+        (CellValue::Empty, CellValue::Text(t)) | (CellValue::Text(t), CellValue::Empty)
+            if t.is_empty() =>
+        {
+            Ordering::Equal
+        }
+        (CellValue::Empty, CellValue::Empty) => Ordering::Equal,
+        (CellValue::Empty, CellValue::Text(_)) => Ordering::Less,
+        (CellValue::Text(_), CellValue::Empty) => Ordering::Greater,
+        (CellValue::Empty, CellValue::Bool(b)) => false.cmp(b),
+        (CellValue::Bool(b), CellValue::Empty) => b.cmp(&false),
+        (CellValue::Empty, CellValue::Number(n)) => 0.0_f64.total_cmp(n),
+        (CellValue::Number(n), CellValue::Empty) => n.total_cmp(&0.0),
+        (CellValue::Number(x), CellValue::Number(y)) => x.total_cmp(y),
+        (CellValue::Text(x), CellValue::Text(y)) => x.to_lowercase().cmp(&y.to_lowercase()),
+        (CellValue::Bool(x), CellValue::Bool(y)) => x.cmp(y),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Ordering for lookups, where only values of the same type are comparable
+fn lookup_compare(a: &CellValue, b: &CellValue) -> Option<Ordering> {
+    match (a, b) {
+        (CellValue::Number(_), CellValue::Number(_))
+        | (CellValue::Text(_), CellValue::Text(_))
+        | (CellValue::Bool(_), CellValue::Bool(_)) => Some(compare(a, b)),
+        _ => None,
+    }
+}
+
+/// Case-insensitive match with `*`, `?` and `~` escapes, as in exact MATCH/VLOOKUP
+///
+/// Two pointers walk the pattern and the text; on a mismatch the last `*`
+/// takes one more character and matching resumes after it, so the cost is
+/// at most pattern length times text length.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    // `None` is `?`, and `~` makes the next character literal
+    enum Token {
+        Star,
+        Char(Option<char>),
+    }
+    let pattern = pattern.to_lowercase();
+    let mut chars = pattern.chars();
+    let mut tokens = Vec::new();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' => Token::Star,
+            '?' => Token::Char(None),
+            '~' => Token::Char(Some(chars.next().unwrap_or('~'))),
+            c => Token::Char(Some(c)),
+        });
+    }
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*`, and where in the text it stopped
+    let mut backtrack = None;
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(Token::Star) => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(Token::Char(c)) if c.is_none_or(|c| c == text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((after_star, from)) => {
+                    p = after_star;
+                    t = from + 1;
+                    backtrack = Some((after_star, t));
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[p..].iter().all(|token| matches!(token, Token::Star))
+}
+
+/// Position of `target` in `values`: `mode` 0 is an exact match, 1 the largest
+/// value not above it (ascending data) and -1 the smallest not below it
+fn find_position(target: &CellValue, values: &[CellValue], mode: i32) -> Outcome<usize> {
+    if mode == 0 {
+        return values
+            .iter()
+            .position(|value| match (target, value) {
+                (CellValue::Text(pattern), CellValue::Text(text)) => wildcard_match(pattern, text),
+                _ => lookup_compare(target, value) == Some(Ordering::Equal),
+            })
+            .ok_or_else(|| "#N/A".to_string());
+    }
+    let wanted = if mode > 0 {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    };
+    let mut found = None;
+    for (i, value) in values.iter().enumerate() {
+        match lookup_compare(value, target) {
+            Some(Ordering::Equal) => return Ok(i),
+            Some(ordering) if ordering == wanted => break,
+            Some(_) => found = Some(i),
+            None => {}
+        }
+    }
+    found.ok_or_else(|| "#N/A".to_string())
+}
+
+fn round_half_away(n: f64, digits: i32) -> f64 {
+    let factor = 10f64.powi(digits);
+    let scaled = n * factor;
+    // Nudge values like 2.675 that sit just under the half in binary
+    let rounded = (scaled + scaled.signum() * scaled.abs() * f64::EPSILON * 4.0).round();
+    rounded / factor
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+/// Serial number of a date, letting months and days overflow as DATE does
+fn date_serial(year: i64, month: i64, day: i64, date1904: bool) -> Outcome<f64> {
+    let months = year * 12 + month - 1;
+    let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+    if !(0..=9999).contains(&year) {
+        return error("#NUM!");
+    }
+    let days = days_from_civil(year as i32, month as u32, 1) + day - 1;
+    let serial = if date1904 {
+        days - days_from_civil(1904, 1, 1)
+    } else {
+        let serial = days - days_from_civil(1899, 12, 31);
+        // Account for the phantom 1900-02-29
+        if serial >= 60 {
+            serial + 1
+        } else {
+            serial
+        }
+    };
+    if serial < 0 {
+        return error("#NUM!");
+    }
+    Ok(serial as f64)
+}
+
+/// Render a number with an Excel number format code, as TEXT does
+fn format_value(n: f64, code: &str, date1904: bool) -> Outcome<String> {
+    let sections: Vec<&str> = code.split(';').collect();
+    let (section, n) = match sections.as_slice() {
+        [positive, negative, ..] if n < 0.0 => (*negative, -n),
+        [_, _, zero, ..] if n == 0.0 => (*zero, n),
+        [first, ..] => (*first, n),
+        [] => (code, n),
+    };
+    if section.is_empty() || section.eq_ignore_ascii_case("General") {
+        return Ok(format_number(n));
+    }
+    if is_date_format(0, Some(section)) {
+        let dt = serial_to_datetime(n, date1904).ok_or("#VALUE!")?;
+        return Ok(format_date(&dt, section));
+    }
+
+    // Literal text around the digit placeholders passes through unchanged
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    let mut pattern = String::new();
+    let mut chars = section.chars().peekable();
+    let mut percent = false;
+    while let Some(ch) = chars.next() {
+        let mut literal = String::new();
+        match ch {
+            '"' => literal.extend(chars.by_ref().take_while(|&c| c != '"')),
+            '\\' => literal.extend(chars.next()),
+            // `_x` pads with the width of x
+            '_' => {
+                chars.next();
+                literal.push(' ');
+            }
+            // Colors and conditions
+            '[' => chars.by_ref().take_while(|&c| c != ']').for_each(drop),
+            '%' => {
+                percent = true;
+                literal.push('%');
+            }
+            '0' | '#' | '?' | '.' | ',' if suffix.is_empty() => {
+                pattern.push(ch);
+                continue;
+            }
+            _ => literal.push(ch),
+        }
+        if pattern.is_empty() {
+            prefix.push_str(&literal);
+        } else {
+            suffix.push_str(&literal);
+        }
+    }
+    if pattern.is_empty() {
+        return Ok(prefix + &suffix);
+    }
+
+    let n = if percent { n * 100.0 } else { n };
+    let (int_pattern, frac_pattern) = pattern.split_once('.').unwrap_or((&pattern, ""));
+    // Trailing commas scale by a thousand each
+    let scale_commas = int_pattern.len() - int_pattern.trim_end_matches(',').len();
+    let int_pattern = int_pattern.trim_end_matches(',');
+    let n = n / 1000f64.powi(scale_commas as i32);
+    let grouped = int_pattern.contains(',');
+    let decimals = frac_pattern
+        .chars()
+        .filter(|c| matches!(c, '0' | '#' | '?'))
+        .count();
+    let min_int = int_pattern.chars().filter(|&c| c == '0').count();
+    let min_frac = frac_pattern.chars().filter(|&c| c == '0').count();
+
+    let rounded = round_half_away(n.abs(), decimals as i32);
+    let formatted = format!("{:.*}", decimals, rounded);
+    let (int_digits, frac_digits) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let mut int_digits = int_digits.trim_start_matches('0').to_string();
+    while int_digits.len() < min_int {
+        int_digits.insert(0, '0');
+    }
+    if grouped {
+        let bytes = int_digits.as_bytes();
+        let mut with_commas = String::new();
+        for (i, digit) in bytes.iter().enumerate() {
+            if i > 0 && (bytes.len() - i).is_multiple_of(3) {
+                with_commas.push(',');
+            }
+            with_commas.push(*digit as char);
+        }
+        int_digits = with_commas;
+    }
+    let mut frac_digits = frac_digits.to_string();
+    while frac_digits.len() > min_frac && frac_digits.ends_with('0') {
+        frac_digits.pop();
+    }
+
+    let mut out = String::new();
+    // The negative section supplies its own sign
+    if n < 0.0 && sections.len() < 2 && rounded != 0.0 {
+        out.push('-');
+    }
+    out.push_str(&prefix);
+    out.push_str(&int_digits);
+    if !frac_pattern.is_empty() && (!frac_digits.is_empty() || pattern.ends_with('.')) {
+        out.push('.');
+        out.push_str(&frac_digits);
+    }
+    out.push_str(&suffix);
+    Ok(out)
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Day of the week, 0 for Sunday
+fn weekday(dt: &DateTime) -> usize {
+    (days_from_civil(dt.year, dt.month, dt.day) + 4).rem_euclid(7) as usize
+}
+
+/// Render a date/time format code such as `dddd, mmmm d, yyyy h:mm AM/PM`
+fn format_date(dt: &DateTime, code: &str) -> String {
+    let lower = code.to_ascii_lowercase();
+    let twelve_hour = lower.contains("am/pm") || lower.contains("a/p");
+    let chars: Vec<char> = code.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    // `m` after an hour or before seconds means minutes
+    let mut after_hour = false;
+    while i < chars.len() {
+        let ch = chars[i];
+        let run = chars[i..]
+            .iter()
+            .take_while(|c| c.eq_ignore_ascii_case(&ch))
+            .count();
+        let rest: String = chars[i..].iter().collect::<String>().to_ascii_lowercase();
+        match ch.to_ascii_lowercase() {
+            'y' => {
+                after_hour = false;
+                if run > 2 {
+                    out.push_str(&format!("{:04}", dt.year));
+                } else {
+                    out.push_str(&format!("{:02}", dt.year.rem_euclid(100)));
+                }
+            }
+            'm' => {
+                let next = chars[i + run..]
+                    .iter()
+                    .find(|c| c.is_ascii_alphabetic())
+                    .map(|c| c.to_ascii_lowercase());
+                if (after_hour || next == Some('s')) && run <= 2 {
+                    out.push_str(&format!("{:0width$}", dt.minute, width = run));
+                } else {
+                    let name = MONTHS[dt.month as usize - 1];
+                    match run {
+                        1 => out.push_str(&dt.month.to_string()),
+                        2 => out.push_str(&format!("{:02}", dt.month)),
+                        3 => out.push_str(&name[..3]),
+                        5 => out.push_str(&name[..1]),
+                        _ => out.push_str(name),
+                    }
+                }
+                after_hour = false;
+            }
+            'd' => {
+                after_hour = false;
+                let name = WEEKDAYS[weekday(dt)];
+                match run {
+                    1 => out.push_str(&dt.day.to_string()),
+                    2 => out.push_str(&format!("{:02}", dt.day)),
+                    3 => out.push_str(&name[..3]),
+                    _ => out.push_str(name),
+                }
+            }
+            'h' => {
+                let hour = if twelve_hour {
+                    (dt.hour + 11) % 12 + 1
+                } else {
+                    dt.hour
+                };
+                out.push_str(&format!("{:0width$}", hour, width = run.min(2)));
+                after_hour = true;
+            }
+            's' => {
+                out.push_str(&format!("{:0width$}", dt.second, width = run.min(2)));
+            }
+            'a' if rest.starts_with("am/pm") => {
+                out.push_str(if dt.hour < 12 { "AM" } else { "PM" });
+                i += 5;
+                continue;
+            }
+            'a' if rest.starts_with("a/p") => {
+                out.push(if dt.hour < 12 { 'A' } else { 'P' });
+                i += 3;
+                continue;
+            }
+            '"' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .map_or(chars.len(), |p| i + 1 + p);
+                out.extend(&chars[i + 1..end]);
+                i = end + 1;
+                continue;
+            }
+            '\\' => {
+                out.extend(chars.get(i + 1));
+                i += 2;
+                continue;
+            }
+            '[' => {
+                i += chars[i..].iter().position(|&c| c == ']').unwrap_or(0) + 1;
+                continue;
+            }
+            _ => {
+                out.push(ch);
+                i += 1;
+                continue;
+            }
+        }
+        i += run;
+    }
+    out
+}
+
+struct Evaluator<'a> {
+    workbook: &'a EvalWorkbook,
+    /// Current value of every non-empty cell, per sheet
+    values: Vec<HashMap<(u32, u32), CellValue>>,
+    /// Last used (row, col) per sheet, to bound whole-row/column references
+    extents: Vec<(u32, u32)>,
+    /// Cell being evaluated, for `[@Column]` references
+    cell: (u32, u32),
+    name_depth: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(workbook: &'a EvalWorkbook) -> Self {
+        let mut values = Vec::with_capacity(workbook.sheets.len());
+        let mut extents = Vec::with_capacity(workbook.sheets.len());
+        for sheet in &workbook.sheets {
+            let mut cells = HashMap::new();
+            let mut extent = (0, 0);
+            for row in &sheet.worksheet.rows {
+                for cell in &row.cells {
+                    let Some((r, c)) = parse_cell_ref(&cell.reference) else {
+                        continue;
+                    };
+                    extent = (extent.0.max(r), extent.1.max(c));
+                    cells.insert((r, c), resolve_cell_value(cell, &workbook.shared_strings));
+                }
+            }
+            values.push(cells);
+            extents.push(extent);
+        }
+        Evaluator {
+            workbook,
+            values,
+            extents,
+            cell: (1, 1),
+            name_depth: 0,
+        }
+    }
+
+    fn evaluate_formula(&mut self, formula: &str, sheet: usize) -> CellValue {
+        let result = Parser::new(formula)
+            .and_then(Parser::parse)
+            .map_err(|_| "#NAME?".to_string())
+            .and_then(|expr| self.eval(&expr, sheet)?.scalar());
+        match result {
+            // A formula pointing at a blank cell shows 0
+            Ok(CellValue::Empty) => CellValue::Number(0.0),
+            Ok(value) => value,
+            Err(code) => CellValue::Error(code),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, sheet: usize) -> Outcome<Value> {
+        Ok(match expr {
+            Expr::Number(n) => Value::Scalar(CellValue::Number(*n)),
+            Expr::Text(text) => Value::Scalar(CellValue::Text(text.clone())),
+            Expr::Bool(b) => Value::Scalar(CellValue::Bool(*b)),
+            Expr::Error(code) => return Err(code.clone()),
+            Expr::Missing => Value::Scalar(CellValue::Empty),
+            Expr::Array(rows) => Value::Array(Grid::from_rows(rows.clone())),
+            Expr::Ref(reference) => self.reference(reference, sheet)?,
+            Expr::Name(name) => self.name(name, sheet)?,
+            Expr::StructuredRef(written) => self.structured(written, sheet)?,
+            Expr::Negate(operand) => {
+                let n = to_number(&self.eval(operand, sheet)?.scalar()?)?;
+                Value::Scalar(CellValue::Number(-n))
+            }
+            Expr::Percent(operand) => {
+                let n = to_number(&self.eval(operand, sheet)?.scalar()?)?;
+                Value::Scalar(CellValue::Number(n / 100.0))
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, sheet)?.scalar()?;
+                let rhs = self.eval(rhs, sheet)?.scalar()?;
+                binary(op, &lhs, &rhs)?
+            }
+            Expr::Call(name, args) => self.call(name, args, sheet)?,
+        })
+    }
+
+    fn reference(&mut self, reference: &Reference, sheet: usize) -> Outcome<Value> {
+        let first = match &reference.sheet {
+            Some(name) => sheet_index(&self.workbook.sheets, name).ok_or("#REF!")?,
+            None => sheet,
+        };
+        let last = match &reference.last_sheet {
+            Some(name) => sheet_index(&self.workbook.sheets, name).ok_or("#REF!")?,
+            None => first,
+        };
+
+        let end = reference.end.unwrap_or(reference.start);
+        let r1 = reference.start.row.map_or(1, |a| a.index);
+        let c1 = reference.start.col.map_or(1, |a| a.index);
+        let r2 = end.row.map_or(MAX_ROWS, |a| a.index);
+        let c2 = end.col.map_or(MAX_COLS, |a| a.index);
+        let (r1, c1, r2, c2) = (r1.min(r2), c1.min(c2), r1.max(r2), c1.max(c2));
+
+        // Cells past the last used row or column are blank and left out.
+        // 3D references stack each sheet's block vertically.
+        let sheets = first.min(last)..=first.max(last);
+        let max_row = sheets.clone().map(|s| self.extents[s].0).max().unwrap_or(0);
+        let mut cells = Vec::new();
+        for s in sheets.clone() {
+            let max_col = self.extents[s].1;
+            for r in r1..=r2.min(max_row) {
+                let row = (c1..=c2.min(max_col))
+                    .map(|c| {
+                        self.values[s]
+                            .get(&(r, c))
+                            .cloned()
+                            .unwrap_or(CellValue::Empty)
+                    })
+                    .collect();
+                cells.push(row);
+            }
+        }
+        let rows = if sheets.count() > 1 {
+            cells.len()
+        } else {
+            (r2 - r1 + 1) as usize
+        };
+        Ok(Value::Range(Grid {
+            rows,
+            cols: (c2 - c1 + 1) as usize,
+            cells,
+        }))
+    }
+
+    fn structured(&mut self, written: &str, sheet: usize) -> Outcome<Value> {
+        let workbook = self.workbook;
+        let reference = resolve_structured_reference(
+            written,
+            &workbook.tables,
+            Some(&workbook.sheets[sheet].name),
+            Some(self.cell),
+        )?;
+        self.reference(&reference, sheet)
+    }
+
+    fn name(&mut self, written: &str, sheet: usize) -> Outcome<Value> {
+        let workbook = self.workbook;
+        let Some((defined, scope)) =
+            find_defined_name(&workbook.sheets, &workbook.defined_names, written, sheet)
+        else {
+            // A bare table name stands for its data rows
+            return self
+                .structured(&format!("{}[]", written), sheet)
+                .or_else(|_| error("#NAME?"));
+        };
+        if self.name_depth >= MAX_NAME_DEPTH {
+            return error("#REF!");
+        }
+        let expr = Parser::new(&defined.value)
+            .and_then(Parser::parse)
+            .map_err(|_| "#NAME?".to_string())?;
+        self.name_depth += 1;
+        let value = self.eval(&expr, scope);
+        self.name_depth -= 1;
+        value
+    }
+
+    fn scalar(&mut self, expr: &Expr, sheet: usize) -> Outcome<CellValue> {
+        self.eval(expr, sheet)?.scalar()
+    }
+
+    fn number(&mut self, expr: &Expr, sheet: usize) -> Outcome<f64> {
+        to_number(&self.scalar(expr, sheet)?)
+    }
+
+    fn text(&mut self, expr: &Expr, sheet: usize) -> Outcome<String> {
+        to_text(&self.scalar(expr, sheet)?)
+    }
+
+    /// Optional argument `index`, or `default` when it is absent or omitted
+    fn number_or(
+        &mut self,
+        args: &[Expr],
+        index: usize,
+        default: f64,
+        sheet: usize,
+    ) -> Outcome<f64> {
+        match args.get(index) {
+            None | Some(Expr::Missing) => Ok(default),
+            Some(expr) => self.number(expr, sheet),
+        }
+    }
+
+    fn grid(&mut self, expr: &Expr, sheet: usize) -> Outcome<Grid> {
+        match self.eval(expr, sheet)? {
+            Value::Range(grid) | Value::Array(grid) => Ok(grid),
+            Value::Scalar(CellValue::Error(code)) => Err(code),
+            Value::Scalar(value) => Ok(Grid::from_rows(vec![vec![value]])),
+        }
+    }
+
+    /// Numbers for aggregate functions: referenced text, logicals and blanks
+    /// are skipped, while values typed directly into the call are coerced
+    fn numbers(&mut self, args: &[Expr], sheet: usize) -> Outcome<Vec<f64>> {
+        let mut numbers = Vec::new();
+        for arg in args {
+            match self.eval(arg, sheet)? {
+                Value::Scalar(CellValue::Empty) => {}
+                Value::Scalar(value) => numbers.push(to_number(&value)?),
+                Value::Range(grid) | Value::Array(grid) => {
+                    for value in grid.values() {
+                        match value {
+                            CellValue::Number(n) => numbers.push(*n),
+                            CellValue::Error(code) => return Err(code.clone()),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        Ok(numbers)
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], sheet: usize) -> Outcome<Value> {
+        let arity = |min: usize, max: usize| -> Outcome<()> {
+            if (min..=max).contains(&args.len()) {
+                Ok(())
+            } else {
+                error("#VALUE!")
+            }
+        };
+        let text = |s: String| -> Outcome<Value> { Ok(Value::Scalar(CellValue::Text(s))) };
+        let boolean = |b: bool| -> Outcome<Value> { Ok(Value::Scalar(CellValue::Bool(b))) };
+        let date1904 = self.workbook.date1904;
+
+        match name {
+            "SUM" => number_result(self.numbers(args, sheet)?.iter().sum()),
+            "PRODUCT" => number_result(self.numbers(args, sheet)?.iter().product()),
+            "AVERAGE" => {
+                let numbers = self.numbers(args, sheet)?;
+                if numbers.is_empty() {
+                    return error("#DIV/0!");
+                }
+                number_result(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+            "MIN" | "MAX" => {
+                let numbers = self.numbers(args, sheet)?;
+                let pick = if name == "MIN" { f64::min } else { f64::max };
+                number_result(numbers.into_iter().reduce(pick).unwrap_or(0.0))
+            }
+            "COUNT" | "COUNTA" => {
+                let mut count = 0;
+                for arg in args {
+                    let counts = |value: &CellValue| match value {
+                        CellValue::Number(_) => true,
+                        CellValue::Empty => false,
+                        _ => name == "COUNTA",
+                    };
+                    match self.eval(arg, sheet) {
+                        Ok(Value::Scalar(value)) => {
+                            let numeric = name == "COUNT" && to_number(&value).is_ok();
+                            count += usize::from(counts(&value) || numeric);
+                        }
+                        Ok(Value::Range(grid) | Value::Array(grid)) => {
+                            count += grid.values().filter(|v| counts(v)).count();
+                        }
+                        Err(_) => count += usize::from(name == "COUNTA"),
+                    }
+                }
+                number_result(count as f64)
+            }
+            "ABS" | "INT" | "SQRT" => {
+                arity(1, 1)?;
+                let n = self.number(&args[0], sheet)?;
+                match name {
+                    "ABS" => number_result(n.abs()),
+                    "INT" => number_result(n.floor()),
+                    _ if n < 0.0 => error("#NUM!"),
+                    _ => number_result(n.sqrt()),
+                }
+            }
+            "ROUND" | "ROUNDUP" | "ROUNDDOWN" => {
+                arity(2, 2)?;
+                let n = self.number(&args[0], sheet)?;
+                let digits = self.number(&args[1], sheet)?.trunc() as i32;
+                let factor = 10f64.powi(digits);
+                match name {
+                    "ROUND" => number_result(round_half_away(n, digits)),
+                    "ROUNDUP" => number_result((n * factor).abs().ceil().copysign(n) / factor),
+                    _ => number_result((n * factor).trunc() / factor),
+                }
+            }
+            "MOD" => {
+                arity(2, 2)?;
+                let n = self.number(&args[0], sheet)?;
+                let d = self.number(&args[1], sheet)?;
+                if d == 0.0 {
+                    return error("#DIV/0!");
+                }
+                number_result(n - d * (n / d).floor())
+            }
+            "IF" => {
+                arity(1, 3)?;
+                let condition = to_bool(&self.scalar(&args[0], sheet)?)?;
+                match (condition, args.get(1), args.get(2)) {
+                    (true, Some(then), _) => self.eval(then, sheet),
+                    (false, _, Some(otherwise)) => self.eval(otherwise, sheet),
+                    _ => boolean(condition),
+                }
+                .map(|value| match value {
+                    // An omitted branch evaluates to 0
+                    Value::Scalar(CellValue::Empty) => Value::Scalar(CellValue::Number(0.0)),
+                    value => value,
+                })
+            }
+            "IFERROR" | "IFNA" => {
+                arity(2, 2)?;
+                match self.scalar(&args[0], sheet) {
+                    Err(code) if name == "IFERROR" || code == "#N/A" => self.eval(&args[1], sheet),
+                    result => result.map(Value::Scalar),
+                }
+            }
+            "AND" | "OR" => {
+                let mut values = Vec::new();
+                for arg in args {
+                    match self.eval(arg, sheet)? {
+                        Value::Scalar(value) => values.push(to_bool(&value)?),
+                        Value::Range(grid) | Value::Array(grid) => {
+                            for value in grid.values() {
+                                match value {
+                                    CellValue::Number(_) | CellValue::Bool(_) => {
+                                        values.push(to_bool(value)?)
+                                    }
+                                    CellValue::Error(code) => return Err(code.clone()),
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                }
+                if values.is_empty() {
+                    return error("#VALUE!");
+                }
+                boolean(if name == "AND" {
+                    values.iter().all(|&b| b)
+                } else {
+                    values.iter().any(|&b| b)
+                })
+            }
+            "NOT" => {
+                arity(1, 1)?;
+                boolean(!to_bool(&self.scalar(&args[0], sheet)?)?)
+            }
+            "CONCATENATE" | "CONCAT" => {
+                let mut out = String::new();
+                for arg in args {
+                    match self.eval(arg, sheet)? {
+                        Value::Scalar(value) => out.push_str(&to_text(&value)?),
+                        Value::Range(grid) | Value::Array(grid) => {
+                            for value in grid.values() {
+                                out.push_str(&to_text(value)?);
+                            }
+                        }
+                    }
+                }
+                text(out)
+            }
+            "LEN" => {
+                arity(1, 1)?;
+                number_result(self.text(&args[0], sheet)?.chars().count() as f64)
+            }
+            "UPPER" | "LOWER" | "TRIM" => {
+                arity(1, 1)?;
+                let s = self.text(&args[0], sheet)?;
+                text(match name {
+                    "UPPER" => s.to_uppercase(),
+                    "LOWER" => s.to_lowercase(),
+                    _ => s
+                        .split(' ')
+                        .filter(|w| !w.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                })
+            }
+            "LEFT" | "RIGHT" => {
+                arity(1, 2)?;
+                let s: Vec<char> = self.text(&args[0], sheet)?.chars().collect();
+                let count = self.number_or(args, 1, 1.0, sheet)?;
+                if count < 0.0 {
+                    return error("#VALUE!");
+                }
+                let count = (count as usize).min(s.len());
+                let part = if name == "LEFT" {
+                    &s[..count]
+                } else {
+                    &s[s.len() - count..]
+                };
+                text(part.iter().collect())
+            }
+            "MID" => {
+                arity(3, 3)?;
+                let s: Vec<char> = self.text(&args[0], sheet)?.chars().collect();
+                let start = self.number(&args[1], sheet)?;
+                let count = self.number(&args[2], sheet)?;
+                if start < 1.0 || count < 0.0 {
+                    return error("#VALUE!");
+                }
+                let start = (start as usize - 1).min(s.len());
+                let end = start.saturating_add(count as usize).min(s.len());
+                text(s[start..end].iter().collect())
+            }
+            "TEXT" => {
+                arity(2, 2)?;
+                let value = self.scalar(&args[0], sheet)?;
+                let code = self.text(&args[1], sheet)?;
+                match to_number(&value) {
+                    Ok(n) if !matches!(value, CellValue::Bool(_)) => {
+                        text(format_value(n, &code, date1904)?)
+                    }
+                    // Text passes through, substituted for `@` when present
+                    _ => {
+                        let s = to_text(&value)?;
+                        text(if code.contains('@') {
+                            code.replace('@', &s).replace('"', "")
+                        } else {
+                            s
+                        })
+                    }
+                }
+            }
+            "DATE" => {
+                arity(3, 3)?;
+                let mut year = self.number(&args[0], sheet)?.trunc() as i64;
+                let month = self.number(&args[1], sheet)?.trunc() as i64;
+                let day = self.number(&args[2], sheet)?.trunc() as i64;
+                if year < 1900 {
+                    year += if date1904 { 1904 } else { 1900 };
+                }
+                number_result(date_serial(year, month, day, date1904)?)
+            }
+            "TIME" => {
+                arity(3, 3)?;
+                let h = self.number(&args[0], sheet)?.trunc();
+                let m = self.number(&args[1], sheet)?.trunc();
+                let s = self.number(&args[2], sheet)?.trunc();
+                let seconds = h * 3600.0 + m * 60.0 + s;
+                if seconds < 0.0 {
+                    return error("#NUM!");
+                }
+                number_result((seconds / 86400.0).fract())
+            }
+            "YEAR" | "MONTH" | "DAY" | "HOUR" | "MINUTE" | "SECOND" | "WEEKDAY" => {
+                arity(1, if name == "WEEKDAY" { 2 } else { 1 })?;
+                let serial = self.number(&args[0], sheet)?;
+                let dt = serial_to_datetime(serial, date1904).ok_or("#NUM!")?;
+                let part = match name {
+                    "YEAR" => dt.year as u32,
+                    "MONTH" => dt.month,
+                    "DAY" => dt.day,
+                    "HOUR" => dt.hour,
+                    "MINUTE" => dt.minute,
+                    "SECOND" => dt.second,
+                    _ => {
+                        let sunday_based = weekday(&dt) as u32;
+                        match self.number_or(args, 1, 1.0, sheet)? as u32 {
+                            1 => sunday_based + 1,
+                            2 => (sunday_based + 6) % 7 + 1,
+                            3 => (sunday_based + 6) % 7,
+                            _ => return error("#NUM!"),
+                        }
+                    }
+                };
+                number_result(part as f64)
+            }
+            "EDATE" | "EOMONTH" => {
+                arity(2, 2)?;
+                let serial = self.number(&args[0], sheet)?;
+                let months = self.number(&args[1], sheet)?.trunc() as i64;
+                let dt = serial_to_datetime(serial.trunc(), date1904).ok_or("#NUM!")?;
+                let total = dt.year as i64 * 12 + dt.month as i64 - 1 + months;
+                let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+                let last_day = days_in_month(year as i32, month);
+                let day = if name == "EOMONTH" {
+                    last_day
+                } else {
+                    dt.day.min(last_day)
+                };
+                number_result(date_serial(year, month as i64, day as i64, date1904)?)
+            }
+            "MATCH" => {
+                arity(2, 3)?;
+                let target = self.scalar(&args[0], sheet)?;
+                let values = self.grid(&args[1], sheet)?.vector().ok_or("#N/A")?;
+                let mode = match self.number_or(args, 2, 1.0, sheet)? {
+                    m if m > 0.0 => 1,
+                    m if m < 0.0 => -1,
+                    _ => 0,
+                };
+                number_result(find_position(&target, &values, mode)? as f64 + 1.0)
+            }
+            "VLOOKUP" | "HLOOKUP" => {
+                arity(3, 4)?;
+                let target = self.scalar(&args[0], sheet)?;
+                let table = self.grid(&args[1], sheet)?;
+                let index = self.number(&args[2], sheet)?.trunc();
+                let approximate = match args.get(3) {
+                    None | Some(Expr::Missing) => true,
+                    Some(arg) => to_bool(&self.scalar(arg, sheet)?)?,
+                };
+                let vertical = name == "VLOOKUP";
+                let (length, width) = if vertical {
+                    (table.rows, table.cols)
+                } else {
+                    (table.cols, table.rows)
+                };
+                if index < 1.0 {
+                    return error("#VALUE!");
+                }
+                if index as usize > width {
+                    return error("#REF!");
+                }
+                let keys: Vec<CellValue> = (0..length)
+                    .map(|i| {
+                        if vertical {
+                            table.get(i, 0)
+                        } else {
+                            table.get(0, i)
+                        }
+                    })
+                    .collect();
+                let found = find_position(&target, &keys, i32::from(approximate))?;
+                let index = index as usize - 1;
+                let value = if vertical {
+                    table.get(found, index)
+                } else {
+                    table.get(index, found)
+                };
+                Ok(Value::Scalar(value))
+            }
+            "INDEX" => {
+                arity(2, 3)?;
+                let grid = self.grid(&args[0], sheet)?;
+                let mut row = self.number(&args[1], sheet)?.trunc();
+                let mut col = self.number_or(args, 2, 0.0, sheet)?.trunc();
+                // A single row indexed by one number picks a column
+                if grid.rows == 1 && args.len() == 2 {
+                    (row, col) = (1.0, row);
+                }
+                if row < 0.0 || col < 0.0 || row as usize > grid.rows || col as usize > grid.cols {
+                    return error("#REF!");
+                }
+                let (row, col) = (row as usize, col as usize);
+                match (row, col) {
+                    (0, 0) => Ok(Value::Array(grid)),
+                    (0, c) => Ok(Value::Array(Grid::from_rows(
+                        (0..grid.rows).map(|r| vec![grid.get(r, c - 1)]).collect(),
+                    ))),
+                    (r, 0) if grid.cols > 1 => Ok(Value::Array(Grid::from_rows(vec![(0..grid
+                        .cols)
+                        .map(|c| grid.get(r - 1, c))
+                        .collect()]))),
+                    (r, c) => Ok(Value::Scalar(grid.get(r - 1, c.max(1) - 1))),
+                }
+            }
+            _ => error("#NAME?"),
+        }
+    }
+}
+
+fn binary(op: &str, lhs: &CellValue, rhs: &CellValue) -> Outcome<Value> {
+    let ordering = || compare(lhs, rhs);
+    let boolean = |b: bool| -> Outcome<Value> { Ok(Value::Scalar(CellValue::Bool(b))) };
+    match op {
+        "=" => boolean(ordering() == Ordering::Equal),
+        "<>" => boolean(ordering() != Ordering::Equal),
+        "<" => boolean(ordering() == Ordering::Less),
+        ">" => boolean(ordering() == Ordering::Greater),
+        "<=" => boolean(ordering() != Ordering::Greater),
+        ">=" => boolean(ordering() != Ordering::Less),
+        "&" => Ok(Value::Scalar(CellValue::Text(
+            to_text(lhs)? + &to_text(rhs)?,
+        ))),
+        _ => {
+            let (x, y) = (to_number(lhs)?, to_number(rhs)?);
+            match op {
+                "+" => number_result(x + y),
+                "-" => number_result(x - y),
+                "*" => number_result(x * y),
+                "/" if y == 0.0 => error("#DIV/0!"),
+                "/" => number_result(x / y),
+                _ if x == 0.0 && y < 0.0 => error("#DIV/0!"),
+                _ => number_result(x.powf(y)),
+            }
+        }
+    }
+}
+
+pub(crate) fn recalculate_workbook_impl(
+    workbook: &EvalWorkbook,
+) -> Result<Vec<EvaluatedCell>, String> {
+    let graph = formula_graph(&workbook.sheets, &workbook.defined_names, &workbook.tables)?;
+    let mut evaluator = Evaluator::new(workbook);
+    let mut results = vec![CellValue::Empty; graph.cells.len()];
+    for component in graph.components() {
+        for i in component {
+            let (s, r, c, formula) = graph.cells[i];
+            evaluator.cell = (r, c);
+            let value = evaluator.evaluate_formula(formula, s);
+            evaluator.values[s].insert((r, c), value.clone());
+            results[i] = value;
+        }
+    }
+
+    Ok(graph
+        .cells
+        .iter()
+        .zip(results)
+        .map(|(&(s, r, c, _), value)| {
+            let (cell_type, value) = match value {
+                CellValue::Number(n) => (None, format_number(n)),
+                CellValue::Text(text) => (Some("str"), text),
+                CellValue::Bool(b) => (Some("b"), if b { "1" } else { "0" }.to_string()),
+                CellValue::Error(code) => (Some("e"), code),
+                CellValue::Empty => (None, "0".to_string()),
+            };
+            EvaluatedCell {
+                sheet: workbook.sheets[s].name.clone(),
+                reference: cell_ref(r, c),
+                cell_type: cell_type.map(str::to_string),
+                value,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParsedCell, ParsedRow, ParsedWorksheet};
+
+    fn sheet(name: &str, cells: &[(&str, &str)]) -> ParsedSheet {
+        let cells = cells
+            .iter()
+            .map(|(reference, content)| {
+                let (formula, value) = match content.strip_prefix('=') {
+                    Some(formula) => (Some(formula.to_string()), None),
+                    None => (None, Some(content.to_string())),
+                };
+                let is_text = value.as_ref().is_some_and(|v| v.parse::<f64>().is_err());
+                ParsedCell {
+                    reference: reference.to_string(),
+                    cell_type: is_text.then(|| "str".to_string()),
+                    style_index: None,
+                    value,
+                    formula,
+                }
+            })
+            .collect();
+        ParsedSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet {
+                rows: vec![ParsedRow {
+                    row_num: 1,
+                    cells,
+                    height: None,
+                    hidden: false,
+                }],
+                ..Default::default()
+            },
+        }
+    }
+
+    fn evaluate(formula: &str) -> String {
+        let workbook = EvalWorkbook {
+            sheets: vec![
+                sheet(
+                    "Sheet1",
+                    &[
+                        ("A1", "10"),
+                        ("A2", "20"),
+                        ("A3", "apple"),
+                        ("A4", "45292"),
+                        ("Z1", formula),
+                    ],
+                ),
+                sheet(
+                    "Prices",
+                    &[
+                        ("A1", "apple"),
+                        ("B1", "1.5"),
+                        ("A2", "banana"),
+                        ("B2", "0.25"),
+                        ("A3", "cherry"),
+                        ("B3", "4"),
+                    ],
+                ),
+            ],
+            shared_strings: Vec::new(),
+            defined_names: vec![ParsedDefinedName {
+                name: "Table".to_string(),
+                value: "Prices!$A$1:$B$3".to_string(),
+                local_sheet_id: None,
+                hidden: false,
+            }],
+            tables: vec![ParsedTable {
+                name: "Fruit".to_string(),
+                display_name: "Fruit".to_string(),
+                reference: "A1:B3".to_string(),
+                header_row_count: 0,
+                totals_row_count: 0,
+                columns: vec!["Name".to_string(), "Price".to_string()],
+                sheet: Some("Prices".to_string()),
+                style: None,
+                totals: Vec::new(),
+            }],
+            date1904: false,
+        };
+        recalculate_workbook_impl(&workbook)
+            .unwrap()
+            .remove(0)
+            .value
+    }
+
+    #[test]
+    fn test_operators_and_aggregates() {
+        assert_eq!(evaluate("=1+2*3^2-(4/2)"), "17");
+        assert_eq!(evaluate("=-2^2&\"x\""), "4x");
+        assert_eq!(evaluate("=SUM(A1:A3,5)+AVERAGE(A1:A2)"), "50");
+        assert_eq!(evaluate("=IF(A1>5,\"big\",\"small\")"), "big");
+        assert_eq!(evaluate("=IFERROR(1/0,\"none\")"), "none");
+        assert_eq!(evaluate("=A3+1"), "#VALUE!");
+        assert_eq!(evaluate("=COUNT(A1:A4)+COUNTA(A:A)"), "7");
+        assert_eq!(evaluate("=ROUND(2.675,2)"), "2.68");
+        assert_eq!(evaluate("=Nope(1)"), "#NAME?");
+    }
+
+    #[test]
+    fn test_lookups_and_text() {
+        assert_eq!(evaluate("=VLOOKUP(A3,Prices!A1:B3,2,FALSE)"), "1.5");
+        assert_eq!(evaluate("=VLOOKUP(\"b*\",Table,2,FALSE)"), "0.25");
+        assert_eq!(evaluate("=VLOOKUP(\"kiwi\",Table,2,FALSE)"), "#N/A");
+        assert_eq!(
+            evaluate("=INDEX(Table,MATCH(\"cherry\",Prices!A:A,0),2)"),
+            "4"
+        );
+        assert_eq!(evaluate("=MATCH(15,A1:A2)"), "1");
+        assert_eq!(evaluate("=INDEX({1,2;3,4},2,1)"), "3");
+        assert_eq!(evaluate("=SUM(Fruit[Nope])"), "#REF!");
+        assert_eq!(
+            evaluate("=VLOOKUP(\"cherry\",Fruit,2,FALSE)*SUM(Fruit[Price])"),
+            "23"
+        );
+        assert_eq!(evaluate("=TEXT(1234.5,\"#,##0.00\")"), "1,234.50");
+        assert_eq!(evaluate("=TEXT(0.256,\"0.0%\")"), "25.6%");
+        assert_eq!(
+            evaluate("=TEXT(A4,\"dddd d mmm yyyy\")"),
+            "Monday 1 Jan 2024"
+        );
+        assert_eq!(evaluate("=UPPER(LEFT(A3,3))&LEN(A3)"), "APP5");
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("b*", "Banana"));
+        assert!(wildcard_match("?pple", "apple"));
+        assert!(wildcard_match("*an*a", "banana"));
+        assert!(wildcard_match("a~*b", "a*b"));
+        assert!(!wildcard_match("a~*b", "axb"));
+        assert!(wildcard_match("100~", "100~"));
+        assert!(!wildcard_match("b?", "banana"));
+        assert!(wildcard_match("**", ""));
+        // Many stars against text that almost matches stay fast
+        let text = "a".repeat(32_767);
+        assert!(!wildcard_match("*a*a*a*a*a*c", &text));
+        assert!(wildcard_match("*a*a*a*a*a*", &text));
+    }
+
+    #[test]
+    fn test_dates_and_recalculation() {
+        assert_eq!(evaluate("=DATE(2024,1,1)"), "45292");
+        assert_eq!(evaluate("=DATE(2023,14,1)=DATE(2024,2,1)"), "1");
+        assert_eq!(evaluate("=YEAR(A4)*100+MONTH(EOMONTH(A4,1))"), "202402");
+        assert_eq!(evaluate("=EDATE(DATE(2024,1,31),1)-DATE(2024,2,29)"), "0");
+        assert_eq!(evaluate("=WEEKDAY(A4)"), "2");
+
+        // Formulas see recalculated precedents regardless of sheet order
+        let workbook = EvalWorkbook {
+            sheets: vec![
+                sheet("Sheet1", &[("A1", "=Sheet2!A1*2"), ("A2", "=A1+1")]),
+                sheet("Sheet2", &[("A1", "=3+4")]),
+            ],
+            shared_strings: Vec::new(),
+            defined_names: Vec::new(),
+            tables: Vec::new(),
+            date1904: false,
+        };
+        let values: Vec<String> = recalculate_workbook_impl(&workbook)
+            .unwrap()
+            .into_iter()
+            .map(|cell| cell.value)
+            .collect();
+        assert_eq!(values, ["14", "15", "7"]);
+    }
+}
@@ -0,0 +1,999 @@
+//! Workbook-to-XLSX assembly
+//!
+//! Runs all part writers over an export model and packages the result into
+//! a ZIP archive entirely inside WASM.
+
+#[cfg(feature = "charts")]
+use crate::chart::write_chart_impl;
+#[cfg(feature = "comments")]
+use crate::comments::{
+    is_thread, thread_authors, vml_blocks, write_comments_impl, write_persons_impl,
+    write_threaded_comments_impl, write_vml_drawing_impl,
+};
+#[cfg(feature = "drawings")]
+use crate::drawing::{image_info, write_drawing_impl};
+#[cfg(all(feature = "wasm", feature = "encryption"))]
+use crate::encryption::encrypt_with_password;
+use crate::formula::{format_sheet, MAX_ROWS};
+use crate::properties::{
+    write_app_properties_impl, write_core_properties_impl, write_custom_properties_impl,
+    DocumentProperties,
+};
+use crate::reference::{col_to_letters, parse_cell_ref, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::{
+    assign_relationship_ids, rel_types, write_content_types_impl, write_relationships_impl,
+    write_shared_strings_impl, write_styles_impl, write_table_impl, write_workbook_impl,
+    write_worksheet_impl, PackagePart, SharedStringEntry,
+};
+use crate::zip::{ZipWriter, DEFAULT_COMPRESSION_LEVEL};
+use crate::{
+    ParsedDefinedName, ParsedRelationship, ParsedSheetInfo, ParsedStyles, ParsedTable,
+    ParsedWorkbookPr, ParsedWorksheet,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const FILTER_DATABASE: &str = "_xlnm._FilterDatabase";
+pub(crate) const PRINT_AREA: &str = "_xlnm.Print_Area";
+pub(crate) const PRINT_TITLES: &str = "_xlnm.Print_Titles";
+
+/// A sheet to export
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportSheet {
+    pub name: String,
+    #[serde(default)]
+    pub state: Option<String>,
+    pub worksheet: ParsedWorksheet,
+    /// Sheet-level relationships (e.g. external hyperlink targets) referenced
+    /// by `r:id` from the worksheet
+    #[serde(default)]
+    pub relationships: Vec<ParsedRelationship>,
+    /// Tables on the sheet, each written as its own part; their header
+    /// cells should hold the column names
+    #[serde(default)]
+    pub tables: Vec<ParsedTable>,
+    /// Print area and titles, written as the sheet's built-in names in
+    /// place of any `defined_names` gives it
+    #[serde(default)]
+    pub print: PrintSettings,
+}
+
+/// What printing a sheet takes in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintSettings {
+    /// Ranges to print, e.g. "A1:F40" or "A1:F40,H1:K40"; all of the sheet
+    /// when left out
+    pub area: Option<String>,
+    /// Rows repeated at the top of every page, e.g. "1:2"
+    pub title_rows: Option<String>,
+    /// Columns repeated at the left of every page, e.g. "A:B"
+    pub title_cols: Option<String>,
+}
+
+/// Everything needed to produce an .xlsx package
+///
+/// Cells of type `s` index into `shared_strings` and cell style indices
+/// index into `styles.cell_xfs`; both are remapped after deduplication.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportWorkbook {
+    pub sheets: Vec<ExportSheet>,
+    #[serde(default)]
+    pub shared_strings: Vec<SharedStringEntry>,
+    #[serde(default)]
+    pub styles: Option<ParsedStyles>,
+    #[serde(default)]
+    pub defined_names: Vec<ParsedDefinedName>,
+    #[serde(default)]
+    pub workbook_pr: Option<ParsedWorkbookPr>,
+    /// DEFLATE level for XML parts (0 stores uncompressed, max 10)
+    #[serde(default)]
+    pub compression_level: Option<u8>,
+    /// Encrypt the package with this password (ECMA-376 agile encryption)
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Title, author, company and custom properties, written to `docProps`
+    #[serde(default)]
+    pub properties: Option<DocumentProperties>,
+}
+
+/// Assemble a complete .xlsx file from an export model
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn workbook_to_xlsx(workbook: JsValue) -> Result<Vec<u8>, JsValue> {
+    trap(|| {
+        let mut workbook: ExportWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        let password = workbook.password.take();
+        let package = workbook_to_xlsx_impl(workbook).map_err(|e| JsValue::from_str(&e))?;
+        match password {
+            #[cfg(feature = "encryption")]
+            Some(password) => encrypt_with_password(&package, &password),
+            #[cfg(not(feature = "encryption"))]
+            Some(_) => Err(JsValue::from_str(
+                "This build leaves out encryption, so a password can't be set",
+            )),
+            None => Ok(package),
+        }
+    })
+}
+
+pub(crate) fn workbook_to_xlsx_impl(mut workbook: ExportWorkbook) -> Result<Vec<u8>, String> {
+    let level = workbook
+        .compression_level
+        .unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+
+    if workbook.sheets.is_empty() {
+        workbook.sheets.push(ExportSheet {
+            name: "Sheet1".to_string(),
+            state: None,
+            worksheet: ParsedWorksheet::default(),
+            relationships: Vec::new(),
+            tables: Vec::new(),
+            print: PrintSettings::default(),
+        });
+    }
+
+    let shared_strings = if workbook.shared_strings.is_empty() {
+        None
+    } else {
+        Some(write_shared_strings_impl(&workbook.shared_strings))
+    };
+    let styles = write_styles_impl(workbook.styles.as_ref().unwrap_or(&ParsedStyles::default()));
+
+    // Point cells at the deduplicated string and style tables
+    for sheet in &mut workbook.sheets {
+        for row in &mut sheet.worksheet.rows {
+            for cell in &mut row.cells {
+                if let Some(style) = cell.style_index {
+                    cell.style_index =
+                        Some(styles.xf_indices.get(style as usize).copied().unwrap_or(0));
+                }
+                if cell.cell_type.as_deref() == Some("s") {
+                    let remapped = shared_strings.as_ref().and_then(|sst| {
+                        let index: usize = cell.value.as_deref()?.parse().ok()?;
+                        sst.indices.get(index).copied()
+                    });
+                    if let Some(index) = remapped {
+                        cell.value = Some(index.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut parts: Vec<PackagePart> = Vec::new();
+    let mut workbook_rels: Vec<ParsedRelationship> = Vec::new();
+    let mut sheet_infos: Vec<ParsedSheetInfo> = Vec::new();
+    let mut table_count = 0;
+    #[cfg(feature = "drawings")]
+    let mut image_count = 0;
+    #[cfg(feature = "charts")]
+    let mut chart_count = 0;
+    #[cfg(feature = "comments")]
+    let persons = thread_authors(workbook.sheets.iter().flat_map(|s| &s.worksheet.comments));
+    // Each sheet's VML drawing takes its own blocks of shape ids
+    #[cfg(feature = "comments")]
+    let mut next_block = 1;
+
+    for (i, sheet) in workbook.sheets.iter_mut().enumerate() {
+        let number = i + 1;
+        let rid = format!("rId{}", number);
+        let part_name = format!("xl/worksheets/sheet{}.xml", number);
+        link_relationships(sheet);
+
+        for table in &sheet.tables {
+            table_count += 1;
+            let table_name = format!("xl/tables/table{}.xml", table_count);
+            files.push((
+                table_name.clone(),
+                write_table_impl(table, table_count)?.into_bytes(),
+            ));
+            parts.push(PackagePart::Name(table_name));
+            sheet.relationships.push(relationship(
+                "",
+                rel_types::TABLE,
+                &format!("../tables/table{}.xml", table_count),
+            ));
+        }
+        if !sheet.tables.is_empty() {
+            let rels = &mut sheet.relationships;
+            assign_relationship_ids(rels);
+            let table_rels = rels[rels.len() - sheet.tables.len()..].iter();
+            sheet.worksheet.table_parts = table_rels.map(|rel| rel.id.clone()).collect();
+        }
+        #[cfg(feature = "drawings")]
+        {
+            // Images and charts are numbered across the workbook, drawings by their sheet
+            let mut drawing_rels = Vec::new();
+            for image in &sheet.worksheet.images {
+                let info =
+                    image_info(&image.data).ok_or("Images must be PNG, JPEG, GIF or BMP files")?;
+                image_count += 1;
+                let media = format!("media/image{}.{}", image_count, info.extension);
+                files.push((format!("xl/{}", media), image.data.clone()));
+                parts.push(PackagePart::Name(format!("xl/{}", media)));
+                drawing_rels.push(relationship(
+                    &format!("rId{}", drawing_rels.len() + 1),
+                    rel_types::IMAGE,
+                    &format!("../{}", media),
+                ));
+            }
+            #[cfg(feature = "charts")]
+            for chart in &sheet.worksheet.charts {
+                chart_count += 1;
+                let chart_name = format!("charts/chart{}.xml", chart_count);
+                files.push((
+                    format!("xl/{}", chart_name),
+                    write_chart_impl(chart)?.into_bytes(),
+                ));
+                parts.push(PackagePart::Name(format!("xl/{}", chart_name)));
+                drawing_rels.push(relationship(
+                    &format!("rId{}", drawing_rels.len() + 1),
+                    rel_types::CHART,
+                    &format!("../{}", chart_name),
+                ));
+            }
+            if !drawing_rels.is_empty() {
+                let drawing_name = format!("xl/drawings/drawing{}.xml", number);
+                files.push((
+                    drawing_name.clone(),
+                    write_drawing_impl(
+                        &sheet.worksheet.images,
+                        #[cfg(feature = "charts")]
+                        &sheet.worksheet.charts,
+                    )?
+                    .into_bytes(),
+                ));
+                parts.push(PackagePart::Name(drawing_name));
+                files.push((
+                    format!("xl/drawings/_rels/drawing{}.xml.rels", number),
+                    write_relationships_impl(&drawing_rels).into_bytes(),
+                ));
+                let target = format!("../drawings/drawing{}.xml", number);
+                let rels = &mut sheet.relationships;
+                rels.push(relationship("", rel_types::DRAWING, &target));
+                assign_relationship_ids(rels);
+                sheet.worksheet.drawing = rels.last().map(|rel| rel.id.clone());
+            }
+        }
+        #[cfg(feature = "comments")]
+        if !sheet.worksheet.comments.is_empty() {
+            let comments = &sheet.worksheet.comments;
+            let mut comment_parts = vec![
+                (
+                    format!("comments{}.xml", number),
+                    write_comments_impl(comments, number as u32),
+                    rel_types::COMMENTS,
+                ),
+                (
+                    format!("drawings/vmlDrawing{}.vml", number),
+                    write_vml_drawing_impl(comments, next_block)?,
+                    rel_types::VML_DRAWING,
+                ),
+            ];
+            next_block += vml_blocks(comments.len() as u32);
+            if comments.iter().any(is_thread) {
+                comment_parts.push((
+                    format!("threadedComments/threadedComment{}.xml", number),
+                    write_threaded_comments_impl(comments, number as u32, &persons)?,
+                    rel_types::THREADED_COMMENT,
+                ));
+            }
+            for (name, xml, rel_type) in comment_parts {
+                files.push((format!("xl/{}", name), xml.into_bytes()));
+                parts.push(PackagePart::Name(format!("xl/{}", name)));
+                let target = format!("../{}", name);
+                sheet
+                    .relationships
+                    .push(relationship("", rel_type, &target));
+            }
+            assign_relationship_ids(&mut sheet.relationships);
+            let vml = sheet
+                .relationships
+                .iter()
+                .find(|rel| rel.rel_type == rel_types::VML_DRAWING);
+            sheet.worksheet.legacy_drawing = vml.map(|rel| rel.id.clone());
+        }
+
+        files.push((
+            part_name.clone(),
+            write_worksheet_impl(&sheet.worksheet).into_bytes(),
+        ));
+        parts.push(PackagePart::Name(part_name));
+
+        if !sheet.relationships.is_empty() {
+            assign_relationship_ids(&mut sheet.relationships);
+            let rels_name = format!("xl/worksheets/_rels/sheet{}.xml.rels", number);
+            files.push((
+                rels_name,
+                write_relationships_impl(&sheet.relationships).into_bytes(),
+            ));
+        }
+
+        workbook_rels.push(relationship(
+            &rid,
+            rel_types::WORKSHEET,
+            &format!("worksheets/sheet{}.xml", number),
+        ));
+        sheet_infos.push(ParsedSheetInfo {
+            name: sheet.name.clone(),
+            sheet_id: number as u32,
+            rid,
+            state: sheet.state.clone(),
+        });
+    }
+
+    let next_rid = |rels: &[ParsedRelationship]| format!("rId{}", rels.len() + 1);
+
+    files.push(("xl/styles.xml".to_string(), styles.xml.into_bytes()));
+    parts.push(PackagePart::Name("xl/styles.xml".to_string()));
+    workbook_rels.push(relationship(
+        &next_rid(&workbook_rels),
+        rel_types::STYLES,
+        "styles.xml",
+    ));
+
+    if let Some(sst) = shared_strings {
+        files.push(("xl/sharedStrings.xml".to_string(), sst.xml.into_bytes()));
+        parts.push(PackagePart::Name("xl/sharedStrings.xml".to_string()));
+        workbook_rels.push(relationship(
+            &next_rid(&workbook_rels),
+            rel_types::SHARED_STRINGS,
+            "sharedStrings.xml",
+        ));
+    }
+
+    #[cfg(feature = "comments")]
+    if !persons.is_empty() {
+        let name = "xl/persons/person.xml".to_string();
+        files.push((name.clone(), write_persons_impl(&persons).into_bytes()));
+        parts.push(PackagePart::Name(name));
+        workbook_rels.push(relationship(
+            &next_rid(&workbook_rels),
+            rel_types::PERSON,
+            "persons/person.xml",
+        ));
+    }
+
+    filter_databases(&workbook.sheets, &mut workbook.defined_names);
+    print_names(&workbook.sheets, &mut workbook.defined_names)?;
+    let workbook_xml = write_workbook_impl(
+        &sheet_infos,
+        &workbook.defined_names,
+        workbook
+            .workbook_pr
+            .as_ref()
+            .unwrap_or(&ParsedWorkbookPr::default()),
+    );
+    files.push(("xl/workbook.xml".to_string(), workbook_xml.into_bytes()));
+    parts.push(PackagePart::Name("xl/workbook.xml".to_string()));
+    files.push((
+        "xl/_rels/workbook.xml.rels".to_string(),
+        write_relationships_impl(&workbook_rels).into_bytes(),
+    ));
+
+    let mut root_rels = vec![relationship(
+        "rId1",
+        rel_types::OFFICE_DOCUMENT,
+        "xl/workbook.xml",
+    )];
+    if let Some(properties) = &workbook.properties {
+        let sheet_names: Vec<_> = workbook.sheets.iter().map(|s| s.name.clone()).collect();
+        let mut doc_props = vec![
+            (
+                "core",
+                write_core_properties_impl(properties)?,
+                rel_types::CORE_PROPERTIES,
+            ),
+            (
+                "app",
+                write_app_properties_impl(properties, &sheet_names),
+                rel_types::EXTENDED_PROPERTIES,
+            ),
+        ];
+        if !properties.custom.is_empty() {
+            doc_props.push((
+                "custom",
+                write_custom_properties_impl(&properties.custom)?,
+                rel_types::CUSTOM_PROPERTIES,
+            ));
+        }
+        for (stem, xml, rel_type) in doc_props {
+            let name = format!("docProps/{}.xml", stem);
+            files.push((name.clone(), xml.into_bytes()));
+            parts.push(PackagePart::Name(name.clone()));
+            root_rels.push(relationship(&next_rid(&root_rels), rel_type, &name));
+        }
+    }
+
+    // [Content_Types].xml and the package rels lead the archive, as Excel writes them
+    let mut zip = ZipWriter::new();
+    zip.add_file(
+        "[Content_Types].xml",
+        write_content_types_impl(&parts).as_bytes(),
+        level,
+    );
+    zip.add_file(
+        "_rels/.rels",
+        write_relationships_impl(&root_rels).as_bytes(),
+        level,
+    );
+    for (name, data) in &files {
+        zip.add_file(name, data, level);
+    }
+    Ok(zip.finish())
+}
+
+/// Add the hidden `_FilterDatabase` name Excel expects for each sheet with
+/// an autoFilter, unless it has one already
+fn filter_databases(sheets: &[ExportSheet], defined_names: &mut Vec<ParsedDefinedName>) {
+    for (index, sheet) in sheets.iter().enumerate() {
+        let Some(filter) = &sheet.worksheet.auto_filter else {
+            continue;
+        };
+        let Ok(value) = print_area(&sheet.name, &filter.reference) else {
+            continue;
+        };
+        let local_sheet_id = Some(index as u32);
+        let exists = defined_names
+            .iter()
+            .any(|name| name.name == FILTER_DATABASE && name.local_sheet_id == local_sheet_id);
+        if !exists {
+            defined_names.push(ParsedDefinedName {
+                name: FILTER_DATABASE.to_string(),
+                value,
+                local_sheet_id,
+                hidden: true,
+            });
+        }
+    }
+}
+
+/// Write each sheet's print settings as its built-in names
+fn print_names(
+    sheets: &[ExportSheet],
+    defined_names: &mut Vec<ParsedDefinedName>,
+) -> Result<(), String> {
+    for (index, sheet) in sheets.iter().enumerate() {
+        let print = &sheet.print;
+        if let Some(area) = &print.area {
+            let value = print_area(&sheet.name, area)?;
+            set_local_name(defined_names, PRINT_AREA, index as u32, Some(value));
+        }
+        let (rows, cols) = (print.title_rows.as_deref(), print.title_cols.as_deref());
+        if rows.is_some() || cols.is_some() {
+            let value = print_titles(&sheet.name, rows, cols)?;
+            set_local_name(defined_names, PRINT_TITLES, index as u32, Some(value));
+        }
+    }
+    Ok(())
+}
+
+/// Give sheet `index` the name `name` holding `value` in place of any it
+/// has, or remove it when `value` is `None`
+pub(crate) fn set_local_name(
+    defined_names: &mut Vec<ParsedDefinedName>,
+    name: &str,
+    index: u32,
+    value: Option<String>,
+) {
+    let local_sheet_id = Some(index);
+    defined_names.retain(|n| !(n.name == name && n.local_sheet_id == local_sheet_id));
+    if let Some(value) = value {
+        defined_names.push(ParsedDefinedName {
+            name: name.to_string(),
+            value,
+            local_sheet_id,
+            hidden: false,
+        });
+    }
+}
+
+/// `ranges` ("A1:F40", or several joined by commas) on `sheet`, as a print
+/// area holds them
+pub(crate) fn print_area(sheet: &str, ranges: &str) -> Result<String, String> {
+    let prefix = format_sheet(sheet, None);
+    let ranges = ranges.split(',').map(|range| {
+        let (r1, c1, r2, c2) =
+            range_bounds(range.trim()).ok_or_else(|| format!("Invalid print area: {}", range))?;
+        Ok(format!(
+            "{}${}${}:${}${}",
+            prefix,
+            col_to_letters(c1),
+            r1,
+            col_to_letters(c2),
+            r2
+        ))
+    });
+    Ok(ranges.collect::<Result<Vec<_>, String>>()?.join(","))
+}
+
+/// Print titles repeating `rows` ("1:2", or "1" for one) and `cols` ("A:B")
+/// of `sheet`
+pub(crate) fn print_titles(
+    sheet: &str,
+    rows: Option<&str>,
+    cols: Option<&str>,
+) -> Result<String, String> {
+    // Lines are read bare or absolute, first and last in either order
+    fn span(text: &str, read: impl Fn(&str) -> Option<u32>) -> Option<(u32, u32)> {
+        let (first, last) = text.split_once(':').unwrap_or((text, text));
+        let read = |line: &str| read(line.trim().trim_start_matches('$'));
+        let (first, last) = (read(first)?, read(last)?);
+        Some((first.min(last), first.max(last)))
+    }
+    let prefix = format_sheet(sheet, None);
+    let mut titles = Vec::new();
+    // Excel lists the columns first
+    if let Some(cols) = cols {
+        let col = |letters: &str| {
+            let letters_only =
+                !letters.is_empty() && letters.chars().all(|c| c.is_ascii_alphabetic());
+            let (_, col) = parse_cell_ref(&format!("{}1", letters)).filter(|_| letters_only)?;
+            Some(col)
+        };
+        let (c1, c2) = span(cols, col).ok_or_else(|| format!("Invalid title columns: {}", cols))?;
+        titles.push(format!(
+            "{}${}:${}",
+            prefix,
+            col_to_letters(c1),
+            col_to_letters(c2)
+        ));
+    }
+    if let Some(rows) = rows {
+        let row = |digits: &str| {
+            digits
+                .parse()
+                .ok()
+                .filter(|row| (1..=MAX_ROWS).contains(row))
+        };
+        let (r1, r2) = span(rows, row).ok_or_else(|| format!("Invalid title rows: {}", rows))?;
+        titles.push(format!("{}${}:${}", prefix, r1, r2));
+    }
+    Ok(titles.join(","))
+}
+
+/// Give each hyperlink with a `target` a relationship for it, unless its
+/// `rid` already names one of the sheet's relationships
+fn link_relationships(sheet: &mut ExportSheet) {
+    let rels = &mut sheet.relationships;
+    let mut added = Vec::new();
+    for (index, link) in sheet.worksheet.hyperlinks.iter().enumerate() {
+        let Some(target) = &link.target else {
+            continue;
+        };
+        if link
+            .rid
+            .as_ref()
+            .is_some_and(|rid| rels.iter().any(|rel| rel.id == *rid))
+        {
+            continue;
+        }
+        added.push((index, rels.len()));
+        rels.push(ParsedRelationship {
+            target_mode: Some("External".to_string()),
+            ..relationship("", rel_types::HYPERLINK, target)
+        });
+    }
+    assign_relationship_ids(rels);
+    for (link, rel) in added {
+        sheet.worksheet.hyperlinks[link].rid = Some(rels[rel].id.clone());
+    }
+}
+
+fn relationship(id: &str, rel_type: &str, target: &str) -> ParsedRelationship {
+    ParsedRelationship {
+        id: id.to_string(),
+        rel_type: rel_type.to_string(),
+        target: target.to_string(),
+        target_mode: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::cell;
+    #[cfg(feature = "comments")]
+    use crate::ParsedComment;
+    use crate::{
+        AutoFilter, CustomFilter, CustomProperty, FilterColumn, ParsedCell, ParsedFont, ParsedRow,
+        ParsedStyle, TypedValue,
+    };
+    use miniz_oxide::inflate::decompress_to_vec;
+    use std::collections::HashMap;
+
+    /// Read every entry of an archive produced by `ZipWriter`
+    fn read_entries(bytes: &[u8]) -> HashMap<String, Vec<u8>> {
+        let mut entries = HashMap::new();
+        let mut pos = 0;
+        let u16_at = |p: usize| u16::from_le_bytes([bytes[p], bytes[p + 1]]) as usize;
+        let u32_at =
+            |p: usize| u32::from_le_bytes([bytes[p], bytes[p + 1], bytes[p + 2], bytes[p + 3]]);
+
+        while u32_at(pos) == 0x0403_4b50 {
+            let method = u16_at(pos + 8);
+            let size = u32_at(pos + 18) as usize;
+            let name_len = u16_at(pos + 26);
+            let extra_len = u16_at(pos + 28);
+            let name = String::from_utf8(bytes[pos + 30..pos + 30 + name_len].to_vec()).unwrap();
+            let start = pos + 30 + name_len + extra_len;
+            let payload = &bytes[start..start + size];
+            let data = if method == 8 {
+                decompress_to_vec(payload).unwrap()
+            } else {
+                payload.to_vec()
+            };
+            entries.insert(name, data);
+            pos = start + size;
+        }
+        entries
+    }
+
+    #[test]
+    fn test_workbook_to_xlsx() {
+        let workbook = ExportWorkbook {
+            sheets: vec![ExportSheet {
+                name: "Data".to_string(),
+                state: None,
+                worksheet: ParsedWorksheet {
+                    rows: vec![ParsedRow {
+                        row_num: 1,
+                        cells: vec![
+                            ParsedCell {
+                                style_index: Some(1),
+                                ..cell("A1", Some("s"), "0")
+                            },
+                            ParsedCell {
+                                style_index: Some(2),
+                                ..cell("B1", Some("s"), "1")
+                            },
+                            cell("C1", Some("s"), "2"),
+                            cell("D1", None, "3.5"),
+                        ],
+                        height: None,
+                        hidden: false,
+                    }],
+                    ..Default::default()
+                },
+                relationships: Vec::new(),
+                tables: Vec::new(),
+                print: PrintSettings::default(),
+            }],
+            shared_strings: vec![
+                SharedStringEntry::Plain("Name".to_string()),
+                SharedStringEntry::Plain("Name".to_string()),
+                SharedStringEntry::Plain("Total".to_string()),
+            ],
+            styles: Some(ParsedStyles {
+                cell_xfs: vec![
+                    ParsedStyle::default(),
+                    ParsedStyle {
+                        font_id: Some(1),
+                        ..ParsedStyle::default()
+                    },
+                    ParsedStyle {
+                        font_id: Some(1),
+                        ..ParsedStyle::default()
+                    },
+                ],
+                fonts: vec![
+                    ParsedFont::default(),
+                    ParsedFont {
+                        bold: true,
+                        ..ParsedFont::default()
+                    },
+                ],
+                ..ParsedStyles::default()
+            }),
+            defined_names: Vec::new(),
+            workbook_pr: None,
+            compression_level: None,
+            password: None,
+            properties: None,
+        };
+
+        let bytes = workbook_to_xlsx_impl(workbook).unwrap();
+        assert_eq!(&bytes[30..49], b"[Content_Types].xml");
+
+        let entries = read_entries(&bytes);
+        for name in [
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "xl/workbook.xml",
+            "xl/_rels/workbook.xml.rels",
+            "xl/styles.xml",
+            "xl/sharedStrings.xml",
+            "xl/worksheets/sheet1.xml",
+        ] {
+            assert!(entries.contains_key(name), "missing {}", name);
+        }
+
+        let text = |name: &str| String::from_utf8(entries[name].clone()).unwrap();
+        let workbook = crate::parse_workbook_impl(&text("xl/workbook.xml"));
+        assert_eq!(workbook.sheets[0].name, "Data");
+
+        let rels = crate::parse_relationships_impl(&text("xl/_rels/workbook.xml.rels"));
+        let sheet_rel = rels
+            .iter()
+            .find(|rel| rel.id == workbook.sheets[0].rid)
+            .unwrap();
+        assert_eq!(sheet_rel.target, "worksheets/sheet1.xml");
+        assert!(rels.iter().any(|rel| rel.target == "sharedStrings.xml"));
+
+        let strings = crate::parse_shared_strings_impl(&text("xl/sharedStrings.xml"));
+        assert_eq!(strings, vec!["Name", "Total"]);
+
+        let sheet = crate::parse_worksheet_impl(&text("xl/worksheets/sheet1.xml"));
+        let cells = &sheet.rows[0].cells;
+        assert_eq!(cells[0].value.as_deref(), Some("0"));
+        assert_eq!(cells[1].value.as_deref(), Some("0"));
+        assert_eq!(cells[2].value.as_deref(), Some("1"));
+        assert_eq!(cells[0].style_index, Some(1));
+        assert_eq!(cells[1].style_index, Some(1));
+        assert_eq!(cells[3].value.as_deref(), Some("3.5"));
+
+        let content_types = text("[Content_Types].xml");
+        assert!(content_types.contains("/xl/worksheets/sheet1.xml"));
+        assert!(content_types.contains("/xl/sharedStrings.xml"));
+    }
+
+    #[test]
+    fn test_workbook_to_xlsx_auto_filter() {
+        let sheet = |name: &str, auto_filter| ExportSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet {
+                auto_filter,
+                ..Default::default()
+            },
+            relationships: Vec::new(),
+            tables: Vec::new(),
+            print: PrintSettings::default(),
+        };
+        let filter = AutoFilter {
+            reference: "A1:C20".to_string(),
+            columns: vec![
+                FilterColumn {
+                    col_id: 1,
+                    values: vec!["East".to_string(), "West".to_string()],
+                    ..Default::default()
+                },
+                FilterColumn {
+                    col_id: 2,
+                    custom: vec![CustomFilter {
+                        operator: Some("greaterThan".to_string()),
+                        value: "100".to_string(),
+                    }],
+                    ..Default::default()
+                },
+            ],
+        };
+        let bytes = workbook_to_xlsx_impl(ExportWorkbook {
+            sheets: vec![
+                sheet("Notes", None),
+                sheet("Q1 Sales", Some(filter)),
+                sheet(
+                    "Q2",
+                    Some(AutoFilter {
+                        reference: "B2:D9".to_string(),
+                        columns: Vec::new(),
+                    }),
+                ),
+            ],
+            shared_strings: Vec::new(),
+            styles: None,
+            defined_names: vec![ParsedDefinedName {
+                name: "_xlnm._FilterDatabase".to_string(),
+                value: "Q2!$B$2:$D$5".to_string(),
+                local_sheet_id: Some(2),
+                hidden: true,
+            }],
+            workbook_pr: None,
+            compression_level: Some(0),
+            password: None,
+            properties: None,
+        })
+        .unwrap();
+
+        let entries = read_entries(&bytes);
+        let text = |name: &str| String::from_utf8(entries[name].clone()).unwrap();
+        let xml = text("xl/worksheets/sheet2.xml");
+        assert!(xml.contains("<sheetPr filterMode=\"1\"/>"));
+        assert!(xml.contains(
+            "<autoFilter ref=\"A1:C20\"><filterColumn colId=\"1\"><filters>\
+             <filter val=\"East\"/><filter val=\"West\"/></filters></filterColumn>\
+             <filterColumn colId=\"2\"><customFilters><customFilter operator=\"greaterThan\" \
+             val=\"100\"/></customFilters></filterColumn></autoFilter>"
+        ));
+        assert!(text("xl/worksheets/sheet3.xml").contains("<autoFilter ref=\"B2:D9\"/>"));
+
+        // The second sheet gets its name; the third keeps the one it has
+        let names = crate::parse_workbook_impl(&text("xl/workbook.xml")).defined_names;
+        let values: Vec<_> = names
+            .iter()
+            .map(|name| (name.local_sheet_id, name.value.as_str(), name.hidden))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (Some(2), "Q2!$B$2:$D$5", true),
+                (Some(1), "'Q1 Sales'!$A$1:$C$20", true),
+            ]
+        );
+    }
+
+    #[cfg(feature = "comments")]
+    #[test]
+    fn test_workbook_to_xlsx_comments() {
+        let comment = |reference: &str, threaded| ParsedComment {
+            reference: reference.to_string(),
+            author: Some("Ana".to_string()),
+            text: "Note".to_string(),
+            threaded,
+            ..Default::default()
+        };
+        let sheet = |name: &str, comments| ExportSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet {
+                comments,
+                ..Default::default()
+            },
+            relationships: vec![relationship(
+                "",
+                rel_types::HYPERLINK,
+                "https://example.com",
+            )],
+            tables: Vec::new(),
+            print: PrintSettings::default(),
+        };
+        let bytes = workbook_to_xlsx_impl(ExportWorkbook {
+            sheets: vec![
+                sheet("Notes", vec![comment("A1", false), comment("C4", false)]),
+                sheet("Empty", Vec::new()),
+                sheet("Threads", vec![comment("B2", true)]),
+            ],
+            shared_strings: Vec::new(),
+            styles: None,
+            defined_names: Vec::new(),
+            workbook_pr: None,
+            compression_level: Some(0),
+            password: None,
+            properties: None,
+        })
+        .unwrap();
+
+        let entries = read_entries(&bytes);
+        let text = |name: &str| String::from_utf8(entries[name].clone()).unwrap();
+        assert!(text("xl/worksheets/sheet1.xml").contains("<legacyDrawing r:id=\"rId3\"/>"));
+        let rels = text("xl/worksheets/_rels/sheet1.xml.rels");
+        assert!(rels.contains(&format!(
+            "Id=\"rId2\" Type=\"{}\" Target=\"../comments1.xml\"",
+            rel_types::COMMENTS
+        )));
+        assert!(!entries.contains_key("xl/threadedComments/threadedComment1.xml"));
+        assert!(!entries.contains_key("xl/comments2.xml"));
+
+        // Each drawing has its own shape ids; the thread's author is listed once
+        assert!(text("xl/drawings/vmlDrawing1.vml").contains("_x0000_s1026"));
+        assert!(text("xl/drawings/vmlDrawing3.vml").contains("_x0000_s2049"));
+        assert!(text("xl/threadedComments/threadedComment3.xml").contains("ref=\"B2\""));
+        assert!(text("xl/persons/person.xml").contains("displayName=\"Ana\""));
+        assert!(text("xl/_rels/workbook.xml.rels").contains("Target=\"persons/person.xml\""));
+        let types = text("[Content_Types].xml");
+        assert!(types.contains("<Default Extension=\"vml\""));
+        assert!(types.contains("PartName=\"/xl/threadedComments/threadedComment3.xml\""));
+    }
+
+    #[test]
+    fn test_workbook_to_xlsx_print_settings() {
+        let sheet = |name: &str, print| ExportSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet::default(),
+            relationships: Vec::new(),
+            tables: Vec::new(),
+            print,
+        };
+        let print = PrintSettings {
+            area: Some("A1:F40".to_string()),
+            title_rows: Some("1".to_string()),
+            title_cols: None,
+        };
+        let bytes = workbook_to_xlsx_impl(ExportWorkbook {
+            sheets: vec![
+                sheet("Notes", PrintSettings::default()),
+                sheet("Q1 Sales", print),
+            ],
+            shared_strings: Vec::new(),
+            styles: None,
+            defined_names: vec![ParsedDefinedName {
+                name: PRINT_AREA.to_string(),
+                value: "'Q1 Sales'!$A$1:$B$2".to_string(),
+                local_sheet_id: Some(1),
+                hidden: false,
+            }],
+            workbook_pr: None,
+            compression_level: Some(0),
+            password: None,
+            properties: None,
+        })
+        .unwrap();
+
+        // The sheet's own settings replace the print area it was given
+        let entries = read_entries(&bytes);
+        let xml = String::from_utf8(entries["xl/workbook.xml"].clone()).unwrap();
+        let names = crate::parse_workbook_impl(&xml).defined_names;
+        let values: Vec<_> = names
+            .iter()
+            .map(|name| (name.name.as_str(), name.local_sheet_id, name.value.as_str()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (PRINT_AREA, Some(1), "'Q1 Sales'!$A$1:$F$40"),
+                (PRINT_TITLES, Some(1), "'Q1 Sales'!$1:$1"),
+            ]
+        );
+        assert!(print_titles("Data", None, Some("A1:B1")).is_err());
+    }
+
+    #[test]
+    fn test_workbook_to_xlsx_adds_default_sheet() {
+        let bytes = workbook_to_xlsx_impl(ExportWorkbook {
+            sheets: Vec::new(),
+            shared_strings: Vec::new(),
+            styles: None,
+            defined_names: Vec::new(),
+            workbook_pr: None,
+            compression_level: Some(0),
+            password: None,
+            properties: None,
+        })
+        .unwrap();
+
+        let entries = read_entries(&bytes);
+        assert!(entries.contains_key("xl/worksheets/sheet1.xml"));
+        assert!(!entries.contains_key("xl/sharedStrings.xml"));
+    }
+
+    #[test]
+    fn test_workbook_to_xlsx_properties() {
+        let properties = DocumentProperties {
+            title: Some("Budget".to_string()),
+            company: Some("Acme".to_string()),
+            custom: vec![CustomProperty {
+                name: "Batch".to_string(),
+                value: TypedValue::Number(42.0),
+            }],
+            ..Default::default()
+        };
+        let bytes = workbook_to_xlsx_impl(ExportWorkbook {
+            sheets: Vec::new(),
+            shared_strings: Vec::new(),
+            styles: None,
+            defined_names: Vec::new(),
+            workbook_pr: None,
+            compression_level: Some(0),
+            password: None,
+            properties: Some(properties),
+        })
+        .unwrap();
+
+        let entries = read_entries(&bytes);
+        let part = |name: &str| String::from_utf8(entries[name].clone()).unwrap();
+        assert!(part("docProps/core.xml").contains("<dc:title>Budget</dc:title>"));
+        assert!(part("docProps/app.xml").contains("<vt:lpstr>Sheet1</vt:lpstr>"));
+        assert!(part("docProps/custom.xml").contains("name=\"Batch\"><vt:i4>42</vt:i4>"));
+        let rels = part("_rels/.rels");
+        assert!(rels.contains("metadata/core-properties\" Target=\"docProps/core.xml\""));
+        assert!(rels.contains("Id=\"rId4\""));
+        let types = part("[Content_Types].xml");
+        assert!(types.contains("<Override PartName=\"/docProps/app.xml\""));
+    }
+}
@@ -0,0 +1,302 @@
+//! Worksheet parsing fed one chunk at a time
+//!
+//! A [`WorksheetFeed`] parses worksheet XML as it arrives, e.g. while the
+//! file is still downloading. Each time a chunk completes one or more rows,
+//! those rows go through the streaming parser behind the document's head
+//! (everything up to `<sheetData>`), so only the unfinished row after them
+//! is held back. `parse_worksheet_stream` drives a feed from a WHATWG
+//! `ReadableStream`.
+
+use crate::context::ParserScratch;
+#[cfg(feature = "wasm")]
+use crate::memory;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{
+    normalize_rows, parse_worksheet_from, ParsedWorksheet, WarningKind, WorksheetParseOptions,
+};
+use memchr::{memchr, memmem, memrchr};
+use std::io::Read;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "wasm")]
+use wasm_bindgen_futures::JsFuture;
+
+/// Worksheet parser that takes its XML in chunks
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Default)]
+pub struct WorksheetFeed {
+    options: WorksheetParseOptions,
+    scratch: ParserScratch,
+    /// The document through the `<sheetData>` start tag, once it has arrived
+    head: Option<Vec<u8>>,
+    /// Bytes after the last row parsed
+    pending: Vec<u8>,
+    /// Document bytes before `pending`
+    consumed: u64,
+    worksheet: ParsedWorksheet,
+    /// Rows still to leave out for `skip_rows`
+    skip: u32,
+    /// In strict mode, set at the first problem; later chunks are ignored
+    stopped: bool,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl WorksheetFeed {
+    /// Start a parse with `parse_worksheet_with_options` options
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: JsValue) -> Result<WorksheetFeed, JsValue> {
+        trap(|| {
+            let options: Option<WorksheetParseOptions> = serde_wasm_bindgen::from_value(options)?;
+            Ok(WorksheetFeed::with_options(options.unwrap_or_default()))
+        })
+    }
+
+    /// Parse the rows a chunk of the XML completes
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), JsValue> {
+        trap(|| {
+            let guard = memory::guard();
+            self.write(chunk);
+            guard.check().map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Parse the rest of the XML and return the `ParsedWorksheet`
+    pub fn finish(&mut self) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let guard = memory::guard();
+            let worksheet = self.end();
+            guard.check().map_err(|e| JsValue::from_str(&e))?;
+            self.options.check(&worksheet)?;
+            Ok(serde_wasm_bindgen::to_value(&worksheet)?)
+        })
+    }
+
+    /// Number of rows parsed so far
+    #[wasm_bindgen(getter)]
+    pub fn row_count(&self) -> usize {
+        self.worksheet.rows.len()
+    }
+}
+
+/// Parse worksheet XML from a stream while it is still arriving
+///
+/// `read` is called for each chunk and returns a `{ done, value }` result (or
+/// a promise of one), so `() => reader.read()` on a `ReadableStream` reader
+/// drives it directly. Options are those of `parse_worksheet_with_options`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub async fn parse_worksheet_stream(
+    read: js_sys::Function,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let mut feed = WorksheetFeed::new(options)?;
+    loop {
+        let next = js_sys::Promise::resolve(&read.call0(&JsValue::UNDEFINED)?);
+        let next = JsFuture::from(next).await?;
+        if js_sys::Reflect::get(&next, &JsValue::from_str("done"))?.is_truthy() {
+            break;
+        }
+        let chunk: js_sys::Uint8Array = js_sys::Reflect::get(&next, &JsValue::from_str("value"))?
+            .dyn_into()
+            .map_err(|_| JsValue::from_str("Stream chunks must be Uint8Arrays"))?;
+        feed.push(&chunk.to_vec())?;
+    }
+    feed.finish()
+}
+
+impl WorksheetFeed {
+    pub(crate) fn with_options(options: WorksheetParseOptions) -> Self {
+        WorksheetFeed {
+            skip: options.skip_rows,
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn write(&mut self, chunk: &[u8]) {
+        if self.stopped {
+            return;
+        }
+        self.pending.extend_from_slice(chunk);
+        if self.head.is_none() {
+            let Some(end) = sheet_data_start(&self.pending) else {
+                return;
+            };
+            self.head = Some(self.pending.drain(..end).collect());
+        }
+        if let Some(cut) = last_row_end(&self.pending) {
+            self.parse(cut);
+        }
+    }
+
+    /// Parse what is left, closing tags and all
+    pub(crate) fn end(&mut self) -> ParsedWorksheet {
+        if !self.stopped {
+            self.parse(self.pending.len());
+        }
+        let mut worksheet = std::mem::take(&mut self.worksheet);
+        if self.options.normalize_rows {
+            normalize_rows(&mut worksheet.rows);
+        }
+        worksheet
+    }
+
+    /// Parse the first `len` pending bytes, which end at a row boundary
+    fn parse(&mut self, len: usize) {
+        let full = self
+            .options
+            .max_rows
+            .is_some_and(|max| self.worksheet.rows.len() >= max as usize);
+        let tail = len == self.pending.len();
+        if !full || tail {
+            let head = self.head.as_deref().unwrap_or_default();
+            let first = self.consumed == 0;
+            // Skipping and the row cap apply across pieces, so they are counted here
+            let options = WorksheetParseOptions {
+                skip_rows: 0,
+                max_rows: None,
+                ..self.options.clone()
+            };
+            let piece = head.chain(&self.pending[..len]);
+            let mut parsed = parse_worksheet_from(piece, &mut self.scratch, &options);
+            if let Some(row) = parsed.rows.last() {
+                self.scratch.resume = Some(row.row_num);
+            }
+
+            let skipped = parsed.rows.len().min(self.skip as usize);
+            self.skip -= skipped as u32;
+            let room = self.options.max_rows.map_or(usize::MAX, |max| {
+                (max as usize).saturating_sub(self.worksheet.rows.len())
+            });
+            let rows = parsed
+                .rows
+                .drain(skipped..)
+                .take(if full { 0 } else { room });
+            self.worksheet.rows.extend(rows);
+
+            // Offsets are into the whole document; the head's problems count once
+            let start = self.consumed.max(head.len() as u64);
+            for mut warning in parsed.warnings {
+                if warning.offset >= head.len() as u64 {
+                    warning.offset += start - head.len() as u64;
+                } else if !first {
+                    continue;
+                }
+                self.stopped |= self.options.strict && warning.kind != WarningKind::DuplicateRow;
+                self.worksheet.warnings.push(warning);
+            }
+            self.worksheet.col_widths.extend(parsed.col_widths);
+            self.worksheet.merge_cells.append(&mut parsed.merge_cells);
+            self.worksheet.hyperlinks.append(&mut parsed.hyperlinks);
+        }
+        if self.consumed == 0 {
+            self.consumed = self.head.as_ref().map_or(0, |head| head.len() as u64);
+        }
+        self.consumed += len as u64;
+        self.pending.drain(..len);
+    }
+}
+
+/// End of the `<sheetData>` start tag
+fn sheet_data_start(xml: &[u8]) -> Option<usize> {
+    let at = memmem::find_iter(xml, b"sheetData").find(|&at| {
+        is_tag(xml, at, false)
+            && matches!(
+                xml.get(at + b"sheetData".len()),
+                Some(b' ' | b'\t' | b'\r' | b'\n' | b'/' | b'>')
+            )
+    })?;
+    Some(at + memchr(b'>', &xml[at..])? + 1)
+}
+
+/// End of the last `</row>` end tag
+fn last_row_end(xml: &[u8]) -> Option<usize> {
+    memmem::rfind_iter(xml, b"row>")
+        .find(|&at| is_tag(xml, at, true))
+        .map(|at| at + b"row>".len())
+}
+
+/// Whether the name at `at` directly follows a tag's `<` (or `</` for an
+/// end tag), allowing for a namespace prefix
+fn is_tag(xml: &[u8], at: usize, end: bool) -> bool {
+    let Some(open) = memrchr(b'<', &xml[..at]) else {
+        return false;
+    };
+    let prefix = &xml[open + 1..at];
+    let prefix = match (end, prefix.strip_prefix(b"/")) {
+        (true, Some(prefix)) => prefix,
+        (false, None) => prefix,
+        _ => return false,
+    };
+    match prefix.split_last() {
+        None => true,
+        Some((b':', name)) => name
+            .iter()
+            .all(|&b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.')),
+        Some(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_worksheet_impl;
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<x:worksheet xmlns:x="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+<x:cols><x:col min="1" max="1" width="12"/></x:cols>
+<x:sheetData>
+<x:row r="1"><x:c r="A1"><x:f t="shared" ref="A1:A3" si="0">B1*2</x:f><x:v>2</x:v></x:c></x:row>
+<x:row><x:c r="A2"><x:f t="shared" si="0"/><x:v>4</x:v></x:c><x:c t="inlineStr"><x:is><x:t>héllo</x:t></x:is></x:c></x:row>
+<x:row r="3"><x:c r="A3"><x:f t="shared" si="0"/><x:v>6</x:v></x:c></x:row>
+</x:sheetData>
+<x:mergeCells count="1"><x:mergeCell ref="A1:B1"/></x:mergeCells>
+</x:worksheet>"#;
+
+    fn feed(xml: &[u8], chunk: usize, options: WorksheetParseOptions) -> ParsedWorksheet {
+        let mut feed = WorksheetFeed::with_options(options);
+        for chunk in xml.chunks(chunk) {
+            feed.write(chunk);
+        }
+        feed.end()
+    }
+
+    #[test]
+    fn test_feed_matches_whole_parse() {
+        let whole = serde_json::to_value(parse_worksheet_impl(XML)).unwrap();
+        // Chunks split tags, rows and the two bytes of é every which way
+        for size in [1, 7, 64, XML.len()] {
+            let fed = feed(XML.as_bytes(), size, WorksheetParseOptions::default());
+            assert_eq!(
+                serde_json::to_value(&fed).unwrap(),
+                whole,
+                "chunk size {}",
+                size
+            );
+        }
+        let fed = feed(XML.as_bytes(), 5, WorksheetParseOptions::default());
+        assert_eq!(fed.rows[1].row_num, 2);
+        assert_eq!(fed.rows[2].cells[0].formula.as_deref(), Some("B3*2"));
+    }
+
+    #[test]
+    fn test_feed_skip_and_max_rows() {
+        let options = WorksheetParseOptions {
+            skip_rows: 1,
+            max_rows: Some(1),
+            ..Default::default()
+        };
+        let fed = feed(XML.as_bytes(), 3, options);
+        let rows: Vec<_> = fed.rows.iter().map(|r| r.row_num).collect();
+        assert_eq!(rows, vec![2]);
+        // What follows the rows is still read
+        assert_eq!(fed.merge_cells, vec!["A1:B1"]);
+
+        let broken = XML.replace("<x:v>4</x:v>", "<x:v>4</x:q>");
+        let fed = feed(broken.as_bytes(), 16, WorksheetParseOptions::default());
+        let offset = broken.find("</x:q>").unwrap() as u64;
+        assert!(fed.warnings[0].offset.abs_diff(offset) < 8);
+    }
+}
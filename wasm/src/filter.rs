@@ -0,0 +1,369 @@
+//! Row filtering with a JSON predicate
+//!
+//! A predicate is a condition on one column (`{ column, operator, value }`)
+//! or an `and`, `or` or `not` group of predicates. Numbers compare with
+//! numbers, including numbers written as text and ISO dates compared with
+//! date serials; text compares without regard to case unless asked.
+
+use crate::reference::{parse_cell_ref, parse_col, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{
+    datetime_to_serial, format_number, parse_iso_date, resolve_cell_value, CellValue,
+};
+use crate::workbook::TypedValue;
+use crate::ParsedWorksheet;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A test on a row, as passed to [`filter_rows`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RowPredicate {
+    And { and: Vec<RowPredicate> },
+    Or { or: Vec<RowPredicate> },
+    Not { not: Box<RowPredicate> },
+    Condition(RowCondition),
+}
+
+/// A test on one cell of the row
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RowCondition {
+    /// Column letters, e.g. "C"
+    pub column: String,
+    /// "eq", "ne", "gt", "gte", "lt", "lte" (or "=", "!=", ">", ">=", "<",
+    /// "<="), "contains", "starts_with", "ends_with", "in", "empty" or
+    /// "not_empty"
+    pub operator: String,
+    /// Value to compare with; ISO date text compares with date serials
+    #[serde(default)]
+    pub value: Option<TypedValue>,
+    /// Values for "in"
+    #[serde(default)]
+    pub values: Vec<TypedValue>,
+}
+
+/// Options for [`filter_rows`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterOptions {
+    /// First row tested (1-based), e.g. 2 to keep a header row out of it
+    pub first_row: Option<u32>,
+    /// Compare text with its case
+    pub case_sensitive: bool,
+    /// Return the worksheet holding the rows above `first_row` and the
+    /// matching rows, rather than the matching row numbers
+    pub sheet: bool,
+    pub shared_strings: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Contains,
+    StartsWith,
+    EndsWith,
+    In,
+    Empty,
+    NotEmpty,
+}
+
+/// A predicate with its columns and operators read
+enum Test<'a> {
+    And(Vec<Test<'a>>),
+    Or(Vec<Test<'a>>),
+    Not(Box<Test<'a>>),
+    Condition {
+        col: u32,
+        operator: Operator,
+        values: Vec<&'a TypedValue>,
+    },
+}
+
+/// Row numbers matching `predicate` in sheet order, or with `sheet` set the
+/// worksheet without the other rows; kept rows keep their numbers
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn filter_rows(
+    worksheet: JsValue,
+    predicate: JsValue,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let predicate: RowPredicate = serde_wasm_bindgen::from_value(predicate)?;
+        let options: Option<FilterOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+        let rows = filter_rows_impl(&worksheet, &predicate, &options)
+            .map_err(|e| JsValue::from_str(&e))?;
+        match options.sheet {
+            true => Ok(serde_wasm_bindgen::to_value(&filter_sheet(
+                worksheet, &rows, &options,
+            ))?),
+            false => Ok(serde_wasm_bindgen::to_value(&rows)?),
+        }
+    })
+}
+
+pub(crate) fn filter_rows_impl(
+    worksheet: &ParsedWorksheet,
+    predicate: &RowPredicate,
+    options: &FilterOptions,
+) -> Result<Vec<u32>, String> {
+    let test = compile(predicate)?;
+    let first_row = options.first_row.unwrap_or(1);
+    Ok(worksheet
+        .rows
+        .iter()
+        .filter(|row| row.row_num >= first_row)
+        .filter(|row| {
+            let by_col: HashMap<u32, CellValue> = row
+                .cells
+                .iter()
+                .filter_map(|cell| {
+                    let (_, col) = parse_cell_ref(&cell.reference)?;
+                    Some((col, resolve_cell_value(cell, &options.shared_strings)))
+                })
+                .collect();
+            matches(&test, &by_col, options.case_sensitive)
+        })
+        .map(|row| row.row_num)
+        .collect())
+}
+
+/// The worksheet with only the rows above `first_row` and `rows`, and the
+/// merges, hyperlinks and comments starting on them
+pub(crate) fn filter_sheet(
+    mut worksheet: ParsedWorksheet,
+    rows: &[u32],
+    options: &FilterOptions,
+) -> ParsedWorksheet {
+    let first_row = options.first_row.unwrap_or(1);
+    let rows: HashSet<u32> = rows.iter().copied().collect();
+    let kept = |row: u32| row < first_row || rows.contains(&row);
+    let starts_kept = |range: &str| range_bounds(range).is_none_or(|(r1, ..)| kept(r1));
+    worksheet.rows.retain(|row| kept(row.row_num));
+    worksheet.merge_cells.retain(|merge| starts_kept(merge));
+    worksheet
+        .hyperlinks
+        .retain(|link| starts_kept(&link.reference));
+    #[cfg(feature = "comments")]
+    worksheet
+        .comments
+        .retain(|comment| starts_kept(&comment.reference));
+    worksheet
+}
+
+fn compile(predicate: &RowPredicate) -> Result<Test<'_>, String> {
+    let condition = match predicate {
+        RowPredicate::And { and } => return Ok(Test::And(compile_all(and)?)),
+        RowPredicate::Or { or } => return Ok(Test::Or(compile_all(or)?)),
+        RowPredicate::Not { not } => return Ok(Test::Not(Box::new(compile(not)?))),
+        RowPredicate::Condition(condition) => condition,
+    };
+
+    let col = parse_col(&condition.column)
+        .ok_or_else(|| format!("Invalid column: {}", condition.column))?;
+    let operator = match condition.operator.as_str() {
+        "eq" | "=" | "==" => Operator::Eq,
+        "ne" | "!=" | "<>" => Operator::Ne,
+        "gt" | ">" => Operator::Gt,
+        "gte" | ">=" => Operator::Gte,
+        "lt" | "<" => Operator::Lt,
+        "lte" | "<=" => Operator::Lte,
+        "contains" => Operator::Contains,
+        "starts_with" => Operator::StartsWith,
+        "ends_with" => Operator::EndsWith,
+        "in" => Operator::In,
+        "empty" => Operator::Empty,
+        "not_empty" => Operator::NotEmpty,
+        other => return Err(format!("Unknown operator: {}", other)),
+    };
+    let values: Vec<&TypedValue> = match operator {
+        Operator::Empty | Operator::NotEmpty => Vec::new(),
+        Operator::In => condition.values.iter().collect(),
+        _ => vec![condition.value.as_ref().ok_or_else(|| {
+            format!(
+                "Operator {} on column {} needs a value",
+                condition.operator, condition.column
+            )
+        })?],
+    };
+    Ok(Test::Condition {
+        col,
+        operator,
+        values,
+    })
+}
+
+fn compile_all(predicates: &[RowPredicate]) -> Result<Vec<Test<'_>>, String> {
+    predicates.iter().map(compile).collect()
+}
+
+fn matches(test: &Test, by_col: &HashMap<u32, CellValue>, case_sensitive: bool) -> bool {
+    let (col, operator, values) = match test {
+        Test::And(tests) => return tests.iter().all(|t| matches(t, by_col, case_sensitive)),
+        Test::Or(tests) => return tests.iter().any(|t| matches(t, by_col, case_sensitive)),
+        Test::Not(test) => return !matches(test, by_col, case_sensitive),
+        Test::Condition {
+            col,
+            operator,
+            values,
+        } => (col, *operator, values),
+    };
+    let cell = by_col.get(col).unwrap_or(&CellValue::Empty);
+    let empty = match cell {
+        CellValue::Empty => true,
+        CellValue::Text(text) => text.trim().is_empty(),
+        _ => false,
+    };
+    let fold = |text: String| match case_sensitive {
+        true => text,
+        false => text.to_lowercase(),
+    };
+    let order = |value: &TypedValue| compare(cell, value, case_sensitive);
+    let text = |value: &TypedValue| (fold(cell_text(cell)), fold(typed_text(value)));
+    match operator {
+        Operator::Empty => empty,
+        Operator::NotEmpty => !empty,
+        Operator::Ne => values.iter().all(|v| order(v) != Some(Ordering::Equal)),
+        Operator::Eq | Operator::In => values.iter().any(|v| order(v) == Some(Ordering::Equal)),
+        Operator::Gt => values.iter().all(|v| order(v) == Some(Ordering::Greater)),
+        Operator::Gte => values.iter().all(|v| order(v).is_some_and(Ordering::is_ge)),
+        Operator::Lt => values.iter().all(|v| order(v) == Some(Ordering::Less)),
+        Operator::Lte => values.iter().all(|v| order(v).is_some_and(Ordering::is_le)),
+        Operator::Contains => values.iter().all(|v| {
+            let (cell, value) = text(v);
+            !empty && cell.contains(&value)
+        }),
+        Operator::StartsWith => values.iter().all(|v| {
+            let (cell, value) = text(v);
+            !empty && cell.starts_with(&value)
+        }),
+        Operator::EndsWith => values.iter().all(|v| {
+            let (cell, value) = text(v);
+            !empty && cell.ends_with(&value)
+        }),
+    }
+}
+
+/// How a cell's value orders against `value`; `None` when they can't be
+/// compared, as text with a number that it doesn't read as
+fn compare(cell: &CellValue, value: &TypedValue, case_sensitive: bool) -> Option<Ordering> {
+    match (cell, value) {
+        (CellValue::Number(n), TypedValue::Number(v)) => n.partial_cmp(v),
+        (CellValue::Number(n), TypedValue::Text(text)) => {
+            let text = text.trim();
+            let v = match text.parse::<f64>() {
+                Ok(v) => v,
+                Err(_) => datetime_to_serial(&parse_iso_date(text)?),
+            };
+            n.partial_cmp(&v)
+        }
+        (CellValue::Text(text), TypedValue::Number(v)) => {
+            text.trim().parse::<f64>().ok()?.partial_cmp(v)
+        }
+        (CellValue::Text(a), TypedValue::Text(b)) => Some(match case_sensitive {
+            true => a.cmp(b),
+            false => a.to_lowercase().cmp(&b.to_lowercase()),
+        }),
+        (CellValue::Bool(a), TypedValue::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn cell_text(cell: &CellValue) -> String {
+    match cell {
+        CellValue::Empty => String::new(),
+        CellValue::Number(n) => format_number(*n),
+        CellValue::Text(text) | CellValue::Error(text) => text.clone(),
+        CellValue::Bool(b) => bool_text(*b),
+    }
+}
+
+fn typed_text(value: &TypedValue) -> String {
+    match value {
+        TypedValue::Number(n) => format_number(*n),
+        TypedValue::Text(text) => text.clone(),
+        TypedValue::Bool(b) => bool_text(*b),
+    }
+}
+
+fn bool_text(b: bool) -> String {
+    match b {
+        true => "TRUE",
+        false => "FALSE",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::sheet_from_rows;
+
+    #[test]
+    fn test_filter_rows() {
+        let worksheet = ParsedWorksheet {
+            merge_cells: vec!["A3:B3".to_string(), "A1:B1".to_string()],
+            ..sheet_from_rows(&[
+                &["Name", "Joined"],
+                &["Ann Lee", "45292"],
+                &["bob", "45000"],
+                &["'7", ""],
+                &["", "45500"],
+                &["Annika", "2024-06-01"],
+            ])
+        };
+        let options = FilterOptions {
+            first_row: Some(2),
+            ..Default::default()
+        };
+        let filter = |json: &str| {
+            let predicate: RowPredicate = serde_json::from_str(json).unwrap();
+            filter_rows_impl(&worksheet, &predicate, &options)
+        };
+
+        assert_eq!(
+            filter(r#"{"column": "A", "operator": "starts_with", "value": "ann"}"#),
+            Ok(vec![2, 6])
+        );
+        // Numbers in text, and ISO dates against serials as well as text
+        assert_eq!(
+            filter(r#"{"column": "a", "operator": ">", "value": 5}"#),
+            Ok(vec![4])
+        );
+        assert_eq!(
+            filter(r#"{"column": "B", "operator": "gte", "value": "2024-01-01"}"#),
+            Ok(vec![2, 5, 6])
+        );
+        assert_eq!(
+            filter(
+                r#"{"or": [
+                    {"and": [
+                        {"column": "B", "operator": "<", "value": 45300},
+                        {"not": {"column": "A", "operator": "eq", "value": "BOB"}}
+                    ]},
+                    {"column": "A", "operator": "empty"},
+                    {"column": "A", "operator": "in", "values": ["7", "x"]}
+                ]}"#
+            ),
+            Ok(vec![2, 4, 5])
+        );
+        assert!(filter(r#"{"column": "A", "operator": "like", "value": "a"}"#).is_err());
+        assert!(filter(r#"{"column": "A", "operator": "eq"}"#).is_err());
+
+        let sheet = filter_sheet(worksheet.clone(), &[2, 6], &options);
+        let kept: Vec<u32> = sheet.rows.iter().map(|row| row.row_num).collect();
+        assert_eq!(kept, [1, 2, 6]);
+        assert_eq!(sheet.merge_cells, ["A1:B1"]);
+    }
+}
@@ -0,0 +1,709 @@
+//! Formula tokenizer and A1 / R1C1 reference conversion
+//!
+//! Formulas are split into tokens with byte spans so callers can rewrite
+//! references and copy everything else through unchanged. References are
+//! held as absolute 1-based positions plus `$` flags; R1C1 offsets are
+//! resolved against the anchor cell while tokenizing.
+
+use crate::reference::{col_to_letters, parse_cell_ref};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use std::ops::Range;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+pub(crate) const MAX_ROWS: u32 = 1_048_576;
+pub(crate) const MAX_COLS: u32 = 16_384;
+
+/// One coordinate of a reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Axis {
+    /// 1-based row or column
+    pub index: u32,
+    /// `$`-anchored in A1, an absolute number rather than an offset in R1C1
+    pub absolute: bool,
+}
+
+/// A cell (both axes), a whole row (row only) or a whole column (column only)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RefPoint {
+    pub row: Option<Axis>,
+    pub col: Option<Axis>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Reference {
+    /// Unquoted sheet name
+    pub sheet: Option<String>,
+    /// Last sheet of a 3D reference (`Sheet1:Sheet3!A1`)
+    pub last_sheet: Option<String>,
+    pub start: RefPoint,
+    pub end: Option<RefPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(String),
+    Ref(Reference),
+    /// A defined name, possibly sheet-qualified as written
+    Name(String),
+    /// Function name; the `(` follows as its own token
+    Function(String),
+    /// `Table1[Column]`, `[@Column]`, `Table1[[#Headers],[A]:[B]]`
+    StructuredRef(String),
+    Operator(String),
+    Open,
+    Close,
+    /// `,` or `;`
+    Separator(char),
+    ArrayOpen,
+    ArrayClose,
+    Whitespace,
+}
+
+/// How cell references are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Notation {
+    A1,
+    /// R1C1, with relative offsets taken from this (row, col) anchor
+    R1C1(u32, u32),
+}
+
+const ERRORS: [&str; 10] = [
+    "#NULL!",
+    "#DIV/0!",
+    "#VALUE!",
+    "#REF!",
+    "#NAME?",
+    "#NUM!",
+    "#N/A",
+    "#GETTING_DATA",
+    "#SPILL!",
+    "#CALC!",
+];
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'\\' | b'?') || b >= 0x80
+}
+
+/// Whether a sheet name must be quoted in a formula
+pub(crate) fn needs_quotes(name: &str) -> bool {
+    name.is_empty()
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        || a1_point(name.as_bytes(), 0).is_some_and(|(_, end)| end == name.len())
+        || r1c1_point(name.as_bytes(), 0, (1, 1)).is_some_and(|(_, end)| end == name.len())
+}
+
+pub(crate) fn format_sheet(sheet: &str, last_sheet: Option<&str>) -> String {
+    let joined = match last_sheet {
+        Some(last) => format!("{}:{}", sheet, last),
+        None => sheet.to_string(),
+    };
+    if needs_quotes(sheet) || last_sheet.is_some_and(needs_quotes) {
+        format!("'{}'!", joined.replace('\'', "''"))
+    } else {
+        format!("{}!", joined)
+    }
+}
+
+impl Reference {
+    pub(crate) fn sheet_prefix(&self) -> String {
+        self.sheet
+            .as_deref()
+            .map(|sheet| format_sheet(sheet, self.last_sheet.as_deref()))
+            .unwrap_or_default()
+    }
+
+    /// Write the reference in A1 notation
+    pub(crate) fn to_a1(&self) -> String {
+        let point = |p: &RefPoint| {
+            let mut out = String::new();
+            if let Some(col) = p.col {
+                if col.absolute {
+                    out.push('$');
+                }
+                out.push_str(&col_to_letters(col.index));
+            }
+            if let Some(row) = p.row {
+                if row.absolute {
+                    out.push('$');
+                }
+                out.push_str(&row.index.to_string());
+            }
+            out
+        };
+        let mut out = self.sheet_prefix();
+        out.push_str(&point(&self.start));
+        if let Some(end) = &self.end {
+            out.push(':');
+            out.push_str(&point(end));
+        }
+        out
+    }
+
+    /// Write the reference in R1C1 notation relative to `anchor` (row, col)
+    pub(crate) fn to_r1c1(&self, anchor: (u32, u32)) -> String {
+        let axis = |out: &mut String, prefix: char, axis: Axis, origin: u32| {
+            out.push(prefix);
+            if axis.absolute {
+                out.push_str(&axis.index.to_string());
+            } else if axis.index != origin {
+                out.push_str(&format!("[{}]", axis.index as i64 - origin as i64));
+            }
+        };
+        let point = |p: &RefPoint| {
+            let mut out = String::new();
+            if let Some(row) = p.row {
+                axis(&mut out, 'R', row, anchor.0);
+            }
+            if let Some(col) = p.col {
+                axis(&mut out, 'C', col, anchor.1);
+            }
+            out
+        };
+
+        let mut out = self.sheet_prefix();
+        out.push_str(&point(&self.start));
+        match &self.end {
+            // Whole rows and columns collapse to `R1` / `C2` when they span one line
+            Some(end)
+                if *end == self.start && self.start.row.is_some() != self.start.col.is_some() => {}
+            Some(end) => {
+                out.push(':');
+                out.push_str(&point(end));
+            }
+            None => {}
+        }
+        out
+    }
+}
+
+/// Parse `$?LETTERS$?DIGITS`, `$?LETTERS` or `$?DIGITS` at `pos`
+fn a1_point(bytes: &[u8], mut pos: usize) -> Option<(RefPoint, usize)> {
+    let mut col = None;
+    let mut row = None;
+
+    let absolute = bytes.get(pos) == Some(&b'$');
+    let letters_start = pos + absolute as usize;
+    let mut end = letters_start;
+    while end < bytes.len() && bytes[end].is_ascii_alphabetic() && end - letters_start < 3 {
+        end += 1;
+    }
+    if end > letters_start {
+        let index = bytes[letters_start..end].iter().fold(0u32, |acc, b| {
+            acc * 26 + (b.to_ascii_uppercase() - b'A' + 1) as u32
+        });
+        if index > MAX_COLS {
+            return None;
+        }
+        col = Some(Axis { index, absolute });
+        pos = end;
+    }
+
+    let absolute = bytes.get(pos) == Some(&b'$');
+    let digits_start = pos + absolute as usize;
+    let mut end = digits_start;
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    if end > digits_start {
+        let index: u32 = std::str::from_utf8(&bytes[digits_start..end])
+            .ok()?
+            .parse()
+            .ok()?;
+        if index == 0 || index > MAX_ROWS {
+            return None;
+        }
+        row = Some(Axis { index, absolute });
+        pos = end;
+    } else if absolute {
+        return None;
+    }
+
+    (col.is_some() || row.is_some()).then_some((RefPoint { row, col }, pos))
+}
+
+/// Parse one `R`/`C` axis: a number, a bracketed offset, or nothing (offset 0)
+fn r1c1_axis(bytes: &[u8], pos: usize, origin: u32, max: u32) -> Option<(Axis, usize)> {
+    let bracketed = bytes.get(pos) == Some(&b'[');
+    let start = pos + bracketed as usize;
+    let mut end = start;
+    if bracketed && bytes.get(end) == Some(&b'-') {
+        end += 1;
+    }
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    let digits = std::str::from_utf8(&bytes[start..end]).ok()?;
+
+    if bracketed {
+        if bytes.get(end) != Some(&b']') {
+            return None;
+        }
+        let offset: i64 = digits.parse().ok()?;
+        let index = origin as i64 + offset;
+        // An offset that leaves the sheet still parses; the caller reports #REF!
+        let index = if (1..=max as i64).contains(&index) {
+            index as u32
+        } else {
+            0
+        };
+        Some((
+            Axis {
+                index,
+                absolute: false,
+            },
+            end + 1,
+        ))
+    } else if digits.is_empty() {
+        Some((
+            Axis {
+                index: origin,
+                absolute: false,
+            },
+            end,
+        ))
+    } else {
+        let index: u32 = digits.parse().ok()?;
+        if index == 0 || index > max {
+            return None;
+        }
+        Some((
+            Axis {
+                index,
+                absolute: true,
+            },
+            end,
+        ))
+    }
+}
+
+/// Parse `R..C..`, `R..` or `C..` at `pos`
+fn r1c1_point(bytes: &[u8], mut pos: usize, anchor: (u32, u32)) -> Option<(RefPoint, usize)> {
+    let mut point = RefPoint {
+        row: None,
+        col: None,
+    };
+    if matches!(bytes.get(pos), Some(b'R' | b'r')) {
+        let (axis, end) = r1c1_axis(bytes, pos + 1, anchor.0, MAX_ROWS)?;
+        point.row = Some(axis);
+        pos = end;
+    }
+    if matches!(bytes.get(pos), Some(b'C' | b'c')) {
+        let (axis, end) = r1c1_axis(bytes, pos + 1, anchor.1, MAX_COLS)?;
+        point.col = Some(axis);
+        pos = end;
+    }
+    (point.row.is_some() || point.col.is_some()).then_some((point, pos))
+}
+
+fn point_kind(point: &RefPoint) -> (bool, bool) {
+    (point.row.is_some(), point.col.is_some())
+}
+
+/// Parse a reference (without sheet) at `pos`, requiring a word boundary after it
+fn parse_reference(
+    bytes: &[u8],
+    pos: usize,
+    notation: Notation,
+) -> Option<(RefPoint, Option<RefPoint>, usize)> {
+    let point = |pos| match notation {
+        Notation::A1 => a1_point(bytes, pos),
+        Notation::R1C1(row, col) => r1c1_point(bytes, pos, (row, col)),
+    };
+
+    let (start, mut end) = point(pos)?;
+    let mut range_end = None;
+    if bytes.get(end) == Some(&b':') {
+        if let Some((second, after)) = point(end + 1) {
+            if point_kind(&second) == point_kind(&start) {
+                range_end = Some(second);
+                end = after;
+            }
+        }
+    }
+
+    if bytes
+        .get(end)
+        .is_some_and(|&b| is_word_byte(b) || b == b'(' || b == b'[' || b == b'!')
+    {
+        return None;
+    }
+    // A1 whole rows and columns only exist as ranges (`A:A`, `1:1`)
+    let (has_row, has_col) = point_kind(&start);
+    match (notation, range_end) {
+        (Notation::A1, None) if !(has_row && has_col) => None,
+        (Notation::R1C1(..), None) if !(has_row && has_col) => Some((start, Some(start), end)),
+        _ => Some((start, range_end, end)),
+    }
+}
+
+/// Index of the byte after a `'quoted'` run starting at `pos`, and its unescaped text
+fn quoted(formula: &str, pos: usize, quote: u8) -> Option<(String, usize)> {
+    let bytes = formula.as_bytes();
+    let mut text = String::new();
+    let mut i = pos + 1;
+    let mut run_start = i;
+    loop {
+        match bytes.get(i) {
+            Some(&b) if b == quote => {
+                text.push_str(&formula[run_start..i]);
+                if bytes.get(i + 1) == Some(&quote) {
+                    text.push(quote as char);
+                    i += 2;
+                    run_start = i;
+                } else {
+                    return Some((text, i + 1));
+                }
+            }
+            Some(_) => i += 1,
+            None => return None,
+        }
+    }
+}
+
+/// Index just past a balanced `[...]` group starting at `pos`
+fn bracket_end(bytes: &[u8], pos: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = pos;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            // An apostrophe escapes the next character in a column name
+            b'\'' => i += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn ref_token(
+    sheet: Option<String>,
+    last_sheet: Option<String>,
+    start: RefPoint,
+    end: Option<RefPoint>,
+) -> Token {
+    let out_of_range =
+        |p: &RefPoint| p.row.is_some_and(|a| a.index == 0) || p.col.is_some_and(|a| a.index == 0);
+    if out_of_range(&start) || end.as_ref().is_some_and(out_of_range) {
+        return Token::Error("#REF!".to_string());
+    }
+    Token::Ref(Reference {
+        sheet,
+        last_sheet,
+        start,
+        end,
+    })
+}
+
+/// Split a formula (with or without the leading `=`) into tokens and their byte spans
+pub(crate) fn tokenize(
+    formula: &str,
+    notation: Notation,
+) -> Result<Vec<(Token, Range<usize>)>, String> {
+    let bytes = formula.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let b = bytes[pos];
+        let token = match b {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t' | b'\r' | b'\n') {
+                    pos += 1;
+                }
+                Token::Whitespace
+            }
+            b'"' => {
+                let (text, end) =
+                    quoted(formula, pos, b'"').ok_or("Unterminated string literal")?;
+                pos = end;
+                Token::Text(text)
+            }
+            b'\'' => {
+                let (sheet, end) = quoted(formula, pos, b'\'').ok_or("Unterminated sheet name")?;
+                if bytes.get(end) != Some(&b'!') {
+                    return Err(format!("Expected ! after sheet name '{}'", sheet));
+                }
+                let (sheet, last_sheet) = match sheet.split_once(':') {
+                    Some((first, last)) => (first.to_string(), Some(last.to_string())),
+                    None => (sheet, None),
+                };
+                match parse_reference(bytes, end + 1, notation) {
+                    Some((first, second, after)) => {
+                        pos = after;
+                        ref_token(Some(sheet), last_sheet, first, second)
+                    }
+                    None => {
+                        // Sheet-scoped defined name
+                        pos = end + 1;
+                        while pos < bytes.len() && is_word_byte(bytes[pos]) {
+                            pos += 1;
+                        }
+                        Token::Name(formula[start..pos].to_string())
+                    }
+                }
+            }
+            b'#' => match ERRORS
+                .iter()
+                .find(|e| formula[pos..].to_ascii_uppercase().starts_with(*e))
+            {
+                Some(error) => {
+                    pos += error.len();
+                    Token::Error(error.to_string())
+                }
+                None => {
+                    pos += 1;
+                    Token::Operator("#".to_string())
+                }
+            },
+            b'(' => {
+                pos += 1;
+                Token::Open
+            }
+            b')' => {
+                pos += 1;
+                Token::Close
+            }
+            b'{' => {
+                pos += 1;
+                Token::ArrayOpen
+            }
+            b'}' => {
+                pos += 1;
+                Token::ArrayClose
+            }
+            b',' | b';' => {
+                pos += 1;
+                Token::Separator(b as char)
+            }
+            b'[' => {
+                pos = bracket_end(bytes, pos).ok_or("Unterminated structured reference")?;
+                Token::StructuredRef(formula[start..pos].to_string())
+            }
+            b'<' | b'>' => {
+                pos += 1;
+                if matches!(bytes.get(pos), Some(b'='))
+                    || (b == b'<' && bytes.get(pos) == Some(&b'>'))
+                {
+                    pos += 1;
+                }
+                Token::Operator(formula[start..pos].to_string())
+            }
+            b'+' | b'-' | b'*' | b'/' | b'^' | b'&' | b'=' | b'%' | b':' | b'@' | b'!' => {
+                pos += 1;
+                Token::Operator((b as char).to_string())
+            }
+            _ => {
+                if let Some((first, second, after)) = parse_reference(bytes, pos, notation) {
+                    pos = after;
+                    ref_token(None, None, first, second)
+                } else if b.is_ascii_digit()
+                    || (b == b'.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit))
+                {
+                    while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'.') {
+                        pos += 1;
+                    }
+                    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+                        let mut exp = pos + 1;
+                        if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+                            exp += 1;
+                        }
+                        if bytes.get(exp).is_some_and(u8::is_ascii_digit) {
+                            pos = exp;
+                            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                                pos += 1;
+                            }
+                        }
+                    }
+                    let number = formula[start..pos]
+                        .parse()
+                        .map_err(|_| format!("Invalid number: {}", &formula[start..pos]))?;
+                    Token::Number(number)
+                } else if is_word_byte(b) || b == b'$' {
+                    while pos < bytes.len() && (is_word_byte(bytes[pos]) || bytes[pos] == b'$') {
+                        pos += 1;
+                    }
+                    let word = &formula[start..pos];
+
+                    // Sheet prefix, possibly 3D (`Sheet1:Sheet3!`)
+                    let mut sheet_end = None;
+                    if bytes.get(pos) == Some(&b'!') {
+                        sheet_end = Some((word.to_string(), None, pos));
+                    } else if bytes.get(pos) == Some(&b':') {
+                        let mut end = pos + 1;
+                        while end < bytes.len() && is_word_byte(bytes[end]) {
+                            end += 1;
+                        }
+                        if end > pos + 1 && bytes.get(end) == Some(&b'!') {
+                            sheet_end = Some((
+                                word.to_string(),
+                                Some(formula[pos + 1..end].to_string()),
+                                end,
+                            ));
+                        }
+                    }
+
+                    if let Some((sheet, last_sheet, bang)) = sheet_end {
+                        match parse_reference(bytes, bang + 1, notation) {
+                            Some((first, second, after)) => {
+                                pos = after;
+                                ref_token(Some(sheet), last_sheet, first, second)
+                            }
+                            None => {
+                                pos = bang + 1;
+                                while pos < bytes.len() && is_word_byte(bytes[pos]) {
+                                    pos += 1;
+                                }
+                                Token::Name(formula[start..pos].to_string())
+                            }
+                        }
+                    } else if bytes.get(pos) == Some(&b'(') {
+                        Token::Function(word.to_string())
+                    } else if bytes.get(pos) == Some(&b'[') {
+                        pos = bracket_end(bytes, pos).ok_or("Unterminated structured reference")?;
+                        Token::StructuredRef(formula[start..pos].to_string())
+                    } else if word.eq_ignore_ascii_case("TRUE")
+                        || word.eq_ignore_ascii_case("FALSE")
+                    {
+                        Token::Bool(word.eq_ignore_ascii_case("TRUE"))
+                    } else {
+                        Token::Name(word.to_string())
+                    }
+                } else {
+                    return Err(format!("Unexpected character at position {}", pos));
+                }
+            }
+        };
+        tokens.push((token, start..pos));
+    }
+    Ok(tokens)
+}
+
+/// Rewrite every reference in `formula`, copying other tokens through as written
+pub(crate) fn rewrite_references<F>(
+    formula: &str,
+    notation: Notation,
+    mut rewrite: F,
+) -> Result<String, String>
+where
+    F: FnMut(&Reference) -> String,
+{
+    let mut out = String::with_capacity(formula.len());
+    for (token, span) in tokenize(formula, notation)? {
+        match token {
+            Token::Ref(reference) => out.push_str(&rewrite(&reference)),
+            // Out-of-range R1C1 offsets come back as #REF!
+            Token::Error(error) => out.push_str(&error),
+            _ => out.push_str(&formula[span]),
+        }
+    }
+    Ok(out)
+}
+
+/// Re-anchor a formula written for the cell `from` so it applies to `to`, as
+/// when filling a shared formula or pasting a copied cell
+pub(crate) fn translate_formula(formula: &str, from: (u32, u32), to: (u32, u32)) -> Option<String> {
+    let r1c1 = rewrite_references(formula, Notation::A1, |r| r.to_r1c1(from)).ok()?;
+    rewrite_references(&r1c1, Notation::R1C1(to.0, to.1), Reference::to_a1).ok()
+}
+
+#[cfg(feature = "wasm")]
+fn parse_anchor(anchor: &str) -> Result<(u32, u32), JsValue> {
+    parse_cell_ref(anchor)
+        .ok_or_else(|| JsValue::from_str(&format!("Invalid anchor cell: {}", anchor)))
+}
+
+/// Convert an A1 formula to R1C1, with relative references measured from `anchor`
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn formula_a1_to_r1c1(formula: &str, anchor: &str) -> Result<String, JsValue> {
+    trap(|| {
+        let anchor = parse_anchor(anchor)?;
+        rewrite_references(formula, Notation::A1, |r| r.to_r1c1(anchor))
+            .map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+/// Convert an R1C1 formula to A1, resolving relative offsets against `anchor`
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn formula_r1c1_to_a1(formula: &str, anchor: &str) -> Result<String, JsValue> {
+    trap(|| {
+        let (row, col) = parse_anchor(anchor)?;
+        rewrite_references(formula, Notation::R1C1(row, col), Reference::to_a1)
+            .map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_r1c1(formula: &str, anchor: (u32, u32)) -> String {
+        rewrite_references(formula, Notation::A1, |r| r.to_r1c1(anchor)).unwrap()
+    }
+
+    fn to_a1(formula: &str, anchor: (u32, u32)) -> String {
+        rewrite_references(
+            formula,
+            Notation::R1C1(anchor.0, anchor.1),
+            Reference::to_a1,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_a1_to_r1c1() {
+        let anchor = (3, 2); // B3
+        assert_eq!(
+            to_r1c1("=SUM(A1:B2)*$C$4+B3", anchor),
+            "=SUM(R[-2]C[-1]:R[-1]C)*R4C3+RC"
+        );
+        assert_eq!(
+            to_r1c1("='My Sheet'!A$1&\"A1\"+Data!$B:$B+LOG10(2)", anchor),
+            "='My Sheet'!R1C[-1]&\"A1\"+Data!C2+LOG10(2)"
+        );
+        assert_eq!(
+            to_r1c1("=SUM(3:5)+Sheet1:Sheet3!A1", anchor),
+            "=SUM(R:R[2])+Sheet1:Sheet3!R[-2]C[-1]"
+        );
+        assert_eq!(
+            to_r1c1("=Table1[Amount]+rate*1.5E+3", anchor),
+            "=Table1[Amount]+rate*1.5E+3"
+        );
+    }
+
+    #[test]
+    fn test_r1c1_to_a1() {
+        let anchor = (3, 2);
+        assert_eq!(
+            to_a1("=SUM(R[-2]C[-1]:R[-1]C)*R4C3+RC", anchor),
+            "=SUM(A1:B2)*$C$4+B3"
+        );
+        assert_eq!(
+            to_a1("='My Sheet'!R1C[-1]+C2+R[1]", anchor),
+            "='My Sheet'!A$1+$B:$B+4:4"
+        );
+        assert_eq!(to_a1("=R[-5]C", anchor), "=#REF!");
+        assert_eq!(to_a1("=Rate*2", anchor), "=Rate*2");
+        assert_eq!(
+            translate_formula("A1*$B$1+Sheet2!C$3", (2, 3), (4, 4)).as_deref(),
+            Some("B3*$B$1+Sheet2!D$3")
+        );
+    }
+}
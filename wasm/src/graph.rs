@@ -0,0 +1,699 @@
+//! Formula dependency graph
+//!
+//! Every formula cell becomes a node. A node depends on the formula cells
+//! that fall inside the ranges it references, directly or through defined
+//! names, and the graph is ordered so precedents come before dependents.
+//! The same walk records calls to volatile functions such as NOW and OFFSET.
+
+use crate::formula::{
+    format_sheet, tokenize, Axis, Notation, RefPoint, Reference, Token, MAX_COLS, MAX_ROWS,
+};
+use crate::range::contains_cell;
+use crate::reference::{cell_ref, parse_cell_ref, Bounds};
+use crate::table::{resolve_structured_reference, ParsedTable};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{ParsedDefinedName, ParsedSheet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Functions whose result can change without any precedent changing
+const VOLATILE_FUNCTIONS: [&str; 9] = [
+    "NOW",
+    "TODAY",
+    "RAND",
+    "RANDBETWEEN",
+    "RANDARRAY",
+    "OFFSET",
+    "INDIRECT",
+    "CELL",
+    "INFO",
+];
+
+/// Sheets, plus any defined names and tables, to build the graph from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphWorkbook {
+    pub sheets: Vec<ParsedSheet>,
+    /// `local_sheet_id` is taken as an index into `sheets`
+    #[serde(default)]
+    pub defined_names: Vec<ParsedDefinedName>,
+    #[serde(default)]
+    pub tables: Vec<ParsedTable>,
+}
+
+/// A formula cell and its direct links
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DependencyNode {
+    /// Sheet-qualified cell, e.g. `Sheet1!B2`
+    pub cell: String,
+    pub formula: String,
+    /// Referenced cells and ranges, sheet-qualified and without `$` markers
+    pub precedents: Vec<String>,
+    /// Formula cells that reference this cell
+    pub dependents: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    /// Formula cells in sheet and row order
+    pub nodes: Vec<DependencyNode>,
+    /// Formula cells ordered so each comes after its precedents; the members
+    /// of a cycle are kept together
+    pub order: Vec<String>,
+    /// Groups of formula cells that depend on each other
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// A formula cell calling volatile functions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolatileCell {
+    pub cell: String,
+    /// Volatile functions called, directly or through defined names
+    pub functions: Vec<String>,
+}
+
+/// What a host should know before turning on recalculation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FormulaAnalysis {
+    pub volatile: Vec<VolatileCell>,
+    /// Formula cells that depend on a volatile cell, and so recalculate with it
+    pub volatile_dependents: Vec<String>,
+    /// Groups of formula cells that depend on each other
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// Build the dependency graph of all formula cells in a workbook
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn build_dependency_graph(workbook: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let workbook: GraphWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        let graph = build_dependency_graph_impl(&workbook).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&graph)?)
+    })
+}
+
+/// Find volatile formulas and circular reference chains in a workbook
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn analyze_formulas(workbook: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let workbook: GraphWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        let analysis = analyze_formulas_impl(&workbook).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&analysis)?)
+    })
+}
+
+/// A referenced area: sheet index (`None` for unknown sheets) and bounds
+struct Area {
+    sheet: Option<usize>,
+    bounds: Bounds,
+}
+
+struct Collector<'a> {
+    sheets: &'a [ParsedSheet],
+    defined_names: &'a [ParsedDefinedName],
+    tables: &'a [ParsedTable],
+    /// The formula's own cell, for `[@Column]` references
+    cell: (u32, u32),
+    precedents: Vec<String>,
+    areas: Vec<Area>,
+    volatile: Vec<String>,
+    /// Defined names already expanded, to stop self-referencing names
+    names: HashSet<String>,
+}
+
+impl Collector<'_> {
+    fn sheet_index(&self, name: &str) -> Option<usize> {
+        sheet_index(self.sheets, name)
+    }
+
+    fn add_formula(&mut self, formula: &str, sheet: usize) -> Result<(), String> {
+        for (token, _) in tokenize(formula, Notation::A1)? {
+            match token {
+                Token::Ref(reference) => self.add_reference(&reference, sheet),
+                Token::Name(name) => self.add_name(&name, sheet)?,
+                Token::StructuredRef(written) => self.add_structured(&written, sheet),
+                Token::Function(name) => {
+                    let name = name.to_ascii_uppercase();
+                    let name = name.strip_prefix("_XLFN.").unwrap_or(&name);
+                    if VOLATILE_FUNCTIONS.contains(&name)
+                        && !self.volatile.iter().any(|v| v == name)
+                    {
+                        self.volatile.push(name.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add_reference(&mut self, reference: &Reference, sheet: usize) {
+        let relative = |axis: Option<Axis>| {
+            axis.map(|a| Axis {
+                absolute: false,
+                ..a
+            })
+        };
+        let point = |p: &RefPoint| RefPoint {
+            row: relative(p.row),
+            col: relative(p.col),
+        };
+
+        // Whole rows and columns run to the sheet edge
+        let end = reference.end.unwrap_or(reference.start);
+        let r1 = reference.start.row.map_or(1, |a| a.index);
+        let c1 = reference.start.col.map_or(1, |a| a.index);
+        let r2 = end.row.map_or(MAX_ROWS, |a| a.index);
+        let c2 = end.col.map_or(MAX_COLS, |a| a.index);
+        let area_bounds = (r1.min(r2), c1.min(c2), r1.max(r2), c1.max(c2));
+
+        // Sheets the reference spans, by name, in workbook order for 3D references
+        let names: Vec<String> = match (&reference.sheet, &reference.last_sheet) {
+            (None, _) => vec![self.sheets[sheet].name.clone()],
+            (Some(first), None) => vec![first.clone()],
+            (Some(first), Some(last)) => match (self.sheet_index(first), self.sheet_index(last)) {
+                (Some(a), Some(b)) => self.sheets[a.min(b)..=a.max(b)]
+                    .iter()
+                    .map(|s| s.name.clone())
+                    .collect(),
+                _ => vec![first.clone(), last.clone()],
+            },
+        };
+
+        for name in names {
+            let written = Reference {
+                sheet: Some(name.clone()),
+                last_sheet: None,
+                start: point(&reference.start),
+                end: reference.end.as_ref().map(point),
+            }
+            .to_a1();
+            if !self.precedents.contains(&written) {
+                self.precedents.push(written);
+            }
+            self.areas.push(Area {
+                sheet: self.sheet_index(&name),
+                bounds: area_bounds,
+            });
+        }
+    }
+
+    fn add_name(&mut self, written: &str, sheet: usize) -> Result<(), String> {
+        let Some((defined, scope)) =
+            find_defined_name(self.sheets, self.defined_names, written, sheet)
+        else {
+            // A bare table name covers its data rows; other unknown names have no cells
+            self.add_structured(&format!("{}[]", written), sheet);
+            return Ok(());
+        };
+        if !self.names.insert(defined.name.to_lowercase()) {
+            return Ok(());
+        }
+        self.add_formula(&defined.value, scope)
+            .map_err(|e| format!("Defined name {}: {}", defined.name, e))
+    }
+
+    fn add_structured(&mut self, written: &str, sheet: usize) {
+        let name = &self.sheets[sheet].name;
+        // Unresolvable references evaluate to errors and have no precedents
+        if let Ok(reference) =
+            resolve_structured_reference(written, self.tables, Some(name), Some(self.cell))
+        {
+            self.add_reference(&reference, sheet);
+        }
+    }
+}
+
+pub(crate) fn sheet_index(sheets: &[ParsedSheet], name: &str) -> Option<usize> {
+    sheets
+        .iter()
+        .position(|sheet| sheet.name.eq_ignore_ascii_case(name))
+}
+
+/// Resolve a defined name as written in a formula on `sheet`, preferring a
+/// sheet-scoped definition over a global one. Returns the definition and the
+/// sheet its unqualified references point to.
+pub(crate) fn find_defined_name<'a>(
+    sheets: &[ParsedSheet],
+    defined_names: &'a [ParsedDefinedName],
+    written: &str,
+    sheet: usize,
+) -> Option<(&'a ParsedDefinedName, usize)> {
+    let (scope, name) = match written.rsplit_once('!') {
+        Some((scope, name)) => (sheet_index(sheets, scope.trim_matches('\'')), name),
+        None => (Some(sheet), written),
+    };
+    let matches = |d: &&ParsedDefinedName| d.name.eq_ignore_ascii_case(name);
+    let defined = defined_names
+        .iter()
+        .filter(matches)
+        .find(|d| {
+            d.local_sheet_id
+                .is_some_and(|id| Some(id as usize) == scope)
+        })
+        .or_else(|| {
+            defined_names
+                .iter()
+                .filter(matches)
+                .find(|d| d.local_sheet_id.is_none())
+        })?;
+    Some((defined, scope.unwrap_or(sheet)))
+}
+
+/// Tarjan's strongly connected components, iteratively so long chains
+/// cannot overflow the stack. Components come out dependents-first.
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = edges.len();
+    let mut index: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut counter = 0;
+
+    for root in 0..n {
+        if index[root].is_some() {
+            continue;
+        }
+        let mut calls = vec![(root, 0)];
+        index[root] = Some(counter);
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((v, next)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&w) = edges[v].get(*next) {
+                *next += 1;
+                match index[w] {
+                    None => {
+                        index[w] = Some(counter);
+                        low[w] = counter;
+                        counter += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        calls.push((w, 0));
+                    }
+                    Some(i) if on_stack[w] => low[v] = low[v].min(i),
+                    Some(_) => {}
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if Some(low[v]) == index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Formula cells and the links between them
+pub(crate) struct FormulaGraph<'a> {
+    /// (sheet index, row, col, formula), in sheet and row order
+    pub cells: Vec<(usize, u32, u32, &'a str)>,
+    /// Referenced cells and ranges of each formula cell
+    pub precedents: Vec<Vec<String>>,
+    /// Volatile functions each formula cell calls
+    pub volatile: Vec<Vec<String>>,
+    /// edges[p] lists the cells that depend on cell p, sorted
+    pub edges: Vec<Vec<usize>>,
+}
+
+impl FormulaGraph<'_> {
+    /// Strongly connected components, precedents first
+    pub(crate) fn components(&self) -> Vec<Vec<usize>> {
+        let mut components = strongly_connected(&self.edges);
+        components.reverse();
+        components
+    }
+
+    /// Whether a component is a cycle rather than a lone acyclic cell
+    pub(crate) fn is_cycle(&self, component: &[usize]) -> bool {
+        component.len() > 1 || self.edges[component[0]].contains(&component[0])
+    }
+
+    /// Cells that depend on each other, grouped and in sheet order
+    pub(crate) fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut cycles: Vec<Vec<usize>> = strongly_connected(&self.edges)
+            .into_iter()
+            .filter(|component| self.is_cycle(component))
+            .collect();
+        cycles.sort_unstable();
+        cycles
+    }
+}
+
+/// Link every formula cell to the formula cells it references
+pub(crate) fn formula_graph<'a>(
+    sheets: &'a [ParsedSheet],
+    defined_names: &[ParsedDefinedName],
+    tables: &[ParsedTable],
+) -> Result<FormulaGraph<'a>, String> {
+    let mut cells = Vec::new();
+    for (s, sheet) in sheets.iter().enumerate() {
+        for row in &sheet.worksheet.rows {
+            for cell in &row.cells {
+                if let (Some(formula), Some((r, c))) =
+                    (&cell.formula, parse_cell_ref(&cell.reference))
+                {
+                    cells.push((s, r, c, formula.as_str()));
+                }
+            }
+        }
+    }
+    cells.sort_by_key(|&(s, r, c, _)| (s, r, c));
+
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); cells.len()];
+    let mut precedents = Vec::with_capacity(cells.len());
+    let mut volatile = Vec::with_capacity(cells.len());
+    for (i, &(s, r, c, formula)) in cells.iter().enumerate() {
+        let mut collector = Collector {
+            sheets,
+            defined_names,
+            tables,
+            cell: (r, c),
+            precedents: Vec::new(),
+            areas: Vec::new(),
+            volatile: Vec::new(),
+            names: HashSet::new(),
+        };
+        collector
+            .add_formula(formula, s)
+            .map_err(|e| format!("{}: {}", cell_key(sheets, s, r, c), e))?;
+
+        for area in &collector.areas {
+            if let Some(sheet) = area.sheet {
+                for p in cells_in(&cells, sheet, area.bounds) {
+                    edges[p].push(i);
+                }
+            }
+        }
+        precedents.push(collector.precedents);
+        volatile.push(collector.volatile);
+    }
+    for dependents in &mut edges {
+        dependents.sort_unstable();
+        dependents.dedup();
+    }
+
+    Ok(FormulaGraph {
+        cells,
+        precedents,
+        volatile,
+        edges,
+    })
+}
+
+/// Positions of the formula cells within `bounds` on `sheet`
+///
+/// `cells` is sorted by sheet, row and column, so one cell is a binary
+/// search and a range is the run of its rows, filtered by column.
+fn cells_in<'a>(
+    cells: &'a [(usize, u32, u32, &str)],
+    sheet: usize,
+    bounds: Bounds,
+) -> impl Iterator<Item = usize> + 'a {
+    let (r1, c1, r2, c2) = bounds;
+    let run = if (r1, c1) == (r2, c2) {
+        let found = cells.binary_search_by_key(&(sheet, r1, c1), |&(s, r, c, _)| (s, r, c));
+        found.map_or(0..0, |p| p..p + 1)
+    } else {
+        let start = cells.partition_point(|&(s, r, ..)| (s, r) < (sheet, r1));
+        let end = cells.partition_point(|&(s, r, ..)| (s, r) <= (sheet, r2));
+        start..end
+    };
+    run.filter(move |&p| contains_cell(bounds, cells[p].1, cells[p].2))
+}
+
+/// Sheet-qualified A1 name of a cell, e.g. `'My Sheet'!B2`
+pub(crate) fn cell_key(sheets: &[ParsedSheet], sheet: usize, row: u32, col: u32) -> String {
+    format!(
+        "{}{}",
+        format_sheet(&sheets[sheet].name, None),
+        cell_ref(row, col)
+    )
+}
+
+pub(crate) fn build_dependency_graph_impl(
+    workbook: &GraphWorkbook,
+) -> Result<DependencyGraph, String> {
+    let graph = formula_graph(&workbook.sheets, &workbook.defined_names, &workbook.tables)?;
+    let keys: Vec<String> = graph
+        .cells
+        .iter()
+        .map(|&(s, r, c, _)| cell_key(&workbook.sheets, s, r, c))
+        .collect();
+
+    let order = graph
+        .components()
+        .into_iter()
+        .flatten()
+        .map(|i| keys[i].clone())
+        .collect();
+    let cycles = graph
+        .cycles()
+        .into_iter()
+        .map(|cycle| cycle.into_iter().map(|i| keys[i].clone()).collect())
+        .collect();
+
+    let nodes = graph
+        .cells
+        .iter()
+        .zip(graph.precedents)
+        .zip(&graph.edges)
+        .enumerate()
+        .map(
+            |(i, ((&(_, _, _, formula), precedents), dependents))| DependencyNode {
+                cell: keys[i].clone(),
+                formula: formula.to_string(),
+                precedents,
+                dependents: dependents.iter().map(|&d| keys[d].clone()).collect(),
+            },
+        )
+        .collect();
+
+    Ok(DependencyGraph {
+        nodes,
+        order,
+        cycles,
+    })
+}
+
+pub(crate) fn analyze_formulas_impl(workbook: &GraphWorkbook) -> Result<FormulaAnalysis, String> {
+    let graph = formula_graph(&workbook.sheets, &workbook.defined_names, &workbook.tables)?;
+    let key = |i: usize| {
+        let (s, r, c, _) = graph.cells[i];
+        cell_key(&workbook.sheets, s, r, c)
+    };
+
+    // Walk the dependents of every volatile cell
+    let mut reached = vec![false; graph.cells.len()];
+    let mut pending: Vec<usize> = (0..graph.cells.len())
+        .filter(|&i| !graph.volatile[i].is_empty())
+        .collect();
+    while let Some(i) = pending.pop() {
+        for &d in &graph.edges[i] {
+            if !reached[d] {
+                reached[d] = true;
+                pending.push(d);
+            }
+        }
+    }
+
+    Ok(FormulaAnalysis {
+        volatile: graph
+            .volatile
+            .iter()
+            .enumerate()
+            .filter(|(_, functions)| !functions.is_empty())
+            .map(|(i, functions)| VolatileCell {
+                cell: key(i),
+                functions: functions.clone(),
+            })
+            .collect(),
+        volatile_dependents: (0..graph.cells.len())
+            .filter(|&i| reached[i] && graph.volatile[i].is_empty())
+            .map(key)
+            .collect(),
+        cycles: graph
+            .cycles()
+            .into_iter()
+            .map(|cycle| cycle.into_iter().map(key).collect())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParsedCell, ParsedRow, ParsedWorksheet};
+
+    fn sheet(name: &str, cells: &[(&str, &str)]) -> ParsedSheet {
+        let cells = cells
+            .iter()
+            .map(|(reference, formula)| ParsedCell {
+                reference: reference.to_string(),
+                cell_type: None,
+                style_index: None,
+                value: None,
+                formula: (!formula.is_empty()).then(|| formula.to_string()),
+            })
+            .collect();
+        ParsedSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet {
+                rows: vec![ParsedRow {
+                    row_num: 1,
+                    cells,
+                    height: None,
+                    hidden: false,
+                }],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_order() {
+        let workbook = GraphWorkbook {
+            sheets: vec![
+                sheet("Sheet1", &[("A1", ""), ("B1", "C1+Total"), ("C1", "A1*2")]),
+                sheet("My Data", &[("A1", "SUM(Sheet1!B:C)")]),
+            ],
+            defined_names: vec![ParsedDefinedName {
+                name: "Total".to_string(),
+                value: "Sheet1!$A$1".to_string(),
+                local_sheet_id: None,
+                hidden: false,
+            }],
+            tables: Vec::new(),
+        };
+        let graph = build_dependency_graph_impl(&workbook).unwrap();
+        assert_eq!(graph.order, ["Sheet1!C1", "Sheet1!B1", "'My Data'!A1"]);
+        assert!(graph.cycles.is_empty());
+
+        let b1 = &graph.nodes[0];
+        assert_eq!(b1.cell, "Sheet1!B1");
+        assert_eq!(b1.precedents, ["Sheet1!C1", "Sheet1!A1"]);
+        assert_eq!(b1.dependents, ["'My Data'!A1"]);
+        assert_eq!(graph.nodes[2].precedents, ["Sheet1!B:C"]);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let workbook = GraphWorkbook {
+            sheets: vec![sheet(
+                "Sheet1",
+                &[("A1", "B1+1"), ("B1", "A1"), ("C1", "C1"), ("D1", "A1")],
+            )],
+            defined_names: Vec::new(),
+            tables: Vec::new(),
+        };
+        let graph = build_dependency_graph_impl(&workbook).unwrap();
+        assert_eq!(
+            graph.cycles,
+            [vec!["Sheet1!A1", "Sheet1!B1"], vec!["Sheet1!C1"]]
+        );
+        let analysis = analyze_formulas_impl(&workbook).unwrap();
+        assert_eq!(analysis.cycles, graph.cycles);
+
+        let d1 = graph.order.iter().position(|c| c == "Sheet1!D1").unwrap();
+        let a1 = graph.order.iter().position(|c| c == "Sheet1!A1").unwrap();
+        assert!(a1 < d1);
+    }
+
+    #[test]
+    fn test_volatile() {
+        let workbook = GraphWorkbook {
+            sheets: vec![sheet(
+                "Sheet1",
+                &[
+                    ("A1", "NOW()"),
+                    ("B1", "A1+1"),
+                    ("C1", "B1*2"),
+                    ("D1", "_xlfn.RANDARRAY(2)+Noise"),
+                    ("E1", "1+1"),
+                ],
+            )],
+            defined_names: vec![ParsedDefinedName {
+                name: "Noise".to_string(),
+                value: "RAND()".to_string(),
+                local_sheet_id: None,
+                hidden: false,
+            }],
+            tables: Vec::new(),
+        };
+        let analysis = analyze_formulas_impl(&workbook).unwrap();
+        let volatile: Vec<(&str, Vec<String>)> = analysis
+            .volatile
+            .iter()
+            .map(|v| (v.cell.as_str(), v.functions.clone()))
+            .collect();
+        assert_eq!(
+            volatile,
+            [
+                ("Sheet1!A1", vec!["NOW".to_string()]),
+                (
+                    "Sheet1!D1",
+                    vec!["RANDARRAY".to_string(), "RAND".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(analysis.volatile_dependents, ["Sheet1!B1", "Sheet1!C1"]);
+        assert!(analysis.cycles.is_empty());
+    }
+
+    #[test]
+    fn test_areas() {
+        // A chain down column A, with ranges across rows and columns
+        let mut chain = sheet("Sheet1", &[("B1", "SUM(A2:A4)"), ("C1", "SUM(A3:B5)")]);
+        let rows = (2..=20_000).map(|row_num| ParsedRow {
+            row_num,
+            cells: vec![ParsedCell {
+                reference: format!("A{}", row_num),
+                cell_type: None,
+                style_index: None,
+                value: None,
+                formula: Some(format!("A{}+1", row_num - 1)),
+            }],
+            height: None,
+            hidden: false,
+        });
+        chain.worksheet.rows.extend(rows);
+        let workbook = GraphWorkbook {
+            sheets: vec![chain],
+            defined_names: Vec::new(),
+            tables: Vec::new(),
+        };
+        let graph = build_dependency_graph_impl(&workbook).unwrap();
+        let node = |cell: &str| graph.nodes.iter().find(|n| n.cell == cell).unwrap();
+        assert_eq!(
+            node("Sheet1!A3").dependents,
+            ["Sheet1!B1", "Sheet1!C1", "Sheet1!A4"]
+        );
+        assert_eq!(node("Sheet1!A5").dependents, ["Sheet1!C1", "Sheet1!A6"]);
+        assert_eq!(node("Sheet1!A19999").dependents, ["Sheet1!A20000"]);
+        assert!(node("Sheet1!B1").dependents.is_empty());
+        let position = |cell: &str| graph.order.iter().position(|c| c == cell).unwrap();
+        assert!(position("Sheet1!A5") < position("Sheet1!C1"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Gzip-compressed XML part input
+//!
+//! Some pipelines store parts already extracted from the package but
+//! gzipped individually. The `*_bytes` entry points accept a part either
+//! as plain bytes or gzipped, detected from the magic number, and inflate
+//! it in wasm before parsing.
+
+use crate::context::ParserScratch;
+use crate::encoding::{decode_xml, xml_to_string};
+use crate::limits::{LimitExceeded, ParseLimits, ReadError};
+use crate::memory;
+use crate::profile::phase;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::zip::crc32;
+use crate::{parse_shared_strings_with, parse_worksheet_with, WorksheetParseOptions};
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, TINFLStatus};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const GZIP_MAGIC: [u8; 3] = [0x1F, 0x8B, 0x08];
+
+const FLAG_HCRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+/// Whether `data` starts with a gzip (DEFLATE) header
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// Inflate a gzip member, checking its CRC and length trailer
+pub(crate) fn gunzip(data: &[u8], limits: &ParseLimits) -> Result<Vec<u8>, ReadError> {
+    let truncated = || ReadError::from("Truncated gzip data");
+    let flags = *data.get(3).ok_or_else(truncated)?;
+    let mut pos = 10;
+    if flags & FLAG_EXTRA != 0 {
+        let len = data.get(pos..pos + 2).ok_or_else(truncated)?;
+        pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    for flag in [FLAG_NAME, FLAG_COMMENT] {
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0));
+            pos += end.ok_or_else(truncated)? + 1;
+        }
+    }
+    if flags & FLAG_HCRC != 0 {
+        pos += 2;
+    }
+    if data.len() < pos + 8 {
+        return Err(truncated());
+    }
+
+    let max = limits.max_decompressed_size;
+    let cap = max.min(usize::MAX as u64) as usize;
+    let contents = decompress_to_vec_with_limit(&data[pos..data.len() - 8], cap).map_err(|e| {
+        match e.status {
+            TINFLStatus::HasMoreOutput => LimitExceeded::new("decompressed_size", max, None).into(),
+            status => ReadError::from(format!("Failed to inflate gzip data: {:?}", status)),
+        }
+    })?;
+
+    let trailer = &data[data.len() - 8..];
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc32(&contents) != crc || contents.len() as u32 != size {
+        return Err("Checksum mismatch in gzip data".into());
+    }
+    Ok(contents)
+}
+
+/// Text of an XML part given as plain or gzipped bytes
+pub(crate) fn part_text(data: &[u8]) -> Result<String, ReadError> {
+    if is_gzip(data) {
+        let inflated = phase("gunzip", || gunzip(data, &ParseLimits::default()))?;
+        Ok(xml_to_string(inflated)?)
+    } else {
+        Ok(decode_xml(data)?.into_owned())
+    }
+}
+
+/// Decode an XML part, inflating it first when it is gzipped
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn decode_xml_part(data: &[u8]) -> Result<String, JsValue> {
+    trap(|| Ok(part_text(data)?))
+}
+
+/// `parse_worksheet_with_options` for a part given as plain or gzipped bytes
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_bytes(data: &[u8], options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let options: Option<WorksheetParseOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+        let xml = part_text(data)?;
+        let guard = memory::guard();
+        let result = phase("worksheet", || {
+            parse_worksheet_with(&xml, &mut ParserScratch::default(), &options)
+        });
+        guard.check().map_err(|e| JsValue::from_str(&e))?;
+        options.check(&result)?;
+        Ok(phase("serialization", || {
+            serde_wasm_bindgen::to_value(&result)
+        })?)
+    })
+}
+
+/// `parse_shared_strings` for a part given as plain or gzipped bytes
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_shared_strings_bytes(data: &[u8]) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let xml = part_text(data)?;
+        let result = phase("sharedStrings", || {
+            parse_shared_strings_with(&xml, &mut ParserScratch::default())
+        });
+        Ok(phase("serialization", || {
+            serde_wasm_bindgen::to_value(&result)
+        })?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec;
+
+    fn gzip(data: &[u8], name: Option<&str>) -> Vec<u8> {
+        let flags = if name.is_some() { FLAG_NAME } else { 0 };
+        let mut out = vec![0x1F, 0x8B, 0x08, flags, 0, 0, 0, 0, 0, 0xFF];
+        if let Some(name) = name {
+            out.extend_from_slice(name.as_bytes());
+            out.push(0);
+        }
+        out.extend_from_slice(&compress_to_vec(data, 6));
+        out.extend_from_slice(&crc32(data).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn test_gzip_part() {
+        let xml = r#"<sst><si><t>hello</t></si></sst>"#;
+        let gzipped = gzip(xml.as_bytes(), Some("sharedStrings.xml"));
+        assert!(is_gzip(&gzipped));
+        assert_eq!(part_text(&gzipped).unwrap(), xml);
+        assert_eq!(part_text(xml.as_bytes()).unwrap(), xml);
+
+        let mut corrupt = gzip(xml.as_bytes(), None);
+        let len = corrupt.len();
+        corrupt[len - 8] ^= 1;
+        assert!(part_text(&corrupt).is_err());
+
+        let limits = ParseLimits {
+            max_decompressed_size: 10,
+            ..Default::default()
+        };
+        assert!(matches!(
+            gunzip(&gzipped, &limits),
+            Err(ReadError::Limit(_))
+        ));
+    }
+}
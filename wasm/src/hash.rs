@@ -0,0 +1,331 @@
+//! Content hashes for change detection
+//!
+//! A SHA-256 over a canonical encoding of what a sheet holds: cell values
+//! (shared strings resolved, so the string table's order doesn't matter),
+//! formulas, merges, hyperlinks and comments, in row and column order.
+//! Formula cells are hashed by their formula rather than their cached
+//! result, which volatile functions like NOW() change on every save, and
+//! formatting only counts when asked for, since style indexes shift
+//! whenever the style table is rewritten.
+
+use crate::crypto::HashAlgorithm;
+use crate::reference::{bounds_ref, parse_cell_ref, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{resolve_cell_value, CellValue};
+use crate::{ParsedSheet, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Parsed sheets hashed by [`hash_workbook`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HashWorkbook {
+    pub sheets: Vec<ParsedSheet>,
+    #[serde(default)]
+    pub shared_strings: Vec<String>,
+}
+
+/// Options for [`hash_worksheet`] and [`hash_workbook`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HashOptions {
+    /// Also hash cell style indexes, row heights, hidden rows and column
+    /// widths
+    pub include_formatting: bool,
+    /// Shared strings for [`hash_worksheet`]; a workbook carries its own
+    pub shared_strings: Vec<String>,
+}
+
+/// A workbook's content hash, with each sheet's
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WorkbookHash {
+    /// Hex SHA-256 over the sheets' names, visibility and hashes, in order
+    pub hash: String,
+    pub sheets: Vec<SheetHash>,
+}
+
+/// One sheet's content hash
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SheetHash {
+    pub name: String,
+    /// Hex SHA-256, as [`hash_worksheet`] gives it
+    pub hash: String,
+}
+
+/// Hex content hash of a parsed worksheet
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn hash_worksheet(worksheet: JsValue, options: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<HashOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+        Ok(hash_worksheet_impl(
+            &worksheet,
+            &options.shared_strings,
+            &options,
+        ))
+    })
+}
+
+/// Content hashes of a workbook and of each of its sheets
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn hash_workbook(workbook: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let workbook: HashWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        let options: Option<HashOptions> = serde_wasm_bindgen::from_value(options)?;
+        let hash = hash_workbook_impl(&workbook, &options.unwrap_or_default());
+        Ok(serde_wasm_bindgen::to_value(&hash)?)
+    })
+}
+
+pub(crate) fn hash_workbook_impl(workbook: &HashWorkbook, options: &HashOptions) -> WorkbookHash {
+    let sheets: Vec<SheetHash> = workbook
+        .sheets
+        .iter()
+        .map(|sheet| SheetHash {
+            name: sheet.name.clone(),
+            hash: hash_worksheet_impl(&sheet.worksheet, &workbook.shared_strings, options),
+        })
+        .collect();
+    let mut out = Canonical::default();
+    for (sheet, hash) in workbook.sheets.iter().zip(&sheets) {
+        out.text(&sheet.name);
+        out.text(sheet.state.as_deref().unwrap_or("visible"));
+        out.text(&hash.hash);
+    }
+    WorkbookHash {
+        hash: out.finish(),
+        sheets,
+    }
+}
+
+pub(crate) fn hash_worksheet_impl(
+    worksheet: &ParsedWorksheet,
+    shared_strings: &[String],
+    options: &HashOptions,
+) -> String {
+    let mut out = Canonical::default();
+
+    let mut cells: Vec<_> = worksheet
+        .rows
+        .iter()
+        .flat_map(|row| &row.cells)
+        .filter_map(|cell| Some((parse_cell_ref(&cell.reference)?, cell)))
+        .collect();
+    cells.sort_by_key(|&(position, _)| position);
+    for ((row, col), cell) in cells {
+        let value = match &cell.formula {
+            Some(_) => CellValue::Empty,
+            None => resolve_cell_value(cell, shared_strings),
+        };
+        let style = cell.style_index.filter(|_| options.include_formatting);
+        let blank = matches!(&value, CellValue::Empty)
+            || matches!(&value, CellValue::Text(text) if text.is_empty());
+        if blank && cell.formula.is_none() && style.is_none() {
+            continue;
+        }
+        out.tag(b'c');
+        out.number(row);
+        out.number(col);
+        match value {
+            _ if blank => out.tag(b'_'),
+            CellValue::Number(n) => {
+                out.tag(b'n');
+                // 0 and -0 are the same number
+                out.0.extend((n + 0.0).to_bits().to_le_bytes());
+            }
+            CellValue::Text(text) => {
+                out.tag(b's');
+                out.text(&text);
+            }
+            CellValue::Bool(b) => out.tag(if b { b'T' } else { b'F' }),
+            CellValue::Error(code) => {
+                out.tag(b'e');
+                out.text(&code);
+            }
+            CellValue::Empty => out.tag(b'_'),
+        }
+        if let Some(formula) = &cell.formula {
+            out.tag(b'f');
+            out.text(formula);
+        }
+        if let Some(style) = style {
+            out.tag(b'y');
+            out.number(style);
+        }
+    }
+
+    let normalize = |range: &str| range_bounds(range).map_or(range.to_string(), bounds_ref);
+    let mut merges: Vec<String> = worksheet.merge_cells.iter().map(|m| normalize(m)).collect();
+    merges.sort();
+    for merge in merges {
+        out.tag(b'm');
+        out.text(&merge);
+    }
+    let mut links: Vec<_> = worksheet
+        .hyperlinks
+        .iter()
+        .map(|link| {
+            // Relationship ids are renumbered on save; the target they name
+            // is what counts
+            let target = link.target.as_deref();
+            (normalize(&link.reference), target, link.location.as_deref())
+        })
+        .collect();
+    links.sort();
+    for (reference, target, location) in links {
+        out.tag(b'h');
+        out.text(&reference);
+        out.text(target.unwrap_or(""));
+        out.text(location.unwrap_or(""));
+    }
+    #[cfg(feature = "comments")]
+    {
+        let mut comments: Vec<_> = worksheet
+            .comments
+            .iter()
+            .map(|comment| (normalize(&comment.reference), comment))
+            .collect();
+        comments.sort_by(|a, b| a.0.cmp(&b.0));
+        for (reference, comment) in comments {
+            out.tag(b'k');
+            out.text(&reference);
+            out.text(&comment.text);
+            out.number(comment.replies.len() as u32);
+            for reply in &comment.replies {
+                out.text(&reply.text);
+            }
+        }
+    }
+
+    if options.include_formatting {
+        let mut rows: Vec<_> = worksheet
+            .rows
+            .iter()
+            .filter(|row| row.height.is_some() || row.hidden)
+            .collect();
+        rows.sort_by_key(|row| row.row_num);
+        for row in rows {
+            out.tag(b'r');
+            out.number(row.row_num);
+            out.0
+                .extend(row.height.unwrap_or(-1.0).to_bits().to_le_bytes());
+            out.tag(u8::from(row.hidden));
+        }
+        let mut widths: Vec<_> = worksheet.col_widths.iter().collect();
+        widths.sort_by_key(|&(&col, _)| col);
+        for (&col, width) in widths {
+            out.tag(b'w');
+            out.number(col);
+            out.0.extend(width.to_bits().to_le_bytes());
+        }
+    }
+
+    out.finish()
+}
+
+/// Bytes hashed for a sheet; strings are length-prefixed so no two
+/// contents encode the same
+#[derive(Default)]
+struct Canonical(Vec<u8>);
+
+impl Canonical {
+    fn tag(&mut self, tag: u8) {
+        self.0.push(tag);
+    }
+
+    fn number(&mut self, n: u32) {
+        self.0.extend(n.to_le_bytes());
+    }
+
+    fn text(&mut self, text: &str) {
+        self.number(text.len() as u32);
+        self.0.extend(text.as_bytes());
+    }
+
+    /// Hex SHA-256 of the bytes
+    fn finish(self) -> String {
+        HashAlgorithm::Sha256
+            .digest(&[&self.0])
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+
+    #[test]
+    fn test_hash_worksheet() {
+        let mut total = cell("B2", None, "46000.5");
+        total.formula = Some("NOW()".to_string());
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                row(1, vec![cell("A1", Some("s"), "0"), cell("B1", None, "-0")]),
+                row(2, vec![cell("A2", Some("s"), "1"), total]),
+            ],
+            merge_cells: vec!["c1:d1".to_string()],
+            ..Default::default()
+        };
+        let options = HashOptions::default();
+        let hash = hash_worksheet_impl(&worksheet, &["Name", "Ann"].map(String::from), &options);
+        assert_eq!(hash.len(), 64);
+
+        // Another string table order, row order, cached result, style and
+        // merge spelling give the same content
+        let mut resaved = ParsedWorksheet {
+            rows: vec![
+                row(
+                    2,
+                    vec![
+                        cell("A2", Some("s"), "0"),
+                        worksheet.rows[1].cells[1].clone(),
+                    ],
+                ),
+                row(1, vec![cell("A1", Some("s"), "1"), cell("B1", None, "0")]),
+            ],
+            merge_cells: vec!["C1:D1".to_string()],
+            ..Default::default()
+        };
+        resaved.rows[0].cells[1].value = Some("46001.25".to_string());
+        resaved.rows[1].cells[0].style_index = Some(3);
+        resaved.rows[1].cells.push(cell("C1", Some("str"), ""));
+        let strings = ["Ann", "Name"].map(String::from);
+        assert_eq!(hash_worksheet_impl(&resaved, &strings, &options), hash);
+
+        let formatting = HashOptions {
+            include_formatting: true,
+            ..Default::default()
+        };
+        assert_ne!(hash_worksheet_impl(&resaved, &strings, &formatting), hash);
+        resaved.rows[1].cells[1].value = Some("1".to_string());
+        assert_ne!(hash_worksheet_impl(&resaved, &strings, &options), hash);
+
+        let workbook = HashWorkbook {
+            sheets: vec![ParsedSheet {
+                name: "Data".to_string(),
+                state: None,
+                worksheet,
+            }],
+            shared_strings: ["Name", "Ann"].map(String::from).to_vec(),
+        };
+        let workbook_hash = hash_workbook_impl(&workbook, &options);
+        assert_eq!(workbook_hash.sheets[0].hash, hash);
+        let renamed = HashWorkbook {
+            sheets: vec![ParsedSheet {
+                name: "Data 2".to_string(),
+                ..workbook.sheets.into_iter().next().unwrap()
+            }],
+            ..workbook
+        };
+        let renamed_hash = hash_workbook_impl(&renamed, &options);
+        assert_eq!(renamed_hash.sheets[0].hash, hash);
+        assert_ne!(renamed_hash.hash, workbook_hash.hash);
+    }
+}
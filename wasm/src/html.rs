@@ -0,0 +1,488 @@
+//! HTML table export for clipboard copy and previews
+//!
+//! Cell formatting is emitted as inline `style` attributes because that is
+//! what spreadsheet apps and rich-text editors keep when pasting.
+
+use crate::range::intersect;
+use crate::reference::{parse_cell_ref, range_bounds, used_range, Bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{
+    date_style_flags, format_datetime, format_number, resolve_cell_value, serial_to_datetime,
+    CellValue,
+};
+use crate::writer::{push_attr, push_escaped};
+use crate::{ParsedStyles, ParsedWorksheet};
+use std::collections::HashMap;
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Export a parsed worksheet as an HTML `<table>`
+///
+/// `styles` and `shared_strings` are the results of `parse_styles` and
+/// `parse_shared_strings`; either may be undefined. Set `date1904` for
+/// workbooks that count dates from 1904.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn worksheet_to_html(
+    worksheet: JsValue,
+    styles: JsValue,
+    shared_strings: JsValue,
+    date1904: Option<bool>,
+) -> Result<String, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let styles: Option<ParsedStyles> = serde_wasm_bindgen::from_value(styles)?;
+        let shared_strings: Option<Vec<String>> = serde_wasm_bindgen::from_value(shared_strings)?;
+        Ok(worksheet_to_html_impl(
+            &worksheet,
+            styles.as_ref(),
+            shared_strings.as_deref().unwrap_or_default(),
+            date1904.unwrap_or(false),
+        ))
+    })
+}
+
+/// Convert an ARGB (or RGB) hex color to CSS `#rrggbb`
+fn css_color(argb: &str) -> Option<String> {
+    let hex = if argb.len() == 8 { &argb[2..] } else { argb };
+    if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(format!("#{}", hex.to_ascii_lowercase()))
+    } else {
+        None
+    }
+}
+
+/// Map an Excel border style to a CSS `border` shorthand without the color
+fn css_border(style: &str) -> Option<&'static str> {
+    Some(match style {
+        "thin" => "1px solid",
+        "medium" => "2px solid",
+        "thick" => "3px solid",
+        "double" => "3px double",
+        "hair" | "dotted" => "1px dotted",
+        "dashed" | "dashDot" | "dashDotDot" => "1px dashed",
+        "mediumDashed" | "mediumDashDot" | "mediumDashDotDot" | "slantDashDot" => "2px dashed",
+        _ => return None,
+    })
+}
+
+/// Build the inline CSS for a cellXfs entry
+fn xf_css(styles: &ParsedStyles, xf_index: usize) -> String {
+    let mut css = String::new();
+    let xf = match styles.cell_xfs.get(xf_index) {
+        Some(xf) => xf,
+        None => return css,
+    };
+
+    if let Some(font) = xf.font_id.and_then(|id| styles.fonts.get(id as usize)) {
+        if font.bold {
+            css.push_str("font-weight:bold;");
+        }
+        if font.italic {
+            css.push_str("font-style:italic;");
+        }
+        match (font.underline, font.strikethrough) {
+            (true, true) => css.push_str("text-decoration:underline line-through;"),
+            (true, false) => css.push_str("text-decoration:underline;"),
+            (false, true) => css.push_str("text-decoration:line-through;"),
+            (false, false) => {}
+        }
+        if let Some(size) = font.size {
+            let _ = write!(css, "font-size:{}pt;", size);
+        }
+        if let Some(color) = font.color.as_deref().and_then(css_color) {
+            let _ = write!(css, "color:{};", color);
+        }
+        if let Some(ref name) = font.name {
+            let _ = write!(css, "font-family:'{}';", name.replace('\'', ""));
+        }
+    }
+
+    if let Some(fill) = xf.fill_id.and_then(|id| styles.fills.get(id as usize)) {
+        if fill.pattern_type.as_deref() == Some("solid") {
+            if let Some(color) = fill.fg_color.as_deref().and_then(css_color) {
+                let _ = write!(css, "background-color:{};", color);
+            }
+        }
+    }
+
+    if let Some(border) = xf.border_id.and_then(|id| styles.borders.get(id as usize)) {
+        let sides = [
+            ("left", &border.left_style, &border.left_color),
+            ("right", &border.right_style, &border.right_color),
+            ("top", &border.top_style, &border.top_color),
+            ("bottom", &border.bottom_style, &border.bottom_color),
+        ];
+        for (side, style, color) in sides {
+            if let Some(line) = style.as_deref().and_then(css_border) {
+                let color = color
+                    .as_deref()
+                    .and_then(css_color)
+                    .unwrap_or_else(|| "#000000".to_string());
+                let _ = write!(css, "border-{}:{} {};", side, line, color);
+            }
+        }
+    }
+
+    match xf.horizontal.as_deref() {
+        Some("left") => css.push_str("text-align:left;"),
+        Some("center") | Some("centerContinuous") => css.push_str("text-align:center;"),
+        Some("right") => css.push_str("text-align:right;"),
+        Some("justify") | Some("distributed") => css.push_str("text-align:justify;"),
+        _ => {}
+    }
+    match xf.vertical.as_deref() {
+        Some("top") => css.push_str("vertical-align:top;"),
+        Some("center") => css.push_str("vertical-align:middle;"),
+        Some("bottom") => css.push_str("vertical-align:bottom;"),
+        _ => {}
+    }
+    if xf.wrap_text {
+        css.push_str("white-space:pre-wrap;");
+    }
+    if let Some(indent) = xf.indent.filter(|&indent| indent > 0) {
+        let _ = write!(css, "padding-left:{}em;", indent);
+    }
+
+    css
+}
+
+/// A merged range as drawn: its visible part and the cell holding its value
+struct Merge {
+    bounds: Bounds,
+    /// Visible rows spanned
+    rows: u32,
+    origin: (u32, u32),
+}
+
+/// Approximate pixel width of an Excel column width (in characters)
+fn column_px(width: f64) -> u32 {
+    (width * 7.0 + 5.0).round().max(0.0) as u32
+}
+
+pub(crate) fn worksheet_to_html_impl(
+    worksheet: &ParsedWorksheet,
+    styles: Option<&ParsedStyles>,
+    shared_strings: &[String],
+    date1904: bool,
+) -> String {
+    let mut out = String::from("<table style=\"border-collapse:collapse\">");
+
+    let (start_row, start_col, end_row, end_col) = match used_range(worksheet) {
+        Some(bounds) => bounds,
+        None => {
+            out.push_str("</table>");
+            return out;
+        }
+    };
+    let rows: HashMap<u32, _> = worksheet
+        .rows
+        .iter()
+        .map(|row| (row.row_num, row))
+        .collect();
+    let mut hidden: Vec<u32> = worksheet
+        .rows
+        .iter()
+        .filter(|row| row.hidden)
+        .map(|row| row.row_num)
+        .collect();
+    hidden.sort_unstable();
+    hidden.dedup();
+    let hidden_before = |row: u32| hidden.partition_point(|&r| r < row) as u32;
+
+    // Merges are clipped to the used range and drawn from their first
+    // visible row, spanning only the visible rows; sorted by that row so each
+    // row only checks the merges that have started
+    let mut merges: Vec<Merge> = worksheet
+        .merge_cells
+        .iter()
+        .filter_map(|merge| range_bounds(merge))
+        .filter_map(|merge| {
+            let (r1, c1, r2, c2) = intersect(merge, (start_row, start_col, end_row, end_col))?;
+            let top = (r1..=r2).find(|row| hidden.binary_search(row).is_err())?;
+            Some(Merge {
+                bounds: (top, c1, r2, c2),
+                rows: r2 + 1 - top - (hidden_before(r2 + 1) - hidden_before(top)),
+                origin: (merge.0, merge.1),
+            })
+        })
+        .collect();
+    merges.sort_by_key(|merge| merge.bounds.0);
+
+    if !worksheet.col_widths.is_empty() {
+        out.push_str("<colgroup>");
+        for col in start_col..=end_col {
+            match worksheet.col_widths.get(&col) {
+                Some(&width) => {
+                    let _ = write!(out, "<col style=\"width:{}px\">", column_px(width));
+                }
+                None => out.push_str("<col>"),
+            }
+        }
+        out.push_str("</colgroup>");
+    }
+
+    let date_styles = styles.map(date_style_flags).unwrap_or_default();
+    let mut style_cache: HashMap<u32, String> = HashMap::new();
+
+    out.push_str("<tbody>");
+    for row_num in start_row..=end_row {
+        let row = rows.get(&row_num);
+        if row.is_some_and(|row| row.hidden) {
+            continue;
+        }
+
+        match row.and_then(|row| row.height) {
+            Some(height) => {
+                let _ = write!(out, "<tr style=\"height:{}pt\">", height);
+            }
+            None => out.push_str("<tr>"),
+        }
+
+        let started = merges.partition_point(|merge| merge.bounds.0 <= row_num);
+        let active: Vec<_> = merges[..started]
+            .iter()
+            .filter(|merge| merge.bounds.2 >= row_num)
+            .collect();
+
+        let cells: HashMap<u32, _> = row
+            .map(|row| {
+                row.cells
+                    .iter()
+                    .filter_map(|cell| parse_cell_ref(&cell.reference).map(|(_, c)| (c, cell)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for col in start_col..=end_col {
+            let merge = active
+                .iter()
+                .find(|merge| (merge.bounds.1..=merge.bounds.3).contains(&col));
+            // Covered cells are drawn by the merge's first cell
+            if merge.is_some_and(|merge| (merge.bounds.0, merge.bounds.1) != (row_num, col)) {
+                continue;
+            }
+
+            out.push_str("<td");
+            if let Some(merge) = merge {
+                let (_, c1, _, c2) = merge.bounds;
+                if c2 > c1 {
+                    let _ = write!(out, " colspan=\"{}\"", c2 - c1 + 1);
+                }
+                if merge.rows > 1 {
+                    let _ = write!(out, " rowspan=\"{}\"", merge.rows);
+                }
+            }
+
+            // A merge shows the value of its top-left cell, whose row may be hidden
+            let (source_row, source_col) = merge.map_or((row_num, col), |merge| merge.origin);
+            let cell = if source_row == row_num {
+                cells.get(&source_col).copied()
+            } else {
+                rows.get(&source_row).and_then(|row| {
+                    row.cells.iter().find(|cell| {
+                        parse_cell_ref(&cell.reference).is_some_and(|(_, c)| c == source_col)
+                    })
+                })
+            };
+            let cell = match cell {
+                Some(cell) => cell,
+                None => {
+                    out.push_str("></td>");
+                    continue;
+                }
+            };
+
+            let value = resolve_cell_value(cell, shared_strings);
+            let mut css = match (styles, cell.style_index) {
+                (Some(styles), Some(index)) => style_cache
+                    .entry(index)
+                    .or_insert_with(|| xf_css(styles, index as usize))
+                    .clone(),
+                _ => String::new(),
+            };
+            // Excel right-aligns numbers unless told otherwise
+            if matches!(value, CellValue::Number(_)) && !css.contains("text-align") {
+                css.push_str("text-align:right;");
+            }
+            if !css.is_empty() {
+                push_attr(&mut out, "style", &css);
+            }
+            out.push('>');
+
+            let text = match value {
+                CellValue::Empty => String::new(),
+                CellValue::Text(text) | CellValue::Error(text) => text,
+                CellValue::Bool(b) => if b { "TRUE" } else { "FALSE" }.to_string(),
+                CellValue::Number(number) => {
+                    let is_date = cell
+                        .style_index
+                        .and_then(|s| date_styles.get(s as usize).copied())
+                        .unwrap_or(false);
+                    match serial_to_datetime(number, date1904) {
+                        Some(dt) if is_date => format_datetime(&dt, "ISO"),
+                        _ => format_number(number),
+                    }
+                }
+            };
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    out.push_str("<br>");
+                }
+                push_escaped(&mut out, line);
+            }
+            out.push_str("</td>");
+        }
+        out.push_str("</tr>");
+    }
+    out.push_str("</tbody></table>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+    use crate::{ParsedFill, ParsedFont, ParsedRow, ParsedStyle};
+
+    #[test]
+    fn test_worksheet_to_html() {
+        let mut header = cell("A1", Some("s"), "0");
+        header.style_index = Some(1);
+
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                ParsedRow {
+                    row_num: 1,
+                    cells: vec![header],
+                    height: Some(20.0),
+                    hidden: false,
+                },
+                ParsedRow {
+                    row_num: 2,
+                    cells: vec![
+                        cell("A2", None, "42"),
+                        cell("B2", Some("inlineStr"), "a < b\nnext"),
+                    ],
+                    height: None,
+                    hidden: false,
+                },
+            ],
+            merge_cells: vec!["A1:B1".to_string()],
+            ..Default::default()
+        };
+        let styles = ParsedStyles {
+            cell_xfs: vec![
+                ParsedStyle::default(),
+                ParsedStyle {
+                    font_id: Some(1),
+                    fill_id: Some(2),
+                    horizontal: Some("center".to_string()),
+                    ..Default::default()
+                },
+            ],
+            fonts: vec![
+                ParsedFont::default(),
+                ParsedFont {
+                    bold: true,
+                    color: Some("FFFF0000".to_string()),
+                    ..Default::default()
+                },
+            ],
+            fills: vec![
+                ParsedFill {
+                    pattern_type: Some("none".to_string()),
+                    fg_color: None,
+                    bg_color: None,
+                },
+                ParsedFill {
+                    pattern_type: Some("gray125".to_string()),
+                    fg_color: None,
+                    bg_color: None,
+                },
+                ParsedFill {
+                    pattern_type: Some("solid".to_string()),
+                    fg_color: Some("FFFFFF00".to_string()),
+                    bg_color: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let html = worksheet_to_html_impl(&worksheet, Some(&styles), &["Title".to_string()], false);
+        assert_eq!(
+            html,
+            "<table style=\"border-collapse:collapse\"><tbody>\
+             <tr style=\"height:20pt\"><td colspan=\"2\" style=\"font-weight:bold;color:#ff0000;\
+             background-color:#ffff00;text-align:center;\">Title</td></tr>\
+             <tr><td style=\"text-align:right;\">42</td><td>a &lt; b<br>next</td></tr>\
+             </tbody></table>"
+        );
+
+        // Serial 0 is 1 January 1904 in a 1904-based workbook
+        let mut date = cell("A1", None, "0");
+        date.style_index = Some(2);
+        let dates = ParsedWorksheet {
+            rows: vec![ParsedRow {
+                row_num: 1,
+                cells: vec![date],
+                height: None,
+                hidden: false,
+            }],
+            ..Default::default()
+        };
+        let mut styles = styles;
+        styles.cell_xfs.push(ParsedStyle {
+            num_fmt_id: Some(14),
+            ..Default::default()
+        });
+        let html = worksheet_to_html_impl(&dates, Some(&styles), &[], true);
+        assert!(html.contains(">1904-01-01</td>"), "{}", html);
+    }
+
+    #[test]
+    fn test_merges() {
+        let cells = |cells: &[(&str, &str)]| {
+            cells
+                .iter()
+                .map(|&(reference, value)| cell(reference, Some("str"), value))
+                .collect()
+        };
+        // A whole-sheet merge draws only the used range
+        let worksheet = ParsedWorksheet {
+            rows: vec![row(1, cells(&[("A1", "a")])), row(2, cells(&[("B2", "b")]))],
+            merge_cells: vec!["A1:XFD1048576".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            worksheet_to_html_impl(&worksheet, None, &[], false),
+            "<table style=\"border-collapse:collapse\"><tbody>\
+             <tr><td colspan=\"2\" rowspan=\"2\">a</td></tr><tr></tr></tbody></table>"
+        );
+
+        // A merge whose top row is hidden starts on its first visible row
+        // and spans only the visible ones
+        let mut worksheet = ParsedWorksheet {
+            rows: vec![
+                row(1, cells(&[("A1", "top"), ("C1", "1")])),
+                row(2, cells(&[("C2", "2")])),
+                row(3, cells(&[("C3", "3")])),
+                row(4, cells(&[("C4", "4")])),
+                row(5, cells(&[("A5", "5")])),
+            ],
+            merge_cells: vec!["A1:B4".to_string()],
+            ..Default::default()
+        };
+        worksheet.rows[0].hidden = true;
+        worksheet.rows[2].hidden = true;
+        assert_eq!(
+            worksheet_to_html_impl(&worksheet, None, &[], false),
+            "<table style=\"border-collapse:collapse\"><tbody>\
+             <tr><td colspan=\"2\" rowspan=\"2\">top</td><td>2</td></tr>\
+             <tr><td>4</td></tr>\
+             <tr><td>5</td><td></td><td></td></tr></tbody></table>"
+        );
+    }
+}
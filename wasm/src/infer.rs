@@ -0,0 +1,330 @@
+//! Column type inference for import mapping
+//!
+//! Classifies each column of a parsed worksheet by the values below its
+//! header, so an import screen can propose a type per field. Numbers in
+//! date-formatted cells count as dates, and text that reads as a number,
+//! an ISO date or true/false counts as that type, since imported sheets
+//! often hold those as text.
+
+use crate::json::header_names;
+use crate::reference::{parse_cell_ref, used_range};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{date_style_flags, parse_iso_date, resolve_cell_value, CellValue};
+use crate::{ParsedStyles, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Types a column can take besides "string" and "empty", most specific
+/// first; each one's values also fit the one after it where that widens it
+const TYPES: [&str; 5] = ["integer", "float", "date", "datetime", "bool"];
+
+/// Options for [`infer_column_types`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnTypeOptions {
+    /// Row holding the headers (1-based); defaults to the first used row
+    pub header_row: Option<u32>,
+    /// Treat every row as data and name columns by letter
+    pub no_header: bool,
+    /// Rows to read below the header; all of them when left out
+    pub sample_rows: Option<u32>,
+    /// Read numbers, ISO dates and true/false written as text as those types
+    pub parse_text: bool,
+    /// Share of a column's values a type must fit (0 to 1), below which
+    /// the column is a string
+    pub min_confidence: f64,
+    pub shared_strings: Vec<String>,
+    /// Styles used to detect date-formatted numbers
+    pub styles: Option<ParsedStyles>,
+}
+
+impl Default for ColumnTypeOptions {
+    fn default() -> Self {
+        ColumnTypeOptions {
+            header_row: None,
+            no_header: false,
+            sample_rows: None,
+            parse_text: true,
+            min_confidence: 0.9,
+            shared_strings: Vec::new(),
+            styles: None,
+        }
+    }
+}
+
+/// A column's inferred type
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ColumnType {
+    /// 1-based column
+    pub col: u32,
+    /// Header text, unique and never empty (falls back to the column letter)
+    pub header: String,
+    /// "integer", "float", "date", "datetime", "bool", "string" or "empty"
+    /// (no values read)
+    pub column_type: String,
+    /// Share of the values read that fit the type, from 0 to 1; for a
+    /// string, the share that no other type reads
+    pub confidence: f64,
+    /// Cells read with a value
+    pub value_count: u32,
+    /// Cells read that are empty, blank text or an error
+    pub null_count: u32,
+}
+
+/// Infer each column's type from the rows below its header
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn infer_column_types(worksheet: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<ColumnTypeOptions> = serde_wasm_bindgen::from_value(options)?;
+        let types = infer_column_types_impl(&worksheet, &options.unwrap_or_default());
+        Ok(serde_wasm_bindgen::to_value(&types)?)
+    })
+}
+
+pub(crate) fn infer_column_types_impl(
+    worksheet: &ParsedWorksheet,
+    options: &ColumnTypeOptions,
+) -> Vec<ColumnType> {
+    let Some((start_row, start_col, end_row, end_col)) = used_range(worksheet) else {
+        return Vec::new();
+    };
+    let header_row = match options.no_header {
+        true => None,
+        false => Some(options.header_row.unwrap_or(start_row)),
+    };
+    let header_cells = header_row.and_then(|header_row| {
+        let row = worksheet
+            .rows
+            .iter()
+            .find(|row| row.row_num == header_row)?;
+        Some(row.cells.as_slice())
+    });
+    let headers = header_names(header_cells, start_col, end_col, &options.shared_strings);
+
+    let first_row = header_row.map_or(start_row, |row| row + 1);
+    let last_row = match options.sample_rows {
+        Some(n) => end_row.min(first_row.saturating_add(n).saturating_sub(1)),
+        None => end_row,
+    };
+    let rows_read = (last_row + 1).saturating_sub(first_row);
+    let date_styles = options
+        .styles
+        .as_ref()
+        .map(date_style_flags)
+        .unwrap_or_default();
+
+    // Per column, how many values are of each kind; "string" is text no
+    // other type reads
+    let mut counts: HashMap<u32, HashMap<&str, u32>> = HashMap::new();
+    for row in &worksheet.rows {
+        if row.row_num < first_row || row.row_num > last_row {
+            continue;
+        }
+        for cell in &row.cells {
+            let Some((_, col)) = parse_cell_ref(&cell.reference) else {
+                continue;
+            };
+            let kind = match resolve_cell_value(cell, &options.shared_strings) {
+                CellValue::Empty | CellValue::Error(_) => continue,
+                CellValue::Number(n) => {
+                    let date = cell
+                        .style_index
+                        .and_then(|i| date_styles.get(i as usize).copied())
+                        .unwrap_or(false);
+                    match (date, n.fract() == 0.0) {
+                        (true, true) => "date",
+                        (true, false) => "datetime",
+                        (false, true) => "integer",
+                        (false, false) => "float",
+                    }
+                }
+                CellValue::Bool(_) => "bool",
+                CellValue::Text(text) if text.trim().is_empty() => continue,
+                CellValue::Text(text) if options.parse_text => text_kind(text.trim()),
+                CellValue::Text(_) => "string",
+            };
+            *counts.entry(col).or_default().entry(kind).or_default() += 1;
+        }
+    }
+
+    (start_col..=end_col)
+        .zip(headers)
+        .map(|(col, header)| {
+            let counts = counts.remove(&col).unwrap_or_default();
+            let count = |kind: &str| counts.get(kind).copied().unwrap_or(0);
+            let values: u32 = counts.values().sum();
+            let (column_type, confidence) = if values == 0 {
+                ("empty", 0.0)
+            } else {
+                // Integers also fit floats, and dates datetimes
+                let support = |kind: &str| match kind {
+                    "float" => count("float") + count("integer"),
+                    "datetime" => count("datetime") + count("date"),
+                    _ => count(kind),
+                };
+                // The last maximum wins, so ties go to the more specific type
+                let (best, fit) = TYPES
+                    .iter()
+                    .rev()
+                    .map(|&kind| (kind, support(kind)))
+                    .max_by_key(|&(_, fit)| fit)
+                    .unwrap_or(("string", 0));
+                let share = |n: u32| f64::from(n) / f64::from(values);
+                if fit > 0 && share(fit) >= options.min_confidence {
+                    (best, share(fit))
+                } else {
+                    ("string", share(count("string")))
+                }
+            };
+            ColumnType {
+                col,
+                header,
+                column_type: column_type.to_string(),
+                confidence,
+                value_count: values,
+                null_count: rows_read.saturating_sub(values),
+            }
+        })
+        .collect()
+}
+
+/// The type text reads as: a whole or decimal number, an ISO date with or
+/// without a time, true/false, or else a string
+fn text_kind(text: &str) -> &'static str {
+    let digits = text.trim_start_matches(['-', '+']);
+    // Leading zeros mark codes ("00501") rather than numbers
+    let coded = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    let numeric = text.bytes().any(|b| b.is_ascii_digit())
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E'));
+    if numeric && !coded {
+        if text.parse::<i64>().is_ok() {
+            return "integer";
+        }
+        if text.parse::<f64>().is_ok_and(f64::is_finite) {
+            return "float";
+        }
+    }
+    if let Some(dt) = parse_iso_date(text) {
+        return match text.contains(['T', ' ']) && (dt.hour, dt.minute, dt.second) != (0, 0, 0) {
+            true => "datetime",
+            false => "date",
+        };
+    }
+    if text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false") {
+        return "bool";
+    }
+    "string"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+    use crate::{ParsedCell, ParsedStyle};
+
+    #[test]
+    fn test_infer_column_types() {
+        let text = Some("str");
+        let rows = [
+            ["Id", "Price", "Due", "Paid", "Zip", "Note"],
+            ["1", "9.5", "45413", "1", "00501", "n/a"],
+            ["2", "10", "45414.5", "0", "02134", ""],
+            ["3", "12.25", "2024-05-03", "TRUE", "10001", "x"],
+            ["#N/A", "", "", "false", "94105", "7"],
+        ];
+        let worksheet = ParsedWorksheet {
+            rows: rows
+                .iter()
+                .enumerate()
+                .map(|(i, values)| {
+                    let cells = values
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, value)| !value.is_empty())
+                        .map(|(col, value)| {
+                            let reference = format!("{}{}", (b'A' + col as u8) as char, i + 1);
+                            let (cell_type, style) = match (i, col) {
+                                (0, _) | (_, 4 | 5) | (3, 2 | 3) | (4, 3) => (text, 0),
+                                (4, 0) => (Some("e"), 0),
+                                (_, 2) => (None, 1),
+                                (_, 3) => (Some("b"), 0),
+                                _ => (None, 0),
+                            };
+                            ParsedCell {
+                                style_index: Some(style),
+                                ..cell(&reference, cell_type, value)
+                            }
+                        })
+                        .collect();
+                    row(i as u32 + 1, cells)
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let options = ColumnTypeOptions {
+            styles: Some(ParsedStyles {
+                cell_xfs: vec![
+                    ParsedStyle::default(),
+                    ParsedStyle {
+                        num_fmt_id: Some(14),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let types = infer_column_types_impl(&worksheet, &options);
+        let summary: Vec<_> = types
+            .iter()
+            .map(|t| {
+                (
+                    t.header.as_str(),
+                    t.column_type.as_str(),
+                    t.value_count,
+                    t.null_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Id", "integer", 3, 1),
+                ("Price", "float", 3, 1),
+                ("Due", "datetime", 3, 1),
+                ("Paid", "bool", 4, 0),
+                ("Zip", "string", 4, 0),
+                ("Note", "string", 3, 1),
+            ]
+        );
+        assert_eq!(types[4].confidence, 0.5);
+        assert!((types[5].confidence - 2.0 / 3.0).abs() < 1e-9);
+
+        // Without a header, the header texts are values too
+        let options = ColumnTypeOptions {
+            no_header: true,
+            sample_rows: Some(2),
+            ..options
+        };
+        let types = infer_column_types_impl(&worksheet, &options);
+        assert_eq!(types[0].header, "A");
+        assert_eq!(types[0].column_type, "string");
+        assert_eq!(types[1].column_type, "string");
+        let options = ColumnTypeOptions {
+            min_confidence: 0.5,
+            ..options
+        };
+        assert_eq!(
+            infer_column_types_impl(&worksheet, &options)[1].column_type,
+            "float"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Compact parse output with repeated strings interned
+//!
+//! Cell types and style attribute names (alignments, fill patterns, border
+//! styles, colors, font names) come from a handful of values repeated across
+//! the whole file. The interned variants of the parsers emit them as indices
+//! into a `strings` lookup table, so JS holds each distinct value once and
+//! serialization copies far fewer strings.
+
+use crate::context::ParserScratch;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{
+    parse_styles_impl, parse_worksheet_with, ParsedHyperlink, ParsedStyles, ParsedWorksheet,
+    WorksheetParseOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Deduplicating string table
+#[derive(Default)]
+struct Interner {
+    strings: Vec<String>,
+    index: HashMap<String, u32>,
+}
+
+impl Interner {
+    fn intern(&mut self, value: Option<String>) -> Option<u32> {
+        let value = value?;
+        if let Some(&id) = self.index.get(&value) {
+            return Some(id);
+        }
+        let id = self.strings.len() as u32;
+        self.strings.push(value.clone());
+        self.index.insert(value, id);
+        Some(id)
+    }
+}
+
+/// A cell whose `cell_type` is an index into the worksheet's `strings`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedCell {
+    pub reference: String,
+    pub cell_type: Option<u32>,
+    pub style_index: Option<u32>,
+    pub value: Option<String>,
+    pub formula: Option<String>,
+}
+
+/// A row of [`InternedCell`]s
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedRow {
+    pub row_num: u32,
+    pub cells: Vec<InternedCell>,
+    pub height: Option<f64>,
+    pub hidden: bool,
+}
+
+/// [`ParsedWorksheet`] with interned cell types
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedWorksheet {
+    pub strings: Vec<String>,
+    pub rows: Vec<InternedRow>,
+    pub merge_cells: Vec<String>,
+    pub hyperlinks: Vec<ParsedHyperlink>,
+    pub col_widths: HashMap<u32, f64>,
+}
+
+/// [`crate::ParsedStyle`] with interned alignment names
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedStyle {
+    pub num_fmt_id: Option<u32>,
+    pub font_id: Option<u32>,
+    pub fill_id: Option<u32>,
+    pub border_id: Option<u32>,
+    pub xf_id: Option<u32>,
+    pub apply_number_format: bool,
+    pub apply_font: bool,
+    pub apply_fill: bool,
+    pub apply_border: bool,
+    pub apply_alignment: bool,
+    pub horizontal: Option<u32>,
+    pub vertical: Option<u32>,
+    pub wrap_text: bool,
+    pub text_rotation: Option<i32>,
+    pub indent: Option<u32>,
+}
+
+/// [`crate::ParsedFont`] with interned color and name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedFont {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub size: Option<f64>,
+    pub color: Option<u32>,
+    pub name: Option<u32>,
+}
+
+/// [`crate::ParsedFill`] with interned pattern and colors
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedFill {
+    pub pattern_type: Option<u32>,
+    pub fg_color: Option<u32>,
+    pub bg_color: Option<u32>,
+}
+
+/// [`crate::ParsedBorder`] with interned styles and colors
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedBorder {
+    pub left_style: Option<u32>,
+    pub left_color: Option<u32>,
+    pub right_style: Option<u32>,
+    pub right_color: Option<u32>,
+    pub top_style: Option<u32>,
+    pub top_color: Option<u32>,
+    pub bottom_style: Option<u32>,
+    pub bottom_color: Option<u32>,
+}
+
+/// [`ParsedStyles`] with every attribute string interned into `strings`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternedStyles {
+    pub strings: Vec<String>,
+    pub cell_xfs: Vec<InternedStyle>,
+    pub fonts: Vec<InternedFont>,
+    pub fills: Vec<InternedFill>,
+    pub borders: Vec<InternedBorder>,
+    pub num_fmts: HashMap<u32, String>,
+}
+
+/// Parse worksheet XML, emitting cell types as indices into `strings`
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_interned(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = parse_worksheet_interned_with(xml, &mut ParserScratch::default());
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    })
+}
+
+/// Parse styles.xml, emitting attribute strings as indices into `strings`
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_styles_interned(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = intern_styles(parse_styles_impl(xml));
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    })
+}
+
+pub(crate) fn parse_worksheet_interned_with(
+    xml: &str,
+    scratch: &mut ParserScratch,
+) -> InternedWorksheet {
+    intern_worksheet(parse_worksheet_with(
+        xml,
+        scratch,
+        &WorksheetParseOptions::default(),
+    ))
+}
+
+fn intern_worksheet(worksheet: ParsedWorksheet) -> InternedWorksheet {
+    let mut interner = Interner::default();
+    let rows = worksheet
+        .rows
+        .into_iter()
+        .map(|row| InternedRow {
+            row_num: row.row_num,
+            cells: row
+                .cells
+                .into_iter()
+                .map(|cell| InternedCell {
+                    reference: cell.reference,
+                    cell_type: interner.intern(cell.cell_type),
+                    style_index: cell.style_index,
+                    value: cell.value,
+                    formula: cell.formula,
+                })
+                .collect(),
+            height: row.height,
+            hidden: row.hidden,
+        })
+        .collect();
+    InternedWorksheet {
+        strings: interner.strings,
+        rows,
+        merge_cells: worksheet.merge_cells,
+        hyperlinks: worksheet.hyperlinks,
+        col_widths: worksheet.col_widths,
+    }
+}
+
+fn intern_styles(styles: ParsedStyles) -> InternedStyles {
+    let mut interner = Interner::default();
+    let cell_xfs = styles
+        .cell_xfs
+        .into_iter()
+        .map(|xf| InternedStyle {
+            num_fmt_id: xf.num_fmt_id,
+            font_id: xf.font_id,
+            fill_id: xf.fill_id,
+            border_id: xf.border_id,
+            xf_id: xf.xf_id,
+            apply_number_format: xf.apply_number_format,
+            apply_font: xf.apply_font,
+            apply_fill: xf.apply_fill,
+            apply_border: xf.apply_border,
+            apply_alignment: xf.apply_alignment,
+            horizontal: interner.intern(xf.horizontal),
+            vertical: interner.intern(xf.vertical),
+            wrap_text: xf.wrap_text,
+            text_rotation: xf.text_rotation,
+            indent: xf.indent,
+        })
+        .collect();
+    let fonts = styles
+        .fonts
+        .into_iter()
+        .map(|font| InternedFont {
+            bold: font.bold,
+            italic: font.italic,
+            underline: font.underline,
+            strikethrough: font.strikethrough,
+            size: font.size,
+            color: interner.intern(font.color),
+            name: interner.intern(font.name),
+        })
+        .collect();
+    let fills = styles
+        .fills
+        .into_iter()
+        .map(|fill| InternedFill {
+            pattern_type: interner.intern(fill.pattern_type),
+            fg_color: interner.intern(fill.fg_color),
+            bg_color: interner.intern(fill.bg_color),
+        })
+        .collect();
+    let borders = styles
+        .borders
+        .into_iter()
+        .map(|border| InternedBorder {
+            left_style: interner.intern(border.left_style),
+            left_color: interner.intern(border.left_color),
+            right_style: interner.intern(border.right_style),
+            right_color: interner.intern(border.right_color),
+            top_style: interner.intern(border.top_style),
+            top_color: interner.intern(border.top_color),
+            bottom_style: interner.intern(border.bottom_style),
+            bottom_color: interner.intern(border.bottom_color),
+        })
+        .collect();
+    InternedStyles {
+        strings: interner.strings,
+        cell_xfs,
+        fonts,
+        fills,
+        borders,
+        num_fmts: styles.num_fmts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interned_worksheet() {
+        let xml = r#"<worksheet><sheetData><row r="1">
+            <c r="A1" t="s"><v>0</v></c><c r="B1"><v>1</v></c>
+            <c r="C1" t="s"><v>1</v></c><c r="D1" t="b"><v>1</v></c>
+        </row></sheetData></worksheet>"#;
+        let sheet = parse_worksheet_interned_with(xml, &mut ParserScratch::default());
+        assert_eq!(sheet.strings, vec!["s", "b"]);
+        let types: Vec<_> = sheet.rows[0].cells.iter().map(|c| c.cell_type).collect();
+        assert_eq!(types, vec![Some(0), None, Some(0), Some(1)]);
+    }
+}
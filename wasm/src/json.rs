@@ -0,0 +1,352 @@
+//! JSON / NDJSON records export
+//!
+//! One row becomes one object keyed by the header row. Values are typed in
+//! Rust (numbers, booleans, ISO dates for date-formatted cells) so the JS
+//! side can `JSON.parse` each line without further conversion.
+
+use crate::reference::{col_to_letters, parse_cell_ref, used_range};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{
+    date_style_flags, format_number, resolve_cell_value, serial_to_datetime, CellValue,
+};
+use crate::{ParsedCell, ParsedStyles, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// NDJSON output is handed to the sink once this much has accumulated
+const SINK_FLUSH_BYTES: usize = 64 * 1024;
+
+/// Options for [`worksheet_to_json`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonExportOptions {
+    /// Row holding the headers (1-based); defaults to the first used row
+    pub header_row: Option<u32>,
+    /// "ndjson" for one record per line, "array" for a JSON array
+    pub format: String,
+    /// Skip rows with no values
+    pub skip_empty_rows: bool,
+    /// Emit `null` for empty cells instead of omitting the key
+    pub include_empty: bool,
+    pub shared_strings: Vec<String>,
+    /// Styles used to detect date-formatted numbers
+    pub styles: Option<ParsedStyles>,
+    pub date1904: bool,
+}
+
+impl Default for JsonExportOptions {
+    fn default() -> Self {
+        JsonExportOptions {
+            header_row: None,
+            format: "ndjson".to_string(),
+            skip_empty_rows: true,
+            include_empty: false,
+            shared_strings: Vec::new(),
+            styles: None,
+            date1904: false,
+        }
+    }
+}
+
+/// Export a parsed worksheet as JSON records
+///
+/// With a `sink`, NDJSON output is streamed to `sink(chunk: string)` in
+/// batches of whole lines and the returned string holds only what is left.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn worksheet_to_json(
+    worksheet: JsValue,
+    options: JsValue,
+    sink: Option<js_sys::Function>,
+) -> Result<String, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<JsonExportOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+
+        let Some(sink) = sink else {
+            return worksheet_to_json_impl(&worksheet, &options, |_| Ok(()))
+                .map_err(|e| JsValue::from_str(&e));
+        };
+        // An exception from the sink is rethrown as it was
+        let mut thrown = None;
+        let result = worksheet_to_json_impl(&worksheet, &options, |chunk| {
+            if chunk.len() >= SINK_FLUSH_BYTES {
+                sink.call1(&JsValue::NULL, &JsValue::from_str(chunk))
+                    .map_err(|e| {
+                        thrown = Some(e);
+                        "The JSON sink threw".to_string()
+                    })?;
+                chunk.clear();
+            }
+            Ok(())
+        });
+        result.map_err(|e| thrown.unwrap_or_else(|| JsValue::from_str(&e)))
+    })
+}
+
+/// Append `text` as a JSON string literal
+pub(crate) fn push_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Header names for each column, made unique and never empty
+pub(crate) fn header_names(
+    row: Option<&[ParsedCell]>,
+    start_col: u32,
+    end_col: u32,
+    shared_strings: &[String],
+) -> Vec<String> {
+    let mut by_col: HashMap<u32, String> = HashMap::new();
+    for cell in row.unwrap_or_default() {
+        if let Some((_, col)) = parse_cell_ref(&cell.reference) {
+            let name = match resolve_cell_value(cell, shared_strings) {
+                CellValue::Empty => continue,
+                CellValue::Text(text) | CellValue::Error(text) => text.trim().to_string(),
+                CellValue::Number(number) => format_number(number),
+                CellValue::Bool(b) => if b { "TRUE" } else { "FALSE" }.to_string(),
+            };
+            by_col.insert(col, name);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    (start_col..=end_col)
+        .map(|col| {
+            let base = by_col
+                .remove(&col)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| col_to_letters(col));
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !seen.insert(name.clone()) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+/// Serialize the records, passing the output so far to `flush` after each
+/// NDJSON line; whatever it leaves in the buffer is returned at the end
+pub(crate) fn worksheet_to_json_impl<F>(
+    worksheet: &ParsedWorksheet,
+    options: &JsonExportOptions,
+    mut flush: F,
+) -> Result<String, String>
+where
+    F: FnMut(&mut String) -> Result<(), String>,
+{
+    let array = match options.format.as_str() {
+        "ndjson" => false,
+        "array" => true,
+        other => return Err(format!("Unknown JSON format: {}", other)),
+    };
+
+    let mut out = String::new();
+    let (start_row, start_col, end_row, end_col) = match used_range(worksheet) {
+        Some(bounds) => bounds,
+        None => {
+            if array {
+                out.push_str("[]");
+            }
+            return Ok(out);
+        }
+    };
+
+    let header_row = options.header_row.unwrap_or(start_row);
+    let header_cells = worksheet
+        .rows
+        .iter()
+        .find(|row| row.row_num == header_row)
+        .map(|row| row.cells.as_slice());
+    let headers = header_names(header_cells, start_col, end_col, &options.shared_strings);
+
+    let date_styles = options
+        .styles
+        .as_ref()
+        .map(date_style_flags)
+        .unwrap_or_default();
+
+    let mut rows: Vec<_> = worksheet
+        .rows
+        .iter()
+        .filter(|row| row.row_num > header_row && row.row_num <= end_row)
+        .collect();
+    rows.sort_by_key(|row| row.row_num);
+
+    if array {
+        out.push('[');
+    }
+    let mut first_record = true;
+    let mut values: Vec<Option<String>> = vec![None; headers.len()];
+
+    for row in rows {
+        values.iter_mut().for_each(|value| *value = None);
+        for cell in &row.cells {
+            let col = match parse_cell_ref(&cell.reference) {
+                Some((_, col)) if col >= start_col && col <= end_col => col,
+                _ => continue,
+            };
+
+            let mut json = String::new();
+            match resolve_cell_value(cell, &options.shared_strings) {
+                CellValue::Empty => continue,
+                CellValue::Text(text) | CellValue::Error(text) => {
+                    push_json_string(&mut json, &text)
+                }
+                CellValue::Bool(b) => json.push_str(if b { "true" } else { "false" }),
+                CellValue::Number(number) => {
+                    let is_date = cell
+                        .style_index
+                        .and_then(|s| date_styles.get(s as usize).copied())
+                        .unwrap_or(false);
+                    match serial_to_datetime(number, options.date1904) {
+                        Some(dt) if is_date => push_json_string(&mut json, &dt.to_iso()),
+                        // JSON has no NaN/Infinity
+                        _ if !number.is_finite() => json.push_str("null"),
+                        _ => {
+                            let _ = write!(json, "{}", number);
+                        }
+                    }
+                }
+            }
+            values[(col - start_col) as usize] = Some(json);
+        }
+
+        if options.skip_empty_rows && values.iter().all(Option::is_none) {
+            continue;
+        }
+
+        if array && !first_record {
+            out.push(',');
+        }
+        first_record = false;
+
+        out.push('{');
+        let mut first_field = true;
+        for (header, value) in headers.iter().zip(&values) {
+            let value = match value {
+                Some(value) => value.as_str(),
+                None if options.include_empty => "null",
+                None => continue,
+            };
+            if !first_field {
+                out.push(',');
+            }
+            first_field = false;
+            push_json_string(&mut out, header);
+            out.push(':');
+            out.push_str(value);
+        }
+        out.push('}');
+
+        if !array {
+            out.push('\n');
+            flush(&mut out)?;
+        }
+    }
+
+    if array {
+        out.push(']');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+    use crate::ParsedStyle;
+
+    #[test]
+    fn test_worksheet_to_json() {
+        let mut date = cell("C3", None, "45292.5");
+        date.style_index = Some(1);
+
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                row(
+                    1,
+                    vec![
+                        cell("A1", Some("s"), "0"),
+                        cell("B1", Some("s"), "0"),
+                        cell("C1", Some("inlineStr"), "when"),
+                    ],
+                ),
+                row(
+                    2,
+                    vec![
+                        cell("A2", Some("inlineStr"), "say \"hi\"\n"),
+                        cell("B2", None, "1.5"),
+                    ],
+                ),
+                row(3, vec![cell("B3", Some("b"), "0"), date]),
+                row(4, vec![]),
+            ],
+            ..Default::default()
+        };
+        let options = JsonExportOptions {
+            shared_strings: vec!["name".to_string()],
+            styles: Some(ParsedStyles {
+                cell_xfs: vec![
+                    ParsedStyle::default(),
+                    ParsedStyle {
+                        num_fmt_id: Some(22),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let ndjson = worksheet_to_json_impl(&worksheet, &options, |_| Ok(())).unwrap();
+        assert_eq!(
+            ndjson,
+            "{\"name\":\"say \\\"hi\\\"\\n\",\"name_2\":1.5}\n\
+             {\"name_2\":false,\"when\":\"2024-01-01T12:00:00\"}\n"
+        );
+        // A flush that takes everything leaves nothing to return
+        let mut lines = Vec::new();
+        let rest = worksheet_to_json_impl(&worksheet, &options, |chunk| {
+            lines.push(std::mem::take(chunk));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(lines.concat(), ndjson);
+        assert_eq!(lines.len(), 2);
+
+        let options = JsonExportOptions {
+            format: "array".to_string(),
+            header_row: Some(2),
+            include_empty: true,
+            ..options
+        };
+        let json = worksheet_to_json_impl(&worksheet, &options, |_| Ok(())).unwrap();
+        assert_eq!(
+            json,
+            "[{\"say \\\"hi\\\"\":null,\"1.5\":false,\"C\":\"2024-01-01T12:00:00\"}]"
+        );
+    }
+}
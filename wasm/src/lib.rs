@@ -2,25 +2,253 @@
 //!
 //! This module provides fast XML parsing for XLSX files, replacing the
 //! regex-based JavaScript parser with a streaming XML parser.
-
+//!
+//! The `wasm` feature (on by default) provides the `#[wasm_bindgen]`
+//! exports. Without it the crate is a plain Rust library for reading
+//! workbooks: [`native`] has the part parsers and a whole-file reader,
+//! returning the parsed structs directly.
+//!
+//! The heavier optional parts, also on by default through `full`, each have
+//! a feature so a build that only reads XLSX values and styles can leave
+//! them out: `xls`, `ods`, `encryption`, `formulas` (recalculation and the
+//! dependency graph), `exports` (Arrow and HTML), `charts`, `pivots`,
+//! `drawings` (pictures) and `comments`.
+
+// The helpers behind the JS exports go unused when they are compiled out
+#![cfg_attr(not(feature = "wasm"), allow(dead_code, unused_imports))]
+
+use context::ParserScratch;
+use formula::{MAX_COLS, MAX_ROWS};
+use namespace::Namespaces;
+use profile::phase;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use serde::{Deserialize, Serialize};
+use source::{BufferedSource, StrSource, XmlSource};
 use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+#[cfg(feature = "wasm")]
+use trap::trap;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+mod adjust;
+mod aggregate;
+#[cfg(feature = "exports")]
+mod arrow;
+mod autofit;
+mod batch;
+mod builder;
+#[cfg(any(feature = "xls", feature = "encryption"))]
+// Compound files are only written when encrypting
+#[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+mod cfb;
+#[cfg(feature = "charts")]
+mod chart;
+mod columnar;
+#[cfg(feature = "comments")]
+mod comments;
+mod context;
+// Sheet protection only needs the hashes
+#[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+mod crypto;
+mod csv;
+mod cursor;
+#[cfg(feature = "drawings")]
+mod drawing;
+mod duplicates;
+mod encoding;
+#[cfg(feature = "encryption")]
+mod encryption;
+#[cfg(feature = "formulas")]
+mod eval;
+mod export;
+mod feed;
+mod filter;
+mod formula;
+#[cfg(feature = "formulas")]
+mod graph;
+mod gzip;
+mod hash;
+#[cfg(feature = "exports")]
+mod html;
+mod infer;
+mod intern;
+mod json;
+mod limits;
+mod memory;
+mod msgpack;
+mod namespace;
+pub mod native;
+#[cfg(feature = "ods")]
+mod ods;
+#[cfg(feature = "pivots")]
+mod pivot;
+mod profile;
+mod properties;
+mod protection;
+mod range;
+mod reference;
+mod regex;
+mod repair;
+mod rich;
+mod scan;
+mod search;
+mod sheet;
+mod sort;
+mod source;
+mod stats;
+#[cfg(feature = "wasm")]
+mod stream;
+mod strings;
+mod table;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "wasm")]
+mod trap;
+mod typed;
+mod validate;
+mod values;
+mod views;
+mod workbook;
+mod writer;
+#[cfg(feature = "xls")]
+mod xls;
+mod xstring;
+mod zip;
+
+pub use adjust::StructureChange;
+pub use aggregate::{Aggregate, AggregateGroup, AggregateSpec};
+pub use batch::BatchSheet;
+pub use builder::{StyleSpec, WorkbookBuilder};
+pub use columnar::ColumnarWorksheet;
+pub use context::ParserContext;
+pub use cursor::RowCursor;
+pub use duplicates::{DuplicateGroup, DuplicateOptions};
+#[cfg(feature = "formulas")]
+pub use eval::{EvalWorkbook, EvaluatedCell};
+pub use feed::WorksheetFeed;
+pub use filter::{FilterOptions, RowCondition, RowPredicate};
+#[cfg(feature = "formulas")]
+pub use graph::{DependencyGraph, DependencyNode, FormulaAnalysis, GraphWorkbook, VolatileCell};
+pub use hash::{HashOptions, HashWorkbook, SheetHash, WorkbookHash};
+pub use infer::{ColumnType, ColumnTypeOptions};
+pub use intern::{
+    InternedBorder, InternedCell, InternedFill, InternedFont, InternedRow, InternedStyle,
+    InternedStyles, InternedWorksheet,
+};
+pub use limits::{LimitExceeded, ParseLimits};
+pub use memory::MemoryStats;
+#[cfg(feature = "ods")]
+pub use ods::ParsedOds;
+#[cfg(feature = "pivots")]
+pub use pivot::{PivotOptions, PivotRow, PivotTable};
+pub use profile::{PhaseTiming, ProfileReport};
+pub use properties::{CustomProperty, DocumentProperties};
+pub use protection::SheetProtection;
+pub use reference::{CellPosition, RangePosition, UsedRange, UsedRangeOptions};
+pub use repair::Repair;
+pub use rich::RichSharedString;
+pub use search::{CellComment, SearchMatch, SearchOptions, SearchWorkbook};
+pub use sheet::{Cell, Sheet};
+pub use sort::{SortKey, SortOptions};
+pub use stats::{SheetStats, StatsWorkbook, WorksheetStats};
+pub use strings::SharedStringTable;
+pub use table::{ColumnTotal, ParsedTable, TableStyleInfo};
+pub use typed::{ProbeColumn, ValueCell, ValueRow, ValuesParseOptions, WorksheetProbe};
+pub use validate::ValidationIssue;
+pub use views::{CustomSheetView, PageMargins, ViewPrintSettings};
+pub use workbook::{CellData, TypedValue, Workbook, WorkbookData};
+#[cfg(feature = "xls")]
+pub use xls::ParsedXls;
+pub use zip::ZipPackage;
+
+// The JS-facing entry points; native callers use `native` instead
+#[cfg(all(feature = "wasm", feature = "charts"))]
+pub use chart::write_chart;
+#[cfg(all(feature = "wasm", feature = "comments"))]
+pub use comments::{
+    vml_blocks, write_comments, write_persons, write_threaded_comments, write_vml_drawing,
+};
+#[cfg(all(feature = "wasm", feature = "drawings"))]
+pub use drawing::write_drawing;
+#[cfg(all(feature = "wasm", feature = "encryption"))]
+pub use encryption::{decrypt_workbook, is_encrypted_workbook};
+#[cfg(all(feature = "wasm", feature = "pivots"))]
+pub use pivot::pivot;
+#[cfg(feature = "wasm")]
+pub use {
+    adjust::adjust_formulas,
+    aggregate::aggregate,
+    batch::parse_worksheets_batch,
+    columnar::parse_worksheet_columnar,
+    csv::{parse_csv, worksheet_to_csv},
+    duplicates::find_duplicate_rows,
+    export::workbook_to_xlsx,
+    feed::parse_worksheet_stream,
+    filter::filter_rows,
+    formula::{formula_a1_to_r1c1, formula_r1c1_to_a1},
+    gzip::{decode_xml_part, parse_shared_strings_bytes, parse_worksheet_bytes},
+    hash::{hash_workbook, hash_worksheet},
+    infer::infer_column_types,
+    intern::{parse_styles_interned, parse_worksheet_interned},
+    json::worksheet_to_json,
+    memory::{memory_stats, reset_memory_peak, set_memory_limit},
+    msgpack::{
+        parse_relationships_msgpack, parse_shared_strings_msgpack, parse_styles_msgpack,
+        parse_workbook_msgpack, parse_worksheet_msgpack,
+    },
+    profile::{get_profile, reset_profile, set_profiling},
+    properties::{write_app_properties, write_core_properties, write_custom_properties},
+    protection::sheet_protection,
+    range::{
+        range_bounding_box, range_contains, range_contains_cell, range_intersect, range_subtract,
+        range_union,
+    },
+    reference::{
+        col_letter_to_index, compute_used_range, index_to_col_letter, parse_range, parse_ref,
+        parse_refs,
+    },
+    rich::parse_shared_strings_rich,
+    search::search,
+    sort::sort_rows,
+    stats::{count_cells, workbook_stats},
+    stream::WorksheetWriter,
+    strings::parse_shared_strings_table,
+    table::{expand_structured_references, parse_table},
+    typed::{parse_worksheet_values, probe_worksheet},
+    validate::validate_workbook,
+    views::{apply_custom_sheet_view, parse_custom_sheet_views},
+    writer::{
+        write_content_types, write_relationships, write_shared_strings, write_styles, write_table,
+        write_workbook, write_worksheet,
+    },
+};
+#[cfg(all(feature = "wasm", feature = "exports"))]
+pub use {arrow::worksheet_to_arrow, html::worksheet_to_html};
+#[cfg(all(feature = "wasm", feature = "formulas"))]
+pub use {
+    eval::recalculate_workbook,
+    graph::{analyze_formulas, build_dependency_graph},
+};
+#[cfg(all(feature = "wasm", feature = "ods"))]
+pub use {msgpack::parse_ods_msgpack, ods::parse_ods};
+#[cfg(all(feature = "wasm", feature = "xls"))]
+pub use {msgpack::parse_xls_msgpack, xls::parse_xls};
+
 #[cfg(feature = "console_error_panic_hook")]
 pub use console_error_panic_hook::set_once as set_panic_hook;
 
 /// Initialize the WASM module (call once at startup)
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn init() {
-    #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
+    trap::install();
 }
 
 /// Parsed cell data from worksheet XML
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedCell {
     pub reference: String,
     pub cell_type: Option<String>,
@@ -30,7 +258,7 @@ pub struct ParsedCell {
 }
 
 /// Parsed row data
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedRow {
     pub row_num: u32,
     pub cells: Vec<ParsedCell>,
@@ -39,69 +267,600 @@ pub struct ParsedRow {
 }
 
 /// Parsed worksheet data
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ParsedWorksheet {
     pub rows: Vec<ParsedRow>,
     pub merge_cells: Vec<String>,
     pub hyperlinks: Vec<ParsedHyperlink>,
     pub col_widths: HashMap<u32, f64>,
+    /// Malformed XML that was skipped over (or, in strict mode, stopped at)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ParseWarning>,
+    /// Validation rules to write; the parsers don't read them yet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_validations: Vec<DataValidation>,
+    /// Conditional formatting to write; the parsers don't read it yet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditional_formats: Vec<ConditionalFormat>,
+    /// Filter dropdowns to write; the parsers don't read them yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_filter: Option<AutoFilter>,
+    /// Relationship ids of the sheet's table parts, to write
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub table_parts: Vec<String>,
+    /// Comments to write; the parsers don't read them yet
+    #[cfg(feature = "comments")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<ParsedComment>,
+    /// Relationship id of the VML drawing that shows the comments, to write
+    #[cfg(feature = "comments")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_drawing: Option<String>,
+    /// Pictures to write; the parsers don't read them yet
+    #[cfg(feature = "drawings")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<SheetImage>,
+    /// Charts to write; the parsers don't read them yet
+    #[cfg(feature = "charts")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub charts: Vec<SheetChart>,
+    /// Relationship id of the drawing that holds the pictures and charts, to
+    /// write
+    #[cfg(feature = "drawings")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drawing: Option<String>,
+    /// Locks against editing the sheet, to write; the parsers don't read it
+    /// yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protection: Option<SheetProtection>,
 }
 
-/// Parsed hyperlink
+/// What a [`ParseWarning`] reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    #[default]
+    MalformedXml,
+    /// A row or cell outside Excel's 1,048,576 rows by 16,384 columns, or
+    /// with a reference that can't be read; it is left out
+    OutOfBounds,
+    /// A row number seen before; with `normalize_rows` the later cells win
+    DuplicateRow,
+}
+
+impl WarningKind {
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            WarningKind::MalformedXml => "malformed_xml",
+            WarningKind::OutOfBounds => "out_of_bounds",
+            WarningKind::DuplicateRow => "duplicate_row",
+        }
+    }
+}
+
+/// A problem found while parsing a part, located so it can be fixed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseWarning {
+    #[serde(default)]
+    pub kind: WarningKind,
+    /// Byte offset in the part where the problem was detected
+    pub offset: u64,
+    /// 1-based line and column of `offset` (approximate for streamed parts)
+    pub line: u32,
+    pub column: u32,
+    /// Innermost element being read, e.g. "v" or "c"
+    pub element: Option<String>,
+    /// Reference of the last cell read before the problem
+    pub last_cell: Option<String>,
+    pub message: String,
+}
+
+impl ParseWarning {
+    fn at<S: XmlSource>(
+        source: &S,
+        kind: WarningKind,
+        element: Option<&str>,
+        last_cell: Option<String>,
+        message: String,
+    ) -> Self {
+        let offset = source.position();
+        let (line, column) = source.line_column(offset);
+        ParseWarning {
+            kind,
+            offset,
+            line,
+            column,
+            element: element.map(str::to_string),
+            last_cell,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            WarningKind::MalformedXml => "Malformed XML",
+            WarningKind::OutOfBounds => "Out of bounds",
+            WarningKind::DuplicateRow => "Duplicate row",
+        };
+        write!(f, "{} at line {}, column {}", what, self.line, self.column)?;
+        if let Some(element) = &self.element {
+            write!(f, " in <{}>", element)?;
+        }
+        if let Some(cell) = &self.last_cell {
+            write!(f, " after cell {}", cell)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Serialize)]
+struct WarningError<'a> {
+    error: &'static str,
+    description: String,
+    #[serde(flatten)]
+    warning: &'a ParseWarning,
+}
+
+/// Strict mode failures become `{ error: "malformed_xml", description, offset, line, ... }`
+/// (or `"out_of_bounds"`)
+#[cfg(feature = "wasm")]
+impl From<&ParseWarning> for JsValue {
+    fn from(warning: &ParseWarning) -> Self {
+        let error = WarningError {
+            error: warning.kind.code(),
+            description: warning.to_string(),
+            warning,
+        };
+        serde_wasm_bindgen::to_value(&error)
+            .unwrap_or_else(|_| JsValue::from_str(&error.description))
+    }
+}
+
+/// A worksheet read from a non-XLSX workbook, with its sheet metadata
 #[derive(Debug, Serialize, Deserialize)]
+pub struct ParsedSheet {
+    pub name: String,
+    /// "hidden" or "veryHidden"; `None` when visible
+    pub state: Option<String>,
+    pub worksheet: ParsedWorksheet,
+}
+
+/// Parsed hyperlink
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedHyperlink {
     pub reference: String,
     pub rid: Option<String>,
     pub location: Option<String>,
     pub display: Option<String>,
     pub tooltip: Option<String>,
+    /// External address behind `rid`, filled in by whole-workbook reads; on
+    /// export a link with a target gets its relationship written for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+/// A data validation rule, such as a dropdown list
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataValidation {
+    /// Cells it applies to, as space-separated ranges ("A2:A100 C2")
+    pub sqref: String,
+    /// "list", "whole", "decimal", "date", "time", "textLength" or "custom";
+    /// none accepts any value and only shows the prompt
+    pub validation_type: Option<String>,
+    /// "between" (the default), "notBetween", "equal", "lessThan", ...
+    pub operator: Option<String>,
+    /// A list's items ("\"Yes,No\"") or source range, or the first bound
+    pub formula1: Option<String>,
+    pub formula2: Option<String>,
+    pub allow_blank: bool,
+    /// Leave out a list's in-cell dropdown
+    pub hide_dropdown: bool,
+    pub show_input_message: bool,
+    pub show_error_message: bool,
+    /// "stop" (the default), "warning" or "information"
+    pub error_style: Option<String>,
+    pub error_title: Option<String>,
+    pub error: Option<String>,
+    pub prompt_title: Option<String>,
+    pub prompt: Option<String>,
+}
+
+/// Filter dropdowns on the header row of a range
+///
+/// Excel doesn't apply the criteria when it opens a file, only when the
+/// filter is next changed, so rows they exclude should also be hidden.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoFilter {
+    /// The range filtered, header row included ("A1:D100")
+    pub reference: String,
+    /// Criteria of the columns that are filtered
+    pub columns: Vec<FilterColumn>,
+}
+
+/// Criteria for one column of an [`AutoFilter`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterColumn {
+    /// Position in the filtered range, from 0 for its first column
+    pub col_id: u32,
+    /// Show only cells with one of these values
+    pub values: Vec<String>,
+    /// Show blank cells too
+    pub blank: bool,
+    /// Up to two comparisons, shown when either holds
+    pub custom: Vec<CustomFilter>,
+    /// Show only cells where both comparisons hold
+    pub and: bool,
+}
+
+/// A comparison in a [`FilterColumn`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomFilter {
+    /// "equal" (the default), "notEqual", "greaterThan", "lessThan",
+    /// "greaterThanOrEqual" or "lessThanOrEqual"
+    pub operator: Option<String>,
+    /// Number or text compared with, where text can use `*` and `?`
+    pub value: String,
+}
+
+/// A note on a cell, or the first post of a comment thread
+#[cfg(feature = "comments")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParsedComment {
+    pub reference: String,
+    pub author: Option<String>,
+    pub text: String,
+    /// Write a threaded comment, which Excel 365 shows as a conversation,
+    /// rather than a note; comments with replies always are
+    pub threaded: bool,
+    pub replies: Vec<CommentReply>,
+    /// When a thread was started, as an ISO 8601 date and time
+    pub date: Option<String>,
+    /// Mark the thread resolved
+    pub resolved: bool,
+}
+
+/// A picture placed on a sheet
+#[cfg(feature = "drawings")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetImage {
+    /// The image file: PNG, JPEG, GIF or BMP
+    pub data: Vec<u8>,
+    /// A cell for the picture's top-left corner, at the image's own size, or
+    /// a range to stretch it over
+    pub anchor: String,
+    /// Alternative text
+    pub description: Option<String>,
+}
+
+/// A chart placed on a sheet
+#[cfg(feature = "charts")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetChart {
+    /// "column", "bar" (horizontal bars), "line" or "pie"
+    pub chart_type: String,
+    pub title: Option<String>,
+    pub series: Vec<ChartSeries>,
+    /// A cell for the chart's top-left corner, at Excel's default size, or a
+    /// range to fill
+    pub anchor: String,
+    /// Where the legend goes: "r", "l", "t", "b" or "tr"; no legend when left
+    /// out
+    pub legend: Option<String>,
+}
+
+/// One line, set of bars or pie of a chart
+#[cfg(feature = "charts")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChartSeries {
+    /// Name shown in the legend
+    pub name: Option<String>,
+    /// Range holding the category labels, e.g. "Data!$A$2:$A$13"
+    pub categories: Option<String>,
+    /// Range holding the values, e.g. "Data!$B$2:$B$13"
+    pub values: String,
+}
+
+/// A reply in a comment thread
+#[cfg(feature = "comments")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommentReply {
+    pub author: Option<String>,
+    pub text: String,
+    pub date: Option<String>,
+}
+
+/// Conditional formatting rules for a set of cells
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConditionalFormat {
+    /// Cells the rules apply to, as space-separated ranges ("A2:A100 C2")
+    pub sqref: String,
+    pub rules: Vec<ConditionalRule>,
+}
+
+/// One conditional formatting rule; which fields it uses depends on its type
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConditionalRule {
+    /// "cellIs", "expression", "containsText", "top10", "aboveAverage",
+    /// "duplicateValues", "colorScale", "dataBar" or "iconSet"
+    pub rule_type: String,
+    /// A "cellIs" comparison: "between", "equal", "greaterThan", ...
+    pub operator: Option<String>,
+    /// Bounds or condition, relative to the first cell of `sqref`
+    pub formulas: Vec<String>,
+    /// Formatting to apply, as an index into `ParsedStyles.dxfs`
+    pub dxf_id: Option<u32>,
+    /// Evaluation order, lowest first; defaults to the rule's position
+    /// among the sheet's rules
+    pub priority: Option<u32>,
+    pub stop_if_true: bool,
+    /// The text a "containsText" rule looks for
+    pub text: Option<String>,
+    /// How many cells (or what percent of them) a "top10" rule picks
+    pub rank: Option<u32>,
+    pub percent: bool,
+    /// Pick the lowest values, or those below the average
+    pub bottom: bool,
+    /// Thresholds of a color scale, data bar or icon set, lowest first;
+    /// left out, they are spread evenly from the lowest value to the highest
+    pub thresholds: Vec<ConditionalThreshold>,
+    /// A color scale's color for each threshold, or a data bar's color
+    pub colors: Vec<String>,
+    /// "3TrafficLights1" (the default), "3Arrows", "5Rating", ...; "3Stars",
+    /// "3Triangles" and "5Boxes" need Excel 2010 or later
+    pub icon_set: Option<String>,
+    /// Show icons from lowest to highest instead
+    pub reverse: bool,
+    /// Show only the bar or icon, not the cell's value
+    pub hide_value: bool,
+    /// Fill data bars with a solid color rather than a gradient (Excel 2010)
+    pub solid_fill: bool,
+    /// Color of data bars for negative values (Excel 2010)
+    pub negative_color: Option<String>,
+}
+
+/// A threshold of a color scale, data bar or icon set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConditionalThreshold {
+    /// "min", "max", "num", "percent", "percentile" or "formula"
+    pub value_type: String,
+    pub value: Option<String>,
 }
 
 /// Parse worksheet XML and return structured data
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn parse_worksheet(xml: &str) -> JsValue {
-    let result = parse_worksheet_impl(xml);
-    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+pub fn parse_worksheet(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = phase("worksheet", || parse_worksheet_impl(xml));
+        let value = phase("serialization", || serde_wasm_bindgen::to_value(&result));
+        Ok(value.unwrap_or(JsValue::NULL))
+    })
+}
+
+/// Parse worksheet XML and return it as a JSON string for `JSON.parse`
+///
+/// For large sheets this is usually faster than building the object graph
+/// through wasm-bindgen, and `col_widths` comes out as a plain object.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_json(xml: &str) -> Result<String, JsValue> {
+    trap(|| {
+        let result = parse_worksheet_impl(xml);
+        Ok(serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string()))
+    })
+}
+
+/// Options for [`parse_worksheet_with_options`]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct WorksheetParseOptions {
+    /// Omit cells with no value or formula whose style is unset or 0
+    pub skip_empty_cells: bool,
+    /// With `skip_empty_cells`, also omit empty cells carrying another style
+    pub skip_styled_empty_cells: bool,
+    /// Only read cells inside this A1 range, e.g. "A1:F200"
+    pub range: Option<String>,
+    /// Skip this many `<row>` elements (after `range`), for paging through a sheet
+    pub skip_rows: u32,
+    /// Stop after this many `<row>` elements (after `skip_rows`)
+    pub max_rows: Option<u32>,
+    /// Leave out styles, formulas, hyperlinks, merges and column widths
+    pub values_only: bool,
+    /// Stop at the first malformed XML or out-of-bounds row or cell instead
+    /// of skipping to the next one
+    pub strict: bool,
+    /// Only read elements in the SpreadsheetML namespace, skipping foreign
+    /// elements whose local names collide (e.g. `row` inside an extension)
+    pub check_namespaces: bool,
+    /// Sort rows that come out of order and merge rows repeating a row
+    /// number, later cells replacing earlier ones in the same column
+    pub normalize_rows: bool,
+}
+
+impl WorksheetParseOptions {
+    /// In strict mode, fail with the problem the parse stopped at
+    #[cfg(feature = "wasm")]
+    pub(crate) fn check(&self, worksheet: &ParsedWorksheet) -> Result<(), JsValue> {
+        let stopped = worksheet
+            .warnings
+            .iter()
+            .find(|warning| warning.kind != WarningKind::DuplicateRow);
+        match stopped {
+            Some(warning) if self.strict => Err(warning.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn skips(&self, cell: &ParsedCell) -> bool {
+        self.skip_empty_cells
+            && cell.value.is_none()
+            && cell.formula.is_none()
+            && (self.skip_styled_empty_cells || cell.style_index.unwrap_or(0) == 0)
+    }
+}
+
+/// Parse worksheet XML with output options; `options` may be undefined
+///
+/// Rows left without cells by `skip_empty_cells` are dropped too, unless
+/// they carry a height or are hidden.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_with_options(xml: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let options: Option<WorksheetParseOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+        let guard = memory::guard();
+        let result = phase("worksheet", || {
+            parse_worksheet_with(xml, &mut ParserScratch::default(), &options)
+        });
+        guard.check().map_err(|e| JsValue::from_str(&e))?;
+        options.check(&result)?;
+        Ok(phase("serialization", || {
+            serde_wasm_bindgen::to_value(&result)
+        })?)
+    })
+}
+
+/// Parse only the cells of worksheet XML that fall inside `range` (e.g. "A1:F200")
+///
+/// Rows and cells outside the window are skipped without being decoded, and
+/// parsing of the sheet data stops after the window's last row. Merges and
+/// hyperlinks are limited to those overlapping the window.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_range(xml: &str, range: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        if reference::range_bounds(range).is_none() {
+            return Err(JsValue::from_str(&format!("Invalid range: {}", range)));
+        }
+        let options = WorksheetParseOptions {
+            range: Some(range.to_string()),
+            ..Default::default()
+        };
+        let guard = memory::guard();
+        let result = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+        guard.check().map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    })
 }
 
 fn parse_worksheet_impl(xml: &str) -> ParsedWorksheet {
-    let mut reader = Reader::from_str(xml);
-    reader.trim_text(true);
+    parse_worksheet_with(
+        xml,
+        &mut ParserScratch::default(),
+        &WorksheetParseOptions::default(),
+    )
+}
+
+/// Parse worksheet XML using (and refilling) reusable working memory
+pub(crate) fn parse_worksheet_with(
+    xml: &str,
+    scratch: &mut ParserScratch,
+    options: &WorksheetParseOptions,
+) -> ParsedWorksheet {
+    // Events borrow from `xml`, so unlike the other parsers no event buffer is needed
+    parse_worksheet_events(&mut StrSource::new(xml), scratch, options)
+}
+
+/// Parse worksheet XML as it is read from `input`, e.g. while a ZIP entry inflates
+///
+/// Only the current event is buffered, so the sheet never exists as one string.
+pub(crate) fn parse_worksheet_from(
+    input: impl BufRead,
+    scratch: &mut ParserScratch,
+    options: &WorksheetParseOptions,
+) -> ParsedWorksheet {
+    let mut source = BufferedSource::new(input, std::mem::take(&mut scratch.buf));
+    let worksheet = parse_worksheet_events(&mut source, scratch, options);
+    scratch.buf = source.into_buf();
+    worksheet
+}
+
+fn parse_worksheet_events<S: XmlSource>(
+    source: &mut S,
+    scratch: &mut ParserScratch,
+    options: &WorksheetParseOptions,
+) -> ParsedWorksheet {
+    let ParserScratch {
+        text: text_content,
+        shared_formulas,
+        row_capacity,
+        cell_capacity,
+        resume,
+        ..
+    } = scratch;
+    text_content.clear();
+    // Where rows and cells without an `r` attribute go
+    let mut last_row_num = match *resume {
+        Some(row_num) => row_num,
+        None => {
+            shared_formulas.clear();
+            0
+        }
+    };
 
     let mut worksheet = ParsedWorksheet {
-        rows: Vec::new(),
-        merge_cells: Vec::new(),
-        hyperlinks: Vec::new(),
-        col_widths: HashMap::new(),
+        rows: Vec::with_capacity(*row_capacity),
+        ..Default::default()
     };
 
-    let mut buf = Vec::new();
     let mut current_row: Option<ParsedRow> = None;
     let mut current_cell: Option<ParsedCell> = None;
     let mut in_value = false;
     let mut in_formula = false;
     let mut in_inline_str = false;
-    let mut text_content = String::new();
+    let mut current_shared: Option<String> = None;
+    let window = options.range.as_deref().and_then(reference::range_bounds);
+    let mut sheet_data: Option<Vec<u8>> = None;
+    let last_row_index = options
+        .max_rows
+        .map(|max| options.skip_rows.saturating_add(max));
+    let mut row_index = 0u32;
+    // Name of an element being skipped, copied out of the event it borrows from
+    let mut skip = Vec::new();
+    let mut last_resync: Option<u64> = None;
+    let mut namespaces = options.check_namespaces.then(Namespaces::default);
+    let mut last_col = 0u32;
+    let mut seen_rows = std::collections::HashSet::new();
 
     loop {
-        match reader.read_event_into(&mut buf) {
+        match source.next_event() {
             Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                if let Some(namespaces) = &mut namespaces {
+                    if !namespaces.is_spreadsheetml(&e) {
+                        skip.clear();
+                        skip.extend_from_slice(e.name().as_ref());
+                        source.skip_to_end(&skip);
+                        continue;
+                    }
+                }
                 match e.local_name().as_ref() {
                     b"row" => {
                         let mut row = ParsedRow {
                             row_num: 0,
-                            cells: Vec::new(),
+                            cells: Vec::with_capacity(*cell_capacity),
                             height: None,
                             hidden: false,
                         };
 
+                        let mut declared = None;
                         for attr in e.attributes().flatten() {
                             match attr.key.as_ref() {
-                                b"r" => {
-                                    if let Ok(val) = std::str::from_utf8(&attr.value) {
-                                        row.row_num = val.parse().unwrap_or(0);
-                                    }
-                                }
+                                b"r" => declared = Some(parse_u32(&attr.value)),
                                 b"ht" => {
                                     if let Ok(val) = std::str::from_utf8(&attr.value) {
                                         row.height = val.parse().ok();
@@ -116,7 +875,89 @@ fn parse_worksheet_impl(xml: &str) -> ParsedWorksheet {
                             }
                         }
 
-                        current_row = Some(row);
+                        // `r` may be left out, meaning the row after the previous one
+                        let row_num = declared.unwrap_or(Some(last_row_num.saturating_add(1)));
+                        match row_num.filter(|r| (1..=MAX_ROWS).contains(r)) {
+                            Some(row_num) => row.row_num = row_num,
+                            None => {
+                                let r = e.try_get_attribute("r").ok().flatten();
+                                let r = r.map(|a| String::from_utf8_lossy(&a.value).into_owned());
+                                let message = match r {
+                                    Some(r) => {
+                                        format!("Row number {} is not in 1..={}", r, MAX_ROWS)
+                                    }
+                                    None => format!("Row after {} is past the last row", MAX_ROWS),
+                                };
+                                skip.clear();
+                                skip.extend_from_slice(e.name().as_ref());
+                                let last_cell = last_cell_ref(&current_row, &worksheet.rows);
+                                worksheet.warnings.push(ParseWarning::at(
+                                    source,
+                                    WarningKind::OutOfBounds,
+                                    Some("row"),
+                                    last_cell,
+                                    message,
+                                ));
+                                if options.strict {
+                                    break;
+                                }
+                                source.skip_to_end(&skip);
+                                continue;
+                            }
+                        }
+                        // Noted once the row is kept, as `e` still borrows the source
+                        let duplicate = options.normalize_rows && !seen_rows.insert(row.row_num);
+                        last_row_num = row.row_num;
+                        last_col = 0;
+
+                        if memory::over_limit() {
+                            break;
+                        }
+                        let past_window =
+                            window.is_some_and(|(_, _, last_row, _)| row.row_num > last_row);
+                        if past_window && options.normalize_rows {
+                            // Rows may be out of order, so only this one can go
+                            skip.clear();
+                            skip.extend_from_slice(e.name().as_ref());
+                            source.skip_to_end(&skip);
+                            continue;
+                        }
+                        if past_window || last_row_index.is_some_and(|last| row_index >= last) {
+                            // Rows are in order, so nothing after this one is needed
+                            if let Some(name) = &sheet_data {
+                                source.skip_to_end(name);
+                            }
+                            continue;
+                        }
+                        if let Some((first_row, _, _, _)) = window {
+                            if row.row_num != 0 && row.row_num < first_row {
+                                skip.clear();
+                                skip.extend_from_slice(e.name().as_ref());
+                                source.skip_to_end(&skip);
+                                continue;
+                            }
+                        }
+                        row_index += 1;
+                        if row_index <= options.skip_rows {
+                            skip.clear();
+                            skip.extend_from_slice(e.name().as_ref());
+                            source.skip_to_end(&skip);
+                            continue;
+                        }
+
+                        if duplicate {
+                            worksheet.warnings.push(ParseWarning::at(
+                                source,
+                                WarningKind::DuplicateRow,
+                                Some("row"),
+                                last_cell_ref(&current_row, &worksheet.rows),
+                                format!("Row {} appears more than once", row.row_num),
+                            ));
+                        }
+                        // A row left open by malformed XML keeps the cells read so far
+                        if let Some(unclosed) = current_row.replace(row) {
+                            worksheet.rows.push(unclosed);
+                        }
                     }
                     b"c" => {
                         let mut cell = ParsedCell {
@@ -139,24 +980,83 @@ fn parse_worksheet_impl(xml: &str) -> ParsedWorksheet {
                                         cell.cell_type = Some(val.to_string());
                                     }
                                 }
-                                b"s" => {
-                                    if let Ok(val) = std::str::from_utf8(&attr.value) {
-                                        cell.style_index = val.parse().ok();
-                                    }
+                                b"s" if !options.values_only => {
+                                    cell.style_index = parse_u32(&attr.value)
                                 }
                                 _ => {}
                             }
                         }
 
+                        // `r` may be left out too, meaning the cell after the previous one
+                        let position = if cell.reference.is_empty() {
+                            let row_num = current_row.as_ref().map_or(last_row_num, |r| r.row_num);
+                            let col = last_col.saturating_add(1);
+                            (col <= MAX_COLS).then_some((row_num.max(1), col))
+                        } else {
+                            reference::parse_cell_ref(&cell.reference)
+                        };
+                        let Some((row_num, col)) = position else {
+                            let message = if cell.reference.is_empty() {
+                                format!("Cell after column {} is past the last column", MAX_COLS)
+                            } else {
+                                format!("Cell reference {} is outside the sheet", cell.reference)
+                            };
+                            skip.clear();
+                            skip.extend_from_slice(e.name().as_ref());
+                            let last_cell = last_cell_ref(&current_row, &worksheet.rows);
+                            worksheet.warnings.push(ParseWarning::at(
+                                source,
+                                WarningKind::OutOfBounds,
+                                Some("c"),
+                                last_cell,
+                                message,
+                            ));
+                            if options.strict {
+                                break;
+                            }
+                            source.skip_to_end(&skip);
+                            continue;
+                        };
+                        if cell.reference.is_empty() {
+                            cell.reference = reference::cell_ref(row_num, col);
+                        }
+                        last_col = col;
+
+                        if let Some((_, first_col, _, end_col)) = window {
+                            if col < first_col || col > end_col {
+                                skip.clear();
+                                skip.extend_from_slice(e.name().as_ref());
+                                source.skip_to_end(&skip);
+                                continue;
+                            }
+                        }
+
                         current_cell = Some(cell);
                     }
-                    b"v" => {
-                        in_value = true;
-                        text_content.clear();
+                    b"sheetData" => sheet_data = Some(e.name().as_ref().to_vec()),
+                    b"v" => in_value = true,
+                    b"f" | b"col" | b"mergeCell" | b"hyperlink" if options.values_only => {
+                        skip.clear();
+                        skip.extend_from_slice(e.name().as_ref());
+                        source.skip_to_end(&skip);
                     }
                     b"f" => {
                         in_formula = true;
                         text_content.clear();
+                        let mut shared = false;
+                        for attr in e.attributes().flatten() {
+                            match attr.key.as_ref() {
+                                b"t" => shared = attr.value.as_ref() == b"shared",
+                                b"si" => {
+                                    current_shared =
+                                        std::str::from_utf8(&attr.value).ok().map(str::to_string)
+                                }
+                                _ => {}
+                            }
+                        }
+                        if !shared {
+                            current_shared = None;
+                        }
                     }
                     b"is" => {
                         in_inline_str = true;
@@ -213,6 +1113,7 @@ fn parse_worksheet_impl(xml: &str) -> ParsedWorksheet {
                             location: None,
                             display: None,
                             tooltip: None,
+                            target: None,
                         };
 
                         for attr in e.attributes().flatten() {
@@ -260,20 +1161,39 @@ fn parse_worksheet_impl(xml: &str) -> ParsedWorksheet {
             Ok(Event::End(e)) => match e.local_name().as_ref() {
                 b"row" => {
                     if let Some(row) = current_row.take() {
-                        worksheet.rows.push(row);
+                        *cell_capacity = row.cells.len();
+                        let dropped = options.skip_empty_cells
+                            && row.cells.is_empty()
+                            && row.height.is_none()
+                            && !row.hidden;
+                        if !dropped {
+                            worksheet.rows.push(row);
+                        }
                     }
                 }
                 b"c" => {
-                    if let Some(cell) = current_cell.take() {
+                    if let Some(mut cell) = current_cell.take() {
+                        // Cells sharing a formula carry only its `si`; shift the master's text
+                        if let Some(si) = current_shared.take() {
+                            let target = reference::parse_cell_ref(&cell.reference);
+                            if let (None, Some((master, anchor)), Some(target)) =
+                                (&cell.formula, shared_formulas.get(&si), target)
+                            {
+                                cell.formula = formula::translate_formula(master, *anchor, target);
+                            }
+                        }
                         if let Some(ref mut row) = current_row {
-                            row.cells.push(cell);
+                            if !options.skips(&cell) {
+                                row.cells.push(cell);
+                            }
                         }
                     }
                 }
                 b"v" => {
                     in_value = false;
+                    // `<v></v>` still marks the cell as having a (blank) value
                     if let Some(ref mut cell) = current_cell {
-                        cell.value = Some(text_content.clone());
+                        cell.value.get_or_insert_with(String::new);
                     }
                 }
                 b"f" => {
@@ -281,53 +1201,199 @@ fn parse_worksheet_impl(xml: &str) -> ParsedWorksheet {
                     if let Some(ref mut cell) = current_cell {
                         if !text_content.is_empty() {
                             cell.formula = Some(text_content.clone());
+                            let anchor = reference::parse_cell_ref(&cell.reference);
+                            if let (Some(si), Some(anchor)) = (&current_shared, anchor) {
+                                shared_formulas.insert(si.clone(), (text_content.clone(), anchor));
+                            }
                         }
                     }
                 }
                 b"is" => {
                     in_inline_str = false;
                     if let Some(ref mut cell) = current_cell {
-                        cell.value = Some(text_content.clone());
+                        cell.value = Some(xstring::decode_xstring(text_content).into_owned());
                     }
                 }
                 _ => {}
             },
-            Ok(Event::Text(e)) => {
-                if in_value || in_formula || in_inline_str {
-                    if let Ok(text) = e.unescape() {
-                        text_content.push_str(&text);
+            // Values are usually one text event; move it straight into the cell
+            Ok(Event::Text(e)) if in_value => {
+                if let (Some(cell), Ok(text)) = (&mut current_cell, e.unescape()) {
+                    match &mut cell.value {
+                        Some(value) => value.push_str(&text),
+                        None => cell.value = Some(text.into_owned()),
                     }
                 }
             }
+            Ok(Event::Text(e)) if in_formula || in_inline_str => {
+                if let Ok(text) = e.unescape() {
+                    text_content.push_str(&text);
+                }
+            }
             Ok(Event::Eof) => break,
-            Err(_) => break,
+            Err(e) => {
+                let element = if in_value {
+                    Some("v")
+                } else if in_formula {
+                    Some("f")
+                } else if in_inline_str {
+                    Some("is")
+                } else if current_cell.is_some() {
+                    Some("c")
+                } else if current_row.is_some() {
+                    Some("row")
+                } else {
+                    None
+                };
+                let last_cell = last_cell_ref(&current_row, &worksheet.rows);
+                worksheet.warnings.push(ParseWarning::at(
+                    source,
+                    WarningKind::MalformedXml,
+                    element,
+                    last_cell,
+                    e.to_string(),
+                ));
+                if options.strict {
+                    break;
+                }
+                // Keep what was read of an interrupted cell
+                if let (Some(cell), Some(row)) = (current_cell.take(), &mut current_row) {
+                    if !options.skips(&cell) {
+                        row.cells.push(cell);
+                    }
+                }
+                in_value = false;
+                in_formula = false;
+                in_inline_str = false;
+                // Give up if resyncing makes no progress
+                match source.resync() {
+                    Some(next) if last_resync.is_none_or(|last| next > last) => {
+                        last_resync = Some(next)
+                    }
+                    _ => break,
+                }
+            }
             _ => {}
         }
-        buf.clear();
+    }
+    if let Some(unclosed) = current_row.take() {
+        worksheet.rows.push(unclosed);
+    }
+    if options.normalize_rows {
+        normalize_rows(&mut worksheet.rows);
+    }
+
+    if let Some(window) = window {
+        let overlaps = |r: &str| {
+            reference::range_bounds(r).is_some_and(|b| range::intersect(b, window).is_some())
+        };
+        worksheet.merge_cells.retain(|r| overlaps(r));
+        worksheet.hyperlinks.retain(|h| overlaps(&h.reference));
     }
 
+    *row_capacity = worksheet.rows.len();
     worksheet
 }
 
+/// Sort rows by number and merge rows sharing one, the later row's cells and
+/// attributes winning
+fn normalize_rows(rows: &mut Vec<ParsedRow>) {
+    if rows
+        .windows(2)
+        .all(|pair| pair[0].row_num < pair[1].row_num)
+    {
+        return;
+    }
+    rows.sort_by_key(|row| row.row_num);
+    rows.dedup_by(|later, earlier| {
+        if later.row_num != earlier.row_num {
+            return false;
+        }
+        for cell in later.cells.drain(..) {
+            match earlier
+                .cells
+                .iter_mut()
+                .find(|c| c.reference == cell.reference)
+            {
+                Some(existing) => *existing = cell,
+                None => earlier.cells.push(cell),
+            }
+        }
+        earlier.cells.sort_by_key(|cell| {
+            reference::parse_cell_ref(&cell.reference).map_or(0, |(_, col)| col)
+        });
+        earlier.height = later.height.or(earlier.height);
+        earlier.hidden = later.hidden;
+        true
+    });
+}
+
+/// Reference of the last cell read, in the open row or the last one pushed
+fn last_cell_ref(current_row: &Option<ParsedRow>, rows: &[ParsedRow]) -> Option<String> {
+    current_row
+        .iter()
+        .chain(rows.last())
+        .find_map(|row| row.cells.last())
+        .map(|cell| cell.reference.clone())
+}
+
+/// Parse an unsigned decimal attribute without a UTF-8 round trip
+fn parse_u32(bytes: &[u8]) -> Option<u32> {
+    if bytes.is_empty() {
+        return None;
+    }
+    bytes.iter().try_fold(0u32, |n, &b| {
+        if b.is_ascii_digit() {
+            n.checked_mul(10)?.checked_add((b - b'0') as u32)
+        } else {
+            None
+        }
+    })
+}
+
 /// Parse shared strings XML
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn parse_shared_strings(xml: &str) -> JsValue {
-    let result = parse_shared_strings_impl(xml);
-    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+pub fn parse_shared_strings(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = phase("sharedStrings", || parse_shared_strings_impl(xml));
+        let value = phase("serialization", || serde_wasm_bindgen::to_value(&result));
+        Ok(value.unwrap_or(JsValue::NULL))
+    })
 }
 
 fn parse_shared_strings_impl(xml: &str) -> Vec<String> {
+    parse_shared_strings_with(xml, &mut ParserScratch::default())
+}
+
+/// Parse shared strings XML using (and refilling) reusable working memory
+pub(crate) fn parse_shared_strings_with(xml: &str, scratch: &mut ParserScratch) -> Vec<String> {
+    let mut strings: Vec<String> = Vec::new();
+    for_each_shared_string(xml, scratch, |text| strings.push(text.to_string()));
+    strings
+}
+
+/// Stream each shared string item's text to `f`, in table order
+pub(crate) fn for_each_shared_string(
+    xml: &str,
+    scratch: &mut ParserScratch,
+    mut f: impl FnMut(&str),
+) {
     let mut reader = Reader::from_str(xml);
     reader.trim_text(false); // Preserve whitespace in strings
 
-    let mut strings: Vec<String> = Vec::new();
-    let mut buf = Vec::new();
+    let ParserScratch {
+        buf,
+        text: current_string,
+        ..
+    } = scratch;
+    buf.clear();
+
     let mut in_si = false;
     let mut in_t = false;
-    let mut current_string = String::new();
 
     loop {
-        match reader.read_event_into(&mut buf) {
+        match reader.read_event_into(buf) {
             Ok(Event::Start(e)) => match e.local_name().as_ref() {
                 b"si" => {
                     in_si = true;
@@ -341,18 +1407,19 @@ fn parse_shared_strings_impl(xml: &str) -> Vec<String> {
             Ok(Event::End(e)) => match e.local_name().as_ref() {
                 b"si" => {
                     in_si = false;
-                    strings.push(current_string.clone());
+                    f(&xstring::decode_xstring(current_string));
+                    if memory::over_limit() {
+                        break;
+                    }
                 }
                 b"t" => {
                     in_t = false;
                 }
                 _ => {}
             },
-            Ok(Event::Text(e)) => {
-                if in_t {
-                    if let Ok(text) = e.unescape() {
-                        current_string.push_str(&text);
-                    }
+            Ok(Event::Text(e)) if in_t => {
+                if let Ok(text) = e.unescape() {
+                    current_string.push_str(&text);
                 }
             }
             Ok(Event::Eof) => break,
@@ -361,12 +1428,10 @@ fn parse_shared_strings_impl(xml: &str) -> Vec<String> {
         }
         buf.clear();
     }
-
-    strings
 }
 
 /// Style definition from styles.xml
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ParsedStyle {
     pub num_fmt_id: Option<u32>,
     pub font_id: Option<u32>,
@@ -386,7 +1451,7 @@ pub struct ParsedStyle {
 }
 
 /// Font definition
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ParsedFont {
     pub bold: bool,
     pub italic: bool,
@@ -398,7 +1463,7 @@ pub struct ParsedFont {
 }
 
 /// Fill definition
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ParsedFill {
     pub pattern_type: Option<String>,
     pub fg_color: Option<String>,
@@ -406,7 +1471,7 @@ pub struct ParsedFill {
 }
 
 /// Border definition
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ParsedBorder {
     pub left_style: Option<String>,
     pub left_color: Option<String>,
@@ -419,20 +1484,38 @@ pub struct ParsedBorder {
 }
 
 /// Parsed styles data
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ParsedStyles {
     pub cell_xfs: Vec<ParsedStyle>,
     pub fonts: Vec<ParsedFont>,
     pub fills: Vec<ParsedFill>,
     pub borders: Vec<ParsedBorder>,
     pub num_fmts: HashMap<u32, String>,
+    /// Formatting for conditional formatting rules to write; not parsed yet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dxfs: Vec<ParsedDxf>,
+}
+
+/// Differential formatting a conditional format lays over a cell's own
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParsedDxf {
+    pub font: Option<ParsedFont>,
+    pub fill: Option<ParsedFill>,
+    pub border: Option<ParsedBorder>,
+    /// Number format code
+    pub num_fmt: Option<String>,
 }
 
 /// Parse styles.xml
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn parse_styles(xml: &str) -> JsValue {
-    let result = parse_styles_impl(xml);
-    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+pub fn parse_styles(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = phase("styles", || parse_styles_impl(xml));
+        let value = phase("serialization", || serde_wasm_bindgen::to_value(&result));
+        Ok(value.unwrap_or(JsValue::NULL))
+    })
 }
 
 fn parse_styles_impl(xml: &str) -> ParsedStyles {
@@ -775,24 +1858,88 @@ pub struct ParsedSheetInfo {
     pub state: Option<String>,
 }
 
+/// Pivot cache registration (cacheId to workbook relationship)
+#[cfg(feature = "pivots")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParsedPivotCache {
+    pub cache_id: u32,
+    pub rid: String,
+}
+
+/// Application version info from `<fileVersion>`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ParsedFileVersion {
+    pub app_name: Option<String>,
+    pub last_edited: Option<u32>,
+    pub lowest_edited: Option<u32>,
+    pub rup_build: Option<u32>,
+}
+
+/// Defined name (named range, print area, etc.)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedDefinedName {
+    pub name: String,
+    pub value: String,
+    pub local_sheet_id: Option<u32>,
+    pub hidden: bool,
+}
+
+/// Workbook-level properties from `<workbookPr>` and the first `<workbookView>`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ParsedWorkbookPr {
+    pub date1904: bool,
+    pub code_name: Option<String>,
+    pub default_theme_version: Option<u32>,
+    pub active_tab: Option<u32>,
+    pub first_sheet: Option<u32>,
+}
+
+/// Parsed workbook.xml data
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ParsedWorkbook {
+    pub sheets: Vec<ParsedSheetInfo>,
+    pub defined_names: Vec<ParsedDefinedName>,
+    pub workbook_pr: ParsedWorkbookPr,
+    #[cfg(feature = "pivots")]
+    pub pivot_caches: Vec<ParsedPivotCache>,
+    pub file_version: Option<ParsedFileVersion>,
+    /// Absolute path the file was last saved to (`x15ac:absPath`)
+    pub abs_path: Option<String>,
+}
+
 /// Parse workbook.xml to get sheet list
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_workbook(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = parse_workbook_impl(xml).sheets;
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    })
+}
+
+/// Parse workbook.xml including sheets, pivot cache registry and file provenance
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn parse_workbook(xml: &str) -> JsValue {
-    let result = parse_workbook_impl(xml);
-    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+pub fn parse_workbook_full(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = parse_workbook_impl(xml);
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    })
 }
 
-fn parse_workbook_impl(xml: &str) -> Vec<ParsedSheetInfo> {
+fn parse_workbook_impl(xml: &str) -> ParsedWorkbook {
     let mut reader = Reader::from_str(xml);
     reader.trim_text(true);
 
-    let mut sheets: Vec<ParsedSheetInfo> = Vec::new();
+    let mut workbook = ParsedWorkbook::default();
     let mut buf = Vec::new();
+    let mut current_name: Option<ParsedDefinedName> = None;
+    let mut seen_view = false;
 
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
-                if e.local_name().as_ref() == b"sheet" {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"sheet" => {
                     let mut sheet = ParsedSheetInfo {
                         name: String::new(),
                         sheet_id: 0,
@@ -831,7 +1978,164 @@ fn parse_workbook_impl(xml: &str) -> Vec<ParsedSheetInfo> {
                     }
 
                     if !sheet.name.is_empty() {
-                        sheets.push(sheet);
+                        workbook.sheets.push(sheet);
+                    }
+                }
+                #[cfg(feature = "pivots")]
+                b"pivotCache" => {
+                    let mut cache_id: Option<u32> = None;
+                    let mut rid: Option<String> = None;
+
+                    for attr in e.attributes().flatten() {
+                        match attr.key.as_ref() {
+                            b"cacheId" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    cache_id = val.parse().ok();
+                                }
+                            }
+                            _ => {
+                                // Check for r:id
+                                if let Ok(key) = std::str::from_utf8(attr.key.as_ref()) {
+                                    if key.ends_with(":id") || key == "id" {
+                                        if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                            rid = Some(val.to_string());
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    if let (Some(cache_id), Some(rid)) = (cache_id, rid) {
+                        workbook
+                            .pivot_caches
+                            .push(ParsedPivotCache { cache_id, rid });
+                    }
+                }
+                b"fileVersion" => {
+                    let mut version = ParsedFileVersion::default();
+
+                    for attr in e.attributes().flatten() {
+                        match attr.key.as_ref() {
+                            b"appName" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    version.app_name = Some(val.to_string());
+                                }
+                            }
+                            b"lastEdited" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    version.last_edited = val.parse().ok();
+                                }
+                            }
+                            b"lowestEdited" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    version.lowest_edited = val.parse().ok();
+                                }
+                            }
+                            b"rupBuild" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    version.rup_build = val.parse().ok();
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    workbook.file_version = Some(version);
+                }
+                b"absPath" => {
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref() == b"url" {
+                            if let Ok(val) = attr.unescape_value() {
+                                workbook.abs_path = Some(val.to_string());
+                            }
+                        }
+                    }
+                }
+                b"workbookPr" => {
+                    let pr = &mut workbook.workbook_pr;
+                    for attr in e.attributes().flatten() {
+                        match attr.key.as_ref() {
+                            b"date1904" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    pr.date1904 = val == "1" || val == "true";
+                                }
+                            }
+                            b"codeName" => {
+                                if let Ok(val) = attr.unescape_value() {
+                                    pr.code_name = Some(val.to_string());
+                                }
+                            }
+                            b"defaultThemeVersion" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    pr.default_theme_version = val.parse().ok();
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                b"workbookView" if !seen_view => {
+                    seen_view = true;
+                    let pr = &mut workbook.workbook_pr;
+                    for attr in e.attributes().flatten() {
+                        match attr.key.as_ref() {
+                            b"activeTab" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    pr.active_tab = val.parse().ok();
+                                }
+                            }
+                            b"firstSheet" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    pr.first_sheet = val.parse().ok();
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                b"definedName" => {
+                    let mut defined_name = ParsedDefinedName {
+                        name: String::new(),
+                        value: String::new(),
+                        local_sheet_id: None,
+                        hidden: false,
+                    };
+
+                    for attr in e.attributes().flatten() {
+                        match attr.key.as_ref() {
+                            b"name" => {
+                                if let Ok(val) = attr.unescape_value() {
+                                    defined_name.name = val.to_string();
+                                }
+                            }
+                            b"localSheetId" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    defined_name.local_sheet_id = val.parse().ok();
+                                }
+                            }
+                            b"hidden" => {
+                                if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                    defined_name.hidden = val == "1" || val == "true";
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    current_name = Some(defined_name);
+                }
+                _ => {}
+            },
+            Ok(Event::Text(e)) if current_name.is_some() => {
+                if let (Some(defined_name), Ok(text)) = (current_name.as_mut(), e.unescape()) {
+                    defined_name.value.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"definedName" => {
+                if let Some(defined_name) = current_name.take() {
+                    if !defined_name.name.is_empty() {
+                        workbook.defined_names.push(defined_name);
                     }
                 }
             }
@@ -842,7 +2146,7 @@ fn parse_workbook_impl(xml: &str) -> Vec<ParsedSheetInfo> {
         buf.clear();
     }
 
-    sheets
+    workbook
 }
 
 /// Relationship info
@@ -855,10 +2159,13 @@ pub struct ParsedRelationship {
 }
 
 /// Parse relationships file (.rels)
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn parse_relationships(xml: &str) -> JsValue {
-    let result = parse_relationships_impl(xml);
-    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+pub fn parse_relationships(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = parse_relationships_impl(xml);
+        Ok(serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL))
+    })
 }
 
 fn parse_relationships_impl(xml: &str) -> Vec<ParsedRelationship> {
@@ -870,44 +2177,45 @@ fn parse_relationships_impl(xml: &str) -> Vec<ParsedRelationship> {
 
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
-                if e.local_name().as_ref() == b"Relationship" {
-                    let mut rel = ParsedRelationship {
-                        id: String::new(),
-                        rel_type: String::new(),
-                        target: String::new(),
-                        target_mode: None,
-                    };
-
-                    for attr in e.attributes().flatten() {
-                        match attr.key.as_ref() {
-                            b"Id" => {
-                                if let Ok(val) = std::str::from_utf8(&attr.value) {
-                                    rel.id = val.to_string();
-                                }
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if e.local_name().as_ref() == b"Relationship" =>
+            {
+                let mut rel = ParsedRelationship {
+                    id: String::new(),
+                    rel_type: String::new(),
+                    target: String::new(),
+                    target_mode: None,
+                };
+
+                for attr in e.attributes().flatten() {
+                    match attr.key.as_ref() {
+                        b"Id" => {
+                            if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                rel.id = val.to_string();
                             }
-                            b"Type" => {
-                                if let Ok(val) = std::str::from_utf8(&attr.value) {
-                                    rel.rel_type = val.to_string();
-                                }
+                        }
+                        b"Type" => {
+                            if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                rel.rel_type = val.to_string();
                             }
-                            b"Target" => {
-                                if let Ok(val) = std::str::from_utf8(&attr.value) {
-                                    rel.target = val.to_string();
-                                }
+                        }
+                        b"Target" => {
+                            // URLs often carry escaped query strings (`&amp;`)
+                            if let Ok(val) = attr.unescape_value() {
+                                rel.target = val.to_string();
                             }
-                            b"TargetMode" => {
-                                if let Ok(val) = std::str::from_utf8(&attr.value) {
-                                    rel.target_mode = Some(val.to_string());
-                                }
+                        }
+                        b"TargetMode" => {
+                            if let Ok(val) = std::str::from_utf8(&attr.value) {
+                                rel.target_mode = Some(val.to_string());
                             }
-                            _ => {}
                         }
+                        _ => {}
                     }
+                }
 
-                    if !rel.id.is_empty() {
-                        rels.push(rel);
-                    }
+                if !rel.id.is_empty() {
+                    rels.push(rel);
                 }
             }
             Ok(Event::Eof) => break,
@@ -940,6 +2248,7 @@ mod tests {
         assert_eq!(strings[2], "RichText");
     }
 
+    #[cfg(feature = "wasm")]
     #[test]
     fn test_parse_worksheet() {
         let xml = r#"<?xml version="1.0"?>
@@ -949,15 +2258,236 @@ mod tests {
                     <c r="A1" t="s"><v>0</v></c>
                     <c r="B1"><v>42</v></c>
                 </row>
+                <row r="2">
+                    <c r="A2"><f t="shared" ref="A2:B2" si="0">A1*2</f><v>0</v></c>
+                    <c r="B2"><f t="shared" si="0"/><v>84</v></c>
+                </row>
+                <row r="3">
+                    <c r="A3" t="str"><v>a &amp; b</v></c>
+                    <c r="B3" t="str"><v></v></c>
+                </row>
             </sheetData>
         </worksheet>"#;
 
         let worksheet = parse_worksheet_impl(xml);
-        assert_eq!(worksheet.rows.len(), 1);
+        assert_eq!(worksheet.rows.len(), 3);
         assert_eq!(worksheet.rows[0].cells.len(), 2);
         assert_eq!(worksheet.rows[0].cells[0].reference, "A1");
         assert_eq!(worksheet.rows[0].cells[0].cell_type, Some("s".to_string()));
         assert_eq!(worksheet.rows[0].cells[0].value, Some("0".to_string()));
+        assert_eq!(worksheet.rows[1].cells[1].formula.as_deref(), Some("B1*2"));
+        assert_eq!(worksheet.rows[1].cells[1].value.as_deref(), Some("84"));
+        assert_eq!(worksheet.rows[2].cells[0].value.as_deref(), Some("a & b"));
+        assert_eq!(worksheet.rows[2].cells[1].value.as_deref(), Some(""));
+
+        let json = parse_worksheet_json(xml).unwrap();
+        assert!(json.starts_with(r#"{"rows":[{"row_num":1,"cells":[{"reference":"A1""#));
+    }
+
+    #[test]
+    fn test_skip_empty_cells() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c><c r="B1"/><c r="C1" s="0"/><c r="D1" s="4"/></row>
+            <row r="2"><c r="A2" s="4"/></row>
+            <row r="3" ht="30"><c r="A3"/></row>
+        </sheetData></worksheet>"#;
+        let parse = |options: WorksheetParseOptions| {
+            parse_worksheet_with(xml, &mut ParserScratch::default(), &options)
+        };
+
+        let sheet = parse(WorksheetParseOptions {
+            skip_empty_cells: true,
+            ..Default::default()
+        });
+        let refs: Vec<_> = sheet.rows[0]
+            .cells
+            .iter()
+            .map(|c| c.reference.as_str())
+            .collect();
+        assert_eq!(refs, vec!["A1", "D1"]);
+        assert_eq!(sheet.rows.len(), 3);
+        assert!(sheet.rows[2].cells.is_empty());
+
+        let sheet = parse(WorksheetParseOptions {
+            skip_empty_cells: true,
+            skip_styled_empty_cells: true,
+            ..Default::default()
+        });
+        assert_eq!(sheet.rows.len(), 2);
+        assert_eq!(sheet.rows[0].cells.len(), 1);
+        assert_eq!(sheet.rows[1].row_num, 3);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c><c r="B1"><v>2</v></c><c r="C1"><v>3</v></c></row>
+            <row r="2"><c r="A2"><v>4</v></c><c r="B2"><f>A2*2</f><v>8</v></c><c r="C2"/></row>
+            <row r="3"><c r="B3"><v>6</v></c></row>
+            <row r="4"><c r="B4"><v>7</v></c></row>
+        </sheetData><mergeCells><mergeCell ref="A1:A2"/><mergeCell ref="D5:E6"/></mergeCells>
+        </worksheet>"#;
+        let options = WorksheetParseOptions {
+            range: Some("B2:C3".to_string()),
+            ..Default::default()
+        };
+        let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+        let refs: Vec<Vec<&str>> = sheet
+            .rows
+            .iter()
+            .map(|row| row.cells.iter().map(|c| c.reference.as_str()).collect())
+            .collect();
+        assert_eq!(refs, vec![vec!["B2", "C2"], vec!["B3"]]);
+        assert_eq!(sheet.rows[0].cells[0].formula.as_deref(), Some("A2*2"));
+        assert!(sheet.merge_cells.is_empty());
+    }
+
+    #[test]
+    fn test_row_pagination() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c></row>
+            <row r="3"><c r="A3"><v>3</v></c></row>
+            <row r="4"><c r="A4"><v>4</v></c></row>
+            <row r="7"><c r="A7"><v>7</v></c></row>
+        </sheetData><mergeCells><mergeCell ref="B1:C1"/></mergeCells></worksheet>"#;
+        let page = |skip_rows: u32| {
+            let options = WorksheetParseOptions {
+                skip_rows,
+                max_rows: Some(2),
+                ..Default::default()
+            };
+            let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+            assert_eq!(sheet.merge_cells, vec!["B1:C1"]);
+            sheet.rows.iter().map(|row| row.row_num).collect::<Vec<_>>()
+        };
+        assert_eq!(page(0), vec![1, 3]);
+        assert_eq!(page(2), vec![4, 7]);
+        assert!(page(4).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_recovery() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c><c r="B1"><v>2</v><!oops></c><c r="C1"><v>3</v></c></row>
+            <row r="2"><c r="A2"><v>4</v></c></row>
+        </sheetData></worksheet>"#;
+        let refs = |sheet: &ParsedWorksheet| {
+            let cells = sheet.rows.iter().flat_map(|row| &row.cells);
+            cells.map(|c| c.reference.clone()).collect::<Vec<_>>()
+        };
+
+        let options = WorksheetParseOptions::default();
+        let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+        assert_eq!(refs(&sheet), vec!["A1", "B1", "C1", "A2"]);
+        assert_eq!(sheet.warnings.len(), 1);
+        let bad = xml.find("<!oops>").unwrap();
+        assert!((bad..bad + 7).contains(&(sheet.warnings[0].offset as usize)));
+        let warning = &sheet.warnings[0];
+        assert_eq!(warning.line, 2);
+        assert_eq!(warning.element.as_deref(), Some("c"));
+        assert_eq!(warning.last_cell.as_deref(), Some("A1"));
+        assert!(warning
+            .to_string()
+            .starts_with("Malformed XML at line 2, column 65 in <c> after cell A1: "));
+
+        // The streaming source recovers the same way
+        let streamed = parse_worksheet_from(
+            std::io::BufReader::with_capacity(8, xml.as_bytes()),
+            &mut ParserScratch::default(),
+            &options,
+        );
+        assert_eq!(refs(&streamed), refs(&sheet));
+        assert_eq!(streamed.warnings[0].line, 2);
+
+        let strict = WorksheetParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &strict);
+        assert_eq!(refs(&sheet), vec!["A1"]);
+        assert_eq!(sheet.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_implicit_positions() {
+        let xml = r#"<worksheet><sheetData>
+            <row><c><f t="shared" si="0" ref="A1:A2">B1*2</f><v>2</v></c><c r="C1"/><c><v>1</v></c></row>
+            <row r="4"><c><f t="shared" si="0"/><v>8</v></c></row>
+            <row><c t="inlineStr"><is><t>x</t></is></c></row>
+        </sheetData></worksheet>"#;
+        let options = WorksheetParseOptions::default();
+        let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+        let cells: Vec<_> = sheet
+            .rows
+            .iter()
+            .flat_map(|row| row.cells.iter().map(move |c| (row.row_num, c)))
+            .map(|(row, c)| (row, c.reference.as_str(), c.formula.as_deref()))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (1, "A1", Some("B1*2")),
+                (1, "C1", None),
+                (1, "D1", None),
+                (4, "A4", Some("B4*2")),
+                (5, "A5", None),
+            ]
+        );
+        assert!(sheet.warnings.is_empty());
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_normalize_rows() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="3"><c r="A3"><v>1</v></c></row>
+            <row r="1"><c r="A1"><v>2</v></c><c r="B1"><v>3</v></c></row>
+            <row><c r="A2"><v>4</v></c></row>
+            <row r="1" ht="20"><c r="B1"><v>5</v></c><c r="C1"><v>6</v></c></row>
+        </sheetData></worksheet>"#;
+        let options = WorksheetParseOptions {
+            normalize_rows: true,
+            ..Default::default()
+        };
+        let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+        let rows: Vec<_> = sheet.rows.iter().map(|row| row.row_num).collect();
+        assert_eq!(rows, vec![1, 2, 3]);
+        let values: Vec<_> = sheet.rows[0]
+            .cells
+            .iter()
+            .map(|c| (c.reference.as_str(), c.value.as_deref().unwrap()))
+            .collect();
+        assert_eq!(values, vec![("A1", "2"), ("B1", "5"), ("C1", "6")]);
+        assert_eq!(sheet.rows[0].height, Some(20.0));
+        assert_eq!(sheet.warnings.len(), 1);
+        assert_eq!(sheet.warnings[0].kind, WarningKind::DuplicateRow);
+        assert!(options.check(&sheet).is_ok());
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let xml = r#"<worksheet><sheetData>
+            <row><c><v>1</v></c><c><v>2</v></c></row>
+            <row r="1048577"><c r="A1048577"><v>3</v></c></row>
+            <row r="5"><c r="XFE5"><v>4</v></c><c r="C5"><v>5</v></c></row>
+        </sheetData></worksheet>"#;
+        let options = WorksheetParseOptions::default();
+        let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+        let rows: Vec<_> = sheet.rows.iter().map(|row| row.row_num).collect();
+        assert_eq!(rows, vec![1, 5]);
+        let refs: Vec<_> = sheet.rows.iter().flat_map(|row| &row.cells).collect();
+        let refs: Vec<_> = refs.iter().map(|c| c.reference.as_str()).collect();
+        assert_eq!(refs, vec!["A1", "B1", "C5"]);
+
+        assert_eq!(sheet.warnings.len(), 2);
+        assert!(sheet
+            .warnings
+            .iter()
+            .all(|w| w.kind == WarningKind::OutOfBounds));
+        assert_eq!(sheet.warnings[1].element.as_deref(), Some("c"));
+        assert!(sheet.warnings[0]
+            .to_string()
+            .starts_with("Out of bounds at line 3"));
     }
 
     #[test]
@@ -970,9 +2500,62 @@ mod tests {
             </sheets>
         </workbook>"#;
 
-        let sheets = parse_workbook_impl(xml);
+        let sheets = parse_workbook_impl(xml).sheets;
         assert_eq!(sheets.len(), 2);
         assert_eq!(sheets[0].name, "Sheet1");
         assert_eq!(sheets[1].name, "Sheet2");
     }
+
+    #[cfg(feature = "pivots")]
+    #[test]
+    fn test_parse_workbook_pivot_caches() {
+        let xml = r#"<?xml version="1.0"?>
+        <workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"
+                  xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+            <sheets>
+                <sheet name="Data" sheetId="1" r:id="rId1"/>
+            </sheets>
+            <pivotCaches>
+                <pivotCache cacheId="5" r:id="rId3"/>
+                <pivotCache cacheId="7" r:id="rId4"/>
+            </pivotCaches>
+        </workbook>"#;
+
+        let workbook = parse_workbook_impl(xml);
+        assert_eq!(workbook.sheets.len(), 1);
+        assert_eq!(workbook.pivot_caches.len(), 2);
+        assert_eq!(workbook.pivot_caches[0].cache_id, 5);
+        assert_eq!(workbook.pivot_caches[0].rid, "rId3");
+        assert_eq!(workbook.pivot_caches[1].cache_id, 7);
+        assert_eq!(workbook.pivot_caches[1].rid, "rId4");
+    }
+
+    #[test]
+    fn test_parse_workbook_file_version() {
+        let xml = r#"<?xml version="1.0"?>
+        <workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"
+                  xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006"
+                  xmlns:x15ac="http://schemas.microsoft.com/office/spreadsheetml/2010/11/ac">
+            <fileVersion appName="xl" lastEdited="7" lowestEdited="6" rupBuild="27328"/>
+            <mc:AlternateContent>
+                <mc:Choice Requires="x15">
+                    <x15ac:absPath url="C:\Users\finance\Reports &amp; Data\"/>
+                </mc:Choice>
+            </mc:AlternateContent>
+            <sheets>
+                <sheet name="Sheet1" sheetId="1" r:id="rId1"/>
+            </sheets>
+        </workbook>"#;
+
+        let workbook = parse_workbook_impl(xml);
+        let version = workbook.file_version.expect("fileVersion should be parsed");
+        assert_eq!(version.app_name.as_deref(), Some("xl"));
+        assert_eq!(version.last_edited, Some(7));
+        assert_eq!(version.lowest_edited, Some(6));
+        assert_eq!(version.rup_build, Some(27328));
+        assert_eq!(
+            workbook.abs_path.as_deref(),
+            Some("C:\\Users\\finance\\Reports & Data\\")
+        );
+    }
 }
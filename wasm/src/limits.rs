@@ -0,0 +1,277 @@
+//! Resource limits for parsing untrusted files
+//!
+//! A small .xlsx can expand to gigabytes (a zip bomb) or hold millions of
+//! cells or deeply nested XML. When a workbook is parsed from bytes these
+//! limits are checked before the expensive work, and a breach is reported
+//! to JS as a structured "limits_exceeded" error instead of hanging the page.
+
+use memchr::memchr;
+use memchr::memmem;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Limits applied when parsing a workbook from bytes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseLimits {
+    /// Total bytes decompressed from the archive
+    pub max_decompressed_size: u64,
+    /// Entries in the archive
+    pub max_parts: u32,
+    /// Cells across all sheets
+    pub max_cells: u64,
+    /// XML element nesting depth
+    pub max_depth: u32,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_decompressed_size: 1 << 30,
+            max_parts: 10_000,
+            max_cells: 50_000_000,
+            max_depth: 256,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// Check the nesting depth of an XML part and count its `cell_tag` elements
+    pub(crate) fn check_xml(
+        &self,
+        part: &str,
+        xml: &str,
+        cell_tag: &[u8],
+    ) -> Result<u64, LimitExceeded> {
+        let (depth, cells) = xml_shape(xml.as_bytes(), cell_tag);
+        if depth > self.max_depth {
+            return Err(LimitExceeded::new(
+                "nesting_depth",
+                self.max_depth as u64,
+                Some(part),
+            ));
+        }
+        Ok(cells)
+    }
+}
+
+/// Which limit was crossed; sent to JS with `error: "limits_exceeded"`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LimitExceeded {
+    /// "decompressed_size", "part_count", "cell_count" or "nesting_depth"
+    pub limit: String,
+    pub max: u64,
+    /// The package part being read, when the limit is tied to one
+    pub part: Option<String>,
+}
+
+impl LimitExceeded {
+    pub(crate) fn new(limit: &str, max: u64, part: Option<&str>) -> Self {
+        LimitExceeded {
+            limit: limit.to_string(),
+            max,
+            part: part.map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Limit exceeded: {} over {}", self.limit, self.max)?;
+        if let Some(part) = &self.part {
+            write!(f, " in {}", part)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error from reading a package: a plain message or a crossed limit
+#[derive(Debug, Clone)]
+pub(crate) enum ReadError {
+    Message(String),
+    Limit(LimitExceeded),
+}
+
+impl From<String> for ReadError {
+    fn from(message: String) -> Self {
+        ReadError::Message(message)
+    }
+}
+
+impl From<&str> for ReadError {
+    fn from(message: &str) -> Self {
+        ReadError::Message(message.to_string())
+    }
+}
+
+impl From<LimitExceeded> for ReadError {
+    fn from(limit: LimitExceeded) -> Self {
+        ReadError::Limit(limit)
+    }
+}
+
+impl From<ReadError> for String {
+    fn from(error: ReadError) -> Self {
+        match error {
+            ReadError::Message(message) => message,
+            ReadError::Limit(limit) => limit.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LimitErrorObject<'a> {
+    error: &'static str,
+    message: String,
+    #[serde(flatten)]
+    limit: &'a LimitExceeded,
+}
+
+/// Messages become strings; limits become `{ error, message, limit, max, part }`
+#[cfg(feature = "wasm")]
+impl From<ReadError> for JsValue {
+    fn from(error: ReadError) -> Self {
+        match error {
+            ReadError::Message(message) => JsValue::from_str(&message),
+            ReadError::Limit(limit) => {
+                let object = LimitErrorObject {
+                    error: "limits_exceeded",
+                    message: limit.to_string(),
+                    limit: &limit,
+                };
+                serde_wasm_bindgen::to_value(&object)
+                    .unwrap_or_else(|_| JsValue::from_str(&limit.to_string()))
+            }
+        }
+    }
+}
+
+/// Maximum element depth and number of elements named `cell_tag`
+fn xml_shape(xml: &[u8], cell_tag: &[u8]) -> (u32, u64) {
+    let mut shape = XmlShape::new(cell_tag);
+    shape.feed(xml);
+    (shape.max_depth, shape.cells)
+}
+
+/// Running element depth and `cell_tag` count of XML fed in chunks
+///
+/// A tag-level scan, far cheaper than parsing: it only looks at the bytes
+/// after each `<`, skipping comments, CDATA and processing instructions.
+/// Markup cut off at the end of a chunk is carried over to the next one.
+pub(crate) struct XmlShape<'a> {
+    cell_tag: &'a [u8],
+    depth: u32,
+    pub max_depth: u32,
+    pub cells: u64,
+    carry: Vec<u8>,
+}
+
+impl<'a> XmlShape<'a> {
+    pub(crate) fn new(cell_tag: &'a [u8]) -> Self {
+        XmlShape {
+            cell_tag,
+            depth: 0,
+            max_depth: 0,
+            cells: 0,
+            carry: Vec::new(),
+        }
+    }
+
+    pub(crate) fn feed(&mut self, chunk: &[u8]) {
+        if self.carry.is_empty() {
+            if let Some(partial) = self.scan(chunk) {
+                self.carry.extend_from_slice(&chunk[partial..]);
+            }
+        } else {
+            let mut pending = std::mem::take(&mut self.carry);
+            pending.extend_from_slice(chunk);
+            if let Some(partial) = self.scan(&pending) {
+                pending.drain(..partial);
+                self.carry = pending;
+            }
+        }
+    }
+
+    /// Scan complete markup, returning where an unfinished tag starts
+    fn scan(&mut self, xml: &[u8]) -> Option<usize> {
+        let mut pos = 0;
+        while let Some(offset) = memchr(b'<', &xml[pos..]) {
+            let open = pos + offset;
+            let start = open + 1;
+            let rest = &xml[start..];
+            let skip_to = |end: &[u8]| memmem::find(rest, end).map(|i| start + i + end.len());
+            let next = match rest.first() {
+                Some(b'/') => {
+                    self.depth = self.depth.saturating_sub(1);
+                    Some(start)
+                }
+                Some(b'?') => skip_to(b"?>"),
+                Some(b'!') if rest.starts_with(b"!--") => skip_to(b"-->"),
+                Some(b'!') if rest.starts_with(b"![CDATA[") => skip_to(b"]]>"),
+                Some(b'!') => skip_to(b">"),
+                _ => memchr(b'>', rest).map(|end| {
+                    self.open_tag(&rest[..end]);
+                    start + end + 1
+                }),
+            };
+            match next {
+                Some(next) => pos = next,
+                None => return Some(open),
+            }
+        }
+        None
+    }
+
+    fn open_tag(&mut self, tag: &[u8]) {
+        let name_end = tag
+            .iter()
+            .position(|&b| b.is_ascii_whitespace() || b == b'/')
+            .unwrap_or(tag.len());
+        let name = &tag[..name_end];
+        let local = match memchr(b':', name) {
+            Some(colon) => &name[colon + 1..],
+            None => name,
+        };
+        if local == self.cell_tag {
+            self.cells += 1;
+        }
+        if !tag.ends_with(b"/") {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_shape() {
+        let xml = br#"<?xml version="1.0"?><!-- <x><x> --><worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c><x:c r="B1"/><col/></row>
+            <![CDATA[<c>]]></sheetData></worksheet>"#;
+        assert_eq!(xml_shape(xml, b"c"), (5, 2));
+
+        // Tags and comments split across chunks are carried over
+        let mut shape = XmlShape::new(b"c");
+        for chunk in xml.chunks(3) {
+            shape.feed(chunk);
+        }
+        assert_eq!((shape.max_depth, shape.cells), (5, 2));
+
+        let limits = ParseLimits {
+            max_depth: 4,
+            ..Default::default()
+        };
+        let error = limits
+            .check_xml("sheet1.xml", std::str::from_utf8(xml).unwrap(), b"c")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Limit exceeded: nesting_depth over 4 in sheet1.xml"
+        );
+    }
+}
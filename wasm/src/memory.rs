@@ -0,0 +1,180 @@
+//! Allocation tracking and the per-parse memory cap
+//!
+//! A counting wrapper around the system allocator keeps the live and peak
+//! byte counts. With a cap set through [`set_memory_limit`], the parse entry
+//! points watch those counts: the parsers stop at the next row or string
+//! once the cap is crossed, and the call fails with a "memory limit exceeded"
+//! error instead of growing until the tab runs out of memory.
+
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use serde::Serialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+/// Cap on `ALLOCATED` during a guarded parse; 0 means none
+static LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local!(static GUARDED: Cell<bool> = const { Cell::new(false) });
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            added(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            added(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            added(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn added(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+/// Snapshot returned by [`memory_stats`]; sizes are in bytes
+#[derive(Debug, Serialize)]
+pub struct MemoryStats {
+    /// Size of the wasm linear memory (never shrinks); null outside wasm
+    pub heap_size: Option<usize>,
+    /// Bytes currently allocated
+    pub allocated: usize,
+    /// Most bytes allocated at once since start-up or `reset_memory_peak`
+    pub peak_allocated: usize,
+    pub limit: Option<usize>,
+}
+
+/// Current heap size and allocation counts
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn memory_stats() -> Result<JsValue, JsValue> {
+    trap(|| {
+        #[cfg(target_arch = "wasm32")]
+        let heap_size = Some(core::arch::wasm32::memory_size(0) * 65536);
+        #[cfg(not(target_arch = "wasm32"))]
+        let heap_size = None;
+
+        let stats = MemoryStats {
+            heap_size,
+            allocated: ALLOCATED.load(Ordering::Relaxed),
+            peak_allocated: PEAK.load(Ordering::Relaxed),
+            limit: limit(),
+        };
+        Ok(serde_wasm_bindgen::to_value(&stats)?)
+    })
+}
+
+/// Start measuring `peak_allocated` from the current allocation
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn reset_memory_peak() {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Cap the bytes allocated (by anything in the module) while a parse runs; undefined removes the cap
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn set_memory_limit(bytes: Option<u32>) {
+    LIMIT.store(bytes.unwrap_or(0) as usize, Ordering::Relaxed);
+}
+
+fn limit() -> Option<usize> {
+    Some(LIMIT.load(Ordering::Relaxed)).filter(|&limit| limit > 0)
+}
+
+/// Whether a guarded parse has crossed the cap and should stop
+pub(crate) fn over_limit() -> bool {
+    GUARDED.with(Cell::get)
+        && limit().is_some_and(|limit| ALLOCATED.load(Ordering::Relaxed) > limit)
+}
+
+/// Enforces the cap for the parse it is held across
+pub(crate) struct MemoryGuard {
+    outer: bool,
+}
+
+/// Begin a parse that is subject to the memory cap
+pub(crate) fn guard() -> MemoryGuard {
+    reset_memory_peak();
+    MemoryGuard {
+        outer: GUARDED.with(|guarded| guarded.replace(true)),
+    }
+}
+
+impl MemoryGuard {
+    /// Fail if the cap was crossed at any point since the guard was taken
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let peak = PEAK.load(Ordering::Relaxed);
+        match limit() {
+            Some(limit) if peak > limit => Err(format!(
+                "Memory limit exceeded: the parse needed more than {} (limit {})",
+                megabytes(peak),
+                megabytes(limit)
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for MemoryGuard {
+    fn drop(&mut self) {
+        GUARDED.with(|guarded| guarded.set(self.outer));
+    }
+}
+
+fn megabytes(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_shared_strings_impl;
+
+    #[test]
+    fn test_memory_limit() {
+        let xml = format!("<sst>{}</sst>", "<si><t>some text</t></si>".repeat(50_000));
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let guard = guard();
+        set_memory_limit(Some((before + 256 * 1024) as u32));
+        let strings = parse_shared_strings_impl(&xml);
+        assert!(strings.len() < 50_000);
+        assert!(guard
+            .check()
+            .unwrap_err()
+            .starts_with("Memory limit exceeded"));
+        drop(guard);
+        assert!(!over_limit());
+        set_memory_limit(None);
+    }
+}
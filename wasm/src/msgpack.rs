@@ -0,0 +1,553 @@
+//! MessagePack output for the parse results
+//!
+//! A compact binary alternative to building JS objects through
+//! wasm-bindgen: the result crosses the boundary as one `Uint8Array` and is
+//! decoded on the JS side by `decodeMsgpack` (src/formats/xlsx/xlsx.msgpack.ts).
+//! Structs become maps keyed by field name, so the decoded value has the same
+//! shape as the regular parser output.
+
+#[cfg(all(feature = "wasm", feature = "ods"))]
+use crate::ods::parse_ods_impl;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+#[cfg(all(feature = "wasm", feature = "xls"))]
+use crate::xls::parse_xls_impl;
+use crate::{
+    parse_relationships_impl, parse_shared_strings_impl, parse_styles_impl, parse_workbook_impl,
+    parse_worksheet_impl,
+};
+use serde::ser::{self, Serialize};
+use std::fmt;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Parse worksheet XML into MessagePack
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_msgpack(xml: &str) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_worksheet_impl(xml)))
+}
+
+/// Parse shared strings XML into MessagePack
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_shared_strings_msgpack(xml: &str) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_shared_strings_impl(xml)))
+}
+
+/// Parse styles.xml into MessagePack
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_styles_msgpack(xml: &str) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_styles_impl(xml)))
+}
+
+/// Parse workbook.xml (as `parse_workbook_full`) into MessagePack
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_workbook_msgpack(xml: &str) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_workbook_impl(xml)))
+}
+
+/// Parse a relationships file into MessagePack
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_relationships_msgpack(xml: &str) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_relationships_impl(xml)))
+}
+
+/// Parse an .ods package into MessagePack
+#[cfg(all(feature = "wasm", feature = "ods"))]
+#[wasm_bindgen]
+pub fn parse_ods_msgpack(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_ods_impl(data).map_err(|e| JsValue::from_str(&e))?))
+}
+
+/// Parse a legacy .xls workbook into MessagePack
+#[cfg(all(feature = "wasm", feature = "xls"))]
+#[wasm_bindgen]
+pub fn parse_xls_msgpack(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    trap(|| to_msgpack(&parse_xls_impl(data).map_err(|e| JsValue::from_str(&e))?))
+}
+
+#[cfg(feature = "wasm")]
+fn to_msgpack<T: Serialize>(value: &T) -> Result<Vec<u8>, JsValue> {
+    encode(value).map_err(|e| JsValue::from_str(&e.0))
+}
+
+/// Encode any serializable value as MessagePack
+pub(crate) fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut encoder = Encoder { out: Vec::new() };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+struct Encoder {
+    out: Vec<u8>,
+}
+
+/// Where a container header was written, so its count can be patched
+///
+/// Structs may skip fields and maps may not know their length up front, so
+/// the header is sized for the expected count and rewritten with the actual
+/// one when the container ends.
+struct Container<'a> {
+    encoder: &'a mut Encoder,
+    header: usize,
+    /// Header width in bytes: 1 (fix), 3 (16-bit) or 5 (32-bit)
+    width: usize,
+    count: u32,
+    map: bool,
+}
+
+impl Encoder {
+    fn uint(&mut self, n: u64) {
+        match n {
+            0..=0x7f => self.out.push(n as u8),
+            0x80..=0xff => self.out.extend_from_slice(&[0xcc, n as u8]),
+            0x100..=0xffff => {
+                self.out.push(0xcd);
+                self.out.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.out.push(0xce);
+                self.out.extend_from_slice(&(n as u32).to_be_bytes());
+            }
+            _ => {
+                self.out.push(0xcf);
+                self.out.extend_from_slice(&n.to_be_bytes());
+            }
+        }
+    }
+
+    fn int(&mut self, n: i64) {
+        if n >= 0 {
+            return self.uint(n as u64);
+        }
+        match n {
+            -32..=-1 => self.out.push(n as u8),
+            -0x80..=-33 => self.out.extend_from_slice(&[0xd0, n as u8]),
+            -0x8000..=-0x81 => {
+                self.out.push(0xd1);
+                self.out.extend_from_slice(&(n as i16).to_be_bytes());
+            }
+            -0x8000_0000..=-0x8001 => {
+                self.out.push(0xd2);
+                self.out.extend_from_slice(&(n as i32).to_be_bytes());
+            }
+            _ => {
+                self.out.push(0xd3);
+                self.out.extend_from_slice(&n.to_be_bytes());
+            }
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        let len = s.len();
+        match len {
+            0..=31 => self.out.push(0xa0 | len as u8),
+            32..=0xff => self.out.extend_from_slice(&[0xd9, len as u8]),
+            0x100..=0xffff => {
+                self.out.push(0xda);
+                self.out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            _ => {
+                self.out.push(0xdb);
+                self.out.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+        }
+        self.out.extend_from_slice(s.as_bytes());
+    }
+
+    fn container(&mut self, expected: Option<usize>, map: bool) -> Container<'_> {
+        let width = match expected {
+            Some(0..=15) => 1,
+            Some(16..=0xffff) => 3,
+            _ => 5,
+        };
+        let header = self.out.len();
+        self.out.resize(header + width, 0);
+        Container {
+            encoder: self,
+            header,
+            width,
+            count: 0,
+            map,
+        }
+    }
+}
+
+impl Container<'_> {
+    fn finish(self) -> Result<(), Error> {
+        let out = &mut self.encoder.out[self.header..self.header + self.width];
+        let count = self.count;
+        match (self.width, self.map) {
+            (1, false) if count <= 15 => out[0] = 0x90 | count as u8,
+            (1, true) if count <= 15 => out[0] = 0x80 | count as u8,
+            (3, _) if count <= 0xffff => {
+                out[0] = if self.map { 0xde } else { 0xdc };
+                out[1..].copy_from_slice(&(count as u16).to_be_bytes());
+            }
+            (5, _) => {
+                out[0] = if self.map { 0xdf } else { 0xdd };
+                out[1..].copy_from_slice(&count.to_be_bytes());
+            }
+            _ => {
+                return Err(Error(
+                    "Container length exceeded its declared size".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Encoder {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Container<'a>;
+    type SerializeTuple = Container<'a>;
+    type SerializeTupleStruct = Container<'a>;
+    type SerializeTupleVariant = Container<'a>;
+    type SerializeMap = Container<'a>;
+    type SerializeStruct = Container<'a>;
+    type SerializeStructVariant = Container<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.out.push(if v { 0xc3 } else { 0xc2 });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.int(v as i64);
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.int(v as i64);
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.int(v as i64);
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.int(v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.uint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.uint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.uint(v as u64);
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.uint(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.out.push(0xca);
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.out.push(0xcb);
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.str(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.str(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        match v.len() {
+            len @ 0..=0xff => self.out.extend_from_slice(&[0xc4, len as u8]),
+            len @ 0x100..=0xffff => {
+                self.out.push(0xc5);
+                self.out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                self.out.push(0xc6);
+                self.out.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+        }
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.out.push(0xc0);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.str(variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    /// Externally tagged, as `{ variant: value }`, matching serde_json
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.out.push(0x81);
+        self.str(variant);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Container<'a>, Error> {
+        Ok(self.container(len, false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Container<'a>, Error> {
+        Ok(self.container(Some(len), false))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Container<'a>, Error> {
+        Ok(self.container(Some(len), false))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Container<'a>, Error> {
+        self.out.push(0x81);
+        self.str(variant);
+        Ok(self.container(Some(len), false))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Container<'a>, Error> {
+        Ok(self.container(len, true))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Container<'a>, Error> {
+        Ok(self.container(Some(len), true))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Container<'a>, Error> {
+        self.out.push(0x81);
+        self.str(variant);
+        Ok(self.container(Some(len), true))
+    }
+}
+
+impl Container<'_> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.count += 1;
+        value.serialize(&mut *self.encoder)
+    }
+}
+
+impl ser::SerializeSeq for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.encoder)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.element(key)?;
+        value.serialize(&mut *self.encoder)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Container<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.element(key)?;
+        value.serialize(&mut *self.encoder)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let strings = vec!["a".to_string(), "x".repeat(40)];
+        let mut expected = vec![0x92, 0xa1, b'a', 0xd9, 40];
+        expected.extend_from_slice("x".repeat(40).as_bytes());
+        assert_eq!(encode(&strings).unwrap(), expected);
+
+        let worksheet = parse_worksheet_impl(
+            r#"<worksheet><sheetData><row r="1"><c r="A1"><v>300</v></c></row></sheetData></worksheet>"#,
+        );
+        let bytes = encode(&worksheet).unwrap();
+        // { rows: [{ row_num: 1, cells: [...] ... }], merge_cells: [], ... }
+        assert_eq!(bytes[0], 0x84);
+        assert_eq!(&bytes[1..6], b"\xa4rows");
+        assert_eq!(&bytes[6..8], &[0x91, 0x84]);
+        assert_eq!(&bytes[8..16], b"\xa7row_num");
+        assert_eq!(bytes[16], 1);
+
+        let mut large = vec![0u32; 20];
+        large[19] = 70000;
+        let bytes = encode(&large).unwrap();
+        assert_eq!(&bytes[..3], &[0xdc, 0, 20]);
+        assert_eq!(&bytes[bytes.len() - 5..], &[0xce, 0, 1, 0x11, 0x70]);
+    }
+}
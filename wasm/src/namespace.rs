@@ -0,0 +1,83 @@
+//! Namespace verification for worksheet elements
+//!
+//! Elements are normally matched by local name alone, which is fast and
+//! copes with any prefix. With `check_namespaces`, an element only counts
+//! when its prefix is bound to the SpreadsheetML namespace, so extension
+//! content with a colliding local name (a `row` in some `extLst` entry) is
+//! skipped instead of being read as sheet data.
+
+use quick_xml::events::BytesStart;
+
+const NS_MAIN: &[u8] = b"http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+/// The same namespace in ISO/IEC 29500 Strict files
+const NS_MAIN_STRICT: &[u8] = b"http://purl.oclc.org/ooxml/spreadsheetml/main";
+
+/// Prefix bindings seen so far
+///
+/// Foreign elements are skipped with their subtrees, so only declarations
+/// on SpreadsheetML elements (in practice the root) are ever recorded, and
+/// they are treated as applying to the rest of the part.
+#[derive(Default)]
+pub(crate) struct Namespaces {
+    /// Prefix (empty for the default namespace) and whether it is SpreadsheetML
+    bindings: Vec<(Vec<u8>, bool)>,
+}
+
+impl Namespaces {
+    /// Record the declarations on `element`, then report whether it is in
+    /// SpreadsheetML; unprefixed elements count when no default namespace
+    /// has been declared
+    pub(crate) fn is_spreadsheetml(&mut self, element: &BytesStart) -> bool {
+        for attr in element.attributes().flatten() {
+            let key = attr.key.as_ref();
+            let prefix = match key.strip_prefix(b"xmlns") {
+                Some(b"") => b"".as_slice(),
+                Some(rest) => match rest.strip_prefix(b":") {
+                    Some(prefix) => prefix,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let main = matches!(attr.value.as_ref(), NS_MAIN | NS_MAIN_STRICT);
+            match self.bindings.iter_mut().find(|(bound, _)| bound == prefix) {
+                Some(binding) => binding.1 = main,
+                None => self.bindings.push((prefix.to_vec(), main)),
+            }
+        }
+
+        let name = element.name();
+        let prefix = name
+            .prefix()
+            .map_or(b"".as_slice(), |prefix| prefix.into_inner());
+        match self.bindings.iter().find(|(bound, _)| bound == prefix) {
+            Some(&(_, main)) => main,
+            None => prefix.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ParserScratch;
+    use crate::{parse_worksheet_with, WorksheetParseOptions};
+
+    #[test]
+    fn test_check_namespaces() {
+        let xml = r#"<x:worksheet xmlns:x="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+            <x:sheetData><x:row r="1"><x:c r="A1"><x:v>1</x:v></x:c></x:row></x:sheetData>
+            <x:extLst><x:ext uri="{0}"><foo:data xmlns:foo="urn:foo">
+                <foo:row r="9"><foo:c r="A9"><foo:v>9</foo:v></foo:c></foo:row>
+            </foo:data></x:ext></x:extLst>
+        </x:worksheet>"#;
+        let rows = |check_namespaces| {
+            let options = WorksheetParseOptions {
+                check_namespaces,
+                ..Default::default()
+            };
+            let sheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+            sheet.rows.iter().map(|row| row.row_num).collect::<Vec<_>>()
+        };
+        assert_eq!(rows(false), vec![1, 9]);
+        assert_eq!(rows(true), vec![1]);
+    }
+}
@@ -0,0 +1,47 @@
+//! Plain Rust entry points for non-wasm hosts
+//!
+//! The `#[wasm_bindgen]` exports exchange `JsValue`s, which only work inside
+//! a wasm instance. These functions run the same parsers and return the
+//! parsed structs directly, for the N-API build in `native/` and other
+//! native callers.
+//!
+//! Only the read side is here: the XML part parsers and [`read_file`]. The
+//! writers, exports and formula tools are still reached through their
+//! `#[wasm_bindgen]` exports.
+
+use crate::context::ParserScratch;
+use crate::limits::ParseLimits;
+use crate::workbook::{read_workbook, WorkbookData};
+use crate::{
+    ParsedRelationship, ParsedStyles, ParsedWorkbook, ParsedWorksheet, WorksheetParseOptions,
+};
+
+/// Parse worksheet XML
+pub fn parse_worksheet(xml: &str, options: &WorksheetParseOptions) -> ParsedWorksheet {
+    crate::parse_worksheet_with(xml, &mut ParserScratch::default(), options)
+}
+
+/// Parse sharedStrings.xml into its strings, rich text flattened
+pub fn parse_shared_strings(xml: &str) -> Vec<String> {
+    crate::parse_shared_strings_impl(xml)
+}
+
+/// Parse styles.xml
+pub fn parse_styles(xml: &str) -> ParsedStyles {
+    crate::parse_styles_impl(xml)
+}
+
+/// Parse workbook.xml
+pub fn parse_workbook(xml: &str) -> ParsedWorkbook {
+    crate::parse_workbook_impl(xml)
+}
+
+/// Parse a relationships (.rels) part
+pub fn parse_relationships(xml: &str) -> Vec<ParsedRelationship> {
+    crate::parse_relationships_impl(xml)
+}
+
+/// Read a whole .xlsx, .ods or .xls file
+pub fn read_file(data: &[u8], limits: ParseLimits) -> Result<WorkbookData, String> {
+    read_workbook(data, limits, None).map_err(String::from)
+}
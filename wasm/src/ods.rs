@@ -0,0 +1,1147 @@
+//! OpenDocument Spreadsheet (.ods / flat .fods) reader
+//!
+//! Tables in `content.xml` are mapped onto the same `ParsedWorksheet` model
+//! the XLSX parsers produce. Cell, column and row styles from `styles.xml`
+//! and the automatic styles in `content.xml` are folded into a
+//! `ParsedStyles` table so cell `style_index` values work unchanged.
+
+use crate::encoding::decode_xml;
+use crate::limits::{LimitExceeded, ParseLimits, ReadError};
+use crate::reference::cell_ref;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{datetime_to_serial, format_number, parse_iso_date};
+use crate::zip::ZipArchive;
+use crate::{
+    ParsedBorder, ParsedCell, ParsedFill, ParsedFont, ParsedRow, ParsedSheet, ParsedStyle,
+    ParsedStyles, ParsedWorksheet,
+};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Spreadsheet limits; repeated rows/columns beyond these are not materialised
+const MAX_ROWS: u32 = 1_048_576;
+const MAX_COLS: u32 = 16_384;
+
+/// Built-in number formats used for ODS date and time cells
+const NUM_FMT_DATE: u32 = 14;
+const NUM_FMT_DATETIME: u32 = 22;
+const NUM_FMT_TIME: u32 = 21;
+
+/// Result of [`parse_ods`]
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ParsedOds {
+    pub sheets: Vec<ParsedSheet>,
+    pub styles: ParsedStyles,
+}
+
+/// Parse an .ods package (or a flat .fods XML document)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_ods(data: &[u8]) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = parse_ods_with(data, ParseLimits::default())?;
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    })
+}
+
+pub(crate) fn parse_ods_impl(data: &[u8]) -> Result<ParsedOds, String> {
+    Ok(parse_ods_with(data, ParseLimits::default())?)
+}
+
+/// Parse an ODS package, checking `limits` before each part is parsed
+pub(crate) fn parse_ods_with(data: &[u8], limits: ParseLimits) -> Result<ParsedOds, ReadError> {
+    let mut parser = OdsParser {
+        max_cells: limits.max_cells,
+        ..Default::default()
+    };
+    let mut parse = |part: &str, xml: &str| -> Result<(), ReadError> {
+        let cells = limits.check_xml(part, xml, b"table-cell")?;
+        if cells > limits.max_cells {
+            return Err(LimitExceeded::new("cell_count", limits.max_cells, Some(part)).into());
+        }
+        // Repeated cells and rows expand past the tags counted above
+        parser
+            .parse(xml)
+            .map_err(|_| LimitExceeded::new("cell_count", limits.max_cells, Some(part)))?;
+        Ok(())
+    };
+
+    if data.starts_with(b"PK") {
+        let archive = ZipArchive::new(data, limits)?;
+        if let Some(styles) = archive.read("styles.xml")? {
+            parse("styles.xml", &decode_xml(&styles)?)?;
+        }
+        let content = archive
+            .read("content.xml")?
+            .ok_or("Not an OpenDocument spreadsheet: content.xml is missing")?;
+        parse("content.xml", &decode_xml(&content)?)?;
+    } else {
+        // Flat ODF keeps styles and content in a single document
+        parse("content.xml", &decode_xml(data)?)?;
+    }
+
+    Ok(parser.finish())
+}
+
+/// Style properties collected from a `style:style` element
+#[derive(Debug, Clone, Default)]
+struct OdsStyle {
+    parent: Option<String>,
+    data_style: Option<String>,
+    column_width: Option<f64>,
+    row_height: Option<f64>,
+    optimal_row_height: bool,
+    table_hidden: bool,
+    font: ParsedFont,
+    has_font: bool,
+    background: Option<String>,
+    border: ParsedBorder,
+    has_border: bool,
+    horizontal: Option<String>,
+    vertical: Option<String>,
+    wrap_text: bool,
+}
+
+/// Kind of value a date/time data style formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DataStyleKind {
+    Date,
+    Time,
+}
+
+/// A column definition covering `start..=end`
+struct ColumnDef {
+    start: u32,
+    end: u32,
+    width: Option<f64>,
+    default_cell_style: Option<String>,
+}
+
+/// A cell being collected between its start and end tags
+#[derive(Default)]
+struct PendingCell {
+    value_type: Option<String>,
+    value: Option<String>,
+    date_value: Option<String>,
+    time_value: Option<String>,
+    boolean_value: Option<String>,
+    string_value: Option<String>,
+    formula: Option<String>,
+    style: Option<String>,
+    repeat: u32,
+    col_span: u32,
+    row_span: u32,
+    text: String,
+    paragraphs: u32,
+}
+
+/// A row being collected between its start and end tags
+struct PendingRow {
+    repeat: u32,
+    style: Option<String>,
+    hidden: bool,
+    cells: Vec<(u32, ParsedCell)>,
+    /// (first column, last column, rows spanned)
+    merges: Vec<(u32, u32, u32)>,
+    col: u32,
+}
+
+struct PendingSheet {
+    name: String,
+    style: Option<String>,
+    columns: Vec<ColumnDef>,
+    next_col: u32,
+    rows: Vec<ParsedRow>,
+    merges: Vec<String>,
+    next_row: u32,
+    max_col: u32,
+}
+
+#[derive(Default)]
+struct OdsParser {
+    styles: HashMap<String, OdsStyle>,
+    data_styles: HashMap<String, DataStyleKind>,
+    sheets: Vec<ParsedSheet>,
+    parsed_styles: ParsedStyles,
+    /// (cell style name, number format) -> cellXfs index
+    xf_indices: HashMap<(Option<String>, u32), u32>,
+    /// Cells materialised so far, repetitions included, and the most allowed
+    cells: u64,
+    max_cells: u64,
+}
+
+/// More cells than `max_cells` once repetitions are expanded
+struct TooManyCells;
+
+/// Attribute values keyed by local name, ignoring the LibreOffice extension namespaces
+fn attributes(e: &BytesStart) -> HashMap<Vec<u8>, String> {
+    let mut attrs = HashMap::new();
+    for attr in e.attributes().flatten() {
+        let key = attr.key.as_ref();
+        if key.starts_with(b"calcext:") || key.starts_with(b"loext:") {
+            continue;
+        }
+        if let Ok(value) = attr.unescape_value() {
+            attrs.insert(attr.key.local_name().as_ref().to_vec(), value.into_owned());
+        }
+    }
+    attrs
+}
+
+fn attr<'a>(attrs: &'a HashMap<Vec<u8>, String>, name: &[u8]) -> Option<&'a str> {
+    attrs.get(name).map(String::as_str)
+}
+
+fn repeat_count(attrs: &HashMap<Vec<u8>, String>, name: &[u8]) -> u32 {
+    attr(attrs, name)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Convert an ODF length ("2.258cm", "0.1665in", "12pt") to points
+fn length_to_points(length: &str) -> Option<f64> {
+    let split = length
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(length.len());
+    let value: f64 = length[..split].trim().parse().ok()?;
+    let factor = match &length[split..] {
+        "pt" | "" => 1.0,
+        "cm" => 72.0 / 2.54,
+        "mm" => 72.0 / 25.4,
+        "in" => 72.0,
+        "pc" => 12.0,
+        "px" => 0.75,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+/// Convert a column width in points to Excel character units
+fn points_to_char_width(points: f64) -> f64 {
+    let px = points * 96.0 / 72.0;
+    (((px - 5.0) / 7.0).max(0.0) * 100.0).round() / 100.0
+}
+
+/// Convert "#rrggbb" to the ARGB form used by `ParsedStyles`
+fn argb(color: &str) -> Option<String> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(format!("FF{}", hex.to_ascii_uppercase()))
+    } else {
+        None
+    }
+}
+
+/// Map an ODF border ("0.74pt solid #000000") to an Excel style and color
+fn parse_border(spec: &str) -> (Option<String>, Option<String>) {
+    if spec == "none" || spec.is_empty() {
+        return (None, None);
+    }
+    let mut width = 0.75;
+    let mut line = "solid";
+    let mut color = None;
+    for part in spec.split_whitespace() {
+        if part.starts_with('#') {
+            color = argb(part);
+        } else if let Some(points) = length_to_points(part) {
+            width = points;
+        } else {
+            line = part;
+        }
+    }
+    let style = match line {
+        "double" => "double",
+        "dotted" => "dotted",
+        "dashed" if width >= 1.5 => "mediumDashed",
+        "dashed" => "dashed",
+        "none" | "hidden" => return (None, None),
+        _ if width >= 2.5 => "thick",
+        _ if width >= 1.5 => "medium",
+        _ => "thin",
+    };
+    (Some(style.to_string()), color)
+}
+
+/// Parse an ISO 8601 duration ("PT12H30M15S") as a fraction of a day
+fn parse_duration(value: &str) -> Option<f64> {
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let (days_part, time_part) = rest.split_once('T').unwrap_or((rest, ""));
+
+    let mut seconds = 0.0;
+    if let Some(days) = days_part.strip_suffix('D') {
+        seconds += days.parse::<f64>().ok()? * 86400.0;
+    } else if !days_part.is_empty() {
+        return None;
+    }
+
+    let mut number = String::new();
+    for ch in time_part.chars() {
+        let factor = match ch {
+            'H' => 3600.0,
+            'M' => 60.0,
+            'S' => 1.0,
+            _ => {
+                number.push(ch);
+                continue;
+            }
+        };
+        seconds += number.parse::<f64>().ok()? * factor;
+        number.clear();
+    }
+
+    let days = seconds / 86400.0;
+    Some(if negative { -days } else { days })
+}
+
+/// Convert an OpenFormula expression ("of:=SUM([.A1:.B2])") to Excel syntax
+pub(crate) fn ods_formula_to_a1(formula: &str) -> String {
+    let body = formula
+        .split_once(":=")
+        .map(|(_, body)| body)
+        .or_else(|| formula.strip_prefix('='))
+        .unwrap_or(formula);
+
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                out.push('"');
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                let mut reference = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    reference.push(c);
+                }
+                out.push_str(&ods_reference_to_a1(&reference));
+            }
+            ';' => out.push(','),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Convert ".A1", "Sheet2.A1:.B3" or "$'My Sheet'.A1" to A1 syntax
+fn ods_reference_to_a1(reference: &str) -> String {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for ch in reference.chars() {
+        match ch {
+            '\'' => {
+                in_quotes = !in_quotes;
+                current.push(ch);
+            }
+            ':' if !in_quotes => parts.push(std::mem::take(&mut current)),
+            _ => current.push(ch),
+        }
+    }
+    parts.push(current);
+
+    let mut out = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            out.push(':');
+        }
+        // The cell address follows the last dot outside quotes
+        let mut split = None;
+        let mut in_quotes = false;
+        for (pos, ch) in part.char_indices() {
+            match ch {
+                '\'' => in_quotes = !in_quotes,
+                '.' if !in_quotes => split = Some(pos),
+                _ => {}
+            }
+        }
+        let (sheet, cell) = match split {
+            Some(pos) => (&part[..pos], &part[pos + 1..]),
+            None => ("", part.as_str()),
+        };
+        let sheet = sheet.trim_start_matches('$');
+        if !sheet.is_empty() && i == 0 {
+            out.push_str(sheet);
+            out.push('!');
+        }
+        out.push_str(cell);
+    }
+    out
+}
+
+impl OdsParser {
+    fn parse(&mut self, xml: &str) -> Result<(), TooManyCells> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(false);
+
+        let mut buf = Vec::new();
+        let mut current_style: Option<(String, OdsStyle)> = None;
+        let mut current_data_style: Option<(String, DataStyleKind)> = None;
+        let mut sheet: Option<PendingSheet> = None;
+        let mut row: Option<PendingRow> = None;
+        let mut cell: Option<PendingCell> = None;
+        let mut annotation_depth = 0u32;
+
+        loop {
+            let event = reader.read_event_into(&mut buf);
+            let (e, is_empty) = match event {
+                Ok(Event::Start(ref e)) => (Some(e), false),
+                Ok(Event::Empty(ref e)) => (Some(e), true),
+                _ => (None, false),
+            };
+
+            if let Some(e) = e {
+                let name = e.local_name();
+                let name = name.as_ref();
+
+                if annotation_depth > 0 {
+                    if !is_empty {
+                        annotation_depth += 1;
+                    }
+                } else {
+                    match name {
+                        b"style" => {
+                            let attrs = attributes(e);
+                            if let Some(style_name) = attr(&attrs, b"name") {
+                                let style = OdsStyle {
+                                    parent: attr(&attrs, b"parent-style-name").map(String::from),
+                                    data_style: attr(&attrs, b"data-style-name").map(String::from),
+                                    ..Default::default()
+                                };
+                                if is_empty {
+                                    self.styles.insert(style_name.to_string(), style);
+                                } else {
+                                    current_style = Some((style_name.to_string(), style));
+                                }
+                            }
+                        }
+                        b"date-style" | b"time-style" => {
+                            let attrs = attributes(e);
+                            if let Some(style_name) = attr(&attrs, b"name") {
+                                let kind = if name == b"date-style" {
+                                    DataStyleKind::Date
+                                } else {
+                                    DataStyleKind::Time
+                                };
+                                if is_empty {
+                                    self.data_styles.insert(style_name.to_string(), kind);
+                                } else {
+                                    current_data_style = Some((style_name.to_string(), kind));
+                                }
+                            }
+                        }
+                        b"table-column-properties"
+                        | b"table-row-properties"
+                        | b"table-properties"
+                        | b"text-properties"
+                        | b"table-cell-properties"
+                        | b"paragraph-properties"
+                            if current_style.is_some() =>
+                        {
+                            if let Some((_, ref mut style)) = current_style {
+                                apply_style_properties(style, name, &attributes(e));
+                            }
+                        }
+                        b"table" => {
+                            let attrs = attributes(e);
+                            sheet = Some(PendingSheet {
+                                name: attr(&attrs, b"name").unwrap_or("Sheet").to_string(),
+                                style: attr(&attrs, b"style-name").map(String::from),
+                                columns: Vec::new(),
+                                next_col: 1,
+                                rows: Vec::new(),
+                                merges: Vec::new(),
+                                next_row: 1,
+                                max_col: 0,
+                            });
+                        }
+                        b"table-column" => {
+                            if let Some(ref mut sheet) = sheet {
+                                let attrs = attributes(e);
+                                let repeat = repeat_count(&attrs, b"number-columns-repeated");
+                                let start = sheet.next_col;
+                                let end = start.saturating_add(repeat - 1).min(MAX_COLS);
+                                let width = attr(&attrs, b"style-name")
+                                    .and_then(|s| self.resolve(s, |st| st.column_width));
+                                sheet.columns.push(ColumnDef {
+                                    start,
+                                    end,
+                                    width,
+                                    default_cell_style: attr(&attrs, b"default-cell-style-name")
+                                        .map(String::from),
+                                });
+                                sheet.next_col = end.saturating_add(1);
+                            }
+                        }
+                        b"table-row" if sheet.is_some() => {
+                            let attrs = attributes(e);
+                            let pending = PendingRow {
+                                repeat: repeat_count(&attrs, b"number-rows-repeated"),
+                                style: attr(&attrs, b"style-name").map(String::from),
+                                hidden: matches!(
+                                    attr(&attrs, b"visibility"),
+                                    Some("collapse") | Some("filter")
+                                ),
+                                cells: Vec::new(),
+                                merges: Vec::new(),
+                                col: 1,
+                            };
+                            if is_empty {
+                                if let Some(ref mut sheet) = sheet {
+                                    self.finish_row(sheet, pending)?;
+                                }
+                            } else {
+                                row = Some(pending);
+                            }
+                        }
+                        b"table-cell" | b"covered-table-cell" if row.is_some() => {
+                            let attrs = attributes(e);
+                            let pending = PendingCell {
+                                value_type: attr(&attrs, b"value-type").map(String::from),
+                                value: attr(&attrs, b"value").map(String::from),
+                                date_value: attr(&attrs, b"date-value").map(String::from),
+                                time_value: attr(&attrs, b"time-value").map(String::from),
+                                boolean_value: attr(&attrs, b"boolean-value").map(String::from),
+                                string_value: attr(&attrs, b"string-value").map(String::from),
+                                formula: attr(&attrs, b"formula").map(String::from),
+                                style: attr(&attrs, b"style-name").map(String::from),
+                                repeat: repeat_count(&attrs, b"number-columns-repeated"),
+                                col_span: repeat_count(&attrs, b"number-columns-spanned"),
+                                row_span: repeat_count(&attrs, b"number-rows-spanned"),
+                                ..Default::default()
+                            };
+                            if is_empty {
+                                if let (Some(sheet), Some(row)) = (sheet.as_ref(), row.as_mut()) {
+                                    self.finish_cell(sheet, row, pending)?;
+                                }
+                            } else {
+                                cell = Some(pending);
+                            }
+                        }
+                        b"annotation" if cell.is_some() && !is_empty => {
+                            annotation_depth = 1;
+                        }
+                        b"p" | b"h" => {
+                            if let Some(ref mut cell) = cell {
+                                if cell.paragraphs > 0 {
+                                    cell.text.push('\n');
+                                }
+                                cell.paragraphs += 1;
+                            }
+                        }
+                        b"s" => {
+                            if let Some(ref mut cell) = cell {
+                                let count = repeat_count(&attributes(e), b"c") as usize;
+                                cell.text.extend(std::iter::repeat_n(' ', count.min(1024)));
+                            }
+                        }
+                        b"tab" => {
+                            if let Some(ref mut cell) = cell {
+                                cell.text.push('\t');
+                            }
+                        }
+                        b"line-break" => {
+                            if let Some(ref mut cell) = cell {
+                                cell.text.push('\n');
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            } else {
+                match event {
+                    Ok(Event::Text(e)) if cell.is_some() && annotation_depth == 0 => {
+                        if let (Some(ref mut cell), Ok(text)) = (&mut cell, e.unescape()) {
+                            if cell.paragraphs > 0 {
+                                cell.text.push_str(&text);
+                            }
+                        }
+                    }
+                    Ok(Event::End(ref e)) => {
+                        if annotation_depth > 0 {
+                            annotation_depth -= 1;
+                        } else {
+                            match e.local_name().as_ref() {
+                                b"style" => {
+                                    if let Some((name, style)) = current_style.take() {
+                                        self.styles.insert(name, style);
+                                    }
+                                }
+                                b"date-style" | b"time-style" => {
+                                    if let Some((name, kind)) = current_data_style.take() {
+                                        self.data_styles.insert(name, kind);
+                                    }
+                                }
+                                b"table-cell" | b"covered-table-cell" => {
+                                    if let (Some(sheet), Some(row), Some(pending)) =
+                                        (sheet.as_ref(), row.as_mut(), cell.take())
+                                    {
+                                        self.finish_cell(sheet, row, pending)?;
+                                    }
+                                }
+                                b"table-row" => {
+                                    if let (Some(sheet), Some(pending)) =
+                                        (sheet.as_mut(), row.take())
+                                    {
+                                        self.finish_row(sheet, pending)?;
+                                    }
+                                }
+                                b"table" => {
+                                    if let Some(pending) = sheet.take() {
+                                        self.finish_sheet(pending);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    Ok(Event::Eof) => break,
+                    Err(_) => break,
+                    _ => {}
+                }
+            }
+            buf.clear();
+        }
+        Ok(())
+    }
+
+    /// Look up a style property, following `parent-style-name` links
+    fn resolve<T>(&self, name: &str, get: impl Fn(&OdsStyle) -> Option<T>) -> Option<T> {
+        let mut current = self.styles.get(name);
+        // Guard against cyclic parent links
+        for _ in 0..16 {
+            let style = current?;
+            if let Some(value) = get(style) {
+                return Some(value);
+            }
+            current = style.parent.as_deref().and_then(|p| self.styles.get(p));
+        }
+        None
+    }
+
+    /// Merge a cell style with its ancestors, nearest definition winning
+    fn flatten_style(&self, name: &str) -> OdsStyle {
+        let mut chain = Vec::new();
+        let mut current = self.styles.get(name);
+        while let Some(style) = current {
+            if chain.len() == 16 {
+                break;
+            }
+            chain.push(style);
+            current = style.parent.as_deref().and_then(|p| self.styles.get(p));
+        }
+
+        let mut merged = OdsStyle::default();
+        for style in chain.into_iter().rev() {
+            if style.has_font {
+                merged.font = style.font.clone();
+                merged.has_font = true;
+            }
+            if style.has_border {
+                merged.border = style.border.clone();
+                merged.has_border = true;
+            }
+            merged.data_style = style.data_style.clone().or(merged.data_style);
+            merged.background = style.background.clone().or(merged.background);
+            merged.horizontal = style.horizontal.clone().or(merged.horizontal);
+            merged.vertical = style.vertical.clone().or(merged.vertical);
+            merged.wrap_text |= style.wrap_text;
+        }
+        merged
+    }
+
+    /// The cellXfs index for a cell style combined with a number format
+    fn xf_index(&mut self, style_name: Option<&str>, num_fmt_id: u32) -> Option<u32> {
+        if style_name.is_none() && num_fmt_id == 0 {
+            return None;
+        }
+        let key = (style_name.map(String::from), num_fmt_id);
+        if let Some(&index) = self.xf_indices.get(&key) {
+            return Some(index);
+        }
+
+        let styles = &mut self.parsed_styles;
+        if styles.cell_xfs.is_empty() {
+            styles.cell_xfs.push(ParsedStyle::default());
+            styles.fonts.push(ParsedFont::default());
+            for pattern in ["none", "gray125"] {
+                styles.fills.push(ParsedFill {
+                    pattern_type: Some(pattern.to_string()),
+                    fg_color: None,
+                    bg_color: None,
+                });
+            }
+            styles.borders.push(ParsedBorder::default());
+        }
+
+        let mut xf = ParsedStyle {
+            num_fmt_id: Some(num_fmt_id),
+            apply_number_format: num_fmt_id != 0,
+            ..Default::default()
+        };
+        if let Some(name) = style_name {
+            let style = self.flatten_style(name);
+            let styles = &mut self.parsed_styles;
+            if style.has_font {
+                xf.font_id = Some(styles.fonts.len() as u32);
+                xf.apply_font = true;
+                styles.fonts.push(style.font);
+            }
+            if let Some(color) = style.background {
+                xf.fill_id = Some(styles.fills.len() as u32);
+                xf.apply_fill = true;
+                styles.fills.push(ParsedFill {
+                    pattern_type: Some("solid".to_string()),
+                    fg_color: Some(color),
+                    bg_color: None,
+                });
+            }
+            if style.has_border {
+                xf.border_id = Some(styles.borders.len() as u32);
+                xf.apply_border = true;
+                styles.borders.push(style.border);
+            }
+            xf.apply_alignment =
+                style.horizontal.is_some() || style.vertical.is_some() || style.wrap_text;
+            xf.horizontal = style.horizontal;
+            xf.vertical = style.vertical;
+            xf.wrap_text = style.wrap_text;
+        }
+
+        let styles = &mut self.parsed_styles;
+        let index = styles.cell_xfs.len() as u32;
+        styles.cell_xfs.push(xf);
+        self.xf_indices.insert(key, index);
+        Some(index)
+    }
+
+    fn finish_cell(
+        &mut self,
+        sheet: &PendingSheet,
+        row: &mut PendingRow,
+        pending: PendingCell,
+    ) -> Result<(), TooManyCells> {
+        let col = row.col;
+        row.col = row.col.saturating_add(pending.repeat);
+
+        let style_name = pending.style.clone().or_else(|| {
+            sheet
+                .columns
+                .iter()
+                .find(|c| col >= c.start && col <= c.end)
+                .and_then(|c| c.default_cell_style.clone())
+        });
+        let data_style = style_name
+            .as_deref()
+            .and_then(|s| self.resolve(s, |st| st.data_style.clone()))
+            .and_then(|d| self.data_styles.get(&d).copied());
+
+        let text = || {
+            pending
+                .string_value
+                .clone()
+                .unwrap_or_else(|| pending.text.clone())
+        };
+        let (cell_type, value, num_fmt_id) = match pending.value_type.as_deref() {
+            Some("float") | Some("percentage") | Some("currency") => {
+                (None, pending.value.clone(), 0)
+            }
+            Some("date") => {
+                let dt = pending.date_value.as_deref().and_then(parse_iso_date);
+                let has_time = dt.is_some_and(|dt| dt.hour + dt.minute + dt.second > 0);
+                let format = if has_time {
+                    NUM_FMT_DATETIME
+                } else {
+                    NUM_FMT_DATE
+                };
+                (
+                    None,
+                    dt.map(|dt| format_number(datetime_to_serial(&dt))),
+                    format,
+                )
+            }
+            Some("time") => (
+                None,
+                pending
+                    .time_value
+                    .as_deref()
+                    .and_then(parse_duration)
+                    .map(format_number),
+                NUM_FMT_TIME,
+            ),
+            Some("boolean") => {
+                let value = matches!(pending.boolean_value.as_deref(), Some("true") | Some("1"));
+                (
+                    Some("b".to_string()),
+                    Some(if value { "1" } else { "0" }.to_string()),
+                    0,
+                )
+            }
+            Some("string") => (Some("inlineStr".to_string()), Some(text()), 0),
+            _ if !pending.text.is_empty() => (Some("inlineStr".to_string()), Some(text()), 0),
+            _ => (None, None, 0),
+        };
+
+        // A custom date style on a float cell still marks it as a date
+        let num_fmt_id = match (num_fmt_id, data_style) {
+            (0, Some(DataStyleKind::Date)) if cell_type.is_none() && value.is_some() => {
+                NUM_FMT_DATE
+            }
+            (0, Some(DataStyleKind::Time)) if cell_type.is_none() && value.is_some() => {
+                NUM_FMT_TIME
+            }
+            (id, _) => id,
+        };
+
+        let formula = pending.formula.as_deref().map(ods_formula_to_a1);
+        if value.is_none() && formula.is_none() {
+            // Styled empty cells are kept only when explicitly styled
+            if pending.style.is_none() || pending.repeat > 1 {
+                self.record_merge(row, col, &pending);
+                return Ok(());
+            }
+        }
+
+        let style_index = self.xf_index(style_name.as_deref(), num_fmt_id);
+        let repeat = pending.repeat.min((MAX_COLS + 1).saturating_sub(col));
+        if self.cells + (row.cells.len() + repeat as usize) as u64 > self.max_cells {
+            return Err(TooManyCells);
+        }
+        for offset in 0..repeat {
+            row.cells.push((
+                col + offset,
+                ParsedCell {
+                    reference: String::new(),
+                    cell_type: cell_type.clone(),
+                    style_index,
+                    value: value.clone(),
+                    formula: formula.clone(),
+                },
+            ));
+        }
+        self.record_merge(row, col, &pending);
+        Ok(())
+    }
+
+    /// Note a spanned cell's merge, clipped to the sheet
+    fn record_merge(&self, row: &mut PendingRow, col: u32, pending: &PendingCell) {
+        if (pending.col_span > 1 || pending.row_span > 1) && col <= MAX_COLS {
+            let end = col.saturating_add(pending.col_span - 1).min(MAX_COLS);
+            row.merges.push((col, end, pending.row_span));
+        }
+    }
+
+    fn finish_row(
+        &mut self,
+        sheet: &mut PendingSheet,
+        pending: PendingRow,
+    ) -> Result<(), TooManyCells> {
+        let start = sheet.next_row;
+        sheet.next_row = start.saturating_add(pending.repeat);
+        if pending.cells.is_empty() && pending.merges.is_empty() {
+            return Ok(());
+        }
+
+        let height = pending.style.as_deref().and_then(|name| {
+            self.resolve(name, |s| {
+                if s.optimal_row_height {
+                    None
+                } else {
+                    s.row_height
+                }
+            })
+        });
+
+        // Merges are repeated with their row, so they count as cells too
+        let repeat = pending.repeat.min((MAX_ROWS + 1).saturating_sub(start));
+        self.cells += repeat as u64 * (pending.cells.len() + pending.merges.len()) as u64;
+        if self.cells > self.max_cells {
+            return Err(TooManyCells);
+        }
+        for offset in 0..repeat {
+            let row_num = start + offset;
+            let cells = pending
+                .cells
+                .iter()
+                .map(|(col, cell)| {
+                    sheet.max_col = sheet.max_col.max(*col);
+                    ParsedCell {
+                        reference: cell_ref(row_num, *col),
+                        cell_type: cell.cell_type.clone(),
+                        style_index: cell.style_index,
+                        value: cell.value.clone(),
+                        formula: cell.formula.clone(),
+                    }
+                })
+                .collect();
+            for &(col, end_col, rows) in &pending.merges {
+                let end_row = row_num.saturating_add(rows - 1).min(MAX_ROWS);
+                sheet.merges.push(format!(
+                    "{}:{}",
+                    cell_ref(row_num, col),
+                    cell_ref(end_row, end_col)
+                ));
+            }
+            sheet.rows.push(ParsedRow {
+                row_num,
+                cells,
+                height,
+                hidden: pending.hidden,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish_sheet(&mut self, pending: PendingSheet) {
+        let mut col_widths = HashMap::new();
+        for column in &pending.columns {
+            if let Some(width) = column.width {
+                // Trailing repeated columns are only kept within the used range
+                for col in column.start..=column.end.min(pending.max_col) {
+                    col_widths.insert(col, points_to_char_width(width));
+                }
+            }
+        }
+
+        let hidden = pending
+            .style
+            .as_deref()
+            .and_then(|s| self.resolve(s, |st| st.table_hidden.then_some(true)))
+            .unwrap_or(false);
+
+        self.sheets.push(ParsedSheet {
+            name: pending.name,
+            state: hidden.then(|| "hidden".to_string()),
+            worksheet: ParsedWorksheet {
+                rows: pending.rows,
+                merge_cells: pending.merges,
+                col_widths,
+                ..Default::default()
+            },
+        });
+    }
+
+    fn finish(self) -> ParsedOds {
+        ParsedOds {
+            sheets: self.sheets,
+            styles: self.parsed_styles,
+        }
+    }
+}
+
+fn apply_style_properties(style: &mut OdsStyle, element: &[u8], attrs: &HashMap<Vec<u8>, String>) {
+    match element {
+        b"table-column-properties" => {
+            style.column_width = attr(attrs, b"column-width").and_then(length_to_points);
+        }
+        b"table-row-properties" => {
+            style.row_height = attr(attrs, b"row-height").and_then(length_to_points);
+            style.optimal_row_height = attr(attrs, b"use-optimal-row-height") == Some("true");
+        }
+        b"table-properties" => {
+            style.table_hidden = attr(attrs, b"display") == Some("false");
+        }
+        b"text-properties" => {
+            let font = &mut style.font;
+            if let Some(weight) = attr(attrs, b"font-weight") {
+                font.bold = weight == "bold" || weight.parse::<u32>().is_ok_and(|w| w >= 600);
+            }
+            if let Some(font_style) = attr(attrs, b"font-style") {
+                font.italic = font_style == "italic" || font_style == "oblique";
+            }
+            if let Some(underline) = attr(attrs, b"text-underline-style") {
+                font.underline = underline != "none";
+            }
+            if let Some(strike) = attr(attrs, b"text-line-through-style") {
+                font.strikethrough = strike != "none";
+            }
+            if let Some(size) = attr(attrs, b"font-size").and_then(length_to_points) {
+                font.size = Some(size);
+            }
+            if let Some(color) = attr(attrs, b"color").and_then(argb) {
+                font.color = Some(color);
+            }
+            if let Some(name) = attr(attrs, b"font-name") {
+                font.name = Some(name.to_string());
+            }
+            style.has_font = true;
+        }
+        b"table-cell-properties" => {
+            style.background = attr(attrs, b"background-color").and_then(argb);
+            style.wrap_text = attr(attrs, b"wrap-option") == Some("wrap");
+            style.vertical = match attr(attrs, b"vertical-align") {
+                Some("top") => Some("top".to_string()),
+                Some("middle") => Some("center".to_string()),
+                Some("bottom") => Some("bottom".to_string()),
+                _ => None,
+            };
+
+            let all = attr(attrs, b"border").map(parse_border);
+            let side = |name: &[u8]| attr(attrs, name).map(parse_border).or(all.clone());
+            let border = &mut style.border;
+            if let Some((s, c)) = side(b"border-left") {
+                (border.left_style, border.left_color) = (s, c);
+            }
+            if let Some((s, c)) = side(b"border-right") {
+                (border.right_style, border.right_color) = (s, c);
+            }
+            if let Some((s, c)) = side(b"border-top") {
+                (border.top_style, border.top_color) = (s, c);
+            }
+            if let Some((s, c)) = side(b"border-bottom") {
+                (border.bottom_style, border.bottom_color) = (s, c);
+            }
+            style.has_border = border.left_style.is_some()
+                || border.right_style.is_some()
+                || border.top_style.is_some()
+                || border.bottom_style.is_some();
+        }
+        b"paragraph-properties" => {
+            style.horizontal = match attr(attrs, b"text-align") {
+                Some("start") | Some("left") => Some("left".to_string()),
+                Some("center") => Some("center".to_string()),
+                Some("end") | Some("right") => Some("right".to_string()),
+                Some("justify") => Some("justify".to_string()),
+                _ => None,
+            };
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::{ZipWriter, DEFAULT_COMPRESSION_LEVEL};
+
+    const CONTENT: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:of="urn:oasis:names:tc:opendocument:xmlns:of:1.2" xmlns:calcext="urn:org:documentfoundation:names:experimental:calc:xmlns:calcext:1.0"><office:automatic-styles><style:style style:name="co1" style:family="table-column"><style:table-column-properties style:column-width="2.258cm"/></style:style><style:style style:name="ro1" style:family="table-row"><style:table-row-properties style:row-height="0.8cm" style:use-optimal-row-height="false"/></style:style><style:style style:name="ta2" style:family="table"><style:table-properties table:display="false"/></style:style><style:style style:name="ce1" style:family="table-cell" style:parent-style-name="Default"><style:table-cell-properties fo:background-color="#ffff00"/><style:text-properties fo:font-weight="bold"/></style:style></office:automatic-styles><office:body><office:spreadsheet><table:table table:name="Data"><table:table-column table:style-name="co1" table:number-columns-repeated="1024"/><table:table-row table:style-name="ro1"><table:table-cell table:style-name="ce1" office:value-type="string" calcext:value-type="string" table:number-columns-spanned="2"><text:p>Hello<text:s text:c="2"/>World</text:p><text:p>Line 2</text:p></table:table-cell><table:covered-table-cell/><table:table-cell office:value-type="float" office:value="1.5"><text:p>1.5</text:p><office:annotation><text:p>note</text:p></office:annotation></table:table-cell></table:table-row><table:table-row table:number-rows-repeated="2"><table:table-cell office:value-type="date" office:date-value="2024-01-01"><text:p>01/01/24</text:p></table:table-cell><table:table-cell office:value-type="boolean" office:boolean-value="true" table:number-columns-repeated="2"/><table:table-cell table:formula="of:=SUM([.C1];[Other.A1:.A3])" office:value-type="float" office:value="7"/></table:table-row><table:table-row table:number-rows-repeated="1048572"><table:table-cell table:number-columns-repeated="1024"/></table:table-row></table:table><table:table table:name="Other" table:style-name="ta2"><table:table-row><table:table-cell office:value-type="time" office:time-value="PT12H00M00S"/></table:table-row></table:table></office:spreadsheet></office:body></office:document-content>"##;
+
+    #[test]
+    fn test_parse_ods() {
+        let mut zip = ZipWriter::new();
+        zip.add_file(
+            "mimetype",
+            b"application/vnd.oasis.opendocument.spreadsheet",
+            0,
+        );
+        zip.add_file("content.xml", CONTENT.as_bytes(), DEFAULT_COMPRESSION_LEVEL);
+        let ods = parse_ods_impl(&zip.finish()).unwrap();
+
+        assert_eq!(ods.sheets.len(), 2);
+        let data = &ods.sheets[0];
+        assert_eq!(data.name, "Data");
+        assert_eq!(data.state, None);
+        let ws = &data.worksheet;
+        assert_eq!(ws.rows.len(), 3);
+        assert_eq!(ws.merge_cells, vec!["A1:B1".to_string()]);
+        assert_eq!(ws.col_widths.len(), 4);
+        assert!((ws.col_widths[&1] - 11.48).abs() < 0.01);
+
+        let first = &ws.rows[0];
+        assert!((first.height.unwrap() - 22.68).abs() < 0.01);
+        assert_eq!(first.cells[0].reference, "A1");
+        assert_eq!(
+            first.cells[0].value.as_deref(),
+            Some("Hello  World\nLine 2")
+        );
+        assert_eq!(first.cells[1].reference, "C1");
+        assert_eq!(first.cells[1].value.as_deref(), Some("1.5"));
+
+        let header_xf = &ods.styles.cell_xfs[first.cells[0].style_index.unwrap() as usize];
+        assert!(ods.styles.fonts[header_xf.font_id.unwrap() as usize].bold);
+        assert_eq!(
+            ods.styles.fills[header_xf.fill_id.unwrap() as usize]
+                .fg_color
+                .as_deref(),
+            Some("FFFFFF00")
+        );
+
+        for (row, row_num) in ws.rows[1..].iter().zip([2, 3]) {
+            assert_eq!(row.row_num, row_num);
+            assert_eq!(row.cells.len(), 4);
+            assert_eq!(row.cells[0].value.as_deref(), Some("45292"));
+            let xf = &ods.styles.cell_xfs[row.cells[0].style_index.unwrap() as usize];
+            assert_eq!(xf.num_fmt_id, Some(NUM_FMT_DATE));
+            assert_eq!(row.cells[2].cell_type.as_deref(), Some("b"));
+            assert_eq!(row.cells[3].formula.as_deref(), Some("SUM(C1,Other!A1:A3)"));
+        }
+
+        let other = &ods.sheets[1];
+        assert_eq!(other.state.as_deref(), Some("hidden"));
+        assert_eq!(
+            other.worksheet.rows[0].cells[0].value.as_deref(),
+            Some("0.5")
+        );
+    }
+
+    #[test]
+    fn test_repeated_cells_limit() {
+        // About 200 bytes expanding to 1,048,576 x 16,384 cells
+        let xml = r#"<office:document-content xmlns:office="o" xmlns:table="t"><office:body>
+            <office:spreadsheet><table:table table:name="Big">
+            <table:table-row table:number-rows-repeated="1048576"><table:table-cell office:value-type="float" office:value="0" table:number-columns-repeated="16384"/></table:table-row>
+            </table:table></office:spreadsheet></office:body></office:document-content>"#;
+        let result = parse_ods_with(xml.as_bytes(), ParseLimits::default());
+        let Err(ReadError::Limit(limit)) = result else {
+            panic!("expected a cell_count limit");
+        };
+        assert_eq!(limit.limit, "cell_count");
+        assert_eq!(limit.part.as_deref(), Some("content.xml"));
+    }
+
+    #[test]
+    fn test_repeated_merges_limit() {
+        // An empty spanned cell makes no cells, only a merge per row
+        let xml = r#"<office:document-content xmlns:office="o" xmlns:table="t"><office:body>
+            <office:spreadsheet><table:table table:name="Merged">
+            <table:table-row table:number-rows-repeated="1048576"><table:table-cell table:number-columns-spanned="2"/></table:table-row>
+            </table:table></office:spreadsheet></office:body></office:document-content>"#;
+        let limits = ParseLimits {
+            max_cells: 100_000,
+            ..Default::default()
+        };
+        let Err(ReadError::Limit(limit)) = parse_ods_with(xml.as_bytes(), limits) else {
+            panic!("expected a cell_count limit");
+        };
+        assert_eq!(limit.limit, "cell_count");
+    }
+
+    #[test]
+    fn test_oversized_spans() {
+        let xml = r#"<office:document-content xmlns:office="o" xmlns:table="t"><office:body>
+            <office:spreadsheet><table:table table:name="Spans"><table:table-row>
+            <table:table-cell table:number-columns-repeated="2"/>
+            <table:table-cell office:value-type="float" office:value="1" table:number-columns-spanned="4294967295" table:number-rows-spanned="4294967295"/>
+            </table:table-row></table:table></office:spreadsheet></office:body></office:document-content>"#;
+        let ods = parse_ods_with(xml.as_bytes(), ParseLimits::default()).unwrap();
+        assert_eq!(ods.sheets[0].worksheet.merge_cells, ["C1:XFD1048576"]);
+    }
+
+    #[test]
+    fn test_ods_formula_to_a1() {
+        assert_eq!(ods_formula_to_a1("of:=[.A1]+[.B2]"), "A1+B2");
+        assert_eq!(
+            ods_formula_to_a1("of:=IF([$'My Sheet'.$A$1]>0;\"[x]\";1)"),
+            "IF('My Sheet'!$A$1>0,\"[x]\",1)"
+        );
+    }
+}
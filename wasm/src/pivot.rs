@@ -0,0 +1,327 @@
+//! Pivot tables
+//!
+//! Groups rows by the values of row fields and column fields and aggregates
+//! value fields in each cell of the resulting table, with totals per row,
+//! per column and overall, as a spreadsheet pivot table shows them. Labels
+//! are sorted ascending: numbers, then text without regard to case, then
+//! booleans, with missing values last.
+
+use crate::aggregate::{group_key, row_values, typed_value, Accumulator, Function};
+use crate::duplicates::Key;
+use crate::reference::parse_col;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::CellValue;
+use crate::workbook::TypedValue;
+use crate::ParsedWorksheet;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Options for [`pivot`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PivotOptions {
+    /// First row read (1-based), e.g. 2 to leave out a header row
+    pub first_row: Option<u32>,
+    pub shared_strings: Vec<String>,
+}
+
+/// A computed pivot table
+///
+/// Each figure list holds one figure per value field, in order; `None`
+/// where no rows fall, or for an average or extreme without numbers.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PivotTable {
+    /// Labels of the table's columns, one value per column field
+    pub columns: Vec<Vec<Option<TypedValue>>>,
+    pub rows: Vec<PivotRow>,
+    /// Figures for each column over all rows
+    pub totals: Vec<Vec<Option<f64>>>,
+    /// Figures over every row
+    pub grand_total: Vec<Option<f64>>,
+}
+
+/// One row of a pivot table
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PivotRow {
+    /// The row's label, one value per row field
+    pub key: Vec<Option<TypedValue>>,
+    /// Figures for each of the table's columns
+    pub values: Vec<Vec<Option<f64>>>,
+    /// Figures over the row's columns
+    pub total: Vec<Option<f64>>,
+}
+
+/// Labels found for one axis, with their accumulators
+struct Axis {
+    index: HashMap<Vec<Key>, usize>,
+    labels: Vec<Vec<Option<TypedValue>>>,
+    totals: Vec<Vec<Accumulator>>,
+}
+
+impl Axis {
+    fn new() -> Self {
+        Axis {
+            index: HashMap::new(),
+            labels: Vec::new(),
+            totals: Vec::new(),
+        }
+    }
+
+    /// Index of the label for these values, added when new
+    fn find(&mut self, values: &[Option<&CellValue>], fields: usize) -> usize {
+        let labels = &mut self.labels;
+        let totals = &mut self.totals;
+        *self.index.entry(group_key(values)).or_insert_with(|| {
+            labels.push(values.iter().map(|value| value.map(typed_value)).collect());
+            totals.push(accumulators(fields));
+            labels.len() - 1
+        })
+    }
+
+    /// Label indexes in ascending label order
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.labels.len()).collect();
+        order.sort_by(|&a, &b| compare_labels(&self.labels[a], &self.labels[b]));
+        order
+    }
+}
+
+/// Pivot `worksheet`: group by the `rows` and `columns` fields ("A", "C",
+/// ...) and aggregate each `values` field with its function in
+/// `aggregations` ("sum", "avg", "min", "max", "count" or "distinct"; one
+/// function applies to every field)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn pivot(
+    worksheet: JsValue,
+    rows: Vec<String>,
+    columns: Vec<String>,
+    values: Vec<String>,
+    aggregations: Vec<String>,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<PivotOptions> = serde_wasm_bindgen::from_value(options)?;
+        let table = pivot_impl(
+            &worksheet,
+            &rows,
+            &columns,
+            &values,
+            &aggregations,
+            &options.unwrap_or_default(),
+        )
+        .map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&table)?)
+    })
+}
+
+pub(crate) fn pivot_impl(
+    worksheet: &ParsedWorksheet,
+    rows: &[String],
+    columns: &[String],
+    values: &[String],
+    aggregations: &[String],
+    options: &PivotOptions,
+) -> Result<PivotTable, String> {
+    let parse = |fields: &[String]| {
+        fields
+            .iter()
+            .map(|letters| parse_col(letters).ok_or(format!("Invalid column: {}", letters)))
+            .collect::<Result<Vec<u32>, String>>()
+    };
+    let (row_fields, column_fields, value_fields) = (parse(rows)?, parse(columns)?, parse(values)?);
+    if value_fields.is_empty() {
+        return Err("At least one value field is required".to_string());
+    }
+    let functions = aggregations
+        .iter()
+        .map(|name| Function::parse(name))
+        .collect::<Result<Vec<_>, String>>()?;
+    let functions = match functions.len() {
+        1 => vec![functions[0]; value_fields.len()],
+        n if n == value_fields.len() => functions,
+        _ => {
+            return Err(format!(
+                "Expected 1 or {} aggregations, got {}",
+                value_fields.len(),
+                functions.len()
+            ))
+        }
+    };
+
+    let first_row = options.first_row.unwrap_or(1);
+    let mut row_axis = Axis::new();
+    let mut column_axis = Axis::new();
+    let mut cells: HashMap<(usize, usize), Vec<Accumulator>> = HashMap::new();
+    let mut grand = accumulators(value_fields.len());
+    for row in &worksheet.rows {
+        if row.row_num < first_row {
+            continue;
+        }
+        let by_col = row_values(row, &options.shared_strings);
+        if by_col.is_empty() {
+            continue;
+        }
+        let labels = |fields: &[u32]| fields.iter().map(|col| by_col.get(col)).collect::<Vec<_>>();
+        let r = row_axis.find(&labels(&row_fields), value_fields.len());
+        let c = column_axis.find(&labels(&column_fields), value_fields.len());
+        let cell = cells
+            .entry((r, c))
+            .or_insert_with(|| accumulators(value_fields.len()));
+        for (i, col) in value_fields.iter().enumerate() {
+            if let Some(value) = by_col.get(col) {
+                for acc in [
+                    &mut cell[i],
+                    &mut row_axis.totals[r][i],
+                    &mut column_axis.totals[c][i],
+                    &mut grand[i],
+                ] {
+                    acc.add(value);
+                }
+            }
+        }
+    }
+
+    let figures = |accumulators: Option<&Vec<Accumulator>>| -> Vec<Option<f64>> {
+        functions
+            .iter()
+            .enumerate()
+            .map(|(i, &function)| accumulators.and_then(|accs| accs[i].result(function)))
+            .collect()
+    };
+    let column_order = column_axis.order();
+    Ok(PivotTable {
+        columns: column_order
+            .iter()
+            .map(|&c| column_axis.labels[c].clone())
+            .collect(),
+        rows: row_axis
+            .order()
+            .into_iter()
+            .map(|r| PivotRow {
+                key: row_axis.labels[r].clone(),
+                values: column_order
+                    .iter()
+                    .map(|&c| figures(cells.get(&(r, c))))
+                    .collect(),
+                total: figures(Some(&row_axis.totals[r])),
+            })
+            .collect(),
+        totals: column_order
+            .iter()
+            .map(|&c| figures(Some(&column_axis.totals[c])))
+            .collect(),
+        grand_total: figures(Some(&grand)),
+    })
+}
+
+fn accumulators(fields: usize) -> Vec<Accumulator> {
+    (0..fields).map(|_| Accumulator::default()).collect()
+}
+
+/// Ascending label order, field by field
+fn compare_labels(a: &[Option<TypedValue>], b: &[Option<TypedValue>]) -> Ordering {
+    let rank = |value: &Option<TypedValue>| match value {
+        Some(TypedValue::Number(_)) => 0,
+        Some(TypedValue::Text(_)) => 1,
+        Some(TypedValue::Bool(_)) => 2,
+        None => 3,
+    };
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| match (a, b) {
+            (Some(TypedValue::Number(a)), Some(TypedValue::Number(b))) => a.total_cmp(b),
+            (Some(TypedValue::Text(a)), Some(TypedValue::Text(b))) => a
+                .to_lowercase()
+                .cmp(&b.to_lowercase())
+                .then_with(|| a.cmp(b)),
+            (Some(TypedValue::Bool(a)), Some(TypedValue::Bool(b))) => a.cmp(b),
+            _ => rank(a).cmp(&rank(b)),
+        })
+        .find(|order| order.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::sheet_from_rows;
+
+    #[test]
+    fn test_pivot() {
+        let worksheet = sheet_from_rows(&[
+            &["Region", "Quarter", "Sales"],
+            &["West", "Q2", "4"],
+            &["east", "Q1", "10"],
+            &["West", "Q1", "6"],
+            &["east", "Q2", "2"],
+            &["West", "Q1", "8"],
+            &["", "Q2", "1"],
+        ]);
+        let fields = |fields: &[&str]| fields.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let options = PivotOptions {
+            first_row: Some(2),
+            ..Default::default()
+        };
+
+        let table = pivot_impl(
+            &worksheet,
+            &fields(&["A"]),
+            &fields(&["B"]),
+            &fields(&["C", "C"]),
+            &fields(&["sum", "count"]),
+            &options,
+        )
+        .unwrap();
+        let text = |s: &str| Some(TypedValue::Text(s.to_string()));
+        assert_eq!(table.columns, [vec![text("Q1")], vec![text("Q2")]]);
+        let keys: Vec<_> = table.rows.iter().map(|row| row.key.clone()).collect();
+        assert_eq!(keys, [vec![text("east")], vec![text("West")], vec![None]]);
+        let west = &table.rows[1];
+        assert_eq!(
+            west.values,
+            [vec![Some(14.0), Some(2.0)], vec![Some(4.0), Some(1.0)]]
+        );
+        assert_eq!(west.total, [Some(18.0), Some(3.0)]);
+        assert_eq!(table.rows[2].values[0], [None, None]);
+        assert_eq!(
+            table.totals,
+            [vec![Some(24.0), Some(3.0)], vec![Some(7.0), Some(3.0)]]
+        );
+        assert_eq!(table.grand_total, [Some(31.0), Some(6.0)]);
+
+        // Without column fields there is one column; one function applies
+        // to every value field
+        let table = pivot_impl(
+            &worksheet,
+            &fields(&["B"]),
+            &[],
+            &fields(&["C"]),
+            &fields(&["AVG"]),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(table.columns, [Vec::<Option<TypedValue>>::new()]);
+        assert_eq!(table.rows[0].values, [vec![Some(8.0)]]);
+        assert_eq!(table.rows[1].total, [Some(7.0 / 3.0)]);
+
+        let pivot = |aggregations: &[&str]| {
+            let values = fields(&["C", "C"]);
+            pivot_impl(
+                &worksheet,
+                &[],
+                &[],
+                &values,
+                &fields(aggregations),
+                &options,
+            )
+        };
+        assert!(pivot(&["sum", "min", "max"]).is_err());
+        assert!(pivot(&["median"]).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Opt-in phase timing
+//!
+//! When enabled with [`set_profiling`], the parsers record how long each
+//! phase took (unzip, sharedStrings, styles, every sheet, serialization).
+//! [`get_profile`] returns the totals, so a slow import can be traced to
+//! the part responsible. Disabled, a phase costs one flag check.
+
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use serde::Serialize;
+use std::cell::RefCell;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// Milliseconds from an arbitrary fixed point
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    performance_now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    thread_local!(static START: std::time::Instant = std::time::Instant::now());
+    START.with(|start| start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Accumulated time of one phase
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub ms: f64,
+    /// How many times the phase ran
+    pub calls: u32,
+}
+
+/// Report returned by [`get_profile`]; phases are in order of first use
+#[derive(Debug, Default, Serialize)]
+pub struct ProfileReport {
+    pub phases: Vec<PhaseTiming>,
+    /// Sum of the outermost phases, so nested phases are not counted twice
+    pub total_ms: f64,
+}
+
+#[derive(Default)]
+struct Profiler {
+    enabled: bool,
+    report: ProfileReport,
+    depth: u32,
+}
+
+thread_local!(static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default()));
+
+/// Turn phase timing on or off; turning it on starts a fresh report
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn set_profiling(enabled: bool) {
+    PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        if enabled && !profiler.enabled {
+            profiler.report = ProfileReport::default();
+        }
+        profiler.enabled = enabled;
+    });
+}
+
+/// The phases recorded since profiling was enabled (or last reset)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn get_profile() -> Result<JsValue, JsValue> {
+    trap(|| Ok(PROFILER.with(|profiler| serde_wasm_bindgen::to_value(&profiler.borrow().report))?))
+}
+
+/// Clear the recorded phases without changing whether profiling is on
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn reset_profile() {
+    PROFILER.with(|profiler| profiler.borrow_mut().report = ProfileReport::default());
+}
+
+/// Run `f`, adding its duration to the phase `name` when profiling is on
+pub(crate) fn phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let enabled = PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        if profiler.enabled {
+            profiler.depth += 1;
+        }
+        profiler.enabled
+    });
+    if !enabled {
+        return f();
+    }
+
+    let start = now_ms();
+    let result = f();
+    let elapsed = now_ms() - start;
+    PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        profiler.depth -= 1;
+        if profiler.depth == 0 {
+            profiler.report.total_ms += elapsed;
+        }
+        let phases = &mut profiler.report.phases;
+        match phases.iter_mut().find(|phase| phase.name == name) {
+            Some(phase) => {
+                phase.ms += elapsed;
+                phase.calls += 1;
+            }
+            None => phases.push(PhaseTiming {
+                name: name.to_string(),
+                ms: elapsed,
+                calls: 1,
+            }),
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases() {
+        phase("ignored", || ());
+        set_profiling(true);
+        let value = phase("sheet", || {
+            phase("serialization", || ());
+            phase("serialization", || 7)
+        });
+        assert_eq!(value, 7);
+        set_profiling(false);
+
+        PROFILER.with(|profiler| {
+            let report = &profiler.borrow().report;
+            let phases: Vec<_> = report
+                .phases
+                .iter()
+                .map(|p| (p.name.as_str(), p.calls))
+                .collect();
+            assert_eq!(phases, vec![("serialization", 2), ("sheet", 1)]);
+            assert_eq!(report.total_ms, report.phases[1].ms);
+        });
+    }
+}
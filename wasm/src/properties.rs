@@ -0,0 +1,306 @@
+//! Document properties for XLSX export
+//!
+//! What Excel shows under File > Info lives outside the workbook, in
+//! `docProps/core.xml` (title, author, dates), `docProps/app.xml` (company,
+//! manager and the list of sheets) and `docProps/custom.xml` (properties
+//! named by the user), each related from the package root.
+
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::parse_iso_date;
+use crate::workbook::TypedValue;
+use crate::writer::{push_escaped, XML_DECLARATION};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const NS_CORE_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/package/2006/metadata/core-properties";
+const NS_EXTENDED_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties";
+const NS_CUSTOM_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/custom-properties";
+const NS_DOC_PROPS_VTYPES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes";
+/// Format id every custom property carries
+const CUSTOM_FMTID: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+/// Longest custom property name Excel accepts
+const MAX_NAME_LEN: usize = 255;
+
+/// A workbook's title, author and other metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentProperties {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    /// Author
+    pub creator: Option<String>,
+    /// Tags
+    pub keywords: Option<String>,
+    /// Comments
+    pub description: Option<String>,
+    pub last_modified_by: Option<String>,
+    pub category: Option<String>,
+    /// When the file was created, ISO 8601 in UTC ("2024-05-01T09:30:00Z")
+    pub created: Option<String>,
+    /// When the file was last saved, ISO 8601 in UTC
+    pub modified: Option<String>,
+    /// Program named as the file's author; "Microsoft Excel" when left out
+    pub application: Option<String>,
+    pub company: Option<String>,
+    pub manager: Option<String>,
+    /// Properties named by the user, as in File > Properties > Custom
+    pub custom: Vec<CustomProperty>,
+}
+
+/// A named custom property holding text, a number or a yes/no value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomProperty {
+    pub name: String,
+    pub value: TypedValue,
+}
+
+/// Serialize the core properties part (`docProps/core.xml`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_core_properties(properties: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let properties: DocumentProperties = serde_wasm_bindgen::from_value(properties)?;
+        write_core_properties_impl(&properties).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn write_core_properties_impl(
+    properties: &DocumentProperties,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(1024);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<cp:coreProperties xmlns:cp=\"{}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:dcterms=\"http://purl.org/dc/terms/\" \
+         xmlns:dcmitype=\"http://purl.org/dc/dcmitype/\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">",
+        NS_CORE_PROPERTIES
+    );
+    let text = [
+        ("dc:title", &properties.title),
+        ("dc:subject", &properties.subject),
+        ("dc:creator", &properties.creator),
+        ("cp:keywords", &properties.keywords),
+        ("dc:description", &properties.description),
+        ("cp:lastModifiedBy", &properties.last_modified_by),
+    ];
+    for (tag, value) in text {
+        push_element(&mut out, tag, value.as_deref());
+    }
+    for (tag, value) in [
+        ("dcterms:created", &properties.created),
+        ("dcterms:modified", &properties.modified),
+    ] {
+        if let Some(value) = value {
+            let _ = write!(
+                out,
+                "<{tag} xsi:type=\"dcterms:W3CDTF\">{}</{tag}>",
+                w3c_date(value)?,
+                tag = tag
+            );
+        }
+    }
+    push_element(&mut out, "cp:category", properties.category.as_deref());
+    out.push_str("</cp:coreProperties>");
+    Ok(out)
+}
+
+/// Serialize the extended properties part (`docProps/app.xml`), listing
+/// `sheet_names` as Excel does
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_app_properties(
+    properties: JsValue,
+    sheet_names: Vec<String>,
+) -> Result<String, JsValue> {
+    trap(|| {
+        let properties: DocumentProperties = serde_wasm_bindgen::from_value(properties)?;
+        Ok(write_app_properties_impl(&properties, &sheet_names))
+    })
+}
+
+pub(crate) fn write_app_properties_impl(
+    properties: &DocumentProperties,
+    sheet_names: &[String],
+) -> String {
+    let mut out = String::with_capacity(512 + sheet_names.len() * 32);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<Properties xmlns=\"{}\" xmlns:vt=\"{}\">",
+        NS_EXTENDED_PROPERTIES, NS_DOC_PROPS_VTYPES
+    );
+    let application = properties.application.as_deref();
+    push_element(
+        &mut out,
+        "Application",
+        application.or(Some("Microsoft Excel")),
+    );
+    out.push_str("<DocSecurity>0</DocSecurity><ScaleCrop>false</ScaleCrop>");
+    let _ = write!(
+        out,
+        "<HeadingPairs><vt:vector size=\"2\" baseType=\"variant\">\
+         <vt:variant><vt:lpstr>Worksheets</vt:lpstr></vt:variant>\
+         <vt:variant><vt:i4>{}</vt:i4></vt:variant></vt:vector></HeadingPairs>\
+         <TitlesOfParts><vt:vector size=\"{}\" baseType=\"lpstr\">",
+        sheet_names.len(),
+        sheet_names.len()
+    );
+    for name in sheet_names {
+        push_element(&mut out, "vt:lpstr", Some(name));
+    }
+    out.push_str("</vt:vector></TitlesOfParts>");
+    push_element(&mut out, "Manager", properties.manager.as_deref());
+    push_element(&mut out, "Company", properties.company.as_deref());
+    out.push_str(
+        "<LinksUpToDate>false</LinksUpToDate><SharedDoc>false</SharedDoc>\
+         <HyperlinksChanged>false</HyperlinksChanged></Properties>",
+    );
+    out
+}
+
+/// Serialize the custom properties part (`docProps/custom.xml`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_custom_properties(custom: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let custom: Vec<CustomProperty> = serde_wasm_bindgen::from_value(custom)?;
+        write_custom_properties_impl(&custom).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn write_custom_properties_impl(custom: &[CustomProperty]) -> Result<String, String> {
+    let mut out = String::with_capacity(256 + custom.len() * 160);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<Properties xmlns=\"{}\" xmlns:vt=\"{}\">",
+        NS_CUSTOM_PROPERTIES, NS_DOC_PROPS_VTYPES
+    );
+    let mut names = HashSet::new();
+    for (i, property) in custom.iter().enumerate() {
+        let name = &property.name;
+        if name.trim().is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("Invalid custom property name: {:?}", name));
+        }
+        // Names are case-insensitive, as in Excel's dialog
+        if !names.insert(name.to_lowercase()) {
+            return Err(format!("Duplicate custom property: {}", name));
+        }
+        // Ids 0 and 1 are reserved
+        let _ = write!(
+            out,
+            "<property fmtid=\"{}\" pid=\"{}\" name=\"",
+            CUSTOM_FMTID,
+            i + 2
+        );
+        push_escaped(&mut out, name);
+        out.push_str("\">");
+        match &property.value {
+            TypedValue::Text(text) => push_element(&mut out, "vt:lpwstr", Some(text)),
+            TypedValue::Number(n) if !n.is_finite() => {
+                return Err(format!("Custom property {} must be a finite number", name))
+            }
+            // Whole numbers take Excel's integer type
+            TypedValue::Number(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => {
+                let _ = write!(out, "<vt:i4>{}</vt:i4>", *n as i32);
+            }
+            TypedValue::Number(n) => {
+                let _ = write!(out, "<vt:r8>{}</vt:r8>", n);
+            }
+            TypedValue::Bool(b) => {
+                let _ = write!(out, "<vt:bool>{}</vt:bool>", b);
+            }
+        }
+        out.push_str("</property>");
+    }
+    out.push_str("</Properties>");
+    Ok(out)
+}
+
+/// Append `<tag>value</tag>` when there is a value
+fn push_element(out: &mut String, tag: &str, value: Option<&str>) {
+    if let Some(value) = value {
+        let _ = write!(out, "<{}>", tag);
+        push_escaped(out, value);
+        let _ = write!(out, "</{}>", tag);
+    }
+}
+
+/// An ISO 8601 date or date-time as the W3C form the core properties take
+fn w3c_date(value: &str) -> Result<String, String> {
+    let dt = parse_iso_date(value).ok_or(format!("Invalid date: {}", value))?;
+    Ok(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_properties() {
+        let properties = DocumentProperties {
+            title: Some("Q1 <Sales>".to_string()),
+            creator: Some("Finance".to_string()),
+            created: Some("2024-05-01T09:30:00Z".to_string()),
+            modified: Some("2024-05-02".to_string()),
+            company: Some("Acme & Co".to_string()),
+            ..Default::default()
+        };
+        let core = write_core_properties_impl(&properties).unwrap();
+        assert!(
+            core.contains("<dc:title>Q1 &lt;Sales&gt;</dc:title><dc:creator>Finance</dc:creator>")
+        );
+        assert!(core.contains(
+            "<dcterms:created xsi:type=\"dcterms:W3CDTF\">2024-05-01T09:30:00Z</dcterms:created>\
+             <dcterms:modified xsi:type=\"dcterms:W3CDTF\">2024-05-02T00:00:00Z</dcterms:modified>"
+        ));
+        let broken = DocumentProperties {
+            created: Some("May 1".to_string()),
+            ..Default::default()
+        };
+        assert!(write_core_properties_impl(&broken).is_err());
+
+        let app =
+            write_app_properties_impl(&properties, &["Data".to_string(), "Chart".to_string()]);
+        assert!(app.contains("<Application>Microsoft Excel</Application>"));
+        assert!(app.contains(
+            "<vt:vector size=\"2\" baseType=\"lpstr\"><vt:lpstr>Data</vt:lpstr>\
+             <vt:lpstr>Chart</vt:lpstr></vt:vector>"
+        ));
+        assert!(app.contains("<Company>Acme &amp; Co</Company>"));
+
+        let property = |name: &str, value| CustomProperty {
+            name: name.to_string(),
+            value,
+        };
+        let mut custom = vec![
+            property("Project", TypedValue::Text("Apollo".to_string())),
+            property("Revision", TypedValue::Number(3.0)),
+            property("Rate", TypedValue::Number(0.25)),
+            property("Approved", TypedValue::Bool(true)),
+        ];
+        let xml = write_custom_properties_impl(&custom).unwrap();
+        assert!(xml.contains(
+            "<property fmtid=\"{D5CDD505-2E9C-101B-9397-08002B2CF9AE}\" pid=\"2\" \
+             name=\"Project\"><vt:lpwstr>Apollo</vt:lpwstr></property>"
+        ));
+        assert!(xml.contains("pid=\"3\" name=\"Revision\"><vt:i4>3</vt:i4>"));
+        assert!(xml.contains("<vt:r8>0.25</vt:r8>"));
+        assert!(xml.contains("<vt:bool>true</vt:bool>"));
+        custom.push(property("project", TypedValue::Bool(false)));
+        assert!(write_custom_properties_impl(&custom).is_err());
+    }
+}
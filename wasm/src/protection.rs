@@ -0,0 +1,244 @@
+//! Sheet protection for XLSX export
+//!
+//! `<sheetProtection>` locks a sheet's locked cells (every cell, unless its
+//! style says otherwise) and its structure against edits. A password is only
+//! stored as a hash: Excel 2013 and later use a salted SHA-512 hash spun
+//! 100,000 times, while older versions and some other apps only read the
+//! legacy 16-bit hash, which is easily reversed and best kept for them.
+
+#[cfg(feature = "wasm")]
+use crate::crypto::random_bytes;
+use crate::crypto::{base64_encode, HashAlgorithm};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::push_attr;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Spin count Excel uses for sheet passwords
+const SPIN_COUNT: u32 = 100_000;
+/// Length of a password salt in bytes
+pub(crate) const SALT_LEN: usize = 16;
+/// Longest password Excel accepts
+const MAX_PASSWORD_LEN: usize = 255;
+
+/// A worksheet's protection
+///
+/// The flags say what users may still do on the protected sheet; by
+/// default they can only select cells.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetProtection {
+    /// Algorithm of `hash_value`, "SHA-512"
+    pub algorithm_name: Option<String>,
+    /// Base64 hash of the salted password
+    pub hash_value: Option<String>,
+    /// Base64 salt
+    pub salt_value: Option<String>,
+    /// Times the hash was rehashed
+    pub spin_count: Option<u32>,
+    /// Legacy 16-bit password hash in hex, e.g. "DAA7"
+    pub legacy_hash: Option<String>,
+    pub select_locked_cells: bool,
+    pub select_unlocked_cells: bool,
+    pub format_cells: bool,
+    pub format_columns: bool,
+    pub format_rows: bool,
+    pub insert_columns: bool,
+    pub insert_rows: bool,
+    pub insert_hyperlinks: bool,
+    pub delete_columns: bool,
+    pub delete_rows: bool,
+    pub sort: bool,
+    pub auto_filter: bool,
+    pub pivot_tables: bool,
+    /// Edit pictures, charts and comments
+    pub objects: bool,
+    pub scenarios: bool,
+}
+
+impl Default for SheetProtection {
+    fn default() -> Self {
+        SheetProtection {
+            algorithm_name: None,
+            hash_value: None,
+            salt_value: None,
+            spin_count: None,
+            legacy_hash: None,
+            select_locked_cells: true,
+            select_unlocked_cells: true,
+            format_cells: false,
+            format_columns: false,
+            format_rows: false,
+            insert_columns: false,
+            insert_rows: false,
+            insert_hyperlinks: false,
+            delete_columns: false,
+            delete_rows: false,
+            sort: false,
+            auto_filter: false,
+            pivot_tables: false,
+            objects: false,
+            scenarios: false,
+        }
+    }
+}
+
+/// Protection locking a sheet with `password`, to set as a worksheet's
+/// `protection`; `legacy` stores only the 16-bit hash, for apps that don't
+/// read the SHA-512 one
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn sheet_protection(password: &str, legacy: bool) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let salt = match legacy {
+            true => None,
+            false => Some(random_bytes(SALT_LEN)?),
+        };
+        let mut protection = SheetProtection::default();
+        set_password(&mut protection, password, salt.as_deref())
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&protection)?)
+    })
+}
+
+/// Lock `protection` with `password`: hashed with SHA-512 and `salt`, or
+/// without one with the legacy hash alone
+pub(crate) fn set_password(
+    protection: &mut SheetProtection,
+    password: &str,
+    salt: Option<&[u8]>,
+) -> Result<(), String> {
+    let len = password.encode_utf16().count();
+    if len == 0 || len > MAX_PASSWORD_LEN {
+        return Err(format!(
+            "Sheet passwords must be 1 to {} characters",
+            MAX_PASSWORD_LEN
+        ));
+    }
+    match salt {
+        Some(salt) => {
+            protection.algorithm_name = Some("SHA-512".to_string());
+            protection.hash_value = Some(base64_encode(&hash_password(password, salt, SPIN_COUNT)));
+            protection.salt_value = Some(base64_encode(salt));
+            protection.spin_count = Some(SPIN_COUNT);
+            protection.legacy_hash = None;
+        }
+        None => {
+            protection.algorithm_name = None;
+            protection.hash_value = None;
+            protection.salt_value = None;
+            protection.spin_count = None;
+            protection.legacy_hash = Some(format!("{:04X}", legacy_hash(password)));
+        }
+    }
+    Ok(())
+}
+
+/// SHA-512 of the salt and UTF-16 password, rehashed `spin_count` times
+fn hash_password(password: &str, salt: &[u8], spin_count: u32) -> Vec<u8> {
+    let password: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut hash = HashAlgorithm::Sha512.digest(&[salt, &password]);
+    // Unlike encryption keys, the round number follows the hash
+    for i in 0..spin_count {
+        hash = HashAlgorithm::Sha512.digest(&[&hash, &i.to_le_bytes()]);
+    }
+    hash
+}
+
+/// Excel's original password hash: each character rotated within 15 bits
+/// by its position, XORed together with the length and a constant
+fn legacy_hash(password: &str) -> u16 {
+    let rotate = |hash: u16| ((hash >> 14) & 1) | ((hash << 1) & 0x7FFF);
+    let units: Vec<u16> = password.encode_utf16().collect();
+    let hash = units
+        .iter()
+        .rev()
+        .fold(0, |hash, &unit| rotate(hash) ^ unit);
+    rotate(hash) ^ units.len() as u16 ^ 0xCE4B
+}
+
+/// Append the `<sheetProtection>` element
+pub(crate) fn write_sheet_protection(out: &mut String, protection: &SheetProtection) {
+    out.push_str("<sheetProtection");
+    let hashes = [
+        ("password", &protection.legacy_hash),
+        ("algorithmName", &protection.algorithm_name),
+        ("hashValue", &protection.hash_value),
+        ("saltValue", &protection.salt_value),
+    ];
+    for (name, value) in hashes {
+        if let Some(value) = value {
+            push_attr(out, name, value);
+        }
+    }
+    if let Some(spin_count) = protection.spin_count {
+        let _ = write!(out, " spinCount=\"{}\"", spin_count);
+    }
+    out.push_str(" sheet=\"1\"");
+
+    // Each attribute is true when the action is locked; objects, scenarios
+    // and selection default to unlocked, the rest to locked
+    let p = protection;
+    let locks = [
+        ("objects", p.objects, false),
+        ("scenarios", p.scenarios, false),
+        ("formatCells", p.format_cells, true),
+        ("formatColumns", p.format_columns, true),
+        ("formatRows", p.format_rows, true),
+        ("insertColumns", p.insert_columns, true),
+        ("insertRows", p.insert_rows, true),
+        ("insertHyperlinks", p.insert_hyperlinks, true),
+        ("deleteColumns", p.delete_columns, true),
+        ("deleteRows", p.delete_rows, true),
+        ("selectLockedCells", p.select_locked_cells, false),
+        ("sort", p.sort, true),
+        ("autoFilter", p.auto_filter, true),
+        ("pivotTables", p.pivot_tables, true),
+        ("selectUnlockedCells", p.select_unlocked_cells, false),
+    ];
+    for (name, allowed, locked_by_default) in locks {
+        if allowed == locked_by_default {
+            let _ = write!(out, " {}=\"{}\"", name, u8::from(!allowed));
+        }
+    }
+    out.push_str("/>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sheet_protection() {
+        assert_eq!(legacy_hash("secret"), 0xDAA7);
+        assert_eq!(legacy_hash("password"), 0x83AF);
+
+        let salt: Vec<u8> = (0..SALT_LEN as u8).collect();
+        let mut protection = SheetProtection {
+            format_columns: true,
+            sort: true,
+            select_locked_cells: false,
+            ..Default::default()
+        };
+        set_password(&mut protection, "secret", Some(&salt)).unwrap();
+        let mut xml = String::new();
+        write_sheet_protection(&mut xml, &protection);
+        assert_eq!(
+            xml,
+            "<sheetProtection algorithmName=\"SHA-512\" \
+             hashValue=\"M5SOVnbQG4SHyBnRVAYzAx8mPtxyyzMuWxcMv7tkyFO3MBXX9OJjklwPglNHdoHV\
+             kKPm4MPfUblqHmAsXfF5HA==\" \
+             saltValue=\"AAECAwQFBgcICQoLDA0ODw==\" spinCount=\"100000\" sheet=\"1\" \
+             objects=\"1\" scenarios=\"1\" formatColumns=\"0\" selectLockedCells=\"1\" sort=\"0\"/>"
+        );
+
+        set_password(&mut protection, "secret", None).unwrap();
+        xml.clear();
+        write_sheet_protection(&mut xml, &protection);
+        assert!(xml.starts_with("<sheetProtection password=\"DAA7\" sheet=\"1\""));
+        assert!(set_password(&mut protection, "", None).is_err());
+    }
+}
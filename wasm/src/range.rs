@@ -0,0 +1,142 @@
+//! Range algebra over inclusive 1-based bounds
+//!
+//! Subtraction and union return disjoint rectangles: the band above the
+//! removed area, the band below it, then the pieces to its left and right.
+
+use crate::reference::{bounds_ref, parse_cell_ref, range_bounds, Bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+pub(crate) fn contains_cell((r1, c1, r2, c2): Bounds, row: u32, col: u32) -> bool {
+    (r1..=r2).contains(&row) && (c1..=c2).contains(&col)
+}
+
+/// Whether `inner` lies entirely within `outer`
+pub(crate) fn contains(outer: Bounds, inner: Bounds) -> bool {
+    contains_cell(outer, inner.0, inner.1) && contains_cell(outer, inner.2, inner.3)
+}
+
+pub(crate) fn intersect(a: Bounds, b: Bounds) -> Option<Bounds> {
+    let bounds = (a.0.max(b.0), a.1.max(b.1), a.2.min(b.2), a.3.min(b.3));
+    (bounds.0 <= bounds.2 && bounds.1 <= bounds.3).then_some(bounds)
+}
+
+/// Smallest range covering both
+pub(crate) fn bounding_box(a: Bounds, b: Bounds) -> Bounds {
+    (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))
+}
+
+/// Cells of `a` outside `b`
+pub(crate) fn subtract(a: Bounds, b: Bounds) -> Vec<Bounds> {
+    let Some((r1, c1, r2, c2)) = intersect(a, b) else {
+        return vec![a];
+    };
+    let mut pieces = Vec::new();
+    if a.0 < r1 {
+        pieces.push((a.0, a.1, r1 - 1, a.3));
+    }
+    if r2 < a.2 {
+        pieces.push((r2 + 1, a.1, a.2, a.3));
+    }
+    if a.1 < c1 {
+        pieces.push((r1, a.1, r2, c1 - 1));
+    }
+    if c2 < a.3 {
+        pieces.push((r1, c2 + 1, r2, a.3));
+    }
+    pieces
+}
+
+/// Cells in either range, as `a` followed by the parts of `b` outside it
+pub(crate) fn union(a: Bounds, b: Bounds) -> Vec<Bounds> {
+    if contains(b, a) {
+        return vec![b];
+    }
+    let mut pieces = vec![a];
+    pieces.extend(subtract(b, a));
+    pieces
+}
+
+#[cfg(feature = "wasm")]
+fn parse(range: &str) -> Result<Bounds, JsValue> {
+    range_bounds(range).ok_or_else(|| JsValue::from_str(&format!("Invalid range: {}", range)))
+}
+
+fn refs(pieces: Vec<Bounds>) -> Vec<String> {
+    pieces.into_iter().map(bounds_ref).collect()
+}
+
+/// Overlap of two ranges, or undefined when they are disjoint
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_intersect(a: &str, b: &str) -> Result<Option<String>, JsValue> {
+    trap(|| Ok(intersect(parse(a)?, parse(b)?).map(bounds_ref)))
+}
+
+/// Cells in either range, as disjoint ranges
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_union(a: &str, b: &str) -> Result<Vec<String>, JsValue> {
+    trap(|| Ok(refs(union(parse(a)?, parse(b)?))))
+}
+
+/// Cells of `a` not in `b`, as disjoint ranges (empty when `b` covers `a`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_subtract(a: &str, b: &str) -> Result<Vec<String>, JsValue> {
+    trap(|| Ok(refs(subtract(parse(a)?, parse(b)?))))
+}
+
+/// Smallest range covering both
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_bounding_box(a: &str, b: &str) -> Result<String, JsValue> {
+    trap(|| Ok(bounds_ref(bounding_box(parse(a)?, parse(b)?))))
+}
+
+/// Whether `inner` lies entirely within `outer`
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_contains(outer: &str, inner: &str) -> Result<bool, JsValue> {
+    trap(|| Ok(contains(parse(outer)?, parse(inner)?)))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn range_contains_cell(range: &str, cell: &str) -> Result<bool, JsValue> {
+    trap(|| {
+        let (row, col) = parse_cell_ref(cell)
+            .ok_or_else(|| JsValue::from_str(&format!("Invalid cell: {}", cell)))?;
+        Ok(contains_cell(parse(range)?, row, col))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b(range: &str) -> Bounds {
+        range_bounds(range).unwrap()
+    }
+
+    #[test]
+    fn test_range_algebra() {
+        assert_eq!(intersect(b("A1:C3"), b("B2:D4")), Some(b("B2:C3")));
+        assert_eq!(intersect(b("A1:B2"), b("C3:D4")), None);
+        assert!(contains(b("A1:D4"), b("B2:C3")));
+        assert!(!contains(b("A1:D4"), b("C3:E5")));
+        assert!(contains_cell(b("B2:C3"), 3, 2));
+        assert_eq!(
+            refs(subtract(b("A1:C3"), b("B2"))),
+            ["A1:C1", "A3:C3", "A2", "C2"]
+        );
+        assert!(subtract(b("B2"), b("A1:C3")).is_empty());
+        assert_eq!(
+            refs(union(b("A1:B2"), b("B2:C3"))),
+            ["A1:B2", "B3:C3", "C2"]
+        );
+        assert_eq!(bounds_ref(bounding_box(b("B2"), b("D1"))), "B1:D2");
+    }
+}
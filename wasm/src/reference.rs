@@ -0,0 +1,316 @@
+//! A1-style cell reference helpers
+//!
+//! The crate works with 1-based rows and columns; the exported helpers use
+//! the 0-based indices of the JS API.
+
+use crate::formula::{MAX_COLS, MAX_ROWS};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::{ParsedCell, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// 0-based position of a cell
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CellPosition {
+    pub row: u32,
+    pub col: u32,
+}
+
+/// 0-based inclusive corners of a range, normalised so start <= end
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RangePosition {
+    pub start_row: u32,
+    pub start_col: u32,
+    pub end_row: u32,
+    pub end_col: u32,
+}
+
+/// Options for [`compute_used_range`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsedRangeOptions {
+    /// Leave out cells that carry a style but no value or formula
+    pub ignore_style_only: bool,
+}
+
+/// Bounding box of a worksheet's non-empty cells
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct UsedRange {
+    /// A1 form such as `B2:D9`, or a single cell
+    pub reference: String,
+    pub start_row: u32,
+    pub start_col: u32,
+    pub end_row: u32,
+    pub end_col: u32,
+}
+
+#[cfg(feature = "wasm")]
+fn invalid(kind: &str, value: &str) -> JsValue {
+    JsValue::from_str(&format!("Invalid {}: {}", kind, value))
+}
+
+/// Convert column letters to a 0-based index ("A" -> 0, "AA" -> 26)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn col_letter_to_index(letters: &str) -> Result<u32, JsValue> {
+    trap(|| {
+        let mut col: u32 = 0;
+        for ch in letters.chars() {
+            if !ch.is_ascii_alphabetic() {
+                return Err(invalid("column", letters));
+            }
+            let digit = ch.to_ascii_uppercase() as u32 - 'A' as u32 + 1;
+            col = col
+                .checked_mul(26)
+                .and_then(|col| col.checked_add(digit))
+                .ok_or_else(|| invalid("column", letters))?;
+        }
+        col.checked_sub(1).ok_or_else(|| invalid("column", letters))
+    })
+}
+
+/// Convert a 0-based column index to letters (0 -> "A", 26 -> "AA")
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn index_to_col_letter(index: u32) -> String {
+    col_to_letters(index.saturating_add(1))
+}
+
+/// Parse an A1 reference such as `B7` or `$B$7` into a 0-based position
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_ref(reference: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let (row, col) =
+            parse_cell_ref(reference).ok_or_else(|| invalid("cell reference", reference))?;
+        let position = CellPosition {
+            row: row - 1,
+            col: col - 1,
+        };
+        Ok(serde_wasm_bindgen::to_value(&position)?)
+    })
+}
+
+/// Parse an A1 range such as `A1:C9` (or a single cell) into 0-based corners
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_range(range: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let (r1, c1, r2, c2) = range_bounds(range).ok_or_else(|| invalid("range", range))?;
+        let position = RangePosition {
+            start_row: r1 - 1,
+            start_col: c1 - 1,
+            end_row: r2 - 1,
+            end_col: c2 - 1,
+        };
+        Ok(serde_wasm_bindgen::to_value(&position)?)
+    })
+}
+
+/// Compute the tight bounding box of non-empty cells, ignoring the declared
+/// `<dimension>`; returns null for an empty worksheet
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn compute_used_range(worksheet: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let options: Option<UsedRangeOptions> = serde_wasm_bindgen::from_value(options)?;
+        let used = compute_used_range_impl(&worksheet, &options.unwrap_or_default());
+        Ok(serde_wasm_bindgen::to_value(&used)?)
+    })
+}
+
+/// Parse many A1 references at once, returning `[row0, col0, row1, col1, ...]`
+/// (0-based) so large batches cross the boundary as one typed array
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_refs(references: Vec<String>) -> Result<Vec<u32>, JsValue> {
+    trap(|| {
+        let mut positions = Vec::with_capacity(references.len() * 2);
+        for reference in &references {
+            let (row, col) =
+                parse_cell_ref(reference).ok_or_else(|| invalid("cell reference", reference))?;
+            positions.extend([row - 1, col - 1]);
+        }
+        Ok(positions)
+    })
+}
+
+/// Convert a 1-based column number to its letter form (1 -> "A", 27 -> "AA")
+pub(crate) fn col_to_letters(mut col: u32) -> String {
+    let mut letters = Vec::new();
+    while col > 0 {
+        let rem = (col - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        col = (col - 1) / 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap_or_default()
+}
+
+/// Parse an A1-style reference into 1-based (row, col), ignoring `$` markers;
+/// `None` past Excel's last row or column
+pub(crate) fn parse_cell_ref(reference: &str) -> Option<(u32, u32)> {
+    let mut col: u32 = 0;
+    let mut row: u32 = 0;
+    let mut seen_digit = false;
+
+    for ch in reference.chars() {
+        match ch {
+            '$' => {}
+            'A'..='Z' | 'a'..='z' if !seen_digit => {
+                let digit = ch.to_ascii_uppercase() as u32 - 'A' as u32 + 1;
+                col = col.checked_mul(26)?.checked_add(digit)?;
+            }
+            '0'..='9' => {
+                seen_digit = true;
+                row = row.checked_mul(10)?.checked_add(ch as u32 - '0' as u32)?;
+            }
+            _ => return None,
+        }
+    }
+
+    if col == 0 || row == 0 || col > MAX_COLS || row > MAX_ROWS {
+        return None;
+    }
+
+    Some((row, col))
+}
+
+/// Parse column letters ("C", "$AB") into a 1-based column
+pub(crate) fn parse_col(letters: &str) -> Option<u32> {
+    let letters = letters.trim().trim_start_matches('$');
+    if letters.is_empty() || !letters.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+    parse_cell_ref(&format!("{}1", letters)).map(|(_, col)| col)
+}
+
+/// Build an A1-style reference from 1-based (row, col)
+pub(crate) fn cell_ref(row: u32, col: u32) -> String {
+    format!("{}{}", col_to_letters(col), row)
+}
+
+/// Inclusive (start_row, start_col, end_row, end_col), 1-based
+pub(crate) type Bounds = (u32, u32, u32, u32);
+
+/// Write bounds as an A1 range, or a single cell when they cover one
+pub(crate) fn bounds_ref((r1, c1, r2, c2): Bounds) -> String {
+    if (r1, c1) == (r2, c2) {
+        cell_ref(r1, c1)
+    } else {
+        format!("{}:{}", cell_ref(r1, c1), cell_ref(r2, c2))
+    }
+}
+
+/// Parse an A1-style range (or single cell) into its bounds
+pub(crate) fn range_bounds(range: &str) -> Option<Bounds> {
+    let (start, end) = range.split_once(':').unwrap_or((range, range));
+    let (r1, c1) = parse_cell_ref(start)?;
+    let (r2, c2) = parse_cell_ref(end)?;
+    Some((r1.min(r2), c1.min(c2), r1.max(r2), c1.max(c2)))
+}
+
+/// Bounds of all cells that have a reference
+pub(crate) fn used_range(worksheet: &ParsedWorksheet) -> Option<Bounds> {
+    cell_bounds(worksheet, |_| true)
+}
+
+/// Bounds of the cells accepted by `include`
+fn cell_bounds<F>(worksheet: &ParsedWorksheet, include: F) -> Option<Bounds>
+where
+    F: Fn(&ParsedCell) -> bool,
+{
+    let mut bounds: Option<Bounds> = None;
+    for row in &worksheet.rows {
+        for cell in row.cells.iter().filter(|cell| include(cell)) {
+            let (r, c) = parse_cell_ref(&cell.reference).unwrap_or((row.row_num, 0));
+            if c == 0 {
+                continue;
+            }
+            bounds = Some(match bounds {
+                Some((r1, c1, r2, c2)) => (r1.min(r), c1.min(c), r2.max(r), c2.max(c)),
+                None => (r, c, r, c),
+            });
+        }
+    }
+    bounds
+}
+
+pub(crate) fn compute_used_range_impl(
+    worksheet: &ParsedWorksheet,
+    options: &UsedRangeOptions,
+) -> Option<UsedRange> {
+    let (r1, c1, r2, c2) = cell_bounds(worksheet, |cell| {
+        let has_content = cell.value.as_deref().is_some_and(|v| !v.is_empty())
+            || cell.formula.as_deref().is_some_and(|f| !f.is_empty());
+        has_content || (!options.ignore_style_only && cell.style_index.is_some())
+    })?;
+    Some(UsedRange {
+        reference: bounds_ref((r1, c1, r2, c2)),
+        start_row: r1 - 1,
+        start_col: c1 - 1,
+        end_row: r2 - 1,
+        end_col: c2 - 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParsedRow;
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_exported_helpers() {
+        assert_eq!(col_letter_to_index("A").unwrap(), 0);
+        assert_eq!(col_letter_to_index("aa").unwrap(), 26);
+        assert_eq!(col_letter_to_index("XFD").unwrap(), 16_383);
+        assert_eq!(index_to_col_letter(0), "A");
+        assert_eq!(index_to_col_letter(701), "ZZ");
+        assert_eq!(
+            parse_refs(vec!["B7".to_string(), "$AA$1".to_string()]).unwrap(),
+            [6, 1, 0, 26]
+        );
+        assert_eq!(range_bounds("C9:A1"), Some((1, 1, 9, 3)));
+    }
+
+    #[test]
+    fn test_compute_used_range() {
+        let cell = |reference: &str, value: Option<&str>, style_index: Option<u32>| ParsedCell {
+            reference: reference.to_string(),
+            cell_type: None,
+            style_index,
+            value: value.map(str::to_string),
+            formula: None,
+        };
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                ParsedRow {
+                    row_num: 2,
+                    cells: vec![cell("C2", Some("1"), None), cell("A2", None, None)],
+                    height: None,
+                    hidden: false,
+                },
+                ParsedRow {
+                    row_num: 9,
+                    cells: vec![cell("E9", Some("x"), None), cell("G12", None, Some(3))],
+                    height: None,
+                    hidden: false,
+                },
+            ],
+            ..Default::default()
+        };
+        let used = |ignore_style_only| {
+            compute_used_range_impl(&worksheet, &UsedRangeOptions { ignore_style_only })
+                .map(|used| used.reference)
+        };
+        assert_eq!(used(false).as_deref(), Some("C2:G12"));
+        assert_eq!(used(true).as_deref(), Some("C2:E9"));
+        assert_eq!(
+            compute_used_range_impl(&ParsedWorksheet::default(), &UsedRangeOptions::default()),
+            None
+        );
+    }
+}
@@ -0,0 +1,584 @@
+//! Minimal regular expressions
+//!
+//! Supports the everyday subset: literals, `.`, character classes with
+//! ranges and negation, the `\d \w \s` shorthands (and their negations),
+//! `\b`, anchors, groups, alternation and greedy or lazy `* + ? {n,m}`
+//! quantifiers. Patterns compile to a small instruction list that runs
+//! without recursion in time linear in the text, and a step budget still
+//! stops oversized searches with an error rather than hanging the page.
+
+/// VM steps allowed per search before giving up
+const STEP_BUDGET: usize = 10_000_000;
+
+/// Instructions allowed in a compiled pattern; `{n,m}` copies its operand
+const MAX_PROGRAM: usize = 10_000;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    WordBoundary,
+    Group(Box<Node>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(low, high) => (low..=high).contains(&c),
+            ClassItem::Digit(negated) => c.is_ascii_digit() != negated,
+            ClassItem::Word(negated) => is_word(c) != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A compiled pattern
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    prog: Vec<Inst>,
+    ignore_case: bool,
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    pattern: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Invalid pattern {:?}: {}", self.pattern, message)
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('(') => {
+                // Non-capturing groups behave the same; nothing is captured
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let inner = self.alternation()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("missing )"));
+                }
+                Ok(Node::Group(Box::new(inner)))
+            }
+            Some('[') => self.class(),
+            Some('\\') => match self.escape()? {
+                Escape::Char(c) => Ok(Node::Char(c)),
+                Escape::Class(item) => Ok(Node::Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+                Escape::WordBoundary => Ok(Node::WordBoundary),
+            },
+            Some(c @ ('*' | '+' | '?')) => {
+                Err(self.error(&format!("nothing to repeat before {}", c)))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn escape(&mut self) -> Result<Escape, String> {
+        let c = self
+            .next()
+            .ok_or_else(|| self.error("trailing backslash"))?;
+        Ok(match c {
+            'd' => Escape::Class(ClassItem::Digit(false)),
+            'D' => Escape::Class(ClassItem::Digit(true)),
+            'w' => Escape::Class(ClassItem::Word(false)),
+            'W' => Escape::Class(ClassItem::Word(true)),
+            's' => Escape::Class(ClassItem::Space(false)),
+            'S' => Escape::Class(ClassItem::Space(true)),
+            'b' => Escape::WordBoundary,
+            'n' => Escape::Char('\n'),
+            'r' => Escape::Char('\r'),
+            't' => Escape::Char('\t'),
+            c => Escape::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or_else(|| self.error("missing ]"))?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Class(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Escape::WordBoundary => '\u{8}',
+                },
+                c => c,
+            };
+            let is_range = self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']');
+            if !is_range {
+                items.push(ClassItem::Range(low, low));
+                continue;
+            }
+            self.pos += 1;
+            let high = match self.next() {
+                Some('\\') => match self.escape()? {
+                    Escape::Char(c) => c,
+                    _ => return Err(self.error("invalid class range")),
+                },
+                Some(c) => c,
+                None => return Err(self.error("missing ]")),
+            };
+            if high < low {
+                return Err(self.error("class range out of order"));
+            }
+            items.push(ClassItem::Range(low, high));
+        }
+        Ok(Node::Class { items, negated })
+    }
+
+    fn quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max, width) = match self.peek() {
+            Some('*') => (0, None, 1),
+            Some('+') => (1, None, 1),
+            Some('?') => (0, Some(1), 1),
+            Some('{') => match self.bounds() {
+                Some(bounds) => bounds,
+                // A brace that doesn't form a quantifier is a literal
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += width;
+        if matches!(atom, Node::Start | Node::End | Node::WordBoundary) {
+            return Err(self.error("nothing to repeat"));
+        }
+        let greedy = self.peek() != Some('?');
+        if !greedy {
+            self.pos += 1;
+        }
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    /// Parse `{n}`, `{n,}` or `{n,m}` at `pos`, with its length in chars
+    fn bounds(&self) -> Option<(u32, Option<u32>, usize)> {
+        let close = self.chars[self.pos..].iter().position(|&c| c == '}')? + self.pos;
+        let body: String = self.chars[self.pos + 1..close].iter().collect();
+        let (min, max) = match body.split_once(',') {
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+            None => {
+                let n = body.parse().ok()?;
+                (n, Some(n))
+            }
+        };
+        if max.is_some_and(|max| max < min) {
+            return None;
+        }
+        Some((min, max, close - self.pos + 1))
+    }
+}
+
+enum Escape {
+    Char(char),
+    Class(ClassItem),
+    WordBoundary,
+}
+
+/// One instruction of a compiled pattern
+#[derive(Debug, Clone)]
+enum Inst {
+    /// Consume one char matching a `Char`, `Any` or `Class` node
+    Test(Node),
+    /// Check a `Start`, `End` or `WordBoundary` node without consuming
+    Assert(Node),
+    /// Continue at both targets, preferring the first
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+fn push(prog: &mut Vec<Inst>, inst: Inst) -> Result<usize, String> {
+    if prog.len() >= MAX_PROGRAM {
+        return Err("Pattern is too large".to_string());
+    }
+    prog.push(inst);
+    Ok(prog.len() - 1)
+}
+
+/// Set the target of the `Jmp` at `at`, or the second target of a `Split`
+fn patch(prog: &mut [Inst], at: usize, target: usize) {
+    match &mut prog[at] {
+        Inst::Split(_, second) | Inst::Jmp(second) => *second = target,
+        _ => unreachable!(),
+    }
+}
+
+fn compile(node: &Node, prog: &mut Vec<Inst>) -> Result<(), String> {
+    match node {
+        Node::Char(_) | Node::Any | Node::Class { .. } => {
+            push(prog, Inst::Test(node.clone()))?;
+        }
+        Node::Start | Node::End | Node::WordBoundary => {
+            push(prog, Inst::Assert(node.clone()))?;
+        }
+        Node::Group(inner) => compile(inner, prog)?,
+        Node::Concat(nodes) => {
+            for node in nodes {
+                compile(node, prog)?;
+            }
+        }
+        Node::Alt(branches) => {
+            let mut jumps = Vec::new();
+            let (last, rest) = branches.split_last().expect("alternation has branches");
+            for branch in rest {
+                let split = push(prog, Inst::Split(prog.len() + 1, 0))?;
+                compile(branch, prog)?;
+                jumps.push(push(prog, Inst::Jmp(0))?);
+                let next = prog.len();
+                patch(prog, split, next);
+            }
+            compile(last, prog)?;
+            let end = prog.len();
+            for jump in jumps {
+                patch(prog, jump, end);
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            for _ in 0..*min {
+                compile(node, prog)?;
+            }
+            // Each optional copy is a split between running the body and
+            // skipping past every remaining copy
+            let optional = |prog: &mut Vec<Inst>| -> Result<usize, String> {
+                let split = push(prog, Inst::Split(prog.len() + 1, 0))?;
+                compile(node, prog)?;
+                Ok(split)
+            };
+            let mut splits = Vec::new();
+            match max {
+                Some(max) => {
+                    for _ in *min..*max {
+                        splits.push(optional(prog)?);
+                    }
+                }
+                None => {
+                    let split = optional(prog)?;
+                    push(prog, Inst::Jmp(split))?;
+                    splits.push(split);
+                }
+            }
+            let end = prog.len();
+            for split in splits {
+                prog[split] = match greedy {
+                    true => Inst::Split(split + 1, end),
+                    false => Inst::Split(end, split + 1),
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+/// State for one search
+///
+/// A Pike VM: the threads alive at each position advance one char at a
+/// time, highest priority first, and threads that reach the same
+/// instruction are merged, so the work is bounded by text length times
+/// pattern size and nothing recurses.
+struct Matcher<'a> {
+    prog: &'a [Inst],
+    text: &'a [char],
+    ignore_case: bool,
+    steps: usize,
+    /// Position at which each instruction was last added to a thread list
+    seen: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+impl Matcher<'_> {
+    fn char_matches(&self, node: &Node, c: char) -> bool {
+        let folded = |test: &dyn Fn(char) -> bool| {
+            test(c) || self.ignore_case && c.to_lowercase().chain(c.to_uppercase()).any(test)
+        };
+        match node {
+            Node::Char(expected) => folded(&|c| c == *expected),
+            Node::Any => c != '\n',
+            Node::Class { items, negated } => {
+                folded(&|c| items.iter().any(|item| item.matches(c))) != *negated
+            }
+            _ => false,
+        }
+    }
+
+    fn assert(&self, node: &Node, pos: usize) -> bool {
+        match node {
+            Node::Start => pos == 0,
+            Node::End => pos == self.text.len(),
+            Node::WordBoundary => {
+                let before = pos > 0 && is_word(self.text[pos - 1]);
+                let after = self.text.get(pos).is_some_and(|&c| is_word(c));
+                before != after
+            }
+            _ => false,
+        }
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        self.steps += 1;
+        if self.steps > STEP_BUDGET {
+            return Err(format!(
+                "Pattern is too complex: gave up after {} steps",
+                STEP_BUDGET
+            ));
+        }
+        Ok(())
+    }
+
+    /// Add the thread at `pc` and everything reachable from it without
+    /// consuming a char to `threads`, in priority order
+    fn add(
+        &mut self,
+        threads: &mut Vec<(usize, usize)>,
+        pc: usize,
+        start: usize,
+        pos: usize,
+    ) -> Result<(), String> {
+        self.stack.push(pc);
+        while let Some(pc) = self.stack.pop() {
+            if self.seen[pc] == pos {
+                continue;
+            }
+            self.seen[pc] = pos;
+            self.step()?;
+            match &self.prog[pc] {
+                Inst::Jmp(target) => self.stack.push(*target),
+                Inst::Split(first, second) => {
+                    self.stack.push(*second);
+                    self.stack.push(*first);
+                }
+                Inst::Assert(node) => {
+                    if self.assert(node, pos) {
+                        self.stack.push(pc + 1);
+                    }
+                }
+                Inst::Test(_) | Inst::Match => threads.push((pc, start)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Leftmost match as `(start, end)`, starting only at 0 when `anchored`
+    /// and ending only at the end of the text when `full`
+    fn run(&mut self, anchored: bool, full: bool) -> Result<Option<(usize, usize)>, String> {
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        let mut found = None;
+        for pos in 0..=self.text.len() {
+            if found.is_none() && (pos == 0 || !anchored) {
+                self.add(&mut current, 0, pos, pos)?;
+            }
+            if current.is_empty() && (found.is_some() || anchored) {
+                break;
+            }
+            for &(pc, start) in &current {
+                self.step()?;
+                match &self.prog[pc] {
+                    Inst::Match => {
+                        if !full || pos == self.text.len() {
+                            // Lower-priority threads can't win any more
+                            found = Some((start, pos));
+                            break;
+                        }
+                    }
+                    Inst::Test(node) => {
+                        if let Some(&c) = self.text.get(pos) {
+                            if self.char_matches(node, c) {
+                                self.add(&mut next, pc + 1, start, pos + 1)?;
+                            }
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        Ok(found)
+    }
+}
+
+impl Regex {
+    pub(crate) fn new(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            pattern,
+        };
+        let root = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched )"));
+        }
+        let mut prog = Vec::new();
+        compile(&root, &mut prog)?;
+        push(&mut prog, Inst::Match)?;
+        Ok(Regex { prog, ignore_case })
+    }
+
+    /// A pattern matching `text` literally
+    pub(crate) fn literal(text: &str, ignore_case: bool) -> Regex {
+        let prog = text
+            .chars()
+            .map(|c| Inst::Test(Node::Char(c)))
+            .chain([Inst::Match])
+            .collect();
+        Regex { prog, ignore_case }
+    }
+
+    fn matcher<'a>(&'a self, text: &'a [char]) -> Matcher<'a> {
+        Matcher {
+            prog: &self.prog,
+            text,
+            ignore_case: self.ignore_case,
+            steps: 0,
+            seen: vec![usize::MAX; self.prog.len()],
+            stack: Vec::new(),
+        }
+    }
+
+    /// First match in `text` as a `(start, end)` pair of char indices, or
+    /// an error when the step budget runs out first
+    pub(crate) fn find(&self, text: &[char]) -> Result<Option<(usize, usize)>, String> {
+        self.matcher(text).run(false, false)
+    }
+
+    /// Whether the pattern matches all of `text`
+    pub(crate) fn is_full_match(&self, text: &[char]) -> Result<bool, String> {
+        Ok(self.matcher(text).run(true, true)?.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+        let text: Vec<char> = text.chars().collect();
+        Regex::new(pattern, false).unwrap().find(&text).unwrap()
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("b+", "abbbc"), Some((1, 4)));
+        assert_eq!(find(r"\d{3}-\d{4}", "call 555-1234 now"), Some((5, 13)));
+        assert_eq!(find("^(cat|dog)s?$", "dogs"), Some((0, 4)));
+        assert_eq!(find("^(cat|dog)s?$", "hotdogs"), None);
+        assert_eq!(find("[^a-c]+", "abcxyz"), Some((3, 6)));
+        assert_eq!(find(r"\bis\b", "this is"), Some((5, 7)));
+        assert_eq!(find("a.*?c", "abcbc"), Some((0, 3)));
+        assert_eq!(find("(a*)*b", "aaab"), Some((0, 4)));
+
+        let text: Vec<char> = "TOTAL".chars().collect();
+        let regex = Regex::new("tot[a-z]l", true).unwrap();
+        assert_eq!(regex.is_full_match(&text), Ok(true));
+        assert!(Regex::new("a(b", false).is_err());
+        assert!(Regex::new("*a", false).is_err());
+        assert!(Regex::new("a{100000}", false).is_err());
+    }
+
+    #[test]
+    fn test_long_text() {
+        // The longest text a cell can hold
+        let mut text = "a".repeat(32_767);
+        assert_eq!(find("a*b", &text), None);
+        assert_eq!(find("(a|aa)*$", &text), Some((0, 32_767)));
+        text.push('b');
+        assert_eq!(find("a*b", &text), Some((0, 32_768)));
+
+        let chars: Vec<char> = text.chars().collect();
+        let regex = Regex::new("(a?){1000}b", false).unwrap();
+        assert!(regex.find(&chars).unwrap_err().contains("too complex"));
+    }
+}
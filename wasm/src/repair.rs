@@ -0,0 +1,120 @@
+//! Best-effort repair of broken XLSX packages
+//!
+//! `Workbook.open_repaired` reads what it can from a package that would
+//! otherwise fail to open, much like Excel's "We found a problem with some
+//! content" recovery: a missing shared strings part leaves its cells blank,
+//! a sheet whose relationship is gone is looked for at its conventional
+//! path, and a sheet whose part is absent is dropped. Every fix is listed
+//! as a [`Repair`] so callers can show what was lost.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+/// One problem worked around while opening a package
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Repair {
+    /// "missing_part", "dangling_relationship" or "missing_content_type"
+    pub kind: String,
+    /// The part the problem was found in or about
+    pub part: Option<String>,
+    pub description: String,
+}
+
+impl Repair {
+    pub(crate) fn new(kind: &str, part: Option<&str>, description: String) -> Self {
+        Repair {
+            kind: kind.to_string(),
+            part: part.map(str::to_string),
+            description,
+        }
+    }
+}
+
+/// The generic type a `Default` gives any `.xml` part, which doesn't say
+/// what the part is
+const GENERIC_XML: &str = "application/xml";
+
+/// Content types declared in `[Content_Types].xml`
+#[derive(Debug, Default)]
+pub(crate) struct ContentTypes {
+    /// (extension, content type), extensions lowercased
+    defaults: Vec<(String, String)>,
+    /// Part names lowercased, without the leading `/`
+    overrides: Vec<String>,
+}
+
+impl ContentTypes {
+    pub(crate) fn parse(xml: &str) -> Self {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+        let mut types = ContentTypes::default();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                    let attr = |name: &[u8]| {
+                        e.attributes()
+                            .flatten()
+                            .find(|a| a.key.as_ref() == name)
+                            .map(|a| String::from_utf8_lossy(&a.value).to_lowercase())
+                    };
+                    match e.local_name().as_ref() {
+                        b"Default" => {
+                            if let (Some(ext), Some(ty)) =
+                                (attr(b"Extension"), attr(b"ContentType"))
+                            {
+                                types.defaults.push((ext, ty));
+                            }
+                        }
+                        b"Override" => {
+                            if let Some(name) = attr(b"PartName") {
+                                types
+                                    .overrides
+                                    .push(name.trim_start_matches('/').to_string());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+        types
+    }
+
+    /// Whether `part` has a content type more specific than generic XML
+    pub(crate) fn covers(&self, part: &str) -> bool {
+        let part = part.to_lowercase();
+        if self.overrides.contains(&part) {
+            return true;
+        }
+        let ext = part.rsplit_once('.').map_or("", |(_, ext)| ext);
+        self.defaults
+            .iter()
+            .any(|(e, ty)| e == ext && ty != GENERIC_XML)
+    }
+}
+
+/// Where the `index`th (0-based) worksheet conventionally lives
+pub(crate) fn default_sheet_path(index: usize) -> String {
+    format!("xl/worksheets/sheet{}.xml", index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_types() {
+        let types = ContentTypes::parse(
+            r#"<Types><Default Extension="xml" ContentType="application/xml"/>
+            <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+            <Override PartName="/xl/workbook.xml" ContentType="x"/></Types>"#,
+        );
+        assert!(types.covers("xl/workbook.xml"));
+        assert!(types.covers("xl/_rels/workbook.xml.rels"));
+        assert!(!types.covers("xl/worksheets/sheet1.xml"));
+        assert_eq!(default_sheet_path(1), "xl/worksheets/sheet2.xml");
+    }
+}
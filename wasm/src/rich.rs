@@ -0,0 +1,207 @@
+//! Rich-text shared strings
+//!
+//! `parse_shared_strings` flattens each item to its text. Cells that mix
+//! formats (a bold word, a red figure) store their string as `<r>` runs,
+//! each with its own `<rPr>` font properties; this parser keeps those runs,
+//! in the same shape `write_shared_strings` takes, so they survive a round
+//! trip. Phonetic guides (`<rPh>`) are left out, as in the plain text.
+
+use crate::memory;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::RichTextRun;
+use crate::xstring;
+use crate::ParsedFont;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A shared string item with its formatted runs
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RichSharedString {
+    /// The item's text, as `parse_shared_strings` gives it
+    pub text: String,
+    /// Runs in order; empty for plain text
+    pub runs: Vec<RichTextRun>,
+}
+
+/// Parse sharedStrings.xml keeping each item's runs and their fonts
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_shared_strings_rich(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        Ok(serde_wasm_bindgen::to_value(
+            &parse_shared_strings_rich_impl(xml),
+        )?)
+    })
+}
+
+pub(crate) fn parse_shared_strings_rich_impl(xml: &str) -> Vec<RichSharedString> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(false); // Preserve whitespace in strings
+
+    let mut strings = Vec::new();
+    // Text written straight in the item, outside any run
+    let mut plain = String::new();
+    let mut runs: Vec<RichTextRun> = Vec::new();
+    let (mut in_si, mut in_run, mut in_phonetic, mut in_t) = (false, false, false, false);
+    let mut font: Option<ParsedFont> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"si" => {
+                    in_si = true;
+                    plain.clear();
+                    runs.clear();
+                }
+                b"r" if in_si => {
+                    in_run = true;
+                    runs.push(RichTextRun {
+                        text: String::new(),
+                        font: None,
+                    });
+                }
+                b"rPr" if in_run => font = Some(ParsedFont::default()),
+                b"rPh" => in_phonetic = true,
+                b"t" if in_si && !in_phonetic => in_t = true,
+                _ => {
+                    if let Some(font) = font.as_mut() {
+                        read_run_property(font, &e);
+                    }
+                }
+            },
+            Ok(Event::Empty(e)) => {
+                if let Some(font) = font.as_mut() {
+                    read_run_property(font, &e);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"si" if in_si => {
+                    in_si = false;
+                    if !runs.is_empty() && !plain.is_empty() {
+                        let text = std::mem::take(&mut plain);
+                        runs.insert(0, RichTextRun { text, font: None });
+                    }
+                    for run in &mut runs {
+                        run.text = xstring::decode_xstring(&run.text).into_owned();
+                    }
+                    let text = match runs.is_empty() {
+                        true => xstring::decode_xstring(&plain).into_owned(),
+                        false => runs.iter().map(|run| run.text.as_str()).collect(),
+                    };
+                    strings.push(RichSharedString {
+                        text,
+                        runs: std::mem::take(&mut runs),
+                    });
+                    if memory::over_limit() {
+                        break;
+                    }
+                }
+                b"r" => in_run = false,
+                b"rPr" => {
+                    if let Some(run) = runs.last_mut() {
+                        run.font = font.take();
+                    }
+                }
+                b"rPh" => in_phonetic = false,
+                b"t" => in_t = false,
+                _ => {}
+            },
+            Ok(Event::Text(e)) if in_t => {
+                if let Ok(text) = e.unescape() {
+                    match runs.last_mut() {
+                        Some(run) if in_run => run.text.push_str(&text),
+                        _ => plain.push_str(&text),
+                    }
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// Apply one `<rPr>` child to the run's font; toggles honor `val="0"`
+fn read_run_property(font: &mut ParsedFont, e: &BytesStart) {
+    let val = e
+        .try_get_attribute("val")
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|val| val.into_owned());
+    let on = !matches!(val.as_deref(), Some("0" | "false" | "none"));
+    match e.local_name().as_ref() {
+        b"b" => font.bold = on,
+        b"i" => font.italic = on,
+        b"u" => font.underline = on,
+        b"strike" => font.strikethrough = on,
+        b"sz" => font.size = val.and_then(|val| val.parse().ok()),
+        b"rFont" => font.name = val,
+        b"color" => {
+            if let Ok(Some(rgb)) = e.try_get_attribute("rgb") {
+                font.color = rgb.unescape_value().ok().map(|rgb| rgb.into_owned());
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::{write_shared_strings_impl, SharedStringEntry};
+
+    #[test]
+    fn test_parse_shared_strings_rich() {
+        let xml = r#"<?xml version="1.0"?>
+        <sst xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+            <si><t>Plain_x000D_</t></si>
+            <si>
+                <r><rPr><b/><i val="0"/><sz val="11"/><color rgb="FFFF0000"/>
+                    <rFont val="Calibri"/></rPr><t>Bold</t></r>
+                <r><t xml:space="preserve"> text</t></r>
+            </si>
+            <si><t>東京</t><rPh sb="0" eb="2"><t>トウキョウ</t></rPh></si>
+        </sst>"#;
+
+        let strings = parse_shared_strings_rich_impl(xml);
+        assert_eq!(strings.len(), 3);
+        assert_eq!(strings[0].text, "Plain\r");
+        assert!(strings[0].runs.is_empty());
+        assert_eq!(strings[1].text, "Bold text");
+        let bold = ParsedFont {
+            bold: true,
+            size: Some(11.0),
+            color: Some("FFFF0000".to_string()),
+            name: Some("Calibri".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            strings[1].runs,
+            [
+                RichTextRun {
+                    text: "Bold".to_string(),
+                    font: Some(bold),
+                },
+                RichTextRun {
+                    text: " text".to_string(),
+                    font: None,
+                },
+            ]
+        );
+        assert_eq!(strings[2].text, "東京");
+
+        // The runs write back as they were read
+        let written = write_shared_strings_impl(&[SharedStringEntry::Rich(
+            strings.into_iter().nth(1).unwrap().runs,
+        )]);
+        assert!(written.xml.contains(
+            "<si><r><rPr><b/><sz val=\"11\"/><color rgb=\"FFFF0000\"/><rFont val=\"Calibri\"/>\
+             </rPr><t>Bold</t></r><r><t xml:space=\"preserve\"> text</t></r></si>"
+        ));
+    }
+}
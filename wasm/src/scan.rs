@@ -0,0 +1,39 @@
+//! Fast byte scanning helpers
+//!
+//! UTF-8 validation goes through simdutf8 and signature searches through
+//! memchr; both use wasm SIMD when the module is built with `+simd128`
+//! (see build.sh) and fall back to scalar code otherwise.
+
+use std::borrow::Cow;
+
+/// Decode UTF-8, replacing invalid sequences like `String::from_utf8_lossy`
+///
+/// Valid input, by far the common case for package parts, is checked with
+/// the vectorized validator and borrowed as-is.
+pub(crate) fn utf8_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    match simdutf8::basic::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => String::from_utf8_lossy(bytes),
+    }
+}
+
+/// Offset of the last occurrence of `needle` in `haystack`
+pub(crate) fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memchr::memmem::rfind(haystack, needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        assert!(matches!(
+            utf8_lossy("héllo".as_bytes()),
+            Cow::Borrowed("héllo")
+        ));
+        assert_eq!(utf8_lossy(b"a\xffb"), "a\u{fffd}b");
+        assert_eq!(rfind(b"PK..PK..", b"PK"), Some(4));
+        assert_eq!(rfind(b"abc", b"PK"), None);
+    }
+}
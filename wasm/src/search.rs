@@ -0,0 +1,287 @@
+//! Workbook-wide find
+//!
+//! Cell values, formulas and comments are matched in one pass over the
+//! parsed sheets, so find-as-you-type stays responsive on large workbooks.
+
+use crate::reference::parse_cell_ref;
+use crate::regex::Regex;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{format_number, resolve_cell_value, CellValue};
+use crate::{ParsedSheet, ParsedWorksheet};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Workbook contents searched by [`search`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchWorkbook {
+    pub sheets: Vec<ParsedSheet>,
+    #[serde(default)]
+    pub shared_strings: Vec<String>,
+    #[serde(default)]
+    pub comments: Vec<CellComment>,
+}
+
+/// A cell comment (note) to include in the search
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CellComment {
+    pub sheet: String,
+    pub reference: String,
+    pub text: String,
+}
+
+/// Options for [`search`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub match_case: bool,
+    /// Only match when the query covers the whole text
+    pub whole_cell: bool,
+    /// Treat the query as a regular expression
+    pub regex: bool,
+    /// Any of "values", "formulas" and "comments"
+    pub scope: Vec<String>,
+    /// Stop after this many matches
+    pub limit: Option<usize>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            match_case: false,
+            whole_cell: false,
+            regex: false,
+            scope: vec!["values".to_string()],
+            limit: None,
+        }
+    }
+}
+
+/// A match location; `start` and `end` are UTF-16 offsets into `text`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SearchMatch {
+    pub sheet: String,
+    pub reference: String,
+    /// 1-based row
+    pub row: u32,
+    /// 1-based column
+    pub col: u32,
+    /// "value", "formula" or "comment"
+    pub scope: String,
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Find `query` across a workbook, returning matches in sheet, row and column order
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn search(workbook: JsValue, query: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let workbook: SearchWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        let options: Option<SearchOptions> = serde_wasm_bindgen::from_value(options)?;
+        let sheets = workbook
+            .sheets
+            .iter()
+            .map(|sheet| (sheet.name.as_str(), &sheet.worksheet));
+        let matches = search_impl(
+            sheets,
+            &workbook.shared_strings,
+            &workbook.comments,
+            query,
+            &options.unwrap_or_default(),
+        )
+        .map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&matches)?)
+    })
+}
+
+/// Text of a resolved value as it is searched
+fn value_text(value: CellValue) -> Option<String> {
+    match value {
+        CellValue::Empty => None,
+        CellValue::Number(number) => Some(format_number(number)),
+        CellValue::Text(text) | CellValue::Error(text) => Some(text),
+        CellValue::Bool(value) => Some(if value { "TRUE" } else { "FALSE" }.to_string()),
+    }
+}
+
+/// Position of the first match in `text` as UTF-16 offsets
+fn find(pattern: &Regex, whole_cell: bool, text: &str) -> Result<Option<(usize, usize)>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let found = if whole_cell {
+        pattern.is_full_match(&chars)?.then_some((0, chars.len()))
+    } else {
+        pattern.find(&chars)?
+    };
+    let utf16 = |chars: &[char]| chars.iter().map(|c| c.len_utf16()).sum::<usize>();
+    Ok(found.map(|(start, end)| {
+        let start_offset = utf16(&chars[..start]);
+        (start_offset, start_offset + utf16(&chars[start..end]))
+    }))
+}
+
+pub(crate) fn search_impl<'a>(
+    sheets: impl Iterator<Item = (&'a str, &'a ParsedWorksheet)>,
+    shared_strings: &[String],
+    comments: &[CellComment],
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SearchMatch>, String> {
+    let in_scope = |scope: &str| options.scope.iter().any(|s| s == scope);
+    for scope in &options.scope {
+        if !matches!(scope.as_str(), "values" | "formulas" | "comments") {
+            return Err(format!("Unknown search scope: {}", scope));
+        }
+    }
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pattern = if options.regex {
+        Regex::new(query, !options.match_case)?
+    } else {
+        Regex::literal(query, !options.match_case)
+    };
+    let limit = options.limit.unwrap_or(usize::MAX);
+
+    let mut matches = Vec::new();
+    for (name, worksheet) in sheets {
+        let mut found = Vec::new();
+        let mut check = |row: u32, col: u32, rank: u8, reference: &str, text: &str| {
+            if let Some((start, end)) = find(&pattern, options.whole_cell, text)? {
+                let scope = ["value", "formula", "comment"][rank as usize];
+                found.push((
+                    (row, col, rank),
+                    SearchMatch {
+                        sheet: name.to_string(),
+                        reference: reference.to_string(),
+                        row,
+                        col,
+                        scope: scope.to_string(),
+                        text: text.to_string(),
+                        start,
+                        end,
+                    },
+                ));
+            }
+            Ok::<_, String>(())
+        };
+
+        for cell in worksheet.rows.iter().flat_map(|row| &row.cells) {
+            let Some((row, col)) = parse_cell_ref(&cell.reference) else {
+                continue;
+            };
+            if in_scope("values") {
+                if let Some(text) = value_text(resolve_cell_value(cell, shared_strings)) {
+                    check(row, col, 0, &cell.reference, &text)?;
+                }
+            }
+            if in_scope("formulas") {
+                if let Some(formula) = &cell.formula {
+                    check(row, col, 1, &cell.reference, &format!("={}", formula))?;
+                }
+            }
+        }
+        if in_scope("comments") {
+            for comment in comments
+                .iter()
+                .filter(|c| c.sheet.eq_ignore_ascii_case(name))
+            {
+                if let Some((row, col)) = parse_cell_ref(&comment.reference) {
+                    check(row, col, 2, &comment.reference, &comment.text)?;
+                }
+            }
+        }
+
+        found.sort_by_key(|(key, _)| *key);
+        for (_, found) in found {
+            if matches.len() >= limit {
+                return Ok(matches);
+            }
+            matches.push(found);
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParsedCell, ParsedRow};
+
+    fn cell(reference: &str, value: &str, formula: Option<&str>) -> ParsedCell {
+        ParsedCell {
+            reference: reference.to_string(),
+            cell_type: Some("str".to_string()),
+            style_index: None,
+            value: Some(value.to_string()),
+            formula: formula.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let worksheet = ParsedWorksheet {
+            rows: vec![ParsedRow {
+                row_num: 1,
+                cells: vec![
+                    cell("B1", "Total revenue", Some("\"Total \"&\"revenue\"")),
+                    cell("A1", "total", None),
+                ],
+                height: None,
+                hidden: false,
+            }],
+            ..Default::default()
+        };
+        let comments = vec![CellComment {
+            sheet: "Data".to_string(),
+            reference: "A1".to_string(),
+            text: "Check the TOTAL".to_string(),
+        }];
+        let run = |query: &str, options: SearchOptions| {
+            search_impl(
+                [("Data", &worksheet)].into_iter(),
+                &[],
+                &comments,
+                query,
+                &options,
+            )
+            .unwrap()
+        };
+
+        let found = run("total", SearchOptions::default());
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].reference.as_str(), found[0].end), ("A1", 5));
+
+        let found = run(
+            "total",
+            SearchOptions {
+                match_case: true,
+                whole_cell: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(found.len(), 1);
+
+        let found = run(
+            r"rev\w+",
+            SearchOptions {
+                regex: true,
+                scope: vec!["formulas".to_string(), "comments".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].scope.as_str(), found[0].start), ("formula", 11));
+
+        let found = run(
+            "total",
+            SearchOptions {
+                scope: vec!["comments".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!((found[0].start, found[0].end), (10, 15));
+    }
+}
@@ -0,0 +1,221 @@
+//! Object-style access to a [`Workbook`](crate::Workbook)
+//!
+//! [`Sheet`] and [`Cell`] handles share the workbook's parsed data in wasm
+//! memory, so JS can walk it as objects (`workbook.sheet("Data").cell("B2")
+//! .value()`) while only the values it asks for cross into JS. A handle
+//! keeps the data alive after the workbook itself is freed.
+
+use crate::reference::{bounds_ref, cell_ref, parse_cell_ref, range_bounds, used_range};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::workbook::{Book, CellData, SheetIndex};
+use crate::ParsedCell;
+use std::rc::Rc;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// One sheet of a workbook
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Sheet {
+    book: Rc<Book>,
+    index: usize,
+}
+
+/// One cell of a sheet, which reads as empty when the file doesn't set it
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Cell {
+    book: Rc<Book>,
+    sheet: usize,
+    row: u32,
+    col: u32,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Sheet {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.parsed().name.clone()
+    }
+
+    /// Position in the workbook, 0-based
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// A cell by local reference, e.g. `sheet.cell("B2")`
+    pub fn cell(&self, reference: &str) -> Result<Cell, JsValue> {
+        trap(|| self.cell_at(reference).map_err(|e| JsValue::from_str(&e)))
+    }
+
+    /// Read a range, e.g. "A1:D100", as rows of `{ reference, type, value, formula }`
+    ///
+    /// Rows and columns past the sheet's last used cell are left out.
+    pub fn range(&self, reference: &str) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let rows = self
+                .range_data(reference)
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(serde_wasm_bindgen::to_value(&rows)?)
+        })
+    }
+
+    /// Read a 1-based row from column A to its last used cell
+    pub fn row(&self, row: u32) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let cells = self
+                .book
+                .sheet_row(self.parsed(), row)
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(serde_wasm_bindgen::to_value(&cells)?)
+        })
+    }
+
+    /// Merged ranges, e.g. `["A1:B2"]`
+    #[wasm_bindgen(js_name = mergedRanges)]
+    pub fn merged_ranges(&self) -> Vec<String> {
+        self.parsed().worksheet.merge_cells.clone()
+    }
+
+    /// The range spanning every cell, e.g. "B2:F40"; undefined for an empty sheet
+    #[wasm_bindgen(js_name = usedRange)]
+    pub fn used_range(&self) -> Option<String> {
+        self.used_range_ref()
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Cell {
+    /// A1 reference, e.g. "B2"
+    #[wasm_bindgen(getter)]
+    pub fn reference(&self) -> String {
+        cell_ref(self.row, self.col)
+    }
+
+    /// 1-based row number
+    #[wasm_bindgen(getter)]
+    pub fn row(&self) -> u32 {
+        self.row
+    }
+
+    /// 1-based column number
+    #[wasm_bindgen(getter)]
+    pub fn column(&self) -> u32 {
+        self.col
+    }
+
+    /// The value as a number, string, boolean or ISO 8601 date string;
+    /// undefined when empty
+    pub fn value(&self) -> Result<JsValue, JsValue> {
+        trap(|| Ok(serde_wasm_bindgen::to_value(&self.data().value)?))
+    }
+
+    /// "number", "string", "boolean", "error", "date" or "empty"
+    #[wasm_bindgen(js_name = "type")]
+    pub fn value_type(&self) -> String {
+        self.data().value_type
+    }
+
+    pub fn formula(&self) -> Option<String> {
+        self.parsed().and_then(|cell| cell.formula.clone())
+    }
+
+    /// Index into the workbook's cell formats
+    #[wasm_bindgen(js_name = styleIndex)]
+    pub fn style_index(&self) -> Option<u32> {
+        self.parsed().and_then(|cell| cell.style_index)
+    }
+}
+
+impl Sheet {
+    pub(crate) fn new(book: Rc<Book>, index: usize) -> Self {
+        Sheet { book, index }
+    }
+
+    fn parsed(&self) -> &SheetIndex {
+        &self.book.sheets[self.index]
+    }
+
+    pub(crate) fn cell_at(&self, reference: &str) -> Result<Cell, String> {
+        let (row, col) =
+            parse_cell_ref(reference).ok_or(format!("Invalid cell reference: {}", reference))?;
+        Ok(Cell {
+            book: self.book.clone(),
+            sheet: self.index,
+            row,
+            col,
+        })
+    }
+
+    pub(crate) fn range_data(&self, reference: &str) -> Result<Vec<Vec<CellData>>, String> {
+        let bounds = range_bounds(reference).ok_or(format!("Invalid range: {}", reference))?;
+        Ok(self.book.sheet_range(self.parsed(), bounds))
+    }
+
+    pub(crate) fn used_range_ref(&self) -> Option<String> {
+        used_range(&self.parsed().worksheet).map(bounds_ref)
+    }
+}
+
+impl Cell {
+    pub(crate) fn data(&self) -> CellData {
+        let sheet = &self.book.sheets[self.sheet];
+        self.book.cell_data(sheet, self.row, self.col)
+    }
+
+    fn parsed(&self) -> Option<&ParsedCell> {
+        self.book.sheets[self.sheet].parsed_cell(self.row, self.col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workbook::{TypedValue, Workbook, WorkbookData};
+    use crate::{ParsedCell, ParsedRow, ParsedSheet, ParsedWorksheet};
+
+    #[test]
+    fn test_sheet_handles() {
+        let cell = |reference: &str, value: &str| ParsedCell {
+            reference: reference.to_string(),
+            cell_type: None,
+            style_index: Some(2),
+            value: Some(value.to_string()),
+            formula: None,
+        };
+        let worksheet = ParsedWorksheet {
+            rows: vec![ParsedRow {
+                row_num: 2,
+                cells: vec![cell("B2", "1"), cell("D2", "4")],
+                height: None,
+                hidden: false,
+            }],
+            merge_cells: vec!["B2:C2".to_string()],
+            ..Default::default()
+        };
+        let workbook = Workbook::from_data(WorkbookData {
+            sheets: vec![ParsedSheet {
+                name: "Data".to_string(),
+                state: None,
+                worksheet,
+            }],
+            shared_strings: Vec::new(),
+            styles: None,
+            date1904: false,
+            defined_names: Vec::new(),
+        });
+
+        let sheet = workbook.sheet_handle(Some("data")).unwrap();
+        assert!(workbook.sheet_handle(Some("Other")).is_err());
+        // Handles outlive the workbook they came from
+        drop(workbook);
+        assert_eq!(sheet.used_range_ref().as_deref(), Some("B2:D2"));
+        let d2 = sheet.cell_at("D2").unwrap();
+        assert_eq!(d2.data().value, Some(TypedValue::Number(4.0)));
+        assert_eq!(d2.parsed().and_then(|c| c.style_index), Some(2));
+        assert_eq!(sheet.cell_at("A9").unwrap().data().value_type, "empty");
+        assert_eq!(sheet.range_data("A1:Z9").unwrap()[1].len(), 4);
+        assert!(sheet.cell_at("9A").is_err());
+    }
+}
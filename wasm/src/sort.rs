@@ -0,0 +1,421 @@
+//! Row sorting
+//!
+//! Reorders a parsed worksheet's rows by the values of key columns the way
+//! Excel's sort does: numbers (dates included, being serials) before text,
+//! then false and true, then errors, with blank cells last whichever the
+//! direction. The sort is stable, so rows with equal keys keep their order.
+//! Whole rows move, their formulas re-anchored as when cut and pasted, along
+//! with the hyperlinks, comments and one-row merges on them; formulas
+//! elsewhere that point into the sorted rows are left as they are.
+
+use crate::formula::translate_formula;
+use crate::reference::{bounds_ref, cell_ref, parse_cell_ref, parse_col, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{resolve_cell_value, CellValue};
+use crate::ParsedWorksheet;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A column to sort by
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SortKey {
+    /// Column letters, e.g. "C"
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// Options for [`sort_rows`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortOptions {
+    /// First row sorted (1-based), e.g. 2 to keep a header row in place
+    pub first_row: Option<u32>,
+    /// Last row sorted; defaults to the last row with cells
+    pub last_row: Option<u32>,
+    /// Tell case apart, lowercase first ("a" before "A"), rather than
+    /// treating it as equal
+    pub case_sensitive: bool,
+    /// BCP 47 locale ("de", "sv-SE") whose collation orders text, through
+    /// `Intl.Collator`; text is otherwise compared by code point after case
+    /// folding
+    pub locale: Option<String>,
+    pub shared_strings: Vec<String>,
+}
+
+/// A text order, in place of the built-in comparison
+type Collate<'a> = &'a dyn Fn(&str, &str) -> Ordering;
+
+/// A key cell's value as sorted; text holds its rank among the key texts
+#[derive(Debug, PartialEq)]
+enum SortValue {
+    Number(f64),
+    Text(usize),
+    Bool(bool),
+    Error,
+    Empty,
+}
+
+impl SortValue {
+    fn class(&self) -> u8 {
+        match self {
+            SortValue::Number(_) => 0,
+            SortValue::Text(_) => 1,
+            SortValue::Bool(_) => 2,
+            SortValue::Error => 3,
+            SortValue::Empty => 4,
+        }
+    }
+
+    /// Ascending order; blanks come last in either direction
+    fn compare(&self, other: &SortValue, descending: bool) -> Ordering {
+        let order = match (self, other) {
+            (SortValue::Empty, SortValue::Empty) => return Ordering::Equal,
+            (SortValue::Empty, _) => return Ordering::Greater,
+            (_, SortValue::Empty) => return Ordering::Less,
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (SortValue::Bool(a), SortValue::Bool(b)) => a.cmp(b),
+            _ => self.class().cmp(&other.class()),
+        };
+        match descending {
+            true => order.reverse(),
+            false => order,
+        }
+    }
+}
+
+/// Sort `worksheet`'s rows by `keys` (first key first), returning the
+/// reordered worksheet
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn sort_rows(worksheet: JsValue, keys: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let mut worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let keys: Vec<SortKey> = serde_wasm_bindgen::from_value(keys)?;
+        let options: Option<SortOptions> = serde_wasm_bindgen::from_value(options)?;
+        let options = options.unwrap_or_default();
+        let collator = match &options.locale {
+            Some(locale) => Some(locale_collator(locale, options.case_sensitive)?),
+            None => None,
+        };
+        let collate = collator.as_ref().map(|compare| {
+            move |a: &str, b: &str| {
+                compare
+                    .call2(&JsValue::NULL, &JsValue::from_str(a), &JsValue::from_str(b))
+                    .ok()
+                    .and_then(|order| order.as_f64())
+                    .and_then(|order| order.partial_cmp(&0.0))
+                    .unwrap_or(Ordering::Equal)
+            }
+        });
+        let collate = collate.as_ref().map(|c| c as Collate);
+        sort_rows_impl(&mut worksheet, &keys, &options, collate)
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&worksheet)?)
+    })
+}
+
+/// `Intl.Collator(locale).compare`, built through `Reflect` so that an
+/// invalid locale is an error rather than a throw across the module
+#[cfg(feature = "wasm")]
+fn locale_collator(locale: &str, case_sensitive: bool) -> Result<js_sys::Function, JsValue> {
+    let intl = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("Intl"))?;
+    let constructor: js_sys::Function =
+        js_sys::Reflect::get(&intl, &JsValue::from_str("Collator"))?
+            .dyn_into()
+            .map_err(|_| JsValue::from_str("Intl.Collator is not available"))?;
+    let options = js_sys::Object::new();
+    let sensitivity = match case_sensitive {
+        true => "variant",
+        false => "accent",
+    };
+    js_sys::Reflect::set(
+        &options,
+        &JsValue::from_str("sensitivity"),
+        &JsValue::from_str(sensitivity),
+    )?;
+    js_sys::Reflect::set(
+        &options,
+        &JsValue::from_str("caseFirst"),
+        &JsValue::from_str("lower"),
+    )?;
+    let args = js_sys::Array::of2(&JsValue::from_str(locale), &options);
+    let collator = js_sys::Reflect::construct(&constructor, &args)?;
+    js_sys::Reflect::get(&collator, &JsValue::from_str("compare"))?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("Intl.Collator has no compare function"))
+}
+
+/// Sort the rows in place; `collate` orders text in place of the built-in
+/// comparison
+pub(crate) fn sort_rows_impl(
+    worksheet: &mut ParsedWorksheet,
+    keys: &[SortKey],
+    options: &SortOptions,
+    collate: Option<Collate>,
+) -> Result<(), String> {
+    let columns = keys
+        .iter()
+        .map(|key| parse_col(&key.column).ok_or(format!("Invalid column: {}", key.column)))
+        .collect::<Result<Vec<u32>, String>>()?;
+    if columns.is_empty() {
+        return Err("At least one sort key is required".to_string());
+    }
+    let first_row = options.first_row.unwrap_or(1);
+    let last_row = options.last_row.unwrap_or(u32::MAX);
+    let in_range = |row: u32| row >= first_row && row <= last_row;
+
+    // Merges spanning rows would be split apart
+    for merge in &worksheet.merge_cells {
+        if let Some((r1, _, r2, _)) = range_bounds(merge) {
+            if r1 != r2 && r2 >= first_row && r1 <= last_row {
+                return Err(format!("Can't sort rows across merged cells: {}", merge));
+            }
+        }
+    }
+
+    let start = worksheet
+        .rows
+        .partition_point(|row| row.row_num < first_row);
+    let end = worksheet
+        .rows
+        .partition_point(|row| row.row_num <= last_row);
+    if start >= end {
+        return Ok(());
+    }
+    let mut rows: Vec<_> = worksheet.rows.drain(start..end).collect();
+
+    let values: Vec<Vec<CellValue>> = rows
+        .iter()
+        .map(|row| {
+            let mut by_col: HashMap<u32, CellValue> = row
+                .cells
+                .iter()
+                .filter_map(|cell| {
+                    let (_, col) = parse_cell_ref(&cell.reference)?;
+                    Some((col, resolve_cell_value(cell, &options.shared_strings)))
+                })
+                .collect();
+            columns
+                .iter()
+                .map(|col| by_col.remove(col).unwrap_or(CellValue::Empty))
+                .collect()
+        })
+        .collect();
+
+    // Rank each distinct text once, so sorting compares integers
+    let mut texts: Vec<&str> = values
+        .iter()
+        .flatten()
+        .filter_map(|value| match value {
+            CellValue::Text(text) if !text.is_empty() => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    texts.sort_unstable();
+    texts.dedup();
+    let builtin = |a: &str, b: &str| compare_text(a, b, options.case_sensitive);
+    let collate = collate.unwrap_or(&builtin);
+    texts.sort_by(|a, b| collate(a, b));
+    let mut ranks: HashMap<&str, usize> = HashMap::new();
+    for (i, text) in texts.iter().enumerate() {
+        let rank = match i {
+            0 => 0,
+            _ if collate(texts[i - 1], text) == Ordering::Equal => ranks[texts[i - 1]],
+            _ => i,
+        };
+        ranks.insert(text, rank);
+    }
+
+    let sort_values: Vec<Vec<SortValue>> = values
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| match value {
+                    CellValue::Number(n) => SortValue::Number(*n),
+                    CellValue::Text(text) if text.is_empty() => SortValue::Empty,
+                    CellValue::Text(text) => SortValue::Text(ranks[text.as_str()]),
+                    CellValue::Bool(b) => SortValue::Bool(*b),
+                    CellValue::Error(_) => SortValue::Error,
+                    CellValue::Empty => SortValue::Empty,
+                })
+                .collect()
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by(|&a, &b| {
+        keys.iter()
+            .enumerate()
+            .map(|(k, key)| sort_values[a][k].compare(&sort_values[b][k], key.descending))
+            .find(|order| order.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    // Sorted rows fill the range from its first row, rows without cells
+    // dropping to the end
+    let mut moved: HashMap<u32, u32> = HashMap::new();
+    let mut slots: Vec<_> = rows.drain(..).map(Some).collect();
+    for (i, index) in order.into_iter().enumerate() {
+        let Some(mut row) = slots[index].take() else {
+            continue;
+        };
+        let to = first_row + i as u32;
+        moved.insert(row.row_num, to);
+        for cell in &mut row.cells {
+            let Some((from, col)) = parse_cell_ref(&cell.reference) else {
+                continue;
+            };
+            if let Some(formula) = &cell.formula {
+                if let Some(formula) = translate_formula(formula, (from, col), (to, col)) {
+                    cell.formula = Some(formula);
+                }
+            }
+            cell.reference = cell_ref(to, col);
+        }
+        row.row_num = to;
+        rows.push(row);
+    }
+    worksheet.rows.splice(start..start, rows);
+
+    // Anything anchored to one row of the range moves with it
+    let move_range = |range: &mut String| {
+        if let Some((r1, c1, r2, c2)) = range_bounds(range) {
+            if r1 == r2 && in_range(r1) {
+                if let Some(&to) = moved.get(&r1) {
+                    *range = bounds_ref((to, c1, to, c2));
+                }
+            }
+        }
+    };
+    for merge in &mut worksheet.merge_cells {
+        move_range(merge);
+    }
+    for link in &mut worksheet.hyperlinks {
+        move_range(&mut link.reference);
+    }
+    #[cfg(feature = "comments")]
+    for comment in &mut worksheet.comments {
+        move_range(&mut comment.reference);
+    }
+    Ok(())
+}
+
+/// Built-in text order: case-folded code points, with lowercase before
+/// uppercase breaking ties when `case_sensitive`
+fn compare_text(a: &str, b: &str, case_sensitive: bool) -> Ordering {
+    let folded = a.to_lowercase().cmp(&b.to_lowercase());
+    match case_sensitive {
+        true => folded.then_with(|| {
+            let upper = |s: &str| s.chars().map(char::is_uppercase).collect::<Vec<_>>();
+            upper(a).cmp(&upper(b))
+        }),
+        false => folded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cell, row};
+    #[cfg(feature = "comments")]
+    use crate::ParsedComment;
+
+    #[test]
+    fn test_sort_rows() {
+        let rows = [
+            ("Name", Some("str"), "Score"),
+            ("bob", Some("str"), "3"),
+            ("Ann", Some("str"), "n/a"),
+            ("1", None, "3"),
+            ("", None, "7"),
+            ("TRUE", Some("b"), "1"),
+            ("ann", Some("str"), "5"),
+        ];
+        let mut worksheet = ParsedWorksheet {
+            rows: rows
+                .iter()
+                .enumerate()
+                .map(|(i, &(name, name_type, score))| {
+                    let row_num = i as u32 + 1;
+                    let mut cells = Vec::new();
+                    if !name.is_empty() {
+                        cells.push(cell(&format!("A{}", row_num), name_type, name));
+                    }
+                    let score_type = (score == "n/a").then_some("str");
+                    cells.push(cell(&format!("B{}", row_num), score_type, score));
+                    let mut total = cell(&format!("C{}", row_num), None, "0");
+                    total.formula = Some(format!("B{}*2+$B$1", row_num));
+                    cells.push(total);
+                    row(row_num, cells)
+                })
+                .collect(),
+            merge_cells: vec!["C2:D2".to_string()],
+            #[cfg(feature = "comments")]
+            comments: vec![ParsedComment {
+                reference: "A2".to_string(),
+                author: None,
+                text: "First".to_string(),
+                threaded: false,
+                replies: Vec::new(),
+                date: None,
+                resolved: false,
+            }],
+            ..Default::default()
+        };
+        let key = |column: &str, descending| SortKey {
+            column: column.to_string(),
+            descending,
+        };
+        let options = SortOptions {
+            first_row: Some(2),
+            ..Default::default()
+        };
+        let names = |worksheet: &ParsedWorksheet| {
+            worksheet
+                .rows
+                .iter()
+                .map(|row| {
+                    let a = row.cells.iter().find(|c| c.reference.starts_with('A'));
+                    a.and_then(|c| c.value.as_deref()).unwrap_or("")
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        sort_rows_impl(&mut worksheet, &[key("A", false)], &options, None).unwrap();
+        assert_eq!(names(&worksheet), "Name,1,Ann,ann,bob,TRUE,");
+        // Formulas, merges and comments moved with their rows
+        let bob = &worksheet.rows[4];
+        assert_eq!(bob.row_num, 5);
+        assert_eq!(bob.cells[2].reference, "C5");
+        assert_eq!(bob.cells[2].formula.as_deref(), Some("B5*2+$B$1"));
+        assert_eq!(worksheet.merge_cells, ["C5:D5"]);
+        #[cfg(feature = "comments")]
+        assert_eq!(worksheet.comments[0].reference, "A5");
+
+        // Descending keeps blanks last; ties on the first key fall to the
+        // second, and otherwise keep their order
+        let keys = [key("B", true), key("a", false)];
+        sort_rows_impl(&mut worksheet, &keys, &options, None).unwrap();
+        assert_eq!(names(&worksheet), "Name,Ann,,ann,1,bob,TRUE");
+        let options = SortOptions {
+            case_sensitive: true,
+            ..options
+        };
+        sort_rows_impl(&mut worksheet, &[key("A", false)], &options, None).unwrap();
+        assert_eq!(names(&worksheet), "Name,1,ann,Ann,bob,TRUE,");
+
+        let reverse = |a: &str, b: &str| b.cmp(a);
+        sort_rows_impl(&mut worksheet, &[key("A", false)], &options, Some(&reverse)).unwrap();
+        assert_eq!(names(&worksheet), "Name,1,bob,ann,Ann,TRUE,");
+
+        worksheet.merge_cells.push("A6:A7".to_string());
+        assert!(sort_rows_impl(&mut worksheet, &[key("A", false)], &options, None).is_err());
+        assert!(sort_rows_impl(&mut worksheet, &[], &options, None).is_err());
+    }
+}
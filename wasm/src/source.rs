@@ -0,0 +1,280 @@
+//! XML event sources for the worksheet parser
+//!
+//! The worksheet parser runs over either a complete string, where events
+//! borrow straight from the input, or a `BufRead` stream such as a ZIP
+//! entry being inflated, where events are read into a reused buffer.
+//!
+//! quick-xml stops for good at the first error, so both sources can
+//! `resync`: restart reading at the next `<row>` or `<c>` start tag and
+//! let the parser carry on past a malformed stretch.
+
+use memchr::{memchr, memchr_iter, memrchr};
+use quick_xml::events::Event;
+use quick_xml::name::QName;
+use quick_xml::Reader;
+use std::cell::Cell;
+use std::io::{self, BufRead, Cursor, Read};
+use std::rc::Rc;
+
+pub(crate) trait XmlSource {
+    fn next_event(&mut self) -> quick_xml::Result<Event<'_>>;
+
+    /// Skip past the end tag matching an element that just started
+    fn skip_to_end(&mut self, name: &[u8]);
+
+    /// Byte offset reached in the input
+    fn position(&self) -> u64;
+
+    /// 1-based line and column of `offset`, approximate for streamed input
+    fn line_column(&self, offset: u64) -> (u32, u32);
+
+    /// Restart at the next row or cell start tag after an error, returning
+    /// its offset; `None` when the input has no more
+    fn resync(&mut self) -> Option<u64>;
+}
+
+fn worksheet_reader<R>(input: R) -> Reader<R> {
+    let mut reader = Reader::from_reader(input);
+    reader.trim_text(true);
+    // Self-closing rows and cells (`<c r="B2" s="3"/>`) need their End handling too
+    reader.expand_empty_elements(true);
+    reader
+}
+
+/// A reader picking up mid-document, where end tags of elements opened
+/// before it started are expected
+fn resumed_reader<R>(input: R) -> Reader<R> {
+    let mut reader = worksheet_reader(input);
+    reader.check_end_names(false);
+    reader
+}
+
+/// Whether a tag named `name` (namespace prefix allowed) opens a row or cell
+fn is_boundary(name: &[u8]) -> bool {
+    let local = match name.iter().rposition(|&b| b == b':') {
+        Some(colon) => &name[colon + 1..],
+        None => name,
+    };
+    local == b"row" || local == b"c"
+}
+
+fn ends_name(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || byte == b'>' || byte == b'/'
+}
+
+/// A complete document in memory
+pub(crate) struct StrSource<'a> {
+    input: &'a [u8],
+    reader: Reader<&'a [u8]>,
+    /// Error from `skip_to_end`, reported by the next `next_event`
+    error: Option<quick_xml::Error>,
+}
+
+impl<'a> StrSource<'a> {
+    pub(crate) fn new(xml: &'a str) -> Self {
+        StrSource {
+            input: xml.as_bytes(),
+            reader: worksheet_reader(xml.as_bytes()),
+            error: None,
+        }
+    }
+}
+
+impl<'a> XmlSource for StrSource<'a> {
+    fn next_event(&mut self) -> quick_xml::Result<Event<'_>> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.reader.read_event(),
+        }
+    }
+
+    fn skip_to_end(&mut self, name: &[u8]) {
+        if let Err(error) = self.reader.read_to_end(QName(name)) {
+            self.error = Some(error);
+        }
+    }
+
+    fn position(&self) -> u64 {
+        // The reader advances its slice as it goes
+        (self.input.len() - self.reader.get_ref().len()) as u64
+    }
+
+    fn line_column(&self, offset: u64) -> (u32, u32) {
+        let before = &self.input[..(offset as usize).min(self.input.len())];
+        let line = memchr_iter(b'\n', before).count() as u32 + 1;
+        let line_start = memrchr(b'\n', before).map_or(0, |newline| newline + 1);
+        (line, (before.len() - line_start) as u32 + 1)
+    }
+
+    fn resync(&mut self) -> Option<u64> {
+        let rest: &'a [u8] = self.reader.get_ref();
+        let start = self.input.len() - rest.len();
+        let mut pos = 0;
+        while let Some(offset) = memchr(b'<', &rest[pos..]) {
+            let open = pos + offset;
+            let name = &rest[open + 1..];
+            pos = open + 1;
+            if let Some(end) = name.iter().position(|&b| ends_name(b) || b == b'<') {
+                if name[end] != b'<' && is_boundary(&name[..end]) {
+                    self.reader = resumed_reader(&rest[open..]);
+                    self.error = None;
+                    return Some((start + open) as u64);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Lines seen in the bytes consumed from a stream
+#[derive(Debug, Clone, Copy, Default)]
+struct Lines {
+    consumed: u64,
+    newlines: u32,
+    /// Offset just past the last newline
+    line_start: u64,
+}
+
+/// Counts lines as bytes are consumed, since streamed input can't be looked back at
+struct LineCounter<R> {
+    inner: R,
+    lines: Rc<Cell<Lines>>,
+}
+
+impl<R: BufRead> Read for LineCounter<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for LineCounter<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        // The bytes are still buffered, so this doesn't read
+        if let Ok(buffered) = self.inner.fill_buf() {
+            let consumed = &buffered[..amount.min(buffered.len())];
+            let mut lines = self.lines.get();
+            lines.newlines += memchr_iter(b'\n', consumed).count() as u32;
+            if let Some(newline) = memrchr(b'\n', consumed) {
+                lines.line_start = lines.consumed + newline as u64 + 1;
+            }
+            lines.consumed += amount as u64;
+            self.lines.set(lines);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+/// A stream read through a reused event buffer
+pub(crate) struct BufferedSource<'a> {
+    reader: Reader<Box<dyn BufRead + 'a>>,
+    buf: Vec<u8>,
+    /// Offset of the current reader's first byte in the input
+    base: u64,
+    error: Option<quick_xml::Error>,
+    lines: Rc<Cell<Lines>>,
+}
+
+impl<'a> BufferedSource<'a> {
+    pub(crate) fn new(input: impl BufRead + 'a, mut buf: Vec<u8>) -> Self {
+        buf.clear();
+        let lines = Rc::new(Cell::new(Lines::default()));
+        let input = LineCounter {
+            inner: input,
+            lines: lines.clone(),
+        };
+        BufferedSource {
+            reader: worksheet_reader(Box::new(input)),
+            buf,
+            base: 0,
+            error: None,
+            lines,
+        }
+    }
+
+    /// Hand back the event buffer for reuse
+    pub(crate) fn into_buf(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl XmlSource for BufferedSource<'_> {
+    fn next_event(&mut self) -> quick_xml::Result<Event<'_>> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.buf.clear();
+        self.reader.read_event_into(&mut self.buf)
+    }
+
+    fn skip_to_end(&mut self, name: &[u8]) {
+        self.buf.clear();
+        if let Err(error) = self.reader.read_to_end_into(QName(name), &mut self.buf) {
+            self.error = Some(error);
+        }
+    }
+
+    fn position(&self) -> u64 {
+        self.base + self.reader.buffer_position() as u64
+    }
+
+    fn line_column(&self, offset: u64) -> (u32, u32) {
+        let lines = self.lines.get();
+        let column = offset.saturating_sub(lines.line_start) as u32 + 1;
+        (lines.newlines + 1, column)
+    }
+
+    fn resync(&mut self) -> Option<u64> {
+        let empty: Box<dyn BufRead> = Box::new(std::io::empty());
+        let reader = std::mem::replace(&mut self.reader, worksheet_reader(empty));
+        let mut offset = self.base + reader.buffer_position() as u64;
+        let mut input = reader.into_inner();
+        self.error = None;
+
+        // Bytes since the last `<`, while they could still be a row or cell tag
+        let mut tag: Vec<u8> = Vec::new();
+        loop {
+            let chunk = input.fill_buf().ok()?;
+            if chunk.is_empty() {
+                return None;
+            }
+            let mut found = None;
+            for (i, &byte) in chunk.iter().enumerate() {
+                if byte == b'<' {
+                    tag.clear();
+                    tag.push(byte);
+                } else if !tag.is_empty() {
+                    if ends_name(byte) {
+                        if is_boundary(&tag[1..]) {
+                            tag.push(byte);
+                            found = Some(i + 1);
+                            break;
+                        }
+                        tag.clear();
+                    } else if tag.len() > 32 {
+                        tag.clear();
+                    } else {
+                        tag.push(byte);
+                    }
+                }
+            }
+            let consumed = found.unwrap_or(chunk.len());
+            input.consume(consumed);
+            offset += consumed as u64;
+            if found.is_some() {
+                // The tag's opening bytes were consumed while matching; replay them
+                let start = offset - tag.len() as u64;
+                self.reader = resumed_reader(Box::new(Cursor::new(tag).chain(input)));
+                self.base = start;
+                return Some(start);
+            }
+        }
+    }
+}
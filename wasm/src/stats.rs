@@ -0,0 +1,292 @@
+//! Worksheet size statistics
+//!
+//! A counting pass over the sheet XML that builds no rows or cells, cheap
+//! enough to run before an import to estimate its cost, and a summary of
+//! parsed sheets' contents for import screens and for finding what makes a
+//! file slow.
+
+use crate::reference::parse_cell_ref;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{date_style_flags, resolve_cell_value, CellValue};
+use crate::{ParsedSheet, ParsedStyles};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Element counts for a worksheet
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct WorksheetStats {
+    pub rows: u32,
+    /// `<c>` elements, including style-only cells without a value
+    pub cells: u32,
+    pub formulas: u32,
+    /// Highest 1-based column referenced by a cell, 0 when there are none
+    pub max_col: u32,
+}
+
+/// Parsed sheets summarized by [`workbook_stats`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsWorkbook {
+    pub sheets: Vec<ParsedSheet>,
+    #[serde(default)]
+    pub shared_strings: Vec<String>,
+    /// Styles used to tell dates from other numbers
+    #[serde(default)]
+    pub styles: Option<ParsedStyles>,
+}
+
+/// What a parsed sheet holds
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct SheetStats {
+    pub name: String,
+    pub rows: u32,
+    pub cells: u32,
+    pub numbers: u32,
+    /// Numbers in date-formatted cells
+    pub dates: u32,
+    pub text: u32,
+    pub booleans: u32,
+    pub errors: u32,
+    /// Cells without a value, such as style-only cells
+    pub empty: u32,
+    pub formulas: u32,
+    pub merges: u32,
+    pub hyperlinks: u32,
+    #[cfg(feature = "comments")]
+    pub comments: u32,
+    /// Distinct cell styles the sheet's cells use
+    pub styles_used: u32,
+    /// Highest 1-based row with a cell, 0 when there are none
+    pub max_row: u32,
+    /// Highest 1-based column with a cell, 0 when there are none
+    pub max_col: u32,
+}
+
+/// Count the rows, cells and formulas in worksheet XML
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn count_cells(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| Ok(serde_wasm_bindgen::to_value(&count_cells_impl(xml))?))
+}
+
+pub(crate) fn count_cells_impl(xml: &str) -> WorksheetStats {
+    let mut reader = Reader::from_str(xml);
+    let mut stats = WorksheetStats::default();
+    // Column of the previous cell in the row, for cells written without `r`
+    let mut col = 0u32;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"row" => {
+                    stats.rows += 1;
+                    col = 0;
+                }
+                b"c" => {
+                    stats.cells += 1;
+                    col = match e.try_get_attribute("r") {
+                        Ok(Some(attr)) => column_of(&attr.value).unwrap_or(col + 1),
+                        _ => col + 1,
+                    };
+                    stats.max_col = stats.max_col.max(col);
+                }
+                b"f" => stats.formulas += 1,
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    stats
+}
+
+/// Summarize each sheet of a parsed workbook
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn workbook_stats(workbook: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let workbook: StatsWorkbook = serde_wasm_bindgen::from_value(workbook)?;
+        Ok(serde_wasm_bindgen::to_value(&workbook_stats_impl(
+            &workbook,
+        ))?)
+    })
+}
+
+pub(crate) fn workbook_stats_impl(workbook: &StatsWorkbook) -> Vec<SheetStats> {
+    let date_styles = workbook
+        .styles
+        .as_ref()
+        .map(date_style_flags)
+        .unwrap_or_default();
+    workbook
+        .sheets
+        .iter()
+        .map(|sheet| {
+            let worksheet = &sheet.worksheet;
+            let mut stats = SheetStats {
+                name: sheet.name.clone(),
+                rows: worksheet.rows.len() as u32,
+                merges: worksheet.merge_cells.len() as u32,
+                hyperlinks: worksheet.hyperlinks.len() as u32,
+                #[cfg(feature = "comments")]
+                comments: worksheet.comments.len() as u32,
+                ..Default::default()
+            };
+            let mut styles = HashSet::new();
+            for row in &worksheet.rows {
+                for cell in &row.cells {
+                    stats.cells += 1;
+                    if cell.formula.is_some() {
+                        stats.formulas += 1;
+                    }
+                    if let Some(style) = cell.style_index {
+                        styles.insert(style);
+                    }
+                    if let Some((row, col)) = parse_cell_ref(&cell.reference) {
+                        stats.max_row = stats.max_row.max(row);
+                        stats.max_col = stats.max_col.max(col);
+                    }
+                    let count = match resolve_cell_value(cell, &workbook.shared_strings) {
+                        CellValue::Number(_) => {
+                            let date = cell
+                                .style_index
+                                .and_then(|i| date_styles.get(i as usize).copied())
+                                .unwrap_or(false);
+                            match date {
+                                true => &mut stats.dates,
+                                false => &mut stats.numbers,
+                            }
+                        }
+                        CellValue::Text(text) if text.is_empty() => &mut stats.empty,
+                        CellValue::Text(_) => &mut stats.text,
+                        CellValue::Bool(_) => &mut stats.booleans,
+                        CellValue::Error(_) => &mut stats.errors,
+                        CellValue::Empty => &mut stats.empty,
+                    };
+                    *count += 1;
+                }
+            }
+            stats.styles_used = styles.len() as u32;
+            stats
+        })
+        .collect()
+}
+
+/// Column number from the letters of an A1 reference
+fn column_of(reference: &[u8]) -> Option<u32> {
+    let col = reference
+        .iter()
+        .filter(|&&b| b != b'$')
+        .take_while(|b| b.is_ascii_alphabetic())
+        .try_fold(0u32, |col, b| {
+            col.checked_mul(26)?
+                .checked_add((b.to_ascii_uppercase() - b'A' + 1) as u32)
+        })?;
+    (col > 0).then_some(col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParsedCell, ParsedRow, ParsedStyle, ParsedWorksheet};
+
+    #[test]
+    fn test_count_cells() {
+        let xml = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1"><v>1</v></c><c r="AB1"><f>A1*2</f><v>2</v></c></row>
+            <row r="2"><c s="1"/><c><v>3</v></c></row>
+            <row r="3"/>
+        </sheetData></worksheet>"#;
+        assert_eq!(
+            count_cells_impl(xml),
+            WorksheetStats {
+                rows: 3,
+                cells: 4,
+                formulas: 1,
+                max_col: 28,
+            }
+        );
+    }
+
+    #[test]
+    fn test_workbook_stats() {
+        let cell =
+            |reference: &str, cell_type: Option<&str>, value: Option<&str>, style| ParsedCell {
+                reference: reference.to_string(),
+                cell_type: cell_type.map(str::to_string),
+                style_index: Some(style),
+                value: value.map(str::to_string),
+                formula: None,
+            };
+        let mut total = cell("B2", None, Some("3"), 0);
+        total.formula = Some("SUM(A1:A2)".to_string());
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                ParsedRow {
+                    row_num: 1,
+                    cells: vec![
+                        cell("A1", Some("s"), Some("0"), 0),
+                        cell("C1", None, Some("45292"), 1),
+                        cell("D1", Some("b"), Some("1"), 0),
+                    ],
+                    height: None,
+                    hidden: false,
+                },
+                ParsedRow {
+                    row_num: 2,
+                    cells: vec![
+                        cell("A2", Some("e"), Some("#DIV/0!"), 0),
+                        total,
+                        cell("E2", None, None, 2),
+                    ],
+                    height: None,
+                    hidden: false,
+                },
+            ],
+            merge_cells: vec!["A1:B1".to_string()],
+            ..Default::default()
+        };
+        let workbook = StatsWorkbook {
+            sheets: vec![ParsedSheet {
+                name: "Data".to_string(),
+                state: None,
+                worksheet,
+            }],
+            shared_strings: vec!["Name".to_string()],
+            styles: Some(ParsedStyles {
+                cell_xfs: vec![
+                    ParsedStyle::default(),
+                    ParsedStyle {
+                        num_fmt_id: Some(14),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }),
+        };
+        assert_eq!(
+            workbook_stats_impl(&workbook),
+            [SheetStats {
+                name: "Data".to_string(),
+                rows: 2,
+                cells: 6,
+                numbers: 1,
+                dates: 1,
+                text: 1,
+                booleans: 1,
+                errors: 1,
+                empty: 1,
+                formulas: 1,
+                merges: 1,
+                styles_used: 3,
+                max_row: 2,
+                max_col: 5,
+                ..Default::default()
+            }]
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Streaming worksheet writer for very large exports
+//!
+//! Rows are serialized and DEFLATE-compressed as they arrive, so only the
+//! compressed output (or nothing at all, when a JS sink drains it) has to be
+//! held in memory.
+
+use crate::reference::cell_ref;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::writer::{write_cols, write_row, NS_RELATIONSHIPS, NS_SPREADSHEETML, XML_DECLARATION};
+use crate::zip::{crc32_update, DEFAULT_COMPRESSION_LEVEL};
+use crate::{ParsedCell, ParsedRow};
+use miniz_oxide::deflate::core::{
+    compress, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Serialized XML is compressed once this much has been buffered
+const PENDING_FLUSH_BYTES: usize = 32 * 1024;
+
+/// Compressed output is handed to the sink once this much has accumulated
+const SINK_FLUSH_BYTES: usize = 64 * 1024;
+
+/// Options for [`WorksheetWriter`]
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct WorksheetWriterOptions {
+    #[serde(default)]
+    pub col_widths: HashMap<u32, f64>,
+    /// DEFLATE level (0-10)
+    #[serde(default)]
+    pub compression_level: Option<u8>,
+}
+
+/// Incrementally writes a worksheet part as a raw DEFLATE stream
+///
+/// The output is suitable as the payload of a deflated ZIP entry; use
+/// `crc32()` and `uncompressed_size()` for the entry header.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct WorksheetWriter {
+    compressor: Box<CompressorOxide>,
+    pending: String,
+    output: Vec<u8>,
+    crc: u32,
+    uncompressed_size: u64,
+    next_row: u32,
+    merge_cells: Vec<String>,
+    sink: Option<js_sys::Function>,
+    finished: bool,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl WorksheetWriter {
+    /// Create a writer; `options` may be undefined
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: JsValue) -> Result<WorksheetWriter, JsValue> {
+        trap(|| {
+            let options: Option<WorksheetWriterOptions> = serde_wasm_bindgen::from_value(options)?;
+            Ok(WorksheetWriter::with_options(options.unwrap_or_default()))
+        })
+    }
+
+    /// Send compressed chunks to `sink(chunk: Uint8Array)` instead of buffering them
+    pub fn set_sink(&mut self, sink: js_sys::Function) {
+        self.sink = Some(sink);
+    }
+
+    /// Append a row of cells; rows are numbered sequentially from 1
+    pub fn write_row(&mut self, cells: JsValue) -> Result<(), JsValue> {
+        trap(|| {
+            let cells: Vec<ParsedCell> = serde_wasm_bindgen::from_value(cells)?;
+            self.push_row(cells)
+        })
+    }
+
+    /// Record a merged range, written after the sheet data
+    pub fn add_merge_cell(&mut self, reference: String) {
+        self.merge_cells.push(reference);
+    }
+
+    /// Close the sheet XML and return the compressed bytes not yet handed to the sink
+    pub fn finish(&mut self) -> Result<Vec<u8>, JsValue> {
+        trap(|| {
+            if self.finished {
+                return Err(JsValue::from_str("WorksheetWriter already finished"));
+            }
+            self.finished = true;
+
+            self.pending.push_str("</sheetData>");
+            if !self.merge_cells.is_empty() {
+                let _ = write!(
+                    self.pending,
+                    "<mergeCells count=\"{}\">",
+                    self.merge_cells.len()
+                );
+                for merge in &self.merge_cells {
+                    self.pending.push_str("<mergeCell");
+                    crate::writer::push_attr(&mut self.pending, "ref", merge);
+                    self.pending.push_str("/>");
+                }
+                self.pending.push_str("</mergeCells>");
+            }
+            self.pending.push_str("</worksheet>");
+
+            self.compress_pending(TDEFLFlush::Finish);
+            self.drain_to_sink(0)?;
+            Ok(std::mem::take(&mut self.output))
+        })
+    }
+
+    /// CRC-32 of the uncompressed XML written so far
+    pub fn crc32(&self) -> u32 {
+        self.crc
+    }
+
+    /// Size in bytes of the uncompressed XML written so far
+    pub fn uncompressed_size(&self) -> f64 {
+        self.uncompressed_size as f64
+    }
+
+    /// Number of rows written
+    pub fn row_count(&self) -> u32 {
+        self.next_row - 1
+    }
+}
+
+impl WorksheetWriter {
+    pub(crate) fn with_options(options: WorksheetWriterOptions) -> Self {
+        let level = options
+            .compression_level
+            .unwrap_or(DEFAULT_COMPRESSION_LEVEL)
+            .min(10);
+        // Negative window bits select a raw DEFLATE stream (no zlib header)
+        let flags = create_comp_flags_from_zip_params(level.into(), -15, 0);
+
+        let mut pending = String::with_capacity(PENDING_FLUSH_BYTES * 2);
+        pending.push_str(XML_DECLARATION);
+        let _ = write!(
+            pending,
+            "<worksheet xmlns=\"{}\" xmlns:r=\"{}\">",
+            NS_SPREADSHEETML, NS_RELATIONSHIPS
+        );
+        write_cols(&mut pending, &options.col_widths);
+        pending.push_str("<sheetData>");
+
+        WorksheetWriter {
+            compressor: Box::new(CompressorOxide::new(flags)),
+            pending,
+            output: Vec::new(),
+            crc: 0,
+            uncompressed_size: 0,
+            next_row: 1,
+            merge_cells: Vec::new(),
+            sink: None,
+            finished: false,
+        }
+    }
+
+    pub(crate) fn push_row(&mut self, mut cells: Vec<ParsedCell>) -> Result<(), JsValue> {
+        if self.finished {
+            return Err(JsValue::from_str("WorksheetWriter already finished"));
+        }
+
+        let row_num = self.next_row;
+        self.next_row += 1;
+        for (i, cell) in cells.iter_mut().enumerate() {
+            if cell.reference.is_empty() {
+                cell.reference = cell_ref(row_num, i as u32 + 1);
+            }
+        }
+
+        let row = ParsedRow {
+            row_num,
+            cells,
+            height: None,
+            hidden: false,
+        };
+        write_row(&mut self.pending, &row);
+
+        if self.pending.len() >= PENDING_FLUSH_BYTES {
+            self.compress_pending(TDEFLFlush::None);
+            self.drain_to_sink(SINK_FLUSH_BYTES)?;
+        }
+        Ok(())
+    }
+
+    /// Feed buffered XML through the compressor
+    fn compress_pending(&mut self, flush: TDEFLFlush) {
+        let input = std::mem::take(&mut self.pending);
+        let bytes = input.as_bytes();
+        self.crc = crc32_update(self.crc, bytes);
+        self.uncompressed_size += bytes.len() as u64;
+
+        let mut remaining = bytes;
+        let mut chunk = vec![0u8; (bytes.len() / 2).max(1024)];
+        loop {
+            let (status, consumed, written) =
+                compress(&mut self.compressor, remaining, &mut chunk, flush);
+            self.output.extend_from_slice(&chunk[..written]);
+            remaining = &remaining[consumed..];
+
+            match status {
+                TDEFLStatus::Done => break,
+                TDEFLStatus::Okay if remaining.is_empty() && written < chunk.len() => break,
+                TDEFLStatus::Okay => {}
+                _ => break,
+            }
+        }
+
+        // Reuse the allocation for the next batch of rows
+        self.pending = input;
+        self.pending.clear();
+    }
+
+    /// Hand compressed output to the sink once at least `threshold` bytes are buffered
+    fn drain_to_sink(&mut self, threshold: usize) -> Result<(), JsValue> {
+        if let Some(ref sink) = self.sink {
+            if !self.output.is_empty() && self.output.len() >= threshold {
+                let chunk = js_sys::Uint8Array::from(self.output.as_slice());
+                sink.call1(&JsValue::NULL, &chunk)?;
+                self.output.clear();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::inflate::decompress_to_vec;
+
+    fn text_cell(value: &str) -> ParsedCell {
+        ParsedCell {
+            reference: String::new(),
+            cell_type: Some("inlineStr".to_string()),
+            style_index: None,
+            value: Some(value.to_string()),
+            formula: None,
+        }
+    }
+
+    #[test]
+    fn test_streaming_writer() {
+        let mut writer = WorksheetWriter::with_options(WorksheetWriterOptions::default());
+        for i in 0..5000 {
+            writer
+                .push_row(vec![
+                    text_cell(&format!("row {}", i)),
+                    ParsedCell {
+                        reference: String::new(),
+                        cell_type: None,
+                        style_index: None,
+                        value: Some(i.to_string()),
+                        formula: None,
+                    },
+                ])
+                .unwrap();
+        }
+        writer.add_merge_cell("A1:B1".to_string());
+        let compressed = writer.finish().unwrap();
+        assert_eq!(writer.row_count(), 5000);
+
+        let xml = decompress_to_vec(&compressed).unwrap();
+        assert_eq!(xml.len() as f64, writer.uncompressed_size());
+        assert_eq!(crate::zip::crc32(&xml), writer.crc32());
+
+        let xml = String::from_utf8(xml).unwrap();
+        let parsed = crate::parse_worksheet_impl(&xml);
+        assert_eq!(parsed.rows.len(), 5000);
+        assert_eq!(parsed.rows[4999].row_num, 5000);
+        assert_eq!(parsed.rows[4999].cells[1].reference, "B5000");
+        assert_eq!(
+            parsed.rows[4999].cells[0].value.as_deref(),
+            Some("row 4999")
+        );
+        assert_eq!(parsed.merge_cells, vec!["A1:B1".to_string()]);
+    }
+}
@@ -0,0 +1,103 @@
+//! Shared strings kept in wasm memory
+//!
+//! Large workbooks can have hundreds of thousands of shared strings, most of
+//! which a caller never looks at. [`SharedStringTable`] stores them as one
+//! UTF-8 buffer plus offsets so JS can materialize strings on demand, or
+//! decode slices of the buffer itself.
+
+use crate::context::ParserScratch;
+use crate::for_each_shared_string;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Shared strings as a concatenated UTF-8 buffer
+///
+/// String `i` is `data[offsets[i]..offsets[i + 1]]`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug)]
+pub struct SharedStringTable {
+    data: Vec<u8>,
+    offsets: Vec<u32>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl SharedStringTable {
+    /// Number of strings
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Materialize one string
+    pub fn get(&self, index: usize) -> Option<String> {
+        self.text(index).map(str::to_string)
+    }
+
+    /// Copy of the UTF-8 buffer, for decoding with `TextDecoder`
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    /// Copy of the offsets; one more entry than there are strings
+    #[wasm_bindgen(getter)]
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
+}
+
+impl SharedStringTable {
+    pub(crate) fn text(&self, index: usize) -> Option<&str> {
+        let start = *self.offsets.get(index)? as usize;
+        let end = *self.offsets.get(index + 1)? as usize;
+        // Every slice was appended from a `&str`, so it is valid UTF-8
+        std::str::from_utf8(&self.data[start..end]).ok()
+    }
+}
+
+/// Parse shared strings XML into a [`SharedStringTable`]
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_shared_strings_table(xml: &str) -> Result<SharedStringTable, JsValue> {
+    trap(|| {
+        Ok(parse_shared_strings_table_with(
+            xml,
+            &mut ParserScratch::default(),
+        ))
+    })
+}
+
+pub(crate) fn parse_shared_strings_table_with(
+    xml: &str,
+    scratch: &mut ParserScratch,
+) -> SharedStringTable {
+    let mut table = SharedStringTable {
+        data: Vec::with_capacity(xml.len() / 2),
+        offsets: vec![0],
+    };
+    for_each_shared_string(xml, scratch, |text| {
+        table.data.extend_from_slice(text.as_bytes());
+        table.offsets.push(table.data.len() as u32);
+    });
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_shared_string_table() {
+        let xml = r#"<sst><si><t>Name</t></si><si><r><t>Ré</t></r><r><t>sumé</t></r></si><si><t/></si></sst>"#;
+        let table = parse_shared_strings_table_with(xml, &mut ParserScratch::default());
+        assert_eq!(table.length(), 3);
+        assert_eq!(table.text(1), Some("Résumé"));
+        assert_eq!(table.text(2), Some(""));
+        assert_eq!(table.text(3), None);
+        assert_eq!(table.offsets, vec![0, 4, 12, 12]);
+    }
+}
@@ -0,0 +1,510 @@
+//! Table parts and structured references
+//!
+//! Reads `xl/tables/tableN.xml` and resolves structured references such as
+//! `Table1[Amount]`, `Table1[[#Headers],[Amount]]` and `[@Amount]` to the
+//! cell ranges they cover, so formulas using them can be evaluated, graphed
+//! and highlighted like any other reference. `write_table` in the writers
+//! writes them back out.
+
+use crate::formula::{tokenize, Axis, Notation, RefPoint, Reference, Token};
+use crate::range::contains_cell;
+use crate::reference::{parse_cell_ref, range_bounds};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A table definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedTable {
+    pub name: String,
+    /// Name used in formulas
+    pub display_name: String,
+    /// Range covered by the table, headers and totals included
+    pub reference: String,
+    #[serde(default = "default_header_row_count")]
+    pub header_row_count: u32,
+    #[serde(default)]
+    pub totals_row_count: u32,
+    pub columns: Vec<String>,
+    /// Sheet holding the table; table parts don't record it, so hosts fill
+    /// it in from the worksheet relationships
+    #[serde(default)]
+    pub sheet: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<TableStyleInfo>,
+    /// What the totals row shows under each column, in column order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub totals: Vec<ColumnTotal>,
+}
+
+/// How a table is drawn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableStyleInfo {
+    /// "TableStyleMedium2", "TableStyleLight9", ...
+    pub name: Option<String>,
+    pub show_first_column: bool,
+    pub show_last_column: bool,
+    pub show_row_stripes: bool,
+    pub show_column_stripes: bool,
+}
+
+impl Default for TableStyleInfo {
+    fn default() -> Self {
+        TableStyleInfo {
+            name: Some("TableStyleMedium2".to_string()),
+            show_first_column: false,
+            show_last_column: false,
+            show_row_stripes: true,
+            show_column_stripes: false,
+        }
+    }
+}
+
+/// A table column's cell in the totals row
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnTotal {
+    /// "sum", "average", "count", "countNums", "max", "min", "stdDev",
+    /// "var" or "custom"
+    pub function: Option<String>,
+    /// Text shown instead of a function, e.g. "Total"
+    pub label: Option<String>,
+    /// The formula of a "custom" function
+    pub formula: Option<String>,
+}
+
+impl ColumnTotal {
+    /// `SUBTOTAL` function number of a built-in function, skipping hidden rows
+    pub(crate) fn subtotal(&self) -> Option<u32> {
+        Some(match self.function.as_deref()? {
+            "average" => 101,
+            "countNums" => 102,
+            "count" => 103,
+            "max" => 104,
+            "min" => 105,
+            "stdDev" => 107,
+            "sum" => 109,
+            "var" => 110,
+            _ => return None,
+        })
+    }
+}
+
+fn default_header_row_count() -> u32 {
+    1
+}
+
+/// Parse a table part (`xl/tables/tableN.xml`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_table(xml: &str) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let table = parse_table_impl(xml).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&table)?)
+    })
+}
+
+/// Replace the structured references in `formula` with A1 ranges. `sheet`
+/// is the sheet holding the formula and `cell` its address, needed for
+/// `[@Column]` and unqualified `[Column]` references.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn expand_structured_references(
+    formula: &str,
+    tables: JsValue,
+    sheet: Option<String>,
+    cell: Option<String>,
+) -> Result<String, JsValue> {
+    trap(|| {
+        let tables: Vec<ParsedTable> = serde_wasm_bindgen::from_value(tables)?;
+        let cell = match cell {
+            Some(cell) => Some(
+                parse_cell_ref(&cell)
+                    .ok_or_else(|| JsValue::from_str(&format!("Invalid cell: {}", cell)))?,
+            ),
+            None => None,
+        };
+        expand_structured_references_impl(formula, &tables, sheet.as_deref(), cell)
+            .map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+pub(crate) fn parse_table_impl(xml: &str) -> Result<ParsedTable, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut table: Option<ParsedTable> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"table" => {
+                    let count = |name: &[u8], default: u32| {
+                        attribute(&e, name)
+                            .and_then(|v| v.parse().ok())
+                            .unwrap_or(default)
+                    };
+                    let name = attribute(&e, b"name").unwrap_or_default();
+                    table = Some(ParsedTable {
+                        display_name: attribute(&e, b"displayName").unwrap_or(name.clone()),
+                        name,
+                        reference: attribute(&e, b"ref").ok_or("Table is missing its ref")?,
+                        header_row_count: count(b"headerRowCount", 1),
+                        totals_row_count: count(b"totalsRowCount", 0),
+                        columns: Vec::new(),
+                        sheet: None,
+                        style: None,
+                        totals: Vec::new(),
+                    });
+                }
+                b"tableColumn" => {
+                    if let Some(table) = table.as_mut() {
+                        table
+                            .columns
+                            .push(attribute(&e, b"name").unwrap_or_default());
+                        table.totals.push(ColumnTotal {
+                            function: attribute(&e, b"totalsRowFunction"),
+                            label: attribute(&e, b"totalsRowLabel"),
+                            formula: None,
+                        });
+                    }
+                }
+                b"tableStyleInfo" => {
+                    if let Some(table) = table.as_mut() {
+                        let flag = |name: &[u8]| {
+                            attribute(&e, name).is_some_and(|v| v == "1" || v == "true")
+                        };
+                        table.style = Some(TableStyleInfo {
+                            name: attribute(&e, b"name"),
+                            show_first_column: flag(b"showFirstColumn"),
+                            show_last_column: flag(b"showLastColumn"),
+                            show_row_stripes: flag(b"showRowStripes"),
+                            show_column_stripes: flag(b"showColumnStripes"),
+                        });
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Invalid table XML: {}", e)),
+            _ => {}
+        }
+        buf.clear();
+    }
+    let mut table = table.ok_or_else(|| "No table element found".to_string())?;
+    let blank = |total: &ColumnTotal| total.function.is_none() && total.label.is_none();
+    if table.totals.iter().all(blank) {
+        table.totals.clear();
+    }
+    Ok(table)
+}
+
+/// Row sections a structured reference can select
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    All,
+    Data,
+    Headers,
+    Totals,
+    ThisRow,
+}
+
+/// Table name, selected sections and column span of a structured reference
+struct Specifier {
+    table: String,
+    sections: Vec<Section>,
+    columns: Option<(String, String)>,
+}
+
+fn section(item: &str) -> Result<Section, String> {
+    Ok(match item.to_ascii_lowercase().as_str() {
+        "#all" => Section::All,
+        "#data" => Section::Data,
+        "#headers" => Section::Headers,
+        "#totals" => Section::Totals,
+        "#this row" => Section::ThisRow,
+        _ => return Err("#REF!".to_string()),
+    })
+}
+
+/// Split `[A],[#Data],[B]:[C]` into its items, undoing `'` escapes, with
+/// whether each item is joined to the previous one by `:`
+fn bracket_items(text: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut items = Vec::new();
+    let mut chars = text.chars();
+    let mut joined = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '[' => {
+                let mut item = String::new();
+                loop {
+                    match chars.next().ok_or("#REF!")? {
+                        '\'' => item.extend(chars.next()),
+                        ']' => break,
+                        c => item.push(c),
+                    }
+                }
+                items.push((item.trim().to_string(), joined));
+                joined = false;
+            }
+            ':' => joined = true,
+            ',' | ' ' => {}
+            _ => return Err("#REF!".to_string()),
+        }
+    }
+    Ok(items)
+}
+
+fn parse_specifier(written: &str) -> Result<Specifier, String> {
+    let open = written.find('[').ok_or("#REF!")?;
+    let inner = written[open..]
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or("#REF!")?
+        .trim();
+    let mut spec = Specifier {
+        table: written[..open].to_string(),
+        sections: Vec::new(),
+        columns: None,
+    };
+
+    let inner = match inner.strip_prefix('@') {
+        Some(rest) => {
+            spec.sections.push(Section::ThisRow);
+            rest.trim()
+        }
+        None => inner,
+    };
+    let items = if inner.starts_with('[') {
+        bracket_items(inner)?
+    } else if inner.is_empty() {
+        Vec::new()
+    } else {
+        // A lone column or section name, escapes included
+        bracket_items(&format!("[{}]", inner))?
+    };
+
+    for (item, joined) in items {
+        if item.starts_with('#') {
+            spec.sections.push(section(&item)?);
+        } else if joined {
+            let (first, _) = spec.columns.take().ok_or("#REF!")?;
+            spec.columns = Some((first, item));
+        } else if spec.columns.is_some() {
+            // Only one column span is allowed
+            return Err("#REF!".to_string());
+        } else {
+            spec.columns = Some((item.clone(), item));
+        }
+    }
+    Ok(spec)
+}
+
+/// Resolve a structured reference to the range it covers. `sheet` and `cell`
+/// locate the formula, for `[@Column]` and references without a table name.
+/// Errors are Excel error codes.
+pub(crate) fn resolve_structured_reference(
+    written: &str,
+    tables: &[ParsedTable],
+    sheet: Option<&str>,
+    cell: Option<(u32, u32)>,
+) -> Result<Reference, String> {
+    let spec = parse_specifier(written)?;
+    let bounds = |table: &ParsedTable| range_bounds(&table.reference);
+    let same_sheet = |table: &ParsedTable| match (&table.sheet, sheet) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => true,
+    };
+    let table = if spec.table.is_empty() {
+        // Unqualified references belong to the table the formula sits in
+        let (row, col) = cell.ok_or("#REF!")?;
+        tables.iter().find(|table| {
+            same_sheet(table) && bounds(table).is_some_and(|bounds| contains_cell(bounds, row, col))
+        })
+    } else {
+        tables.iter().find(|table| {
+            table.display_name.eq_ignore_ascii_case(&spec.table)
+                || table.name.eq_ignore_ascii_case(&spec.table)
+        })
+    }
+    .ok_or("#REF!")?;
+    let (top, left, bottom, right) = bounds(table).ok_or("#REF!")?;
+
+    let header_end = top + table.header_row_count;
+    let totals_start = (bottom + 1).saturating_sub(table.totals_row_count);
+    let mut rows: Option<(u32, u32)> = None;
+    let sections = if spec.sections.is_empty() {
+        vec![Section::Data]
+    } else {
+        spec.sections
+    };
+    for section in sections {
+        let (first, last) = match section {
+            Section::All => (top, bottom),
+            Section::Data => (header_end, totals_start.saturating_sub(1)),
+            Section::Headers if table.header_row_count > 0 => (top, header_end - 1),
+            Section::Totals if table.totals_row_count > 0 => (totals_start, bottom),
+            Section::ThisRow => {
+                let row = cell.map(|(row, _)| row).ok_or("#VALUE!")?;
+                if row < header_end || row >= totals_start || !same_sheet(table) {
+                    return Err("#VALUE!".to_string());
+                }
+                (row, row)
+            }
+            _ => return Err("#REF!".to_string()),
+        };
+        if first > last {
+            return Err("#REF!".to_string());
+        }
+        rows = Some(match rows {
+            Some((a, b)) => (a.min(first), b.max(last)),
+            None => (first, last),
+        });
+    }
+    let (first_row, last_row) = rows.ok_or("#REF!")?;
+
+    let (first_col, last_col) = match &spec.columns {
+        Some((first, last)) => {
+            let index = |name: &str| {
+                table
+                    .columns
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(name))
+                    .map(|i| left + i as u32)
+                    .ok_or("#REF!")
+            };
+            let (a, b) = (index(first)?, index(last)?);
+            (a.min(b), a.max(b))
+        }
+        None => (left, right),
+    };
+
+    let point = |row: u32, col: u32| RefPoint {
+        row: Some(Axis {
+            index: row,
+            absolute: true,
+        }),
+        col: Some(Axis {
+            index: col,
+            absolute: true,
+        }),
+    };
+    let single = first_row == last_row && first_col == last_col;
+    Ok(Reference {
+        sheet: table.sheet.clone(),
+        last_sheet: None,
+        start: point(first_row, first_col),
+        end: (!single).then(|| point(last_row, last_col)),
+    })
+}
+
+pub(crate) fn expand_structured_references_impl(
+    formula: &str,
+    tables: &[ParsedTable],
+    sheet: Option<&str>,
+    cell: Option<(u32, u32)>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(formula.len());
+    for (token, span) in tokenize(formula, Notation::A1)? {
+        let Token::StructuredRef(written) = token else {
+            out.push_str(&formula[span]);
+            continue;
+        };
+        let mut reference = resolve_structured_reference(&written, tables, sheet, cell)
+            .map_err(|e| format!("Cannot resolve {}: {}", written, e))?;
+        // Qualify only references into another sheet
+        if reference
+            .sheet
+            .as_deref()
+            .zip(sheet)
+            .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b))
+        {
+            reference.sheet = None;
+        }
+        out.push_str(&reference.to_a1());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sales() -> ParsedTable {
+        parse_table_impl(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<table xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" id="1" name="Table1" displayName="Sales" ref="B2:D7" totalsRowCount="1">
+  <autoFilter ref="B2:D6"/>
+  <tableColumns count="3">
+    <tableColumn id="1" name="Region"/>
+    <tableColumn id="2" name="Amount"/>
+    <tableColumn id="3" name="Unit [kg]"/>
+  </tableColumns>
+</table>"#,
+        )
+        .map(|table| ParsedTable {
+            sheet: Some("Data".to_string()),
+            ..table
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let table = sales();
+        assert_eq!(table.name, "Table1");
+        assert_eq!(table.display_name, "Sales");
+        assert_eq!(table.reference, "B2:D7");
+        assert_eq!((table.header_row_count, table.totals_row_count), (1, 1));
+        assert_eq!(table.columns, ["Region", "Amount", "Unit [kg]"]);
+    }
+
+    #[test]
+    fn test_resolve_structured_reference() {
+        let tables = [sales()];
+        let resolve = |written: &str, cell: Option<(u32, u32)>| {
+            resolve_structured_reference(written, &tables, Some("Data"), cell).map(|r| r.to_a1())
+        };
+        assert_eq!(resolve("Sales[Amount]", None).unwrap(), "Data!$C$3:$C$6");
+        assert_eq!(
+            resolve("Sales[[#Headers],[Amount]]", None).unwrap(),
+            "Data!$C$2"
+        );
+        assert_eq!(resolve("sales[#All]", None).unwrap(), "Data!$B$2:$D$7");
+        assert_eq!(
+            resolve("Sales[[#Data],[#Totals],[Region]:[Amount]]", None).unwrap(),
+            "Data!$B$3:$C$7"
+        );
+        assert_eq!(
+            resolve("Sales[@[Unit '[kg']]]", Some((4, 5))).unwrap(),
+            "Data!$D$4"
+        );
+        assert_eq!(resolve("[@Amount]", Some((5, 4))).unwrap(), "Data!$C$5");
+        assert_eq!(resolve("[@Amount]", Some((2, 4))).unwrap_err(), "#VALUE!");
+        assert_eq!(resolve("Sales[Missing]", None).unwrap_err(), "#REF!");
+
+        assert_eq!(
+            expand_structured_references_impl(
+                "=SUM(Sales[Amount])/Sales[[#Totals],[Amount]]",
+                &tables,
+                Some("Summary"),
+                None
+            )
+            .unwrap(),
+            "=SUM(Data!$C$3:$C$6)/Data!$C$7"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Worksheet fixtures shared by the module tests
+
+use crate::reference::cell_ref;
+use crate::{ParsedCell, ParsedRow, ParsedWorksheet};
+
+/// A cell holding `value`, with no style or formula
+pub(crate) fn cell(reference: &str, cell_type: Option<&str>, value: &str) -> ParsedCell {
+    ParsedCell {
+        reference: reference.to_string(),
+        cell_type: cell_type.map(str::to_string),
+        style_index: None,
+        value: Some(value.to_string()),
+        formula: None,
+    }
+}
+
+/// A visible row with the default height
+pub(crate) fn row(row_num: u32, cells: Vec<ParsedCell>) -> ParsedRow {
+    ParsedRow {
+        row_num,
+        cells,
+        height: None,
+        hidden: false,
+    }
+}
+
+/// A worksheet from a grid of values starting at A1
+///
+/// Empty strings leave the cell out. Values that parse as numbers are
+/// numeric cells and everything else is an inline string; a leading `'`
+/// forces a string, as it does when typing into Excel.
+pub(crate) fn sheet_from_rows(rows: &[&[&str]]) -> ParsedWorksheet {
+    let rows = rows
+        .iter()
+        .zip(1..)
+        .map(|(values, row_num)| {
+            let cells = values
+                .iter()
+                .zip(1..)
+                .filter(|(value, _)| !value.is_empty())
+                .map(|(value, col)| {
+                    let reference = cell_ref(row_num, col);
+                    match value.strip_prefix('\'') {
+                        Some(text) => cell(&reference, Some("str"), text),
+                        None if value.parse::<f64>().is_ok() => cell(&reference, None, value),
+                        None => cell(&reference, Some("str"), value),
+                    }
+                })
+                .collect();
+            row(row_num, cells)
+        })
+        .collect();
+    ParsedWorksheet {
+        rows,
+        ..Default::default()
+    }
+}
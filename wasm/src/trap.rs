@@ -0,0 +1,118 @@
+//! Panic trapping for the exported entry points
+//!
+//! Every entry point that can fail runs inside [`trap`], so a panic comes
+//! back to JS as a structured `{ error: "panic", message, location }` error
+//! instead of an opaque `RuntimeError: unreachable`.
+//!
+//! Builds that abort on panic (the wasm32 default) can't unwind, so the call
+//! still traps there. The panic hook records what happened first, and the
+//! record is left behind: any later call fails fast with
+//! `{ error: "poisoned", ... }` rather than running on an instance whose
+//! memory may be half-updated. Seeing either error, JS should drop the
+//! instance and load a fresh one.
+
+use serde::Serialize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, Once};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Serialize)]
+struct PanicReport {
+    message: String,
+    /// `file:line:column` of the panic, when known
+    location: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PanicError<'a> {
+    error: &'static str,
+    description: String,
+    #[serde(flatten)]
+    report: &'a PanicReport,
+}
+
+impl PanicReport {
+    fn to_js(&self, error: &'static str) -> JsValue {
+        let error = PanicError {
+            error,
+            description: match error {
+                "poisoned" => format!("An earlier call panicked: {}", self.message),
+                _ => format!("Parser panicked: {}", self.message),
+            },
+            report: self,
+        };
+        serde_wasm_bindgen::to_value(&error)
+            .unwrap_or_else(|_| JsValue::from_str(&error.description))
+    }
+}
+
+/// The last panic not yet reported by [`trap`]
+static LAST_PANIC: Mutex<Option<PanicReport>> = Mutex::new(None);
+static INSTALL: Once = Once::new();
+
+/// Install the recording panic hook; later calls do nothing
+pub(crate) fn install() {
+    INSTALL.call_once(|| {
+        #[cfg(all(feature = "console_error_panic_hook", target_arch = "wasm32"))]
+        let previous = console_error_panic_hook::hook;
+        #[cfg(not(all(feature = "console_error_panic_hook", target_arch = "wasm32")))]
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+            if let Ok(mut last) = LAST_PANIC.lock() {
+                *last = Some(PanicReport { message, location });
+            }
+            previous(info);
+        }));
+    });
+}
+
+fn take_panic() -> Option<PanicReport> {
+    LAST_PANIC.lock().ok()?.take()
+}
+
+/// Run `f`, catching a panic; the error is "panic" for one caught now and
+/// "poisoned" for one left behind by a call that aborted
+fn run<T>(f: impl FnOnce() -> T) -> Result<T, (&'static str, PanicReport)> {
+    install();
+    if let Some(report) = LAST_PANIC.lock().ok().and_then(|last| last.clone()) {
+        return Err(("poisoned", report));
+    }
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| {
+        let report = take_panic().unwrap_or(PanicReport {
+            message: "unknown panic".to_string(),
+            location: None,
+        });
+        ("panic", report)
+    })
+}
+
+/// Run an entry point, turning a panic into a structured JS error
+pub(crate) fn trap<T>(f: impl FnOnce() -> Result<T, JsValue>) -> Result<T, JsValue> {
+    run(f).unwrap_or_else(|(error, report)| Err(report.to_js(error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trap() {
+        assert_eq!(run(|| 1).ok(), Some(1));
+        let cells: Vec<u32> = Vec::new();
+        let (error, report) = run(|| cells[3]).unwrap_err();
+        assert_eq!(error, "panic");
+        assert!(report.message.contains("index out of bounds"));
+        assert!(report.location.unwrap().starts_with("src/trap.rs:"));
+        // A caught panic leaves nothing behind to poison the next call
+        assert_eq!(run(|| 2).ok(), Some(2));
+    }
+}
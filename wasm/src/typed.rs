@@ -0,0 +1,304 @@
+//! Values-only worksheet parsing for data import
+//!
+//! Skips everything that only matters for fidelity (styles, formulas,
+//! hyperlinks, merges, column widths) and returns each cell as its
+//! reference and typed value. Date-formatted numbers stay serial numbers,
+//! since telling them apart needs the styles this mode ignores.
+//!
+//! [`probe_worksheet`] reads only the first rows for import wizards that need
+//! the headers and column types before committing to a full parse.
+
+use crate::context::ParserScratch;
+use crate::json::header_names;
+use crate::reference::{bounds_ref, parse_cell_ref, range_bounds, used_range};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{resolve_cell_value, CellValue};
+use crate::workbook::TypedValue;
+use crate::{parse_worksheet_with, WorksheetParseOptions};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A cell reduced to its typed value; errors are their code as a string
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ValueCell {
+    pub reference: String,
+    pub value: Option<TypedValue>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ValueRow {
+    pub row_num: u32,
+    pub cells: Vec<ValueCell>,
+}
+
+/// Options for [`parse_worksheet_values`]
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ValuesParseOptions {
+    pub shared_strings: Vec<String>,
+    /// Same as [`WorksheetParseOptions::range`]
+    pub range: Option<String>,
+    pub skip_rows: u32,
+    pub max_rows: Option<u32>,
+    /// Leave out cells without a value
+    pub skip_empty_cells: bool,
+}
+
+/// Parse worksheet XML into rows of references and typed values
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_worksheet_values(xml: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let options: Option<ValuesParseOptions> = serde_wasm_bindgen::from_value(options)?;
+        let rows = parse_worksheet_values_impl(xml, &options.unwrap_or_default());
+        Ok(serde_wasm_bindgen::to_value(&rows)?)
+    })
+}
+
+pub(crate) fn parse_worksheet_values_impl(
+    xml: &str,
+    options: &ValuesParseOptions,
+) -> Vec<ValueRow> {
+    let parse_options = WorksheetParseOptions {
+        values_only: true,
+        skip_empty_cells: options.skip_empty_cells,
+        skip_styled_empty_cells: options.skip_empty_cells,
+        range: options.range.clone(),
+        skip_rows: options.skip_rows,
+        max_rows: options.max_rows,
+        strict: false,
+        check_namespaces: false,
+        normalize_rows: false,
+    };
+    let worksheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &parse_options);
+    worksheet
+        .rows
+        .into_iter()
+        .map(|row| ValueRow {
+            row_num: row.row_num,
+            cells: row
+                .cells
+                .iter()
+                .map(|cell| ValueCell {
+                    reference: cell.reference.clone(),
+                    value: match resolve_cell_value(cell, &options.shared_strings) {
+                        CellValue::Empty => None,
+                        CellValue::Number(number) => Some(TypedValue::Number(number)),
+                        CellValue::Text(text) | CellValue::Error(text) => {
+                            Some(TypedValue::Text(text))
+                        }
+                        CellValue::Bool(value) => Some(TypedValue::Bool(value)),
+                    },
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Headers and inferred types from the top of a worksheet
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WorksheetProbe {
+    /// The `<dimension>` ref as written, which may be stale or missing
+    pub dimension: Option<String>,
+    /// Extent of the whole sheet per the dimension, else of the sampled rows
+    pub range: Option<String>,
+    pub columns: Vec<ProbeColumn>,
+    /// Data rows sampled after the header row
+    pub sampled_rows: u32,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ProbeColumn {
+    /// 1-based column
+    pub col: u32,
+    /// Header text, unique and never empty (falls back to the column letter)
+    pub header: String,
+    /// "number", "string", "boolean", "mixed" or "empty" (no values sampled)
+    pub value_type: String,
+}
+
+/// Read the header row and up to `n_rows` rows below it to infer column types
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn probe_worksheet(
+    xml: &str,
+    n_rows: u32,
+    shared_strings: Option<Vec<String>>,
+) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let probe = probe_worksheet_impl(xml, n_rows, &shared_strings.unwrap_or_default());
+        Ok(serde_wasm_bindgen::to_value(&probe)?)
+    })
+}
+
+pub(crate) fn probe_worksheet_impl(
+    xml: &str,
+    n_rows: u32,
+    shared_strings: &[String],
+) -> WorksheetProbe {
+    let dimension = read_dimension(xml);
+    let options = WorksheetParseOptions {
+        values_only: true,
+        skip_empty_cells: true,
+        skip_styled_empty_cells: true,
+        max_rows: Some(n_rows.saturating_add(1)),
+        ..Default::default()
+    };
+    let worksheet = parse_worksheet_with(xml, &mut ParserScratch::default(), &options);
+    let sampled = used_range(&worksheet);
+    let bounds =
+        dimension.as_deref().and_then(range_bounds).or(sampled).map(
+            |(r1, c1, r2, c2)| match sampled {
+                Some((_, sc1, _, sc2)) => (r1, c1.min(sc1), r2, c2.max(sc2)),
+                None => (r1, c1, r2, c2),
+            },
+        );
+    let Some((_, start_col, _, end_col)) = bounds else {
+        return WorksheetProbe {
+            dimension,
+            range: None,
+            columns: Vec::new(),
+            sampled_rows: 0,
+        };
+    };
+
+    let mut rows = worksheet.rows.iter();
+    let headers = header_names(
+        rows.next().map(|row| row.cells.as_slice()),
+        start_col,
+        end_col,
+        shared_strings,
+    );
+    let mut types: HashMap<u32, &str> = HashMap::new();
+    let mut sampled_rows = 0;
+    for row in rows {
+        sampled_rows += 1;
+        for cell in &row.cells {
+            let Some((_, col)) = parse_cell_ref(&cell.reference) else {
+                continue;
+            };
+            let value_type = match resolve_cell_value(cell, shared_strings) {
+                CellValue::Empty | CellValue::Error(_) => continue,
+                CellValue::Number(_) => "number",
+                CellValue::Text(_) => "string",
+                CellValue::Bool(_) => "boolean",
+            };
+            types
+                .entry(col)
+                .and_modify(|seen| {
+                    if *seen != value_type {
+                        *seen = "mixed";
+                    }
+                })
+                .or_insert(value_type);
+        }
+    }
+
+    WorksheetProbe {
+        dimension,
+        range: bounds.map(bounds_ref),
+        columns: (start_col..=end_col)
+            .zip(headers)
+            .map(|(col, header)| ProbeColumn {
+                col,
+                header,
+                value_type: types.get(&col).copied().unwrap_or("empty").to_string(),
+            })
+            .collect(),
+        sampled_rows,
+    }
+}
+
+/// The `<dimension ref>` of a worksheet, read without touching the sheet data
+fn read_dimension(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"dimension" => {
+                    let attr = e.try_get_attribute("ref").ok().flatten()?;
+                    return attr.unescape_value().ok().map(|v| v.into_owned());
+                }
+                b"sheetData" => return None,
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_only() {
+        let xml = r#"<worksheet><sheetData><row r="1">
+            <c r="A1" t="s" s="2"><v>1</v></c>
+            <c r="B1" s="5"><f>1+1</f><v>2</v></c>
+            <c r="C1" t="b"><v>1</v></c>
+            <c r="D1" s="3"/>
+        </row></sheetData><mergeCells><mergeCell ref="A1:B1"/></mergeCells></worksheet>"#;
+        let options = ValuesParseOptions {
+            shared_strings: vec!["x".to_string(), "Name".to_string()],
+            skip_empty_cells: true,
+            ..Default::default()
+        };
+        let rows = parse_worksheet_values_impl(xml, &options);
+        let values: Vec<_> = rows[0].cells.iter().map(|c| c.value.as_ref()).collect();
+        assert_eq!(
+            values,
+            vec![
+                Some(&TypedValue::Text("Name".to_string())),
+                Some(&TypedValue::Number(2.0)),
+                Some(&TypedValue::Bool(true)),
+            ]
+        );
+
+        let worksheet = parse_worksheet_with(
+            xml,
+            &mut ParserScratch::default(),
+            &WorksheetParseOptions {
+                values_only: true,
+                ..Default::default()
+            },
+        );
+        let cell = &worksheet.rows[0].cells[1];
+        assert_eq!((cell.style_index, cell.formula.as_deref()), (None, None));
+        assert!(worksheet.merge_cells.is_empty());
+    }
+
+    #[test]
+    fn test_probe_worksheet() {
+        let xml = r#"<worksheet><dimension ref="A1:C500"/><sheetData>
+            <row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c></row>
+            <row r="2"><c r="A2" t="s"><v>2</v></c><c r="B2"><v>10</v></c></row>
+            <row r="3"><c r="A3" t="s"><v>2</v></c><c r="B3" t="str"><v>n/a</v></c></row>
+            <row r="4"><c r="A4"><v>1</v></c><c r="B4" t="str"><v>n/a</v></c></row>
+        </sheetData></worksheet>"#;
+        let strings = ["Name", "Score", "Ann"].map(str::to_string);
+        let probe = probe_worksheet_impl(xml, 1, &strings);
+        assert_eq!(probe.dimension.as_deref(), Some("A1:C500"));
+        assert_eq!(probe.sampled_rows, 1);
+        let columns: Vec<_> = probe
+            .columns
+            .iter()
+            .map(|c| (c.header.as_str(), c.value_type.as_str()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![("Name", "string"), ("Score", "number"), ("C", "empty")]
+        );
+
+        let probe = probe_worksheet_impl(xml, 10, &strings);
+        assert_eq!(probe.sampled_rows, 3);
+        assert_eq!(probe.columns[0].value_type, "mixed");
+        assert_eq!(probe.columns[1].value_type, "mixed");
+    }
+}
@@ -0,0 +1,254 @@
+//! Workbook structure validation
+//!
+//! [`validate_workbook`] opens a package in repair mode and turns whatever
+//! had to be worked around, plus problems that don't stop a read (duplicate
+//! sheet names, unreadable references, style and shared string indices past
+//! the end of their tables), into a flat list of issues for QA tooling.
+
+use crate::limits::ParseLimits;
+use crate::reference::range_bounds;
+use crate::repair::Repair;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::workbook::{read_workbook, WorkbookData};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// One problem found in a workbook
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// "error" for content that is lost or wrong, "warning" for packaging
+    /// that Excel would tolerate
+    pub severity: String,
+    /// Machine-readable kind, e.g. "duplicate_sheet_name" or "missing_part"
+    pub code: String,
+    pub part: Option<String>,
+    pub sheet: Option<String>,
+    /// First cell or range the issue applies to
+    pub reference: Option<String>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(severity: &str, code: &str, message: String) -> Self {
+        ValidationIssue {
+            severity: severity.to_string(),
+            code: code.to_string(),
+            part: None,
+            sheet: None,
+            reference: None,
+            message,
+        }
+    }
+
+    fn in_sheet(mut self, sheet: &str, reference: Option<&str>) -> Self {
+        self.sheet = Some(sheet.to_string());
+        self.reference = reference.map(str::to_string);
+        self
+    }
+}
+
+impl From<Repair> for ValidationIssue {
+    fn from(repair: Repair) -> Self {
+        let severity = match repair.kind.as_str() {
+            "missing_content_type" => "warning",
+            _ => "error",
+        };
+        let mut issue = ValidationIssue::new(severity, &repair.kind, repair.description);
+        issue.part = repair.part;
+        issue
+    }
+}
+
+/// Check an .xlsx, .ods or .xls file's structure, returning a list of
+/// `{ severity, code, part, sheet, reference, message }` issues; an empty
+/// list means nothing was found
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn validate_workbook(data: &[u8]) -> Result<JsValue, JsValue> {
+    trap(|| Ok(serde_wasm_bindgen::to_value(&validate_impl(data))?))
+}
+
+pub(crate) fn validate_impl(data: &[u8]) -> Vec<ValidationIssue> {
+    let mut repairs = Vec::new();
+    let workbook = match read_workbook(data, ParseLimits::default(), Some(&mut repairs)) {
+        Ok(workbook) => workbook,
+        Err(e) => return vec![ValidationIssue::new("error", "unreadable", e.into())],
+    };
+    let mut issues: Vec<ValidationIssue> = repairs.into_iter().map(Into::into).collect();
+    check_sheets(&workbook, &mut issues);
+    issues
+}
+
+fn check_sheets(workbook: &WorkbookData, issues: &mut Vec<ValidationIssue>) {
+    let mut seen: Vec<String> = Vec::new();
+    for sheet in &workbook.sheets {
+        let name = &sheet.name;
+        let folded = name.to_lowercase();
+        if seen.contains(&folded) {
+            issues.push(
+                ValidationIssue::new(
+                    "error",
+                    "duplicate_sheet_name",
+                    format!("Sheet name {} is used more than once", name),
+                )
+                .in_sheet(name, None),
+            );
+        }
+        seen.push(folded);
+
+        let worksheet = &sheet.worksheet;
+        for warning in &worksheet.warnings {
+            let issue = ValidationIssue::new("error", warning.kind.code(), warning.to_string());
+            issues.push(issue.in_sheet(name, warning.last_cell.as_deref()));
+        }
+        let ranges = worksheet.merge_cells.iter().chain(
+            worksheet
+                .hyperlinks
+                .iter()
+                .map(|hyperlink| &hyperlink.reference),
+        );
+        for range in ranges.filter(|range| range_bounds(range).is_none()) {
+            let message = format!("Invalid reference {} in sheet {}", range, name);
+            let issue = ValidationIssue::new("error", "invalid_reference", message);
+            issues.push(issue.in_sheet(name, Some(range)));
+        }
+
+        // Out-of-range indices are counted per sheet rather than listed per cell
+        let cells = worksheet.rows.iter().flat_map(|row| &row.cells);
+        let style_count = workbook.styles.as_ref().map(|styles| styles.cell_xfs.len());
+        let bad_styles: Vec<_> = cells
+            .clone()
+            .filter(|cell| {
+                let index = cell.style_index.unwrap_or(0) as usize;
+                style_count.is_some_and(|count| index > 0 && index >= count)
+            })
+            .collect();
+        if let Some(first) = bad_styles.first() {
+            let message = format!(
+                "{} cell(s) in sheet {} use style indices past the {} defined",
+                bad_styles.len(),
+                name,
+                style_count.unwrap_or(0)
+            );
+            let issue = ValidationIssue::new("error", "style_out_of_range", message);
+            issues.push(issue.in_sheet(name, Some(&first.reference)));
+        }
+        let string_count = workbook.shared_strings.len();
+        let bad_strings: Vec<_> = cells
+            .filter(|cell| cell.cell_type.as_deref() == Some("s"))
+            .filter(|cell| {
+                let index = cell.value.as_deref().and_then(|v| v.parse::<usize>().ok());
+                index.is_none_or(|index| index >= string_count)
+            })
+            .collect();
+        if let Some(first) = bad_strings.first() {
+            let message = format!(
+                "{} cell(s) in sheet {} refer to shared strings past the {} defined",
+                bad_strings.len(),
+                name,
+                string_count
+            );
+            let issue = ValidationIssue::new("error", "shared_string_out_of_range", message);
+            issues.push(issue.in_sheet(name, Some(&first.reference)));
+        }
+    }
+
+    if let Some(styles) = &workbook.styles {
+        let tables = [
+            ("font", styles.fonts.len()),
+            ("fill", styles.fills.len()),
+            ("border", styles.borders.len()),
+        ];
+        for (index, xf) in styles.cell_xfs.iter().enumerate() {
+            let ids = [xf.font_id, xf.fill_id, xf.border_id];
+            for ((table, len), id) in tables.iter().zip(ids) {
+                if id.is_some_and(|id| id as usize >= *len) {
+                    let mut issue = ValidationIssue::new(
+                        "error",
+                        "style_out_of_range",
+                        format!(
+                            "Cell format {} uses {} {} but only {} are defined",
+                            index,
+                            table,
+                            id.unwrap_or(0),
+                            len
+                        ),
+                    );
+                    issue.part = Some("xl/styles.xml".to_string());
+                    issues.push(issue);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParsedCell, ParsedRow, ParsedSheet, ParsedStyle, ParsedStyles, ParsedWorksheet};
+
+    #[test]
+    fn test_check_sheets() {
+        let cell = |reference: &str, cell_type: Option<&str>, value: &str, style| ParsedCell {
+            reference: reference.to_string(),
+            cell_type: cell_type.map(str::to_string),
+            style_index: Some(style),
+            value: Some(value.to_string()),
+            formula: None,
+        };
+        let sheet = |name: &str| ParsedSheet {
+            name: name.to_string(),
+            state: None,
+            worksheet: ParsedWorksheet {
+                rows: vec![ParsedRow {
+                    row_num: 1,
+                    cells: vec![
+                        cell("A1", Some("s"), "0", 0),
+                        cell("B1", Some("s"), "4", 0),
+                        cell("C1", None, "1", 7),
+                    ],
+                    height: None,
+                    hidden: false,
+                }],
+                merge_cells: vec!["A1:B1".to_string(), "A0".to_string()],
+                ..Default::default()
+            },
+        };
+        let styles = ParsedStyles {
+            cell_xfs: vec![ParsedStyle {
+                font_id: Some(2),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let workbook = WorkbookData {
+            sheets: vec![sheet("Data"), sheet("DATA")],
+            shared_strings: vec!["x".to_string()],
+            styles: Some(styles),
+            date1904: false,
+            defined_names: Vec::new(),
+        };
+
+        let mut issues = Vec::new();
+        check_sheets(&workbook, &mut issues);
+        let codes: Vec<_> = issues
+            .iter()
+            .map(|i| (i.code.as_str(), i.reference.as_deref()))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                ("invalid_reference", Some("A0")),
+                ("style_out_of_range", Some("C1")),
+                ("shared_string_out_of_range", Some("B1")),
+                ("duplicate_sheet_name", None),
+                ("invalid_reference", Some("A0")),
+                ("style_out_of_range", Some("C1")),
+                ("shared_string_out_of_range", Some("B1")),
+                ("style_out_of_range", None),
+            ]
+        );
+    }
+}
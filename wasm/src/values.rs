@@ -0,0 +1,341 @@
+//! Cell value resolution and date handling shared by the export paths
+
+use crate::{ParsedCell, ParsedStyles};
+
+/// A resolved cell value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CellValue {
+    Empty,
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(String),
+}
+
+/// Resolve a parsed cell to its typed value, looking up shared strings
+pub(crate) fn resolve_cell_value(cell: &ParsedCell, shared_strings: &[String]) -> CellValue {
+    let value = match cell.value.as_deref() {
+        Some(value) => value,
+        None => return CellValue::Empty,
+    };
+
+    match cell.cell_type.as_deref() {
+        Some("s") => value
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| shared_strings.get(index))
+            .map(|text| CellValue::Text(text.clone()))
+            .unwrap_or(CellValue::Text(String::new())),
+        Some("b") => CellValue::Bool(value == "1" || value.eq_ignore_ascii_case("true")),
+        Some("e") => CellValue::Error(value.to_string()),
+        Some("str") | Some("inlineStr") => CellValue::Text(value.to_string()),
+        _ => match value.parse::<f64>() {
+            Ok(number) => CellValue::Number(number),
+            Err(_) if value.is_empty() => CellValue::Empty,
+            Err(_) => CellValue::Text(value.to_string()),
+        },
+    }
+}
+
+/// Format a number the way Excel displays a General-formatted value
+pub(crate) fn format_number(number: f64) -> String {
+    if number.is_nan() {
+        "NaN".to_string()
+    } else if number.is_infinite() {
+        if number > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+        .to_string()
+    } else {
+        number.to_string()
+    }
+}
+
+/// Built-in number format ids that represent dates or times
+pub(crate) const DATE_FORMAT_IDS: [u32; 12] = [14, 15, 16, 17, 18, 19, 20, 21, 22, 45, 46, 47];
+
+/// Check if a number format represents a date/time
+pub(crate) fn is_date_format(num_fmt_id: u32, format_code: Option<&str>) -> bool {
+    if DATE_FORMAT_IDS.contains(&num_fmt_id) {
+        return true;
+    }
+
+    let code = match format_code {
+        Some(code) => code,
+        None => return false,
+    };
+
+    // Ignore quoted literals, escaped characters and [color]/[locale] sections
+    let mut in_quotes = false;
+    let mut in_brackets = false;
+    let mut escaped = false;
+    let mut has_date_token = false;
+    for ch in code.chars() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            _ if in_quotes => {}
+            '\\' => escaped = true,
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            _ if in_brackets => {}
+            '0' | '#' | '?' => return false,
+            'd' | 'D' | 'm' | 'M' | 'y' | 'Y' | 'h' | 'H' | 's' | 'S' => has_date_token = true,
+            _ => {}
+        }
+    }
+    has_date_token
+}
+
+/// For each cellXfs entry, whether it applies a date/time number format
+pub(crate) fn date_style_flags(styles: &ParsedStyles) -> Vec<bool> {
+    styles
+        .cell_xfs
+        .iter()
+        .map(|xf| {
+            let id = xf.num_fmt_id.unwrap_or(0);
+            is_date_format(id, styles.num_fmts.get(&id).map(String::as_str))
+        })
+        .collect()
+}
+
+/// Calendar date and time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DateTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    /// ISO 8601 date, with the time appended when it isn't midnight
+    pub(crate) fn to_iso(self) -> String {
+        let date = format!("{:04}-{:02}-{:02}", self.year, self.month, self.day);
+        if self.hour == 0 && self.minute == 0 && self.second == 0 {
+            date
+        } else {
+            format!(
+                "{}T{:02}:{:02}:{:02}",
+                date, self.hour, self.minute, self.second
+            )
+        }
+    }
+}
+
+/// Days from 1970-01-01 to the given civil date (proleptic Gregorian)
+pub(crate) fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year } as i64;
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Civil date for a count of days from 1970-01-01
+pub(crate) fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+    (year, month, day)
+}
+
+/// Convert an Excel serial date to a calendar date and time
+///
+/// Mirrors `excelSerialToDate` in the JS reader, including the 1900 leap
+/// year bug (serial 60 is the non-existent 1900-02-29).
+pub(crate) fn serial_to_datetime(serial: f64, date1904: bool) -> Option<DateTime> {
+    if !serial.is_finite() || serial < 0.0 {
+        return None;
+    }
+
+    let total_seconds = (serial * 86400.0).round() as i64;
+    let mut days = total_seconds.div_euclid(86400);
+    let seconds = total_seconds.rem_euclid(86400) as u32;
+
+    let epoch = if date1904 {
+        days_from_civil(1904, 1, 1)
+    } else {
+        if days >= 60 {
+            days -= 1;
+        }
+        days_from_civil(1899, 12, 31)
+    };
+
+    let (year, month, day) = civil_from_days(epoch + days);
+    Some(DateTime {
+        year,
+        month,
+        day,
+        hour: seconds / 3600,
+        minute: seconds / 60 % 60,
+        second: seconds % 60,
+    })
+}
+
+/// Convert a calendar date and time to an Excel serial date (1900 system)
+pub(crate) fn datetime_to_serial(dt: &DateTime) -> f64 {
+    let mut days = days_from_civil(dt.year, dt.month, dt.day) - days_from_civil(1899, 12, 31);
+    // Account for the phantom 1900-02-29
+    if days >= 60 {
+        days += 1;
+    }
+    let seconds = dt.hour * 3600 + dt.minute * 60 + dt.second;
+    days as f64 + seconds as f64 / 86400.0
+}
+
+/// Parse an ISO 8601 date (`yyyy-mm-dd`), optionally followed by a time
+pub(crate) fn parse_iso_date(value: &str) -> Option<DateTime> {
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time.trim_end_matches('Z'))),
+        None => (value, None),
+    };
+
+    let mut parts = date.split('-');
+    let year_part = parts.next()?;
+    let month_part = parts.next()?;
+    let day_part = parts.next()?;
+    if parts.next().is_some() || year_part.len() != 4 {
+        return None;
+    }
+    let mut dt = valid_date(
+        year_part.parse().ok()?,
+        month_part.parse().ok()?,
+        day_part.parse().ok()?,
+    )?;
+
+    if let Some(time) = time {
+        let mut parts = time.split(':');
+        dt.hour = parts.next()?.parse().ok()?;
+        dt.minute = parts.next()?.parse().ok()?;
+        dt.second = match parts.next() {
+            Some(seconds) => seconds.parse::<f64>().ok()? as u32,
+            None => 0,
+        };
+        if dt.hour > 23 || dt.minute > 59 || dt.second > 59 {
+            return None;
+        }
+    }
+    Some(dt)
+}
+
+/// Build a date, rejecting out-of-range components such as 2023-02-30
+pub(crate) fn valid_date(year: i32, month: u32, day: u32) -> Option<DateTime> {
+    if year <= 0 || !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let days_in_month = days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1);
+    if day as i64 > days_in_month {
+        return None;
+    }
+    Some(DateTime {
+        year,
+        month,
+        day,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    })
+}
+
+/// Format a date using the CSV exporter's tokens
+///
+/// `ISO` produces `yyyy-mm-dd`; otherwise `yyyy`, `yy`, `mm`, `dd`, `HH`,
+/// `MM` and `SS` are substituted, matching the JS `formatDate` helper.
+pub(crate) fn format_datetime(dt: &DateTime, format: &str) -> String {
+    if format == "ISO" {
+        return format!("{:04}-{:02}-{:02}", dt.year, dt.month, dt.day);
+    }
+
+    let mut out = String::with_capacity(format.len() + 4);
+    let mut rest = format;
+    while !rest.is_empty() {
+        let (token, replacement) = if rest.starts_with("yyyy") {
+            (4, format!("{:04}", dt.year))
+        } else if rest.starts_with("yy") {
+            (2, format!("{:02}", dt.year.rem_euclid(100)))
+        } else if rest.starts_with("mm") {
+            (2, format!("{:02}", dt.month))
+        } else if rest.starts_with("dd") {
+            (2, format!("{:02}", dt.day))
+        } else if rest.starts_with("HH") {
+            (2, format!("{:02}", dt.hour))
+        } else if rest.starts_with("MM") {
+            (2, format!("{:02}", dt.minute))
+        } else if rest.starts_with("SS") {
+            (2, format!("{:02}", dt.second))
+        } else {
+            let ch = rest.chars().next().unwrap_or_default();
+            (ch.len_utf8(), ch.to_string())
+        };
+        out.push_str(&replacement);
+        rest = &rest[token..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl DateTime {
+        fn ymd(self) -> String {
+            format_datetime(&self, "ISO")
+        }
+    }
+
+    #[test]
+    fn test_serial_to_datetime() {
+        let dt = serial_to_datetime(45292.0, false).unwrap();
+        assert_eq!((dt.year, dt.month, dt.day), (2024, 1, 1));
+        assert_eq!(serial_to_datetime(1.0, false).unwrap().ymd(), "1900-01-01");
+        assert_eq!(serial_to_datetime(61.0, false).unwrap().ymd(), "1900-03-01");
+        assert_eq!(
+            format_datetime(
+                &serial_to_datetime(45292.75, false).unwrap(),
+                "yyyy-mm-ddTHH:MM:SS"
+            ),
+            "2024-01-01T18:00:00"
+        );
+        assert_eq!(serial_to_datetime(0.0, true).unwrap().ymd(), "1904-01-01");
+        assert_eq!(format_datetime(&dt, "dd/mm/yyyy HH:MM"), "01/01/2024 00:00");
+    }
+
+    #[test]
+    fn test_to_iso() {
+        let to_iso = |serial, date1904| serial_to_datetime(serial, date1904).unwrap().to_iso();
+        assert_eq!(to_iso(45292.0, false), "2024-01-01");
+        assert_eq!(to_iso(45292.75, false), "2024-01-01T18:00:00");
+        assert_eq!(to_iso(0.0, true), "1904-01-01");
+    }
+
+    #[test]
+    fn test_is_date_format() {
+        assert!(is_date_format(14, None));
+        assert!(is_date_format(164, Some("yyyy-mm-dd")));
+        assert!(is_date_format(165, Some("[$-409]d-mmm;@")));
+        assert!(!is_date_format(166, Some("0.00%")));
+        assert!(!is_date_format(167, Some("\"days\" 0")));
+        assert!(!is_date_format(0, None));
+    }
+}
@@ -0,0 +1,475 @@
+//! Custom sheet views
+//!
+//! Excel's View > Custom Views saves named combinations of display and
+//! print settings. Each sheet keeps its part of a view in
+//! `<customSheetViews>`: zoom, grid lines, page setup and the view's own
+//! filter. Which rows and columns the view hides, and what it prints, are
+//! stored as hidden workbook names (`Z_<GUID>_.wvu.Rows`, `.wvu.Cols`,
+//! `.wvu.PrintArea`, `.wvu.PrintTitles`) local to the sheet; given the
+//! workbook's defined names, the parser reads those into the view too.
+
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::workbook::split_sheet;
+use crate::{
+    AutoFilter, CustomFilter, FilterColumn, ParsedDefinedName, ParsedRow, ParsedWorksheet,
+};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// One sheet's part of a custom view
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomSheetView {
+    /// The view's id ("{9C1F...}"), shared by its part on every sheet
+    pub guid: String,
+    /// Zoom in percent
+    pub scale: u32,
+    /// "normal", "pageBreakPreview" or "pageLayout"
+    pub view: Option<String>,
+    /// "visible", "hidden" or "veryHidden": whether the view shows the sheet
+    pub state: Option<String>,
+    /// Cell scrolled to the top left
+    pub top_left_cell: Option<String>,
+    pub show_grid_lines: bool,
+    pub show_row_col: bool,
+    pub show_formulas: bool,
+    pub show_zeros: bool,
+    pub show_auto_filter: bool,
+    /// Rows the view hides ("3:5"), from its `.wvu.Rows` name
+    pub hidden_rows: Vec<String>,
+    /// Columns the view hides ("C:D"), from its `.wvu.Cols` name
+    pub hidden_columns: Vec<String>,
+    /// Ranges printed, from its `.wvu.PrintArea` name
+    pub print_area: Vec<String>,
+    /// Rows and columns repeated on each printed page, from its
+    /// `.wvu.PrintTitles` name
+    pub print_titles: Vec<String>,
+    /// The view's filter dropdowns and criteria
+    pub auto_filter: Option<AutoFilter>,
+    pub print: ViewPrintSettings,
+}
+
+/// How a custom view prints its sheet
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewPrintSettings {
+    /// "portrait" or "landscape"
+    pub orientation: Option<String>,
+    /// Paper size code: 1 for Letter, 9 for A4, ...
+    pub paper_size: Option<u32>,
+    /// Print scale in percent
+    pub scale: Option<u32>,
+    /// Shrink to `fit_to_width` pages wide and `fit_to_height` tall
+    /// rather than print at `scale`
+    pub fit_to_page: bool,
+    /// Pages wide, 0 for any number; Excel takes 1 when missing
+    pub fit_to_width: Option<u32>,
+    /// Pages tall, 0 for any number; Excel takes 1 when missing
+    pub fit_to_height: Option<u32>,
+    pub margins: Option<PageMargins>,
+    pub grid_lines: bool,
+    pub headings: bool,
+    pub horizontal_centered: bool,
+    pub vertical_centered: bool,
+}
+
+/// Page margins in inches
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageMargins {
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+    pub header: f64,
+    pub footer: f64,
+}
+
+/// Parse the `<customSheetViews>` of a worksheet. With the workbook's
+/// `defined_names`, the hidden rows and columns and print ranges of each
+/// view are filled in; `sheet_index` (from 0) picks the sheet's own names
+/// when the workbook has several.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_custom_sheet_views(
+    xml: &str,
+    defined_names: JsValue,
+    sheet_index: Option<u32>,
+) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let names: Option<Vec<ParsedDefinedName>> = serde_wasm_bindgen::from_value(defined_names)?;
+        let views = parse_custom_sheet_views_impl(xml, &names.unwrap_or_default(), sheet_index);
+        Ok(serde_wasm_bindgen::to_value(&views)?)
+    })
+}
+
+/// Show `worksheet` as `view` does: hide the view's rows, unhide the
+/// rest, and take its filter. Hidden columns and print settings have no
+/// place in the worksheet model and are left to the host.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn apply_custom_sheet_view(worksheet: JsValue, view: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let mut worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        let view: CustomSheetView = serde_wasm_bindgen::from_value(view)?;
+        apply_custom_sheet_view_impl(&mut worksheet, &view);
+        Ok(serde_wasm_bindgen::to_value(&worksheet)?)
+    })
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+fn flag(e: &BytesStart, name: &[u8], default: bool) -> bool {
+    attribute(e, name).map_or(default, |v| v == "1" || v == "true")
+}
+
+fn number<T: std::str::FromStr>(e: &BytesStart, name: &[u8]) -> Option<T> {
+    attribute(e, name).and_then(|v| v.parse().ok())
+}
+
+pub(crate) fn parse_custom_sheet_views_impl(
+    xml: &str,
+    defined_names: &[ParsedDefinedName],
+    sheet_index: Option<u32>,
+) -> Vec<CustomSheetView> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut views = Vec::new();
+    let mut view: Option<CustomSheetView> = None;
+
+    loop {
+        buf.clear();
+        let event = reader.read_event_into(&mut buf);
+        let empty = matches!(event, Ok(Event::Empty(_)));
+        match event {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                if e.local_name().as_ref() == b"customSheetView" {
+                    let new = CustomSheetView {
+                        guid: attribute(&e, b"guid").unwrap_or_default(),
+                        scale: number(&e, b"scale").unwrap_or(100),
+                        view: attribute(&e, b"view"),
+                        state: attribute(&e, b"state"),
+                        top_left_cell: attribute(&e, b"topLeftCell"),
+                        show_grid_lines: flag(&e, b"showGridLines", true),
+                        show_row_col: flag(&e, b"showRowCol", true),
+                        show_formulas: flag(&e, b"showFormulas", false),
+                        show_zeros: flag(&e, b"showZeros", true),
+                        show_auto_filter: flag(&e, b"showAutoFilter", false),
+                        print: ViewPrintSettings {
+                            fit_to_page: flag(&e, b"fitToPage", false),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    match empty {
+                        true => views.push(new),
+                        false => view = Some(new),
+                    }
+                    continue;
+                }
+                let Some(view) = view.as_mut() else {
+                    continue;
+                };
+                let print = &mut view.print;
+                match e.local_name().as_ref() {
+                    b"pageMargins" => {
+                        let margin = |name: &[u8]| number(&e, name).unwrap_or(0.0);
+                        print.margins = Some(PageMargins {
+                            left: margin(b"left"),
+                            right: margin(b"right"),
+                            top: margin(b"top"),
+                            bottom: margin(b"bottom"),
+                            header: margin(b"header"),
+                            footer: margin(b"footer"),
+                        });
+                    }
+                    b"printOptions" => {
+                        print.grid_lines = flag(&e, b"gridLines", false);
+                        print.headings = flag(&e, b"headings", false);
+                        print.horizontal_centered = flag(&e, b"horizontalCentered", false);
+                        print.vertical_centered = flag(&e, b"verticalCentered", false);
+                    }
+                    b"pageSetup" => {
+                        print.orientation = attribute(&e, b"orientation");
+                        print.paper_size = number(&e, b"paperSize");
+                        print.scale = number(&e, b"scale");
+                        print.fit_to_width = number(&e, b"fitToWidth");
+                        print.fit_to_height = number(&e, b"fitToHeight");
+                    }
+                    b"autoFilter" => {
+                        view.auto_filter = Some(AutoFilter {
+                            reference: attribute(&e, b"ref").unwrap_or_default(),
+                            columns: Vec::new(),
+                        });
+                    }
+                    name => {
+                        if let Some(filter) = view.auto_filter.as_mut() {
+                            read_filter(filter, name, &e);
+                        }
+                    }
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"customSheetView" => {
+                views.extend(view.take());
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    for view in &mut views {
+        read_view_names(view, defined_names, sheet_index);
+    }
+    views
+}
+
+/// Add one element inside `<autoFilter>` to the filter
+fn read_filter(filter: &mut AutoFilter, name: &[u8], e: &BytesStart) {
+    if name == b"filterColumn" {
+        filter.columns.push(FilterColumn {
+            col_id: number(e, b"colId").unwrap_or(0),
+            ..Default::default()
+        });
+        return;
+    }
+    let Some(column) = filter.columns.last_mut() else {
+        return;
+    };
+    match name {
+        b"filters" => column.blank = flag(e, b"blank", false),
+        b"filter" => column.values.extend(attribute(e, b"val")),
+        b"customFilters" => column.and = flag(e, b"and", false),
+        b"customFilter" => column.custom.push(CustomFilter {
+            operator: attribute(e, b"operator"),
+            value: attribute(e, b"val").unwrap_or_default(),
+        }),
+        _ => {}
+    }
+}
+
+/// Fill in the ranges stored in the view's `Z_<GUID>_.wvu.*` names
+fn read_view_names(
+    view: &mut CustomSheetView,
+    defined_names: &[ParsedDefinedName],
+    sheet_index: Option<u32>,
+) {
+    let guid = view
+        .guid
+        .trim_matches(|c| c == '{' || c == '}')
+        .replace('-', "_");
+    let prefix = format!("Z_{}_.wvu.", guid).to_ascii_lowercase();
+    for name in defined_names {
+        if sheet_index.is_some() && name.local_sheet_id != sheet_index {
+            continue;
+        }
+        let lower = name.name.to_ascii_lowercase();
+        let Some(kind) = lower.strip_prefix(&prefix) else {
+            continue;
+        };
+        let ranges = name_ranges(&name.value);
+        match kind {
+            "rows" => view.hidden_rows = ranges,
+            "cols" => view.hidden_columns = ranges,
+            "printarea" => view.print_area = ranges,
+            "printtitles" => view.print_titles = ranges,
+            _ => {}
+        }
+    }
+}
+
+/// The ranges of a name's value ("Sheet1!$3:$5,Sheet1!$8:$8"), without
+/// sheet names or `$`
+fn name_ranges(value: &str) -> Vec<String> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in value.char_indices().chain([(value.len(), ',')]) {
+        match c {
+            '\'' => quoted = !quoted,
+            ',' if !quoted => {
+                let (_, local) = split_sheet(value[start..i].trim());
+                if !local.is_empty() {
+                    ranges.push(local.replace('$', ""));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/// First and last row of a row range ("3:5" or "8")
+fn row_span(range: &str) -> Option<(u32, u32)> {
+    let (first, last) = range.split_once(':').unwrap_or((range, range));
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
+
+pub(crate) fn apply_custom_sheet_view_impl(
+    worksheet: &mut ParsedWorksheet,
+    view: &CustomSheetView,
+) {
+    let spans: Vec<(u32, u32)> = view
+        .hidden_rows
+        .iter()
+        .filter_map(|r| row_span(r))
+        .collect();
+    let hidden = |row: u32| {
+        spans
+            .iter()
+            .any(|&(first, last)| (first..=last).contains(&row))
+    };
+    let mut present = HashSet::new();
+    for row in &mut worksheet.rows {
+        row.hidden = hidden(row.row_num);
+        present.insert(row.row_num);
+    }
+    // Hidden rows without cells are written too, up to the last row used;
+    // hiding to the bottom of the sheet shouldn't add a million rows
+    let last = present.iter().copied().max().unwrap_or(0);
+    for &(first, end) in &spans {
+        for row_num in first..=end.min(last) {
+            if present.insert(row_num) {
+                worksheet.rows.push(ParsedRow {
+                    row_num,
+                    cells: Vec::new(),
+                    height: None,
+                    hidden: true,
+                });
+            }
+        }
+    }
+    worksheet.rows.sort_by_key(|row| row.row_num);
+    worksheet.auto_filter = view.auto_filter.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParsedCell;
+
+    #[test]
+    fn test_custom_sheet_views() {
+        let xml = r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+            <sheetData/>
+            <customSheetViews>
+                <customSheetView guid="{6B1E7A3F-5D2C-4C3B-9C43-1A2B3C4D5E6F}" scale="85"
+                    showGridLines="0" fitToPage="1" hiddenRows="1" hiddenColumns="1"
+                    printArea="1" filter="1" showAutoFilter="1" topLeftCell="B3">
+                    <selection activeCell="B3" sqref="B3"/>
+                    <pageMargins left="0.5" right="0.5" top="0.75" bottom="0.75"
+                        header="0.3" footer="0.3"/>
+                    <printOptions gridLines="1"/>
+                    <pageSetup paperSize="9" orientation="landscape" fitToHeight="0"/>
+                    <autoFilter ref="A1:C6">
+                        <filterColumn colId="1">
+                            <filters blank="1"><filter val="East"/></filters>
+                        </filterColumn>
+                    </autoFilter>
+                </customSheetView>
+                <customSheetView guid="{0D4C8E21-7F3A-4B6D-8E9F-102132435465}"/>
+            </customSheetViews>
+        </worksheet>"#;
+        let name = |name: &str, value: &str, sheet: u32| ParsedDefinedName {
+            name: name.to_string(),
+            value: value.to_string(),
+            local_sheet_id: Some(sheet),
+            hidden: true,
+        };
+        let guid = "Z_6B1E7A3F_5D2C_4C3B_9C43_1A2B3C4D5E6F_";
+        let names = [
+            name(&format!("{}.wvu.Rows", guid), "Data!$3:$4,Data!$9:$12", 0),
+            name(&format!("{}.wvu.Cols", guid), "Data!$D:$E", 0),
+            name(
+                &format!("{}.wvu.PrintArea", guid),
+                "'My, Data'!$A$1:$C$20",
+                0,
+            ),
+            name(&format!("{}.wvu.Rows", guid), "Other!$1:$1", 1),
+        ];
+
+        let views = parse_custom_sheet_views_impl(xml, &names, Some(0));
+        assert_eq!(views.len(), 2);
+        let view = &views[0];
+        assert_eq!(view.scale, 85);
+        assert!(!view.show_grid_lines && view.show_auto_filter);
+        assert_eq!(view.top_left_cell.as_deref(), Some("B3"));
+        assert_eq!(view.hidden_rows, ["3:4", "9:12"]);
+        assert_eq!(view.hidden_columns, ["D:E"]);
+        assert_eq!(view.print_area, ["A1:C20"]);
+        assert_eq!(
+            view.print,
+            ViewPrintSettings {
+                orientation: Some("landscape".to_string()),
+                paper_size: Some(9),
+                fit_to_page: true,
+                fit_to_height: Some(0),
+                margins: Some(PageMargins {
+                    left: 0.5,
+                    right: 0.5,
+                    top: 0.75,
+                    bottom: 0.75,
+                    header: 0.3,
+                    footer: 0.3,
+                }),
+                grid_lines: true,
+                ..Default::default()
+            }
+        );
+        let filter = view.auto_filter.as_ref().unwrap();
+        assert_eq!(filter.reference, "A1:C6");
+        assert_eq!(filter.columns[0].col_id, 1);
+        assert_eq!(filter.columns[0].values, ["East"]);
+        assert!(filter.columns[0].blank);
+        assert_eq!(views[1].scale, 100);
+        assert!(views[1].show_grid_lines && views[1].hidden_rows.is_empty());
+
+        let row = |row_num: u32, hidden: bool| ParsedRow {
+            row_num,
+            cells: vec![ParsedCell {
+                reference: format!("A{}", row_num),
+                cell_type: None,
+                style_index: None,
+                value: Some("1".to_string()),
+                formula: None,
+            }],
+            height: None,
+            hidden,
+        };
+        let mut worksheet = ParsedWorksheet {
+            rows: vec![row(1, false), row(2, true), row(3, false), row(10, false)],
+            ..Default::default()
+        };
+        apply_custom_sheet_view_impl(&mut worksheet, view);
+        let hidden: Vec<_> = worksheet
+            .rows
+            .iter()
+            .map(|row| (row.row_num, row.hidden))
+            .collect();
+        // Rows 11 and 12 are past the last row used
+        assert_eq!(
+            hidden,
+            [
+                (1, false),
+                (2, false),
+                (3, true),
+                (4, true),
+                (9, true),
+                (10, true)
+            ]
+        );
+        assert_eq!(worksheet.auto_filter.unwrap().reference, "A1:C6");
+    }
+}
@@ -0,0 +1,772 @@
+//! Persistent workbook handle with random-access cell reads
+//!
+//! The workbook is parsed into Rust memory once; JS then pulls individual
+//! cells, ranges or rows as typed values instead of receiving every sheet
+//! as one large object.
+
+use crate::context::ParserScratch;
+use crate::cursor::RowCursor;
+use crate::encoding::{detect_encoding, xml_to_string, XmlEncoding};
+use crate::limits::{LimitExceeded, ParseLimits, ReadError};
+use crate::memory;
+#[cfg(feature = "ods")]
+use crate::ods::parse_ods_with;
+use crate::profile::phase;
+use crate::reference::{cell_ref, parse_cell_ref, range_bounds, Bounds};
+use crate::repair::{default_sheet_path, ContentTypes, Repair};
+use crate::search::{search_impl, SearchOptions};
+use crate::sheet::Sheet;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::{date_style_flags, resolve_cell_value, serial_to_datetime, CellValue};
+#[cfg(feature = "xls")]
+use crate::xls::parse_xls_impl;
+use crate::zip::ZipArchive;
+use crate::{
+    parse_relationships_impl, parse_shared_strings_impl, parse_styles_impl, parse_workbook_impl,
+    parse_worksheet_from, parse_worksheet_with, ParsedCell, ParsedDefinedName, ParsedSheet,
+    ParsedStyles, ParsedWorksheet, WorksheetParseOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
+use std::rc::Rc;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Read-ahead for worksheet parts that are inflated while they are parsed
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Leading bytes of a worksheet part read to detect its encoding
+const ENCODING_PEEK: usize = 256;
+
+/// Parsed workbook contents accepted by the [`Workbook`] constructor
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkbookData {
+    pub sheets: Vec<ParsedSheet>,
+    #[serde(default)]
+    pub shared_strings: Vec<String>,
+    /// Styles used to report date-formatted numbers as dates
+    #[serde(default)]
+    pub styles: Option<ParsedStyles>,
+    #[serde(default)]
+    pub date1904: bool,
+    /// Named ranges, print areas and the like (.xlsx only)
+    #[serde(default)]
+    pub defined_names: Vec<ParsedDefinedName>,
+}
+
+/// A typed cell value; dates are ISO 8601 strings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum TypedValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+/// One cell as returned by the [`Workbook`] accessors
+#[derive(Debug, Serialize, PartialEq)]
+pub struct CellData {
+    pub reference: String,
+    /// "number", "string", "boolean", "error", "date" or "empty"
+    #[serde(rename = "type")]
+    pub value_type: String,
+    pub value: Option<TypedValue>,
+    pub formula: Option<String>,
+}
+
+pub(crate) struct SheetIndex {
+    pub(crate) name: String,
+    pub(crate) worksheet: ParsedWorksheet,
+    /// (row, col) to (row index, cell index) in `worksheet`
+    cells: HashMap<(u32, u32), (usize, usize)>,
+    /// Last used row and column
+    pub(crate) extent: (u32, u32),
+}
+
+/// The parsed contents, shared by a [`Workbook`] and the [`Sheet`]s and
+/// [`Cell`](crate::sheet::Cell)s taken from it
+pub(crate) struct Book {
+    pub(crate) sheets: Vec<SheetIndex>,
+    shared_strings: Vec<String>,
+    date_styles: Vec<bool>,
+    date1904: bool,
+}
+
+/// A workbook held in wasm memory for on-demand reads
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Workbook {
+    book: Rc<Book>,
+    /// Problems worked around by [`Workbook::open_repaired`]
+    repairs: Vec<Repair>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Workbook {
+    /// Wrap an already parsed workbook (`{ sheets, shared_strings, styles, date1904 }`)
+    #[wasm_bindgen(constructor)]
+    pub fn new(workbook: JsValue) -> Result<Workbook, JsValue> {
+        trap(|| {
+            let workbook: WorkbookData = serde_wasm_bindgen::from_value(workbook)?;
+            Ok(Workbook::from_data(workbook))
+        })
+    }
+
+    /// Open an .xlsx, .ods or .xls file with the default [`ParseLimits`]
+    pub fn open(data: &[u8]) -> Result<Workbook, JsValue> {
+        trap(|| Workbook::open_limited(data, ParseLimits::default()))
+    }
+
+    /// Open a file with custom limits; exceeding one throws `{ error: "limits_exceeded", ... }`
+    pub fn open_with_limits(data: &[u8], limits: JsValue) -> Result<Workbook, JsValue> {
+        trap(|| {
+            let limits: Option<ParseLimits> = serde_wasm_bindgen::from_value(limits)?;
+            Workbook::open_limited(data, limits.unwrap_or_default())
+        })
+    }
+
+    /// Open a damaged .xlsx as best it can, instead of failing on missing
+    /// parts or dangling relationships; see [`Workbook::repairs`]
+    pub fn open_repaired(data: &[u8], limits: JsValue) -> Result<Workbook, JsValue> {
+        trap(|| {
+            let limits: Option<ParseLimits> = serde_wasm_bindgen::from_value(limits)?;
+            let mut repairs = Vec::new();
+            let guard = memory::guard();
+            let workbook = read_workbook(data, limits.unwrap_or_default(), Some(&mut repairs))?;
+            guard.check().map_err(|e| JsValue::from_str(&e))?;
+            let mut workbook = Workbook::from_data(workbook);
+            workbook.repairs = repairs;
+            Ok(workbook)
+        })
+    }
+
+    /// What `open_repaired` had to fix, as `{ kind, part, description }`
+    /// objects; empty for an intact package
+    pub fn repairs(&self) -> Result<JsValue, JsValue> {
+        trap(|| Ok(serde_wasm_bindgen::to_value(&self.repairs)?))
+    }
+
+    /// Sheet names in workbook order
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.book
+            .sheets
+            .iter()
+            .map(|sheet| sheet.name.clone())
+            .collect()
+    }
+
+    /// A [`Sheet`] handle for reading one sheet; defaults to the first sheet
+    pub fn sheet(&self, name: Option<String>) -> Result<Sheet, JsValue> {
+        trap(|| {
+            self.sheet_handle(name.as_deref())
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Handles for every sheet, in workbook order
+    pub fn sheets(&self) -> Vec<Sheet> {
+        (0..self.book.sheets.len())
+            .map(|index| Sheet::new(self.book.clone(), index))
+            .collect()
+    }
+
+    /// Read one cell, e.g. "Sheet1!B2"; unqualified references use the first sheet
+    pub fn get_cell(&self, reference: &str) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let cell = self
+                .book
+                .cell(reference)
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(serde_wasm_bindgen::to_value(&cell)?)
+        })
+    }
+
+    /// Read a rectangular range, e.g. "Sheet1!A1:D100", as an array of rows
+    ///
+    /// Rows and columns past the sheet's last used cell are left out.
+    pub fn get_range(&self, reference: &str) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let rows = self
+                .book
+                .range(reference)
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(serde_wasm_bindgen::to_value(&rows)?)
+        })
+    }
+
+    /// Read a 1-based row from column A to its last used cell
+    pub fn get_row(&self, row: u32, sheet: Option<String>) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let cells = self
+                .book
+                .row(row, sheet.as_deref())
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(serde_wasm_bindgen::to_value(&cells)?)
+        })
+    }
+
+    /// A [`RowCursor`] over a copy of the sheet's rows; defaults to the first sheet
+    pub fn row_cursor(&self, sheet: Option<String>) -> Result<RowCursor, JsValue> {
+        trap(|| {
+            let sheet = self
+                .book
+                .sheet(sheet.as_deref())
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(RowCursor::from_rows(sheet.worksheet.rows.clone()))
+        })
+    }
+
+    /// Find `query` in cell values and formulas; see [`crate::search`]
+    pub fn search(&self, query: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        trap(|| {
+            let options: Option<SearchOptions> = serde_wasm_bindgen::from_value(options)?;
+            let sheets = self
+                .book
+                .sheets
+                .iter()
+                .map(|sheet| (sheet.name.as_str(), &sheet.worksheet));
+            let matches = search_impl(
+                sheets,
+                &self.book.shared_strings,
+                &[],
+                query,
+                &options.unwrap_or_default(),
+            )
+            .map_err(|e| JsValue::from_str(&e))?;
+            Ok(serde_wasm_bindgen::to_value(&matches)?)
+        })
+    }
+}
+
+impl Workbook {
+    #[cfg(feature = "wasm")]
+    fn open_limited(data: &[u8], limits: ParseLimits) -> Result<Workbook, JsValue> {
+        let guard = memory::guard();
+        let workbook = read_workbook(data, limits, None)?;
+        guard.check().map_err(|e| JsValue::from_str(&e))?;
+        Ok(Workbook::from_data(workbook))
+    }
+
+    pub(crate) fn sheet_handle(&self, name: Option<&str>) -> Result<Sheet, String> {
+        let index = self.book.sheet_index(name)?;
+        Ok(Sheet::new(self.book.clone(), index))
+    }
+
+    pub(crate) fn from_data(workbook: WorkbookData) -> Self {
+        let sheets = workbook
+            .sheets
+            .into_iter()
+            .map(|sheet| {
+                let mut cells = HashMap::new();
+                let mut extent = (0, 0);
+                for (r, row) in sheet.worksheet.rows.iter().enumerate() {
+                    for (c, cell) in row.cells.iter().enumerate() {
+                        if let Some((row_num, col)) = parse_cell_ref(&cell.reference) {
+                            cells.insert((row_num, col), (r, c));
+                            extent = (extent.0.max(row_num), extent.1.max(col));
+                        }
+                    }
+                }
+                SheetIndex {
+                    name: sheet.name,
+                    worksheet: sheet.worksheet,
+                    cells,
+                    extent,
+                }
+            })
+            .collect();
+        let book = Book {
+            sheets,
+            shared_strings: workbook.shared_strings,
+            date_styles: workbook
+                .styles
+                .as_ref()
+                .map(date_style_flags)
+                .unwrap_or_default(),
+            date1904: workbook.date1904,
+        };
+        Workbook {
+            book: Rc::new(book),
+            repairs: Vec::new(),
+        }
+    }
+}
+
+impl Book {
+    pub(crate) fn sheet_index(&self, name: Option<&str>) -> Result<usize, String> {
+        match name {
+            None if self.sheets.is_empty() => Err("Workbook has no sheets".to_string()),
+            None => Ok(0),
+            Some(name) => self
+                .sheets
+                .iter()
+                .position(|sheet| sheet.name.to_lowercase() == name.to_lowercase())
+                .ok_or(format!("Unknown sheet: {}", name)),
+        }
+    }
+
+    fn sheet(&self, name: Option<&str>) -> Result<&SheetIndex, String> {
+        Ok(&self.sheets[self.sheet_index(name)?])
+    }
+
+    /// The cell at 1-based `row` and `col`, which reads as empty when unset
+    pub(crate) fn cell_data(&self, sheet: &SheetIndex, row: u32, col: u32) -> CellData {
+        let cell = sheet.parsed_cell(row, col);
+        let (value_type, value) = match cell {
+            Some(cell) => self.typed_value(cell),
+            None => ("empty", None),
+        };
+        CellData {
+            reference: cell_ref(row, col),
+            value_type: value_type.to_string(),
+            value,
+            formula: cell.and_then(|cell| cell.formula.clone()),
+        }
+    }
+
+    fn typed_value(&self, cell: &ParsedCell) -> (&'static str, Option<TypedValue>) {
+        match resolve_cell_value(cell, &self.shared_strings) {
+            CellValue::Empty => ("empty", None),
+            CellValue::Number(number) => {
+                let is_date = cell
+                    .style_index
+                    .and_then(|index| self.date_styles.get(index as usize))
+                    .copied()
+                    .unwrap_or(false);
+                match serial_to_datetime(number, self.date1904).filter(|_| is_date) {
+                    Some(dt) => ("date", Some(TypedValue::Text(dt.to_iso()))),
+                    None => ("number", Some(TypedValue::Number(number))),
+                }
+            }
+            CellValue::Text(text) => ("string", Some(TypedValue::Text(text))),
+            CellValue::Bool(value) => ("boolean", Some(TypedValue::Bool(value))),
+            CellValue::Error(code) => ("error", Some(TypedValue::Text(code))),
+        }
+    }
+
+    pub(crate) fn cell(&self, reference: &str) -> Result<CellData, String> {
+        let (sheet, local) = split_sheet(reference);
+        let sheet = self.sheet(sheet.as_deref())?;
+        let (row, col) =
+            parse_cell_ref(local).ok_or(format!("Invalid cell reference: {}", reference))?;
+        Ok(self.cell_data(sheet, row, col))
+    }
+
+    pub(crate) fn range(&self, reference: &str) -> Result<Vec<Vec<CellData>>, String> {
+        let (sheet, local) = split_sheet(reference);
+        let sheet = self.sheet(sheet.as_deref())?;
+        let bounds = range_bounds(local).ok_or(format!("Invalid range: {}", reference))?;
+        Ok(self.sheet_range(sheet, bounds))
+    }
+
+    /// Rows of `bounds`, clipped to the sheet's last used row and column
+    pub(crate) fn sheet_range(&self, sheet: &SheetIndex, bounds: Bounds) -> Vec<Vec<CellData>> {
+        let (r1, c1, r2, c2) = bounds;
+        let (last_row, last_col) = (r2.min(sheet.extent.0), c2.min(sheet.extent.1));
+        (r1..=last_row)
+            .map(|row| {
+                (c1..=last_col)
+                    .map(|col| self.cell_data(sheet, row, col))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub(crate) fn row(&self, row: u32, sheet: Option<&str>) -> Result<Vec<CellData>, String> {
+        let sheet = self.sheet(sheet)?;
+        self.sheet_row(sheet, row)
+    }
+
+    /// A 1-based row from column A to its last used cell
+    pub(crate) fn sheet_row(&self, sheet: &SheetIndex, row: u32) -> Result<Vec<CellData>, String> {
+        if row == 0 {
+            return Err("Row numbers are 1-based".to_string());
+        }
+        let last_col = sheet
+            .cells
+            .keys()
+            .filter(|&&(r, _)| r == row)
+            .map(|&(_, c)| c)
+            .max()
+            .unwrap_or(0);
+        Ok((1..=last_col)
+            .map(|col| self.cell_data(sheet, row, col))
+            .collect())
+    }
+}
+
+impl SheetIndex {
+    pub(crate) fn parsed_cell(&self, row: u32, col: u32) -> Option<&ParsedCell> {
+        self.cells
+            .get(&(row, col))
+            .map(|&(r, c)| &self.worksheet.rows[r].cells[c])
+    }
+}
+
+/// Relationships part of a worksheet part, e.g. `xl/worksheets/_rels/sheet1.xml.rels`
+fn sheet_rels_path(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((dir, file)) => format!("{}/_rels/{}.rels", dir, file),
+        None => format!("_rels/{}.rels", path),
+    }
+}
+
+/// Fill in the external targets of a sheet's hyperlinks from its relationships
+fn link_targets(worksheet: &mut ParsedWorksheet, rels_xml: Option<String>) {
+    let rels = rels_xml
+        .map(|xml| parse_relationships_impl(&xml))
+        .unwrap_or_default();
+    for link in &mut worksheet.hyperlinks {
+        let rel = rels.iter().find(|rel| Some(&rel.id) == link.rid.as_ref());
+        if let Some(rel) = rel.filter(|rel| rel.target_mode.as_deref() == Some("External")) {
+            link.target = Some(rel.target.clone());
+        }
+    }
+}
+
+/// Split "Sheet1!A1" or "'My Sheet'!A1" into the sheet name and the local reference
+pub(crate) fn split_sheet(reference: &str) -> (Option<String>, &str) {
+    match reference.rsplit_once('!') {
+        Some((sheet, local)) => {
+            let sheet = match sheet.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+                Some(quoted) => quoted.replace("''", "'"),
+                None => sheet.to_string(),
+            };
+            (Some(sheet), local)
+        }
+        None => (None, reference),
+    }
+}
+
+#[cfg(feature = "xls")]
+fn read_xls(data: &[u8]) -> Result<WorkbookData, ReadError> {
+    let xls = phase("xls", || parse_xls_impl(data))?;
+    Ok(WorkbookData {
+        sheets: xls.sheets,
+        shared_strings: xls.shared_strings,
+        styles: Some(xls.styles),
+        date1904: xls.date1904,
+        defined_names: Vec::new(),
+    })
+}
+
+#[cfg(not(feature = "xls"))]
+fn read_xls(_data: &[u8]) -> Result<WorkbookData, ReadError> {
+    Err("Not a ZIP package, and this build leaves out .xls support".into())
+}
+
+#[cfg(feature = "ods")]
+fn read_ods(data: &[u8], limits: ParseLimits) -> Result<WorkbookData, ReadError> {
+    let ods = phase("ods", || parse_ods_with(data, limits))?;
+    Ok(WorkbookData {
+        sheets: ods.sheets,
+        shared_strings: Vec::new(),
+        styles: Some(ods.styles),
+        date1904: false,
+        defined_names: Vec::new(),
+    })
+}
+
+#[cfg(not(feature = "ods"))]
+fn read_ods(_data: &[u8], _limits: ParseLimits) -> Result<WorkbookData, ReadError> {
+    Err("Missing xl/workbook.xml, and this build leaves out .ods support".into())
+}
+
+/// Parse a workbook file, detecting the format from its contents
+///
+/// With `repairs`, problems in an XLSX package that would fail the read are
+/// worked around where possible and recorded there instead.
+pub(crate) fn read_workbook(
+    data: &[u8],
+    limits: ParseLimits,
+    repairs: Option<&mut Vec<Repair>>,
+) -> Result<WorkbookData, ReadError> {
+    if !data.starts_with(b"PK") {
+        return read_xls(data);
+    }
+
+    let archive = ZipArchive::new(data, limits)?;
+    let read = |name: &str| -> Result<Option<String>, ReadError> {
+        match phase("unzip", || archive.read(name))? {
+            Some(bytes) => Ok(Some(xml_to_string(bytes)?)),
+            None => Ok(None),
+        }
+    };
+    let text = |name: &str| -> Result<Option<String>, ReadError> {
+        let xml = read(name)?;
+        if let Some(xml) = &xml {
+            limits.check_xml(name, xml, b"c")?;
+        }
+        Ok(xml)
+    };
+    let workbook_xml = match text("xl/workbook.xml")? {
+        Some(xml) => xml,
+        None => return read_ods(data, limits),
+    };
+
+    let repair = repairs.is_some();
+    let mut found = Vec::new();
+    let content_types = match text("[Content_Types].xml")? {
+        Some(xml) => Some(ContentTypes::parse(&xml)),
+        None => {
+            found.push(Repair::new(
+                "missing_part",
+                Some("[Content_Types].xml"),
+                "The package has no content types; parts were read by name".to_string(),
+            ));
+            None
+        }
+    };
+    // Parts read, to check against the content types at the end
+    let mut parts = vec!["xl/workbook.xml".to_string()];
+
+    let workbook = parse_workbook_impl(&workbook_xml);
+    let rels = match text("xl/_rels/workbook.xml.rels")? {
+        Some(xml) => parse_relationships_impl(&xml),
+        None => {
+            if repair {
+                found.push(Repair::new(
+                    "missing_part",
+                    Some("xl/_rels/workbook.xml.rels"),
+                    "The workbook has no relationships; parts were looked for by name".to_string(),
+                ));
+            }
+            Vec::new()
+        }
+    };
+    let part_path = |target: &str| match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("xl/{}", target),
+    };
+    let rel_target = |suffix: &str| {
+        rels.iter()
+            .find(|rel| rel.rel_type.ends_with(suffix))
+            .map(|rel| part_path(&rel.target))
+    };
+
+    // Without a relationship, a repair looks for a part at its usual path
+    let mut optional_part = |suffix: &str, usual: &str| -> Result<Option<String>, ReadError> {
+        let path = match rel_target(suffix) {
+            Some(path) => path,
+            None if repair => usual.to_string(),
+            None => return Ok(None),
+        };
+        let xml = text(&path)?;
+        match &xml {
+            Some(_) => parts.push(path),
+            None if repair && rel_target(suffix).is_some() => found.push(Repair::new(
+                "missing_part",
+                Some(&path),
+                format!("{} is referenced but missing; it was left out", path),
+            )),
+            None => {}
+        }
+        Ok(xml)
+    };
+    let shared_strings = optional_part("/sharedStrings", "xl/sharedStrings.xml")?
+        .map(|xml| phase("sharedStrings", || parse_shared_strings_impl(&xml)))
+        .unwrap_or_default();
+    let styles = optional_part("/styles", "xl/styles.xml")?
+        .map(|xml| phase("styles", || parse_styles_impl(&xml)));
+
+    let mut scratch = ParserScratch::default();
+    let mut sheets = Vec::new();
+    let mut cells = 0;
+    for (index, info) in workbook.sheets.iter().enumerate() {
+        if memory::over_limit() {
+            break;
+        }
+        let path = match rels.iter().find(|rel| rel.id == info.rid) {
+            Some(rel) => part_path(&rel.target),
+            None if repair => {
+                let path = default_sheet_path(index);
+                found.push(Repair::new(
+                    "dangling_relationship",
+                    Some("xl/workbook.xml"),
+                    format!(
+                        "Sheet {} refers to missing relationship {}; tried {}",
+                        info.name, info.rid, path
+                    ),
+                ));
+                path
+            }
+            None => return Err(format!("Missing relationship for sheet {}", info.name).into()),
+        };
+        if repair && !archive.names().any(|name| name == path) {
+            found.push(Repair::new(
+                "missing_part",
+                Some(&path),
+                format!("Sheet {} has no part {}; it was left out", info.name, path),
+            ));
+            continue;
+        }
+        let missing = || format!("Missing worksheet part {}", path);
+        let phase_name = format!("sheet:{}", info.name);
+        let options = WorksheetParseOptions::default();
+
+        // Peeking inflates the first few bytes a second time
+        let mut head = Vec::with_capacity(ENCODING_PEEK);
+        let peek = archive.open(&path)?.ok_or_else(missing)?;
+        let _ = peek.take(ENCODING_PEEK as u64).read_to_end(&mut head);
+        let mut worksheet = if detect_encoding(&head)? == XmlEncoding::Utf8 {
+            // Inflate and parse together, so the sheet's XML is never held whole
+            let mut entry = archive
+                .open(&path)?
+                .ok_or_else(missing)?
+                .check_xml(b"c", limits.max_cells - cells);
+            let worksheet = phase(&phase_name, || {
+                let input = BufReader::with_capacity(STREAM_BUFFER_SIZE, &mut entry);
+                parse_worksheet_from(input, &mut scratch, &options)
+            });
+            cells += entry.finish()?;
+            worksheet
+        } else {
+            // Other encodings are transcoded whole, then checked as text
+            let xml = read(&path)?.ok_or_else(missing)?;
+            cells += limits.check_xml(&path, &xml, b"c")?;
+            if cells > limits.max_cells {
+                return Err(LimitExceeded::new("cell_count", limits.max_cells, Some(&path)).into());
+            }
+            phase(&phase_name, || {
+                parse_worksheet_with(&xml, &mut scratch, &options)
+            })
+        };
+        if worksheet.hyperlinks.iter().any(|link| link.rid.is_some()) {
+            link_targets(&mut worksheet, read(&sheet_rels_path(&path))?);
+        }
+        sheets.push(ParsedSheet {
+            name: info.name.clone(),
+            state: info.state.clone(),
+            worksheet,
+        });
+        parts.push(path);
+    }
+
+    if let Some(repairs) = repairs {
+        if let Some(content_types) = &content_types {
+            for part in parts.iter().filter(|part| !content_types.covers(part)) {
+                found.push(Repair::new(
+                    "missing_content_type",
+                    Some(part),
+                    format!("{} has no content type override", part),
+                ));
+            }
+        }
+        repairs.append(&mut found);
+    }
+
+    Ok(WorkbookData {
+        sheets,
+        shared_strings,
+        styles,
+        date1904: workbook.workbook_pr.date1904,
+        defined_names: workbook.defined_names,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::cell;
+    use crate::ParsedRow;
+
+    fn workbook() -> Workbook {
+        let mut total = cell("C1", None, "3");
+        total.formula = Some("A1+2".to_string());
+        let rows = vec![ParsedRow {
+            row_num: 1,
+            cells: vec![cell("A1", None, "1"), cell("B1", Some("s"), "0"), total],
+            height: None,
+            hidden: false,
+        }];
+        Workbook::from_data(WorkbookData {
+            sheets: vec![ParsedSheet {
+                name: "My Sheet".to_string(),
+                state: None,
+                worksheet: ParsedWorksheet {
+                    rows,
+                    ..Default::default()
+                },
+            }],
+            shared_strings: vec!["Name".to_string()],
+            styles: None,
+            date1904: false,
+            defined_names: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_random_access() {
+        let workbook = workbook();
+        let b1 = workbook.book.cell("'My Sheet'!B1").unwrap();
+        assert_eq!(b1.value_type, "string");
+        assert_eq!(b1.value, Some(TypedValue::Text("Name".to_string())));
+
+        let c1 = workbook.book.cell("C1").unwrap();
+        assert_eq!(c1.value, Some(TypedValue::Number(3.0)));
+        assert_eq!(c1.formula.as_deref(), Some("A1+2"));
+        assert_eq!(workbook.book.cell("D5").unwrap().value_type, "empty");
+        assert!(workbook.book.cell("Other!A1").is_err());
+
+        let range = workbook.book.range("A1:D100").unwrap();
+        assert_eq!(range.len(), 1);
+        assert_eq!(range[0].len(), 3);
+        assert_eq!(workbook.book.row(1, None).unwrap().len(), 3);
+        assert!(workbook.book.row(2, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_repaired() {
+        let sheet = r#"<worksheet><sheetData><row r="1"><c r="A1"><v>7</v></c></row></sheetData></worksheet>"#;
+        let mut zip = crate::zip::ZipWriter::new();
+        let parts = [
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/>
+                <Override PartName="/xl/workbook.xml" ContentType="x"/></Types>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<workbook><sheets><sheet name="One" sheetId="1" r:id="rId1"/>
+                <sheet name="Two" sheetId="2" r:id="rId9"/>
+                <sheet name="Three" sheetId="3" r:id="rId3"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships>
+                <Relationship Id="rId1" Type="x/worksheet" Target="worksheets/sheet1.xml"/>
+                <Relationship Id="rId3" Type="x/worksheet" Target="worksheets/sheet3.xml"/>
+                <Relationship Id="rId4" Type="x/sharedStrings" Target="sharedStrings.xml"/>
+                </Relationships>"#,
+            ),
+            ("xl/worksheets/sheet1.xml", sheet),
+            ("xl/worksheets/sheet2.xml", sheet),
+        ];
+        for (name, xml) in parts {
+            zip.add_file(name, xml.as_bytes(), 6);
+        }
+        let data = zip.finish();
+
+        assert!(read_workbook(&data, ParseLimits::default(), None).is_err());
+        let mut repairs = Vec::new();
+        let workbook = read_workbook(&data, ParseLimits::default(), Some(&mut repairs)).unwrap();
+        let names: Vec<_> = workbook.sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["One", "Two"]);
+        let kinds: Vec<_> = repairs
+            .iter()
+            .map(|r| (r.kind.as_str(), r.part.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("missing_part", "xl/sharedStrings.xml"),
+                ("dangling_relationship", "xl/workbook.xml"),
+                ("missing_part", "xl/worksheets/sheet3.xml"),
+                ("missing_content_type", "xl/worksheets/sheet1.xml"),
+                ("missing_content_type", "xl/worksheets/sheet2.xml"),
+            ]
+        );
+    }
+}
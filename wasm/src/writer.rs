@@ -0,0 +1,2229 @@
+//! XML part writers for XLSX export
+//!
+//! Serializes the parsed data model back into spec-compliant OOXML parts,
+//! mirroring the part generators used by the JavaScript exporter.
+
+use crate::formula::{rewrite_references, Notation};
+use crate::protection::write_sheet_protection;
+use crate::reference::{bounds_ref, cell_ref, parse_cell_ref, range_bounds};
+use crate::table::ParsedTable;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::xstring::push_xstring_escaped;
+use crate::{
+    AutoFilter, ConditionalFormat, ConditionalRule, ConditionalThreshold, DataValidation,
+    ParsedBorder, ParsedCell, ParsedDefinedName, ParsedDxf, ParsedFill, ParsedFont,
+    ParsedRelationship, ParsedRow, ParsedSheetInfo, ParsedStyle, ParsedStyles, ParsedWorkbookPr,
+    ParsedWorksheet,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// XML declaration for all OOXML parts
+pub(crate) const XML_DECLARATION: &str =
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+pub(crate) const NS_SPREADSHEETML: &str =
+    "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+pub(crate) const NS_RELATIONSHIPS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+pub(crate) const NS_PACKAGE_RELATIONSHIPS: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships";
+
+const NS_X14: &str = "http://schemas.microsoft.com/office/spreadsheetml/2009/9/main";
+const NS_XM: &str = "http://schemas.microsoft.com/office/excel/2006/main";
+/// Extension holding Excel 2010 data validations
+const EXT_DATA_VALIDATIONS: &str = "{CCE6A557-97BC-4b89-ADB6-D9C93CAAB3DF}";
+/// Extension holding Excel 2010 conditional formatting
+const EXT_CONDITIONAL_FORMATS: &str = "{78C0D931-6437-407d-A8EE-F0AAD7539E65}";
+/// Extension of a data bar rule naming its Excel 2010 counterpart
+const EXT_DATA_BAR_ID: &str = "{B025F937-C7B1-47D3-B67F-A62EFF666E3E}";
+/// Icon sets Excel 2007 doesn't have, so only the extension can hold them
+const X14_ICON_SETS: [&str; 4] = ["3Stars", "3Triangles", "5Boxes", "NoIcons"];
+
+/// Relationship type URIs for the parts the writers produce
+pub(crate) mod rel_types {
+    pub const OFFICE_DOCUMENT: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+    pub const WORKSHEET: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet";
+    pub const STYLES: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles";
+    pub const SHARED_STRINGS: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/sharedStrings";
+    pub const HYPERLINK: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+    pub const TABLE: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/table";
+    #[cfg(feature = "comments")]
+    pub const COMMENTS: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments";
+    #[cfg(feature = "comments")]
+    pub const VML_DRAWING: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/vmlDrawing";
+    #[cfg(feature = "comments")]
+    pub const THREADED_COMMENT: &str =
+        "http://schemas.microsoft.com/office/2017/10/relationships/threadedComment";
+    #[cfg(feature = "drawings")]
+    pub const DRAWING: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing";
+    #[cfg(feature = "charts")]
+    pub const CHART: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/chart";
+    #[cfg(feature = "drawings")]
+    pub const IMAGE: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+    #[cfg(feature = "comments")]
+    pub const PERSON: &str = "http://schemas.microsoft.com/office/2017/10/relationships/person";
+    pub const CORE_PROPERTIES: &str =
+        "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+    pub const EXTENDED_PROPERTIES: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties";
+    pub const CUSTOM_PROPERTIES: &str =
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
+}
+
+pub(crate) const NS_CONTENT_TYPES: &str =
+    "http://schemas.openxmlformats.org/package/2006/content-types";
+
+/// Content types for the parts an exported workbook can contain
+pub(crate) mod content_types {
+    pub const RELATIONSHIPS: &str = "application/vnd.openxmlformats-package.relationships+xml";
+    pub const XML: &str = "application/xml";
+    pub const WORKBOOK: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml";
+    pub const WORKSHEET: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml";
+    pub const CHARTSHEET: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.chartsheet+xml";
+    pub const STYLES: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml";
+    pub const SHARED_STRINGS: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml";
+    pub const THEME: &str = "application/vnd.openxmlformats-officedocument.theme+xml";
+    pub const CALC_CHAIN: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.calcChain+xml";
+    pub const COMMENTS: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.comments+xml";
+    pub const THREADED_COMMENTS: &str = "application/vnd.ms-excel.threadedcomments+xml";
+    pub const PERSONS: &str = "application/vnd.ms-excel.person+xml";
+    pub const VML_DRAWING: &str = "application/vnd.openxmlformats-officedocument.vmlDrawing";
+    pub const DRAWING: &str = "application/vnd.openxmlformats-officedocument.drawing+xml";
+    pub const CHART: &str = "application/vnd.openxmlformats-officedocument.drawingml.chart+xml";
+    pub const TABLE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.table+xml";
+    pub const PIVOT_TABLE: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotTable+xml";
+    pub const PIVOT_CACHE_DEFINITION: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheDefinition+xml";
+    pub const PIVOT_CACHE_RECORDS: &str =
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheRecords+xml";
+    pub const CORE_PROPERTIES: &str = "application/vnd.openxmlformats-package.core-properties+xml";
+    pub const EXTENDED_PROPERTIES: &str =
+        "application/vnd.openxmlformats-officedocument.extended-properties+xml";
+    pub const CUSTOM_PROPERTIES: &str =
+        "application/vnd.openxmlformats-officedocument.custom-properties+xml";
+    pub const VBA_PROJECT: &str = "application/vnd.ms-office.vbaProject";
+}
+
+/// Append `text` to `out`, escaping XML special characters and dropping
+/// characters that are not allowed in XML 1.0
+pub(crate) fn push_escaped(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(ch),
+            c if (c as u32) < 0x20 || c == '\u{7F}' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+}
+
+/// Append ` name="value"` with the value escaped
+pub(crate) fn push_attr(out: &mut String, name: &str, value: &str) {
+    out.push(' ');
+    out.push_str(name);
+    out.push_str("=\"");
+    push_escaped(out, value);
+    out.push('"');
+}
+
+/// Whether text needs `xml:space="preserve"` to survive a round trip
+pub(crate) fn needs_space_preserve(text: &str) -> bool {
+    text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace)
+}
+
+/// Serialize worksheet data (rows, cells, merges, column widths, hyperlinks)
+/// back to sheet XML
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_worksheet(worksheet: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let worksheet: ParsedWorksheet = serde_wasm_bindgen::from_value(worksheet)?;
+        Ok(write_worksheet_impl(&worksheet))
+    })
+}
+
+pub(crate) fn write_worksheet_impl(worksheet: &ParsedWorksheet) -> String {
+    let mut out = String::with_capacity(256 + worksheet.rows.len() * 64);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<worksheet xmlns=\"{}\" xmlns:r=\"{}\">",
+        NS_SPREADSHEETML, NS_RELATIONSHIPS
+    );
+
+    // Excel marks sheets whose filter has criteria
+    let filtered = worksheet.auto_filter.as_ref().is_some_and(|filter| {
+        filter
+            .columns
+            .iter()
+            .any(|column| !column.values.is_empty() || column.blank || !column.custom.is_empty())
+    });
+    if filtered {
+        out.push_str("<sheetPr filterMode=\"1\"/>");
+    }
+
+    let _ = write!(
+        out,
+        "<dimension ref=\"{}\"/>",
+        worksheet_dimension(worksheet)
+    );
+
+    write_cols(&mut out, &worksheet.col_widths);
+
+    if worksheet.rows.is_empty() {
+        out.push_str("<sheetData/>");
+    } else {
+        out.push_str("<sheetData>");
+        for row in &worksheet.rows {
+            write_row(&mut out, row);
+        }
+        out.push_str("</sheetData>");
+    }
+
+    if let Some(ref protection) = worksheet.protection {
+        write_sheet_protection(&mut out, protection);
+    }
+
+    if let Some(ref filter) = worksheet.auto_filter {
+        write_auto_filter(&mut out, filter);
+    }
+
+    if !worksheet.merge_cells.is_empty() {
+        let _ = write!(
+            out,
+            "<mergeCells count=\"{}\">",
+            worksheet.merge_cells.len()
+        );
+        for merge in &worksheet.merge_cells {
+            out.push_str("<mergeCell");
+            push_attr(&mut out, "ref", merge);
+            out.push_str("/>");
+        }
+        out.push_str("</mergeCells>");
+    }
+
+    let conditional_ext = write_conditional_formats(&mut out, &worksheet.conditional_formats);
+
+    // Rules pointing at other sheets only work from the Excel 2010 extension
+    let (external, local): (Vec<_>, Vec<_>) = worksheet
+        .data_validations
+        .iter()
+        .partition(|validation| refers_to_other_sheet(validation));
+    if !local.is_empty() {
+        let _ = write!(out, "<dataValidations count=\"{}\">", local.len());
+        for validation in local {
+            out.push_str("<dataValidation");
+            push_validation_attrs(&mut out, validation);
+            push_attr(&mut out, "sqref", &validation.sqref);
+            out.push('>');
+            for (tag, formula) in validation_formulas(validation) {
+                let _ = write!(out, "<{}>", tag);
+                push_escaped(&mut out, formula);
+                let _ = write!(out, "</{}>", tag);
+            }
+            out.push_str("</dataValidation>");
+        }
+        out.push_str("</dataValidations>");
+    }
+
+    if !worksheet.hyperlinks.is_empty() {
+        out.push_str("<hyperlinks>");
+        for link in &worksheet.hyperlinks {
+            out.push_str("<hyperlink");
+            push_attr(&mut out, "ref", &link.reference);
+            if let Some(ref rid) = link.rid {
+                push_attr(&mut out, "r:id", rid);
+            }
+            if let Some(ref location) = link.location {
+                push_attr(&mut out, "location", location);
+            }
+            if let Some(ref tooltip) = link.tooltip {
+                push_attr(&mut out, "tooltip", tooltip);
+            }
+            if let Some(ref display) = link.display {
+                push_attr(&mut out, "display", display);
+            }
+            out.push_str("/>");
+        }
+        out.push_str("</hyperlinks>");
+    }
+
+    #[cfg(feature = "drawings")]
+    if let Some(ref rid) = worksheet.drawing {
+        out.push_str("<drawing");
+        push_attr(&mut out, "r:id", rid);
+        out.push_str("/>");
+    }
+    #[cfg(feature = "comments")]
+    if let Some(ref rid) = worksheet.legacy_drawing {
+        out.push_str("<legacyDrawing");
+        push_attr(&mut out, "r:id", rid);
+        out.push_str("/>");
+    }
+
+    if !worksheet.table_parts.is_empty() {
+        let _ = write!(
+            out,
+            "<tableParts count=\"{}\">",
+            worksheet.table_parts.len()
+        );
+        for rid in &worksheet.table_parts {
+            out.push_str("<tablePart");
+            push_attr(&mut out, "r:id", rid);
+            out.push_str("/>");
+        }
+        out.push_str("</tableParts>");
+    }
+
+    if !conditional_ext.is_empty() || !external.is_empty() {
+        out.push_str("<extLst>");
+    }
+    if !conditional_ext.is_empty() {
+        let _ = write!(
+            out,
+            "<ext uri=\"{}\" xmlns:x14=\"{}\"><x14:conditionalFormattings>{}\
+             </x14:conditionalFormattings></ext>",
+            EXT_CONDITIONAL_FORMATS, NS_X14, conditional_ext
+        );
+    }
+    if !external.is_empty() {
+        let _ = write!(
+            out,
+            "<ext uri=\"{}\" xmlns:x14=\"{}\">",
+            EXT_DATA_VALIDATIONS, NS_X14
+        );
+        let _ = write!(
+            out,
+            "<x14:dataValidations count=\"{}\" xmlns:xm=\"{}\">",
+            external.len(),
+            NS_XM
+        );
+        for validation in &external {
+            out.push_str("<x14:dataValidation");
+            push_validation_attrs(&mut out, validation);
+            out.push('>');
+            for (tag, formula) in validation_formulas(validation) {
+                let _ = write!(out, "<x14:{}><xm:f>", tag);
+                push_escaped(&mut out, formula);
+                let _ = write!(out, "</xm:f></x14:{}>", tag);
+            }
+            out.push_str("<xm:sqref>");
+            push_escaped(&mut out, &validation.sqref);
+            out.push_str("</xm:sqref></x14:dataValidation>");
+        }
+        out.push_str("</x14:dataValidations></ext>");
+    }
+    if !conditional_ext.is_empty() || !external.is_empty() {
+        out.push_str("</extLst>");
+    }
+
+    out.push_str("</worksheet>");
+    out
+}
+
+fn write_auto_filter(out: &mut String, filter: &AutoFilter) {
+    out.push_str("<autoFilter");
+    push_attr(out, "ref", &filter.reference);
+    if filter.columns.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+    for column in &filter.columns {
+        let _ = write!(out, "<filterColumn colId=\"{}\">", column.col_id);
+        if !column.custom.is_empty() {
+            out.push_str("<customFilters");
+            if column.and {
+                out.push_str(" and=\"1\"");
+            }
+            out.push('>');
+            for custom in column.custom.iter().take(2) {
+                out.push_str("<customFilter");
+                if let Some(ref operator) = custom.operator {
+                    push_attr(out, "operator", operator);
+                }
+                push_attr(out, "val", &custom.value);
+                out.push_str("/>");
+            }
+            out.push_str("</customFilters>");
+        } else if !column.values.is_empty() || column.blank {
+            out.push_str("<filters");
+            if column.blank {
+                out.push_str(" blank=\"1\"");
+            }
+            out.push('>');
+            for value in &column.values {
+                out.push_str("<filter");
+                push_attr(out, "val", value);
+                out.push_str("/>");
+            }
+            out.push_str("</filters>");
+        }
+        out.push_str("</filterColumn>");
+    }
+    out.push_str("</autoFilter>");
+}
+
+/// Write each format's `<conditionalFormatting>`, returning the
+/// `<x14:conditionalFormatting>` elements of rules that need Excel 2010
+fn write_conditional_formats(out: &mut String, formats: &[ConditionalFormat]) -> String {
+    let mut ext = String::new();
+    let mut position = 0;
+    // Ids pair a data bar's two forms; they only need to be unique
+    let mut ids = 0;
+    let mut next_id = || {
+        ids += 1;
+        format!("{{00000000-0000-4000-8000-{:012X}}}", ids)
+    };
+    for format in formats {
+        let mut plain = String::new();
+        let mut extended = String::new();
+        for rule in &format.rules {
+            position += 1;
+            let priority = rule.priority.unwrap_or(position);
+            let x14_only = rule.rule_type == "iconSet"
+                && rule
+                    .icon_set
+                    .as_deref()
+                    .is_some_and(|set| X14_ICON_SETS.contains(&set));
+            if x14_only {
+                write_x14_icon_set(&mut extended, rule, priority, &next_id());
+            } else if rule.rule_type == "dataBar" {
+                let id = next_id();
+                write_cf_rule(&mut plain, rule, priority, Some(&id));
+                write_x14_data_bar(&mut extended, rule, &id);
+            } else {
+                write_cf_rule(&mut plain, rule, priority, None);
+            }
+        }
+        if !plain.is_empty() {
+            out.push_str("<conditionalFormatting");
+            push_attr(out, "sqref", &format.sqref);
+            let _ = write!(out, ">{}</conditionalFormatting>", plain);
+        }
+        if !extended.is_empty() {
+            let _ = write!(
+                ext,
+                "<x14:conditionalFormatting xmlns:xm=\"{}\">{}<xm:sqref>",
+                NS_XM, extended
+            );
+            push_escaped(&mut ext, &format.sqref);
+            ext.push_str("</xm:sqref></x14:conditionalFormatting>");
+        }
+    }
+    ext
+}
+
+/// A `<cfRule>`; `id` links a data bar to its Excel 2010 form
+fn write_cf_rule(out: &mut String, rule: &ConditionalRule, priority: u32, id: Option<&str>) {
+    out.push_str("<cfRule");
+    push_attr(out, "type", &rule.rule_type);
+    if let Some(dxf_id) = rule.dxf_id {
+        let _ = write!(out, " dxfId=\"{}\"", dxf_id);
+    }
+    let _ = write!(out, " priority=\"{}\"", priority);
+    if rule.stop_if_true {
+        out.push_str(" stopIfTrue=\"1\"");
+    }
+    match rule.rule_type.as_str() {
+        "aboveAverage" if rule.bottom => out.push_str(" aboveAverage=\"0\""),
+        "top10" => {
+            if rule.percent {
+                out.push_str(" percent=\"1\"");
+            }
+            if rule.bottom {
+                out.push_str(" bottom=\"1\"");
+            }
+            let _ = write!(out, " rank=\"{}\"", rule.rank.unwrap_or(10));
+        }
+        _ => {}
+    }
+    if let Some(ref operator) = rule.operator {
+        push_attr(out, "operator", operator);
+    }
+    if let Some(ref text) = rule.text {
+        push_attr(out, "text", text);
+    }
+    out.push('>');
+
+    for formula in &rule.formulas {
+        out.push_str("<formula>");
+        push_escaped(out, formula.strip_prefix('=').unwrap_or(formula));
+        out.push_str("</formula>");
+    }
+    let show_value = if rule.hide_value {
+        " showValue=\"0\""
+    } else {
+        ""
+    };
+    match rule.rule_type.as_str() {
+        "colorScale" => {
+            let colors = scale_colors(rule);
+            out.push_str("<colorScale>");
+            for threshold in thresholds(rule, colors.len()) {
+                push_cfvo(out, &threshold, false);
+            }
+            for color in colors {
+                out.push_str("<color");
+                push_attr(out, "rgb", color);
+                out.push_str("/>");
+            }
+            out.push_str("</colorScale>");
+        }
+        "dataBar" => {
+            let _ = write!(out, "<dataBar{}>", show_value);
+            for threshold in thresholds(rule, 2) {
+                push_cfvo(out, &threshold, false);
+            }
+            out.push_str("<color");
+            push_attr(out, "rgb", bar_color(rule));
+            out.push_str("/></dataBar>");
+        }
+        "iconSet" => {
+            let name = rule.icon_set.as_deref().unwrap_or("3TrafficLights1");
+            out.push_str("<iconSet");
+            push_attr(out, "iconSet", name);
+            if rule.reverse {
+                out.push_str(" reverse=\"1\"");
+            }
+            out.push_str(show_value);
+            out.push('>');
+            for threshold in thresholds(rule, icon_count(name)) {
+                push_cfvo(out, &threshold, false);
+            }
+            out.push_str("</iconSet>");
+        }
+        _ => {}
+    }
+    if let Some(id) = id {
+        let _ = write!(
+            out,
+            "<extLst><ext uri=\"{}\" xmlns:x14=\"{}\"><x14:id>{}</x14:id></ext></extLst>",
+            EXT_DATA_BAR_ID, NS_X14, id
+        );
+    }
+    out.push_str("</cfRule>");
+}
+
+/// The Excel 2010 form of a data bar, which adds solid fills and negative colors
+fn write_x14_data_bar(out: &mut String, rule: &ConditionalRule, id: &str) {
+    let _ = write!(
+        out,
+        "<x14:cfRule type=\"dataBar\" id=\"{}\"><x14:dataBar",
+        id
+    );
+    if rule.solid_fill {
+        out.push_str(" gradient=\"0\"");
+    }
+    out.push('>');
+    for threshold in thresholds(rule, 2) {
+        push_cfvo(out, &threshold, true);
+    }
+    if let Some(ref color) = rule.negative_color {
+        out.push_str("<x14:negativeFillColor");
+        push_attr(out, "rgb", color);
+        out.push_str("/>");
+    }
+    out.push_str("<x14:axisColor rgb=\"FF000000\"/></x14:dataBar></x14:cfRule>");
+}
+
+/// An icon set rule Excel 2007 can't show, written only in the extension
+fn write_x14_icon_set(out: &mut String, rule: &ConditionalRule, priority: u32, id: &str) {
+    let name = rule.icon_set.as_deref().unwrap_or_default();
+    let _ = write!(
+        out,
+        "<x14:cfRule type=\"iconSet\" priority=\"{}\" id=\"{}\"><x14:iconSet",
+        priority, id
+    );
+    push_attr(out, "iconSet", name);
+    if rule.reverse {
+        out.push_str(" reverse=\"1\"");
+    }
+    if rule.hide_value {
+        out.push_str(" showValue=\"0\"");
+    }
+    out.push('>');
+    for threshold in thresholds(rule, icon_count(name)) {
+        push_cfvo(out, &threshold, true);
+    }
+    out.push_str("</x14:iconSet></x14:cfRule>");
+}
+
+/// A rule's thresholds, or `count` of them spread from the lowest value to
+/// the highest
+fn thresholds(rule: &ConditionalRule, count: usize) -> Vec<ConditionalThreshold> {
+    if !rule.thresholds.is_empty() {
+        return rule.thresholds.clone();
+    }
+    let threshold = |value_type: &str, value: Option<usize>| ConditionalThreshold {
+        value_type: value_type.to_string(),
+        value: value.map(|value| value.to_string()),
+    };
+    (0..count)
+        .map(|i| match i {
+            // An icon set's thresholds are where each icon starts
+            _ if rule.rule_type == "iconSet" => {
+                threshold("percent", Some((i * 100 + count / 2) / count))
+            }
+            0 => threshold("min", None),
+            _ if i == count - 1 => threshold("max", None),
+            _ => threshold("percentile", Some(i * 100 / (count - 1))),
+        })
+        .collect()
+}
+
+fn push_cfvo(out: &mut String, threshold: &ConditionalThreshold, x14: bool) {
+    out.push_str(if x14 { "<x14:cfvo" } else { "<cfvo" });
+    push_attr(out, "type", &threshold.value_type);
+    match threshold.value {
+        Some(ref value) if x14 => {
+            out.push_str("><xm:f>");
+            push_escaped(out, value.strip_prefix('=').unwrap_or(value));
+            out.push_str("</xm:f></x14:cfvo>");
+        }
+        Some(ref value) => {
+            push_attr(out, "val", value.strip_prefix('=').unwrap_or(value));
+            out.push_str("/>");
+        }
+        None => out.push_str("/>"),
+    }
+}
+
+/// Number of icons in a set, from its name ("3Arrows", "5Rating")
+fn icon_count(name: &str) -> usize {
+    name.chars()
+        .next()
+        .and_then(|ch| ch.to_digit(10))
+        .map_or(3, |count| count as usize)
+}
+
+/// A color scale's colors, red to green when none are given
+fn scale_colors(rule: &ConditionalRule) -> Vec<&str> {
+    if rule.colors.is_empty() {
+        vec!["FFF8696B", "FF63BE7B"]
+    } else {
+        rule.colors.iter().map(String::as_str).collect()
+    }
+}
+
+/// A data bar's color, Excel's default blue when none is given
+fn bar_color(rule: &ConditionalRule) -> &str {
+    rule.colors.first().map_or("FF638EC6", String::as_str)
+}
+
+/// A rule's formulas by element name, without any leading `=`
+fn validation_formulas(validation: &DataValidation) -> impl Iterator<Item = (&str, &str)> {
+    [
+        ("formula1", &validation.formula1),
+        ("formula2", &validation.formula2),
+    ]
+    .into_iter()
+    .filter_map(|(tag, formula)| {
+        let formula = formula.as_deref()?;
+        Some((tag, formula.strip_prefix('=').unwrap_or(formula)))
+    })
+}
+
+/// Whether a rule's formulas reference cells on another sheet
+fn refers_to_other_sheet(validation: &DataValidation) -> bool {
+    validation_formulas(validation).any(|(_, formula)| {
+        let mut other = false;
+        let _ = rewrite_references(formula, Notation::A1, |reference| {
+            other |= reference.sheet.is_some();
+            String::new()
+        });
+        other
+    })
+}
+
+/// Attributes shared by the plain and extension forms of a rule
+fn push_validation_attrs(out: &mut String, validation: &DataValidation) {
+    let optional = [
+        ("type", &validation.validation_type),
+        ("errorStyle", &validation.error_style),
+        ("operator", &validation.operator),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            push_attr(out, name, value);
+        }
+    }
+    let flags = [
+        ("allowBlank", validation.allow_blank),
+        // The schema's showDropDown actually hides the dropdown
+        ("showDropDown", validation.hide_dropdown),
+        ("showInputMessage", validation.show_input_message),
+        ("showErrorMessage", validation.show_error_message),
+    ];
+    for (name, _) in flags.iter().filter(|(_, set)| *set) {
+        let _ = write!(out, " {}=\"1\"", name);
+    }
+    let messages = [
+        ("errorTitle", &validation.error_title),
+        ("error", &validation.error),
+        ("promptTitle", &validation.prompt_title),
+        ("prompt", &validation.prompt),
+    ];
+    for (name, value) in messages {
+        if let Some(value) = value {
+            push_attr(out, name, value);
+        }
+    }
+}
+
+/// Compute the used range of the worksheet as an A1 reference
+fn worksheet_dimension(worksheet: &ParsedWorksheet) -> String {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+
+    for row in &worksheet.rows {
+        for cell in &row.cells {
+            if let Some((r, c)) = parse_cell_ref(&cell.reference) {
+                bounds = Some(match bounds {
+                    None => (r, c, r, c),
+                    Some((r1, c1, r2, c2)) => (r1.min(r), c1.min(c), r2.max(r), c2.max(c)),
+                });
+            }
+        }
+    }
+
+    match bounds {
+        Some((r1, c1, r2, c2)) if (r1, c1) != (r2, c2) => {
+            format!("{}:{}", cell_ref(r1, c1), cell_ref(r2, c2))
+        }
+        Some((r, c, _, _)) => cell_ref(r, c),
+        None => "A1".to_string(),
+    }
+}
+
+/// Write `<cols>`, collapsing adjacent columns with equal widths into one range
+pub(crate) fn write_cols(out: &mut String, col_widths: &HashMap<u32, f64>) {
+    if col_widths.is_empty() {
+        return;
+    }
+
+    let mut cols: Vec<(u32, f64)> = col_widths
+        .iter()
+        .map(|(&col, &width)| (col, width))
+        .collect();
+    cols.sort_by_key(|&(col, _)| col);
+
+    out.push_str("<cols>");
+    let mut i = 0;
+    while i < cols.len() {
+        let (min, width) = cols[i];
+        let mut max = min;
+        while i + 1 < cols.len() && cols[i + 1].0 == max + 1 && cols[i + 1].1 == width {
+            max += 1;
+            i += 1;
+        }
+        let _ = write!(
+            out,
+            "<col min=\"{}\" max=\"{}\" width=\"{}\" customWidth=\"1\"/>",
+            min, max, width
+        );
+        i += 1;
+    }
+    out.push_str("</cols>");
+}
+
+pub(crate) fn write_row(out: &mut String, row: &ParsedRow) {
+    out.push_str("<row");
+    if row.row_num > 0 {
+        let _ = write!(out, " r=\"{}\"", row.row_num);
+    }
+    if let Some(height) = row.height {
+        let _ = write!(out, " ht=\"{}\" customHeight=\"1\"", height);
+    }
+    if row.hidden {
+        out.push_str(" hidden=\"1\"");
+    }
+
+    if row.cells.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+
+    out.push('>');
+    for cell in &row.cells {
+        write_cell(out, cell);
+    }
+    out.push_str("</row>");
+}
+
+fn write_cell(out: &mut String, cell: &ParsedCell) {
+    out.push_str("<c");
+    if !cell.reference.is_empty() {
+        push_attr(out, "r", &cell.reference);
+    }
+    if let Some(style) = cell.style_index {
+        if style > 0 {
+            let _ = write!(out, " s=\"{}\"", style);
+        }
+    }
+    let cell_type = cell.cell_type.as_deref().filter(|t| *t != "n");
+    if let Some(t) = cell_type {
+        push_attr(out, "t", t);
+    }
+
+    if cell.formula.is_none() && cell.value.is_none() {
+        out.push_str("/>");
+        return;
+    }
+
+    out.push('>');
+    if let Some(ref formula) = cell.formula {
+        out.push_str("<f>");
+        push_escaped(out, formula);
+        out.push_str("</f>");
+    }
+    if let Some(ref value) = cell.value {
+        if cell_type == Some("inlineStr") {
+            out.push_str("<is><t");
+            if needs_space_preserve(value) {
+                out.push_str(" xml:space=\"preserve\"");
+            }
+            out.push('>');
+            push_xstring_escaped(out, value);
+            out.push_str("</t></is>");
+        } else {
+            out.push_str("<v>");
+            push_escaped(out, value);
+            out.push_str("</v>");
+        }
+    }
+    out.push_str("</c>");
+}
+
+/// A formatted run inside a rich-text shared string
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RichTextRun {
+    pub text: String,
+    pub font: Option<ParsedFont>,
+}
+
+/// Shared string table entry: either plain text or a list of rich-text runs
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SharedStringEntry {
+    Plain(String),
+    Rich(Vec<RichTextRun>),
+}
+
+/// Result of writing sharedStrings.xml
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WrittenSharedStrings {
+    pub xml: String,
+    /// Index in the written table for each input entry, in input order
+    pub indices: Vec<u32>,
+    pub unique_count: u32,
+}
+
+/// Build sharedStrings.xml, deduplicating identical entries
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_shared_strings(strings: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let strings: Vec<SharedStringEntry> = serde_wasm_bindgen::from_value(strings)?;
+        let result = write_shared_strings_impl(&strings);
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    })
+}
+
+pub(crate) fn write_shared_strings_impl(strings: &[SharedStringEntry]) -> WrittenSharedStrings {
+    // Identical entries serialize to identical <si> markup, so the markup
+    // itself serves as the dedup key (this also covers rich-text runs)
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    let mut items: Vec<String> = Vec::new();
+    let mut indices = Vec::with_capacity(strings.len());
+
+    for entry in strings {
+        let mut si = String::new();
+        write_string_item(&mut si, entry);
+
+        let index = match seen.get(&si) {
+            Some(&index) => index,
+            None => {
+                let index = items.len() as u32;
+                seen.insert(si.clone(), index);
+                items.push(si);
+                index
+            }
+        };
+        indices.push(index);
+    }
+
+    let mut xml = String::with_capacity(128 + items.iter().map(String::len).sum::<usize>());
+    xml.push_str(XML_DECLARATION);
+    let _ = write!(
+        xml,
+        "<sst xmlns=\"{}\" count=\"{}\" uniqueCount=\"{}\">",
+        NS_SPREADSHEETML,
+        strings.len(),
+        items.len()
+    );
+    for item in &items {
+        xml.push_str(item);
+    }
+    xml.push_str("</sst>");
+
+    WrittenSharedStrings {
+        xml,
+        indices,
+        unique_count: items.len() as u32,
+    }
+}
+
+fn write_string_item(out: &mut String, entry: &SharedStringEntry) {
+    out.push_str("<si>");
+    match entry {
+        SharedStringEntry::Plain(text) => write_text_element(out, text),
+        SharedStringEntry::Rich(runs) => {
+            for run in runs {
+                out.push_str("<r>");
+                if let Some(ref font) = run.font {
+                    out.push_str("<rPr>");
+                    push_font_properties(out, font, "rFont");
+                    out.push_str("</rPr>");
+                }
+                write_text_element(out, &run.text);
+                out.push_str("</r>");
+            }
+        }
+    }
+    out.push_str("</si>");
+}
+
+fn write_text_element(out: &mut String, text: &str) {
+    out.push_str("<t");
+    if needs_space_preserve(text) {
+        out.push_str(" xml:space=\"preserve\"");
+    }
+    out.push('>');
+    push_xstring_escaped(out, text);
+    out.push_str("</t>");
+}
+
+/// Write font child elements; `name_tag` is `name` in styles.xml and
+/// `rFont` inside rich-text run properties
+pub(crate) fn push_font_properties(out: &mut String, font: &ParsedFont, name_tag: &str) {
+    if font.bold {
+        out.push_str("<b/>");
+    }
+    if font.italic {
+        out.push_str("<i/>");
+    }
+    if font.strikethrough {
+        out.push_str("<strike/>");
+    }
+    if font.underline {
+        out.push_str("<u/>");
+    }
+    if let Some(size) = font.size {
+        let _ = write!(out, "<sz val=\"{}\"/>", size);
+    }
+    if let Some(ref color) = font.color {
+        out.push_str("<color");
+        push_attr(out, "rgb", color);
+        out.push_str("/>");
+    }
+    if let Some(ref name) = font.name {
+        out.push('<');
+        out.push_str(name_tag);
+        push_attr(out, "val", name);
+        out.push_str("/>");
+    }
+}
+
+/// Deduplicating registry keyed by the serialized markup of each component
+struct ComponentTable {
+    seen: HashMap<String, u32>,
+    items: Vec<String>,
+}
+
+impl ComponentTable {
+    fn new() -> Self {
+        ComponentTable {
+            seen: HashMap::new(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Register serialized markup, returning the index of the first identical entry
+    fn register(&mut self, markup: String) -> u32 {
+        if let Some(&index) = self.seen.get(&markup) {
+            return index;
+        }
+        let index = self.items.len() as u32;
+        self.seen.insert(markup.clone(), index);
+        self.items.push(markup);
+        index
+    }
+
+    fn write_collection(&self, out: &mut String, tag: &str) {
+        let _ = write!(out, "<{} count=\"{}\">", tag, self.items.len());
+        for item in &self.items {
+            out.push_str(item);
+        }
+        let _ = write!(out, "</{}>", tag);
+    }
+}
+
+/// Result of writing styles.xml
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WrittenStyles {
+    pub xml: String,
+    /// Written cellXfs index for each input cellXfs entry, in input order
+    pub xf_indices: Vec<u32>,
+}
+
+/// Serialize styles (fonts, fills, borders, numFmts, cellXfs) to styles.xml,
+/// deduplicating identical components
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_styles(styles: JsValue) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let styles: ParsedStyles = serde_wasm_bindgen::from_value(styles)?;
+        let result = write_styles_impl(&styles);
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    })
+}
+
+pub(crate) fn write_styles_impl(styles: &ParsedStyles) -> WrittenStyles {
+    // Custom number formats: identical codes collapse onto the lowest id
+    let mut num_fmt_ids: Vec<&u32> = styles.num_fmts.keys().collect();
+    num_fmt_ids.sort();
+    let mut code_ids: HashMap<&str, u32> = HashMap::new();
+    let mut num_fmt_map: HashMap<u32, u32> = HashMap::new();
+    let mut num_fmts: Vec<(u32, &str)> = Vec::new();
+    for &id in num_fmt_ids {
+        let code = styles.num_fmts[&id].as_str();
+        let target = *code_ids.entry(code).or_insert_with(|| {
+            num_fmts.push((id, code));
+            id
+        });
+        num_fmt_map.insert(id, target);
+    }
+
+    let mut fonts = ComponentTable::new();
+    let font_map: Vec<u32> = styles
+        .fonts
+        .iter()
+        .map(|font| fonts.register(font_markup(font)))
+        .collect();
+    if fonts.items.is_empty() {
+        fonts.register(font_markup(&ParsedFont {
+            size: Some(11.0),
+            name: Some("Calibri".to_string()),
+            ..ParsedFont::default()
+        }));
+    }
+
+    // Excel requires the first two fills to be "none" and "gray125"
+    let mut fills = ComponentTable::new();
+    for pattern in ["none", "gray125"] {
+        fills.register(fill_markup(&ParsedFill {
+            pattern_type: Some(pattern.to_string()),
+            ..ParsedFill::default()
+        }));
+    }
+    let fill_map: Vec<u32> = styles
+        .fills
+        .iter()
+        .map(|fill| fills.register(fill_markup(fill)))
+        .collect();
+
+    let mut borders = ComponentTable::new();
+    borders.register(border_markup(&ParsedBorder::default()));
+    let border_map: Vec<u32> = styles
+        .borders
+        .iter()
+        .map(|border| borders.register(border_markup(border)))
+        .collect();
+
+    let remap = |map: &[u32], id: Option<u32>| -> u32 {
+        id.and_then(|id| map.get(id as usize).copied()).unwrap_or(0)
+    };
+
+    let mut xfs = ComponentTable::new();
+    let xf_indices: Vec<u32> = styles
+        .cell_xfs
+        .iter()
+        .map(|style| {
+            let num_fmt_id = style
+                .num_fmt_id
+                .map(|id| num_fmt_map.get(&id).copied().unwrap_or(id))
+                .unwrap_or(0);
+            let ids = XfComponentIds {
+                num_fmt_id,
+                font_id: remap(&font_map, style.font_id),
+                fill_id: remap(&fill_map, style.fill_id),
+                border_id: remap(&border_map, style.border_id),
+            };
+            xfs.register(xf_markup(style, &ids))
+        })
+        .collect();
+    if xfs.items.is_empty() {
+        xfs.register(xf_markup(
+            &ParsedStyle::default(),
+            &XfComponentIds {
+                num_fmt_id: 0,
+                font_id: 0,
+                fill_id: 0,
+                border_id: 0,
+            },
+        ));
+    }
+
+    let mut xml = String::with_capacity(1024);
+    xml.push_str(XML_DECLARATION);
+    let _ = write!(xml, "<styleSheet xmlns=\"{}\">", NS_SPREADSHEETML);
+
+    if !num_fmts.is_empty() {
+        let _ = write!(xml, "<numFmts count=\"{}\">", num_fmts.len());
+        for (id, code) in &num_fmts {
+            let _ = write!(xml, "<numFmt numFmtId=\"{}\"", id);
+            push_attr(&mut xml, "formatCode", code);
+            xml.push_str("/>");
+        }
+        xml.push_str("</numFmts>");
+    }
+
+    fonts.write_collection(&mut xml, "fonts");
+    fills.write_collection(&mut xml, "fills");
+    borders.write_collection(&mut xml, "borders");
+    xml.push_str(
+        "<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>",
+    );
+    xfs.write_collection(&mut xml, "cellXfs");
+    xml.push_str(
+        "<cellStyles count=\"1\"><cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/></cellStyles>",
+    );
+
+    if !styles.dxfs.is_empty() {
+        // A dxf's number format reuses the id of a matching custom format,
+        // and otherwise takes the next free one; ids below 164 are built in
+        let last = num_fmts.iter().map(|&(id, _)| id).max().unwrap_or(0);
+        let mut next_num_fmt_id = (last + 1).max(164);
+        let _ = write!(xml, "<dxfs count=\"{}\">", styles.dxfs.len());
+        for dxf in &styles.dxfs {
+            let num_fmt_id = dxf.num_fmt.as_deref().map(|code| {
+                *code_ids.entry(code).or_insert_with(|| {
+                    next_num_fmt_id += 1;
+                    next_num_fmt_id - 1
+                })
+            });
+            xml.push_str(&dxf_markup(dxf, num_fmt_id));
+        }
+        xml.push_str("</dxfs>");
+    }
+    xml.push_str("</styleSheet>");
+
+    WrittenStyles { xml, xf_indices }
+}
+
+struct XfComponentIds {
+    num_fmt_id: u32,
+    font_id: u32,
+    fill_id: u32,
+    border_id: u32,
+}
+
+fn font_markup(font: &ParsedFont) -> String {
+    let mut out = String::from("<font>");
+    push_font_properties(&mut out, font, "name");
+    out.push_str("</font>");
+    out
+}
+
+fn fill_markup(fill: &ParsedFill) -> String {
+    let mut out = String::from("<fill><patternFill");
+    if let Some(ref pattern) = fill.pattern_type {
+        push_attr(&mut out, "patternType", pattern);
+    }
+    if fill.fg_color.is_none() && fill.bg_color.is_none() {
+        out.push_str("/></fill>");
+        return out;
+    }
+    out.push('>');
+    if let Some(ref color) = fill.fg_color {
+        out.push_str("<fgColor");
+        push_attr(&mut out, "rgb", color);
+        out.push_str("/>");
+    }
+    if let Some(ref color) = fill.bg_color {
+        out.push_str("<bgColor");
+        push_attr(&mut out, "rgb", color);
+        out.push_str("/>");
+    }
+    out.push_str("</patternFill></fill>");
+    out
+}
+
+/// A `<dxf>`, whose number format goes by `num_fmt_id`
+fn dxf_markup(dxf: &ParsedDxf, num_fmt_id: Option<u32>) -> String {
+    let mut out = String::from("<dxf>");
+    if let Some(ref font) = dxf.font {
+        out.push_str(&font_markup(font));
+    }
+    if let (Some(code), Some(id)) = (&dxf.num_fmt, num_fmt_id) {
+        let _ = write!(out, "<numFmt numFmtId=\"{}\"", id);
+        push_attr(&mut out, "formatCode", code);
+        out.push_str("/>");
+    }
+    if let Some(ref fill) = dxf.fill {
+        // Excel paints a dxf's solid fill in its background color
+        let solid = fill
+            .pattern_type
+            .as_deref()
+            .is_none_or(|pattern| pattern == "solid");
+        if solid && fill.bg_color.is_none() {
+            out.push_str(&fill_markup(&ParsedFill {
+                fg_color: None,
+                bg_color: fill.fg_color.clone(),
+                ..fill.clone()
+            }));
+        } else {
+            out.push_str(&fill_markup(fill));
+        }
+    }
+    if let Some(ref border) = dxf.border {
+        out.push_str(&border_markup(border));
+    }
+    out.push_str("</dxf>");
+    out
+}
+
+fn border_markup(border: &ParsedBorder) -> String {
+    let mut out = String::from("<border>");
+    for (side, style, color) in [
+        ("left", &border.left_style, &border.left_color),
+        ("right", &border.right_style, &border.right_color),
+        ("top", &border.top_style, &border.top_color),
+        ("bottom", &border.bottom_style, &border.bottom_color),
+    ] {
+        out.push('<');
+        out.push_str(side);
+        if let Some(style) = style {
+            push_attr(&mut out, "style", style);
+        }
+        match color {
+            Some(color) => {
+                out.push_str("><color");
+                push_attr(&mut out, "rgb", color);
+                let _ = write!(out, "/></{}>", side);
+            }
+            None => out.push_str("/>"),
+        }
+    }
+    out.push_str("<diagonal/></border>");
+    out
+}
+
+fn xf_markup(style: &ParsedStyle, ids: &XfComponentIds) -> String {
+    let mut out = String::new();
+    // Only the Normal cell style is written, so every xf points at it
+    let _ = write!(
+        out,
+        "<xf numFmtId=\"{}\" fontId=\"{}\" fillId=\"{}\" borderId=\"{}\" xfId=\"0\"",
+        ids.num_fmt_id, ids.font_id, ids.fill_id, ids.border_id
+    );
+    for (flag, name) in [
+        (style.apply_number_format, "applyNumberFormat"),
+        (style.apply_font, "applyFont"),
+        (style.apply_fill, "applyFill"),
+        (style.apply_border, "applyBorder"),
+        (style.apply_alignment, "applyAlignment"),
+    ] {
+        if flag {
+            let _ = write!(out, " {}=\"1\"", name);
+        }
+    }
+
+    let has_alignment = style.horizontal.is_some()
+        || style.vertical.is_some()
+        || style.wrap_text
+        || style.text_rotation.is_some()
+        || style.indent.is_some();
+    if !has_alignment {
+        out.push_str("/>");
+        return out;
+    }
+
+    out.push_str("><alignment");
+    if let Some(ref horizontal) = style.horizontal {
+        push_attr(&mut out, "horizontal", horizontal);
+    }
+    if let Some(ref vertical) = style.vertical {
+        push_attr(&mut out, "vertical", vertical);
+    }
+    if let Some(rotation) = style.text_rotation {
+        let _ = write!(out, " textRotation=\"{}\"", rotation);
+    }
+    if style.wrap_text {
+        out.push_str(" wrapText=\"1\"");
+    }
+    if let Some(indent) = style.indent {
+        let _ = write!(out, " indent=\"{}\"", indent);
+    }
+    out.push_str("/></xf>");
+    out
+}
+
+/// Serialize the sheet registry, defined names and workbook properties to
+/// workbook.xml
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_workbook(
+    sheets: JsValue,
+    defined_names: JsValue,
+    workbook_pr: JsValue,
+) -> Result<String, JsValue> {
+    trap(|| {
+        let sheets: Vec<ParsedSheetInfo> = serde_wasm_bindgen::from_value(sheets)?;
+        let defined_names: Option<Vec<ParsedDefinedName>> =
+            serde_wasm_bindgen::from_value(defined_names)?;
+        let workbook_pr: Option<ParsedWorkbookPr> = serde_wasm_bindgen::from_value(workbook_pr)?;
+        Ok(write_workbook_impl(
+            &sheets,
+            &defined_names.unwrap_or_default(),
+            &workbook_pr.unwrap_or_default(),
+        ))
+    })
+}
+
+pub(crate) fn write_workbook_impl(
+    sheets: &[ParsedSheetInfo],
+    defined_names: &[ParsedDefinedName],
+    workbook_pr: &ParsedWorkbookPr,
+) -> String {
+    let mut out = String::with_capacity(512 + sheets.len() * 64);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<workbook xmlns=\"{}\" xmlns:r=\"{}\">",
+        NS_SPREADSHEETML, NS_RELATIONSHIPS
+    );
+
+    out.push_str("<workbookPr");
+    if workbook_pr.date1904 {
+        out.push_str(" date1904=\"1\"");
+    }
+    if let Some(ref code_name) = workbook_pr.code_name {
+        push_attr(&mut out, "codeName", code_name);
+    }
+    if let Some(version) = workbook_pr.default_theme_version {
+        let _ = write!(out, " defaultThemeVersion=\"{}\"", version);
+    }
+    out.push_str("/>");
+
+    out.push_str("<bookViews><workbookView");
+    if let Some(first_sheet) = workbook_pr.first_sheet {
+        let _ = write!(out, " firstSheet=\"{}\"", first_sheet);
+    }
+    if let Some(active_tab) = workbook_pr.active_tab {
+        let _ = write!(out, " activeTab=\"{}\"", active_tab);
+    }
+    out.push_str("/></bookViews>");
+
+    out.push_str("<sheets>");
+    for (i, sheet) in sheets.iter().enumerate() {
+        out.push_str("<sheet");
+        push_attr(&mut out, "name", &sheet.name);
+        let sheet_id = if sheet.sheet_id > 0 {
+            sheet.sheet_id
+        } else {
+            i as u32 + 1
+        };
+        let _ = write!(out, " sheetId=\"{}\"", sheet_id);
+        if let Some(state) = sheet.state.as_deref().filter(|s| *s != "visible") {
+            push_attr(&mut out, "state", state);
+        }
+        if sheet.rid.is_empty() {
+            let _ = write!(out, " r:id=\"rId{}\"", i + 1);
+        } else {
+            push_attr(&mut out, "r:id", &sheet.rid);
+        }
+        out.push_str("/>");
+    }
+    out.push_str("</sheets>");
+
+    if !defined_names.is_empty() {
+        out.push_str("<definedNames>");
+        for defined_name in defined_names {
+            out.push_str("<definedName");
+            push_attr(&mut out, "name", &defined_name.name);
+            if let Some(local_sheet_id) = defined_name.local_sheet_id {
+                let _ = write!(out, " localSheetId=\"{}\"", local_sheet_id);
+            }
+            if defined_name.hidden {
+                out.push_str(" hidden=\"1\"");
+            }
+            out.push('>');
+            push_escaped(&mut out, &defined_name.value);
+            out.push_str("</definedName>");
+        }
+        out.push_str("</definedNames>");
+    }
+
+    out.push_str("</workbook>");
+    out
+}
+
+/// Serialize a table part (`xl/tables/tableN.xml`); `id` must be unique
+/// among the workbook's tables
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_table(table: JsValue, id: u32) -> Result<String, JsValue> {
+    trap(|| {
+        let table: ParsedTable = serde_wasm_bindgen::from_value(table)?;
+        write_table_impl(&table, id).map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+pub(crate) fn write_table_impl(table: &ParsedTable, id: u32) -> Result<String, String> {
+    let (r1, c1, r2, c2) = range_bounds(&table.reference)
+        .ok_or_else(|| format!("Invalid table range: {}", table.reference))?;
+    let width = (c2 - c1 + 1) as usize;
+    if table.columns.len() != width {
+        return Err(format!(
+            "Table {} has {} columns but its range is {} wide",
+            table.name,
+            table.columns.len(),
+            width
+        ));
+    }
+
+    let mut out = String::with_capacity(512 + width * 64);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(out, "<table xmlns=\"{}\" id=\"{}\"", NS_SPREADSHEETML, id);
+    push_attr(&mut out, "name", &table.name);
+    push_attr(&mut out, "displayName", &table.display_name);
+    push_attr(&mut out, "ref", &bounds_ref((r1, c1, r2, c2)));
+    if table.header_row_count != 1 {
+        let _ = write!(out, " headerRowCount=\"{}\"", table.header_row_count);
+    }
+    if table.totals_row_count > 0 {
+        let _ = write!(out, " totalsRowCount=\"{}\"", table.totals_row_count);
+    } else {
+        out.push_str(" totalsRowShown=\"0\"");
+    }
+    out.push('>');
+
+    // The filter buttons sit on the header row and leave out the totals
+    if table.header_row_count > 0 {
+        let last = r2.saturating_sub(table.totals_row_count).max(r1);
+        out.push_str("<autoFilter");
+        push_attr(&mut out, "ref", &bounds_ref((r1, c1, last, c2)));
+        out.push_str("/>");
+    }
+
+    let _ = write!(out, "<tableColumns count=\"{}\">", width);
+    for (index, name) in table.columns.iter().enumerate() {
+        let _ = write!(out, "<tableColumn id=\"{}\"", index + 1);
+        push_attr(&mut out, "name", name);
+        let total = table
+            .totals
+            .get(index)
+            .filter(|_| table.totals_row_count > 0);
+        if let Some(label) = total.and_then(|total| total.label.as_deref()) {
+            push_attr(&mut out, "totalsRowLabel", label);
+        }
+        let function = total.and_then(|total| Some((total.function.as_deref()?, total)));
+        match function {
+            Some(("custom", total)) => {
+                out.push_str(" totalsRowFunction=\"custom\"><totalsRowFormula>");
+                let formula = total.formula.as_deref().unwrap_or_default();
+                push_escaped(&mut out, formula.strip_prefix('=').unwrap_or(formula));
+                out.push_str("</totalsRowFormula></tableColumn>");
+            }
+            Some((function, _)) => {
+                push_attr(&mut out, "totalsRowFunction", function);
+                out.push_str("/>");
+            }
+            None => out.push_str("/>"),
+        }
+    }
+    out.push_str("</tableColumns>");
+
+    let style = table.style.clone().unwrap_or_default();
+    out.push_str("<tableStyleInfo");
+    if let Some(ref name) = style.name {
+        push_attr(&mut out, "name", name);
+    }
+    for (name, flag) in [
+        ("showFirstColumn", style.show_first_column),
+        ("showLastColumn", style.show_last_column),
+        ("showRowStripes", style.show_row_stripes),
+        ("showColumnStripes", style.show_column_stripes),
+    ] {
+        let _ = write!(out, " {}=\"{}\"", name, u8::from(flag));
+    }
+    out.push_str("/></table>");
+    Ok(out)
+}
+
+/// Serialize a relationships part (`_rels/.rels`, `xl/_rels/workbook.xml.rels`,
+/// `xl/worksheets/_rels/sheetN.xml.rels`)
+///
+/// Relationships without an id are assigned the next free `rIdN`, so ids
+/// already referenced from other parts are never renumbered.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_relationships(rels: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let mut rels: Vec<ParsedRelationship> = serde_wasm_bindgen::from_value(rels)?;
+        assign_relationship_ids(&mut rels);
+        Ok(write_relationships_impl(&rels))
+    })
+}
+
+/// Fill in missing relationship ids without colliding with existing ones
+pub(crate) fn assign_relationship_ids(rels: &mut [ParsedRelationship]) {
+    let mut next = rels
+        .iter()
+        .filter_map(|rel| rel.id.strip_prefix("rId")?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+
+    for rel in rels.iter_mut().filter(|rel| rel.id.is_empty()) {
+        next += 1;
+        rel.id = format!("rId{}", next);
+    }
+}
+
+pub(crate) fn write_relationships_impl(rels: &[ParsedRelationship]) -> String {
+    let mut out = String::with_capacity(128 + rels.len() * 160);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(
+        out,
+        "<Relationships xmlns=\"{}\">",
+        NS_PACKAGE_RELATIONSHIPS
+    );
+    for rel in rels {
+        out.push_str("<Relationship");
+        push_attr(&mut out, "Id", &rel.id);
+        push_attr(&mut out, "Type", &rel.rel_type);
+        push_attr(&mut out, "Target", &rel.target);
+        if let Some(ref mode) = rel.target_mode {
+            push_attr(&mut out, "TargetMode", mode);
+        }
+        out.push_str("/>");
+    }
+    out.push_str("</Relationships>");
+    out
+}
+
+/// A package part listed in [Content_Types].xml
+///
+/// Accepts either a bare part name, whose content type is inferred from its
+/// location, or an explicit name/content type pair.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PackagePart {
+    Name(String),
+    Typed {
+        part_name: String,
+        content_type: String,
+    },
+}
+
+/// Serialize [Content_Types].xml for the given package parts
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn write_content_types(parts: JsValue) -> Result<String, JsValue> {
+    trap(|| {
+        let parts: Vec<PackagePart> = serde_wasm_bindgen::from_value(parts)?;
+        Ok(write_content_types_impl(&parts))
+    })
+}
+
+pub(crate) fn write_content_types_impl(parts: &[PackagePart]) -> String {
+    let mut defaults: Vec<(String, &str)> = vec![
+        ("rels".to_string(), content_types::RELATIONSHIPS),
+        ("xml".to_string(), content_types::XML),
+    ];
+    let mut overrides: Vec<(String, String)> = Vec::new();
+
+    for part in parts {
+        let (name, explicit) = match part {
+            PackagePart::Name(name) => (name.as_str(), None),
+            PackagePart::Typed {
+                part_name,
+                content_type,
+            } => (part_name.as_str(), Some(content_type.as_str())),
+        };
+        let name = if name.starts_with('/') {
+            name.to_string()
+        } else {
+            format!("/{}", name)
+        };
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        // Media and other binary parts are covered by extension defaults
+        if explicit.is_none() {
+            if let Some(media_type) = default_content_type(&extension) {
+                if !defaults.iter().any(|(ext, _)| *ext == extension) {
+                    defaults.push((extension, media_type));
+                }
+                continue;
+            }
+        }
+
+        let content_type = match explicit.or_else(|| override_content_type(&name)) {
+            Some(content_type) => content_type,
+            None => continue,
+        };
+        if !overrides.iter().any(|(part_name, _)| *part_name == name) {
+            overrides.push((name, content_type.to_string()));
+        }
+    }
+
+    let mut out = String::with_capacity(512 + overrides.len() * 160);
+    out.push_str(XML_DECLARATION);
+    let _ = write!(out, "<Types xmlns=\"{}\">", NS_CONTENT_TYPES);
+    for (extension, content_type) in &defaults {
+        out.push_str("<Default");
+        push_attr(&mut out, "Extension", extension);
+        push_attr(&mut out, "ContentType", content_type);
+        out.push_str("/>");
+    }
+    for (part_name, content_type) in &overrides {
+        out.push_str("<Override");
+        push_attr(&mut out, "PartName", part_name);
+        push_attr(&mut out, "ContentType", content_type);
+        out.push_str("/>");
+    }
+    out.push_str("</Types>");
+    out
+}
+
+/// Content type registered as an extension default (media and binary parts)
+fn default_content_type(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "svg" => "image/svg+xml",
+        "emf" => "image/x-emf",
+        "wmf" => "image/x-wmf",
+        "vml" => content_types::VML_DRAWING,
+        _ => return None,
+    })
+}
+
+/// Content type override inferred from a part's location in the package
+fn override_content_type(part_name: &str) -> Option<&'static str> {
+    let (dir, file) = part_name.rsplit_once('/').unwrap_or(("", part_name));
+    let stem = file.split('.').next().unwrap_or("");
+    let stem = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    Some(match (dir, stem) {
+        ("/xl", "workbook") => content_types::WORKBOOK,
+        ("/xl", "styles") => content_types::STYLES,
+        ("/xl", "sharedStrings") => content_types::SHARED_STRINGS,
+        ("/xl", "calcChain") => content_types::CALC_CHAIN,
+        ("/xl", "comments") => content_types::COMMENTS,
+        ("/xl", "vbaProject") => content_types::VBA_PROJECT,
+        ("/xl/worksheets", _) => content_types::WORKSHEET,
+        ("/xl/chartsheets", _) => content_types::CHARTSHEET,
+        ("/xl/theme", _) => content_types::THEME,
+        ("/xl/threadedComments", _) => content_types::THREADED_COMMENTS,
+        ("/xl/persons", _) => content_types::PERSONS,
+        ("/xl/drawings", _) => content_types::DRAWING,
+        ("/xl/charts", "chart") => content_types::CHART,
+        ("/xl/tables", _) => content_types::TABLE,
+        ("/xl/pivotTables", _) => content_types::PIVOT_TABLE,
+        ("/xl/pivotCache", "pivotCacheDefinition") => content_types::PIVOT_CACHE_DEFINITION,
+        ("/xl/pivotCache", "pivotCacheRecords") => content_types::PIVOT_CACHE_RECORDS,
+        ("/docProps", "core") => content_types::CORE_PROPERTIES,
+        ("/docProps", "app") => content_types::EXTENDED_PROPERTIES,
+        ("/docProps", "custom") => content_types::CUSTOM_PROPERTIES,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_worksheet_impl, ParsedHyperlink};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_write_worksheet_round_trip() {
+        let mut col_widths = HashMap::new();
+        col_widths.insert(1, 12.5);
+        col_widths.insert(2, 12.5);
+        col_widths.insert(4, 20.0);
+
+        let worksheet = ParsedWorksheet {
+            rows: vec![
+                ParsedRow {
+                    row_num: 1,
+                    cells: vec![
+                        ParsedCell {
+                            reference: "A1".to_string(),
+                            cell_type: Some("s".to_string()),
+                            style_index: Some(2),
+                            value: Some("0".to_string()),
+                            formula: None,
+                        },
+                        ParsedCell {
+                            reference: "B1".to_string(),
+                            cell_type: Some("inlineStr".to_string()),
+                            style_index: None,
+                            value: Some("R&D".to_string()),
+                            formula: None,
+                        },
+                    ],
+                    height: Some(24.0),
+                    hidden: false,
+                },
+                ParsedRow {
+                    row_num: 3,
+                    cells: vec![ParsedCell {
+                        reference: "C3".to_string(),
+                        cell_type: None,
+                        style_index: None,
+                        value: Some("42".to_string()),
+                        formula: Some("SUM(A1:B2)>0".to_string()),
+                    }],
+                    height: None,
+                    hidden: true,
+                },
+            ],
+            merge_cells: vec!["A1:B1".to_string()],
+            hyperlinks: vec![ParsedHyperlink {
+                reference: "C3".to_string(),
+                rid: Some("rId1".to_string()),
+                location: None,
+                display: None,
+                tooltip: Some("Open site".to_string()),
+                target: None,
+            }],
+            col_widths,
+            ..Default::default()
+        };
+
+        let xml = write_worksheet_impl(&worksheet);
+        assert!(xml.starts_with(XML_DECLARATION));
+        assert!(xml.contains("<dimension ref=\"A1:C3\"/>"));
+        assert!(xml.contains("<col min=\"1\" max=\"2\" width=\"12.5\" customWidth=\"1\"/>"));
+        assert!(xml.contains("<col min=\"4\" max=\"4\" width=\"20\" customWidth=\"1\"/>"));
+        assert!(xml.contains("<is><t>R&amp;D</t></is>"));
+        assert!(xml.contains("<f>SUM(A1:B2)&gt;0</f>"));
+
+        let parsed = parse_worksheet_impl(&xml);
+        assert_eq!(parsed.rows.len(), 2);
+        assert_eq!(parsed.rows[0].height, Some(24.0));
+        assert_eq!(parsed.rows[0].cells[0].style_index, Some(2));
+        assert_eq!(parsed.rows[0].cells[1].value.as_deref(), Some("R&D"));
+        assert!(parsed.rows[1].hidden);
+        assert_eq!(
+            parsed.rows[1].cells[0].formula.as_deref(),
+            Some("SUM(A1:B2)>0")
+        );
+        assert_eq!(parsed.rows[1].cells[0].value.as_deref(), Some("42"));
+        assert_eq!(parsed.merge_cells, vec!["A1:B1".to_string()]);
+        assert_eq!(parsed.hyperlinks[0].rid.as_deref(), Some("rId1"));
+        assert_eq!(parsed.hyperlinks[0].tooltip.as_deref(), Some("Open site"));
+        assert_eq!(parsed.col_widths.get(&2), Some(&12.5));
+        assert_eq!(parsed.col_widths.get(&3), None);
+    }
+
+    #[test]
+    fn test_write_data_validations() {
+        let list = |sqref: &str, source: &str| DataValidation {
+            sqref: sqref.to_string(),
+            validation_type: Some("list".to_string()),
+            formula1: Some(source.to_string()),
+            allow_blank: true,
+            show_error_message: true,
+            ..Default::default()
+        };
+        let worksheet = ParsedWorksheet {
+            data_validations: vec![
+                list("A1:A3", "\"Yes,No\""),
+                list("B1 B3", "=Lists!$A$1:$A$5"),
+                DataValidation {
+                    sqref: "C1".to_string(),
+                    validation_type: Some("whole".to_string()),
+                    formula1: Some("1".to_string()),
+                    formula2: Some("10".to_string()),
+                    prompt: Some("1 to 10 & no more".to_string()),
+                    show_input_message: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let xml = write_worksheet_impl(&worksheet);
+        assert!(xml.contains(
+            "<dataValidations count=\"2\"><dataValidation type=\"list\" allowBlank=\"1\" \
+             showErrorMessage=\"1\" sqref=\"A1:A3\"><formula1>&quot;Yes,No&quot;</formula1>"
+        ));
+        assert!(xml.contains(
+            "<dataValidation type=\"whole\" showInputMessage=\"1\" prompt=\"1 to 10 &amp; no more\" \
+             sqref=\"C1\"><formula1>1</formula1><formula2>10</formula2></dataValidation>"
+        ));
+        // The cross-sheet list goes in the extension, after the hyperlinks
+        assert!(xml.contains(
+            "<x14:dataValidations count=\"1\" xmlns:xm=\"http://schemas.microsoft.com/office/excel/2006/main\">\
+             <x14:dataValidation type=\"list\" allowBlank=\"1\" showErrorMessage=\"1\">\
+             <x14:formula1><xm:f>Lists!$A$1:$A$5</xm:f></x14:formula1><xm:sqref>B1 B3</xm:sqref>"
+        ));
+        assert!(xml.ends_with("</ext></extLst></worksheet>"));
+        assert!(parse_worksheet_impl(&xml).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_write_conditional_formats() {
+        let rule = |rule_type: &str| ConditionalRule {
+            rule_type: rule_type.to_string(),
+            ..Default::default()
+        };
+        let worksheet = ParsedWorksheet {
+            conditional_formats: vec![
+                ConditionalFormat {
+                    sqref: "A1:A10".to_string(),
+                    rules: vec![
+                        ConditionalRule {
+                            operator: Some("greaterThan".to_string()),
+                            formulas: vec!["=$B$1".to_string()],
+                            dxf_id: Some(0),
+                            stop_if_true: true,
+                            ..rule("cellIs")
+                        },
+                        ConditionalRule {
+                            solid_fill: true,
+                            negative_color: Some("FFFF0000".to_string()),
+                            ..rule("dataBar")
+                        },
+                    ],
+                },
+                ConditionalFormat {
+                    sqref: "B1:B10".to_string(),
+                    rules: vec![
+                        ConditionalRule {
+                            colors: vec!["FFFFFFFF".into(), "FFFFEB84".into(), "FF63BE7B".into()],
+                            ..rule("colorScale")
+                        },
+                        ConditionalRule {
+                            icon_set: Some("3Stars".to_string()),
+                            ..rule("iconSet")
+                        },
+                    ],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let xml = write_worksheet_impl(&worksheet);
+        assert!(xml.contains(
+            "<conditionalFormatting sqref=\"A1:A10\"><cfRule type=\"cellIs\" dxfId=\"0\" \
+             priority=\"1\" stopIfTrue=\"1\" operator=\"greaterThan\"><formula>$B$1</formula>\
+             </cfRule><cfRule type=\"dataBar\" priority=\"2\"><dataBar><cfvo type=\"min\"/>\
+             <cfvo type=\"max\"/><color rgb=\"FF638EC6\"/></dataBar><extLst>"
+        ));
+        assert!(xml.contains(
+            "<colorScale><cfvo type=\"min\"/><cfvo type=\"percentile\" val=\"50\"/>\
+             <cfvo type=\"max\"/><color rgb=\"FFFFFFFF\"/>"
+        ));
+        // The data bar's solid fill and the stars only exist in the extension
+        let ext = &xml[xml.find("<extLst><ext uri=\"{78C0D931").unwrap()..];
+        assert!(ext.contains(
+            "<x14:cfRule type=\"dataBar\" id=\"{00000000-0000-4000-8000-000000000001}\">\
+             <x14:dataBar gradient=\"0\"><x14:cfvo type=\"min\"/><x14:cfvo type=\"max\"/>\
+             <x14:negativeFillColor rgb=\"FFFF0000\"/>"
+        ));
+        assert!(ext.contains(
+            "<x14:iconSet iconSet=\"3Stars\"><x14:cfvo type=\"percent\"><xm:f>0</xm:f></x14:cfvo>\
+             <x14:cfvo type=\"percent\"><xm:f>33</xm:f></x14:cfvo>\
+             <x14:cfvo type=\"percent\"><xm:f>67</xm:f></x14:cfvo></x14:iconSet></x14:cfRule>\
+             <xm:sqref>B1:B10</xm:sqref>"
+        ));
+        assert_eq!(xml.matches("<cfRule ").count(), 3);
+        assert!(parse_worksheet_impl(&xml).warnings.is_empty());
+
+        let styles = ParsedStyles {
+            num_fmts: HashMap::from([(164, "0.0%".to_string())]),
+            dxfs: vec![ParsedDxf {
+                font: Some(ParsedFont {
+                    bold: true,
+                    ..Default::default()
+                }),
+                fill: Some(ParsedFill {
+                    pattern_type: Some("solid".to_string()),
+                    fg_color: Some("FFFFC7CE".to_string()),
+                    bg_color: None,
+                }),
+                num_fmt: Some("0.000".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let written = write_styles_impl(&styles);
+        assert!(written.xml.contains(
+            "<dxfs count=\"1\"><dxf><font><b/></font><numFmt numFmtId=\"165\" formatCode=\"0.000\"/>\
+             <fill><patternFill patternType=\"solid\"><bgColor rgb=\"FFFFC7CE\"/></patternFill></fill>\
+             </dxf></dxfs>"
+        ));
+    }
+
+    #[test]
+    fn test_write_empty_worksheet() {
+        let worksheet = ParsedWorksheet::default();
+
+        let xml = write_worksheet_impl(&worksheet);
+        assert!(xml.contains("<dimension ref=\"A1\"/><sheetData/></worksheet>"));
+    }
+
+    #[test]
+    fn test_write_inline_string_preserves_space() {
+        let mut out = String::new();
+        write_cell(
+            &mut out,
+            &ParsedCell {
+                reference: "A1".to_string(),
+                cell_type: Some("inlineStr".to_string()),
+                style_index: None,
+                value: Some("  indented".to_string()),
+                formula: None,
+            },
+        );
+        assert_eq!(
+            out,
+            "<c r=\"A1\" t=\"inlineStr\"><is><t xml:space=\"preserve\">  indented</t></is></c>"
+        );
+    }
+
+    #[test]
+    fn test_write_shared_strings_dedup() {
+        let strings = vec![
+            SharedStringEntry::Plain("Hello".to_string()),
+            SharedStringEntry::Plain(" padded ".to_string()),
+            SharedStringEntry::Plain("Hello".to_string()),
+            SharedStringEntry::Rich(vec![
+                RichTextRun {
+                    text: "Bold".to_string(),
+                    font: Some(ParsedFont {
+                        bold: true,
+                        size: Some(11.0),
+                        name: Some("Calibri".to_string()),
+                        ..ParsedFont::default()
+                    }),
+                },
+                RichTextRun {
+                    text: " & plain".to_string(),
+                    font: None,
+                },
+            ]),
+            SharedStringEntry::Plain("World".to_string()),
+        ];
+
+        let result = write_shared_strings_impl(&strings);
+        assert_eq!(result.indices, vec![0, 1, 0, 2, 3]);
+        assert_eq!(result.unique_count, 4);
+        assert!(result.xml.contains("count=\"5\" uniqueCount=\"4\""));
+        assert!(result
+            .xml
+            .contains("<t xml:space=\"preserve\"> padded </t>"));
+        assert!(result
+            .xml
+            .contains("<r><rPr><b/><sz val=\"11\"/><rFont val=\"Calibri\"/></rPr><t>Bold</t></r>"));
+        assert!(result
+            .xml
+            .contains("<r><t xml:space=\"preserve\"> &amp; plain</t></r>"));
+
+        let parsed = crate::parse_shared_strings_impl(&result.xml);
+        assert_eq!(parsed, vec!["Hello", " padded ", "Bold & plain", "World"]);
+    }
+
+    #[test]
+    fn test_write_styles_dedup() {
+        let bold = ParsedFont {
+            bold: true,
+            size: Some(11.0),
+            name: Some("Calibri".to_string()),
+            ..ParsedFont::default()
+        };
+        let mut num_fmts = HashMap::new();
+        num_fmts.insert(164, "0.00%".to_string());
+        num_fmts.insert(165, "0.00%".to_string());
+
+        let styles = ParsedStyles {
+            cell_xfs: vec![
+                ParsedStyle::default(),
+                ParsedStyle {
+                    font_id: Some(1),
+                    fill_id: Some(2),
+                    num_fmt_id: Some(164),
+                    apply_font: true,
+                    ..ParsedStyle::default()
+                },
+                ParsedStyle {
+                    font_id: Some(2),
+                    fill_id: Some(3),
+                    num_fmt_id: Some(165),
+                    apply_font: true,
+                    ..ParsedStyle::default()
+                },
+                ParsedStyle {
+                    horizontal: Some("center".to_string()),
+                    wrap_text: true,
+                    apply_alignment: true,
+                    ..ParsedStyle::default()
+                },
+            ],
+            fonts: vec![
+                ParsedFont {
+                    size: Some(11.0),
+                    name: Some("Calibri".to_string()),
+                    ..ParsedFont::default()
+                },
+                bold,
+                ParsedFont {
+                    bold: true,
+                    size: Some(11.0),
+                    name: Some("Calibri".to_string()),
+                    ..ParsedFont::default()
+                },
+            ],
+            fills: vec![
+                ParsedFill {
+                    pattern_type: Some("none".to_string()),
+                    ..ParsedFill::default()
+                },
+                ParsedFill {
+                    pattern_type: Some("gray125".to_string()),
+                    ..ParsedFill::default()
+                },
+                ParsedFill {
+                    pattern_type: Some("solid".to_string()),
+                    fg_color: Some("FFFFFF00".to_string()),
+                    bg_color: None,
+                },
+                ParsedFill {
+                    pattern_type: Some("solid".to_string()),
+                    fg_color: Some("FFFFFF00".to_string()),
+                    bg_color: None,
+                },
+            ],
+            borders: vec![ParsedBorder {
+                bottom_style: Some("thin".to_string()),
+                bottom_color: Some("FF000000".to_string()),
+                ..ParsedBorder::default()
+            }],
+            num_fmts,
+            dxfs: Vec::new(),
+        };
+
+        let result = write_styles_impl(&styles);
+        assert_eq!(result.xf_indices, vec![0, 1, 1, 2]);
+        assert!(result.xml.contains(
+            "<numFmts count=\"1\"><numFmt numFmtId=\"164\" formatCode=\"0.00%\"/></numFmts>"
+        ));
+        assert!(result.xml.contains("<fonts count=\"2\">"));
+        assert!(result.xml.contains("<fills count=\"3\">"));
+        assert!(result.xml.contains("<borders count=\"2\">"));
+        assert!(result.xml.contains("<cellXfs count=\"3\">"));
+        assert!(result
+            .xml
+            .contains("<bottom style=\"thin\"><color rgb=\"FF000000\"/></bottom>"));
+        assert!(result
+            .xml
+            .contains("<alignment horizontal=\"center\" wrapText=\"1\"/></xf>"));
+
+        let parsed = crate::parse_styles_impl(&result.xml);
+        assert_eq!(parsed.cell_xfs.len(), 3);
+        assert_eq!(parsed.cell_xfs[1].font_id, Some(1));
+        assert_eq!(parsed.cell_xfs[1].fill_id, Some(2));
+        assert_eq!(parsed.cell_xfs[1].num_fmt_id, Some(164));
+        assert!(parsed.fonts[1].bold);
+        assert_eq!(parsed.fills[2].fg_color.as_deref(), Some("FFFFFF00"));
+        assert_eq!(parsed.borders[1].bottom_style.as_deref(), Some("thin"));
+        assert_eq!(parsed.borders[1].bottom_color.as_deref(), Some("FF000000"));
+    }
+
+    #[test]
+    fn test_write_styles_defaults() {
+        let result = write_styles_impl(&ParsedStyles::default());
+        let parsed = crate::parse_styles_impl(&result.xml);
+        assert_eq!(parsed.fonts.len(), 1);
+        assert_eq!(parsed.fills.len(), 2);
+        assert_eq!(parsed.fills[1].pattern_type.as_deref(), Some("gray125"));
+        assert_eq!(parsed.borders.len(), 1);
+        assert_eq!(parsed.cell_xfs.len(), 1);
+    }
+
+    #[test]
+    fn test_write_workbook_round_trip() {
+        let sheets = vec![
+            ParsedSheetInfo {
+                name: "Q1 & Q2".to_string(),
+                sheet_id: 1,
+                rid: "rId1".to_string(),
+                state: None,
+            },
+            ParsedSheetInfo {
+                name: "Lookup".to_string(),
+                sheet_id: 4,
+                rid: "rId2".to_string(),
+                state: Some("hidden".to_string()),
+            },
+        ];
+        let defined_names = vec![ParsedDefinedName {
+            name: "_xlnm.Print_Area".to_string(),
+            value: "'Q1 & Q2'!$A$1:$D$20".to_string(),
+            local_sheet_id: Some(0),
+            hidden: false,
+        }];
+        let workbook_pr = ParsedWorkbookPr {
+            date1904: true,
+            active_tab: Some(0),
+            ..ParsedWorkbookPr::default()
+        };
+
+        let xml = write_workbook_impl(&sheets, &defined_names, &workbook_pr);
+        assert!(xml.contains("<workbookPr date1904=\"1\"/>"));
+        assert!(xml.contains("<bookViews><workbookView activeTab=\"0\"/></bookViews>"));
+        assert!(
+            xml.contains("<sheet name=\"Lookup\" sheetId=\"4\" state=\"hidden\" r:id=\"rId2\"/>")
+        );
+
+        let parsed = crate::parse_workbook_impl(&xml);
+        assert_eq!(parsed.sheets.len(), 2);
+        assert_eq!(parsed.sheets[0].rid, "rId1");
+        assert_eq!(parsed.sheets[1].sheet_id, 4);
+        assert_eq!(parsed.sheets[1].state.as_deref(), Some("hidden"));
+        assert_eq!(parsed.defined_names.len(), 1);
+        assert_eq!(parsed.defined_names[0].name, "_xlnm.Print_Area");
+        assert_eq!(parsed.defined_names[0].value, "'Q1 & Q2'!$A$1:$D$20");
+        assert_eq!(parsed.defined_names[0].local_sheet_id, Some(0));
+        assert!(parsed.workbook_pr.date1904);
+        assert_eq!(parsed.workbook_pr.active_tab, Some(0));
+    }
+
+    #[test]
+    fn test_write_relationships() {
+        let mut rels = vec![
+            ParsedRelationship {
+                id: "rId3".to_string(),
+                rel_type:
+                    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet"
+                        .to_string(),
+                target: "worksheets/sheet1.xml".to_string(),
+                target_mode: None,
+            },
+            ParsedRelationship {
+                id: String::new(),
+                rel_type:
+                    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink"
+                        .to_string(),
+                target: "https://example.com/?a=1&b=2".to_string(),
+                target_mode: Some("External".to_string()),
+            },
+            ParsedRelationship {
+                id: String::new(),
+                rel_type:
+                    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles"
+                        .to_string(),
+                target: "styles.xml".to_string(),
+                target_mode: None,
+            },
+        ];
+
+        assign_relationship_ids(&mut rels);
+        assert_eq!(rels[1].id, "rId4");
+        assert_eq!(rels[2].id, "rId5");
+
+        let xml = write_relationships_impl(&rels);
+        assert!(xml.contains("Target=\"https://example.com/?a=1&amp;b=2\" TargetMode=\"External\""));
+
+        let parsed = crate::parse_relationships_impl(&xml);
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].id, "rId3");
+        assert_eq!(parsed[0].target, "worksheets/sheet1.xml");
+        assert_eq!(parsed[1].target_mode.as_deref(), Some("External"));
+        assert!(parsed[2].rel_type.ends_with("/styles"));
+    }
+
+    #[test]
+    fn test_write_content_types() {
+        let parts = vec![
+            PackagePart::Name("xl/workbook.xml".to_string()),
+            PackagePart::Name("/xl/worksheets/sheet1.xml".to_string()),
+            PackagePart::Name("/xl/worksheets/sheet2.xml".to_string()),
+            PackagePart::Name("/xl/styles.xml".to_string()),
+            PackagePart::Name("/xl/sharedStrings.xml".to_string()),
+            PackagePart::Name("/xl/comments1.xml".to_string()),
+            PackagePart::Name("/xl/drawings/vmlDrawing1.vml".to_string()),
+            PackagePart::Name("/xl/media/image1.png".to_string()),
+            PackagePart::Name("/xl/media/image2.PNG".to_string()),
+            PackagePart::Name("/xl/media/photo.jpeg".to_string()),
+            PackagePart::Name("/docProps/core.xml".to_string()),
+            PackagePart::Name("/xl/worksheets/_rels/sheet1.xml.rels".to_string()),
+            PackagePart::Typed {
+                part_name: "/customXml/item1.xml".to_string(),
+                content_type: "application/vnd.example+xml".to_string(),
+            },
+        ];
+
+        let xml = write_content_types_impl(&parts);
+        assert!(xml.contains("<Default Extension=\"rels\""));
+        assert!(xml.contains("<Default Extension=\"png\" ContentType=\"image/png\"/>"));
+        assert_eq!(xml.matches("Extension=\"png\"").count(), 1);
+        assert!(xml.contains("<Default Extension=\"jpeg\" ContentType=\"image/jpeg\"/>"));
+        assert!(xml.contains("<Default Extension=\"vml\""));
+        assert!(xml.contains(&format!(
+            "<Override PartName=\"/xl/workbook.xml\" ContentType=\"{}\"/>",
+            content_types::WORKBOOK
+        )));
+        assert!(xml.contains(&format!(
+            "<Override PartName=\"/xl/worksheets/sheet2.xml\" ContentType=\"{}\"/>",
+            content_types::WORKSHEET
+        )));
+        assert!(xml.contains(&format!(
+            "<Override PartName=\"/xl/comments1.xml\" ContentType=\"{}\"/>",
+            content_types::COMMENTS
+        )));
+        assert!(xml.contains(
+            "<Override PartName=\"/customXml/item1.xml\" ContentType=\"application/vnd.example+xml\"/>"
+        ));
+        assert!(!xml.contains("sheet1.xml.rels"));
+    }
+}
@@ -0,0 +1,1552 @@
+//! Legacy Excel 97-2003 (.xls, BIFF8) reader
+//!
+//! The `Workbook` stream is read out of the compound file container and its
+//! BIFF records are mapped onto the same models the XLSX parsers produce:
+//! `ParsedWorksheet` per sheet, the shared string table and `ParsedStyles`.
+//! Formulas are decompiled from their RPN token form back to A1 text where
+//! the tokens are understood; otherwise only the cached value is kept.
+
+use crate::cfb::CompoundFile;
+use crate::reference::{cell_ref, col_to_letters};
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use crate::values::format_number;
+use crate::{
+    ParsedBorder, ParsedCell, ParsedFill, ParsedFont, ParsedRow, ParsedSheet, ParsedStyle,
+    ParsedStyles, ParsedWorksheet,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+// Record identifiers (MS-XLS 2.3)
+const RT_FORMULA: u16 = 0x0006;
+const RT_EOF: u16 = 0x000A;
+const RT_EXTERNSHEET: u16 = 0x0017;
+const RT_LBL: u16 = 0x0018;
+const RT_DATEMODE: u16 = 0x0022;
+const RT_FILEPASS: u16 = 0x002F;
+const RT_FONT: u16 = 0x0031;
+const RT_CONTINUE: u16 = 0x003C;
+const RT_COLINFO: u16 = 0x007D;
+const RT_BOUNDSHEET: u16 = 0x0085;
+const RT_PALETTE: u16 = 0x0092;
+const RT_MULRK: u16 = 0x00BD;
+const RT_XF: u16 = 0x00E0;
+const RT_MERGECELLS: u16 = 0x00E5;
+const RT_SST: u16 = 0x00FC;
+const RT_LABELSST: u16 = 0x00FD;
+const RT_NUMBER: u16 = 0x0203;
+const RT_LABEL: u16 = 0x0204;
+const RT_BOOLERR: u16 = 0x0205;
+const RT_STRING: u16 = 0x0207;
+const RT_ROW: u16 = 0x0208;
+const RT_RK: u16 = 0x027E;
+const RT_FORMAT: u16 = 0x041E;
+const RT_SHRFMLA: u16 = 0x04BC;
+const RT_BOF: u16 = 0x0809;
+
+const BIFF8_VERSION: u16 = 0x0600;
+
+/// Result of [`parse_xls`]
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ParsedXls {
+    pub sheets: Vec<ParsedSheet>,
+    pub shared_strings: Vec<String>,
+    pub styles: ParsedStyles,
+    pub date1904: bool,
+}
+
+/// Parse a legacy .xls (BIFF8) workbook
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn parse_xls(data: &[u8]) -> Result<JsValue, JsValue> {
+    trap(|| {
+        let result = parse_xls_impl(data).map_err(|e| JsValue::from_str(&e))?;
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    })
+}
+
+/// A BIFF record with any CONTINUE records that follow it
+struct Record<'a> {
+    id: u16,
+    segments: Vec<&'a [u8]>,
+}
+
+impl Record<'_> {
+    fn data(&self) -> &[u8] {
+        self.segments.first().copied().unwrap_or_default()
+    }
+}
+
+/// Split a substream into records, starting at `offset` and ending after its EOF
+fn read_records(stream: &[u8], mut offset: usize) -> Vec<Record<'_>> {
+    let mut records: Vec<Record> = Vec::new();
+    while offset + 4 <= stream.len() {
+        let id = u16::from_le_bytes([stream[offset], stream[offset + 1]]);
+        let len = u16::from_le_bytes([stream[offset + 2], stream[offset + 3]]) as usize;
+        let data = match stream.get(offset + 4..offset + 4 + len) {
+            Some(data) => data,
+            None => break,
+        };
+
+        match records.last_mut() {
+            Some(last) if id == RT_CONTINUE => last.segments.push(data),
+            _ => records.push(Record {
+                id,
+                segments: vec![data],
+            }),
+        }
+        offset += 4 + len;
+        if id == RT_EOF {
+            break;
+        }
+    }
+    records
+}
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    data.get(pos..pos + 2)
+        .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    data.get(pos..pos + 4)
+        .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn f64_at(data: &[u8], pos: usize) -> f64 {
+    data.get(pos..pos + 8)
+        .and_then(|b| b.try_into().ok())
+        .map_or(0.0, f64::from_le_bytes)
+}
+
+/// Decode `count` characters, either compressed (Latin-1) or UTF-16LE
+fn decode_chars(bytes: &[u8], high_byte: bool) -> String {
+    if high_byte {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    }
+}
+
+/// Read an XLUnicodeString (`len_size` 2) or ShortXLUnicodeString (`len_size` 1)
+///
+/// Returns the string and the position just past it.
+fn read_unicode_string(data: &[u8], pos: usize, len_size: usize) -> Option<(String, usize)> {
+    let cch = match len_size {
+        1 => *data.get(pos)? as usize,
+        _ => u16_at(data, pos) as usize,
+    };
+    let flags = *data.get(pos + len_size)?;
+    let mut cursor = pos + len_size + 1;
+
+    let runs = if flags & 0x08 != 0 {
+        cursor += 2;
+        u16_at(data, cursor - 2) as usize
+    } else {
+        0
+    };
+    let ext = if flags & 0x04 != 0 {
+        cursor += 4;
+        u32_at(data, cursor - 4) as usize
+    } else {
+        0
+    };
+
+    let byte_len = if flags & 0x01 != 0 { cch * 2 } else { cch };
+    let text = decode_chars(data.get(cursor..cursor + byte_len)?, flags & 0x01 != 0);
+    Some((text, cursor + byte_len + runs * 4 + ext))
+}
+
+/// Reads across CONTINUE boundaries, as the shared string table requires
+struct SegmentReader<'a> {
+    segments: &'a [&'a [u8]],
+    segment: usize,
+    pos: usize,
+}
+
+impl<'a> SegmentReader<'a> {
+    fn advance_segment(&mut self) -> bool {
+        while self.segment < self.segments.len() && self.pos >= self.segments[self.segment].len() {
+            self.segment += 1;
+            self.pos = 0;
+        }
+        self.segment < self.segments.len()
+    }
+
+    fn read_bytes(&mut self, mut count: usize) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity(count);
+        while count > 0 {
+            if !self.advance_segment() {
+                return None;
+            }
+            let segment = self.segments[self.segment];
+            let take = count.min(segment.len() - self.pos);
+            out.extend_from_slice(&segment[self.pos..self.pos + take]);
+            self.pos += take;
+            count -= take;
+        }
+        Some(out)
+    }
+
+    fn skip(&mut self, count: usize) -> Option<()> {
+        self.read_bytes(count).map(|_| ())
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes(1).map(|b| b[0])
+    }
+
+    fn read_u16(&mut self) -> Option<u16> {
+        self.read_bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        self.read_bytes(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Character data restarts with a fresh option byte in each CONTINUE record
+    fn read_chars(&mut self, mut count: usize, mut high_byte: bool) -> Option<String> {
+        let mut text = String::with_capacity(count);
+        while count > 0 {
+            if self.pos >= self.segments.get(self.segment)?.len() {
+                self.segment += 1;
+                self.pos = 0;
+                high_byte = self.read_u8()? & 0x01 != 0;
+            }
+            let segment = self.segments[self.segment];
+            let width = if high_byte { 2 } else { 1 };
+            let take = count.min((segment.len() - self.pos) / width);
+            if take == 0 {
+                return None;
+            }
+            let end = self.pos + take * width;
+            text.push_str(&decode_chars(&segment[self.pos..end], high_byte));
+            self.pos = end;
+            count -= take;
+        }
+        Some(text)
+    }
+
+    /// Read an XLUnicodeRichExtendedString
+    fn read_rich_string(&mut self) -> Option<String> {
+        let cch = self.read_u16()? as usize;
+        let flags = self.read_u8()?;
+        let runs = if flags & 0x08 != 0 {
+            self.read_u16()? as usize
+        } else {
+            0
+        };
+        let ext = if flags & 0x04 != 0 {
+            self.read_u32()? as usize
+        } else {
+            0
+        };
+        let text = self.read_chars(cch, flags & 0x01 != 0)?;
+        self.skip(runs * 4 + ext)?;
+        Some(text)
+    }
+}
+
+fn parse_sst(record: &Record) -> Vec<String> {
+    let mut reader = SegmentReader {
+        segments: &record.segments,
+        segment: 0,
+        pos: 0,
+    };
+    let _total = reader.read_u32();
+    let unique = reader.read_u32().unwrap_or(0) as usize;
+
+    let mut strings = Vec::with_capacity(unique.min(1 << 20));
+    for _ in 0..unique {
+        match reader.read_rich_string() {
+            Some(text) => strings.push(text),
+            None => break,
+        }
+    }
+    strings
+}
+
+/// The standard BIFF8 color palette for indices 8-63
+const DEFAULT_PALETTE: [u32; 56] = [
+    0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00, 0x0000FF, 0xFFFF00, 0xFF00FF, 0x00FFFF, 0x800000,
+    0x008000, 0x000080, 0x808000, 0x800080, 0x008080, 0xC0C0C0, 0x808080, 0x9999FF, 0x993366,
+    0xFFFFCC, 0xCCFFFF, 0x660066, 0xFF8080, 0x0066CC, 0xCCCCFF, 0x000080, 0xFF00FF, 0xFFFF00,
+    0x00FFFF, 0x800080, 0x800000, 0x008080, 0x0000FF, 0x00CCFF, 0xCCFFFF, 0xCCFFCC, 0xFFFF99,
+    0x99CCFF, 0xFF99CC, 0xCC99FF, 0xFFCC99, 0x3366FF, 0x33CCCC, 0x99CC00, 0xFFCC00, 0xFF9900,
+    0xFF6600, 0x666699, 0x969696, 0x003366, 0x339966, 0x003300, 0x333300, 0x993300, 0x993366,
+    0x333399, 0x333333,
+];
+
+const HORIZONTAL_ALIGNMENTS: [Option<&str>; 8] = [
+    None,
+    Some("left"),
+    Some("center"),
+    Some("right"),
+    Some("fill"),
+    Some("justify"),
+    Some("centerContinuous"),
+    Some("distributed"),
+];
+
+const VERTICAL_ALIGNMENTS: [Option<&str>; 5] = [
+    Some("top"),
+    Some("center"),
+    None, // bottom is the default
+    Some("justify"),
+    Some("distributed"),
+];
+
+const BORDER_STYLES: [Option<&str>; 14] = [
+    None,
+    Some("thin"),
+    Some("medium"),
+    Some("dashed"),
+    Some("dotted"),
+    Some("thick"),
+    Some("double"),
+    Some("hair"),
+    Some("mediumDashed"),
+    Some("dashDot"),
+    Some("mediumDashDot"),
+    Some("dashDotDot"),
+    Some("mediumDashDotDot"),
+    Some("slantDashDot"),
+];
+
+const FILL_PATTERNS: [&str; 19] = [
+    "none",
+    "solid",
+    "mediumGray",
+    "darkGray",
+    "lightGray",
+    "darkHorizontal",
+    "darkVertical",
+    "darkDown",
+    "darkUp",
+    "darkGrid",
+    "darkTrellis",
+    "lightHorizontal",
+    "lightVertical",
+    "lightDown",
+    "lightUp",
+    "lightGrid",
+    "lightTrellis",
+    "gray125",
+    "gray0625",
+];
+
+/// Error values as stored in BOOLERR and FORMULA records
+fn error_text(code: u8) -> &'static str {
+    match code {
+        0x00 => "#NULL!",
+        0x07 => "#DIV/0!",
+        0x0F => "#VALUE!",
+        0x17 => "#REF!",
+        0x1D => "#NAME?",
+        0x24 => "#NUM!",
+        0x2A => "#N/A",
+        _ => "#GETTING_DATA",
+    }
+}
+
+/// Decode an RK-encoded number
+fn decode_rk(rk: u32) -> f64 {
+    let value = if rk & 0x02 != 0 {
+        ((rk as i32) >> 2) as f64
+    } else {
+        f64::from_bits(((rk & 0xFFFF_FFFC) as u64) << 32)
+    };
+    if rk & 0x01 != 0 {
+        value / 100.0
+    } else {
+        value
+    }
+}
+
+/// Workbook-global state needed while reading sheets
+#[derive(Default)]
+struct Globals {
+    sheet_names: Vec<String>,
+    /// XTI entries from EXTERNSHEET: (first sheet, last sheet) indexes
+    externsheets: Vec<(i16, i16)>,
+    names: Vec<String>,
+    palette: Vec<u32>,
+}
+
+impl Globals {
+    fn color(&self, index: u16) -> Option<String> {
+        let index = match index {
+            0..=7 => index + 8,
+            8..=63 => index,
+            // System foreground/background and "automatic"
+            _ => return None,
+        } as usize;
+        self.palette
+            .get(index - 8)
+            .map(|rgb| format!("FF{:06X}", rgb))
+    }
+
+    fn sheet_prefix(&self, ixti: u16) -> Option<String> {
+        let &(first, last) = self.externsheets.get(ixti as usize)?;
+        let name = |index: i16| -> Option<String> {
+            let name = self.sheet_names.get(usize::try_from(index).ok()?)?;
+            Some(if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                name.clone()
+            } else {
+                format!("'{}'", name.replace('\'', "''"))
+            })
+        };
+        Some(if first == last {
+            format!("{}!", name(first)?)
+        } else {
+            format!("{}:{}!", name(first)?, name(last)?)
+        })
+    }
+}
+
+pub(crate) fn parse_xls_impl(data: &[u8]) -> Result<ParsedXls, String> {
+    let cfb = CompoundFile::new(data)?;
+    let stream = match cfb.read_stream("Workbook")? {
+        Some(stream) => stream,
+        None if cfb.read_stream("EncryptedPackage")?.is_some() => {
+            return Err("This is a password-protected .xlsx; use decrypt_workbook".to_string())
+        }
+        None if cfb.read_stream("Book")?.is_some() => {
+            return Err("BIFF5 and older .xls files are not supported".to_string())
+        }
+        None => return Err("Not an Excel workbook: Workbook stream is missing".to_string()),
+    };
+
+    let records = read_records(&stream, 0);
+    match records.first() {
+        Some(bof) if bof.id == RT_BOF && u16_at(bof.data(), 0) == BIFF8_VERSION => {}
+        _ => return Err("Unsupported BIFF version; only BIFF8 (.xls 97-2003) is read".to_string()),
+    }
+
+    let mut result = ParsedXls::default();
+    let mut globals = Globals {
+        palette: DEFAULT_PALETTE.to_vec(),
+        ..Default::default()
+    };
+    let mut boundsheets: Vec<(usize, Option<String>, u8)> = Vec::new();
+    let mut fonts: Vec<ParsedFont> = Vec::new();
+    let mut xfs: Vec<Vec<u8>> = Vec::new();
+
+    for record in &records {
+        let data = record.data();
+        match record.id {
+            RT_FILEPASS => return Err("Workbook is password protected".to_string()),
+            RT_BOUNDSHEET => {
+                let position = u32_at(data, 0) as usize;
+                let state = match data.get(4).map(|s| s & 0x03) {
+                    Some(1) => Some("hidden".to_string()),
+                    Some(2) => Some("veryHidden".to_string()),
+                    _ => None,
+                };
+                let sheet_type = data.get(5).copied().unwrap_or(0);
+                let name = read_unicode_string(data, 6, 1)
+                    .map(|(name, _)| name)
+                    .unwrap_or_default();
+                globals.sheet_names.push(name);
+                boundsheets.push((position, state, sheet_type));
+            }
+            RT_SST => result.shared_strings = parse_sst(record),
+            RT_FORMAT => {
+                if let Some((code, _)) = read_unicode_string(data, 2, 2) {
+                    result.styles.num_fmts.insert(u16_at(data, 0) as u32, code);
+                }
+            }
+            RT_FONT => {
+                let grbit = u16_at(data, 2);
+                fonts.push(ParsedFont {
+                    size: Some(u16_at(data, 0) as f64 / 20.0),
+                    italic: grbit & 0x02 != 0,
+                    strikethrough: grbit & 0x08 != 0,
+                    // Colors are resolved once the palette is known
+                    color: Some(u16_at(data, 4).to_string()),
+                    bold: u16_at(data, 6) >= 700,
+                    underline: data.get(10).is_some_and(|&u| u != 0),
+                    name: read_unicode_string(data, 14, 1).map(|(name, _)| name),
+                });
+            }
+            RT_XF => xfs.push(data.to_vec()),
+            RT_DATEMODE => result.date1904 = u16_at(data, 0) == 1,
+            RT_PALETTE => {
+                let count = u16_at(data, 0) as usize;
+                for i in 0..count.min(56) {
+                    let rgb = &data[(2 + i * 4).min(data.len())..];
+                    if rgb.len() >= 3 {
+                        globals.palette[i] =
+                            (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32;
+                    }
+                }
+            }
+            RT_EXTERNSHEET => {
+                let count = u16_at(data, 0) as usize;
+                globals.externsheets = (0..count)
+                    .map(|i| {
+                        let pos = 2 + i * 6;
+                        (u16_at(data, pos + 2) as i16, u16_at(data, pos + 4) as i16)
+                    })
+                    .collect();
+            }
+            RT_LBL => {
+                let builtin = u16_at(data, 0) & 0x20 != 0;
+                let cch = data.get(3).copied().unwrap_or(0) as usize;
+                let flags = data.get(14).copied().unwrap_or(0);
+                let width = if flags & 0x01 != 0 { 2 } else { 1 };
+                let raw = data.get(15..15 + cch * width).unwrap_or_default();
+                let name = decode_chars(raw, flags & 0x01 != 0);
+                globals.names.push(if builtin {
+                    builtin_name(name.chars().next().unwrap_or('\0'))
+                } else {
+                    name
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Fonts first (XFs reference them), then colors now that the palette is final
+    for font in &mut fonts {
+        font.color = font
+            .color
+            .as_deref()
+            .and_then(|c| c.parse().ok())
+            .and_then(|icv| globals.color(icv));
+    }
+    result.styles.fonts = fonts;
+    build_styles(&mut result.styles, &xfs, &globals);
+
+    for (index, (position, state, sheet_type)) in boundsheets.into_iter().enumerate() {
+        // Worksheets only; chart, macro and dialog sheets are skipped
+        if sheet_type != 0 {
+            continue;
+        }
+        result.sheets.push(ParsedSheet {
+            name: globals.sheet_names[index].clone(),
+            state,
+            worksheet: parse_sheet(&stream, position, &globals),
+        });
+    }
+
+    Ok(result)
+}
+
+fn builtin_name(code: char) -> String {
+    match code {
+        '\u{00}' => "_xlnm.Consolidate_Area",
+        '\u{01}' => "_xlnm.Auto_Open",
+        '\u{02}' => "_xlnm.Auto_Close",
+        '\u{03}' => "_xlnm.Extract",
+        '\u{04}' => "_xlnm.Database",
+        '\u{05}' => "_xlnm.Criteria",
+        '\u{06}' => "_xlnm.Print_Area",
+        '\u{07}' => "_xlnm.Print_Titles",
+        '\u{08}' => "_xlnm.Recorder",
+        '\u{09}' => "_xlnm.Data_Form",
+        '\u{0A}' => "_xlnm.Auto_Activate",
+        '\u{0B}' => "_xlnm.Auto_Deactivate",
+        '\u{0C}' => "_xlnm.Sheet_Title",
+        '\u{0D}' => "_xlnm._FilterDatabase",
+        _ => "_xlnm.Unknown",
+    }
+    .to_string()
+}
+
+/// Convert XF records into cellXfs, adding fills and borders as needed
+fn build_styles(styles: &mut ParsedStyles, xfs: &[Vec<u8>], globals: &Globals) {
+    for pattern in ["none", "gray125"] {
+        styles.fills.push(ParsedFill {
+            pattern_type: Some(pattern.to_string()),
+            fg_color: None,
+            bg_color: None,
+        });
+    }
+    styles.borders.push(ParsedBorder::default());
+
+    for xf in xfs {
+        let ifnt = u16_at(xf, 0) as u32;
+        let alignment = xf.get(6).copied().unwrap_or(0);
+        let rotation = xf.get(7).copied().unwrap_or(0);
+        let indent = xf.get(8).copied().unwrap_or(0) & 0x0F;
+        let border_lines = u32_at(xf, 10);
+        let border_colors = u32_at(xf, 14);
+        let fill_colors = u16_at(xf, 18);
+
+        let mut style = ParsedStyle {
+            num_fmt_id: Some(u16_at(xf, 2) as u32),
+            // Font index 4 does not exist in BIFF, so later fonts shift down by one
+            font_id: Some(if ifnt >= 4 { ifnt - 1 } else { ifnt }),
+            horizontal: HORIZONTAL_ALIGNMENTS[(alignment & 0x07) as usize].map(String::from),
+            vertical: VERTICAL_ALIGNMENTS
+                .get(((alignment >> 4) & 0x07) as usize)
+                .copied()
+                .flatten()
+                .map(String::from),
+            wrap_text: alignment & 0x08 != 0,
+            text_rotation: (rotation != 0).then_some(rotation as i32),
+            indent: (indent != 0).then_some(indent as u32),
+            apply_number_format: u16_at(xf, 2) != 0,
+            apply_font: true,
+            ..Default::default()
+        };
+        style.apply_alignment =
+            style.horizontal.is_some() || style.vertical.is_some() || style.wrap_text;
+
+        let pattern = (border_colors >> 26) as usize;
+        if pattern != 0 {
+            let fg = globals.color(fill_colors & 0x7F);
+            let bg = globals.color((fill_colors >> 7) & 0x7F);
+            style.fill_id = Some(styles.fills.len() as u32);
+            style.apply_fill = true;
+            styles.fills.push(ParsedFill {
+                pattern_type: FILL_PATTERNS.get(pattern).map(|p| p.to_string()),
+                fg_color: fg,
+                bg_color: bg,
+            });
+        }
+
+        let line = |shift: u32| {
+            BORDER_STYLES
+                .get(((border_lines >> shift) & 0x0F) as usize)
+                .copied()
+                .flatten()
+                .map(String::from)
+        };
+        let border = ParsedBorder {
+            left_style: line(0),
+            left_color: globals.color(((border_lines >> 16) & 0x7F) as u16),
+            right_style: line(4),
+            right_color: globals.color(((border_lines >> 23) & 0x7F) as u16),
+            top_style: line(8),
+            top_color: globals.color((border_colors & 0x7F) as u16),
+            bottom_style: line(12),
+            bottom_color: globals.color(((border_colors >> 7) & 0x7F) as u16),
+        };
+        if border.left_style.is_some()
+            || border.right_style.is_some()
+            || border.top_style.is_some()
+            || border.bottom_style.is_some()
+        {
+            style.border_id = Some(styles.borders.len() as u32);
+            style.apply_border = true;
+            styles.borders.push(border);
+        }
+
+        styles.cell_xfs.push(style);
+    }
+}
+
+/// A formula cell whose text may depend on a SHRFMLA record seen later
+struct PendingFormula {
+    row: u32,
+    col: u32,
+    rgce: Vec<u8>,
+}
+
+fn parse_sheet(stream: &[u8], position: usize, globals: &Globals) -> ParsedWorksheet {
+    let records = read_records(stream, position);
+    let mut worksheet = ParsedWorksheet::default();
+    let mut rows: BTreeMap<u32, ParsedRow> = BTreeMap::new();
+    let mut formulas: Vec<PendingFormula> = Vec::new();
+    let mut shared: HashMap<(u32, u32), Vec<u8>> = HashMap::new();
+    let mut pending_string: Option<(u32, u32)> = None;
+
+    let put = |rows: &mut BTreeMap<u32, ParsedRow>, row: u32, col: u32, cell: ParsedCell| {
+        let entry = rows.entry(row).or_insert_with(|| ParsedRow {
+            row_num: row + 1,
+            cells: Vec::new(),
+            height: None,
+            hidden: false,
+        });
+        entry.cells.push(ParsedCell {
+            reference: cell_ref(row + 1, col + 1),
+            ..cell
+        });
+    };
+    let cell = |cell_type: Option<&str>, style: u16, value: String| ParsedCell {
+        reference: String::new(),
+        cell_type: cell_type.map(String::from),
+        style_index: Some(style as u32),
+        value: Some(value),
+        formula: None,
+    };
+
+    for record in records.iter().skip(1) {
+        let data = record.data();
+        let (row, col, xf) = (
+            u16_at(data, 0) as u32,
+            u16_at(data, 2) as u32,
+            u16_at(data, 4),
+        );
+        match record.id {
+            RT_ROW => {
+                let flags = data.get(12).copied().unwrap_or(0);
+                let height = u16_at(data, 6);
+                let entry = rows.entry(row).or_insert_with(|| ParsedRow {
+                    row_num: row + 1,
+                    cells: Vec::new(),
+                    height: None,
+                    hidden: false,
+                });
+                // fUnsynced marks a custom height; fDyZero hides the row
+                if flags & 0x40 != 0 && height & 0x8000 == 0 {
+                    entry.height = Some((height & 0x7FFF) as f64 / 20.0);
+                }
+                entry.hidden = flags & 0x20 != 0;
+            }
+            RT_NUMBER => put(
+                &mut rows,
+                row,
+                col,
+                cell(None, xf, format_number(f64_at(data, 6))),
+            ),
+            RT_RK => put(
+                &mut rows,
+                row,
+                col,
+                cell(None, xf, format_number(decode_rk(u32_at(data, 6)))),
+            ),
+            RT_MULRK => {
+                let count = data.len().saturating_sub(6) / 6;
+                for i in 0..count {
+                    let pos = 4 + i * 6;
+                    let value = decode_rk(u32_at(data, pos + 2));
+                    put(
+                        &mut rows,
+                        row,
+                        col + i as u32,
+                        cell(None, u16_at(data, pos), format_number(value)),
+                    );
+                }
+            }
+            RT_LABELSST => put(
+                &mut rows,
+                row,
+                col,
+                cell(Some("s"), xf, u32_at(data, 6).to_string()),
+            ),
+            RT_LABEL => {
+                if let Some((text, _)) = read_unicode_string(data, 6, 2) {
+                    put(&mut rows, row, col, cell(Some("inlineStr"), xf, text));
+                }
+            }
+            RT_BOOLERR => {
+                let value = data.get(6).copied().unwrap_or(0);
+                let parsed = if data.get(7) == Some(&1) {
+                    cell(Some("e"), xf, error_text(value).to_string())
+                } else {
+                    cell(Some("b"), xf, value.to_string())
+                };
+                put(&mut rows, row, col, parsed);
+            }
+            RT_FORMULA => {
+                let result = data.get(6..14).unwrap_or(&[0; 8]);
+                let parsed = if result[6] == 0xFF && result[7] == 0xFF {
+                    match result[0] {
+                        0 => {
+                            pending_string = Some((row, col));
+                            cell(Some("str"), xf, String::new())
+                        }
+                        1 => cell(Some("b"), xf, result[2].to_string()),
+                        2 => cell(Some("e"), xf, error_text(result[2]).to_string()),
+                        _ => cell(Some("str"), xf, String::new()),
+                    }
+                } else {
+                    cell(None, xf, format_number(f64_at(data, 6)))
+                };
+                put(&mut rows, row, col, parsed);
+
+                let cce = u16_at(data, 20) as usize;
+                if let Some(rgce) = data.get(22..22 + cce) {
+                    formulas.push(PendingFormula {
+                        row,
+                        col,
+                        rgce: rgce.to_vec(),
+                    });
+                }
+            }
+            RT_STRING => {
+                if let Some((row, col)) = pending_string.take() {
+                    let text = read_unicode_string(data, 0, 2)
+                        .map(|(text, _)| text)
+                        .unwrap_or_default();
+                    if let Some(cell) = rows.get_mut(&row).and_then(|r| {
+                        r.cells
+                            .iter_mut()
+                            .rfind(|c| c.reference == cell_ref(row + 1, col + 1))
+                    }) {
+                        cell.value = Some(text);
+                    }
+                }
+            }
+            RT_SHRFMLA => {
+                let (first_row, first_col) = (u16_at(data, 0) as u32, data[4.min(data.len() - 1)]);
+                let cce = u16_at(data, 8) as usize;
+                if let Some(rgce) = data.get(10..10 + cce) {
+                    shared.insert((first_row, first_col as u32), rgce.to_vec());
+                }
+            }
+            RT_COLINFO => {
+                let (first, last) = (u16_at(data, 0) as u32, u16_at(data, 2) as u32);
+                let width = u16_at(data, 4) as f64 / 256.0;
+                for col in first..=last.min(255) {
+                    worksheet.col_widths.insert(col + 1, width);
+                }
+            }
+            RT_MERGECELLS => {
+                let count = u16_at(data, 0) as usize;
+                for i in 0..count {
+                    let pos = 2 + i * 8;
+                    if pos + 8 > data.len() {
+                        break;
+                    }
+                    let (r1, r2) = (u16_at(data, pos) as u32, u16_at(data, pos + 2) as u32);
+                    let (c1, c2) = (u16_at(data, pos + 4) as u32, u16_at(data, pos + 6) as u32);
+                    worksheet.merge_cells.push(format!(
+                        "{}:{}",
+                        cell_ref(r1 + 1, c1 + 1),
+                        cell_ref(r2 + 1, c2 + 1)
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for pending in formulas {
+        let text = decode_formula(&pending.rgce, pending.row, pending.col, globals, &shared);
+        if let (Some(text), Some(row)) = (text, rows.get_mut(&pending.row)) {
+            let reference = cell_ref(pending.row + 1, pending.col + 1);
+            if let Some(cell) = row.cells.iter_mut().rfind(|c| c.reference == reference) {
+                cell.formula = Some(text);
+            }
+        }
+    }
+
+    worksheet.rows = rows
+        .into_values()
+        .filter(|row| !row.cells.is_empty() || row.height.is_some() || row.hidden)
+        .map(|mut row| {
+            row.cells.sort_by_key(|cell| {
+                crate::reference::parse_cell_ref(&cell.reference).map_or(0, |(_, c)| c)
+            });
+            row
+        })
+        .collect();
+    worksheet
+}
+
+/// Built-in function names and fixed argument counts (-1 = variable), by ftab index
+fn function_info(index: u16) -> Option<(&'static str, i8)> {
+    Some(match index {
+        0 => ("COUNT", -1),
+        1 => ("IF", -1),
+        2 => ("ISNA", 1),
+        3 => ("ISERROR", 1),
+        4 => ("SUM", -1),
+        5 => ("AVERAGE", -1),
+        6 => ("MIN", -1),
+        7 => ("MAX", -1),
+        8 => ("ROW", -1),
+        9 => ("COLUMN", -1),
+        10 => ("NA", 0),
+        11 => ("NPV", -1),
+        12 => ("STDEV", -1),
+        13 => ("DOLLAR", -1),
+        14 => ("FIXED", -1),
+        15 => ("SIN", 1),
+        16 => ("COS", 1),
+        17 => ("TAN", 1),
+        18 => ("ATAN", 1),
+        19 => ("PI", 0),
+        20 => ("SQRT", 1),
+        21 => ("EXP", 1),
+        22 => ("LN", 1),
+        23 => ("LOG10", 1),
+        24 => ("ABS", 1),
+        25 => ("INT", 1),
+        26 => ("SIGN", 1),
+        27 => ("ROUND", 2),
+        28 => ("LOOKUP", -1),
+        29 => ("INDEX", -1),
+        30 => ("REPT", 2),
+        31 => ("MID", 3),
+        32 => ("LEN", 1),
+        33 => ("VALUE", 1),
+        34 => ("TRUE", 0),
+        35 => ("FALSE", 0),
+        36 => ("AND", -1),
+        37 => ("OR", -1),
+        38 => ("NOT", 1),
+        39 => ("MOD", 2),
+        40 => ("DCOUNT", 3),
+        41 => ("DSUM", 3),
+        42 => ("DAVERAGE", 3),
+        43 => ("DMIN", 3),
+        44 => ("DMAX", 3),
+        45 => ("DSTDEV", 3),
+        46 => ("VAR", -1),
+        47 => ("DVAR", 3),
+        48 => ("TEXT", 2),
+        49 => ("LINEST", -1),
+        50 => ("TREND", -1),
+        51 => ("LOGEST", -1),
+        52 => ("GROWTH", -1),
+        56 => ("PV", -1),
+        57 => ("FV", -1),
+        58 => ("NPER", -1),
+        59 => ("PMT", -1),
+        60 => ("RATE", -1),
+        61 => ("MIRR", 3),
+        62 => ("IRR", -1),
+        63 => ("RAND", 0),
+        64 => ("MATCH", -1),
+        65 => ("DATE", 3),
+        66 => ("TIME", 3),
+        67 => ("DAY", 1),
+        68 => ("MONTH", 1),
+        69 => ("YEAR", 1),
+        70 => ("WEEKDAY", -1),
+        71 => ("HOUR", 1),
+        72 => ("MINUTE", 1),
+        73 => ("SECOND", 1),
+        74 => ("NOW", 0),
+        75 => ("AREAS", 1),
+        76 => ("ROWS", 1),
+        77 => ("COLUMNS", 1),
+        78 => ("OFFSET", -1),
+        82 => ("SEARCH", -1),
+        83 => ("TRANSPOSE", 1),
+        86 => ("TYPE", 1),
+        97 => ("ATAN2", 2),
+        98 => ("ASIN", 1),
+        99 => ("ACOS", 1),
+        100 => ("CHOOSE", -1),
+        101 => ("HLOOKUP", -1),
+        102 => ("VLOOKUP", -1),
+        105 => ("ISREF", 1),
+        109 => ("LOG", -1),
+        111 => ("CHAR", 1),
+        112 => ("LOWER", 1),
+        113 => ("UPPER", 1),
+        114 => ("PROPER", 1),
+        115 => ("LEFT", -1),
+        116 => ("RIGHT", -1),
+        117 => ("EXACT", 2),
+        118 => ("TRIM", 1),
+        119 => ("REPLACE", 4),
+        120 => ("SUBSTITUTE", -1),
+        121 => ("CODE", 1),
+        124 => ("FIND", -1),
+        125 => ("CELL", -1),
+        126 => ("ISERR", 1),
+        127 => ("ISTEXT", 1),
+        128 => ("ISNUMBER", 1),
+        129 => ("ISBLANK", 1),
+        130 => ("T", 1),
+        131 => ("N", 1),
+        140 => ("DATEVALUE", 1),
+        141 => ("TIMEVALUE", 1),
+        142 => ("SLN", 3),
+        143 => ("SYD", 4),
+        144 => ("DDB", -1),
+        148 => ("INDIRECT", -1),
+        162 => ("CLEAN", 1),
+        163 => ("MDETERM", 1),
+        164 => ("MINVERSE", 1),
+        165 => ("MMULT", 2),
+        167 => ("IPMT", -1),
+        168 => ("PPMT", -1),
+        169 => ("COUNTA", -1),
+        183 => ("PRODUCT", -1),
+        184 => ("FACT", 1),
+        189 => ("DPRODUCT", 3),
+        190 => ("ISNONTEXT", 1),
+        193 => ("STDEVP", -1),
+        194 => ("VARP", -1),
+        195 => ("DSTDEVP", 3),
+        196 => ("DVARP", 3),
+        197 => ("TRUNC", -1),
+        198 => ("ISLOGICAL", 1),
+        199 => ("DCOUNTA", 3),
+        212 => ("ROUNDUP", 2),
+        213 => ("ROUNDDOWN", 2),
+        216 => ("RANK", -1),
+        219 => ("ADDRESS", -1),
+        220 => ("DAYS360", -1),
+        221 => ("TODAY", 0),
+        222 => ("VDB", -1),
+        227 => ("MEDIAN", -1),
+        228 => ("SUMPRODUCT", -1),
+        229 => ("SINH", 1),
+        230 => ("COSH", 1),
+        231 => ("TANH", 1),
+        232 => ("ASINH", 1),
+        233 => ("ACOSH", 1),
+        234 => ("ATANH", 1),
+        235 => ("DGET", 3),
+        244 => ("INFO", 1),
+        247 => ("DB", -1),
+        252 => ("FREQUENCY", 2),
+        261 => ("ERROR.TYPE", 1),
+        269 => ("AVEDEV", -1),
+        270 => ("BETADIST", -1),
+        271 => ("GAMMALN", 1),
+        272 => ("BETAINV", -1),
+        273 => ("BINOMDIST", 4),
+        274 => ("CHIDIST", 2),
+        275 => ("CHIINV", 2),
+        276 => ("COMBIN", 2),
+        277 => ("CONFIDENCE", 3),
+        278 => ("CRITBINOM", 3),
+        279 => ("EVEN", 1),
+        280 => ("EXPONDIST", 3),
+        281 => ("FDIST", 3),
+        282 => ("FINV", 3),
+        283 => ("FISHER", 1),
+        284 => ("FISHERINV", 1),
+        285 => ("FLOOR", 2),
+        286 => ("GAMMADIST", 4),
+        287 => ("GAMMAINV", 3),
+        288 => ("CEILING", 2),
+        289 => ("HYPGEOMDIST", 4),
+        290 => ("LOGNORMDIST", 3),
+        291 => ("LOGINV", 3),
+        292 => ("NEGBINOMDIST", 3),
+        293 => ("NORMDIST", 4),
+        294 => ("NORMSDIST", 1),
+        295 => ("NORMINV", 3),
+        296 => ("NORMSINV", 1),
+        297 => ("STANDARDIZE", 3),
+        298 => ("ODD", 1),
+        299 => ("PERMUT", 2),
+        300 => ("POISSON", 3),
+        301 => ("TDIST", 3),
+        302 => ("WEIBULL", 4),
+        303 => ("SUMXMY2", 2),
+        304 => ("SUMX2MY2", 2),
+        305 => ("SUMX2PY2", 2),
+        306 => ("CHITEST", 2),
+        307 => ("CORREL", 2),
+        308 => ("COVAR", 2),
+        309 => ("FORECAST", 3),
+        310 => ("FTEST", 2),
+        311 => ("INTERCEPT", 2),
+        312 => ("PEARSON", 2),
+        313 => ("RSQ", 2),
+        314 => ("STEYX", 2),
+        315 => ("SLOPE", 2),
+        316 => ("TTEST", 4),
+        317 => ("PROB", -1),
+        318 => ("DEVSQ", -1),
+        319 => ("GEOMEAN", -1),
+        320 => ("HARMEAN", -1),
+        321 => ("SUMSQ", -1),
+        322 => ("KURT", -1),
+        323 => ("SKEW", -1),
+        324 => ("ZTEST", -1),
+        325 => ("LARGE", 2),
+        326 => ("SMALL", 2),
+        327 => ("QUARTILE", 2),
+        328 => ("PERCENTILE", 2),
+        329 => ("PERCENTRANK", -1),
+        330 => ("MODE", -1),
+        331 => ("TRIMMEAN", 2),
+        332 => ("TINV", 2),
+        336 => ("CONCATENATE", -1),
+        337 => ("POWER", 2),
+        342 => ("RADIANS", 1),
+        343 => ("DEGREES", 1),
+        344 => ("SUBTOTAL", -1),
+        345 => ("SUMIF", -1),
+        346 => ("COUNTIF", 2),
+        347 => ("COUNTBLANK", 1),
+        350 => ("ISPMT", 4),
+        351 => ("DATEDIF", 3),
+        354 => ("ROMAN", -1),
+        358 => ("GETPIVOTDATA", -1),
+        359 => ("HYPERLINK", -1),
+        360 => ("PHONETIC", 1),
+        361 => ("AVERAGEA", -1),
+        362 => ("MAXA", -1),
+        363 => ("MINA", -1),
+        364 => ("STDEVPA", -1),
+        365 => ("VARPA", -1),
+        366 => ("STDEVA", -1),
+        367 => ("VARA", -1),
+        _ => return None,
+    })
+}
+
+/// Format a cell reference; `row`/`col` are 0-based
+fn format_ref(row: u32, col: u32, row_relative: bool, col_relative: bool) -> String {
+    format!(
+        "{}{}{}{}",
+        if col_relative { "" } else { "$" },
+        col_to_letters(col + 1),
+        if row_relative { "" } else { "$" },
+        row + 1
+    )
+}
+
+/// Decode a BIFF8 column field: (column, row relative, column relative)
+fn col_field(value: u16) -> (u32, bool, bool) {
+    (
+        (value & 0x3FFF) as u32,
+        value & 0x8000 != 0,
+        value & 0x4000 != 0,
+    )
+}
+
+/// Resolve a RefN/AreaN operand against the cell holding the formula
+fn relative_ref(base_row: u32, base_col: u32, rw: u16, col: u16) -> String {
+    let (col_bits, row_rel, col_rel) = col_field(col);
+    let row = if row_rel {
+        (base_row as i32 + rw as i16 as i32).rem_euclid(65536) as u32
+    } else {
+        rw as u32
+    };
+    let col = if col_rel {
+        (base_col as i32 + (col_bits as u8) as i8 as i32).rem_euclid(256) as u32
+    } else {
+        col_bits
+    };
+    format_ref(row, col, row_rel, col_rel)
+}
+
+/// Decompile a BIFF8 formula token stream into A1 text
+fn decode_formula(
+    rgce: &[u8],
+    row: u32,
+    col: u32,
+    globals: &Globals,
+    shared: &HashMap<(u32, u32), Vec<u8>>,
+) -> Option<String> {
+    // A formula consisting of a lone ptgExp points at a shared formula
+    if rgce.first() == Some(&0x01) {
+        let anchor = (u16_at(rgce, 1) as u32, u16_at(rgce, 3) as u32);
+        let tokens = shared.get(&anchor)?;
+        return decode_tokens(tokens, row, col, globals);
+    }
+    decode_tokens(rgce, row, col, globals)
+}
+
+fn decode_tokens(rgce: &[u8], row: u32, col: u32, globals: &Globals) -> Option<String> {
+    let mut stack: Vec<String> = Vec::new();
+    let mut pos = 0;
+
+    while pos < rgce.len() {
+        let ptg = rgce[pos];
+        pos += 1;
+        // Operand tokens carry a class in bits 5-6; normalise to the reference class
+        let base = if ptg >= 0x20 {
+            (ptg & 0x1F) | 0x20
+        } else {
+            ptg
+        };
+
+        match base {
+            0x03..=0x11 => {
+                let right = stack.pop()?;
+                let left = stack.pop()?;
+                let op = match base {
+                    0x03 => "+",
+                    0x04 => "-",
+                    0x05 => "*",
+                    0x06 => "/",
+                    0x07 => "^",
+                    0x08 => "&",
+                    0x09 => "<",
+                    0x0A => "<=",
+                    0x0B => "=",
+                    0x0C => ">=",
+                    0x0D => ">",
+                    0x0E => "<>",
+                    0x0F => " ",
+                    0x10 => ",",
+                    _ => ":",
+                };
+                stack.push(format!("{}{}{}", left, op, right));
+            }
+            0x12 => {
+                let operand = stack.pop()?;
+                stack.push(format!("+{}", operand));
+            }
+            0x13 => {
+                let operand = stack.pop()?;
+                stack.push(format!("-{}", operand));
+            }
+            0x14 => {
+                let operand = stack.pop()?;
+                stack.push(format!("{}%", operand));
+            }
+            0x15 => {
+                let operand = stack.pop()?;
+                stack.push(format!("({})", operand));
+            }
+            0x16 => stack.push(String::new()),
+            0x17 => {
+                let (text, next) = read_unicode_string(rgce, pos, 1)?;
+                stack.push(format!("\"{}\"", text.replace('"', "\"\"")));
+                pos = next;
+            }
+            0x19 => {
+                let flags = *rgce.get(pos)?;
+                let data = u16_at(rgce, pos + 1) as usize;
+                pos += 3;
+                if flags & 0x04 != 0 {
+                    // attrChoose is followed by a jump table
+                    pos += (data + 1) * 2;
+                } else if flags & 0x10 != 0 {
+                    let operand = stack.pop()?;
+                    stack.push(format!("SUM({})", operand));
+                }
+            }
+            0x1C => {
+                stack.push(error_text(*rgce.get(pos)?).to_string());
+                pos += 1;
+            }
+            0x1D => {
+                stack.push(
+                    if *rgce.get(pos)? != 0 {
+                        "TRUE"
+                    } else {
+                        "FALSE"
+                    }
+                    .to_string(),
+                );
+                pos += 1;
+            }
+            0x1E => {
+                stack.push(u16_at(rgce, pos).to_string());
+                pos += 2;
+            }
+            0x1F => {
+                stack.push(format_number(f64_at(rgce, pos)));
+                pos += 8;
+            }
+            0x21 | 0x22 => {
+                let (argc, index) = if base == 0x21 {
+                    let index = u16_at(rgce, pos);
+                    pos += 2;
+                    let (_, argc) = function_info(index)?;
+                    if argc < 0 {
+                        return None;
+                    }
+                    (argc as usize, index)
+                } else {
+                    let argc = (*rgce.get(pos)? & 0x7F) as usize;
+                    let index = u16_at(rgce, pos + 1) & 0x7FFF;
+                    pos += 3;
+                    (argc, index)
+                };
+                let (name, _) = function_info(index)?;
+                if stack.len() < argc {
+                    return None;
+                }
+                let args = stack.split_off(stack.len() - argc);
+                stack.push(format!("{}({})", name, args.join(",")));
+            }
+            0x23 => {
+                let index = u32_at(rgce, pos) as usize;
+                pos += 4;
+                stack.push(globals.names.get(index.checked_sub(1)?)?.clone());
+            }
+            0x24 => {
+                let (c, row_rel, col_rel) = col_field(u16_at(rgce, pos + 2));
+                stack.push(format_ref(u16_at(rgce, pos) as u32, c, row_rel, col_rel));
+                pos += 4;
+            }
+            0x25 => {
+                let (r1, r2) = (u16_at(rgce, pos) as u32, u16_at(rgce, pos + 2) as u32);
+                let (c1, r1_rel, c1_rel) = col_field(u16_at(rgce, pos + 4));
+                let (c2, r2_rel, c2_rel) = col_field(u16_at(rgce, pos + 6));
+                stack.push(format!(
+                    "{}:{}",
+                    format_ref(r1, c1, r1_rel, c1_rel),
+                    format_ref(r2, c2, r2_rel, c2_rel)
+                ));
+                pos += 8;
+            }
+            // Mem* tokens wrap a sub-expression that follows inline
+            0x26..=0x28 => pos += 6,
+            0x29 => pos += 2,
+            0x2A => {
+                stack.push("#REF!".to_string());
+                pos += 4;
+            }
+            0x2B => {
+                stack.push("#REF!".to_string());
+                pos += 8;
+            }
+            0x2C => {
+                stack.push(relative_ref(
+                    row,
+                    col,
+                    u16_at(rgce, pos),
+                    u16_at(rgce, pos + 2),
+                ));
+                pos += 4;
+            }
+            0x2D => {
+                let first = relative_ref(row, col, u16_at(rgce, pos), u16_at(rgce, pos + 4));
+                let last = relative_ref(row, col, u16_at(rgce, pos + 2), u16_at(rgce, pos + 6));
+                stack.push(format!("{}:{}", first, last));
+                pos += 8;
+            }
+            0x3A => {
+                let prefix = globals.sheet_prefix(u16_at(rgce, pos))?;
+                let (c, row_rel, col_rel) = col_field(u16_at(rgce, pos + 4));
+                stack.push(format!(
+                    "{}{}",
+                    prefix,
+                    format_ref(u16_at(rgce, pos + 2) as u32, c, row_rel, col_rel)
+                ));
+                pos += 6;
+            }
+            0x3B => {
+                let prefix = globals.sheet_prefix(u16_at(rgce, pos))?;
+                let (r1, r2) = (u16_at(rgce, pos + 2) as u32, u16_at(rgce, pos + 4) as u32);
+                let (c1, r1_rel, c1_rel) = col_field(u16_at(rgce, pos + 6));
+                let (c2, r2_rel, c2_rel) = col_field(u16_at(rgce, pos + 8));
+                stack.push(format!(
+                    "{}{}:{}",
+                    prefix,
+                    format_ref(r1, c1, r1_rel, c1_rel),
+                    format_ref(r2, c2, r2_rel, c2_rel)
+                ));
+                pos += 10;
+            }
+            0x3C => {
+                let prefix = globals.sheet_prefix(u16_at(rgce, pos))?;
+                stack.push(format!("{}#REF!", prefix));
+                pos += 6;
+            }
+            0x3D => {
+                let prefix = globals.sheet_prefix(u16_at(rgce, pos))?;
+                stack.push(format!("{}#REF!", prefix));
+                pos += 10;
+            }
+            // Arrays, tables, external names and extended tokens are not decoded
+            _ => return None,
+        }
+    }
+
+    if stack.len() == 1 {
+        stack.pop()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfb::write_compound_file;
+
+    fn record(out: &mut Vec<u8>, id: u16, data: &[u8]) {
+        out.extend_from_slice(&id.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+
+    fn bof() -> Vec<u8> {
+        let mut data = vec![0u8; 16];
+        data[..2].copy_from_slice(&BIFF8_VERSION.to_le_bytes());
+        data
+    }
+
+    fn cell_header(row: u16, col: u16, xf: u16) -> Vec<u8> {
+        [row.to_le_bytes(), col.to_le_bytes(), xf.to_le_bytes()].concat()
+    }
+
+    fn xf(font: u16, fmt: u16, alignment: u8, fill: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 20];
+        data[..2].copy_from_slice(&font.to_le_bytes());
+        data[2..4].copy_from_slice(&fmt.to_le_bytes());
+        data[6] = alignment;
+        data[14..18].copy_from_slice(&(fill << 26).to_le_bytes());
+        data[18..20].copy_from_slice(&13u16.to_le_bytes()); // yellow foreground
+        data
+    }
+
+    fn build_workbook() -> Vec<u8> {
+        let mut globals = Vec::new();
+        record(&mut globals, RT_BOF, &bof());
+
+        // Bold Arial 10pt
+        let mut font = vec![0u8; 14];
+        font[..2].copy_from_slice(&200u16.to_le_bytes());
+        font[4..6].copy_from_slice(&10u16.to_le_bytes());
+        font[6..8].copy_from_slice(&700u16.to_le_bytes());
+        font.extend_from_slice(&[5, 0]);
+        font.extend_from_slice(b"Arial");
+        record(&mut globals, RT_FONT, &font);
+
+        let mut format = 164u16.to_le_bytes().to_vec();
+        format.extend_from_slice(&[10, 0, 0]);
+        format.extend_from_slice(b"yyyy-mm-dd");
+        record(&mut globals, RT_FORMAT, &format);
+
+        record(&mut globals, RT_XF, &xf(0, 0, 0, 0));
+        record(&mut globals, RT_XF, &xf(0, 164, 0x02, 1));
+
+        // The second string is split across a CONTINUE, switching to UTF-16
+        let mut sst = Vec::new();
+        sst.extend_from_slice(&2u32.to_le_bytes());
+        sst.extend_from_slice(&2u32.to_le_bytes());
+        sst.extend_from_slice(&[5, 0, 0]);
+        sst.extend_from_slice(b"Hello");
+        sst.extend_from_slice(&[4, 0, 0]);
+        sst.extend_from_slice(b"ca");
+        record(&mut globals, RT_SST, &sst);
+        let mut cont = vec![1u8];
+        for ch in "fé".encode_utf16() {
+            cont.extend_from_slice(&ch.to_le_bytes());
+        }
+        record(&mut globals, RT_CONTINUE, &cont);
+
+        let boundsheet_at = globals.len() + 4;
+        let mut boundsheet = vec![0u8; 6];
+        boundsheet.extend_from_slice(&[4, 0]);
+        boundsheet.extend_from_slice(b"Data");
+        record(&mut globals, RT_BOUNDSHEET, &boundsheet);
+        record(&mut globals, RT_EOF, &[]);
+
+        let sheet_offset = globals.len() as u32;
+        globals[boundsheet_at..boundsheet_at + 4].copy_from_slice(&sheet_offset.to_le_bytes());
+
+        let mut sheet = Vec::new();
+        record(&mut sheet, RT_BOF, &bof());
+
+        let mut row = vec![0u8; 16];
+        row[6..8].copy_from_slice(&400u16.to_le_bytes());
+        row[12] = 0x40;
+        record(&mut sheet, RT_ROW, &row);
+
+        let mut label = cell_header(0, 0, 1);
+        label.extend_from_slice(&1u32.to_le_bytes());
+        record(&mut sheet, RT_LABELSST, &label);
+
+        let mut number = cell_header(1, 0, 0);
+        number.extend_from_slice(&1.5f64.to_le_bytes());
+        record(&mut sheet, RT_NUMBER, &number);
+
+        // RK integer 3 and RK 0.25 (stored as 25 / 100)
+        let mut mulrk = cell_header(2, 0, 0)[..4].to_vec();
+        mulrk.extend_from_slice(&0u16.to_le_bytes());
+        mulrk.extend_from_slice(&((3u32 << 2) | 0x02).to_le_bytes());
+        mulrk.extend_from_slice(&0u16.to_le_bytes());
+        mulrk.extend_from_slice(&((25u32 << 2) | 0x03).to_le_bytes());
+        mulrk.extend_from_slice(&1u16.to_le_bytes());
+        record(&mut sheet, RT_MULRK, &mulrk);
+
+        let mut boolean = cell_header(1, 1, 0);
+        boolean.extend_from_slice(&[1, 0]);
+        record(&mut sheet, RT_BOOLERR, &boolean);
+
+        // =SUM(A2:A3)*2 with a cached value of 9
+        let mut formula = cell_header(3, 0, 0);
+        formula.extend_from_slice(&9.0f64.to_le_bytes());
+        formula.extend_from_slice(&[0; 6]);
+        let mut rgce = vec![0x25];
+        rgce.extend_from_slice(&1u16.to_le_bytes());
+        rgce.extend_from_slice(&2u16.to_le_bytes());
+        rgce.extend_from_slice(&0xC000u16.to_le_bytes());
+        rgce.extend_from_slice(&0xC000u16.to_le_bytes());
+        rgce.extend_from_slice(&[0x19, 0x10, 0, 0, 0x1E, 2, 0, 0x05]);
+        formula.extend_from_slice(&(rgce.len() as u16).to_le_bytes());
+        formula.extend_from_slice(&rgce);
+        record(&mut sheet, RT_FORMULA, &formula);
+
+        // =UPPER(B1) returning a string
+        let mut formula = cell_header(3, 1, 0);
+        formula.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0xFF, 0xFF]);
+        formula.extend_from_slice(&[0; 6]);
+        let rgce = [0x24, 0, 0, 1, 0x40, 0x41, 113, 0];
+        formula.extend_from_slice(&(rgce.len() as u16).to_le_bytes());
+        formula.extend_from_slice(&rgce);
+        record(&mut sheet, RT_FORMULA, &formula);
+        record(&mut sheet, RT_STRING, &[2, 0, 0, b'O', b'K']);
+
+        let mut merge = 1u16.to_le_bytes().to_vec();
+        for v in [0u16, 0, 0, 1] {
+            merge.extend_from_slice(&v.to_le_bytes());
+        }
+        record(&mut sheet, RT_MERGECELLS, &merge);
+
+        let mut colinfo = vec![0u8; 12];
+        colinfo[4..6].copy_from_slice(&(20u16 * 256).to_le_bytes());
+        record(&mut sheet, RT_COLINFO, &colinfo);
+        record(&mut sheet, RT_EOF, &[]);
+
+        globals.extend_from_slice(&sheet);
+        write_compound_file(&[("Workbook", &globals)])
+    }
+
+    #[test]
+    fn test_parse_xls() {
+        let xls = parse_xls_impl(&build_workbook()).unwrap();
+        assert_eq!(
+            xls.shared_strings,
+            vec!["Hello".to_string(), "café".to_string()]
+        );
+        assert_eq!(xls.sheets.len(), 1);
+        assert_eq!(xls.sheets[0].name, "Data");
+
+        let ws = &xls.sheets[0].worksheet;
+        assert_eq!(ws.merge_cells, vec!["A1:B1".to_string()]);
+        assert_eq!(ws.col_widths.get(&1), Some(&20.0));
+        assert_eq!(ws.rows.len(), 4);
+        assert_eq!(ws.rows[0].height, Some(20.0));
+
+        let a1 = &ws.rows[0].cells[0];
+        assert_eq!(a1.cell_type.as_deref(), Some("s"));
+        assert_eq!(a1.value.as_deref(), Some("1"));
+        let xf = &xls.styles.cell_xfs[a1.style_index.unwrap() as usize];
+        assert_eq!(xf.horizontal.as_deref(), Some("center"));
+        assert_eq!(
+            xls.styles.num_fmts.get(&164).map(String::as_str),
+            Some("yyyy-mm-dd")
+        );
+        let fill = &xls.styles.fills[xf.fill_id.unwrap() as usize];
+        assert_eq!(fill.fg_color.as_deref(), Some("FFFFFF00"));
+        let font = &xls.styles.fonts[xf.font_id.unwrap() as usize];
+        assert!(font.bold);
+        assert_eq!(font.color.as_deref(), Some("FFFF0000"));
+
+        let row2 = &ws.rows[1].cells;
+        assert_eq!(row2[0].value.as_deref(), Some("1.5"));
+        assert_eq!(row2[1].cell_type.as_deref(), Some("b"));
+
+        let row3 = &ws.rows[2].cells;
+        assert_eq!(row3[0].value.as_deref(), Some("3"));
+        assert_eq!(row3[1].reference, "B3");
+        assert_eq!(row3[1].value.as_deref(), Some("0.25"));
+
+        let row4 = &ws.rows[3].cells;
+        assert_eq!(row4[0].formula.as_deref(), Some("SUM(A2:A3)*2"));
+        assert_eq!(row4[0].value.as_deref(), Some("9"));
+        assert_eq!(row4[1].formula.as_deref(), Some("UPPER(B$1)"));
+        assert_eq!(row4[1].cell_type.as_deref(), Some("str"));
+        assert_eq!(row4[1].value.as_deref(), Some("OK"));
+    }
+
+    #[test]
+    fn test_decode_rk() {
+        assert_eq!(decode_rk((3 << 2) | 0x02), 3.0);
+        assert_eq!(decode_rk(((-7i32 << 2) as u32) | 0x02), -7.0);
+        let bits = 1.5f64.to_bits();
+        assert_eq!(decode_rk((bits >> 32) as u32), 1.5);
+    }
+}
@@ -0,0 +1,98 @@
+//! OOXML `_xHHHH_` escapes in string content
+//!
+//! Characters XML can't carry, such as most control characters or a lone
+//! carriage return, are written in shared and inline strings as `_x` plus
+//! four hex digits of the UTF-16 code unit and `_` (`_x000D_`). A literal
+//! underscore that would otherwise read as an escape is itself escaped as
+//! `_x005F_`.
+
+use crate::writer::push_escaped;
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// UTF-16 code unit of an `_xHHHH_` escape at the start of `text`
+fn escape_unit(text: &str) -> Option<u32> {
+    let bytes = text.as_bytes();
+    if bytes.len() >= 7
+        && bytes.starts_with(b"_x")
+        && bytes[6] == b'_'
+        && bytes[2..6].iter().all(u8::is_ascii_hexdigit)
+    {
+        u32::from_str_radix(&text[2..6], 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Replace `_xHHHH_` escapes with the characters they stand for
+pub(crate) fn decode_xstring(text: &str) -> Cow<'_, str> {
+    if !text.contains("_x") {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("_x") {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        // Characters outside the BMP are escaped as a surrogate pair
+        let decoded = escape_unit(tail).and_then(|unit| match unit {
+            0xD800..=0xDBFF => match escape_unit(&tail[7..]) {
+                Some(low @ 0xDC00..=0xDFFF) => {
+                    char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
+                        .map(|ch| (ch, 14))
+                }
+                _ => None,
+            },
+            unit => char::from_u32(unit).map(|ch| (ch, 7)),
+        });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &tail[len..];
+            }
+            None => {
+                out.push_str("_x");
+                rest = &tail[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Append XML-escaped string content, writing control characters and
+/// escape-like underscores as `_xHHHH_`
+pub(crate) fn push_xstring_escaped(out: &mut String, text: &str) {
+    let mut start = 0;
+    for (i, ch) in text.char_indices() {
+        let unit = match ch {
+            '_' if escape_unit(&text[i..]).is_some() => '_' as u32,
+            '\t' | '\n' => continue,
+            ch if (ch as u32) < 0x20 => ch as u32,
+            _ => continue,
+        };
+        push_escaped(out, &text[start..i]);
+        let _ = write!(out, "_x{:04X}_", unit);
+        start = i + ch.len_utf8();
+    }
+    push_escaped(out, &text[start..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xstring_round_trip() {
+        assert_eq!(decode_xstring("line_x000D_\nnext"), "line\r\nnext");
+        assert_eq!(decode_xstring("_x005F_x0041_ _xD83D__xDE00_"), "_x0041_ 😀");
+        assert_eq!(decode_xstring("file_x.txt _x12_"), "file_x.txt _x12_");
+        assert!(matches!(decode_xstring("plain"), Cow::Borrowed(_)));
+
+        let text = "a\r\nb\u{1}_x0041_ & c";
+        let mut out = String::new();
+        push_xstring_escaped(&mut out, text);
+        assert_eq!(out, "a_x000D_\nb_x0001__x005F_x0041_ &amp; c");
+        assert_eq!(decode_xstring(&out.replace("&amp;", "&")), text);
+    }
+}
@@ -0,0 +1,800 @@
+//! Minimal ZIP archive reader and writer for spreadsheet packages
+//!
+//! Only the subset of the format used by OOXML and ODF packages is handled:
+//! stored or deflated entries, no encryption, no ZIP64. DEFLATE itself is
+//! delegated to `miniz_oxide`.
+
+use crate::encoding::xml_to_string;
+use crate::limits::{LimitExceeded, ParseLimits, ReadError, XmlShape};
+use crate::scan::rfind;
+#[cfg(feature = "wasm")]
+use crate::trap::trap;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZFlush, MZStatus};
+use std::cell::Cell;
+use std::io::{self, Read};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// DOS date for 1980-01-01, keeping output byte-for-byte reproducible
+const DOS_DATE: u16 = (1 << 5) | 1;
+const DOS_TIME: u16 = 0;
+
+/// Default DEFLATE level for XML parts
+pub(crate) const DEFAULT_COMPRESSION_LEVEL: u8 = 6;
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    method: u16,
+    offset: u32,
+}
+
+/// Builds a ZIP archive in memory
+pub(crate) struct ZipWriter {
+    buf: Vec<u8>,
+    entries: Vec<CentralEntry>,
+}
+
+impl ZipWriter {
+    pub(crate) fn new() -> Self {
+        ZipWriter {
+            buf: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add an entry; `level` 0 stores the data uncompressed
+    pub(crate) fn add_file(&mut self, name: &str, data: &[u8], level: u8) {
+        let crc = crc32(data);
+        let compressed = if level > 0 {
+            Some(compress_to_vec(data, level.min(10)))
+        } else {
+            None
+        };
+        // Fall back to storing when DEFLATE doesn't help (already-compressed media)
+        let (method, payload): (u16, &[u8]) = match compressed {
+            Some(ref deflated) if deflated.len() < data.len() => (METHOD_DEFLATED, deflated),
+            _ => (METHOD_STORED, data),
+        };
+        self.add_raw(name, crc, data.len() as u32, method, payload);
+    }
+
+    /// Add an entry whose payload has already been compressed with `method`
+    pub(crate) fn add_raw(
+        &mut self,
+        name: &str,
+        crc: u32,
+        uncompressed_size: u32,
+        method: u16,
+        payload: &[u8],
+    ) {
+        let offset = self.buf.len() as u32;
+        let name_bytes = name.as_bytes();
+
+        self.put_u32(LOCAL_FILE_HEADER);
+        self.put_u16(20); // version needed to extract
+        self.put_u16(0x0800); // UTF-8 file names
+        self.put_u16(method);
+        self.put_u16(DOS_TIME);
+        self.put_u16(DOS_DATE);
+        self.put_u32(crc);
+        self.put_u32(payload.len() as u32);
+        self.put_u32(uncompressed_size);
+        self.put_u16(name_bytes.len() as u16);
+        self.put_u16(0); // extra field length
+        self.buf.extend_from_slice(name_bytes);
+        self.buf.extend_from_slice(payload);
+
+        self.entries.push(CentralEntry {
+            name: name.to_string(),
+            crc,
+            compressed_size: payload.len() as u32,
+            uncompressed_size,
+            method,
+            offset,
+        });
+    }
+
+    /// Write the central directory and return the finished archive
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.buf.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+
+        for entry in &entries {
+            self.put_u32(CENTRAL_DIRECTORY_HEADER);
+            self.put_u16(20); // version made by
+            self.put_u16(20); // version needed to extract
+            self.put_u16(0x0800);
+            self.put_u16(entry.method);
+            self.put_u16(DOS_TIME);
+            self.put_u16(DOS_DATE);
+            self.put_u32(entry.crc);
+            self.put_u32(entry.compressed_size);
+            self.put_u32(entry.uncompressed_size);
+            self.put_u16(entry.name.len() as u16);
+            self.put_u16(0); // extra field length
+            self.put_u16(0); // comment length
+            self.put_u16(0); // disk number start
+            self.put_u16(0); // internal attributes
+            self.put_u32(0); // external attributes
+            self.put_u32(entry.offset);
+            self.buf.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let directory_size = self.buf.len() as u32 - directory_offset;
+        self.put_u32(END_OF_CENTRAL_DIRECTORY);
+        self.put_u16(0); // number of this disk
+        self.put_u16(0); // disk where central directory starts
+        self.put_u16(entries.len() as u16);
+        self.put_u16(entries.len() as u16);
+        self.put_u32(directory_size);
+        self.put_u32(directory_offset);
+        self.put_u16(0); // comment length
+
+        self.buf
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// An entry listed in a ZIP archive's central directory
+struct ZipEntry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    offset: u32,
+}
+
+/// Reads entries from an in-memory ZIP archive, borrowed or owned
+pub(crate) struct ZipArchive<D> {
+    data: D,
+    /// Offset of `data` in the file, when it holds only the file's tail
+    base: usize,
+    entries: Vec<ZipEntry>,
+    limits: ParseLimits,
+    /// Bytes decompressed so far, against `limits.max_decompressed_size`
+    decompressed: Cell<u64>,
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// The end record is 22 bytes plus a comment of up to 64 KiB
+const MAX_END_RECORD_SIZE: usize = 22 + 0xFFFF;
+
+/// Position of the end of central directory record in `data`
+fn find_end_record(data: &[u8]) -> Result<usize, ReadError> {
+    let search_start = data.len().saturating_sub(MAX_END_RECORD_SIZE);
+    let eocd = rfind(
+        &data[search_start..data.len().saturating_sub(18).max(search_start)],
+        &END_OF_CENTRAL_DIRECTORY.to_le_bytes(),
+    )
+    .map(|offset| search_start + offset)
+    .ok_or("Not a ZIP archive: end of central directory not found")?;
+    Ok(eocd)
+}
+
+impl<D: AsRef<[u8]>> ZipArchive<D> {
+    /// Parse the central directory
+    pub(crate) fn new(bytes: D, limits: ParseLimits) -> Result<Self, ReadError> {
+        ZipArchive::with_base(bytes, 0, limits)
+    }
+
+    /// Parse the central directory from the tail of a file, `bytes` starting
+    /// at offset `base`; entries are then read with [`ZipArchive::read_in`]
+    pub(crate) fn with_base(bytes: D, base: usize, limits: ParseLimits) -> Result<Self, ReadError> {
+        let data = bytes.as_ref();
+        let eocd = find_end_record(data)?;
+
+        let count = read_u16(data, eocd + 10).unwrap_or(0) as usize;
+        if count > limits.max_parts as usize {
+            return Err(LimitExceeded::new("part_count", limits.max_parts as u64, None).into());
+        }
+        let truncated = || "Truncated ZIP central directory".to_string();
+        let directory = read_u32(data, eocd + 16).unwrap_or(0) as usize;
+        let mut pos = directory.checked_sub(base).ok_or_else(truncated)?;
+
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            if read_u32(data, pos) != Some(CENTRAL_DIRECTORY_HEADER) {
+                return Err(truncated().into());
+            }
+            let name_len = read_u16(data, pos + 28).ok_or_else(truncated)? as usize;
+            let extra_len = read_u16(data, pos + 30).ok_or_else(truncated)? as usize;
+            let comment_len = read_u16(data, pos + 32).ok_or_else(truncated)? as usize;
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(truncated)?;
+
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: read_u16(data, pos + 10).ok_or_else(truncated)?,
+                crc: read_u32(data, pos + 16).ok_or_else(truncated)?,
+                compressed_size: read_u32(data, pos + 20).ok_or_else(truncated)?,
+                uncompressed_size: read_u32(data, pos + 24).ok_or_else(truncated)?,
+                offset: read_u32(data, pos + 42).ok_or_else(truncated)?,
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(ZipArchive {
+            data: bytes,
+            base,
+            entries,
+            limits,
+            decompressed: Cell::new(0),
+        })
+    }
+
+    /// Entry names in central directory order
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// Decompress an entry by name; `Ok(None)` when it isn't in the archive
+    pub(crate) fn read(&self, name: &str) -> Result<Option<Vec<u8>>, ReadError> {
+        self.read_in(name, self.data.as_ref(), self.base)
+    }
+
+    /// Decompress an entry from `data`, the part of the file starting at
+    /// offset `base` that holds it
+    pub(crate) fn read_in(
+        &self,
+        name: &str,
+        data: &[u8],
+        base: usize,
+    ) -> Result<Option<Vec<u8>>, ReadError> {
+        let Some(mut entry) = self.open_in(name, data, base)? else {
+            return Ok(None);
+        };
+        let mut contents = Vec::with_capacity(entry.expected_size as usize);
+        // Any failure is recorded on the reader and reported by `finish`
+        let _ = entry.read_to_end(&mut contents);
+        entry.finish()?;
+        Ok(Some(contents))
+    }
+
+    /// Open an entry for incremental reading; `Ok(None)` when it isn't in the archive
+    ///
+    /// Data is inflated as it is read, so a large part can be parsed without
+    /// ever being held whole. Call [`EntryReader::finish`] afterwards to
+    /// verify the checksum and collect any error.
+    pub(crate) fn open(&self, name: &str) -> Result<Option<EntryReader<'_>>, ReadError> {
+        self.open_in(name, self.data.as_ref(), self.base)
+    }
+
+    fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        let name = name.trim_start_matches('/');
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// File offset of an entry's local header and its compressed size
+    pub(crate) fn entry_span(&self, name: &str) -> Option<(usize, usize)> {
+        self.entry(name)
+            .map(|entry| (entry.offset as usize, entry.compressed_size as usize))
+    }
+
+    fn open_in<'a>(
+        &'a self,
+        name: &str,
+        data: &'a [u8],
+        base: usize,
+    ) -> Result<Option<EntryReader<'a>>, ReadError> {
+        let name = name.trim_start_matches('/');
+        let entry = match self.entry(name) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let header = (entry.offset as usize)
+            .checked_sub(base)
+            .filter(|&header| read_u32(data, header) == Some(LOCAL_FILE_HEADER))
+            .ok_or_else(|| format!("Corrupt local header for {}", name))?;
+        let name_len = read_u16(data, header + 26).unwrap_or(0) as usize;
+        let extra_len = read_u16(data, header + 28).unwrap_or(0) as usize;
+        let start = header + 30 + name_len + extra_len;
+        let payload = data
+            .get(start..start + entry.compressed_size as usize)
+            .ok_or_else(|| format!("Truncated ZIP entry {}", name))?;
+
+        // The declared size can't be trusted, so inflation is capped as well
+        let max = self.limits.max_decompressed_size;
+        if entry.uncompressed_size as u64 > max.saturating_sub(self.decompressed.get()) {
+            return Err(LimitExceeded::new("decompressed_size", max, Some(name)).into());
+        }
+        let inflater = match entry.method {
+            METHOD_STORED => None,
+            METHOD_DEFLATED => Some(InflateState::new_boxed(DataFormat::Raw)),
+            method => {
+                return Err(
+                    format!("Unsupported compression method {} for {}", method, name).into(),
+                )
+            }
+        };
+        Ok(Some(EntryReader {
+            name: entry.name.clone(),
+            payload,
+            inflater,
+            done: false,
+            crc: 0,
+            size: 0,
+            expected_crc: entry.crc,
+            expected_size: entry.uncompressed_size as u64,
+            decompressed: &self.decompressed,
+            limits: self.limits,
+            shape: None,
+            max_cells: 0,
+            error: None,
+        }))
+    }
+}
+
+/// A ZIP entry being inflated on demand
+pub(crate) struct EntryReader<'a> {
+    name: String,
+    /// Compressed input not yet consumed
+    payload: &'a [u8],
+    /// `None` for stored entries
+    inflater: Option<Box<InflateState>>,
+    done: bool,
+    crc: u32,
+    size: u64,
+    expected_crc: u32,
+    expected_size: u64,
+    decompressed: &'a Cell<u64>,
+    limits: ParseLimits,
+    /// Nesting depth and cell count check, applied as data is inflated
+    shape: Option<XmlShape<'static>>,
+    max_cells: u64,
+    error: Option<ReadError>,
+}
+
+impl EntryReader<'_> {
+    /// Check nesting depth and count `cell_tag` elements while reading,
+    /// failing once more than `max_cells` have been seen
+    pub(crate) fn check_xml(mut self, cell_tag: &'static [u8], max_cells: u64) -> Self {
+        self.shape = Some(XmlShape::new(cell_tag));
+        self.max_cells = max_cells;
+        self
+    }
+
+    /// Drain the entry, verify its size and checksum, and return the
+    /// number of cells counted by [`Self::check_xml`]
+    pub(crate) fn finish(mut self) -> Result<u64, ReadError> {
+        let _ = io::copy(&mut self, &mut io::sink());
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        Ok(self.shape.map_or(0, |shape| shape.cells))
+    }
+
+    fn fail(&mut self, error: ReadError) -> io::Error {
+        self.error = Some(error.clone());
+        io::Error::new(io::ErrorKind::InvalidData, String::from(error))
+    }
+
+    /// Produce the next bytes of the entry into `buf`
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        let Some(inflater) = &mut self.inflater else {
+            let n = buf.len().min(self.payload.len());
+            buf[..n].copy_from_slice(&self.payload[..n]);
+            self.payload = &self.payload[n..];
+            self.done = self.payload.is_empty();
+            return Ok(n);
+        };
+        loop {
+            let result = inflate(inflater, self.payload, buf, MZFlush::None);
+            self.payload = &self.payload[result.bytes_consumed..];
+            match result.status {
+                Ok(MZStatus::StreamEnd) => self.done = true,
+                Ok(_) => {}
+                Err(status) => {
+                    return Err(format!("Failed to inflate {}: {:?}", self.name, status).into())
+                }
+            }
+            if result.bytes_written > 0 || self.done {
+                return Ok(result.bytes_written);
+            }
+            if result.bytes_consumed == 0 {
+                return Err(format!("Truncated ZIP entry {}", self.name).into());
+            }
+        }
+    }
+
+    /// Account for `chunk` against the limits and the running checksum
+    fn check(&mut self, chunk: &[u8]) -> Result<(), ReadError> {
+        let max = self.limits.max_decompressed_size;
+        let total = self.decompressed.get() + chunk.len() as u64;
+        if total > max {
+            return Err(LimitExceeded::new("decompressed_size", max, Some(&self.name)).into());
+        }
+        self.decompressed.set(total);
+        self.size += chunk.len() as u64;
+        self.crc = crc32_update(self.crc, chunk);
+
+        if let Some(shape) = &mut self.shape {
+            shape.feed(chunk);
+            if shape.max_depth > self.limits.max_depth {
+                let max = self.limits.max_depth as u64;
+                return Err(LimitExceeded::new("nesting_depth", max, Some(&self.name)).into());
+            }
+            if shape.cells > self.max_cells {
+                let max = self.limits.max_cells;
+                return Err(LimitExceeded::new("cell_count", max, Some(&self.name)).into());
+            }
+        }
+        if self.done && (self.size != self.expected_size || self.crc != self.expected_crc) {
+            return Err(format!("Checksum mismatch in {}", self.name).into());
+        }
+        Ok(())
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(error) = self.error.clone() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                String::from(error),
+            ));
+        }
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        let result = self
+            .fill(buf)
+            .and_then(|n| self.check(&buf[..n]).map(|_| n));
+        result.map_err(|error| self.fail(error))
+    }
+}
+
+/// A ZIP package held in wasm memory, read entry by entry from JS
+///
+/// Replaces unzipping in JS: the file is copied into wasm once, and each
+/// part is inflated only when it is read.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct ZipPackage {
+    archive: ZipArchive<Vec<u8>>,
+}
+
+/// A ZIP package left in JS memory, typically a `SharedArrayBuffer` that a
+/// pool of workers parses from
+///
+/// Unlike [`ZipPackage`], the file isn't copied into wasm: only the central
+/// directory is, and each entry's compressed bytes when it is read, so every
+/// worker copies just the parts it parses.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub struct SharedZipPackage {
+    archive: SourceArchive<js_sys::Uint8Array>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl ZipPackage {
+    /// Read the central directory of `data`; `limits` takes [`ParseLimits`] fields
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: Vec<u8>, limits: JsValue) -> Result<ZipPackage, JsValue> {
+        trap(|| {
+            let limits: Option<ParseLimits> = serde_wasm_bindgen::from_value(limits)?;
+            let archive = ZipArchive::new(data, limits.unwrap_or_default())?;
+            Ok(ZipPackage { archive })
+        })
+    }
+
+    /// Entry names in the order they are stored
+    pub fn names(&self) -> Vec<String> {
+        self.archive.names().map(str::to_string).collect()
+    }
+
+    /// Inflate an entry; undefined when it doesn't exist
+    pub fn read(&self, name: &str) -> Result<Option<Vec<u8>>, JsValue> {
+        trap(|| Ok(self.archive.read(name)?))
+    }
+
+    /// Inflate an entry and decode it as XML text, transcoding from UTF-16
+    /// or a declared single-byte encoding
+    pub fn read_text(&self, name: &str) -> Result<Option<String>, JsValue> {
+        trap(|| match self.archive.read(name)? {
+            Some(bytes) => Ok(Some(xml_to_string(bytes)?)),
+            None => Ok(None),
+        })
+    }
+}
+
+/// Random access to a file that isn't in wasm memory
+pub(crate) trait ByteSource {
+    fn len(&self) -> usize;
+    /// Copy `start..end` out of the file
+    fn copy(&self, start: usize, end: usize) -> Vec<u8>;
+}
+
+impl ByteSource for &[u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn copy(&self, start: usize, end: usize) -> Vec<u8> {
+        self[start..end].to_vec()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl ByteSource for js_sys::Uint8Array {
+    fn len(&self) -> usize {
+        self.length() as usize
+    }
+
+    fn copy(&self, start: usize, end: usize) -> Vec<u8> {
+        self.subarray(start as u32, end as u32).to_vec()
+    }
+}
+
+/// A ZIP archive read piecemeal from a [`ByteSource`]
+///
+/// Only the central directory is copied up front, and each entry's bytes
+/// when it is read, so workers sharing one file copy just their own parts.
+pub(crate) struct SourceArchive<S> {
+    source: S,
+    archive: ZipArchive<Vec<u8>>,
+}
+
+impl<S: ByteSource> SourceArchive<S> {
+    pub(crate) fn new(source: S, limits: ParseLimits) -> Result<Self, ReadError> {
+        let len = source.len();
+        let tail_start = len.saturating_sub(MAX_END_RECORD_SIZE);
+        let tail = source.copy(tail_start, len);
+        let eocd = find_end_record(&tail)?;
+        let directory = read_u32(&tail, eocd + 16).unwrap_or(0) as usize;
+        // A large central directory starts before the tail
+        let archive = if directory >= tail_start || directory >= len {
+            ZipArchive::with_base(tail, tail_start, limits)?
+        } else {
+            ZipArchive::with_base(source.copy(directory, len), directory, limits)?
+        };
+        Ok(SourceArchive { source, archive })
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.archive.names()
+    }
+
+    /// Copy an entry out of the source and decompress it
+    pub(crate) fn read(&self, name: &str) -> Result<Option<Vec<u8>>, ReadError> {
+        let Some((offset, compressed_size)) = self.archive.entry_span(name) else {
+            return Ok(None);
+        };
+        let len = self.source.len();
+        let header = self.source.copy(offset.min(len), (offset + 30).min(len));
+        let name_len = read_u16(&header, 26).unwrap_or(0) as usize;
+        let extra_len = read_u16(&header, 28).unwrap_or(0) as usize;
+        let end = offset + 30 + name_len + extra_len + compressed_size;
+        let local = self.source.copy(offset.min(len), end.min(len));
+        self.archive.read_in(name, &local, offset)
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl SharedZipPackage {
+    /// Read the central directory of a view onto a (shared) buffer; the
+    /// buffer must not change while the package is in use
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: js_sys::Uint8Array, limits: JsValue) -> Result<SharedZipPackage, JsValue> {
+        trap(|| {
+            let limits: Option<ParseLimits> = serde_wasm_bindgen::from_value(limits)?;
+            let archive = SourceArchive::new(data, limits.unwrap_or_default())?;
+            Ok(SharedZipPackage { archive })
+        })
+    }
+
+    /// Entry names in the order they are stored
+    pub fn names(&self) -> Vec<String> {
+        self.archive.names().map(str::to_string).collect()
+    }
+
+    /// Inflate an entry; undefined when it doesn't exist
+    pub fn read(&self, name: &str) -> Result<Option<Vec<u8>>, JsValue> {
+        trap(|| Ok(self.archive.read(name)?))
+    }
+
+    /// Inflate an entry and decode it as XML text, like [`ZipPackage::read_text`]
+    pub fn read_text(&self, name: &str) -> Result<Option<String>, JsValue> {
+        trap(|| match self.archive.read(name)? {
+            Some(bytes) => Ok(Some(xml_to_string(bytes)?)),
+            None => Ok(None),
+        })
+    }
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// Incremental CRC-32 (IEEE) as used by ZIP
+pub(crate) fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ParserScratch;
+    use crate::{parse_worksheet_from, parse_worksheet_with, WorksheetParseOptions};
+    use std::io::BufReader;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32_update(crc32(b"12345"), b"6789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_zip_layout() {
+        let mut zip = ZipWriter::new();
+        zip.add_file(
+            "a.xml",
+            "<a/>".repeat(100).as_bytes(),
+            DEFAULT_COMPRESSION_LEVEL,
+        );
+        zip.add_file("b.bin", &[1, 2, 3], 0);
+        let bytes = zip.finish();
+
+        assert_eq!(&bytes[0..4], &LOCAL_FILE_HEADER.to_le_bytes());
+        // Highly repetitive XML gets deflated
+        assert_eq!(u16::from_le_bytes([bytes[8], bytes[9]]), METHOD_DEFLATED);
+
+        let eocd = bytes.len() - 22;
+        assert_eq!(
+            &bytes[eocd..eocd + 4],
+            &END_OF_CENTRAL_DIRECTORY.to_le_bytes()
+        );
+        assert_eq!(u16::from_le_bytes([bytes[eocd + 10], bytes[eocd + 11]]), 2);
+    }
+
+    #[test]
+    fn test_zip_round_trip() {
+        let xml = "<a/>".repeat(100);
+        let mut zip = ZipWriter::new();
+        zip.add_file("xl/a.xml", xml.as_bytes(), DEFAULT_COMPRESSION_LEVEL);
+        zip.add_file("b.bin", &[1, 2, 3], 0);
+        let bytes = zip.finish();
+
+        let archive = ZipArchive::new(&bytes, ParseLimits::default()).unwrap();
+        assert_eq!(archive.read("/xl/a.xml").unwrap().unwrap(), xml.as_bytes());
+        assert_eq!(archive.read("b.bin").unwrap().unwrap(), vec![1, 2, 3]);
+        assert!(archive.read("missing").unwrap().is_none());
+        assert!(ZipArchive::new(b"not a zip", ParseLimits::default()).is_err());
+
+        let limits = ParseLimits {
+            max_decompressed_size: 300,
+            ..Default::default()
+        };
+        let archive = ZipArchive::new(&bytes, limits).unwrap();
+        assert!(matches!(archive.read("xl/a.xml"), Err(ReadError::Limit(_))));
+        let limits = ParseLimits {
+            max_parts: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            ZipArchive::new(&bytes, limits),
+            Err(ReadError::Limit(_))
+        ));
+    }
+
+    #[test]
+    fn test_source_archive() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("xl/a.xml", b"<a/>", DEFAULT_COMPRESSION_LEVEL);
+        // Enough entries that the central directory starts before the tail
+        for i in 0..1000 {
+            zip.add_file(&format!("xl/media/image{:0>40}.bin", i), &[7], 0);
+        }
+        let bytes = zip.finish();
+        assert!(bytes.len() > MAX_END_RECORD_SIZE * 2);
+
+        let archive = SourceArchive::new(bytes.as_slice(), ParseLimits::default()).unwrap();
+        assert_eq!(archive.names().count(), 1001);
+        assert_eq!(archive.read("xl/a.xml").unwrap().unwrap(), b"<a/>");
+        let last = format!("xl/media/image{:0>40}.bin", 999);
+        assert_eq!(archive.read(&last).unwrap().unwrap(), vec![7]);
+        assert!(archive.read("missing").unwrap().is_none());
+
+        // A small file's directory is found within the tail
+        let mut zip = ZipWriter::new();
+        zip.add_file("b.bin", &[1, 2, 3], 0);
+        let bytes = zip.finish();
+        let archive = SourceArchive::new(bytes.as_slice(), ParseLimits::default()).unwrap();
+        assert_eq!(archive.read("b.bin").unwrap().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_streamed_entry() {
+        let rows: String = (1..=500)
+            .map(|r| format!(r#"<row r="{r}"><c r="A{r}"><v>{r}</v></c></row>"#))
+            .collect();
+        let xml = format!("<worksheet><sheetData>{}</sheetData></worksheet>", rows);
+        let mut zip = ZipWriter::new();
+        zip.add_file("xl/sheet1.xml", xml.as_bytes(), DEFAULT_COMPRESSION_LEVEL);
+        let bytes = zip.finish();
+        let archive = ZipArchive::new(&bytes, ParseLimits::default()).unwrap();
+        let options = WorksheetParseOptions::default();
+
+        // A tiny read-ahead forces tags to straddle inflated chunks
+        let mut entry = archive
+            .open("xl/sheet1.xml")
+            .unwrap()
+            .unwrap()
+            .check_xml(b"c", 1000);
+        let input = BufReader::with_capacity(16, &mut entry);
+        let streamed = parse_worksheet_from(input, &mut ParserScratch::default(), &options);
+        assert_eq!(entry.finish().unwrap(), 500);
+        let whole = parse_worksheet_with(&xml, &mut ParserScratch::default(), &options);
+        assert_eq!(
+            serde_json::to_string(&streamed).unwrap(),
+            serde_json::to_string(&whole).unwrap()
+        );
+
+        let mut entry = archive
+            .open("xl/sheet1.xml")
+            .unwrap()
+            .unwrap()
+            .check_xml(b"c", 100);
+        let input = BufReader::new(&mut entry);
+        parse_worksheet_from(input, &mut ParserScratch::default(), &options);
+        assert!(
+            matches!(entry.finish(), Err(ReadError::Limit(limit)) if limit.limit == "cell_count")
+        );
+    }
+}